@@ -15,15 +15,25 @@
 use std::sync::Arc;
 
 use grpc_clients::mqtt::admin::call::{
-    cluster_status, mqtt_broker_create_user, mqtt_broker_delete_user,
-    mqtt_broker_enable_slow_subscribe, mqtt_broker_list_connection,
-    mqtt_broker_list_slow_subscribe, mqtt_broker_list_topic, mqtt_broker_list_user,
+    cluster_status, mqtt_broker_cancel_delay_publish, mqtt_broker_create_psk,
+    mqtt_broker_create_rule, mqtt_broker_create_topic_rewrite_rule, mqtt_broker_create_user,
+    mqtt_broker_delete_auto_subscribe_rule, mqtt_broker_delete_psk, mqtt_broker_delete_rule,
+    mqtt_broker_delete_topic_rewrite_rule, mqtt_broker_delete_user,
+    mqtt_broker_enable_slow_subscribe, mqtt_broker_list_auto_subscribe_rule,
+    mqtt_broker_list_connection, mqtt_broker_list_delay_publish, mqtt_broker_list_psk,
+    mqtt_broker_list_rule, mqtt_broker_list_slow_subscribe, mqtt_broker_list_topic,
+    mqtt_broker_list_topic_rewrite_rule, mqtt_broker_list_user,
+    mqtt_broker_set_auto_subscribe_rule,
 };
 use grpc_clients::pool::ClientPool;
 use metadata_struct::mqtt::user::MqttUser;
 use protocol::broker_mqtt::broker_mqtt_admin::{
-    ClusterStatusRequest, CreateUserRequest, DeleteUserRequest, EnableSlowSubscribeRequest,
-    ListConnectionRequest, ListSlowSubscribeRequest, ListTopicRequest, ListUserRequest,
+    CancelDelayPublishRequest, ClusterStatusRequest, CreatePskRequest, CreateRuleRequest,
+    CreateTopicRewriteRuleRequest, CreateUserRequest, DeleteAutoSubscribeRuleRequest,
+    DeletePskRequest, DeleteRuleRequest, DeleteTopicRewriteRuleRequest, DeleteUserRequest,
+    EnableSlowSubscribeRequest, ListAutoSubscribeRuleRequest, ListConnectionRequest,
+    ListDelayPublishRequest, ListPskRequest, ListRuleRequest, ListSlowSubscribeRequest,
+    ListTopicRequest, ListTopicRewriteRuleRequest, ListUserRequest, SetAutoSubscribeRuleRequest,
 };
 
 use crate::{error_info, grpc_addr};
@@ -43,6 +53,11 @@ pub enum MqttActionType {
     DeleteUser(DeleteUserRequest),
     ListUser,
 
+    // Psk admin
+    ListPsk,
+    CreatePsk(CreatePskRequest),
+    DeletePsk(DeletePskRequest),
+
     // connection
     ListConnection,
 
@@ -51,6 +66,25 @@ pub enum MqttActionType {
     ListSlowSubscribe,
 
     ListTopic(ListTopicRequest),
+
+    // delayed publish
+    ListDelayPublish,
+    CancelDelayPublish(CancelDelayPublishRequest),
+
+    // topic rewrite
+    ListTopicRewriteRule,
+    CreateTopicRewriteRule(CreateTopicRewriteRuleRequest),
+    DeleteTopicRewriteRule(DeleteTopicRewriteRuleRequest),
+
+    // auto subscribe
+    ListAutoSubscribeRule,
+    SetAutoSubscribeRule(SetAutoSubscribeRuleRequest),
+    DeleteAutoSubscribeRule(DeleteAutoSubscribeRuleRequest),
+
+    // rule engine
+    ListRule,
+    CreateRule(CreateRuleRequest),
+    DeleteRule(DeleteRuleRequest),
 }
 
 pub struct MqttBrokerCommand {}
@@ -83,6 +117,17 @@ impl MqttBrokerCommand {
             MqttActionType::ListUser => {
                 self.list_user(client_pool.clone(), params.clone()).await;
             }
+            MqttActionType::ListPsk => {
+                self.list_psk(client_pool.clone(), params.clone()).await;
+            }
+            MqttActionType::CreatePsk(ref request) => {
+                self.create_psk(client_pool.clone(), params.clone(), request.clone())
+                    .await;
+            }
+            MqttActionType::DeletePsk(ref request) => {
+                self.delete_psk(client_pool.clone(), params.clone(), request.clone())
+                    .await;
+            }
             MqttActionType::ListConnection => {
                 self.list_connections(client_pool.clone(), params.clone())
                     .await;
@@ -99,6 +144,61 @@ impl MqttBrokerCommand {
                 self.list_slow_subscribe(client_pool.clone(), params.clone())
                     .await;
             }
+            MqttActionType::ListDelayPublish => {
+                self.list_delay_publish(client_pool.clone(), params.clone())
+                    .await;
+            }
+            MqttActionType::CancelDelayPublish(ref request) => {
+                self.cancel_delay_publish(client_pool.clone(), params.clone(), request.clone())
+                    .await;
+            }
+            MqttActionType::ListTopicRewriteRule => {
+                self.list_topic_rewrite_rule(client_pool.clone(), params.clone())
+                    .await;
+            }
+            MqttActionType::CreateTopicRewriteRule(ref request) => {
+                self.create_topic_rewrite_rule(
+                    client_pool.clone(),
+                    params.clone(),
+                    request.clone(),
+                )
+                .await;
+            }
+            MqttActionType::DeleteTopicRewriteRule(ref request) => {
+                self.delete_topic_rewrite_rule(
+                    client_pool.clone(),
+                    params.clone(),
+                    request.clone(),
+                )
+                .await;
+            }
+            MqttActionType::ListAutoSubscribeRule => {
+                self.list_auto_subscribe_rule(client_pool.clone(), params.clone())
+                    .await;
+            }
+            MqttActionType::SetAutoSubscribeRule(ref request) => {
+                self.set_auto_subscribe_rule(client_pool.clone(), params.clone(), request.clone())
+                    .await;
+            }
+            MqttActionType::DeleteAutoSubscribeRule(ref request) => {
+                self.delete_auto_subscribe_rule(
+                    client_pool.clone(),
+                    params.clone(),
+                    request.clone(),
+                )
+                .await;
+            }
+            MqttActionType::ListRule => {
+                self.list_rule(client_pool.clone(), params.clone()).await;
+            }
+            MqttActionType::CreateRule(ref request) => {
+                self.create_rule(client_pool.clone(), params.clone(), request.clone())
+                    .await;
+            }
+            MqttActionType::DeleteRule(ref request) => {
+                self.delete_rule(client_pool.clone(), params.clone(), request.clone())
+                    .await;
+            }
         }
     }
 
@@ -175,6 +275,52 @@ impl MqttBrokerCommand {
         }
     }
 
+    async fn list_psk(&self, client_pool: Arc<ClientPool>, params: MqttCliCommandParam) {
+        let request = ListPskRequest {};
+        match mqtt_broker_list_psk(client_pool, &grpc_addr(params.server), request).await {
+            Ok(data) => {
+                println!("psk identity list:");
+                for identity in data.identities {
+                    println!("{},", identity);
+                }
+            }
+            Err(e) => {
+                println!("MQTT broker list psk exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn create_psk(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: CreatePskRequest,
+    ) {
+        match mqtt_broker_create_psk(client_pool, &grpc_addr(params.server), cli_request).await {
+            Ok(_) => println!("Created psk successfully!"),
+            Err(e) => {
+                println!("MQTT broker create psk exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn delete_psk(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: DeletePskRequest,
+    ) {
+        match mqtt_broker_delete_psk(client_pool, &grpc_addr(params.server), cli_request).await {
+            Ok(_) => println!("Deleted psk successfully!"),
+            Err(e) => {
+                println!("MQTT broker delete psk exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
     async fn list_connections(&self, client_pool: Arc<ClientPool>, params: MqttCliCommandParam) {
         let request = ListConnectionRequest {};
         match mqtt_broker_list_connection(client_pool.clone(), &grpc_addr(params.server), request)
@@ -274,4 +420,272 @@ impl MqttBrokerCommand {
             }
         }
     }
+
+    async fn list_delay_publish(&self, client_pool: Arc<ClientPool>, params: MqttCliCommandParam) {
+        let request = ListDelayPublishRequest {};
+        match mqtt_broker_list_delay_publish(client_pool, &grpc_addr(params.server), request).await
+        {
+            Ok(data) => {
+                println!("delayed message list result:");
+                for message in data.messages {
+                    println!(
+                        concat!(
+                            "id: {}\n",
+                            "client id: {}\n",
+                            "topic name: {}\n",
+                            "delay secs: {}\n",
+                            "deliver time: {}\n",
+                            "create time: {}\n"
+                        ),
+                        message.id,
+                        message.client_id,
+                        message.topic_name,
+                        message.delay_secs,
+                        message.deliver_time,
+                        message.create_time
+                    );
+                }
+            }
+            Err(e) => {
+                println!("MQTT broker list delayed message exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn cancel_delay_publish(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: CancelDelayPublishRequest,
+    ) {
+        match mqtt_broker_cancel_delay_publish(client_pool, &grpc_addr(params.server), cli_request)
+            .await
+        {
+            Ok(_) => println!("Cancelled delayed message successfully!"),
+            Err(e) => {
+                println!("MQTT broker cancel delayed message exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn list_topic_rewrite_rule(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+    ) {
+        let request = ListTopicRewriteRuleRequest {};
+        match mqtt_broker_list_topic_rewrite_rule(client_pool, &grpc_addr(params.server), request)
+            .await
+        {
+            Ok(data) => {
+                println!("topic rewrite rule list result:");
+                for rule in data.rules {
+                    println!(
+                        concat!(
+                            "action: {}\n",
+                            "source topic: {}\n",
+                            "dest topic: {}\n",
+                            "regex: {}\n",
+                            "create time: {}\n"
+                        ),
+                        rule.action,
+                        rule.source_topic,
+                        rule.dest_topic,
+                        rule.regex,
+                        rule.create_time
+                    );
+                }
+            }
+            Err(e) => {
+                println!("MQTT broker list topic rewrite rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn create_topic_rewrite_rule(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: CreateTopicRewriteRuleRequest,
+    ) {
+        match mqtt_broker_create_topic_rewrite_rule(
+            client_pool,
+            &grpc_addr(params.server),
+            cli_request,
+        )
+        .await
+        {
+            Ok(_) => println!("Created topic rewrite rule successfully!"),
+            Err(e) => {
+                println!("MQTT broker create topic rewrite rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn delete_topic_rewrite_rule(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: DeleteTopicRewriteRuleRequest,
+    ) {
+        match mqtt_broker_delete_topic_rewrite_rule(
+            client_pool,
+            &grpc_addr(params.server),
+            cli_request,
+        )
+        .await
+        {
+            Ok(_) => println!("Deleted topic rewrite rule successfully!"),
+            Err(e) => {
+                println!("MQTT broker delete topic rewrite rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn list_auto_subscribe_rule(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+    ) {
+        let request = ListAutoSubscribeRuleRequest {};
+        match mqtt_broker_list_auto_subscribe_rule(client_pool, &grpc_addr(params.server), request)
+            .await
+        {
+            Ok(data) => {
+                println!("auto subscribe rule list result:");
+                for rule in data.rules {
+                    println!(
+                        concat!(
+                            "topic: {}\n",
+                            "qos: {}\n",
+                            "no local: {}\n",
+                            "retain as published: {}\n",
+                            "retained handling: {}\n"
+                        ),
+                        rule.topic,
+                        rule.qos,
+                        rule.no_local,
+                        rule.retain_as_published,
+                        rule.retained_handling
+                    );
+                }
+            }
+            Err(e) => {
+                println!("MQTT broker list auto subscribe rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn set_auto_subscribe_rule(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: SetAutoSubscribeRuleRequest,
+    ) {
+        match mqtt_broker_set_auto_subscribe_rule(
+            client_pool,
+            &grpc_addr(params.server),
+            cli_request,
+        )
+        .await
+        {
+            Ok(_) => println!("Set auto subscribe rule successfully!"),
+            Err(e) => {
+                println!("MQTT broker set auto subscribe rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn delete_auto_subscribe_rule(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: DeleteAutoSubscribeRuleRequest,
+    ) {
+        match mqtt_broker_delete_auto_subscribe_rule(
+            client_pool,
+            &grpc_addr(params.server),
+            cli_request,
+        )
+        .await
+        {
+            Ok(_) => println!("Deleted auto subscribe rule successfully!"),
+            Err(e) => {
+                println!("MQTT broker delete auto subscribe rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn list_rule(&self, client_pool: Arc<ClientPool>, params: MqttCliCommandParam) {
+        let request = ListRuleRequest {};
+        match mqtt_broker_list_rule(client_pool, &grpc_addr(params.server), request).await {
+            Ok(data) => {
+                println!("rule list result:");
+                for rule in data.rules {
+                    println!(
+                        concat!(
+                            "rule name: {}\n",
+                            "sql: {}\n",
+                            "actions: {}\n",
+                            "enable: {}\n",
+                            "description: {}\n",
+                            "create time: {}\n",
+                            "hit: {}\n",
+                            "fail: {}\n"
+                        ),
+                        rule.rule_name,
+                        rule.sql,
+                        rule.actions,
+                        rule.enable,
+                        rule.description,
+                        rule.create_time,
+                        rule.hit,
+                        rule.fail
+                    );
+                }
+            }
+            Err(e) => {
+                println!("MQTT broker list rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn create_rule(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: CreateRuleRequest,
+    ) {
+        match mqtt_broker_create_rule(client_pool, &grpc_addr(params.server), cli_request).await {
+            Ok(_) => println!("Created rule successfully!"),
+            Err(e) => {
+                println!("MQTT broker create rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
+
+    async fn delete_rule(
+        &self,
+        client_pool: Arc<ClientPool>,
+        params: MqttCliCommandParam,
+        cli_request: DeleteRuleRequest,
+    ) {
+        match mqtt_broker_delete_rule(client_pool, &grpc_addr(params.server), cli_request).await {
+            Ok(_) => println!("Deleted rule successfully!"),
+            Err(e) => {
+                println!("MQTT broker delete rule exception");
+                error_info(e.to_string());
+            }
+        }
+    }
 }
@@ -21,7 +21,10 @@ use cli_command::placement::{
     PlacementActionType, PlacementCenterCommand, PlacementCliCommandParam,
 };
 use protocol::broker_mqtt::broker_mqtt_admin::{
-    CreateUserRequest, DeleteUserRequest, ListTopicRequest,
+    CancelDelayPublishRequest, CreatePskRequest, CreateRuleRequest, CreateTopicRewriteRuleRequest,
+    CreateUserRequest, DeleteAutoSubscribeRuleRequest, DeletePskRequest, DeleteRuleRequest,
+    DeleteTopicRewriteRuleRequest, DeleteUserRequest, ListTopicRequest,
+    SetAutoSubscribeRuleRequest,
 };
 use protocol::placement_center::placement_center_openraft::{
     AddLearnerRequest, ChangeMembershipRequest, Node,
@@ -69,11 +72,35 @@ enum MQTTAction {
     DeleteUser(DeleteUserArgs),
     ListUser,
 
+    // Psk admin
+    ListPsk,
+    CreatePsk(CreatePskArgs),
+    DeletePsk(DeletePskArgs),
+
     // Connections
     ListConnection,
 
     ListTopic(ListTopicArgs),
 
+    // Delayed publish
+    ListDelayPublish,
+    CancelDelayPublish(CancelDelayPublishArgs),
+
+    // Topic rewrite
+    ListTopicRewriteRule,
+    CreateTopicRewriteRule(CreateTopicRewriteRuleArgs),
+    DeleteTopicRewriteRule(DeleteTopicRewriteRuleArgs),
+
+    // Auto subscribe
+    ListAutoSubscribeRule,
+    SetAutoSubscribeRule(SetAutoSubscribeRuleArgs),
+    DeleteAutoSubscribeRule(DeleteAutoSubscribeRuleArgs),
+
+    // Rule engine
+    ListRule,
+    CreateRule(CreateRuleArgs),
+    DeleteRule(DeleteRuleArgs),
+
     // observability: slow-sub feat
     #[clap(name = "slow-sub")]
     SlowSub(SlowSubArgs),
@@ -97,6 +124,120 @@ struct ListTopicArgs {
     match_option: MatchOption,
 }
 
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: create or replace a tls-psk identity", long_about = None)]
+#[command(next_line_help = true)]
+struct CreatePskArgs {
+    #[arg(short, long, required = true)]
+    identity: String,
+
+    /// hex encoded pre-shared key
+    #[arg(short, long, required = true)]
+    psk: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: delete a tls-psk identity", long_about = None)]
+#[command(next_line_help = true)]
+struct DeletePskArgs {
+    #[arg(short, long, required = true)]
+    identity: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: cancel a pending delayed message", long_about = None)]
+#[command(next_line_help = true)]
+struct CancelDelayPublishArgs {
+    #[arg(short, long, required = true)]
+    id: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: create a topic rewrite rule", long_about = None)]
+#[command(next_line_help = true)]
+struct CreateTopicRewriteRuleArgs {
+    #[arg(short, long, default_value = "all")]
+    action: String,
+
+    #[arg(short, long, required = true)]
+    source_topic: String,
+
+    #[arg(short, long, required = true)]
+    dest_topic: String,
+
+    #[arg(short, long, default_value = "")]
+    regex: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: delete a topic rewrite rule", long_about = None)]
+#[command(next_line_help = true)]
+struct DeleteTopicRewriteRuleArgs {
+    #[arg(short, long, default_value = "all")]
+    action: String,
+
+    #[arg(short, long, required = true)]
+    source_topic: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: set an auto subscribe rule", long_about = None)]
+#[command(next_line_help = true)]
+struct SetAutoSubscribeRuleArgs {
+    #[arg(short, long, required = true)]
+    topic: String,
+
+    #[arg(short, long, default_value_t = 1)]
+    qos: u32,
+
+    #[arg(long, default_value_t = false)]
+    no_local: bool,
+
+    #[arg(long, default_value_t = false)]
+    retain_as_published: bool,
+
+    #[arg(long, default_value_t = 0)]
+    retained_handling: u32,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: delete an auto subscribe rule", long_about = None)]
+#[command(next_line_help = true)]
+struct DeleteAutoSubscribeRuleArgs {
+    #[arg(short, long, required = true)]
+    topic: String,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: create or replace a rule engine rule", long_about = None)]
+#[command(next_line_help = true)]
+struct CreateRuleArgs {
+    #[arg(short, long, required = true)]
+    name: String,
+
+    /// e.g. SELECT payload.temp as t, clientid FROM "sensors/+/data" WHERE payload.temp > 80
+    #[arg(short, long, required = true)]
+    sql: String,
+
+    /// JSON array, e.g. [{"type": "republish", "topic": "alarm/${clientid}", "qos": 1}]
+    #[arg(short, long, required = true)]
+    actions: String,
+
+    #[arg(short, long, default_value = "")]
+    description: String,
+
+    #[arg(long, default_value_t = false)]
+    disable: bool,
+}
+
+#[derive(clap::Args, Debug)]
+#[command(author="RobustMQ", about="action: delete a rule engine rule", long_about = None)]
+#[command(next_line_help = true)]
+struct DeleteRuleArgs {
+    #[arg(short, long, required = true)]
+    name: String,
+}
+
 #[derive(clap::Args, Debug)]
 #[command(author="RobustMQ",  about="Command line tool for placement center", long_about = None)]
 #[command(next_line_help = true)]
@@ -175,6 +316,14 @@ async fn handle_mqtt(args: MqttArgs, cmd: MqttBrokerCommand) {
                 username: arg.username,
             }),
             MQTTAction::ListUser => MqttActionType::ListUser,
+            MQTTAction::ListPsk => MqttActionType::ListPsk,
+            MQTTAction::CreatePsk(args) => MqttActionType::CreatePsk(CreatePskRequest {
+                identity: args.identity,
+                psk: args.psk,
+            }),
+            MQTTAction::DeletePsk(args) => MqttActionType::DeletePsk(DeletePskRequest {
+                identity: args.identity,
+            }),
             MQTTAction::ListConnection => MqttActionType::ListConnection,
             MQTTAction::ListTopic(args) => MqttActionType::ListTopic(ListTopicRequest {
                 topic_name: args.topic_name,
@@ -184,6 +333,51 @@ async fn handle_mqtt(args: MqttArgs, cmd: MqttBrokerCommand) {
                     MatchOption::S => 2,
                 },
             }),
+            MQTTAction::ListDelayPublish => MqttActionType::ListDelayPublish,
+            MQTTAction::CancelDelayPublish(args) => {
+                MqttActionType::CancelDelayPublish(CancelDelayPublishRequest { id: args.id })
+            }
+            MQTTAction::ListTopicRewriteRule => MqttActionType::ListTopicRewriteRule,
+            MQTTAction::CreateTopicRewriteRule(args) => {
+                MqttActionType::CreateTopicRewriteRule(CreateTopicRewriteRuleRequest {
+                    action: args.action,
+                    source_topic: args.source_topic,
+                    dest_topic: args.dest_topic,
+                    regex: args.regex,
+                })
+            }
+            MQTTAction::DeleteTopicRewriteRule(args) => {
+                MqttActionType::DeleteTopicRewriteRule(DeleteTopicRewriteRuleRequest {
+                    action: args.action,
+                    source_topic: args.source_topic,
+                })
+            }
+            MQTTAction::ListAutoSubscribeRule => MqttActionType::ListAutoSubscribeRule,
+            MQTTAction::SetAutoSubscribeRule(args) => {
+                MqttActionType::SetAutoSubscribeRule(SetAutoSubscribeRuleRequest {
+                    topic: args.topic,
+                    qos: args.qos,
+                    no_local: args.no_local,
+                    retain_as_published: args.retain_as_published,
+                    retained_handling: args.retained_handling,
+                })
+            }
+            MQTTAction::DeleteAutoSubscribeRule(args) => {
+                MqttActionType::DeleteAutoSubscribeRule(DeleteAutoSubscribeRuleRequest {
+                    topic: args.topic,
+                })
+            }
+            MQTTAction::ListRule => MqttActionType::ListRule,
+            MQTTAction::CreateRule(args) => MqttActionType::CreateRule(CreateRuleRequest {
+                rule_name: args.name,
+                sql: args.sql,
+                actions: args.actions,
+                enable: !args.disable,
+                description: args.description,
+            }),
+            MQTTAction::DeleteRule(args) => MqttActionType::DeleteRule(DeleteRuleRequest {
+                rule_name: args.name,
+            }),
             _ => unreachable!("UnSupport command"),
         },
     };
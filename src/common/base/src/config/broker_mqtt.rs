@@ -36,8 +36,8 @@ use super::common::{Auth, Log, Storage};
 use super::default_mqtt::{
     default_auth, default_grpc_port, default_http_port, default_log, default_network,
     default_network_quic_port, default_network_tcp_port, default_network_tcps_port,
-    default_network_websocket_port, default_network_websockets_port, default_placement_center,
-    default_storage, default_system, default_tcp_thread,
+    default_network_tcps_psk_port, default_network_websocket_port, default_network_websockets_port,
+    default_placement_center, default_storage, default_system, default_tcp_thread,
 };
 use crate::tools::{read_file, try_create_fold};
 
@@ -71,6 +71,8 @@ pub struct Network {
     pub tcp_port: u32,
     #[serde(default = "default_network_tcps_port")]
     pub tcps_port: u32,
+    #[serde(default = "default_network_tcps_psk_port")]
+    pub tcps_psk_port: u32,
     #[serde(default = "default_network_websocket_port")]
     pub websocket_port: u32,
     #[serde(default = "default_network_websockets_port")]
@@ -12,6 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
@@ -33,6 +35,131 @@ pub struct Auth {
     pub journal_addr: String,
     #[serde(default)]
     pub mysql_addr: String,
+    #[serde(default)]
+    pub redis_addr: String,
+    #[serde(default)]
+    pub redis: AuthRedis,
+    #[serde(default)]
+    pub password_hash: AuthPasswordHash,
+    #[serde(default)]
+    pub jwt: AuthJwt,
+    #[serde(default)]
+    pub http: AuthHttp,
+    #[serde(default)]
+    pub x509: AuthX509,
+    #[serde(default)]
+    pub psk: AuthPsk,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct AuthPasswordHash {
+    // bcrypt, pbkdf2, argon2, sha256 or plain, defaults to argon2
+    #[serde(default)]
+    pub algorithm: String,
+    // prefix or suffix, only used by sha256
+    #[serde(default)]
+    pub salt_position: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct AuthRedis {
+    // Each user is a hash ({user_key_prefix}{username}) with the fields password_hash, salt and is_superuser
+    #[serde(default)]
+    pub user_key_prefix: String,
+    // Each acl resource is a set ({acl_key_prefix}{user|clientid}:{name}) of json encoded acl rules
+    #[serde(default)]
+    pub acl_key_prefix: String,
+    // Hash of json encoded blacklist entries
+    #[serde(default)]
+    pub blacklist_key: String,
+    // Hash of psk identity to hex encoded key
+    #[serde(default)]
+    pub psk_key: String,
+    // plain, bcrypt, pbkdf2, argon2 or sha256
+    #[serde(default)]
+    pub password_hash_algorithm: String,
+    // prefix or suffix, where the salt is joined to the password before sha256
+    #[serde(default)]
+    pub salt_position: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct AuthJwt {
+    #[serde(default)]
+    pub enable: bool,
+    // HS256/HS384/HS512, RS256/RS384/RS512, PS256/PS384/PS512, ES256/ES384
+    #[serde(default)]
+    pub algorithm: String,
+    // Secret used by the HMAC algorithms
+    #[serde(default)]
+    pub secret: String,
+    #[serde(default)]
+    pub secret_base64_encoded: bool,
+    // Path of the PEM encoded public key used by the RSA and ECDSA algorithms
+    #[serde(default)]
+    pub public_key: String,
+    // Require the "sub" claim to be equal to the username of the connection
+    #[serde(default)]
+    pub verify_username: bool,
+    // Require the "clientid" claim to be equal to the client id of the connection
+    #[serde(default)]
+    pub verify_client_id: bool,
+    // Name of the claim that carries the acl rules of the connection
+    #[serde(default)]
+    pub acl_claim_name: String,
+    // Accept tokens without an "exp" claim, such tokens never expire
+    #[serde(default)]
+    pub allow_no_expiry: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct AuthHttp {
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    // Request body template, supports ${clientid}, ${username}, ${password}, ${peerhost} and ${proto_ver}
+    #[serde(default)]
+    pub body: String,
+    #[serde(default)]
+    pub timeout_ms: u64,
+    #[serde(default)]
+    pub pool_size: usize,
+    // How long the allow/deny result of a credential is cached
+    #[serde(default)]
+    pub cache_ttl_sec: u64,
+    // Maximum number of cached credentials, the least recently used are evicted first
+    #[serde(default)]
+    pub cache_max_size: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct AuthX509 {
+    // Ask tcps and wss clients for a certificate signed by ca_cert
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default)]
+    pub ca_cert: String,
+    // Reject the TLS handshake when the client sends no certificate
+    #[serde(default)]
+    pub fail_if_no_peer_cert: bool,
+    // cn or san, empty keeps the value sent in CONNECT
+    #[serde(default)]
+    pub peer_cert_as_username: String,
+    #[serde(default)]
+    pub peer_cert_as_clientid: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct AuthPsk {
+    // Start a TLS-PSK listener on network.tcps_psk_port
+    #[serde(default)]
+    pub enable: bool,
+    // OpenSSL cipher list, defaults to all PSK cipher suites
+    #[serde(default)]
+    pub ciphers: String,
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq, Eq)]
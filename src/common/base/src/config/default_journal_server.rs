@@ -13,7 +13,7 @@
 // limitations under the License.
 
 use super::common::Log;
-use super::journal_server::{Network, Prometheus, Storage, System, TcpThread};
+use super::journal_server::{Network, Prometheus, Replication, Storage, System, TcpThread};
 
 pub fn default_network() -> Network {
     Network {
@@ -22,6 +22,7 @@ pub fn default_network() -> Network {
         tcps_port: default_network_tcps_port(),
         tls_cert: "".to_string(),
         tls_key: "".to_string(),
+        max_read_wait_ms: default_network_max_read_wait_ms(),
     }
 }
 
@@ -34,6 +35,9 @@ pub fn default_network_tcp_port() -> u32 {
 pub fn default_network_tcps_port() -> u32 {
     3111
 }
+pub fn default_network_max_read_wait_ms() -> u64 {
+    30000
+}
 
 pub fn default_prometheus_port() -> u32 {
     9090
@@ -63,6 +67,14 @@ pub fn default_tcp_thread() -> TcpThread {
     }
 }
 
+pub fn default_replication() -> Replication {
+    Replication {
+        replica_lag_time_max_ms: 10000,
+        replica_fetch_max_bytes: 1024 * 1024,
+        replica_fetch_backoff_ms: 100,
+    }
+}
+
 pub fn default_prometheus() -> Prometheus {
     Prometheus {
         enable: false,
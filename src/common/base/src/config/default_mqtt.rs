@@ -13,7 +13,9 @@
 // limitations under the License.
 
 use super::broker_mqtt::{Network, System, TcpThread};
-use super::common::{Auth, Log, Storage};
+use super::common::{
+    Auth, AuthHttp, AuthJwt, AuthPasswordHash, AuthPsk, AuthRedis, AuthX509, Log, Storage,
+};
 
 pub fn default_grpc_port() -> u32 {
     9981
@@ -31,6 +33,7 @@ pub fn default_network() -> Network {
     Network {
         tcp_port: default_network_tcp_port(),
         tcps_port: default_network_tcps_port(),
+        tcps_psk_port: default_network_tcps_psk_port(),
         websocket_port: default_network_websocket_port(),
         websockets_port: default_network_websockets_port(),
         quic_port: default_network_quic_port(),
@@ -44,6 +47,9 @@ pub fn default_network_tcp_port() -> u32 {
 pub fn default_network_tcps_port() -> u32 {
     1884
 }
+pub fn default_network_tcps_psk_port() -> u32 {
+    1885
+}
 pub fn default_network_websocket_port() -> u32 {
     8083
 }
@@ -97,5 +103,12 @@ pub fn default_auth() -> Auth {
         storage_type: "memory".to_string(),
         journal_addr: "".to_string(),
         mysql_addr: "".to_string(),
+        redis_addr: "".to_string(),
+        redis: AuthRedis::default(),
+        password_hash: AuthPasswordHash::default(),
+        jwt: AuthJwt::default(),
+        http: AuthHttp::default(),
+        x509: AuthX509::default(),
+        psk: AuthPsk::default(),
     }
 }
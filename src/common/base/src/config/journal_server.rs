@@ -34,9 +34,10 @@ use serde::Deserialize;
 
 use super::common::Log;
 use super::default_journal_server::{
-    default_grpc_port, default_log, default_network, default_network_tcp_port,
-    default_network_tcps_port, default_prometheus, default_prometheus_port, default_storage,
-    default_system, default_tcp_thread,
+    default_grpc_port, default_log, default_network, default_network_max_read_wait_ms,
+    default_network_tcp_port, default_network_tcps_port, default_prometheus,
+    default_prometheus_port, default_replication, default_storage, default_system,
+    default_tcp_thread,
 };
 use crate::tools::{read_file, try_create_fold};
 
@@ -54,6 +55,8 @@ pub struct JournalServerConfig {
     pub storage: Storage,
     #[serde(default = "default_tcp_thread")]
     pub tcp_thread: TcpThread,
+    #[serde(default = "default_replication")]
+    pub replication: Replication,
     #[serde(default = "default_prometheus")]
     pub prometheus: Prometheus,
     #[serde(default = "default_log")]
@@ -72,6 +75,9 @@ pub struct Network {
     pub tls_cert: String,
     #[serde(default)]
     pub tls_key: String,
+    // Longest time a read may wait for new records, longer waits asked by clients are cut to it
+    #[serde(default = "default_network_max_read_wait_ms")]
+    pub max_read_wait_ms: u64,
 }
 
 #[derive(Debug, Deserialize, Clone, Default)]
@@ -103,6 +109,16 @@ pub struct TcpThread {
     pub response_queue_size: usize,
 }
 
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Replication {
+    #[serde(default)]
+    pub replica_lag_time_max_ms: u64,
+    #[serde(default)]
+    pub replica_fetch_max_bytes: u64,
+    #[serde(default)]
+    pub replica_fetch_backoff_ms: u64,
+}
+
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct Prometheus {
     #[serde(default)]
@@ -188,6 +204,7 @@ mod tests {
         assert_eq!(conf.network.grpc_port, 2228);
         assert_eq!(conf.network.tcp_port, 3110);
         assert_eq!(conf.network.tcps_port, 3111);
+        assert_eq!(conf.network.max_read_wait_ms, 30000);
 
         assert_eq!(conf.system.runtime_work_threads, 100);
 
@@ -198,6 +215,10 @@ mod tests {
         assert_eq!(conf.tcp_thread.request_queue_size, 2000);
         assert_eq!(conf.tcp_thread.response_queue_size, 2000);
 
+        assert_eq!(conf.replication.replica_lag_time_max_ms, 10000);
+        assert_eq!(conf.replication.replica_fetch_max_bytes, 1048576);
+        assert_eq!(conf.replication.replica_fetch_backoff_ms, 100);
+
         assert!(!conf.prometheus.enable);
         assert_eq!(conf.prometheus.model, "pull".to_string());
         assert_eq!(conf.prometheus.port, 9090);
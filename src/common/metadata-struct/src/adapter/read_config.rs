@@ -16,6 +16,8 @@
 pub struct ReadConfig {
     pub max_record_num: u64,
     pub max_size: u64,
+    // How long a read waits for new records when none are available yet, 0 returns immediately
+    pub max_wait_ms: u64,
 }
 
 impl ReadConfig {
@@ -23,6 +25,7 @@ impl ReadConfig {
         ReadConfig {
             max_record_num: 10,
             max_size: 1024 * 1024 * 1024,
+            max_wait_ms: 0,
         }
     }
 }
@@ -26,3 +26,39 @@ pub struct JournalGroupOffset {
     shard_name: String,
     commit_offset: String,
 }
+
+/// The journal node that coordinates a consumer group. Clients and nodes pick it from the
+/// same node list, so the hash must not depend on the process it is computed in.
+pub fn group_coordinator_node(group_name: &str, node_ids: &[u64]) -> Option<u64> {
+    if node_ids.is_empty() {
+        return None;
+    }
+    let mut node_ids = node_ids.to_vec();
+    node_ids.sort();
+
+    // FNV-1a
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in group_name.as_bytes() {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    node_ids
+        .get((hash % node_ids.len() as u64) as usize)
+        .copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::group_coordinator_node;
+
+    #[test]
+    fn group_coordinator_node_test() {
+        assert_eq!(group_coordinator_node("g1", &[]), None);
+        assert_eq!(group_coordinator_node("g1", &[3]), Some(3));
+
+        // the choice does not depend on the order of the node list
+        let node = group_coordinator_node("g1", &[1, 2, 3]);
+        assert_eq!(group_coordinator_node("g1", &[3, 1, 2]), node);
+        assert!(node.is_some());
+    }
+}
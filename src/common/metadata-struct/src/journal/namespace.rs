@@ -19,3 +19,14 @@ use serde::{Deserialize, Serialize};
 pub struct JournalNamespace {
     namespace_name: String,
 }
+
+/// Resource config key under which the default shard retention policy of a namespace is
+/// stored, the value is a json encoded `RetentionPolicy`.
+pub fn namespace_retention_resource_key(namespace: &str) -> Vec<String> {
+    vec![
+        "journal".to_string(),
+        "namespace".to_string(),
+        namespace.to_string(),
+        "retention".to_string(),
+    ]
+}
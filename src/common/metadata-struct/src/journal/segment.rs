@@ -36,6 +36,14 @@ impl JournalSegment {
         self.status == SegmentStatus::Write
     }
 
+    /// A sealed segment no longer accepts writes, so its data does not change anymore.
+    pub fn is_seal_up(&self) -> bool {
+        matches!(
+            self.status,
+            SegmentStatus::SealUp | SegmentStatus::PreDelete | SegmentStatus::Deleting
+        )
+    }
+
     pub fn get_fold(&self, node_id: u64) -> Option<String> {
         for rep in self.replicas.clone() {
             if rep.node_id == node_id {
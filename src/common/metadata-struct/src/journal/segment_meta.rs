@@ -24,6 +24,9 @@ pub struct JournalSegmentMetadata {
     pub end_offset: i64,
     pub start_timestamp: i64,
     pub end_timestamp: i64,
+    // bytes of the segment file, reported by the leader when the segment is sealed
+    #[serde(default)]
+    pub size: i64,
 }
 
 impl JournalSegmentMetadata {
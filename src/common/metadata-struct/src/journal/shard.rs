@@ -27,6 +27,8 @@ pub struct JournalShard {
     pub last_segment_seq: u32,
     pub status: JournalShardStatus,
     pub create_time: u128,
+    #[serde(default)]
+    pub config: JournalShardConfig,
 }
 
 impl JournalShard {
@@ -39,6 +41,54 @@ pub fn shard_name_iden(namespace: &str, shard_name: &str) -> String {
     format!("{}_{}", namespace, shard_name)
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct JournalShardConfig {
+    // None falls back to the default retention policy of the namespace
+    pub retention: Option<RetentionPolicy>,
+    #[serde(default)]
+    pub cleanup_policy: CleanupPolicy,
+    // how long a tombstone of a compacted shard is kept, 0 uses the default
+    #[serde(default)]
+    pub tombstone_retention_sec: u64,
+}
+
+pub const DEFAULT_TOMBSTONE_RETENTION_SEC: u64 = 24 * 3600;
+
+impl JournalShardConfig {
+    pub fn tombstone_retention_sec(&self) -> u64 {
+        if self.tombstone_retention_sec == 0 {
+            return DEFAULT_TOMBSTONE_RETENTION_SEC;
+        }
+        self.tombstone_retention_sec
+    }
+}
+
+/// `Compact` keeps only the latest record of every key in the sealed segments of a shard,
+/// a record with empty content is a tombstone that removes its key.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub enum CleanupPolicy {
+    #[default]
+    Delete,
+    Compact,
+}
+
+/// Sealed segments outside of any of these limits are deleted, a value of 0 disables the limit.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    // max age of the newest record of a segment
+    pub max_age_sec: u64,
+    // max total bytes of the sealed segments of a shard
+    pub max_bytes: u64,
+    // max number of segments of a shard, including the active one
+    pub max_segment_num: u32,
+}
+
+impl RetentionPolicy {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_age_sec == 0 && self.max_bytes == 0 && self.max_segment_num == 0
+    }
+}
+
 #[derive(Default, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 pub enum JournalShardStatus {
     #[default]
@@ -12,7 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use protocol::mqtt::common::QoS;
+use protocol::mqtt::common::{QoS, RetainForwardRule};
 use serde::{Deserialize, Serialize};
 
 // Dynamic configuration of MQTT cluster latitude
@@ -23,6 +23,12 @@ pub struct MqttClusterDynamicConfig {
     pub security: MqttClusterDynamicConfigSecurity,
     pub network: MqttClusterDynamicConfigNetwork,
     pub slow: MqttClusterDynamicSlowSub,
+    #[serde(default)]
+    pub flow_control: MqttClusterDynamicFlowControl,
+    #[serde(default)]
+    pub delay_publish: MqttClusterDynamicDelayPublish,
+    #[serde(default)]
+    pub auto_subscribe: MqttClusterDynamicAutoSubscribe,
 }
 
 // MQTT cluster protocol related dynamic configuration
@@ -66,6 +72,65 @@ pub struct MqttClusterDynamicConfigFeature {
     pub shared_subscription_available: AvailableFlag,
 }
 
+// MQTT cluster flow control related dynamic configuration.
+// Every *_rate is a per-second rate, 0 means unlimited. Every *_burst is the number
+// of tokens that can be spent at once after an idle period, 0 means one second of the rate.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct MqttClusterDynamicFlowControl {
+    // New connections accepted by each broker across all of its listeners. The limit is
+    // not shared between brokers, a cluster of N brokers accepts up to N times this rate.
+    pub max_broker_connection_rate: u64,
+    #[serde(default)]
+    pub max_broker_connection_burst: u64,
+    // New connections accepted by each listener (tcp, tls, websocket, websockets, quic)
+    pub max_listener_connection_rate: u64,
+    #[serde(default)]
+    pub max_listener_connection_burst: u64,
+    // New connections accepted from a single client IP
+    pub max_client_ip_connection_rate: u64,
+    #[serde(default)]
+    pub max_client_ip_connection_burst: u64,
+    // SUBSCRIBE packets accepted from a single client
+    pub max_client_subscribe_rate: u64,
+    #[serde(default)]
+    pub max_client_subscribe_burst: u64,
+    // PUBLISH packets accepted from a single client
+    pub max_client_publish_rate: u64,
+    #[serde(default)]
+    pub max_client_publish_burst: u64,
+    // Publish payload bytes accepted on a single connection
+    pub max_connection_bytes_rate: u64,
+    #[serde(default)]
+    pub max_connection_bytes_burst: u64,
+}
+
+// MQTT cluster delayed publish related dynamic configuration, 0 means unlimited.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct MqttClusterDynamicDelayPublish {
+    // Delayed messages that may be pending across the cluster at the same time
+    pub max_delayed_messages: u64,
+    // Longest delay in seconds a client may ask for
+    pub max_delay_secs: u64,
+}
+
+// MQTT cluster auto subscribe related dynamic configuration.
+// Every client is subscribed to these filters as soon as it connects.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct MqttClusterDynamicAutoSubscribe {
+    pub rules: Vec<MqttAutoSubscribeRule>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+pub struct MqttAutoSubscribeRule {
+    // Topic filter, ${clientid} and ${username} are replaced with the connecting client's values
+    pub topic: String,
+    pub qos: QoS,
+    // The following options are only applied to MQTT 5 clients
+    pub no_local: bool,
+    pub retain_as_published: bool,
+    pub retained_handling: RetainForwardRule,
+}
+
 #[derive(Serialize, Deserialize, Default, Clone)]
 pub struct MqttClusterDynamicSlowSub {
     pub enable: bool,
@@ -112,6 +177,25 @@ impl MqttClusterDynamicConfig {
                 internal_ms: 0,
                 response_ms: 0,
             },
+            flow_control: MqttClusterDynamicFlowControl {
+                max_broker_connection_rate: 0,
+                max_broker_connection_burst: 0,
+                max_listener_connection_rate: 0,
+                max_listener_connection_burst: 0,
+                max_client_ip_connection_rate: 0,
+                max_client_ip_connection_burst: 0,
+                max_client_subscribe_rate: 0,
+                max_client_subscribe_burst: 0,
+                max_client_publish_rate: 0,
+                max_client_publish_burst: 0,
+                max_connection_bytes_rate: 0,
+                max_connection_bytes_burst: 0,
+            },
+            delay_publish: MqttClusterDynamicDelayPublish {
+                max_delayed_messages: 100000,
+                max_delay_secs: 4294967,
+            },
+            auto_subscribe: MqttClusterDynamicAutoSubscribe { rules: Vec::new() },
         }
     }
 
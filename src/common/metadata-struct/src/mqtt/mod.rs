@@ -17,6 +17,9 @@ pub mod connection;
 pub mod lastwill;
 pub mod message;
 pub mod node_extend;
+pub mod psk;
+pub mod rule;
 pub mod session;
 pub mod topic;
+pub mod topic_rewrite_rule;
 pub mod user;
@@ -0,0 +1,101 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use common_base::error::common::CommonError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct MqttRule {
+    pub rule_name: String,
+    // SELECT <fields> FROM "<topic filter>" [WHERE <condition>]
+    pub sql: String,
+    // Every action receives the projected result of a message that passed the rule
+    pub actions: Vec<MqttRuleAction>,
+    pub enable: bool,
+    pub description: String,
+    pub create_time: u64,
+}
+
+impl MqttRule {
+    pub fn encode(&self) -> Result<Vec<u8>, CommonError> {
+        Ok(serde_json::to_vec(&self)?)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, CommonError> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MqttRuleAction {
+    // Publish the result as a JSON payload, ${clientid}, ${username} and ${topic}
+    // in the topic are replaced with the values of the source message
+    Republish {
+        topic: String,
+        #[serde(default)]
+        qos: u8,
+        #[serde(default)]
+        retain: bool,
+    },
+    // Append the result as a JSON record to a shard of the message storage
+    Storage {
+        shard_name: String,
+    },
+    // Hand the result to a registered bridge sink
+    Bridge {
+        sink_name: String,
+    },
+}
+
+impl MqttRuleAction {
+    pub fn decode_list(data: &str) -> Result<Vec<MqttRuleAction>, CommonError> {
+        Ok(serde_json::from_str(data)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mqtt::rule::MqttRuleAction;
+
+    #[test]
+    fn decode_rule_action_test() {
+        let actions = MqttRuleAction::decode_list(
+            r#"[
+                {"type": "republish", "topic": "alarm/${clientid}", "qos": 1},
+                {"type": "storage", "shard_name": "sensor_alarm"},
+                {"type": "bridge", "sink_name": "kafka"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            actions,
+            vec![
+                MqttRuleAction::Republish {
+                    topic: "alarm/${clientid}".to_string(),
+                    qos: 1,
+                    retain: false,
+                },
+                MqttRuleAction::Storage {
+                    shard_name: "sensor_alarm".to_string(),
+                },
+                MqttRuleAction::Bridge {
+                    sink_name: "kafka".to_string(),
+                },
+            ]
+        );
+
+        assert!(MqttRuleAction::decode_list(r#"[{"type": "unknown"}]"#).is_err());
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::fmt;
+
+use common_base::error::common::CommonError;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct MqttTopicRewriteRule {
+    pub action: MqttTopicRewriteAction,
+    // Topic filter a topic has to match for the rule to apply
+    pub source_topic: String,
+    // Template of the new topic, $N refers to a capture, ${clientid} and
+    // ${username} to the client
+    pub dest_topic: String,
+    // Optional regex that provides the captures, when empty every wildcard
+    // level of source_topic is a capture
+    pub regex: String,
+    pub create_time: u64,
+}
+
+impl MqttTopicRewriteRule {
+    pub fn encode(&self) -> Result<Vec<u8>, CommonError> {
+        Ok(serde_json::to_vec(&self)?)
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, CommonError> {
+        Ok(serde_json::from_slice(data)?)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum MqttTopicRewriteAction {
+    All,
+    Publish,
+    Subscribe,
+}
+
+impl MqttTopicRewriteAction {
+    pub fn parse(action: &str) -> Result<Self, CommonError> {
+        match action.to_lowercase().as_str() {
+            "all" => Ok(MqttTopicRewriteAction::All),
+            "publish" => Ok(MqttTopicRewriteAction::Publish),
+            "subscribe" => Ok(MqttTopicRewriteAction::Subscribe),
+            _ => Err(CommonError::CommonError(format!(
+                "Invalid topic rewrite action {}, it must be one of all, publish, subscribe",
+                action
+            ))),
+        }
+    }
+
+    // Whether a rule of this action applies to the given operation
+    pub fn contains(&self, action: MqttTopicRewriteAction) -> bool {
+        *self == MqttTopicRewriteAction::All || *self == action
+    }
+}
+
+impl fmt::Display for MqttTopicRewriteAction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                MqttTopicRewriteAction::All => "All",
+                MqttTopicRewriteAction::Publish => "Publish",
+                MqttTopicRewriteAction::Subscribe => "Subscribe",
+            }
+        )
+    }
+}
@@ -17,6 +17,8 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct MqttUser {
     pub username: String,
+    // bcrypt hash, pbkdf2/argon2 PHC string or $sha256$<salt position>$<salt>$<digest>.
+    // Users created before passwords were hashed keep the cleartext until their next login.
     pub password: String,
     pub is_superuser: bool,
 }
@@ -160,6 +160,9 @@ impl RocksDBEngine {
 
         while iter.valid() {
             if let Some(key) = iter.key() {
+                if !key.starts_with(search_key.as_bytes()) {
+                    break;
+                }
                 self.db.delete_cf(cf, key)?
             }
             iter.next();
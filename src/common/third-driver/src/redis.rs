@@ -0,0 +1,88 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use common_base::error::common::CommonError;
+use redis::aio::{ConnectionLike, MultiplexedConnection};
+use redis::{Client, Cmd, Pipeline, RedisFuture, RedisResult, Value};
+use tokio::sync::Mutex;
+
+// A multiplexed async connection that is shared by all callers. When the server
+// drops the connection it is opened again by the next command.
+#[derive(Clone)]
+pub struct RedisConnection {
+    client: Client,
+    conn: Arc<Mutex<Option<MultiplexedConnection>>>,
+}
+
+impl RedisConnection {
+    async fn current(&self) -> RedisResult<MultiplexedConnection> {
+        let mut conn = self.conn.lock().await;
+        if let Some(data) = conn.as_ref() {
+            return Ok(data.clone());
+        }
+        let data = self.client.get_multiplexed_tokio_connection().await?;
+        *conn = Some(data.clone());
+        Ok(data)
+    }
+
+    async fn check<T>(&self, result: RedisResult<T>) -> RedisResult<T> {
+        if let Err(e) = &result {
+            if e.is_connection_dropped() || e.is_io_error() || e.is_unrecoverable_error() {
+                self.conn.lock().await.take();
+            }
+        }
+        result
+    }
+}
+
+impl ConnectionLike for RedisConnection {
+    fn req_packed_command<'a>(&'a mut self, cmd: &'a Cmd) -> RedisFuture<'a, Value> {
+        Box::pin(async move {
+            let mut conn = self.current().await?;
+            let result = conn.req_packed_command(cmd).await;
+            self.check(result).await
+        })
+    }
+
+    fn req_packed_commands<'a>(
+        &'a mut self,
+        cmd: &'a Pipeline,
+        offset: usize,
+        count: usize,
+    ) -> RedisFuture<'a, Vec<Value>> {
+        Box::pin(async move {
+            let mut conn = self.current().await?;
+            let result = conn.req_packed_commands(cmd, offset, count).await;
+            self.check(result).await
+        })
+    }
+
+    fn get_db(&self) -> i64 {
+        self.client.get_connection_info().redis.db
+    }
+}
+
+// The connection is opened lazily by the first command.
+pub fn build_redis_conn(addr: &str) -> Result<RedisConnection, CommonError> {
+    let client = match Client::open(addr) {
+        Ok(client) => client,
+        Err(e) => return Err(CommonError::CommonError(e.to_string())),
+    };
+    Ok(RedisConnection {
+        client,
+        conn: Arc::new(Mutex::new(None)),
+    })
+}
@@ -17,8 +17,10 @@ use std::sync::Arc;
 use common_base::error::common::CommonError;
 use protocol::journal_server::journal_inner::{
     DeleteSegmentFileReply, DeleteSegmentFileRequest, DeleteShardFileReply, DeleteShardFileRequest,
-    GetSegmentDeleteStatusReply, GetSegmentDeleteStatusRequest, GetShardDeleteStatusReply,
-    GetShardDeleteStatusRequest, UpdateJournalCacheReply, UpdateJournalCacheRequest,
+    FetchProducerStateReply, FetchProducerStateRequest, FetchSegmentDataReply,
+    FetchSegmentDataRequest, GetSegmentDeleteStatusReply, GetSegmentDeleteStatusRequest,
+    GetShardDeleteStatusReply, GetShardDeleteStatusRequest, UpdateJournalCacheReply,
+    UpdateJournalCacheRequest,
 };
 
 use crate::journal::{call_once, JournalEngineReply, JournalEngineRequest};
@@ -84,3 +86,27 @@ pub async fn journal_inner_get_segment_delete_status(
         _ => unreachable!("Reply type mismatch"),
     }
 }
+
+pub async fn journal_inner_fetch_segment_data(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: FetchSegmentDataRequest,
+) -> Result<FetchSegmentDataReply, CommonError> {
+    let request = JournalEngineRequest::FetchSegmentData(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        JournalEngineReply::FetchSegmentData(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn journal_inner_fetch_producer_state(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: FetchProducerStateRequest,
+) -> Result<FetchProducerStateReply, CommonError> {
+    let request = JournalEngineRequest::FetchProducerState(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        JournalEngineReply::FetchProducerState(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
@@ -18,8 +18,10 @@ use protocol::journal_server::journal_admin::{
 };
 use protocol::journal_server::journal_inner::{
     DeleteSegmentFileReply, DeleteSegmentFileRequest, DeleteShardFileReply, DeleteShardFileRequest,
-    GetSegmentDeleteStatusReply, GetSegmentDeleteStatusRequest, GetShardDeleteStatusReply,
-    GetShardDeleteStatusRequest, UpdateJournalCacheReply, UpdateJournalCacheRequest,
+    FetchProducerStateReply, FetchProducerStateRequest, FetchSegmentDataReply,
+    FetchSegmentDataRequest, GetSegmentDeleteStatusReply, GetSegmentDeleteStatusRequest,
+    GetShardDeleteStatusReply, GetShardDeleteStatusRequest, UpdateJournalCacheReply,
+    UpdateJournalCacheRequest,
 };
 
 use crate::pool::ClientPool;
@@ -36,6 +38,8 @@ pub enum JournalEngineRequest {
     GetShardDeleteStatus(GetShardDeleteStatusRequest),
     DeleteSegmentFileRequest(DeleteSegmentFileRequest),
     GetSegmentDeleteStatus(GetSegmentDeleteStatusRequest),
+    FetchSegmentData(FetchSegmentDataRequest),
+    FetchProducerState(FetchProducerStateRequest),
 
     // admin
     ListShard(ListShardRequest),
@@ -51,6 +55,8 @@ pub enum JournalEngineReply {
     GetShardDeleteStatus(GetShardDeleteStatusReply),
     DeleteSegmentFile(DeleteSegmentFileReply),
     GetSegmentDeleteStatus(GetSegmentDeleteStatusReply),
+    FetchSegmentData(FetchSegmentDataReply),
+    FetchProducerState(FetchProducerStateReply),
 
     // admin
     ListShard(ListShardReply),
@@ -98,6 +104,20 @@ async fn call_once(
                 reply.into_inner(),
             ))
         }
+        FetchSegmentData(fetch_segment_data_request) => {
+            let mut client = client_pool.journal_inner_services_client(addr).await?;
+            let reply = client
+                .fetch_segment_data(fetch_segment_data_request)
+                .await?;
+            Ok(JournalEngineReply::FetchSegmentData(reply.into_inner()))
+        }
+        FetchProducerState(fetch_producer_state_request) => {
+            let mut client = client_pool.journal_inner_services_client(addr).await?;
+            let reply = client
+                .fetch_producer_state(fetch_producer_state_request)
+                .await?;
+            Ok(JournalEngineReply::FetchProducerState(reply.into_inner()))
+        }
         ListShard(list_shard_request) => {
             let mut client = client_pool.journal_admin_services_client(addr).await?;
             let reply = client.list_shard(list_shard_request).await?;
@@ -16,13 +16,20 @@ use std::sync::Arc;
 
 use common_base::error::common::CommonError;
 use protocol::broker_mqtt::broker_mqtt_admin::{
-    ClusterStatusReply, ClusterStatusRequest, CreateAclReply, CreateAclRequest,
-    CreateBlacklistReply, CreateBlacklistRequest, CreateUserReply, CreateUserRequest,
-    DeleteAclReply, DeleteAclRequest, DeleteBlacklistReply, DeleteBlacklistRequest,
+    CancelDelayPublishReply, CancelDelayPublishRequest, ClusterStatusReply, ClusterStatusRequest,
+    CreateAclReply, CreateAclRequest, CreateBlacklistReply, CreateBlacklistRequest, CreatePskReply,
+    CreatePskRequest, CreateRuleReply, CreateRuleRequest, CreateTopicRewriteRuleReply,
+    CreateTopicRewriteRuleRequest, CreateUserReply, CreateUserRequest, DeleteAclReply,
+    DeleteAclRequest, DeleteAutoSubscribeRuleReply, DeleteAutoSubscribeRuleRequest,
+    DeleteBlacklistReply, DeleteBlacklistRequest, DeletePskReply, DeletePskRequest,
+    DeleteRuleReply, DeleteRuleRequest, DeleteTopicRewriteRuleReply, DeleteTopicRewriteRuleRequest,
     DeleteUserReply, DeleteUserRequest, EnableSlowSubScribeReply, EnableSlowSubscribeRequest,
-    ListAclReply, ListAclRequest, ListBlacklistReply, ListBlacklistRequest, ListConnectionReply,
-    ListConnectionRequest, ListSlowSubscribeReply, ListSlowSubscribeRequest, ListTopicReply,
-    ListTopicRequest, ListUserReply, ListUserRequest,
+    ListAclReply, ListAclRequest, ListAutoSubscribeRuleReply, ListAutoSubscribeRuleRequest,
+    ListBlacklistReply, ListBlacklistRequest, ListConnectionReply, ListConnectionRequest,
+    ListDelayPublishReply, ListDelayPublishRequest, ListPskReply, ListPskRequest, ListRuleReply,
+    ListRuleRequest, ListSlowSubscribeReply, ListSlowSubscribeRequest, ListTopicReply,
+    ListTopicRequest, ListTopicRewriteRuleReply, ListTopicRewriteRuleRequest, ListUserReply,
+    ListUserRequest, SetAutoSubscribeRuleReply, SetAutoSubscribeRuleRequest,
 };
 
 use crate::mqtt::{call_once, MqttBrokerPlacementReply, MqttBrokerPlacementRequest};
@@ -152,6 +159,43 @@ pub async fn mqtt_broker_delete_blacklist(
     }
 }
 
+// ------- psk -------
+pub async fn mqtt_broker_list_psk(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: ListPskRequest,
+) -> Result<ListPskReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::ListPsk(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::ListPsk(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_create_psk(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: CreatePskRequest,
+) -> Result<CreatePskReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::CreatePsk(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::CreatePsk(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_delete_psk(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: DeletePskRequest,
+) -> Result<DeletePskReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::DeletePsk(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::DeletePsk(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
 // ------- connection  -----------
 pub async fn mqtt_broker_list_connection(
     client_pool: Arc<ClientPool>,
@@ -202,3 +246,135 @@ pub async fn mqtt_broker_list_topic(
         _ => unreachable!("Reply type mismatch"),
     }
 }
+
+pub async fn mqtt_broker_list_delay_publish(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: ListDelayPublishRequest,
+) -> Result<ListDelayPublishReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::ListDelayPublish(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::ListDelayPublish(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_cancel_delay_publish(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: CancelDelayPublishRequest,
+) -> Result<CancelDelayPublishReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::CancelDelayPublish(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::CancelDelayPublish(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_list_topic_rewrite_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: ListTopicRewriteRuleRequest,
+) -> Result<ListTopicRewriteRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::ListTopicRewriteRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::ListTopicRewriteRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_create_topic_rewrite_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: CreateTopicRewriteRuleRequest,
+) -> Result<CreateTopicRewriteRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::CreateTopicRewriteRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::CreateTopicRewriteRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_delete_topic_rewrite_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: DeleteTopicRewriteRuleRequest,
+) -> Result<DeleteTopicRewriteRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::DeleteTopicRewriteRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::DeleteTopicRewriteRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_list_auto_subscribe_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: ListAutoSubscribeRuleRequest,
+) -> Result<ListAutoSubscribeRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::ListAutoSubscribeRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::ListAutoSubscribeRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_set_auto_subscribe_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: SetAutoSubscribeRuleRequest,
+) -> Result<SetAutoSubscribeRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::SetAutoSubscribeRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::SetAutoSubscribeRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_delete_auto_subscribe_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: DeleteAutoSubscribeRuleRequest,
+) -> Result<DeleteAutoSubscribeRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::DeleteAutoSubscribeRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::DeleteAutoSubscribeRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_list_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: ListRuleRequest,
+) -> Result<ListRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::ListRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::ListRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_create_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: CreateRuleRequest,
+) -> Result<CreateRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::CreateRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::CreateRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
+
+pub async fn mqtt_broker_delete_rule(
+    client_pool: Arc<ClientPool>,
+    addrs: &[String],
+    request: DeleteRuleRequest,
+) -> Result<DeleteRuleReply, CommonError> {
+    let request = MqttBrokerPlacementRequest::DeleteRule(request);
+    match retry_call(&client_pool, addrs, request, call_once).await? {
+        MqttBrokerPlacementReply::DeleteRule(reply) => Ok(reply),
+        _ => unreachable!("Reply type mismatch"),
+    }
+}
@@ -14,13 +14,20 @@
 
 use common_base::error::common::CommonError;
 use protocol::broker_mqtt::broker_mqtt_admin::{
-    ClusterStatusReply, ClusterStatusRequest, CreateAclReply, CreateAclRequest,
-    CreateBlacklistReply, CreateBlacklistRequest, CreateUserReply, CreateUserRequest,
-    DeleteAclReply, DeleteAclRequest, DeleteBlacklistReply, DeleteBlacklistRequest,
+    CancelDelayPublishReply, CancelDelayPublishRequest, ClusterStatusReply, ClusterStatusRequest,
+    CreateAclReply, CreateAclRequest, CreateBlacklistReply, CreateBlacklistRequest, CreatePskReply,
+    CreatePskRequest, CreateRuleReply, CreateRuleRequest, CreateTopicRewriteRuleReply,
+    CreateTopicRewriteRuleRequest, CreateUserReply, CreateUserRequest, DeleteAclReply,
+    DeleteAclRequest, DeleteAutoSubscribeRuleReply, DeleteAutoSubscribeRuleRequest,
+    DeleteBlacklistReply, DeleteBlacklistRequest, DeletePskReply, DeletePskRequest,
+    DeleteRuleReply, DeleteRuleRequest, DeleteTopicRewriteRuleReply, DeleteTopicRewriteRuleRequest,
     DeleteUserReply, DeleteUserRequest, EnableSlowSubScribeReply, EnableSlowSubscribeRequest,
-    ListAclReply, ListAclRequest, ListBlacklistReply, ListBlacklistRequest, ListConnectionReply,
-    ListConnectionRequest, ListSlowSubscribeReply, ListSlowSubscribeRequest, ListTopicReply,
-    ListTopicRequest, ListUserReply, ListUserRequest,
+    ListAclReply, ListAclRequest, ListAutoSubscribeRuleReply, ListAutoSubscribeRuleRequest,
+    ListBlacklistReply, ListBlacklistRequest, ListConnectionReply, ListConnectionRequest,
+    ListDelayPublishReply, ListDelayPublishRequest, ListPskReply, ListPskRequest, ListRuleReply,
+    ListRuleRequest, ListSlowSubscribeReply, ListSlowSubscribeRequest, ListTopicReply,
+    ListTopicRequest, ListTopicRewriteRuleReply, ListTopicRewriteRuleRequest, ListUserReply,
+    ListUserRequest, SetAutoSubscribeRuleReply, SetAutoSubscribeRuleRequest,
 };
 use protocol::broker_mqtt::broker_mqtt_inner::{
     DeleteSessionReply, DeleteSessionRequest, SendLastWillMessageReply, SendLastWillMessageRequest,
@@ -48,6 +55,9 @@ pub enum MqttBrokerPlacementRequest {
     ListBlacklist(ListBlacklistRequest),
     CreateBlacklist(CreateBlacklistRequest),
     DeleteBlacklist(DeleteBlacklistRequest),
+    ListPsk(ListPskRequest),
+    CreatePsk(CreatePskRequest),
+    DeletePsk(DeletePskRequest),
 
     // connection
     ListConnection(ListConnectionRequest),
@@ -57,6 +67,25 @@ pub enum MqttBrokerPlacementRequest {
     ListSlowSubscribe(ListSlowSubscribeRequest),
 
     ListTopic(ListTopicRequest),
+
+    // delayed publish
+    ListDelayPublish(ListDelayPublishRequest),
+    CancelDelayPublish(CancelDelayPublishRequest),
+
+    // topic rewrite
+    ListTopicRewriteRule(ListTopicRewriteRuleRequest),
+    CreateTopicRewriteRule(CreateTopicRewriteRuleRequest),
+    DeleteTopicRewriteRule(DeleteTopicRewriteRuleRequest),
+
+    // auto subscribe
+    ListAutoSubscribeRule(ListAutoSubscribeRuleRequest),
+    SetAutoSubscribeRule(SetAutoSubscribeRuleRequest),
+    DeleteAutoSubscribeRule(DeleteAutoSubscribeRuleRequest),
+
+    // rule engine
+    ListRule(ListRuleRequest),
+    CreateRule(CreateRuleRequest),
+    DeleteRule(DeleteRuleRequest),
 }
 
 /// Enum wrapper for all possible replies from the mqtt broker
@@ -78,6 +107,9 @@ pub enum MqttBrokerPlacementReply {
     ListBlacklist(ListBlacklistReply),
     CreateBlacklist(CreateBlacklistReply),
     DeleteBlacklist(DeleteBlacklistReply),
+    ListPsk(ListPskReply),
+    CreatePsk(CreatePskReply),
+    DeletePsk(DeletePskReply),
 
     // connection
     ListConnection(ListConnectionReply),
@@ -87,6 +119,25 @@ pub enum MqttBrokerPlacementReply {
 
     ListTopic(ListTopicReply),
     ListSlowSubscribe(ListSlowSubscribeReply),
+
+    // delayed publish
+    ListDelayPublish(ListDelayPublishReply),
+    CancelDelayPublish(CancelDelayPublishReply),
+
+    // topic rewrite
+    ListTopicRewriteRule(ListTopicRewriteRuleReply),
+    CreateTopicRewriteRule(CreateTopicRewriteRuleReply),
+    DeleteTopicRewriteRule(DeleteTopicRewriteRuleReply),
+
+    // auto subscribe
+    ListAutoSubscribeRule(ListAutoSubscribeRuleReply),
+    SetAutoSubscribeRule(SetAutoSubscribeRuleReply),
+    DeleteAutoSubscribeRule(DeleteAutoSubscribeRuleReply),
+
+    // rule engine
+    ListRule(ListRuleReply),
+    CreateRule(CreateRuleReply),
+    DeleteRule(DeleteRuleReply),
 }
 
 pub mod admin;
@@ -179,6 +230,21 @@ async fn call_once(
                 reply.into_inner(),
             ))
         }
+        ListPsk(list_psk_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client.mqtt_broker_list_psk(list_psk_request).await?;
+            Ok(MqttBrokerPlacementReply::ListPsk(reply.into_inner()))
+        }
+        CreatePsk(create_psk_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client.mqtt_broker_create_psk(create_psk_request).await?;
+            Ok(MqttBrokerPlacementReply::CreatePsk(reply.into_inner()))
+        }
+        DeletePsk(delete_psk_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client.mqtt_broker_delete_psk(delete_psk_request).await?;
+            Ok(MqttBrokerPlacementReply::DeletePsk(reply.into_inner()))
+        }
         ListConnection(list_connection_request) => {
             let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
             let reply = client
@@ -211,6 +277,104 @@ async fn call_once(
             let reply = client.mqtt_broker_list_topic(list_topic_request).await?;
             Ok(MqttBrokerPlacementReply::ListTopic(reply.into_inner()))
         }
+
+        ListDelayPublish(list_delay_publish_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client
+                .mqtt_broker_list_delay_publish(list_delay_publish_request)
+                .await?;
+            Ok(MqttBrokerPlacementReply::ListDelayPublish(
+                reply.into_inner(),
+            ))
+        }
+
+        CancelDelayPublish(cancel_delay_publish_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client
+                .mqtt_broker_cancel_delay_publish(cancel_delay_publish_request)
+                .await?;
+            Ok(MqttBrokerPlacementReply::CancelDelayPublish(
+                reply.into_inner(),
+            ))
+        }
+
+        ListTopicRewriteRule(list_topic_rewrite_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client
+                .mqtt_broker_list_topic_rewrite_rule(list_topic_rewrite_rule_request)
+                .await?;
+            Ok(MqttBrokerPlacementReply::ListTopicRewriteRule(
+                reply.into_inner(),
+            ))
+        }
+
+        CreateTopicRewriteRule(create_topic_rewrite_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client
+                .mqtt_broker_create_topic_rewrite_rule(create_topic_rewrite_rule_request)
+                .await?;
+            Ok(MqttBrokerPlacementReply::CreateTopicRewriteRule(
+                reply.into_inner(),
+            ))
+        }
+
+        DeleteTopicRewriteRule(delete_topic_rewrite_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client
+                .mqtt_broker_delete_topic_rewrite_rule(delete_topic_rewrite_rule_request)
+                .await?;
+            Ok(MqttBrokerPlacementReply::DeleteTopicRewriteRule(
+                reply.into_inner(),
+            ))
+        }
+
+        ListAutoSubscribeRule(list_auto_subscribe_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client
+                .mqtt_broker_list_auto_subscribe_rule(list_auto_subscribe_rule_request)
+                .await?;
+            Ok(MqttBrokerPlacementReply::ListAutoSubscribeRule(
+                reply.into_inner(),
+            ))
+        }
+
+        SetAutoSubscribeRule(set_auto_subscribe_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client
+                .mqtt_broker_set_auto_subscribe_rule(set_auto_subscribe_rule_request)
+                .await?;
+            Ok(MqttBrokerPlacementReply::SetAutoSubscribeRule(
+                reply.into_inner(),
+            ))
+        }
+
+        DeleteAutoSubscribeRule(delete_auto_subscribe_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client
+                .mqtt_broker_delete_auto_subscribe_rule(delete_auto_subscribe_rule_request)
+                .await?;
+            Ok(MqttBrokerPlacementReply::DeleteAutoSubscribeRule(
+                reply.into_inner(),
+            ))
+        }
+
+        ListRule(list_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client.mqtt_broker_list_rule(list_rule_request).await?;
+            Ok(MqttBrokerPlacementReply::ListRule(reply.into_inner()))
+        }
+
+        CreateRule(create_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client.mqtt_broker_create_rule(create_rule_request).await?;
+            Ok(MqttBrokerPlacementReply::CreateRule(reply.into_inner()))
+        }
+
+        DeleteRule(delete_rule_request) => {
+            let mut client = client_pool.mqtt_broker_admin_services_client(addr).await?;
+            let reply = client.mqtt_broker_delete_rule(delete_rule_request).await?;
+            Ok(MqttBrokerPlacementReply::DeleteRule(reply.into_inner()))
+        }
     }
 }
 
@@ -83,6 +83,15 @@ pub enum PlacementCenterInterface {
     CreateBlackList,
     DeleteBlackList,
     ListBlackList,
+    CreateTopicRewriteRule,
+    DeleteTopicRewriteRule,
+    ListTopicRewriteRule,
+    CreateRule,
+    DeleteRule,
+    ListRule,
+    CreatePsk,
+    DeletePsk,
+    ListPsk,
 
     // Open Raft
     Vote,
@@ -130,6 +139,12 @@ impl PlacementCenterInterface {
                 set.insert(PlacementCenterInterface::DeleteAcl);
                 set.insert(PlacementCenterInterface::CreateBlackList);
                 set.insert(PlacementCenterInterface::DeleteBlackList);
+                set.insert(PlacementCenterInterface::CreateTopicRewriteRule);
+                set.insert(PlacementCenterInterface::DeleteTopicRewriteRule);
+                set.insert(PlacementCenterInterface::CreateRule);
+                set.insert(PlacementCenterInterface::DeleteRule);
+                set.insert(PlacementCenterInterface::CreatePsk);
+                set.insert(PlacementCenterInterface::DeletePsk);
 
                 // placement inner interface
                 set.insert(PlacementCenterInterface::RegisterNode);
@@ -16,16 +16,20 @@ use std::sync::Arc;
 
 use common_base::error::common::CommonError;
 use protocol::placement_center::placement_center_mqtt::{
-    CreateAclReply, CreateAclRequest, CreateBlacklistReply, CreateBlacklistRequest,
-    CreateSessionReply, CreateSessionRequest, CreateTopicReply, CreateTopicRequest,
-    CreateUserReply, CreateUserRequest, DeleteAclReply, DeleteAclRequest, DeleteBlacklistReply,
-    DeleteBlacklistRequest, DeleteSessionReply, DeleteSessionRequest, DeleteTopicReply,
-    DeleteTopicRequest, DeleteUserReply, DeleteUserRequest, GetShareSubLeaderReply,
+    CreateAclReply, CreateAclRequest, CreateBlacklistReply, CreateBlacklistRequest, CreatePskReply,
+    CreatePskRequest, CreateRuleReply, CreateRuleRequest, CreateSessionReply, CreateSessionRequest,
+    CreateTopicReply, CreateTopicRequest, CreateTopicRewriteRuleReply,
+    CreateTopicRewriteRuleRequest, CreateUserReply, CreateUserRequest, DeleteAclReply,
+    DeleteAclRequest, DeleteBlacklistReply, DeleteBlacklistRequest, DeletePskReply,
+    DeletePskRequest, DeleteRuleReply, DeleteRuleRequest, DeleteSessionReply, DeleteSessionRequest,
+    DeleteTopicReply, DeleteTopicRequest, DeleteTopicRewriteRuleReply,
+    DeleteTopicRewriteRuleRequest, DeleteUserReply, DeleteUserRequest, GetShareSubLeaderReply,
     GetShareSubLeaderRequest, ListAclReply, ListAclRequest, ListBlacklistReply,
-    ListBlacklistRequest, ListSessionReply, ListSessionRequest, ListTopicReply, ListTopicRequest,
-    ListUserReply, ListUserRequest, SaveLastWillMessageReply, SaveLastWillMessageRequest,
-    SetTopicRetainMessageReply, SetTopicRetainMessageRequest, UpdateSessionReply,
-    UpdateSessionRequest,
+    ListBlacklistRequest, ListPskReply, ListPskRequest, ListRuleReply, ListRuleRequest,
+    ListSessionReply, ListSessionRequest, ListTopicReply, ListTopicRequest,
+    ListTopicRewriteRuleReply, ListTopicRewriteRuleRequest, ListUserReply, ListUserRequest,
+    SaveLastWillMessageReply, SaveLastWillMessageRequest, SetTopicRetainMessageReply,
+    SetTopicRetainMessageRequest, UpdateSessionReply, UpdateSessionRequest,
 };
 
 use super::{MqttServiceReply, MqttServiceRequest};
@@ -147,3 +151,27 @@ generate_mqtt_service_call!(
     DeleteBlacklistReply,
     DeleteBlacklist
 );
+generate_mqtt_service_call!(
+    create_topic_rewrite_rule,
+    CreateTopicRewriteRuleRequest,
+    CreateTopicRewriteRuleReply,
+    CreateTopicRewriteRule
+);
+generate_mqtt_service_call!(
+    list_topic_rewrite_rule,
+    ListTopicRewriteRuleRequest,
+    ListTopicRewriteRuleReply,
+    ListTopicRewriteRule
+);
+generate_mqtt_service_call!(
+    delete_topic_rewrite_rule,
+    DeleteTopicRewriteRuleRequest,
+    DeleteTopicRewriteRuleReply,
+    DeleteTopicRewriteRule
+);
+generate_mqtt_service_call!(create_rule, CreateRuleRequest, CreateRuleReply, CreateRule);
+generate_mqtt_service_call!(list_rule, ListRuleRequest, ListRuleReply, ListRule);
+generate_mqtt_service_call!(delete_rule, DeleteRuleRequest, DeleteRuleReply, DeleteRule);
+generate_mqtt_service_call!(create_psk, CreatePskRequest, CreatePskReply, CreatePsk);
+generate_mqtt_service_call!(list_psk, ListPskRequest, ListPskReply, ListPsk);
+generate_mqtt_service_call!(delete_psk, DeletePskRequest, DeletePskReply, DeletePsk);
@@ -16,16 +16,20 @@ use common_base::error::common::CommonError;
 use mobc::Manager;
 use protocol::placement_center::placement_center_mqtt::mqtt_service_client::MqttServiceClient;
 use protocol::placement_center::placement_center_mqtt::{
-    CreateAclReply, CreateAclRequest, CreateBlacklistReply, CreateBlacklistRequest,
-    CreateSessionReply, CreateSessionRequest, CreateTopicReply, CreateTopicRequest,
-    CreateUserReply, CreateUserRequest, DeleteAclReply, DeleteAclRequest, DeleteBlacklistReply,
-    DeleteBlacklistRequest, DeleteSessionReply, DeleteSessionRequest, DeleteTopicReply,
-    DeleteTopicRequest, DeleteUserReply, DeleteUserRequest, GetShareSubLeaderReply,
+    CreateAclReply, CreateAclRequest, CreateBlacklistReply, CreateBlacklistRequest, CreatePskReply,
+    CreatePskRequest, CreateRuleReply, CreateRuleRequest, CreateSessionReply, CreateSessionRequest,
+    CreateTopicReply, CreateTopicRequest, CreateTopicRewriteRuleReply,
+    CreateTopicRewriteRuleRequest, CreateUserReply, CreateUserRequest, DeleteAclReply,
+    DeleteAclRequest, DeleteBlacklistReply, DeleteBlacklistRequest, DeletePskReply,
+    DeletePskRequest, DeleteRuleReply, DeleteRuleRequest, DeleteSessionReply, DeleteSessionRequest,
+    DeleteTopicReply, DeleteTopicRequest, DeleteTopicRewriteRuleReply,
+    DeleteTopicRewriteRuleRequest, DeleteUserReply, DeleteUserRequest, GetShareSubLeaderReply,
     GetShareSubLeaderRequest, ListAclReply, ListAclRequest, ListBlacklistReply,
-    ListBlacklistRequest, ListSessionReply, ListSessionRequest, ListTopicReply, ListTopicRequest,
-    ListUserReply, ListUserRequest, SaveLastWillMessageReply, SaveLastWillMessageRequest,
-    SetTopicRetainMessageReply, SetTopicRetainMessageRequest, UpdateSessionReply,
-    UpdateSessionRequest,
+    ListBlacklistRequest, ListPskReply, ListPskRequest, ListRuleReply, ListRuleRequest,
+    ListSessionReply, ListSessionRequest, ListTopicReply, ListTopicRequest,
+    ListTopicRewriteRuleReply, ListTopicRewriteRuleRequest, ListUserReply, ListUserRequest,
+    SaveLastWillMessageReply, SaveLastWillMessageRequest, SetTopicRetainMessageReply,
+    SetTopicRetainMessageRequest, UpdateSessionReply, UpdateSessionRequest,
 };
 use tonic::transport::Channel;
 
@@ -55,6 +59,15 @@ pub enum MqttServiceRequest {
     CreateBlacklist(CreateBlacklistRequest),
     DeleteBlacklist(DeleteBlacklistRequest),
     ListBlacklist(ListBlacklistRequest),
+    CreateTopicRewriteRule(CreateTopicRewriteRuleRequest),
+    DeleteTopicRewriteRule(DeleteTopicRewriteRuleRequest),
+    ListTopicRewriteRule(ListTopicRewriteRuleRequest),
+    CreateRule(CreateRuleRequest),
+    DeleteRule(DeleteRuleRequest),
+    ListRule(ListRuleRequest),
+    CreatePsk(CreatePskRequest),
+    DeletePsk(DeletePskRequest),
+    ListPsk(ListPskRequest),
 }
 
 /// Enum wrapper for all possible replies from the mqtt service
@@ -79,6 +92,15 @@ pub enum MqttServiceReply {
     CreateBlacklist(CreateBlacklistReply),
     DeleteBlacklist(DeleteBlacklistReply),
     ListBlacklist(ListBlacklistReply),
+    CreateTopicRewriteRule(CreateTopicRewriteRuleReply),
+    DeleteTopicRewriteRule(DeleteTopicRewriteRuleReply),
+    ListTopicRewriteRule(ListTopicRewriteRuleReply),
+    CreateRule(CreateRuleReply),
+    DeleteRule(DeleteRuleReply),
+    ListRule(ListRuleReply),
+    CreatePsk(CreatePskReply),
+    DeletePsk(DeletePskReply),
+    ListPsk(ListPskReply),
 }
 
 pub(super) async fn call_mqtt_service_once(
@@ -222,6 +244,69 @@ pub(super) async fn call_mqtt_service_once(
             let reply = client.list_blacklist(request).await?;
             Ok(MqttServiceReply::ListBlacklist(reply.into_inner()))
         }
+        CreateTopicRewriteRule(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.create_topic_rewrite_rule(request).await?;
+            Ok(MqttServiceReply::CreateTopicRewriteRule(reply.into_inner()))
+        }
+        DeleteTopicRewriteRule(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.delete_topic_rewrite_rule(request).await?;
+            Ok(MqttServiceReply::DeleteTopicRewriteRule(reply.into_inner()))
+        }
+        ListTopicRewriteRule(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.list_topic_rewrite_rule(request).await?;
+            Ok(MqttServiceReply::ListTopicRewriteRule(reply.into_inner()))
+        }
+        CreateRule(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.create_rule(request).await?;
+            Ok(MqttServiceReply::CreateRule(reply.into_inner()))
+        }
+        DeleteRule(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.delete_rule(request).await?;
+            Ok(MqttServiceReply::DeleteRule(reply.into_inner()))
+        }
+        ListRule(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.list_rule(request).await?;
+            Ok(MqttServiceReply::ListRule(reply.into_inner()))
+        }
+        CreatePsk(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.create_psk(request).await?;
+            Ok(MqttServiceReply::CreatePsk(reply.into_inner()))
+        }
+        DeletePsk(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.delete_psk(request).await?;
+            Ok(MqttServiceReply::DeletePsk(reply.into_inner()))
+        }
+        ListPsk(request) => {
+            let mut client = client_pool
+                .placement_center_mqtt_services_client(addr)
+                .await?;
+            let reply = client.list_psk(request).await?;
+            Ok(MqttServiceReply::ListPsk(reply.into_inner()))
+        }
     }
 }
 
@@ -60,6 +60,7 @@ mod tests {
             namespace: namespace.clone(),
             shard_name: shard_name.clone(),
             replica: 1,
+            ..Default::default()
         };
         let res = create_shard(client_pool.clone(), &addrs, request)
             .await
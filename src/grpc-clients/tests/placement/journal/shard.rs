@@ -74,6 +74,7 @@ mod tests {
             shard_name: shard_name.clone(),
             namespace: namespace.clone(),
             replica: 1,
+            ..Default::default()
         };
         if let Err(e) = create_shard(client_pool.clone(), &addrs, request).await {
             println!("{}", e);
@@ -293,6 +294,7 @@ mod tests {
             shard_name: shard_name.clone(),
             namespace: namespace.clone(),
             replica: 1,
+            ..Default::default()
         };
         if let Err(e) = create_shard(client_pool.clone(), &addrs, request).await {
             println!("{}", e);
@@ -368,6 +370,9 @@ mod tests {
             end_offset: 1000,
             start_timestamp: -1,
             end_timestamp: 1731576014,
+            isr: Vec::new(),
+            size: -1,
+            leader_epoch: 0,
         };
         if let Err(e) = update_segment_meta(client_pool.clone(), &addrs, request).await {
             println!("{}", e);
@@ -434,6 +439,7 @@ mod tests {
             shard_name: shard_name.clone(),
             namespace: namespace.clone(),
             replica: 1,
+            ..Default::default()
         };
         if let Err(e) = create_shard(client_pool.clone(), &addrs, request).await {
             println!("{}", e);
@@ -543,6 +549,7 @@ mod tests {
             shard_name: shard_name.clone(),
             namespace: namespace.clone(),
             replica: 1,
+            ..Default::default()
         };
         if let Err(e) = create_shard(client_pool.clone(), &addrs, request).await {
             println!("{}", e);
@@ -177,6 +177,7 @@ mod tests {
             cluster_name: cluster_name.clone(),
             resources: resources.clone(),
             config: config.clone(),
+            ..Default::default()
         };
         assert!(set_resource_config(client_pool.clone(), &addrs, request)
             .await
@@ -186,6 +187,7 @@ mod tests {
             cluster_name: "".to_string(),
             resources,
             config,
+            ..Default::default()
         };
         assert!(
             set_resource_config(client_pool.clone(), &addrs, request_cluster_name_empty)
@@ -215,6 +217,7 @@ mod tests {
             cluster_name: cluster_name.clone(),
             resources: resources.clone(),
             config: config.clone(),
+            ..Default::default()
         };
         assert!(
             set_resource_config(client_pool.clone(), &addrs, set_request)
@@ -62,6 +62,14 @@ impl MetadataCache {
             .insert(shard_name_iden(&shard.namespace, &shard.shard), shard);
     }
 
+    pub fn get_shard(&self, namespace: &str, shard: &str) -> Option<GetShardMetadataRespShard> {
+        let key = shard_name_iden(namespace, shard);
+        if let Some(shard) = self.shards.get(&key) {
+            return Some(shard.clone());
+        }
+        None
+    }
+
     pub fn get_active_segment(&self, namespace: &str, shard: &str) -> Option<u32> {
         let key = shard_name_iden(namespace, shard);
         if let Some(shard) = self.shards.get(&key) {
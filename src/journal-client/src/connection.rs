@@ -72,6 +72,21 @@ impl NodeConnection {
         self.send("read", req_packet).await
     }
 
+    pub async fn group_send(
+        &self,
+        req_packet: JournalEnginePacket,
+    ) -> Result<JournalEnginePacket, JournalClientError> {
+        self.send("group", req_packet).await
+    }
+
+    pub async fn poll_send(
+        &self,
+        poll_key: &str,
+        req_packet: JournalEnginePacket,
+    ) -> Result<JournalEnginePacket, JournalClientError> {
+        self.send(&format!("poll_{}", poll_key), req_packet).await
+    }
+
     async fn send(
         &self,
         module: &str,
@@ -245,6 +260,37 @@ impl ConnectionManager {
         conn.read_send(req_packet).await
     }
 
+    pub async fn group_send(
+        &self,
+        node_id: u64,
+        req_packet: JournalEnginePacket,
+    ) -> Result<JournalEnginePacket, JournalClientError> {
+        if !self.node_conns.contains_key(&node_id) {
+            let conn = NodeConnection::new(node_id, self.metadata_cache.clone());
+            conn.init_conn().await?;
+            self.node_conns.insert(node_id, conn);
+        }
+
+        let conn = self.node_conns.get(&node_id).unwrap();
+        conn.group_send(req_packet).await
+    }
+
+    pub async fn poll_send(
+        &self,
+        node_id: u64,
+        poll_key: &str,
+        req_packet: JournalEnginePacket,
+    ) -> Result<JournalEnginePacket, JournalClientError> {
+        if !self.node_conns.contains_key(&node_id) {
+            let conn = NodeConnection::new(node_id, self.metadata_cache.clone());
+            conn.init_conn().await?;
+            self.node_conns.insert(node_id, conn);
+        }
+
+        let conn = self.node_conns.get(&node_id).unwrap();
+        conn.poll_send(poll_key, req_packet).await
+    }
+
     fn choose_admin_node(&self) -> u64 {
         let node_ids = self.metadata_cache.all_node_ids();
         let posi = self
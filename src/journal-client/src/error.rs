@@ -19,6 +19,12 @@ pub enum JournalClientError {
     #[error("{0}")]
     IoError(#[from] std::io::Error),
 
+    #[error("{0}")]
+    CommonError(#[from] common_base::error::common::CommonError),
+
+    #[error("{0}")]
+    SerdeJsonError(#[from] serde_json::Error),
+
     #[error("{0}")]
     TokioTimeErrorElapsed(#[from] tokio::time::error::Elapsed),
 
@@ -60,4 +66,10 @@ pub enum JournalClientError {
 
     #[error("Shard {0} has no active segments")]
     NotActiveSegmentLeader(String),
+
+    #[error("Shard {0} has no segment to read from")]
+    NoAvailableSegment(String),
+
+    #[error("The reader is not a member of group {0} at the moment, offsets cannot be committed")]
+    GroupNotJoined(String),
 }
@@ -23,9 +23,10 @@ use error::JournalClientError;
 use log::error;
 use metadata_struct::adapter::read_config::ReadConfig;
 use metadata_struct::adapter::record::Record;
-use metadata_struct::journal::shard::shard_name_iden;
-use option::JournalClientOption;
-use protocol::journal_server::journal_engine::{CreateShardReqBody, DeleteShardReqBody};
+use metadata_struct::journal::shard::{shard_name_iden, JournalShardConfig};
+use option::{JournalClientOption, ReaderOption};
+use protocol::journal_server::journal_engine::{AckMode, CreateShardReqBody, DeleteShardReqBody};
+use reader::{read_shard_by_offset, Reader};
 use service::{create_shard, delete_shard};
 use tokio::sync::broadcast::{self, Sender};
 use tokio::time::sleep;
@@ -35,7 +36,7 @@ mod cache;
 mod connection;
 mod error;
 pub mod option;
-mod reader;
+pub mod reader;
 mod service;
 pub mod tool;
 mod writer;
@@ -45,6 +46,9 @@ pub struct JournalEngineClient {
     connection_manager: Arc<ConnectionManager>,
     metadata_cache: Arc<MetadataCache>,
     writer: Arc<Writer>,
+    ack_mode: AckMode,
+    enable_idempotence: bool,
+    retries: u32,
     stop_send: Sender<bool>,
 }
 
@@ -55,12 +59,18 @@ impl JournalEngineClient {
         let sender = Arc::new(Writer::new(
             connection_manager.clone(),
             metadata_cache.clone(),
+            options.ack_timeout_ms,
+            options.compression,
+            options.enable_idempotence,
         ));
         let (stop_send, _) = broadcast::channel::<bool>(2);
         JournalEngineClient {
             metadata_cache,
             connection_manager,
             writer: sender,
+            ack_mode: options.ack_mode,
+            enable_idempotence: options.enable_idempotence,
+            retries: options.retries,
             stop_send,
         }
     }
@@ -87,11 +97,28 @@ impl JournalEngineClient {
         namespace: &str,
         shard_name: &str,
         replica_num: u32,
+    ) -> Result<(), JournalClientError> {
+        self.create_shard_with_config(
+            namespace,
+            shard_name,
+            replica_num,
+            JournalShardConfig::default(),
+        )
+        .await
+    }
+
+    pub async fn create_shard_with_config(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        replica_num: u32,
+        shard_config: JournalShardConfig,
     ) -> Result<(), JournalClientError> {
         let body = CreateShardReqBody {
             namespace: namespace.to_string(),
             shard_name: shard_name.to_string(),
             replica_num,
+            shard_config: serde_json::to_vec(&shard_config)?,
         };
         let _ = create_shard(&self.connection_manager, body).await?;
         Ok(())
@@ -118,6 +145,39 @@ impl JournalEngineClient {
         content: Vec<u8>,
         tags: Vec<String>,
     ) -> Result<SenderMessageResp, JournalClientError> {
+        self.write_with_ack(namespace, shard_name, key, content, tags, self.ack_mode)
+            .await
+    }
+
+    /// Writes a record with the given ack mode. With `AckMode::None` the call returns as soon as
+    /// the record is queued, without an offset.
+    ///
+    /// An idempotent client sends a failed write again up to `retries` times. The record keeps
+    /// its sequence number, so it is written once even if an earlier attempt reached the leader.
+    /// Its writes to a shard go out one at a time, so a failed record never leaves a gap in
+    /// the sequence of the records sent after it.
+    pub async fn write_with_ack(
+        &self,
+        namespace: String,
+        shard_name: String,
+        key: String,
+        content: Vec<u8>,
+        tags: Vec<String>,
+        ack_mode: AckMode,
+    ) -> Result<SenderMessageResp, JournalClientError> {
+        // the producer stays locked until the record is answered
+        let shard_producer = self.writer.shard_producer(&namespace, &shard_name);
+        let mut producer = match &shard_producer {
+            Some(shard_producer) => Some(shard_producer.lock().await),
+            None => None,
+        };
+        let (mut producer_id, mut pkid) = if let Some(producer) = producer.as_mut() {
+            producer.next()
+        } else {
+            ("".to_string(), self.writer.next_pkid())
+        };
+
+        let mut attempts = 0;
         loop {
             let active_segment = if let Some(segment) = self
                 .metadata_cache
@@ -154,12 +214,36 @@ impl JournalEngineClient {
                 &namespace,
                 &shard_name,
                 active_segment,
+                &producer_id,
+                pkid,
                 &key,
                 &content,
                 &tags,
+                ack_mode,
             );
             match self.writer.send(&message).await {
                 Ok(resp) => {
+                    if let Some(e) = &resp.error {
+                        // the leader lost the sequence of the producer, the record was not
+                        // written and is sent again by a new producer
+                        let out_of_order =
+                            resp.error_code.as_deref() == Some("OutOfOrderProducerSequence");
+                        if let Some(producer) = producer.as_mut() {
+                            if out_of_order {
+                                producer.reset();
+                                (producer_id, pkid) = producer.next();
+                            }
+                        }
+                        if self.can_retry(attempts) {
+                            attempts += 1;
+                            error!("Message failed to write. Reason :{} Next attempt.", e);
+                            sleep(Duration::from_millis(100)).await;
+                            continue;
+                        }
+                        if let Some(producer) = producer.as_mut() {
+                            producer.reset();
+                        }
+                    }
                     return Ok(resp);
                 }
                 Err(e) => {
@@ -168,12 +252,38 @@ impl JournalEngineClient {
                         sleep(Duration::from_millis(100)).await;
                         continue;
                     }
+                    if self.can_retry(attempts) {
+                        attempts += 1;
+                        error!("Message failed to send. Reason :{} Next attempt.", e);
+                        sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                    // the record may have been written, later records start a new sequence
+                    if let Some(producer) = producer.as_mut() {
+                        producer.reset();
+                    }
                     return Err(e);
                 }
             }
         }
     }
 
+    fn can_retry(&self, attempts: u32) -> bool {
+        self.enable_idempotence && attempts < self.retries
+    }
+
+    /// Subscribes to the shards of the option, or to the shards of its namespace assigned by
+    /// the consumer group. Reading starts at the offsets committed by the group, or at the
+    /// seek position for shards without one.
+    pub async fn subscribe(&self, option: ReaderOption) -> Result<Reader, JournalClientError> {
+        Reader::start(
+            self.connection_manager.clone(),
+            self.metadata_cache.clone(),
+            option,
+        )
+        .await
+    }
+
     pub async fn read_by_offset(
         &self,
         namespace: &str,
@@ -181,7 +291,27 @@ impl JournalEngineClient {
         offset: u64,
         read_config: &ReadConfig,
     ) -> Result<Vec<Record>, JournalClientError> {
-        Ok(Vec::new())
+        let records = read_shard_by_offset(
+            &self.connection_manager,
+            &self.metadata_cache,
+            namespace,
+            shard_name,
+            offset,
+            read_config,
+        )
+        .await?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| Record {
+                offset: Some(record.offset),
+                header: Vec::new(),
+                key: record.key,
+                data: record.value,
+                tags: record.tags,
+                timestamp: record.timestamp as u128 * 1000,
+            })
+            .collect())
     }
 
     pub async fn read_by_key(
@@ -13,17 +13,34 @@
 // limitations under the License.
 
 use common_base::error::common::CommonError;
+use protocol::journal_server::journal_engine::{AckMode, AssignmentStrategy, CompressionType};
 
 #[derive(Default, Clone)]
 pub struct JournalClientOption {
     pub addrs: Vec<String>,
     pub line_ms: u64,
+    // default ack mode of JournalEngineClient::write
+    pub ack_mode: AckMode,
+    // how long the server waits for the ack condition before returning a timeout error
+    pub ack_timeout_ms: u64,
+    // codec of the record batches sent to the server and stored in the segment files
+    pub compression: CompressionType,
+    // writes carry a producer id and sequence number, so the segment leader skips a
+    // record that a retry sends again
+    pub enable_idempotence: bool,
+    // how often a failed write is sent again, only with enable_idempotence
+    pub retries: u32,
 }
 
 impl JournalClientOption {
     pub fn build() -> Self {
         JournalClientOption {
             line_ms: 10,
+            ack_mode: AckMode::All,
+            ack_timeout_ms: 30000,
+            compression: CompressionType::None,
+            enable_idempotence: false,
+            retries: 3,
             ..Default::default()
         }
     }
@@ -31,6 +48,26 @@ impl JournalClientOption {
     pub fn set_addrs(&mut self, addrs: Vec<String>) {
         self.addrs = addrs;
     }
+
+    pub fn set_ack_mode(&mut self, ack_mode: AckMode) {
+        self.ack_mode = ack_mode;
+    }
+
+    pub fn set_ack_timeout_ms(&mut self, ack_timeout_ms: u64) {
+        self.ack_timeout_ms = ack_timeout_ms;
+    }
+
+    pub fn set_compression(&mut self, compression: CompressionType) {
+        self.compression = compression;
+    }
+
+    pub fn set_enable_idempotence(&mut self, enable_idempotence: bool) {
+        self.enable_idempotence = enable_idempotence;
+    }
+
+    pub fn set_retries(&mut self, retries: u32) {
+        self.retries = retries;
+    }
 }
 
 pub fn options_validator(option: &JournalClientOption) -> Result<(), CommonError> {
@@ -41,3 +78,128 @@ pub fn options_validator(option: &JournalClientOption) -> Result<(), CommonError
     }
     Ok(())
 }
+
+/// Where a reader starts on a shard the group has no committed offset for.
+#[derive(Default, Clone, Debug, PartialEq)]
+pub enum SeekPosition {
+    Earliest,
+    #[default]
+    Latest,
+    Offset(u64),
+    // the first record written at or after the timestamp, in seconds
+    Timestamp(u64),
+}
+
+#[derive(Default, Clone)]
+pub struct ReaderOption {
+    pub group_name: String,
+    // (namespace, shard_name) of every subscribed shard
+    pub shards: Vec<(String, String)>,
+    // instead of fixed shards, consume all shards of the namespace, split between the
+    // readers of the group by its coordinator
+    pub namespace: String,
+    pub assignment_strategy: AssignmentStrategy,
+    // the reader leaves the group when the coordinator gets no heartbeat within this time
+    pub session_timeout_ms: u64,
+    pub heartbeat_interval_ms: u64,
+    pub seek: SeekPosition,
+    // commit the consumed offsets periodically and when the reader is closed
+    pub auto_commit: bool,
+    pub auto_commit_interval_ms: u64,
+    // max number of prefetched records kept in the local buffer
+    pub buffer_size: usize,
+    pub fetch_max_size: u64,
+    pub fetch_max_record: u64,
+    // how long the server holds a read at the end of a shard until new records arrive,
+    // 0 reads again after poll_interval_ms instead
+    pub max_wait_ms: u64,
+    // how long a shard waits before reading again after a failed or empty read
+    pub poll_interval_ms: u64,
+}
+
+impl ReaderOption {
+    pub fn build(group_name: &str) -> Self {
+        ReaderOption {
+            group_name: group_name.to_string(),
+            auto_commit: true,
+            auto_commit_interval_ms: 5000,
+            session_timeout_ms: 10000,
+            heartbeat_interval_ms: 3000,
+            buffer_size: 1000,
+            fetch_max_size: 1024 * 1024,
+            fetch_max_record: 100,
+            max_wait_ms: 1000,
+            poll_interval_ms: 100,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_shard(&mut self, namespace: &str, shard_name: &str) {
+        self.shards
+            .push((namespace.to_string(), shard_name.to_string()));
+    }
+
+    pub fn set_namespace(&mut self, namespace: &str) {
+        self.namespace = namespace.to_string();
+    }
+
+    pub fn set_assignment_strategy(&mut self, assignment_strategy: AssignmentStrategy) {
+        self.assignment_strategy = assignment_strategy;
+    }
+
+    pub fn set_session_timeout_ms(&mut self, session_timeout_ms: u64) {
+        self.session_timeout_ms = session_timeout_ms;
+    }
+
+    pub fn set_heartbeat_interval_ms(&mut self, heartbeat_interval_ms: u64) {
+        self.heartbeat_interval_ms = heartbeat_interval_ms;
+    }
+
+    pub fn set_seek(&mut self, seek: SeekPosition) {
+        self.seek = seek;
+    }
+
+    pub fn set_auto_commit(&mut self, auto_commit: bool) {
+        self.auto_commit = auto_commit;
+    }
+
+    pub fn set_auto_commit_interval_ms(&mut self, auto_commit_interval_ms: u64) {
+        self.auto_commit_interval_ms = auto_commit_interval_ms;
+    }
+
+    pub fn set_buffer_size(&mut self, buffer_size: usize) {
+        self.buffer_size = buffer_size;
+    }
+
+    pub fn set_max_wait_ms(&mut self, max_wait_ms: u64) {
+        self.max_wait_ms = max_wait_ms;
+    }
+
+    pub fn set_poll_interval_ms(&mut self, poll_interval_ms: u64) {
+        self.poll_interval_ms = poll_interval_ms;
+    }
+}
+
+pub fn reader_options_validator(option: &ReaderOption) -> Result<(), CommonError> {
+    if option.group_name.is_empty() {
+        return Err(CommonError::ParameterCannotBeNull(
+            "option.group_name".to_string(),
+        ));
+    }
+    if option.shards.is_empty() == option.namespace.is_empty() {
+        return Err(CommonError::CommonError(
+            "Exactly one of option.shards and option.namespace must be set".to_string(),
+        ));
+    }
+    if !option.namespace.is_empty() && option.heartbeat_interval_ms >= option.session_timeout_ms {
+        return Err(CommonError::CommonError(
+            "option.heartbeat_interval_ms must be less than option.session_timeout_ms".to_string(),
+        ));
+    }
+    if option.buffer_size == 0 {
+        return Err(CommonError::ParameterCannotBeNull(
+            "option.buffer_size".to_string(),
+        ));
+    }
+    Ok(())
+}
@@ -11,20 +11,1050 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
 
 use dashmap::DashMap;
+use futures::Stream;
+use log::{error, info};
+use metadata_struct::adapter::read_config::ReadConfig;
+use metadata_struct::journal::group::group_coordinator_node;
+use metadata_struct::journal::shard::shard_name_iden;
+use protocol::journal_server::journal_engine::{
+    AutoOffsetStrategy, ClientSegmentMetadata, CommitOffsetReqBody, CommitOffsetShard,
+    FetchOffsetReqBody, FetchOffsetShard, GetShardMetadataRespShard, HeartbeatReqBody,
+    JoinGroupReqBody, LeaveGroupReqBody, ReadReqBody, ReadReqFilter, ReadReqMessage,
+    ReadReqOptions, ReadType,
+};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
 
+use crate::cache::{load_node_cache, load_shards_cache, MetadataCache};
+use crate::connection::ConnectionManager;
+use crate::error::JournalClientError;
+use crate::option::{reader_options_validator, ReaderOption, SeekPosition};
+use crate::service::{
+    batch_read, commit_offset, fetch_committed_offset, fetch_offset, heartbeat, join_group,
+    leave_group,
+};
+
+#[derive(Default, Clone, Debug, PartialEq)]
+pub struct ReaderRecord {
+    pub namespace: String,
+    pub shard_name: String,
+    pub segment: u32,
+    pub offset: u64,
+    pub key: String,
+    pub value: Vec<u8>,
+    pub tags: Vec<String>,
+    // create time of the record, in seconds
+    pub timestamp: u64,
+}
+
+/// A consumer of one or more shards, created by `JournalEngineClient::subscribe`.
+/// Every shard is read by its own task that prefetches records into a bounded buffer,
+/// the records of all shards are returned by the `Stream` implementation.
+///
+/// With `ReaderOption::namespace` set the reader joins the consumer group on the group's
+/// coordinator node and reads the shards of the namespace assigned to it. On a rebalance
+/// the reader commits the consumed offsets of the shards it gives up, and the coordinator
+/// hands those shards to their new owner only after that. Offsets are only accepted from
+/// the current generation of the group.
 pub struct Reader {
-    group_local_cache_channel: DashMap<String, String>,
+    group_name: String,
+    // None unless the reader is a member of a consumer group
+    membership: Option<Arc<RwLock<GroupMembership>>>,
+    receiver: mpsc::Receiver<ReaderRecord>,
+    // next offset to read of every shard, updated when a record is handed out
+    consumed_offsets: Arc<DashMap<String, CommitOffsetShard>>,
+    fetchers: Arc<ShardFetchers>,
+    connection_manager: Arc<ConnectionManager>,
+    auto_commit: bool,
+    stop_send: Sender<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct ShardCursor {
+    namespace: String,
+    shard_name: String,
+    segment: u32,
+    offset: u64,
+    // set until the first record is read when seeking by timestamp
+    timestamp: Option<u64>,
 }
 
 impl Reader {
-    pub fn new() -> Self {
-        let group_local_cache_channel = DashMap::with_capacity(2);
-        Reader {
-            group_local_cache_channel,
+    pub(crate) async fn start(
+        connection_manager: Arc<ConnectionManager>,
+        metadata_cache: Arc<MetadataCache>,
+        option: ReaderOption,
+    ) -> Result<Self, JournalClientError> {
+        reader_options_validator(&option)?;
+
+        let (stop_send, _) = broadcast::channel::<bool>(2);
+        let (sender, receiver) = mpsc::channel::<ReaderRecord>(option.buffer_size);
+        let fetchers = Arc::new(ShardFetchers {
+            connection_manager: connection_manager.clone(),
+            metadata_cache: metadata_cache.clone(),
+            option: option.clone(),
+            sender,
+            threads: DashMap::with_capacity(2),
+        });
+        let consumed_offsets = Arc::new(DashMap::with_capacity(2));
+        let membership = if option.namespace.is_empty() {
+            None
+        } else {
+            Some(Arc::new(RwLock::new(GroupMembership::default())))
+        };
+
+        if let Some(membership) = membership.clone() {
+            start_group_member_thread(
+                fetchers.clone(),
+                consumed_offsets.clone(),
+                membership,
+                stop_send.subscribe(),
+            );
+        } else {
+            for cursor in resolve_cursors(
+                &connection_manager,
+                &metadata_cache,
+                &option,
+                &option.shards,
+            )
+            .await?
+            {
+                fetchers.start(cursor);
+            }
+        }
+
+        if option.auto_commit {
+            start_auto_commit_thread(
+                connection_manager.clone(),
+                option.group_name.clone(),
+                membership.clone(),
+                consumed_offsets.clone(),
+                option.auto_commit_interval_ms,
+                stop_send.subscribe(),
+            );
+        }
+
+        Ok(Reader {
+            group_name: option.group_name,
+            membership,
+            receiver,
+            consumed_offsets,
+            fetchers,
+            connection_manager,
+            auto_commit: option.auto_commit,
+            stop_send,
+        })
+    }
+
+    /// Commits the offsets of the records returned so far, reading resumes after them.
+    pub async fn commit(&self) -> Result<(), JournalClientError> {
+        commit_consumed_offsets(
+            &self.connection_manager,
+            &self.group_name,
+            &self.membership,
+            &self.consumed_offsets,
+        )
+        .await
+    }
+
+    /// The shards the reader currently reads, as `namespace_shard_name`.
+    pub fn assigned_shards(&self) -> Vec<String> {
+        let mut shards = self.fetchers.shard_keys();
+        shards.sort();
+        shards
+    }
+
+    pub async fn close(&self) -> Result<(), JournalClientError> {
+        self.fetchers.stop_all();
+        let result = if self.auto_commit {
+            self.commit().await
+        } else {
+            Ok(())
+        };
+        // leaves the group once the offsets are committed, the threads may already be gone
+        let _ = self.stop_send.send(true);
+        result
+    }
+}
+
+impl Drop for Reader {
+    fn drop(&mut self) {
+        self.fetchers.stop_all();
+        let _ = self.stop_send.send(true);
+    }
+}
+
+impl Stream for Reader {
+    type Item = ReaderRecord;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            let poll = self.receiver.poll_recv(cx);
+            if let Poll::Ready(Some(record)) = &poll {
+                let shard_key = shard_name_iden(&record.namespace, &record.shard_name);
+                // prefetched records of a shard that was assigned to another reader
+                if !self.fetchers.contains(&shard_key) {
+                    continue;
+                }
+                self.consumed_offsets.insert(
+                    shard_key,
+                    CommitOffsetShard {
+                        namespace: record.namespace.clone(),
+                        shard_name: record.shard_name.clone(),
+                        offset: record.offset + 1,
+                    },
+                );
+            }
+            return poll;
+        }
+    }
+}
+
+// The fetch threads of a reader, one per shard, each with its own stop signal.
+struct ShardFetchers {
+    connection_manager: Arc<ConnectionManager>,
+    metadata_cache: Arc<MetadataCache>,
+    option: ReaderOption,
+    sender: mpsc::Sender<ReaderRecord>,
+    // namespace_shard_name, stop sender of the fetch thread
+    threads: DashMap<String, Sender<bool>>,
+}
+
+impl ShardFetchers {
+    fn start(&self, cursor: ShardCursor) {
+        let (stop_send, stop_recv) = broadcast::channel::<bool>(2);
+        self.threads.insert(
+            shard_name_iden(&cursor.namespace, &cursor.shard_name),
+            stop_send,
+        );
+        start_shard_fetch_thread(
+            self.connection_manager.clone(),
+            self.metadata_cache.clone(),
+            self.option.clone(),
+            cursor,
+            self.sender.clone(),
+            stop_recv,
+        );
+    }
+
+    fn stop(&self, shard_key: &str) {
+        if let Some((_, stop_send)) = self.threads.remove(shard_key) {
+            let _ = stop_send.send(true);
+        }
+    }
+
+    fn stop_all(&self) {
+        for shard_key in self.shard_keys() {
+            self.stop(&shard_key);
         }
     }
 
-    pub fn read() {}
+    fn contains(&self, shard_key: &str) -> bool {
+        self.threads.contains_key(shard_key)
+    }
+
+    fn shard_keys(&self) -> Vec<String> {
+        self.threads.iter().map(|raw| raw.key().clone()).collect()
+    }
+}
+
+// Where every shard starts: at the offset committed by the group, or at the seek position
+// for shards the group has not committed yet.
+async fn resolve_cursors(
+    connection_manager: &Arc<ConnectionManager>,
+    metadata_cache: &Arc<MetadataCache>,
+    option: &ReaderOption,
+    shards: &[(String, String)],
+) -> Result<Vec<ShardCursor>, JournalClientError> {
+    if shards.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let committed = fetch_committed_offset(
+        connection_manager,
+        FetchOffsetReqBody {
+            group_name: option.group_name.clone(),
+            shards: shards
+                .iter()
+                .map(|(namespace, shard_name)| FetchOffsetShard {
+                    namespace: namespace.clone(),
+                    shard_name: shard_name.clone(),
+                    ..Default::default()
+                })
+                .collect(),
+            committed: true,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let mut cursors = Vec::new();
+    for (namespace, shard_name) in shards.iter() {
+        load_shards_cache(metadata_cache, connection_manager, namespace, shard_name).await?;
+        let shard = if let Some(shard) = metadata_cache.get_shard(namespace, shard_name) {
+            shard
+        } else {
+            return Err(JournalClientError::NoAvailableSegment(shard_name_iden(
+                namespace, shard_name,
+            )));
+        };
+
+        let seek = if let Some(offset) = committed
+            .shard_offsets
+            .iter()
+            .find(|offset| offset.namespace == *namespace && offset.shard_name == *shard_name)
+        {
+            SeekPosition::Offset(offset.offset)
+        } else {
+            option.seek.clone()
+        };
+
+        cursors.push(seek_cursor(connection_manager, &option.group_name, &shard, &seek).await?);
+    }
+    Ok(cursors)
+}
+
+#[derive(Default, Clone)]
+struct GroupMembership {
+    coordinator: u64,
+    member_id: String,
+    generation_id: u64,
+    joined: bool,
+}
+
+// Keeps the reader in its consumer group: joins, sends heartbeats, and moves the fetch
+// threads to the new assignment whenever the generation changes.
+fn start_group_member_thread(
+    fetchers: Arc<ShardFetchers>,
+    consumed_offsets: Arc<DashMap<String, CommitOffsetShard>>,
+    membership: Arc<RwLock<GroupMembership>>,
+    mut stop_recv: Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let option = fetchers.option.clone();
+        let mut wait_ms = 0;
+        loop {
+            tokio::select! {
+                val = stop_recv.recv() => {
+                    // a closed channel means the reader is gone
+                    if let Ok(false) = val {
+                        continue;
+                    }
+                    let current = membership.read().unwrap().clone();
+                    if current.joined {
+                        let body = LeaveGroupReqBody {
+                            group_name: option.group_name.clone(),
+                            member_id: current.member_id.clone(),
+                        };
+                        if let Err(e) = leave_group(&fetchers.connection_manager, current.coordinator, body).await {
+                            error!("Reader failed to leave group {} with error message :{}", option.group_name, e);
+                        }
+                    }
+                    break;
+                }
+                _ = sleep(Duration::from_millis(wait_ms)) => {
+                    wait_ms = option.heartbeat_interval_ms;
+                    let current = membership.read().unwrap().clone();
+                    if current.joined {
+                        match group_heartbeat(&fetchers, &current).await {
+                            Ok(generation_id) => {
+                                if generation_id == current.generation_id {
+                                    continue;
+                                }
+                            }
+                            Err(e) => {
+                                error!("Heartbeat of group {} failed with error message :{}", option.group_name, e);
+                                membership.write().unwrap().joined = false;
+                            }
+                        }
+                    }
+
+                    if let Err(e) = join_group_and_assign(&fetchers, &consumed_offsets, &membership).await {
+                        error!("Reader failed to join group {} with error message :{}", option.group_name, e);
+                        membership.write().unwrap().joined = false;
+                        wait_ms = option.poll_interval_ms;
+                    }
+                }
+            }
+        }
+    });
+}
+
+async fn group_heartbeat(
+    fetchers: &Arc<ShardFetchers>,
+    membership: &GroupMembership,
+) -> Result<u64, JournalClientError> {
+    let body = HeartbeatReqBody {
+        group_name: fetchers.option.group_name.clone(),
+        member_id: membership.member_id.clone(),
+        generation_id: membership.generation_id,
+    };
+    let resp = heartbeat(&fetchers.connection_manager, membership.coordinator, body).await?;
+    Ok(resp.generation_id)
+}
+
+// Joins the current generation of the group. The shards that moved to other members are
+// stopped and their offsets committed before the next heartbeat tells the coordinator
+// that they can be handed over.
+async fn join_group_and_assign(
+    fetchers: &Arc<ShardFetchers>,
+    consumed_offsets: &Arc<DashMap<String, CommitOffsetShard>>,
+    membership: &Arc<RwLock<GroupMembership>>,
+) -> Result<(), JournalClientError> {
+    let option = &fetchers.option;
+    // the node list may have changed since the last join
+    load_node_cache(&fetchers.metadata_cache, &fetchers.connection_manager).await?;
+    let node_ids = fetchers.metadata_cache.all_node_ids();
+    let coordinator = if let Some(node_id) = group_coordinator_node(&option.group_name, &node_ids) {
+        node_id
+    } else {
+        return Err(JournalClientError::NoAvailableConn(0));
+    };
+
+    let body = JoinGroupReqBody {
+        group_name: option.group_name.clone(),
+        namespace: option.namespace.clone(),
+        member_id: membership.read().unwrap().member_id.clone(),
+        session_timeout_ms: option.session_timeout_ms,
+        strategy: option.assignment_strategy.into(),
+    };
+    let resp = join_group(&fetchers.connection_manager, coordinator, body).await?;
+    let current = GroupMembership {
+        coordinator,
+        member_id: resp.member_id,
+        generation_id: resp.generation_id,
+        joined: true,
+    };
+    *membership.write().unwrap() = current.clone();
+
+    let assigned: Vec<String> = resp
+        .shards
+        .iter()
+        .map(|shard_name| shard_name_iden(&option.namespace, shard_name))
+        .collect();
+
+    // commit what was consumed of the revoked shards, so their new owner continues after it
+    let revoked: Vec<String> = fetchers
+        .shard_keys()
+        .into_iter()
+        .filter(|shard_key| !assigned.contains(shard_key))
+        .collect();
+    if !revoked.is_empty() {
+        for shard_key in revoked.iter() {
+            fetchers.stop(shard_key);
+        }
+        commit_consumed_offsets(
+            &fetchers.connection_manager,
+            &option.group_name,
+            &Some(membership.clone()),
+            consumed_offsets,
+        )
+        .await?;
+        for shard_key in revoked.iter() {
+            consumed_offsets.remove(shard_key);
+        }
+    }
+
+    let added: Vec<(String, String)> = resp
+        .shards
+        .into_iter()
+        .filter(|shard_name| !fetchers.contains(&shard_name_iden(&option.namespace, shard_name)))
+        .map(|shard_name| (option.namespace.clone(), shard_name))
+        .collect();
+    for cursor in resolve_cursors(
+        &fetchers.connection_manager,
+        &fetchers.metadata_cache,
+        option,
+        &added,
+    )
+    .await?
+    {
+        fetchers.start(cursor);
+    }
+
+    info!(
+        "Reader {} of group {} joined generation {}, shards: {:?}",
+        current.member_id,
+        option.group_name,
+        current.generation_id,
+        fetchers.shard_keys()
+    );
+    Ok(())
+}
+
+async fn commit_consumed_offsets(
+    connection_manager: &Arc<ConnectionManager>,
+    group_name: &str,
+    membership: &Option<Arc<RwLock<GroupMembership>>>,
+    consumed_offsets: &DashMap<String, CommitOffsetShard>,
+) -> Result<(), JournalClientError> {
+    let offsets: Vec<CommitOffsetShard> = consumed_offsets
+        .iter()
+        .map(|raw| raw.value().clone())
+        .collect();
+    if offsets.is_empty() {
+        return Ok(());
+    }
+
+    let mut body = CommitOffsetReqBody {
+        group_name: group_name.to_string(),
+        offsets,
+        ..Default::default()
+    };
+    let coordinator = if let Some(membership) = membership {
+        let current = membership.read().unwrap().clone();
+        if !current.joined {
+            return Err(JournalClientError::GroupNotJoined(group_name.to_string()));
+        }
+        body.member_id = current.member_id;
+        body.generation_id = current.generation_id;
+        Some(current.coordinator)
+    } else {
+        None
+    };
+
+    commit_offset(connection_manager, coordinator, body).await?;
+    Ok(())
+}
+
+fn start_auto_commit_thread(
+    connection_manager: Arc<ConnectionManager>,
+    group_name: String,
+    membership: Option<Arc<RwLock<GroupMembership>>>,
+    consumed_offsets: Arc<DashMap<String, CommitOffsetShard>>,
+    interval_ms: u64,
+    mut stop_recv: Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let mut last_committed = Vec::new();
+        loop {
+            tokio::select! {
+                val = stop_recv.recv() => {
+                    // a closed channel means the reader is gone
+                    match val {
+                        Ok(false) => {}
+                        Ok(true) | Err(_) => break,
+                    }
+                }
+                _ = sleep(Duration::from_millis(interval_ms)) => {
+                    let mut offsets: Vec<(String, u64)> = consumed_offsets
+                        .iter()
+                        .map(|raw| (raw.key().clone(), raw.value().offset))
+                        .collect();
+                    offsets.sort();
+                    if offsets == last_committed {
+                        continue;
+                    }
+
+                    match commit_consumed_offsets(&connection_manager, &group_name, &membership, &consumed_offsets).await {
+                        Ok(()) => {
+                            last_committed = offsets;
+                        }
+                        Err(e) => {
+                            error!("Group {} failed to commit offsets with error message :{}", group_name, e);
+                        }
+                    }
+                }
+            }
+        }
+    });
+}
+
+async fn seek_cursor(
+    connection_manager: &Arc<ConnectionManager>,
+    group_name: &str,
+    shard: &GetShardMetadataRespShard,
+    seek: &SeekPosition,
+) -> Result<ShardCursor, JournalClientError> {
+    let mut cursor = ShardCursor {
+        namespace: shard.namespace.clone(),
+        shard_name: shard.shard.clone(),
+        segment: shard.active_segment.max(0) as u32,
+        offset: 0,
+        timestamp: None,
+    };
+    let no_segment =
+        || JournalClientError::NoAvailableSegment(shard_name_iden(&shard.namespace, &shard.shard));
+
+    match seek {
+        SeekPosition::Earliest => {
+            let segment = shard
+                .segments
+                .iter()
+                .min_by_key(|segment| segment.segment_no)
+                .ok_or_else(no_segment)?;
+            cursor.segment = segment.segment_no;
+            cursor.offset = segment.start_offset.max(0) as u64;
+        }
+
+        SeekPosition::Latest => {
+            let body = FetchOffsetReqBody {
+                group_name: group_name.to_string(),
+                shards: vec![FetchOffsetShard {
+                    namespace: shard.namespace.clone(),
+                    shard_name: shard.shard.clone(),
+                    segment_no: cursor.segment,
+                    timestamp: 0,
+                }],
+                auto_offset_strategy: AutoOffsetStrategy::Latest.into(),
+                committed: false,
+            };
+            let resp =
+                fetch_offset(connection_manager, shard.active_segment_leader as u64, body).await?;
+            let offset = resp.shard_offsets.first().ok_or_else(no_segment)?;
+            cursor.offset = offset.offset;
+        }
+
+        SeekPosition::Offset(offset) => {
+            cursor.segment =
+                locate_segment_by_offset(&shard.segments, *offset).ok_or_else(no_segment)?;
+            cursor.offset = *offset;
+        }
+
+        SeekPosition::Timestamp(timestamp) => {
+            cursor.segment =
+                locate_segment_by_timestamp(&shard.segments, *timestamp).ok_or_else(no_segment)?;
+            cursor.timestamp = Some(*timestamp);
+        }
+    }
+    Ok(cursor)
+}
+
+fn start_shard_fetch_thread(
+    connection_manager: Arc<ConnectionManager>,
+    metadata_cache: Arc<MetadataCache>,
+    option: ReaderOption,
+    mut cursor: ShardCursor,
+    sender: mpsc::Sender<ReaderRecord>,
+    mut stop_recv: Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let poll_interval = Duration::from_millis(option.poll_interval_ms);
+        loop {
+            if let Ok(true) = stop_recv.try_recv() {
+                break;
+            }
+            if sender.is_closed() {
+                break;
+            }
+
+            let shard = if let Some(shard) =
+                metadata_cache.get_shard(&cursor.namespace, &cursor.shard_name)
+            {
+                shard
+            } else {
+                if let Err(e) = load_shards_cache(
+                    &metadata_cache,
+                    &connection_manager,
+                    &cursor.namespace,
+                    &cursor.shard_name,
+                )
+                .await
+                {
+                    error!(
+                        "Loading Shard {} Metadata info failed, error message :{}",
+                        shard_name_iden(&cursor.namespace, &cursor.shard_name),
+                        e
+                    );
+                }
+                sleep(poll_interval).await;
+                continue;
+            };
+
+            let leader = if let Some(segment) = shard
+                .segments
+                .iter()
+                .find(|segment| segment.segment_no == cursor.segment)
+            {
+                segment.leader
+            } else {
+                // the segment was deleted by retention, continue with the oldest one left
+                if !skip_deleted_segment(&mut cursor, &shard.segments) {
+                    sleep(poll_interval).await;
+                }
+                continue;
+            };
+
+            // Only the active segment receives new records, wait for them on the server
+            let is_active = cursor.segment >= shard.active_segment.max(0) as u32;
+            let max_wait_ms = if is_active { option.max_wait_ms } else { 0 };
+            let body = build_read_body(&cursor, &option, max_wait_ms);
+            let messages = match batch_read(&connection_manager, leader, body).await {
+                Ok(resp) => resp
+                    .messages
+                    .into_iter()
+                    .filter(|raw| raw.segment == cursor.segment)
+                    .flat_map(|raw| raw.messages)
+                    .collect::<Vec<_>>(),
+                Err(e) => {
+                    error!(
+                        "Failed to read Segment {} of Shard {} with error message :{}",
+                        cursor.segment,
+                        shard_name_iden(&cursor.namespace, &cursor.shard_name),
+                        e
+                    );
+                    let _ = load_shards_cache(
+                        &metadata_cache,
+                        &connection_manager,
+                        &cursor.namespace,
+                        &cursor.shard_name,
+                    )
+                    .await;
+                    sleep(poll_interval).await;
+                    continue;
+                }
+            };
+
+            if messages.is_empty() {
+                // A segment that is no longer active will not receive new data, but an empty
+                // read is only the end of it once the cursor is past its last offset
+                if !is_active && is_segment_finished(&cursor, &shard.segments) {
+                    next_segment(&mut cursor, &shard.segments);
+                    continue;
+                }
+                if !is_active {
+                    let _ = load_shards_cache(
+                        &metadata_cache,
+                        &connection_manager,
+                        &cursor.namespace,
+                        &cursor.shard_name,
+                    )
+                    .await;
+                }
+                if max_wait_ms == 0 {
+                    sleep(poll_interval).await;
+                }
+                continue;
+            }
+
+            for message in messages {
+                if cursor.timestamp.is_none() && message.offset < cursor.offset {
+                    continue;
+                }
+                cursor.offset = message.offset + 1;
+                let record = ReaderRecord {
+                    namespace: cursor.namespace.clone(),
+                    shard_name: cursor.shard_name.clone(),
+                    segment: cursor.segment,
+                    offset: message.offset,
+                    key: message.key,
+                    value: message.value,
+                    tags: message.tags,
+                    timestamp: message.timestamp,
+                };
+                if sender.send(record).await.is_err() {
+                    return;
+                }
+            }
+            cursor.timestamp = None;
+        }
+    });
+}
+
+/// Reads the records of a shard from `offset` on, continuing in the next segment when the
+/// segment holding the offset has no records left. Waits up to `max_wait_ms` of the config
+/// for new records once the active segment is reached.
+pub(crate) async fn read_shard_by_offset(
+    connection_manager: &Arc<ConnectionManager>,
+    metadata_cache: &Arc<MetadataCache>,
+    namespace: &str,
+    shard_name: &str,
+    offset: u64,
+    read_config: &ReadConfig,
+) -> Result<Vec<ReaderRecord>, JournalClientError> {
+    if metadata_cache.get_shard(namespace, shard_name).is_none() {
+        load_shards_cache(metadata_cache, connection_manager, namespace, shard_name).await?;
+    }
+    let shard = if let Some(shard) = metadata_cache.get_shard(namespace, shard_name) {
+        shard
+    } else {
+        return Err(JournalClientError::NoAvailableSegment(shard_name_iden(
+            namespace, shard_name,
+        )));
+    };
+
+    let mut cursor = ShardCursor {
+        namespace: namespace.to_string(),
+        shard_name: shard_name.to_string(),
+        segment: locate_segment_by_offset(&shard.segments, offset).ok_or_else(|| {
+            JournalClientError::NoAvailableSegment(shard_name_iden(namespace, shard_name))
+        })?,
+        offset,
+        timestamp: None,
+    };
+    let option = ReaderOption {
+        fetch_max_size: read_config.max_size,
+        fetch_max_record: read_config.max_record_num,
+        ..Default::default()
+    };
+
+    loop {
+        let leader = if let Some(segment) = shard
+            .segments
+            .iter()
+            .find(|segment| segment.segment_no == cursor.segment)
+        {
+            segment.leader
+        } else {
+            return Ok(Vec::new());
+        };
+
+        let is_active = cursor.segment >= shard.active_segment.max(0) as u32;
+        let max_wait_ms = if is_active {
+            read_config.max_wait_ms
+        } else {
+            0
+        };
+        let resp = batch_read(
+            connection_manager,
+            leader,
+            build_read_body(&cursor, &option, max_wait_ms),
+        )
+        .await?;
+
+        let records: Vec<ReaderRecord> = resp
+            .messages
+            .into_iter()
+            .filter(|raw| raw.segment == cursor.segment)
+            .flat_map(|raw| raw.messages)
+            .filter(|message| message.offset >= cursor.offset)
+            .map(|message| ReaderRecord {
+                namespace: cursor.namespace.clone(),
+                shard_name: cursor.shard_name.clone(),
+                segment: cursor.segment,
+                offset: message.offset,
+                key: message.key,
+                value: message.value,
+                tags: message.tags,
+                timestamp: message.timestamp,
+            })
+            .collect();
+
+        if !records.is_empty() || is_active || !is_segment_finished(&cursor, &shard.segments) {
+            return Ok(records);
+        }
+        next_segment(&mut cursor, &shard.segments);
+    }
+}
+
+fn build_read_body(cursor: &ShardCursor, option: &ReaderOption, max_wait_ms: u64) -> ReadReqBody {
+    let (ready_type, filter) = if let Some(timestamp) = cursor.timestamp {
+        (
+            ReadType::Timestamp,
+            ReadReqFilter {
+                timestamp,
+                ..Default::default()
+            },
+        )
+    } else {
+        (
+            ReadType::Offset,
+            ReadReqFilter {
+                offset: cursor.offset,
+                ..Default::default()
+            },
+        )
+    };
+
+    ReadReqBody {
+        messages: vec![ReadReqMessage {
+            namespace: cursor.namespace.clone(),
+            shard_name: cursor.shard_name.clone(),
+            segment: cursor.segment,
+            ready_type: ready_type.into(),
+            filter: Some(filter),
+            options: Some(ReadReqOptions {
+                max_size: option.fetch_max_size,
+                max_record: option.fetch_max_record,
+                max_wait_ms,
+            }),
+        }],
+    }
+}
+
+// Offsets continue across segments, so the next segment is read from the cursor offset,
+// or from its start offset when that is larger.
+fn next_segment(cursor: &mut ShardCursor, segments: &[ClientSegmentMetadata]) {
+    cursor.segment += 1;
+    if let Some(segment) = segments
+        .iter()
+        .find(|segment| segment.segment_no == cursor.segment)
+    {
+        if cursor.timestamp.is_none() && segment.start_offset > cursor.offset as i64 {
+            cursor.offset = segment.start_offset as u64;
+        }
+    }
+}
+
+// Whether the cursor is past the last offset of its segment. The end offset of a segment
+// without records is unknown, the start offset of the next segment tells it then.
+fn is_segment_finished(cursor: &ShardCursor, segments: &[ClientSegmentMetadata]) -> bool {
+    if cursor.timestamp.is_some() {
+        return true;
+    }
+    let offset = cursor.offset as i64;
+    let segment = segments
+        .iter()
+        .find(|segment| segment.segment_no == cursor.segment);
+    if segment.is_some_and(|segment| segment.end_offset >= 0 && offset > segment.end_offset) {
+        return true;
+    }
+    segments
+        .iter()
+        .filter(|segment| segment.segment_no > cursor.segment)
+        .min_by_key(|segment| segment.segment_no)
+        .is_some_and(|segment| segment.start_offset >= 0 && offset >= segment.start_offset)
+}
+
+fn skip_deleted_segment(cursor: &mut ShardCursor, segments: &[ClientSegmentMetadata]) -> bool {
+    if let Some(segment) = segments
+        .iter()
+        .filter(|segment| segment.segment_no > cursor.segment)
+        .min_by_key(|segment| segment.segment_no)
+    {
+        cursor.segment = segment.segment_no;
+        if cursor.timestamp.is_none() && segment.start_offset > cursor.offset as i64 {
+            cursor.offset = segment.start_offset as u64;
+        }
+        return true;
+    }
+    false
+}
+
+/// The segment that holds `offset`: the last segment starting at or before it.
+fn locate_segment_by_offset(segments: &[ClientSegmentMetadata], offset: u64) -> Option<u32> {
+    let mut segments: Vec<&ClientSegmentMetadata> = segments.iter().collect();
+    segments.sort_by_key(|segment| segment.segment_no);
+    let first = segments.first()?.segment_no;
+    Some(
+        segments
+            .iter()
+            .rfind(|segment| segment.start_offset >= 0 && segment.start_offset as u64 <= offset)
+            .map(|segment| segment.segment_no)
+            .unwrap_or(first),
+    )
+}
+
+/// The first segment whose newest record is not older than `timestamp`, or the newest
+/// segment when all of them are older.
+fn locate_segment_by_timestamp(segments: &[ClientSegmentMetadata], timestamp: u64) -> Option<u32> {
+    let mut segments: Vec<&ClientSegmentMetadata> = segments.iter().collect();
+    segments.sort_by_key(|segment| segment.segment_no);
+    let last = segments.last()?.segment_no;
+    Some(
+        segments
+            .iter()
+            .find(|segment| segment.end_timestamp < 0 || segment.end_timestamp as u64 >= timestamp)
+            .map(|segment| segment.segment_no)
+            .unwrap_or(last),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::journal_server::journal_engine::ClientSegmentMetadata;
+
+    use super::{
+        is_segment_finished, locate_segment_by_offset, locate_segment_by_timestamp, next_segment,
+        ShardCursor,
+    };
+
+    fn build_segments() -> Vec<ClientSegmentMetadata> {
+        vec![
+            ClientSegmentMetadata {
+                segment_no: 2,
+                start_offset: 200,
+                end_offset: 299,
+                start_timestamp: 2000,
+                end_timestamp: 2999,
+                ..Default::default()
+            },
+            ClientSegmentMetadata {
+                segment_no: 1,
+                start_offset: 100,
+                end_offset: 199,
+                start_timestamp: 1000,
+                end_timestamp: 1999,
+                ..Default::default()
+            },
+            ClientSegmentMetadata {
+                segment_no: 3,
+                start_offset: 300,
+                end_offset: -1,
+                start_timestamp: 3000,
+                end_timestamp: -1,
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn locate_segment_test() {
+        let segments = build_segments();
+        assert_eq!(locate_segment_by_offset(&segments, 150), Some(1));
+        assert_eq!(locate_segment_by_offset(&segments, 200), Some(2));
+        assert_eq!(locate_segment_by_offset(&segments, 1000), Some(3));
+        // offsets removed by retention start at the oldest segment
+        assert_eq!(locate_segment_by_offset(&segments, 10), Some(1));
+
+        assert_eq!(locate_segment_by_timestamp(&segments, 500), Some(1));
+        assert_eq!(locate_segment_by_timestamp(&segments, 2500), Some(2));
+        assert_eq!(locate_segment_by_timestamp(&segments, 5000), Some(3));
+        assert_eq!(locate_segment_by_timestamp(&[], 5000), None);
+    }
+
+    #[test]
+    fn next_segment_test() {
+        let segments = build_segments();
+        let mut cursor = ShardCursor {
+            namespace: "n1".to_string(),
+            shard_name: "s1".to_string(),
+            segment: 1,
+            offset: 180,
+            timestamp: None,
+        };
+        next_segment(&mut cursor, &segments);
+        assert_eq!(cursor.segment, 2);
+        assert_eq!(cursor.offset, 200);
+
+        cursor.offset = 250;
+        next_segment(&mut cursor, &segments);
+        assert_eq!(cursor.segment, 3);
+        assert_eq!(cursor.offset, 300);
+    }
+
+    #[test]
+    fn is_segment_finished_test() {
+        let mut segments = build_segments();
+        let mut cursor = ShardCursor {
+            namespace: "n1".to_string(),
+            shard_name: "s1".to_string(),
+            segment: 2,
+            offset: 250,
+            timestamp: None,
+        };
+        // an empty read in the middle of a sealed segment does not skip the rest of it
+        assert!(!is_segment_finished(&cursor, &segments));
+        cursor.offset = 300;
+        assert!(is_segment_finished(&cursor, &segments));
+
+        // the end offset is unknown, the next segment starts after the cursor
+        segments[0].end_offset = -1;
+        cursor.offset = 250;
+        assert!(!is_segment_finished(&cursor, &segments));
+        cursor.offset = 300;
+        assert!(is_segment_finished(&cursor, &segments));
+
+        // the active segment is never finished
+        cursor.segment = 3;
+        cursor.offset = 400;
+        assert!(!is_segment_finished(&cursor, &segments));
+    }
 }
@@ -14,13 +14,17 @@
 
 use std::sync::Arc;
 
+use metadata_struct::journal::shard::shard_name_iden;
 use protocol::journal_server::codec::JournalEnginePacket;
 use protocol::journal_server::journal_engine::{
-    ApiKey, ApiVersion, CreateShardReq, CreateShardReqBody, CreateShardRespBody, DeleteShardReq,
-    DeleteShardReqBody, DeleteShardRespBody, FetchOffsetReq, FetchOffsetReqBody,
-    FetchOffsetRespBody, GetClusterMetadataReq, GetClusterMetadataRespBody, GetShardMetadataReq,
-    GetShardMetadataReqBody, GetShardMetadataReqShard, GetShardMetadataRespBody, ReadReq,
-    ReadReqBody, ReadRespBody, ReqHeader, WriteReq, WriteReqBody, WriteRespBody,
+    ApiKey, ApiVersion, CommitOffsetReq, CommitOffsetReqBody, CommitOffsetRespBody, CreateShardReq,
+    CreateShardReqBody, CreateShardRespBody, DeleteShardReq, DeleteShardReqBody,
+    DeleteShardRespBody, FetchOffsetReq, FetchOffsetReqBody, FetchOffsetRespBody,
+    GetClusterMetadataReq, GetClusterMetadataRespBody, GetShardMetadataReq,
+    GetShardMetadataReqBody, GetShardMetadataReqShard, GetShardMetadataRespBody, HeartbeatReq,
+    HeartbeatReqBody, HeartbeatRespBody, JoinGroupReq, JoinGroupReqBody, JoinGroupRespBody,
+    LeaveGroupReq, LeaveGroupReqBody, LeaveGroupRespBody, ReadReq, ReadReqBody, ReadRespBody,
+    ReqHeader, WriteReq, WriteReqBody, WriteRespBody,
 };
 
 use crate::connection::ConnectionManager;
@@ -183,6 +187,7 @@ pub(crate) async fn batch_read(
     node_id: u64,
     body: ReadReqBody,
 ) -> Result<ReadRespBody, JournalClientError> {
+    let poll_key = long_poll_key(&body);
     let req_packet = JournalEnginePacket::ReadReq(ReadReq {
         header: Some(ReqHeader {
             api_key: ApiKey::Read.into(),
@@ -191,9 +196,17 @@ pub(crate) async fn batch_read(
         body: Some(body),
     });
 
-    let resp_packet = connection_manager
-        .read_send(node_id, req_packet.clone())
-        .await?;
+    // A long-poll read holds its connection until records arrive, so every polled shard
+    // gets a connection of its own
+    let resp_packet = if let Some(poll_key) = poll_key {
+        connection_manager
+            .poll_send(node_id, &poll_key, req_packet.clone())
+            .await?
+    } else {
+        connection_manager
+            .read_send(node_id, req_packet.clone())
+            .await?
+    };
 
     if let JournalEnginePacket::ReadResp(data) = resp_packet {
         resp_header_error(&data.header, req_packet.clone())?;
@@ -243,3 +256,179 @@ pub(crate) async fn fetch_offset(
         resp_packet.to_string(),
     ))
 }
+
+pub(crate) async fn fetch_committed_offset(
+    connection_manager: &Arc<ConnectionManager>,
+    body: FetchOffsetReqBody,
+) -> Result<FetchOffsetRespBody, JournalClientError> {
+    let req_packet = JournalEnginePacket::FetchOffsetReq(FetchOffsetReq {
+        header: Some(ReqHeader {
+            api_key: ApiKey::FetchOffset.into(),
+            api_version: ApiVersion::V0.into(),
+        }),
+        body: Some(body),
+    });
+
+    let resp_packet = connection_manager.admin_send(req_packet.clone()).await?;
+
+    if let JournalEnginePacket::FetchOffsetResp(data) = resp_packet {
+        resp_header_error(&data.header, req_packet.clone())?;
+        if let Some(body) = data.body {
+            return Ok(body);
+        }
+        return Err(JournalClientError::ReceivedPacketNotContainBody(
+            req_packet.to_string(),
+        ));
+    }
+
+    Err(JournalClientError::ReceivedPacketTypeError(
+        req_packet.to_string(),
+        resp_packet.to_string(),
+    ))
+}
+
+/// Offsets of a group member go to the group coordinator, which checks the generation.
+pub(crate) async fn commit_offset(
+    connection_manager: &Arc<ConnectionManager>,
+    coordinator: Option<u64>,
+    body: CommitOffsetReqBody,
+) -> Result<CommitOffsetRespBody, JournalClientError> {
+    let req_packet = JournalEnginePacket::CommitOffsetReq(CommitOffsetReq {
+        header: Some(ReqHeader {
+            api_key: ApiKey::CommitOffset.into(),
+            api_version: ApiVersion::V0.into(),
+        }),
+        body: Some(body),
+    });
+
+    let resp_packet = if let Some(node_id) = coordinator {
+        connection_manager
+            .group_send(node_id, req_packet.clone())
+            .await?
+    } else {
+        connection_manager.admin_send(req_packet.clone()).await?
+    };
+
+    if let JournalEnginePacket::CommitOffsetResp(data) = resp_packet {
+        resp_header_error(&data.header, req_packet.clone())?;
+        if let Some(body) = data.body {
+            return Ok(body);
+        }
+        return Err(JournalClientError::ReceivedPacketNotContainBody(
+            req_packet.to_string(),
+        ));
+    }
+
+    Err(JournalClientError::ReceivedPacketTypeError(
+        req_packet.to_string(),
+        resp_packet.to_string(),
+    ))
+}
+
+pub(crate) async fn join_group(
+    connection_manager: &Arc<ConnectionManager>,
+    node_id: u64,
+    body: JoinGroupReqBody,
+) -> Result<JoinGroupRespBody, JournalClientError> {
+    let req_packet = JournalEnginePacket::JoinGroupReq(JoinGroupReq {
+        header: Some(ReqHeader {
+            api_key: ApiKey::JoinGroup.into(),
+            api_version: ApiVersion::V0.into(),
+        }),
+        body: Some(body),
+    });
+
+    let resp_packet = connection_manager
+        .group_send(node_id, req_packet.clone())
+        .await?;
+
+    if let JournalEnginePacket::JoinGroupResp(data) = resp_packet {
+        resp_header_error(&data.header, req_packet.clone())?;
+        if let Some(body) = data.body {
+            return Ok(body);
+        }
+        return Err(JournalClientError::ReceivedPacketNotContainBody(
+            req_packet.to_string(),
+        ));
+    }
+
+    Err(JournalClientError::ReceivedPacketTypeError(
+        req_packet.to_string(),
+        resp_packet.to_string(),
+    ))
+}
+
+pub(crate) async fn heartbeat(
+    connection_manager: &Arc<ConnectionManager>,
+    node_id: u64,
+    body: HeartbeatReqBody,
+) -> Result<HeartbeatRespBody, JournalClientError> {
+    let req_packet = JournalEnginePacket::HeartbeatReq(HeartbeatReq {
+        header: Some(ReqHeader {
+            api_key: ApiKey::Heartbeat.into(),
+            api_version: ApiVersion::V0.into(),
+        }),
+        body: Some(body),
+    });
+
+    let resp_packet = connection_manager
+        .group_send(node_id, req_packet.clone())
+        .await?;
+
+    if let JournalEnginePacket::HeartbeatResp(data) = resp_packet {
+        resp_header_error(&data.header, req_packet.clone())?;
+        if let Some(body) = data.body {
+            return Ok(body);
+        }
+        return Err(JournalClientError::ReceivedPacketNotContainBody(
+            req_packet.to_string(),
+        ));
+    }
+
+    Err(JournalClientError::ReceivedPacketTypeError(
+        req_packet.to_string(),
+        resp_packet.to_string(),
+    ))
+}
+
+pub(crate) async fn leave_group(
+    connection_manager: &Arc<ConnectionManager>,
+    node_id: u64,
+    body: LeaveGroupReqBody,
+) -> Result<LeaveGroupRespBody, JournalClientError> {
+    let req_packet = JournalEnginePacket::LeaveGroupReq(LeaveGroupReq {
+        header: Some(ReqHeader {
+            api_key: ApiKey::LeaveGroup.into(),
+            api_version: ApiVersion::V0.into(),
+        }),
+        body: Some(body),
+    });
+
+    let resp_packet = connection_manager
+        .group_send(node_id, req_packet.clone())
+        .await?;
+
+    if let JournalEnginePacket::LeaveGroupResp(data) = resp_packet {
+        resp_header_error(&data.header, req_packet.clone())?;
+        if let Some(body) = data.body {
+            return Ok(body);
+        }
+        return Err(JournalClientError::ReceivedPacketNotContainBody(
+            req_packet.to_string(),
+        ));
+    }
+
+    Err(JournalClientError::ReceivedPacketTypeError(
+        req_packet.to_string(),
+        resp_packet.to_string(),
+    ))
+}
+
+fn long_poll_key(body: &ReadReqBody) -> Option<String> {
+    let message = body.messages.iter().find(|raw| {
+        raw.options
+            .as_ref()
+            .is_some_and(|options| options.max_wait_ms > 0)
+    })?;
+    Some(shard_name_iden(&message.namespace, &message.shard_name))
+}
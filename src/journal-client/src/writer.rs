@@ -13,20 +13,24 @@
 // limitations under the License.
 
 use std::collections::HashMap;
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
-use common_base::tools::now_mills;
+use common_base::tools::{now_mills, now_second, unique_id};
 use dashmap::DashMap;
-use log::{error, warn};
+use log::error;
 use metadata_struct::journal::segment::segment_name;
 use metadata_struct::journal::shard::shard_name_iden;
+use prost::Message;
+use protocol::journal_server::compression::compress;
 use protocol::journal_server::journal_engine::{
-    WriteReqBody, WriteReqMessages, WriteReqSegmentMessages,
+    AckMode, CompressionType, WriteReqBody, WriteReqSegmentMessages,
 };
+use protocol::journal_server::journal_record::{JournalRecord, JournalRecordBatch};
 use tokio::select;
 use tokio::sync::broadcast::{self, Receiver, Sender};
+use tokio::sync::Mutex;
 use tokio::time::{sleep, timeout};
 
 use crate::cache::{load_shards_cache, MetadataCache};
@@ -40,27 +44,43 @@ pub struct SenderMessage {
     namespace: String,
     shard_name: String,
     segment: u32,
+    // empty unless the writer is idempotent
+    producer_id: String,
+    // sequence number of the record, for idempotent writers it follows the previous
+    // record of the producer on the shard
+    pkid: u64,
     key: String,
     content: Vec<u8>,
     tags: Vec<String>,
+    ack_mode: AckMode,
+    // when the record was produced, it stays with the record inside a batch
+    create_time: u64,
 }
 
 impl SenderMessage {
+    #[allow(clippy::too_many_arguments)]
     pub fn build(
         namespace: &String,
         shard_name: &String,
         segment: u32,
+        producer_id: &str,
+        pkid: u64,
         key: &String,
         content: &Vec<u8>,
         tags: &Vec<String>,
+        ack_mode: AckMode,
     ) -> Self {
         SenderMessage {
             namespace: namespace.to_owned(),
             shard_name: shard_name.to_owned(),
             segment,
+            producer_id: producer_id.to_owned(),
+            pkid,
             key: key.to_owned(),
             content: content.to_owned(),
             tags: tags.to_owned(),
+            ack_mode,
+            create_time: now_second(),
         }
     }
 }
@@ -68,8 +88,40 @@ impl SenderMessage {
 // Send Message Resp Struct
 #[derive(Clone)]
 pub struct SenderMessageResp {
-    pub offset: u64,
+    // None for AckMode::None, the client does not wait for the offset the record gets
+    pub offset: Option<u64>,
     pub error: Option<String>,
+    // the code of a server error, lets the caller tell the errors apart
+    pub error_code: Option<String>,
+}
+
+/// The producer an idempotent writer writes a shard with. The sequence numbers of its
+/// records follow each other, a gap is rejected by the segment leader.
+pub struct ShardProducer {
+    producer_id: String,
+    next_sequence: u64,
+}
+
+impl ShardProducer {
+    fn new() -> Self {
+        ShardProducer {
+            producer_id: unique_id(),
+            next_sequence: 1,
+        }
+    }
+
+    /// The producer id and the sequence number of the next record.
+    pub fn next(&mut self) -> (String, u64) {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        (self.producer_id.clone(), sequence)
+    }
+
+    /// Starts over as a new producer, after a record that may or may not have been written
+    /// the sequence of the old one cannot be continued.
+    pub fn reset(&mut self) {
+        *self = ShardProducer::new();
+    }
 }
 
 // Node Sender Threade Struct
@@ -91,19 +143,55 @@ pub struct Writer {
     node_senders: DashMap<u64, NodeSenderThread>,
     connection_manager: Arc<ConnectionManager>,
     metadata_cache: Arc<MetadataCache>,
+    ack_timeout_ms: u64,
+    compression: CompressionType,
+    enable_idempotence: bool,
+    // the producer of each shard an idempotent writer writes
+    producers: DashMap<String, Arc<Mutex<ShardProducer>>>,
+    pkid_generator: AtomicU64,
 }
 
 impl Writer {
     pub fn new(
         connection_manager: Arc<ConnectionManager>,
         metadata_cache: Arc<MetadataCache>,
+        ack_timeout_ms: u64,
+        compression: CompressionType,
+        enable_idempotence: bool,
     ) -> Self {
         let node_senders = DashMap::with_capacity(2);
         Writer {
             node_senders,
             connection_manager,
             metadata_cache,
+            ack_timeout_ms,
+            compression,
+            enable_idempotence,
+            producers: DashMap::with_capacity(2),
+            pkid_generator: AtomicU64::new(0),
+        }
+    }
+
+    /// The pkid of the next record of a writer that is not idempotent.
+    pub fn next_pkid(&self) -> u64 {
+        self.pkid_generator.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// The producer of the shard, None unless the writer is idempotent. A record keeps its
+    /// sequence number across retries, so the segment leader can recognize the retry.
+    pub fn shard_producer(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+    ) -> Option<Arc<Mutex<ShardProducer>>> {
+        if !self.enable_idempotence {
+            return None;
         }
+        let producer = self
+            .producers
+            .entry(shard_name_iden(namespace, shard_name))
+            .or_insert_with(|| Arc::new(Mutex::new(ShardProducer::new())));
+        Some(producer.clone())
     }
 
     fn add_node_sender(&self, node_id: u64) -> Sender<DataSenderPkg> {
@@ -121,6 +209,8 @@ impl Writer {
             node_id,
             self.connection_manager.clone(),
             self.metadata_cache.clone(),
+            self.ack_timeout_ms,
+            self.compression,
             data_sender.clone(),
             stop_send,
         );
@@ -179,7 +269,24 @@ impl Writer {
         };
 
         let rs = sender.send(data);
-        let resp_data = timeout(Duration::from_secs(30), callback_rx.recv()).await?;
+
+        // fire-and-forget, the write result is not waited for
+        if message.ack_mode == AckMode::None {
+            return Ok(SenderMessageResp {
+                offset: None,
+                error: None,
+                error_code: None,
+            });
+        }
+
+        // leave the server enough time to return its own ack timeout error,
+        // a timeout of 0 means the server default of 30s
+        let wait_ms = if self.ack_timeout_ms > 0 {
+            self.ack_timeout_ms + 5000
+        } else {
+            35000
+        };
+        let resp_data = timeout(Duration::from_millis(wait_ms), callback_rx.recv()).await?;
         Ok(resp_data?)
     }
 
@@ -191,17 +298,19 @@ impl Writer {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn start_sender_thread(
     node_id: u64,
     connection_manager: Arc<ConnectionManager>,
     metadata_cache: Arc<MetadataCache>,
+    ack_timeout_ms: u64,
+    compression: CompressionType,
     node_send: Sender<DataSenderPkg>,
     stop_send: broadcast::Sender<bool>,
 ) {
     tokio::spawn(async move {
         let mut stop_recv = stop_send.subscribe();
         let mut node_recv = node_send.subscribe();
-        let pkid_generator = AtomicU64::new(0);
         loop {
             select! {
                 val = stop_recv.recv()=>{
@@ -214,27 +323,33 @@ pub fn start_sender_thread(
                         sleep(Duration::from_millis(100)).await;
                         continue;
                     }
-                    batch_sender_message(&connection_manager,&metadata_cache, node_id, &pkid_generator, messages).await;
+                    batch_sender_message(&connection_manager,&metadata_cache, node_id, ack_timeout_ms, compression, messages).await;
                 }
             }
         }
     });
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn batch_sender_message(
     connection_manager: &Arc<ConnectionManager>,
     metadata_cache: &Arc<MetadataCache>,
     node_id: u64,
-    pkid_generator: &AtomicU64,
+    ack_timeout_ms: u64,
+    compression: CompressionType,
     messages: Vec<DataSenderPkg>,
 ) {
-    // build data by namespace&shard_name&segment
+    // build data by namespace&shard_name&segment&ack_mode
     let mut segment_data_list: HashMap<String, Vec<DataSenderPkg>> = HashMap::new();
     for pkg in messages.iter() {
-        let key = segment_name(
-            &pkg.message.namespace,
-            &pkg.message.shard_name,
-            pkg.message.segment,
+        let key = format!(
+            "{},{}",
+            segment_name(
+                &pkg.message.namespace,
+                &pkg.message.shard_name,
+                pkg.message.segment,
+            ),
+            pkg.message.ack_mode.as_str_name()
         );
         if let Some(list) = segment_data_list.get_mut(&key) {
             list.push(pkg.to_owned());
@@ -246,7 +361,8 @@ async fn batch_sender_message(
 
     // build WriteReqSegmentMessages
     let mut segments: Vec<WriteReqSegmentMessages> = Vec::new();
-    let mut callback_sender = HashMap::new();
+    // callbacks of each entry in `segments` by pkid, the sequences of shards overlap
+    let mut segment_callbacks: Vec<HashMap<u64, Sender<SenderMessageResp>>> = Vec::new();
     for (_, messages) in segment_data_list {
         if messages.is_empty() {
             continue;
@@ -255,62 +371,108 @@ async fn batch_sender_message(
         let namespace = first_msg.message.namespace.to_owned();
         let shard_name = first_msg.message.shard_name.to_owned();
         let segment = first_msg.message.segment;
+        let ack_mode = first_msg.message.ack_mode;
 
-        let mut write_req_segment_messages = Vec::new();
+        // all messages of a segment go out as one batch, compressed as a whole
+        let mut records = Vec::new();
+        let mut segment_callback_sender = HashMap::new();
         for msg in messages {
-            let pkid = pkid_generator.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-            write_req_segment_messages.push(WriteReqMessages {
+            let pkid = msg.message.pkid;
+            records.push(JournalRecord {
+                producer_id: msg.message.producer_id,
                 pkid,
                 key: msg.message.key,
-                value: msg.message.content,
+                content: msg.message.content,
                 tags: msg.message.tags,
+                create_time: msg.message.create_time,
+                ..Default::default()
             });
-            callback_sender.insert(pkid, msg.callback_sx);
+            // fire-and-forget records are not answered
+            if ack_mode != AckMode::None {
+                segment_callback_sender.insert(pkid, msg.callback_sx);
+            }
         }
 
+        let data = JournalRecordBatch { records }.encode_to_vec();
+        let batch = match compress(compression, &data) {
+            Ok(batch) => batch,
+            Err(e) => {
+                for (_, callback_sx) in segment_callback_sender {
+                    if let Err(e) = callback_sx.send(SenderMessageResp {
+                        offset: None,
+                        error: Some(e.to_string()),
+                        error_code: None,
+                    }) {
+                        error!("{}", e);
+                    }
+                }
+                continue;
+            }
+        };
         let msg = WriteReqSegmentMessages {
             namespace,
             shard_name,
             segment,
-            messages: write_req_segment_messages,
+            ack_mode: ack_mode.into(),
+            compression: compression.into(),
+            batch,
+            ..Default::default()
         };
         segments.push(msg);
+        segment_callbacks.push(segment_callback_sender);
     }
 
     // send data
-    let body = WriteReqBody { data: segments };
+    let body = WriteReqBody {
+        data: segments,
+        timeout_ms: ack_timeout_ms,
+    };
     match batch_write(connection_manager, node_id, body).await {
         Ok(data) => {
-            // callback resp
-            for shard_msg in data.status {
+            // callback resp, the server answers the shards in the order of the request
+            for (shard_msg, callback_sender) in data.status.into_iter().zip(segment_callbacks) {
+                if let Some(e) = shard_msg.error {
+                    for (_, callback_sx) in callback_sender {
+                        if let Err(e) = callback_sx.send(SenderMessageResp {
+                            offset: None,
+                            error: Some(format!("{}:{}", e.code, e.error)),
+                            error_code: Some(e.code.clone()),
+                        }) {
+                            error!("{}", e);
+                        }
+                    }
+                    continue;
+                }
+
                 for msg in shard_msg.messages {
                     if let Some(callback_sx) = callback_sender.get(&msg.pkid) {
                         let resp = if let Some(e) = msg.error {
                             SenderMessageResp {
-                                offset: 0,
+                                offset: None,
                                 error: Some(format!("{}:{}", e.code, e.error)),
+                                error_code: Some(e.code),
                             }
                         } else {
                             SenderMessageResp {
-                                offset: msg.offset,
+                                offset: Some(msg.offset),
                                 error: None,
+                                error_code: None,
                             }
                         };
                         if let Err(e) = callback_sx.send(resp) {
                             error!("{}", e);
                         }
-                    } else {
-                        warn!("");
                     }
                 }
             }
         }
         Err(e) => {
             // callback error
-            for (_, callback_sx) in callback_sender {
+            for (_, callback_sx) in segment_callbacks.into_iter().flatten() {
                 if let Err(e) = callback_sx.send(SenderMessageResp {
-                    offset: 0,
+                    offset: None,
                     error: Some(e.to_string()),
+                    error_code: None,
                 }) {
                     error!("{}", e);
                 }
@@ -29,9 +29,10 @@ use protocol::placement_center::placement_center_inner::NodeListRequest;
 use protocol::placement_center::placement_center_journal::{
     ListSegmentMetaRequest, ListSegmentRequest, ListShardRequest,
 };
-use tokio::sync::broadcast;
+use tokio::sync::{broadcast, RwLock};
 
 use super::cluster::JournalEngineClusterConfig;
+use crate::segment::producer::ProducerState;
 use crate::segment::write::SegmentWrite;
 use crate::segment::SegmentIdentity;
 
@@ -44,7 +45,12 @@ pub struct CacheManager {
     segment_metadatas: DashMap<String, DashMap<u32, JournalSegmentMetadata>>,
     leader_segments: DashMap<String, SegmentIdentity>,
     segment_index_build_thread: DashMap<String, broadcast::Sender<bool>>,
+    segment_fetch_thread: DashMap<String, broadcast::Sender<bool>>,
     segment_writes: DashMap<String, SegmentWrite>,
+    // namespace_shard_name, producer_id, state
+    producer_states: DashMap<String, DashMap<String, ProducerState>>,
+    // reads share the lock of a segment file, replacing the file takes it exclusively
+    segment_file_locks: DashMap<String, Arc<RwLock<()>>>,
 }
 
 impl CacheManager {
@@ -56,7 +62,10 @@ impl CacheManager {
         let segment_metadatas = DashMap::with_capacity(8);
         let leader_segments = DashMap::with_capacity(8);
         let segment_index_build_thread = DashMap::with_capacity(2);
+        let segment_fetch_thread = DashMap::with_capacity(2);
         let segment_write = DashMap::with_capacity(2);
+        let producer_states = DashMap::with_capacity(2);
+        let segment_file_locks = DashMap::with_capacity(8);
         CacheManager {
             cluster,
             node_list,
@@ -65,7 +74,10 @@ impl CacheManager {
             segment_metadatas,
             leader_segments,
             segment_index_build_thread,
+            segment_fetch_thread,
             segment_writes: segment_write,
+            producer_states,
+            segment_file_locks,
         }
     }
 
@@ -77,6 +89,13 @@ impl CacheManager {
         self.node_list.remove(&node_id);
     }
 
+    pub fn get_node(&self, node_id: u64) -> Option<BrokerNode> {
+        if let Some(node) = self.node_list.get(&node_id) {
+            return Some(node.clone());
+        }
+        None
+    }
+
     pub fn all_node(&self) -> Vec<BrokerNode> {
         let mut results = Vec::new();
         for raw in self.node_list.iter() {
@@ -120,6 +139,7 @@ impl CacheManager {
         self.shards.remove(&key);
         self.segments.remove(&key);
         self.segment_metadatas.remove(&key);
+        self.producer_states.remove(&key);
     }
 
     pub fn get_shards(&self) -> Vec<JournalShard> {
@@ -185,6 +205,7 @@ impl CacheManager {
         }
 
         self.remove_leader_segment(segment);
+        self.segment_file_locks.remove(&segment.name());
 
         if let Some(stop_send) = self.segment_index_build_thread.get(&key) {
             if let Err(e) = stop_send.send(true) {
@@ -197,6 +218,12 @@ impl CacheManager {
                 debug!("Trying to stop the segment write thread for segment {} failed with error message:{}", segment.name(),e);
             }
         }
+
+        if let Some(stop_send) = self.segment_fetch_thread.get(&segment.name()) {
+            if let Err(e) = stop_send.send(true) {
+                debug!("Trying to stop the replica fetch thread for segment {} failed with error message:{}", segment.name(),e);
+            }
+        }
     }
 
     pub fn get_segment(&self, segment: &SegmentIdentity) -> Option<JournalSegment> {
@@ -223,6 +250,19 @@ impl CacheManager {
         results
     }
 
+    pub fn get_follower_segments(&self) -> Vec<JournalSegment> {
+        let conf = journal_server_conf();
+        let mut results = Vec::new();
+        for sgement_list in self.segments.iter() {
+            for segment in sgement_list.iter() {
+                if segment.leader != conf.node_id && segment.get_fold(conf.node_id).is_some() {
+                    results.push(segment.value().clone());
+                }
+            }
+        }
+        results
+    }
+
     pub fn update_segment_status(&self, segment_iden: &SegmentIdentity, status: SegmentStatus) {
         if let Some(sgement_list) = self.segments.get(&shard_name_iden(
             &segment_iden.namespace,
@@ -286,6 +326,24 @@ impl CacheManager {
             .contains_key(&segment_iden.name())
     }
 
+    // Replica Fetch Thread
+    pub fn add_fetch_thread(
+        &self,
+        segment_iden: &SegmentIdentity,
+        stop_send: broadcast::Sender<bool>,
+    ) {
+        self.segment_fetch_thread
+            .insert(segment_iden.name(), stop_send);
+    }
+
+    pub fn remove_fetch_thread(&self, segment_iden: &SegmentIdentity) {
+        self.segment_fetch_thread.remove(&segment_iden.name());
+    }
+
+    pub fn contain_fetch_thread(&self, segment_iden: &SegmentIdentity) -> bool {
+        self.segment_fetch_thread.contains_key(&segment_iden.name())
+    }
+
     // Segment Write Thread
     pub fn add_segment_write_thread(
         &self,
@@ -307,6 +365,47 @@ impl CacheManager {
         None
     }
 
+    // Segment File Lock
+    pub fn get_segment_file_lock(&self, segment_iden: &SegmentIdentity) -> Arc<RwLock<()>> {
+        self.segment_file_locks
+            .entry(segment_iden.name())
+            .or_insert_with(|| Arc::new(RwLock::new(())))
+            .clone()
+    }
+
+    // Producer State
+    pub fn set_producer_state(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        producer_id: &str,
+        state: ProducerState,
+    ) {
+        let key = shard_name_iden(namespace, shard_name);
+        if let Some(states) = self.producer_states.get(&key) {
+            states.insert(producer_id.to_string(), state);
+        } else {
+            let states = DashMap::with_capacity(2);
+            states.insert(producer_id.to_string(), state);
+            self.producer_states.insert(key, states);
+        }
+    }
+
+    pub fn get_producer_state(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        producer_id: &str,
+    ) -> Option<ProducerState> {
+        let key = shard_name_iden(namespace, shard_name);
+        if let Some(states) = self.producer_states.get(&key) {
+            if let Some(state) = states.get(producer_id) {
+                return Some(state.clone());
+            }
+        }
+        None
+    }
+
     // Leader Segment
     pub fn get_leader_segment(&self) -> Vec<SegmentIdentity> {
         let mut results = Vec::new();
@@ -15,3 +15,10 @@
 pub const DB_COLUMN_FAMILY_INDEX: &str = "index";
 
 pub const BUILD_INDE_PER_RECORD_NUM: u64 = 10000;
+
+pub const DEFAULT_WRITE_ACK_TIMEOUT_MS: u64 = 30000;
+
+pub const SEGMENT_COMPACTION_INTERVAL_SEC: u64 = 60;
+
+// number of recent records per producer and shard whose offsets are kept for retries
+pub const PRODUCER_STATE_WINDOW: usize = 100;
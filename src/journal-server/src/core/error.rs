@@ -43,6 +43,9 @@ pub enum JournalServerError {
     #[error("{0}")]
     OneshotRecvError(#[from] tokio::sync::oneshot::error::RecvError),
 
+    #[error("{0}")]
+    WatchRecvError(#[from] tokio::sync::watch::error::RecvError),
+
     #[error("{0}")]
     ProstDecodeError(#[from] prost::DecodeError),
 
@@ -96,6 +99,59 @@ pub enum JournalServerError {
 
     #[error("Segment file meta {0} does not exist, maybe it hasn't been initialized yet.")]
     SegmentFileMetaNotExists(String),
+
+    #[error("Node {1} is not a replica of Segment {0}")]
+    NotSegmentReplica(String, u64),
+
+    #[error("Leader epoch of Segment {0} is {1}, but the request carries leader epoch {2}")]
+    LeaderEpochNotMatch(String, u32, u32),
+
+    #[error(
+        "Segment {0} has {1} in-sync replicas, at least {2} are required to acknowledge writes"
+    )]
+    NotEnoughInSyncReplicas(String, usize, usize),
+
+    #[error("Node {0} does not exist in the cluster cache")]
+    NodeNotExist(u64),
+
+    #[error("Offset {1} of Segment {0} was not replicated to all in-sync replicas within {2}ms")]
+    WriteAckTimeout(String, u64, u128),
+
+    #[error(
+        "Record batch at position {1} of Segment {0} has an invalid header, magic {2}, version {3}"
+    )]
+    SegmentRecordInvalidHeader(String, u64, u8, u8),
+
+    #[error(
+        "Record batch at position {1} of Segment {0} failed CRC check, expected {2}, actual {3}"
+    )]
+    SegmentRecordCrcMismatch(String, u64, u32, u32),
+
+    #[error("Record batch at position {1} of Segment {0} is {2} bytes long, at most {3} bytes are allowed")]
+    SegmentRecordTooLarge(String, u64, u64, u64),
+
+    #[error("Segment file {0} is sealed but damaged from position {1} on, it was moved to {2}")]
+    SegmentFileDamaged(String, u64, String),
+
+    #[error(
+        "Segment file {0} was written in format version {1}, which this version does not read"
+    )]
+    SegmentFileFormatNotSupported(String, u8),
+
+    #[error("Node {0} is not the coordinator of group {1}, the coordinator is node {2}")]
+    NotGroupCoordinator(u64, String, u64),
+
+    #[error("Member {1} does not exist in group {0}")]
+    GroupMemberNotFound(String, String),
+
+    #[error("Group {0} consumes namespace {1}, it cannot be joined for namespace {2}")]
+    GroupNamespaceMismatch(String, String, String),
+
+    #[error("Group {0} is at generation {1}, offsets committed in generation {2} are rejected")]
+    GroupGenerationNotMatch(String, u64, u64),
+
+    #[error("Producer {1} of shard {0} sent sequence {3}, but sequence {2} was expected")]
+    OutOfOrderProducerSequence(String, String, u64, u64),
 }
 
 pub fn get_journal_server_code(e: &JournalServerError) -> String {
@@ -109,6 +165,7 @@ pub fn get_journal_server_code(e: &JournalServerError) -> String {
             "MpscSegmentWriteDataSendError".to_string()
         }
         JournalServerError::OneshotRecvError(_) => "OneshotRecvError".to_string(),
+        JournalServerError::WatchRecvError(_) => "WatchRecvError".to_string(),
         JournalServerError::FromUtf8Error(_) => "FromUtf8Error".to_string(),
         JournalServerError::SegmentAlreadySealUp(_) => "SegmentAlreadySealUp".to_string(),
         JournalServerError::TokioTimeErrorElapsed(_) => "TokioTimeErrorElapsed".to_string(),
@@ -131,6 +188,35 @@ pub fn get_journal_server_code(e: &JournalServerError) -> String {
         }
         JournalServerError::SegmentMetaNotExists(_) => "SegmentMetaNotExists".to_string(),
         JournalServerError::SegmentFileMetaNotExists(_) => "SegmentFileMetaNotExists".to_string(),
+        JournalServerError::NotSegmentReplica(_, _) => "NotSegmentReplica".to_string(),
+        JournalServerError::LeaderEpochNotMatch(_, _, _) => "LeaderEpochNotMatch".to_string(),
+        JournalServerError::NotEnoughInSyncReplicas(_, _, _) => {
+            "NotEnoughInSyncReplicas".to_string()
+        }
+        JournalServerError::NodeNotExist(_) => "NodeNotExist".to_string(),
+        JournalServerError::WriteAckTimeout(_, _, _) => "WriteAckTimeout".to_string(),
+        JournalServerError::SegmentRecordInvalidHeader(_, _, _, _) => {
+            "SegmentRecordInvalidHeader".to_string()
+        }
+        JournalServerError::SegmentRecordCrcMismatch(_, _, _, _) => {
+            "SegmentRecordCrcMismatch".to_string()
+        }
+        JournalServerError::SegmentRecordTooLarge(_, _, _, _) => {
+            "SegmentRecordTooLarge".to_string()
+        }
+        JournalServerError::SegmentFileDamaged(_, _, _) => "SegmentFileDamaged".to_string(),
+        JournalServerError::SegmentFileFormatNotSupported(_, _) => {
+            "SegmentFileFormatNotSupported".to_string()
+        }
+        JournalServerError::NotGroupCoordinator(_, _, _) => "NotGroupCoordinator".to_string(),
+        JournalServerError::GroupMemberNotFound(_, _) => "GroupMemberNotFound".to_string(),
+        JournalServerError::GroupNamespaceMismatch(_, _, _) => "GroupNamespaceMismatch".to_string(),
+        JournalServerError::GroupGenerationNotMatch(_, _, _) => {
+            "GroupGenerationNotMatch".to_string()
+        }
+        JournalServerError::OutOfOrderProducerSequence(_, _, _, _) => {
+            "OutOfOrderProducerSequence".to_string()
+        }
     }
 }
 #[cfg(test)]
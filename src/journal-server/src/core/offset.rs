@@ -14,9 +14,14 @@
 
 use std::sync::Arc;
 
+use common_base::config::journal_server::journal_server_conf;
+use grpc_clients::placement::placement::call::{get_offset_data, save_offset_data};
 use grpc_clients::pool::ClientPool;
 use metadata_struct::journal::shard::shard_name_iden;
 use protocol::journal_server::journal_engine::AutoOffsetStrategy;
+use protocol::placement_center::placement_center_inner::{
+    GetOffsetDataRequest, SaveOffsetDataRequest, SaveOffsetDataRequestOffset,
+};
 use serde::{Deserialize, Serialize};
 
 use super::cache::CacheManager;
@@ -101,9 +106,10 @@ impl OffsetManager {
             ));
         };
 
-        Ok(segment_meta.start_offset as u64)
+        Ok(segment_meta.start_offset.max(0) as u64)
     }
 
+    // The latest offset is the offset of the next record written to the shard
     async fn get_latest_offset_by_shard(
         &self,
         namespace: &str,
@@ -117,12 +123,10 @@ impl OffsetManager {
             )));
         };
 
-        let last_segment = shard.last_segment_seq;
-
         let segment_iden = SegmentIdentity {
             namespace: namespace.to_owned(),
             shard_name: shard_name.to_owned(),
-            segment_seq: last_segment,
+            segment_seq: shard.active_segment_seq,
         };
 
         let end_offset =
@@ -134,6 +138,60 @@ impl OffsetManager {
                 ));
             };
 
-        Ok(end_offset as u64)
+        if end_offset >= 0 {
+            return Ok(end_offset as u64 + 1);
+        }
+
+        // nothing has been written to the active segment yet
+        if let Some(meta) = self.cache_manager.get_segment_meta(&segment_iden) {
+            return Ok(meta.start_offset.max(0) as u64);
+        }
+        Ok(0)
+    }
+
+    pub async fn commit_offset(
+        &self,
+        cluster_name: &str,
+        group_name: &str,
+        offsets: Vec<Offset>,
+    ) -> Result<(), JournalServerError> {
+        let conf = journal_server_conf();
+        let request = SaveOffsetDataRequest {
+            cluster_name: cluster_name.to_string(),
+            group: group_name.to_string(),
+            offsets: offsets
+                .into_iter()
+                .map(|offset| SaveOffsetDataRequestOffset {
+                    namespace: offset.namespace,
+                    shard_name: offset.shard_name,
+                    offset: offset.offset,
+                })
+                .collect(),
+        };
+        save_offset_data(self.client_pool.clone(), &conf.placement_center, request).await?;
+        Ok(())
+    }
+
+    pub async fn get_committed_offsets(
+        &self,
+        cluster_name: &str,
+        group_name: &str,
+    ) -> Result<Vec<Offset>, JournalServerError> {
+        let conf = journal_server_conf();
+        let request = GetOffsetDataRequest {
+            cluster_name: cluster_name.to_string(),
+            group: group_name.to_string(),
+        };
+        let reply =
+            get_offset_data(self.client_pool.clone(), &conf.placement_center, request).await?;
+        Ok(reply
+            .offsets
+            .into_iter()
+            .map(|offset| Offset {
+                namespace: offset.namespace,
+                shard_name: offset.shard_name,
+                offset: offset.offset,
+            })
+            .collect())
     }
 }
@@ -28,6 +28,41 @@ use crate::segment::file::{data_file_segment, open_segment_write};
 use crate::segment::manager::SegmentFileManager;
 use crate::segment::SegmentIdentity;
 
+/// A segment can only be written and read on its leader, while it is in the Write state.
+pub fn segment_validator(
+    cache_manager: &Arc<CacheManager>,
+    segment_identity: &SegmentIdentity,
+) -> Result<(), JournalServerError> {
+    if cache_manager
+        .get_shard(&segment_identity.namespace, &segment_identity.shard_name)
+        .is_none()
+    {
+        return Err(JournalServerError::ShardNotExist(
+            segment_identity.shard_name.to_string(),
+        ));
+    }
+
+    let segment = if let Some(segment) = cache_manager.get_segment(segment_identity) {
+        segment
+    } else {
+        return Err(JournalServerError::SegmentNotExist(segment_identity.name()));
+    };
+
+    if !segment.allow_read() {
+        return Err(JournalServerError::SegmentStatusError(
+            segment_identity.name(),
+            segment.status.to_string(),
+        ));
+    }
+
+    let conf = journal_server_conf();
+    if segment.leader != conf.node_id {
+        return Err(JournalServerError::NotLeader(segment_identity.name()));
+    }
+
+    Ok(())
+}
+
 pub fn delete_local_segment(
     cache_manager: Arc<CacheManager>,
     segment_file_manager: Arc<SegmentFileManager>,
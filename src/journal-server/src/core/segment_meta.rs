@@ -38,6 +38,9 @@ pub async fn update_meta_start_offset(
         end_offset: -1,
         start_timestamp: -1,
         end_timestamp: -1,
+        isr: Vec::new(),
+        size: -1,
+        leader_epoch: 0,
     };
     update_segment_meta(client_pool, &conf.placement_center, request).await?;
     Ok(())
@@ -59,6 +62,9 @@ pub async fn update_meta_end_offset(
         end_offset: end_offset as i64,
         start_timestamp: -1,
         end_timestamp: -1,
+        isr: Vec::new(),
+        size: -1,
+        leader_epoch: 0,
     };
     update_segment_meta(client_pool, &conf.placement_center, request).await?;
     Ok(())
@@ -80,6 +86,9 @@ pub async fn update_meta_start_timestamp(
         end_offset: -1,
         start_timestamp: start_timestamp as i64,
         end_timestamp: -1,
+        isr: Vec::new(),
+        size: -1,
+        leader_epoch: 0,
     };
     update_segment_meta(client_pool, &conf.placement_center, request).await?;
     Ok(())
@@ -101,6 +110,56 @@ pub async fn update_meta_end_timestamp(
         end_offset: -1,
         start_timestamp: -1,
         end_timestamp: end_timestamp as i64,
+        isr: Vec::new(),
+        size: -1,
+        leader_epoch: 0,
+    };
+    update_segment_meta(client_pool, &conf.placement_center, request).await?;
+    Ok(())
+}
+
+pub async fn update_meta_isr(
+    client_pool: Arc<ClientPool>,
+    segment_iden: &SegmentIdentity,
+    leader_epoch: u32,
+    isr: Vec<u64>,
+) -> Result<(), JournalServerError> {
+    let conf = journal_server_conf();
+    let request = UpdateSegmentMetaRequest {
+        cluster_name: conf.cluster_name.clone(),
+        namespace: segment_iden.namespace.clone(),
+        shard_name: segment_iden.shard_name.clone(),
+        segment_no: segment_iden.segment_seq,
+        start_offset: -1,
+        end_offset: -1,
+        start_timestamp: -1,
+        end_timestamp: -1,
+        isr,
+        size: -1,
+        leader_epoch,
+    };
+    update_segment_meta(client_pool, &conf.placement_center, request).await?;
+    Ok(())
+}
+
+pub async fn update_meta_size(
+    client_pool: Arc<ClientPool>,
+    segment_iden: &SegmentIdentity,
+    size: u64,
+) -> Result<(), JournalServerError> {
+    let conf = journal_server_conf();
+    let request = UpdateSegmentMetaRequest {
+        cluster_name: conf.cluster_name.clone(),
+        namespace: segment_iden.namespace.clone(),
+        shard_name: segment_iden.shard_name.clone(),
+        segment_no: segment_iden.segment_seq,
+        start_offset: -1,
+        end_offset: -1,
+        start_timestamp: -1,
+        end_timestamp: -1,
+        isr: Vec::new(),
+        size: size as i64,
+        leader_epoch: 0,
     };
     update_segment_meta(client_pool, &conf.placement_center, request).await?;
     Ok(())
@@ -0,0 +1,253 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::collections::{HashMap, HashSet};
+
+use protocol::journal_server::journal_engine::AssignmentStrategy;
+
+/// Splits the shards of a namespace between the members of a consumer group.
+pub trait ShardAssignor {
+    /// `members` and `shards` are sorted, `previous` is the assignment of the last generation.
+    /// Every member is in the result, possibly with no shards.
+    fn assign(
+        &self,
+        members: &[String],
+        shards: &[String],
+        previous: &HashMap<String, Vec<String>>,
+    ) -> HashMap<String, Vec<String>>;
+}
+
+pub fn build_assignor(strategy: AssignmentStrategy) -> Box<dyn ShardAssignor + Send + Sync> {
+    match strategy {
+        AssignmentStrategy::Range => Box::new(RangeAssignor {}),
+        AssignmentStrategy::RoundRobin => Box::new(RoundRobinAssignor {}),
+        AssignmentStrategy::Sticky => Box::new(StickyAssignor {}),
+    }
+}
+
+/// Every member gets a contiguous range of shards, the first members one more when the
+/// shards do not divide evenly.
+pub struct RangeAssignor {}
+
+impl ShardAssignor for RangeAssignor {
+    fn assign(
+        &self,
+        members: &[String],
+        shards: &[String],
+        _: &HashMap<String, Vec<String>>,
+    ) -> HashMap<String, Vec<String>> {
+        let mut results = empty_assignment(members);
+        if members.is_empty() {
+            return results;
+        }
+
+        let per_member = shards.len() / members.len();
+        let extras = shards.len() % members.len();
+        let mut start = 0;
+        for (i, member) in members.iter().enumerate() {
+            let num = per_member + if i < extras { 1 } else { 0 };
+            results.insert(member.clone(), shards[start..start + num].to_vec());
+            start += num;
+        }
+        results
+    }
+}
+
+/// Shards are dealt to the members one at a time.
+pub struct RoundRobinAssignor {}
+
+impl ShardAssignor for RoundRobinAssignor {
+    fn assign(
+        &self,
+        members: &[String],
+        shards: &[String],
+        _: &HashMap<String, Vec<String>>,
+    ) -> HashMap<String, Vec<String>> {
+        let mut results = empty_assignment(members);
+        if members.is_empty() {
+            return results;
+        }
+
+        for (i, shard) in shards.iter().enumerate() {
+            if let Some(list) = results.get_mut(&members[i % members.len()]) {
+                list.push(shard.clone());
+            }
+        }
+        results
+    }
+}
+
+/// As balanced as round-robin, but members keep the shards they had in the previous generation
+/// where possible, so a rebalance moves as few shards as it can.
+pub struct StickyAssignor {}
+
+impl ShardAssignor for StickyAssignor {
+    fn assign(
+        &self,
+        members: &[String],
+        shards: &[String],
+        previous: &HashMap<String, Vec<String>>,
+    ) -> HashMap<String, Vec<String>> {
+        let mut results = empty_assignment(members);
+        if members.is_empty() {
+            return results;
+        }
+
+        // every member gets `quota` shards, `extras` of them one more
+        let quota = shards.len() / members.len();
+        let mut extras = shards.len() % members.len();
+        let available: HashSet<&String> = shards.iter().collect();
+        let mut taken: HashSet<String> = HashSet::new();
+
+        let mut keep = |member: &String, limit: usize, taken: &mut HashSet<String>| -> usize {
+            let mut kept = 0;
+            if let Some(prev) = previous.get(member) {
+                let list = results.get_mut(member).unwrap();
+                for shard in prev.iter() {
+                    if list.len() >= limit {
+                        break;
+                    }
+                    if available.contains(shard) && !taken.contains(shard) {
+                        taken.insert(shard.clone());
+                        list.push(shard.clone());
+                        kept += 1;
+                    }
+                }
+            }
+            kept
+        };
+
+        for member in members.iter() {
+            keep(member, quota, &mut taken);
+        }
+        for member in members.iter() {
+            if extras == 0 {
+                break;
+            }
+            if keep(member, quota + 1, &mut taken) > 0 {
+                extras -= 1;
+            }
+        }
+
+        let mut free = shards.iter().filter(|shard| !taken.contains(*shard));
+        for member in members.iter() {
+            let list = results.get_mut(member).unwrap();
+            while list.len() < quota {
+                if let Some(shard) = free.next() {
+                    list.push(shard.clone());
+                } else {
+                    break;
+                }
+            }
+        }
+        for member in members.iter() {
+            if extras == 0 {
+                break;
+            }
+            let list = results.get_mut(member).unwrap();
+            if list.len() == quota {
+                if let Some(shard) = free.next() {
+                    list.push(shard.clone());
+                    extras -= 1;
+                }
+            }
+        }
+
+        for list in results.values_mut() {
+            list.sort();
+        }
+        results
+    }
+}
+
+fn empty_assignment(members: &[String]) -> HashMap<String, Vec<String>> {
+    members
+        .iter()
+        .map(|member| (member.clone(), Vec::new()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use protocol::journal_server::journal_engine::AssignmentStrategy;
+
+    use super::build_assignor;
+
+    fn names(prefix: &str, num: usize) -> Vec<String> {
+        (0..num).map(|i| format!("{}{}", prefix, i)).collect()
+    }
+
+    #[test]
+    fn range_assign_test() {
+        let assignor = build_assignor(AssignmentStrategy::Range);
+        let res = assignor.assign(&names("m", 2), &names("s", 5), &HashMap::new());
+        assert_eq!(res.get("m0").unwrap(), &names("s", 3));
+        assert_eq!(
+            res.get("m1").unwrap(),
+            &vec!["s3".to_string(), "s4".to_string()]
+        );
+
+        let res = assignor.assign(&names("m", 3), &names("s", 1), &HashMap::new());
+        assert_eq!(res.get("m0").unwrap().len(), 1);
+        assert!(res.get("m2").unwrap().is_empty());
+    }
+
+    #[test]
+    fn round_robin_assign_test() {
+        let assignor = build_assignor(AssignmentStrategy::RoundRobin);
+        let res = assignor.assign(&names("m", 2), &names("s", 5), &HashMap::new());
+        assert_eq!(
+            res.get("m0").unwrap(),
+            &vec!["s0".to_string(), "s2".to_string(), "s4".to_string()]
+        );
+        assert_eq!(
+            res.get("m1").unwrap(),
+            &vec!["s1".to_string(), "s3".to_string()]
+        );
+        assert!(assignor
+            .assign(&[], &names("s", 5), &HashMap::new())
+            .is_empty());
+    }
+
+    #[test]
+    fn sticky_assign_test() {
+        let assignor = build_assignor(AssignmentStrategy::Sticky);
+        let shards = names("s", 6);
+        let first = assignor.assign(&names("m", 2), &shards, &HashMap::new());
+        assert_eq!(first.get("m0").unwrap().len(), 3);
+        assert_eq!(first.get("m1").unwrap().len(), 3);
+
+        // a third member takes one shard from each of the others
+        let second = assignor.assign(&names("m", 3), &shards, &first);
+        for member in names("m", 3) {
+            assert_eq!(second.get(&member).unwrap().len(), 2);
+        }
+        for member in ["m0", "m1"] {
+            for shard in second.get(member).unwrap() {
+                assert!(first.get(member).unwrap().contains(shard));
+            }
+        }
+
+        // when m0 leaves only its shards move
+        let third = assignor.assign(&["m1".to_string(), "m2".to_string()], &shards, &second);
+        for member in ["m1", "m2"] {
+            let list = third.get(member).unwrap();
+            assert_eq!(list.len(), 3);
+            for shard in second.get(member).unwrap() {
+                assert!(list.contains(shard));
+            }
+        }
+    }
+}
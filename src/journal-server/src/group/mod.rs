@@ -0,0 +1,453 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use assignor::build_assignor;
+use common_base::config::journal_server::journal_server_conf;
+use common_base::tools::{now_mills, unique_id};
+use dashmap::DashMap;
+use log::{debug, info};
+use metadata_struct::journal::group::group_coordinator_node;
+use metadata_struct::journal::shard::JournalShardStatus;
+use protocol::journal_server::journal_engine::{
+    AssignmentStrategy, CommitOffsetReqBody, HeartbeatReqBody, HeartbeatRespBody, JoinGroupReqBody,
+    JoinGroupRespBody, LeaveGroupReqBody,
+};
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+
+use crate::core::cache::CacheManager;
+use crate::core::error::JournalServerError;
+
+pub mod assignor;
+
+const DEFAULT_SESSION_TIMEOUT_MS: u64 = 10000;
+
+#[derive(Clone, Debug)]
+pub struct GroupMember {
+    pub member_id: String,
+    pub session_timeout_ms: u64,
+    pub last_heartbeat_time: u128,
+}
+
+/// The members of a consumer group and the shards assigned to them. Every change of the
+/// members or of the shards of the namespace starts a new generation.
+///
+/// A shard moves to its new member only after the previous owner has given it up: the
+/// owner stops reading it and commits its offset when it joins the new generation, and
+/// confirms that with its next heartbeat. Until then the shard is held back from the new
+/// member, so a shard is never read by two members at once.
+#[derive(Clone, Debug)]
+pub struct ConsumerGroup {
+    pub group_name: String,
+    pub namespace: String,
+    pub strategy: AssignmentStrategy,
+    pub generation_id: u64,
+    pub members: HashMap<String, GroupMember>,
+    // the shards of the namespace the assignment was built from, sorted
+    pub shards: Vec<String>,
+    // member id -> shard names
+    pub assignment: HashMap<String, Vec<String>>,
+    // shard name -> the member that was handed the shard and has not given it up yet
+    pub owners: HashMap<String, String>,
+}
+
+impl ConsumerGroup {
+    pub fn new(group_name: &str, namespace: &str, strategy: AssignmentStrategy) -> Self {
+        ConsumerGroup {
+            group_name: group_name.to_string(),
+            namespace: namespace.to_string(),
+            strategy,
+            generation_id: 0,
+            members: HashMap::new(),
+            shards: Vec::new(),
+            assignment: HashMap::new(),
+            owners: HashMap::new(),
+        }
+    }
+
+    pub fn rebalance(&mut self, shards: Vec<String>) {
+        let mut members: Vec<String> = self.members.keys().cloned().collect();
+        members.sort();
+        self.assignment = build_assignor(self.strategy).assign(&members, &shards, &self.assignment);
+        // members that are gone no longer read anything
+        self.owners
+            .retain(|shard, member_id| shards.contains(shard) && members.contains(member_id));
+        self.shards = shards;
+        self.generation_id += 1;
+        info!(
+            "Group {} rebalanced to generation {}, assignment: {:?}",
+            self.group_name, self.generation_id, self.assignment
+        );
+    }
+
+    /// The shards the member reads in this generation: its assignment without the shards
+    /// another member has not given up yet. The member becomes the owner of them.
+    pub fn claim_shards(&mut self, member_id: &str) -> Vec<String> {
+        let mut shards = Vec::new();
+        for shard in self.assignment.get(member_id).cloned().unwrap_or_default() {
+            match self.owners.get(&shard) {
+                Some(owner) if owner != member_id => {}
+                _ => {
+                    self.owners.insert(shard.clone(), member_id.to_string());
+                    shards.push(shard);
+                }
+            }
+        }
+        shards
+    }
+
+    /// Called when the member reports that it runs the current generation. The shards it
+    /// owns but is no longer assigned have been given up. When one of them is assigned to
+    /// another member, a new generation tells that member to join again and take it.
+    pub fn release_shards(&mut self, member_id: &str) -> bool {
+        let assigned = self.assignment.get(member_id).cloned().unwrap_or_default();
+        let released: Vec<String> = self
+            .owners
+            .iter()
+            .filter(|(shard, owner)| *owner == member_id && !assigned.contains(shard))
+            .map(|(shard, _)| shard.clone())
+            .collect();
+        if released.is_empty() {
+            return false;
+        }
+
+        for shard in released.iter() {
+            self.owners.remove(shard);
+        }
+        self.generation_id += 1;
+        info!(
+            "Member {} of group {} gave up shards {:?}, group moves to generation {}",
+            member_id, self.group_name, released, self.generation_id
+        );
+        true
+    }
+
+    /// Removes the members whose session has timed out, returns whether there were any.
+    pub fn expire_members(&mut self, now: u128) -> bool {
+        let before = self.members.len();
+        self.members.retain(|_, member| {
+            now.saturating_sub(member.last_heartbeat_time) <= member.session_timeout_ms as u128
+        });
+        self.members.len() != before
+    }
+}
+
+/// Coordinates the consumer groups whose coordinator is this node. Group state only lives in
+/// memory, when the coordinator changes the members join the new one again.
+pub struct GroupCoordinator {
+    cache_manager: Arc<CacheManager>,
+    groups: DashMap<String, ConsumerGroup>,
+}
+
+impl GroupCoordinator {
+    pub fn new(cache_manager: Arc<CacheManager>) -> Self {
+        GroupCoordinator {
+            cache_manager,
+            groups: DashMap::with_capacity(8),
+        }
+    }
+
+    pub fn join_group(
+        &self,
+        req_body: &JoinGroupReqBody,
+    ) -> Result<JoinGroupRespBody, JournalServerError> {
+        self.check_coordinator(&req_body.group_name)?;
+
+        let shards = self.namespace_shards(&req_body.namespace);
+        let mut group = self
+            .groups
+            .entry(req_body.group_name.clone())
+            .or_insert_with(|| {
+                ConsumerGroup::new(
+                    &req_body.group_name,
+                    &req_body.namespace,
+                    req_body.strategy(),
+                )
+            });
+
+        if group.namespace != req_body.namespace {
+            return Err(JournalServerError::GroupNamespaceMismatch(
+                req_body.group_name.clone(),
+                group.namespace.clone(),
+                req_body.namespace.clone(),
+            ));
+        }
+
+        // A known member id is accepted as is, it may come from a previous coordinator
+        let member_id = if req_body.member_id.is_empty() {
+            unique_id()
+        } else {
+            req_body.member_id.clone()
+        };
+        let session_timeout_ms = if req_body.session_timeout_ms > 0 {
+            req_body.session_timeout_ms
+        } else {
+            DEFAULT_SESSION_TIMEOUT_MS
+        };
+        let is_new_member = group
+            .members
+            .insert(
+                member_id.clone(),
+                GroupMember {
+                    member_id: member_id.clone(),
+                    session_timeout_ms,
+                    last_heartbeat_time: now_mills(),
+                },
+            )
+            .is_none();
+
+        if is_new_member || group.shards != shards {
+            group.rebalance(shards);
+        }
+
+        Ok(JoinGroupRespBody {
+            shards: group.claim_shards(&member_id),
+            member_id,
+            generation_id: group.generation_id,
+        })
+    }
+
+    pub fn heartbeat(
+        &self,
+        req_body: &HeartbeatReqBody,
+    ) -> Result<HeartbeatRespBody, JournalServerError> {
+        self.check_coordinator(&req_body.group_name)?;
+
+        let mut group = if let Some(group) = self.groups.get_mut(&req_body.group_name) {
+            group
+        } else {
+            return Err(JournalServerError::GroupMemberNotFound(
+                req_body.group_name.clone(),
+                req_body.member_id.clone(),
+            ));
+        };
+
+        if let Some(member) = group.members.get_mut(&req_body.member_id) {
+            member.last_heartbeat_time = now_mills();
+        } else {
+            return Err(JournalServerError::GroupMemberNotFound(
+                req_body.group_name.clone(),
+                req_body.member_id.clone(),
+            ));
+        }
+
+        if req_body.generation_id == group.generation_id {
+            group.release_shards(&req_body.member_id);
+        }
+
+        Ok(HeartbeatRespBody {
+            generation_id: group.generation_id,
+        })
+    }
+
+    /// Offsets committed by a member are only accepted from the current generation, a member
+    /// that missed a rebalance would otherwise overwrite the offsets of the new owner.
+    /// Commits without a member come from readers outside of a group.
+    pub fn check_offset_commit(
+        &self,
+        req_body: &CommitOffsetReqBody,
+    ) -> Result<(), JournalServerError> {
+        if req_body.member_id.is_empty() {
+            return Ok(());
+        }
+        self.check_coordinator(&req_body.group_name)?;
+
+        let group = if let Some(group) = self.groups.get(&req_body.group_name) {
+            group
+        } else {
+            return Err(JournalServerError::GroupMemberNotFound(
+                req_body.group_name.clone(),
+                req_body.member_id.clone(),
+            ));
+        };
+
+        if !group.members.contains_key(&req_body.member_id) {
+            return Err(JournalServerError::GroupMemberNotFound(
+                req_body.group_name.clone(),
+                req_body.member_id.clone(),
+            ));
+        }
+
+        if req_body.generation_id != group.generation_id {
+            return Err(JournalServerError::GroupGenerationNotMatch(
+                req_body.group_name.clone(),
+                group.generation_id,
+                req_body.generation_id,
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn leave_group(&self, req_body: &LeaveGroupReqBody) -> Result<(), JournalServerError> {
+        self.check_coordinator(&req_body.group_name)?;
+
+        let shards = self.namespace_shards_of_group(&req_body.group_name);
+        if let Some(mut group) = self.groups.get_mut(&req_body.group_name) {
+            if group.members.remove(&req_body.member_id).is_some() {
+                group.rebalance(shards);
+            }
+        }
+        self.groups
+            .remove_if(&req_body.group_name, |_, group| group.members.is_empty());
+        Ok(())
+    }
+
+    pub async fn start_group_check_thread(&self, stop_send: broadcast::Sender<bool>) {
+        info!("Consumer group check thread started successfully");
+        let mut stop_recv = stop_send.subscribe();
+        loop {
+            select! {
+                val = stop_recv.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            debug!("{}","Consumer group check thread exited successfully");
+                            break;
+                        }
+                    }
+                }
+                _ = sleep(Duration::from_secs(1)) => {
+                    self.check_groups();
+                }
+            }
+        }
+    }
+
+    // Expires members without heartbeat, follows changes of the namespace shards and drops
+    // the groups this node no longer coordinates.
+    fn check_groups(&self) {
+        let now = now_mills();
+        for mut group in self.groups.iter_mut() {
+            let shards = self.namespace_shards(&group.namespace);
+            if group.expire_members(now) || group.shards != shards {
+                group.rebalance(shards);
+            }
+        }
+
+        self.groups.retain(|group_name, group| {
+            !group.members.is_empty() && self.check_coordinator(group_name).is_ok()
+        });
+    }
+
+    fn check_coordinator(&self, group_name: &str) -> Result<(), JournalServerError> {
+        let conf = journal_server_conf();
+        let node_ids: Vec<u64> = self
+            .cache_manager
+            .all_node()
+            .iter()
+            .map(|node| node.node_id)
+            .collect();
+        let coordinator = group_coordinator_node(group_name, &node_ids).unwrap_or(conf.node_id);
+        if coordinator != conf.node_id {
+            return Err(JournalServerError::NotGroupCoordinator(
+                conf.node_id,
+                group_name.to_string(),
+                coordinator,
+            ));
+        }
+        Ok(())
+    }
+
+    fn namespace_shards_of_group(&self, group_name: &str) -> Vec<String> {
+        if let Some(group) = self.groups.get(group_name) {
+            return self.namespace_shards(&group.namespace);
+        }
+        Vec::new()
+    }
+
+    fn namespace_shards(&self, namespace: &str) -> Vec<String> {
+        let mut shards: Vec<String> = self
+            .cache_manager
+            .get_shards()
+            .into_iter()
+            .filter(|shard| shard.namespace == namespace && shard.status == JournalShardStatus::Run)
+            .map(|shard| shard.shard_name)
+            .collect();
+        shards.sort();
+        shards
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use protocol::journal_server::journal_engine::AssignmentStrategy;
+
+    use super::{ConsumerGroup, GroupMember};
+
+    #[test]
+    fn consumer_group_rebalance_test() {
+        let mut group = ConsumerGroup::new("g1", "n1", AssignmentStrategy::RoundRobin);
+        for (member_id, last_heartbeat_time) in [("m1", 1000), ("m2", 5000)] {
+            group.members.insert(
+                member_id.to_string(),
+                GroupMember {
+                    member_id: member_id.to_string(),
+                    session_timeout_ms: 3000,
+                    last_heartbeat_time,
+                },
+            );
+        }
+        let shards = vec!["s1".to_string(), "s2".to_string()];
+        group.rebalance(shards.clone());
+        assert_eq!(group.generation_id, 1);
+        assert_eq!(group.assignment.get("m1").unwrap(), &vec!["s1".to_string()]);
+        assert_eq!(group.assignment.get("m2").unwrap(), &vec!["s2".to_string()]);
+
+        // m1 misses its session timeout, m2 takes over its shards
+        assert!(!group.expire_members(4000));
+        assert!(group.expire_members(6000));
+        group.rebalance(shards.clone());
+        assert_eq!(group.generation_id, 2);
+        assert!(!group.assignment.contains_key("m1"));
+        assert_eq!(group.assignment.get("m2").unwrap(), &shards);
+    }
+
+    #[test]
+    fn consumer_group_hold_shards_test() {
+        let mut group = ConsumerGroup::new("g1", "n1", AssignmentStrategy::RoundRobin);
+        let shards = vec!["s1".to_string(), "s2".to_string()];
+        group.members.insert("m1".to_string(), build_member("m1"));
+        group.rebalance(shards.clone());
+        assert_eq!(group.claim_shards("m1"), shards);
+
+        // m2 joins, s2 moves to it once m1 has given it up
+        group.members.insert("m2".to_string(), build_member("m2"));
+        group.rebalance(shards.clone());
+        assert_eq!(group.generation_id, 2);
+        assert!(group.claim_shards("m2").is_empty());
+        assert_eq!(group.claim_shards("m1"), vec!["s1".to_string()]);
+        assert!(!group.release_shards("m2"));
+
+        assert!(group.release_shards("m1"));
+        assert_eq!(group.generation_id, 3);
+        assert_eq!(group.claim_shards("m2"), vec!["s2".to_string()]);
+        assert!(!group.release_shards("m1"));
+
+        // the shards of a member that left are free right away
+        group.members.remove("m2");
+        group.rebalance(shards.clone());
+        assert_eq!(group.claim_shards("m1"), shards);
+    }
+
+    fn build_member(member_id: &str) -> GroupMember {
+        GroupMember {
+            member_id: member_id.to_string(),
+            session_timeout_ms: 3000,
+            last_heartbeat_time: 0,
+        }
+    }
+}
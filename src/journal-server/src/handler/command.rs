@@ -19,19 +19,24 @@ use grpc_clients::pool::ClientPool;
 use log::{error, info};
 use protocol::journal_server::codec::JournalEnginePacket;
 use protocol::journal_server::journal_engine::{
-    ApiKey, ApiVersion, CreateShardResp, CreateShardRespBody, DeleteShardResp, DeleteShardRespBody,
-    FetchOffsetResp, FetchOffsetRespBody, GetClusterMetadataResp, GetClusterMetadataRespBody,
-    GetShardMetadataResp, GetShardMetadataRespBody, JournalEngineError, ReadResp, ReadRespBody,
-    RespHeader, WriteResp, WriteRespBody,
+    ApiKey, ApiVersion, CommitOffsetResp, CommitOffsetRespBody, CreateShardResp,
+    CreateShardRespBody, DeleteShardResp, DeleteShardRespBody, FetchOffsetResp,
+    FetchOffsetRespBody, GetClusterMetadataResp, GetClusterMetadataRespBody, GetShardMetadataResp,
+    GetShardMetadataRespBody, HeartbeatResp, HeartbeatRespBody, JoinGroupResp, JoinGroupRespBody,
+    JournalEngineError, LeaveGroupResp, LeaveGroupRespBody, ReadResp, ReadRespBody, RespHeader,
+    WriteResp, WriteRespBody,
 };
 use rocksdb_engine::RocksDBEngine;
 
 use super::cluster::ClusterHandler;
 use super::data::DataHandler;
+use super::group::GroupHandler;
 use super::shard::ShardHandler;
 use crate::core::cache::CacheManager;
 use crate::core::error::get_journal_server_code;
 use crate::core::offset::OffsetManager;
+use crate::group::GroupCoordinator;
+use crate::isr::IsrManager;
 use crate::segment::manager::SegmentFileManager;
 use crate::server::connection::NetworkConnection;
 use crate::server::connection_manager::ConnectionManager;
@@ -41,6 +46,7 @@ pub struct Command {
     cluster_handler: ClusterHandler,
     shard_handler: ShardHandler,
     data_handler: DataHandler,
+    group_handler: GroupHandler,
 }
 
 impl Command {
@@ -50,6 +56,8 @@ impl Command {
         offset_manager: Arc<OffsetManager>,
         segment_file_manager: Arc<SegmentFileManager>,
         rocksdb_engine_handler: Arc<RocksDBEngine>,
+        isr_manager: Arc<IsrManager>,
+        group_coordinator: Arc<GroupCoordinator>,
     ) -> Self {
         let cluster_handler = ClusterHandler::new(cache_manager.clone());
         let shard_handler = ShardHandler::new(cache_manager.clone(), client_pool.clone());
@@ -59,11 +67,14 @@ impl Command {
             segment_file_manager,
             rocksdb_engine_handler,
             client_pool,
+            isr_manager,
         );
+        let group_handler = GroupHandler::new(group_coordinator);
         Command {
             cluster_handler,
             shard_handler,
             data_handler,
+            group_handler,
         }
     }
 
@@ -240,6 +251,103 @@ impl Command {
                 return Some(JournalEnginePacket::FetchOffsetResp(resp));
             }
 
+            JournalEnginePacket::CommitOffsetReq(request) => {
+                let mut resp = CommitOffsetResp::default();
+                let mut header = RespHeader {
+                    api_key: ApiKey::CommitOffset.into(),
+                    api_version: ApiVersion::V0.into(),
+                    ..Default::default()
+                };
+                let result = match self.group_handler.check_offset_commit(&request) {
+                    Ok(()) => self.data_handler.commit_offset(request).await,
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(()) => {
+                        resp.body = Some(CommitOffsetRespBody {});
+                    }
+                    Err(e) => {
+                        header.error = Some(JournalEngineError {
+                            code: get_journal_server_code(&e),
+                            error: e.to_string(),
+                        });
+                        resp.body = Some(CommitOffsetRespBody::default());
+                    }
+                }
+                resp.header = Some(header);
+                return Some(JournalEnginePacket::CommitOffsetResp(resp));
+            }
+
+            /* Group Handler */
+            JournalEnginePacket::JoinGroupReq(request) => {
+                let mut resp = JoinGroupResp::default();
+                let mut header = RespHeader {
+                    api_key: ApiKey::JoinGroup.into(),
+                    api_version: ApiVersion::V0.into(),
+                    ..Default::default()
+                };
+                match self.group_handler.join_group(request) {
+                    Ok(data) => {
+                        resp.body = Some(data);
+                    }
+                    Err(e) => {
+                        header.error = Some(JournalEngineError {
+                            code: get_journal_server_code(&e),
+                            error: e.to_string(),
+                        });
+                        resp.body = Some(JoinGroupRespBody::default());
+                    }
+                }
+                resp.header = Some(header);
+                return Some(JournalEnginePacket::JoinGroupResp(resp));
+            }
+
+            JournalEnginePacket::HeartbeatReq(request) => {
+                let mut resp = HeartbeatResp::default();
+                let mut header = RespHeader {
+                    api_key: ApiKey::Heartbeat.into(),
+                    api_version: ApiVersion::V0.into(),
+                    ..Default::default()
+                };
+                match self.group_handler.heartbeat(request) {
+                    Ok(data) => {
+                        resp.body = Some(data);
+                    }
+                    Err(e) => {
+                        header.error = Some(JournalEngineError {
+                            code: get_journal_server_code(&e),
+                            error: e.to_string(),
+                        });
+                        resp.body = Some(HeartbeatRespBody::default());
+                    }
+                }
+                resp.header = Some(header);
+                return Some(JournalEnginePacket::HeartbeatResp(resp));
+            }
+
+            JournalEnginePacket::LeaveGroupReq(request) => {
+                let mut resp = LeaveGroupResp::default();
+                let mut header = RespHeader {
+                    api_key: ApiKey::LeaveGroup.into(),
+                    api_version: ApiVersion::V0.into(),
+                    ..Default::default()
+                };
+                match self.group_handler.leave_group(request) {
+                    Ok(()) => {
+                        resp.body = Some(LeaveGroupRespBody {});
+                    }
+                    Err(e) => {
+                        header.error = Some(JournalEngineError {
+                            code: get_journal_server_code(&e),
+                            error: e.to_string(),
+                        });
+                        resp.body = Some(LeaveGroupRespBody::default());
+                    }
+                }
+                resp.header = Some(header);
+                return Some(JournalEnginePacket::LeaveGroupResp(resp));
+            }
+
             _ => {
                 error!(
                     "server received an unrecognized request, request info: {:?}",
@@ -13,19 +13,23 @@
 // limitations under the License.
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use common_base::config::journal_server::journal_server_conf;
 use grpc_clients::pool::ClientPool;
 use protocol::journal_server::journal_engine::{
-    AutoOffsetStrategy, FetchOffsetReq, FetchOffsetRespBody, FetchOffsetShard,
-    FetchOffsetShardMeta, ReadReq, ReadRespSegmentMessage, WriteReq, WriteRespMessage,
+    AutoOffsetStrategy, CommitOffsetReq, FetchOffsetReq, FetchOffsetRespBody, FetchOffsetShard,
+    FetchOffsetShardMeta, ReadReq, ReadRespSegmentMessage, ReadType, WriteReq, WriteRespMessage,
 };
 use rocksdb_engine::RocksDBEngine;
+use tokio::time::Instant;
 
 use crate::core::cache::CacheManager;
 use crate::core::error::JournalServerError;
-use crate::core::offset::OffsetManager;
+use crate::core::offset::{Offset, OffsetManager};
+use crate::core::segment::segment_validator;
 use crate::index::time::TimestampIndexManager;
+use crate::isr::IsrManager;
 use crate::segment::manager::SegmentFileManager;
 use crate::segment::read::read_data_req;
 use crate::segment::write::write_data_req;
@@ -38,6 +42,7 @@ pub struct DataHandler {
     segment_file_manager: Arc<SegmentFileManager>,
     rocksdb_engine_handler: Arc<RocksDBEngine>,
     client_pool: Arc<ClientPool>,
+    isr_manager: Arc<IsrManager>,
 }
 
 impl DataHandler {
@@ -47,6 +52,7 @@ impl DataHandler {
         segment_file_manager: Arc<SegmentFileManager>,
         rocksdb_engine_handler: Arc<RocksDBEngine>,
         client_pool: Arc<ClientPool>,
+        isr_manager: Arc<IsrManager>,
     ) -> DataHandler {
         DataHandler {
             cache_manager,
@@ -54,6 +60,7 @@ impl DataHandler {
             segment_file_manager,
             rocksdb_engine_handler,
             client_pool,
+            isr_manager,
         }
     }
 
@@ -66,23 +73,15 @@ impl DataHandler {
         }
 
         let req_body = request.body.unwrap();
-        for message in req_body.data.iter() {
-            let segment_identity = SegmentIdentity {
-                namespace: message.namespace.to_string(),
-                shard_name: message.shard_name.to_string(),
-                segment_seq: message.segment,
-            };
-            self.validator(&segment_identity)?;
-        }
-
         let results = write_data_req(
             &self.cache_manager,
             &self.rocksdb_engine_handler,
             &self.segment_file_manager,
             &self.client_pool,
+            &self.isr_manager,
             &req_body,
         )
-        .await?;
+        .await;
         Ok(results)
     }
 
@@ -105,15 +104,76 @@ impl DataHandler {
             self.validator(&segment_identity)?;
         }
 
-        let conf = journal_server_conf();
-        let results = read_data_req(
-            &self.cache_manager,
-            &self.rocksdb_engine_handler,
-            &req_body,
-            conf.node_id,
-        )
-        .await?;
-        Ok(results)
+        // A long-poll read that finds no records waits for the high watermark of one of its
+        // segments to move and reads again, until max_wait_ms has passed
+        let max_wait_ms = req_body
+            .messages
+            .iter()
+            .filter_map(|raw| raw.options.as_ref())
+            .map(|options| options.max_wait_ms)
+            .max()
+            .unwrap_or(0)
+            .min(conf.network.max_read_wait_ms);
+        let deadline = match Instant::now().checked_add(Duration::from_millis(max_wait_ms)) {
+            Some(deadline) => deadline,
+            None => Instant::now(),
+        };
+
+        loop {
+            let mut results = read_data_req(
+                &self.cache_manager,
+                &self.rocksdb_engine_handler,
+                &req_body,
+                conf.node_id,
+            )
+            .await?;
+
+            // Records above the high watermark are not on all in-sync replicas yet and may be lost.
+            // A sealed segment no longer changes, every record in it is committed.
+            let mut wait_thresholds = Vec::new();
+            for (segment_message, raw) in results.iter_mut().zip(req_body.messages.iter()) {
+                let segment_identity = SegmentIdentity::new(
+                    &segment_message.namespace,
+                    &segment_message.shard_name,
+                    segment_message.segment,
+                );
+                if self
+                    .cache_manager
+                    .get_segment(&segment_identity)
+                    .is_some_and(|segment| segment.is_seal_up())
+                {
+                    continue;
+                }
+
+                self.isr_manager.advance_high_watermark(&segment_identity);
+                let high_watermark = self.isr_manager.get_high_watermark(&segment_identity);
+                segment_message
+                    .messages
+                    .retain(|message| message.offset as i64 <= high_watermark);
+
+                let threshold = match (raw.ready_type(), raw.filter.as_ref()) {
+                    (ReadType::Offset, Some(filter)) => {
+                        high_watermark.max(filter.offset as i64 - 1)
+                    }
+                    _ => high_watermark,
+                };
+                wait_thresholds.push((segment_identity, threshold));
+            }
+
+            if max_wait_ms == 0 || results.iter().any(|raw| !raw.messages.is_empty()) {
+                return Ok(results);
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero()
+                || !self
+                    .isr_manager
+                    .wait_high_watermark_above(&wait_thresholds, remaining)
+                    .await
+            {
+                return Ok(results);
+            }
+        }
     }
 
     pub async fn fetch_offset(
@@ -127,6 +187,32 @@ impl DataHandler {
         }
         let req_body = request.body.unwrap();
         let group_name = req_body.group_name.clone();
+
+        if req_body.committed {
+            let conf = journal_server_conf();
+            let committed = self
+                .offset_manager
+                .get_committed_offsets(&conf.cluster_name, &group_name)
+                .await?;
+            let shard_offsets = committed
+                .into_iter()
+                .filter(|offset| {
+                    req_body.shards.iter().any(|shard| {
+                        shard.namespace == offset.namespace && shard.shard_name == offset.shard_name
+                    })
+                })
+                .map(|offset| FetchOffsetShardMeta {
+                    namespace: offset.namespace,
+                    shard_name: offset.shard_name,
+                    offset: offset.offset,
+                })
+                .collect();
+            return Ok(FetchOffsetRespBody {
+                group_name,
+                shard_offsets,
+            });
+        }
+
         let strategy = req_body.auto_offset_strategy();
         let mut meta_list = Vec::new();
         for shard in req_body.shards {
@@ -146,6 +232,28 @@ impl DataHandler {
         })
     }
 
+    pub async fn commit_offset(&self, request: CommitOffsetReq) -> Result<(), JournalServerError> {
+        if request.body.is_none() {
+            return Err(JournalServerError::RequestBodyNotEmpty(
+                "commit_offset".to_string(),
+            ));
+        }
+        let req_body = request.body.unwrap();
+        let conf = journal_server_conf();
+        let offsets = req_body
+            .offsets
+            .into_iter()
+            .map(|offset| Offset {
+                namespace: offset.namespace,
+                shard_name: offset.shard_name,
+                offset: offset.offset,
+            })
+            .collect();
+        self.offset_manager
+            .commit_offset(&conf.cluster_name, &req_body.group_name, offsets)
+            .await
+    }
+
     async fn get_offset_by_timestamp(
         &self,
         shard: &FetchOffsetShard,
@@ -177,34 +285,6 @@ impl DataHandler {
     }
 
     fn validator(&self, segment_identity: &SegmentIdentity) -> Result<(), JournalServerError> {
-        if self
-            .cache_manager
-            .get_shard(&segment_identity.namespace, &segment_identity.shard_name)
-            .is_none()
-        {
-            return Err(JournalServerError::ShardNotExist(
-                segment_identity.shard_name.to_string(),
-            ));
-        }
-
-        let segment = if let Some(segment) = self.cache_manager.get_segment(segment_identity) {
-            segment
-        } else {
-            return Err(JournalServerError::SegmentNotExist(segment_identity.name()));
-        };
-
-        if !segment.allow_read() {
-            return Err(JournalServerError::SegmentStatusError(
-                segment_identity.name(),
-                segment.status.to_string(),
-            ));
-        }
-
-        let conf = journal_server_conf();
-        if segment.leader != conf.node_id {
-            return Err(JournalServerError::NotLeader(segment_identity.name()));
-        }
-
-        Ok(())
+        segment_validator(&self.cache_manager, segment_identity)
     }
 }
@@ -0,0 +1,76 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::sync::Arc;
+
+use protocol::journal_server::journal_engine::{
+    CommitOffsetReq, HeartbeatReq, HeartbeatRespBody, JoinGroupReq, JoinGroupRespBody,
+    LeaveGroupReq,
+};
+
+use crate::core::error::JournalServerError;
+use crate::group::GroupCoordinator;
+
+#[derive(Clone)]
+pub struct GroupHandler {
+    group_coordinator: Arc<GroupCoordinator>,
+}
+
+impl GroupHandler {
+    pub fn new(group_coordinator: Arc<GroupCoordinator>) -> GroupHandler {
+        GroupHandler { group_coordinator }
+    }
+
+    pub fn join_group(
+        &self,
+        request: JoinGroupReq,
+    ) -> Result<JoinGroupRespBody, JournalServerError> {
+        if request.body.is_none() {
+            return Err(JournalServerError::RequestBodyNotEmpty(
+                "join_group".to_string(),
+            ));
+        }
+        let req_body = request.body.unwrap();
+        self.group_coordinator.join_group(&req_body)
+    }
+
+    pub fn heartbeat(
+        &self,
+        request: HeartbeatReq,
+    ) -> Result<HeartbeatRespBody, JournalServerError> {
+        if request.body.is_none() {
+            return Err(JournalServerError::RequestBodyNotEmpty(
+                "heartbeat".to_string(),
+            ));
+        }
+        let req_body = request.body.unwrap();
+        self.group_coordinator.heartbeat(&req_body)
+    }
+
+    pub fn check_offset_commit(&self, request: &CommitOffsetReq) -> Result<(), JournalServerError> {
+        if let Some(req_body) = &request.body {
+            return self.group_coordinator.check_offset_commit(req_body);
+        }
+        Ok(())
+    }
+
+    pub fn leave_group(&self, request: LeaveGroupReq) -> Result<(), JournalServerError> {
+        if request.body.is_none() {
+            return Err(JournalServerError::RequestBodyNotEmpty(
+                "leave_group".to_string(),
+            ));
+        }
+        let req_body = request.body.unwrap();
+        self.group_coordinator.leave_group(&req_body)
+    }
+}
@@ -15,4 +15,5 @@
 pub mod cluster;
 pub mod command;
 pub mod data;
+pub mod group;
 pub mod shard;
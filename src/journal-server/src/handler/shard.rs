@@ -63,6 +63,7 @@ impl ShardHandler {
                 namespace: req_body.namespace.to_string(),
                 shard_name: req_body.shard_name.to_string(),
                 replica: req_body.replica_num,
+                shard_config: req_body.shard_config,
             };
             let reply = grpc_clients::placement::journal::call::create_shard(
                 self.client_pool.clone(),
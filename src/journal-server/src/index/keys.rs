@@ -110,3 +110,10 @@ pub(crate) fn segment_index_prefix(segment_iden: &SegmentIdentity) -> String {
         segment_iden.namespace, segment_iden.shard_name, segment_iden.segment_seq,
     )
 }
+
+pub(crate) fn high_watermark_checkpoint(segment_iden: &SegmentIdentity) -> String {
+    format!(
+        "/index/{}/{}/{}/isr/high_watermark",
+        segment_iden.namespace, segment_iden.shard_name, segment_iden.segment_seq,
+    )
+}
@@ -0,0 +1,67 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use rocksdb_engine::engine::{rocksdb_engine_get, rocksdb_engine_save};
+use rocksdb_engine::RocksDBEngine;
+use serde::{Deserialize, Serialize};
+
+use crate::core::consts::DB_COLUMN_FAMILY_INDEX;
+use crate::core::error::JournalServerError;
+use crate::index::keys::high_watermark_checkpoint;
+use crate::segment::SegmentIdentity;
+
+/// The high watermark of a segment and the leader epoch it was learned in. A follower that
+/// finds a newer epoch in the segment metadata truncates its local data to this high
+/// watermark before it fetches from the new leader.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HighWatermarkCheckpoint {
+    pub leader_epoch: u32,
+    pub high_watermark: i64,
+}
+
+pub fn save_high_watermark_checkpoint(
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    segment_iden: &SegmentIdentity,
+    checkpoint: HighWatermarkCheckpoint,
+) -> Result<(), JournalServerError> {
+    let key = high_watermark_checkpoint(segment_iden);
+    Ok(rocksdb_engine_save(
+        rocksdb_engine_handler.clone(),
+        DB_COLUMN_FAMILY_INDEX,
+        key,
+        checkpoint,
+    )?)
+}
+
+/// Segments without a checkpoint have not committed anything yet, in the first leader epoch.
+pub fn get_high_watermark_checkpoint(
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    segment_iden: &SegmentIdentity,
+) -> Result<HighWatermarkCheckpoint, JournalServerError> {
+    let key = high_watermark_checkpoint(segment_iden);
+    if let Some(res) =
+        rocksdb_engine_get(rocksdb_engine_handler.clone(), DB_COLUMN_FAMILY_INDEX, key)?
+    {
+        return Ok(serde_json::from_slice::<HighWatermarkCheckpoint>(
+            &res.data,
+        )?);
+    }
+
+    Ok(HighWatermarkCheckpoint {
+        leader_epoch: 0,
+        high_watermark: -1,
+    })
+}
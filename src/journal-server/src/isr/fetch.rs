@@ -11,3 +11,426 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use common_base::config::journal_server::journal_server_conf;
+use grpc_clients::journal::inner::call::journal_inner_fetch_segment_data;
+use grpc_clients::pool::ClientPool;
+use log::{debug, error, info, warn};
+use metadata_struct::journal::segment::{JournalSegment, SegmentStatus};
+use protocol::journal_server::journal_inner::{FetchSegmentDataReply, FetchSegmentDataRequest};
+use rocksdb_engine::RocksDBEngine;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+
+use super::checkpoint::HighWatermarkCheckpoint;
+use super::IsrManager;
+use crate::core::cache::CacheManager;
+use crate::core::error::JournalServerError;
+use crate::index::build::try_trigger_build_index;
+use crate::index::offset::OffsetIndexManager;
+use crate::index::time::TimestampIndexManager;
+use crate::segment::batch::{decode_batch_records, RecordBatch};
+use crate::segment::file::{
+    data_file_segment, open_segment_write, truncate_segment_file, SegmentFile,
+};
+use crate::segment::manager::{rebuild_segment_index, SegmentFileManager, SegmentFileMetadata};
+use crate::segment::producer::ProducerStateManager;
+use crate::segment::SegmentIdentity;
+
+/// Serves a follower fetch on the segment leader, returning the batches from the one that
+/// contains `fetch_offset` on, exactly as they are stored in the segment file.
+pub async fn fetch_segment_data_req(
+    cache_manager: &Arc<CacheManager>,
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    isr_manager: &Arc<IsrManager>,
+    req: &FetchSegmentDataRequest,
+) -> Result<FetchSegmentDataReply, JournalServerError> {
+    let conf = journal_server_conf();
+    let segment_iden = SegmentIdentity::new(&req.namespace, &req.shard_name, req.segment);
+    let segment = if let Some(segment) = cache_manager.get_segment(&segment_iden) {
+        segment
+    } else {
+        return Err(JournalServerError::SegmentNotExist(segment_iden.name()));
+    };
+
+    if segment.leader != conf.node_id {
+        return Err(JournalServerError::NotLeader(segment_iden.name()));
+    }
+
+    if segment.leader_epoch != req.leader_epoch {
+        return Err(JournalServerError::LeaderEpochNotMatch(
+            segment_iden.name(),
+            segment.leader_epoch,
+            req.leader_epoch,
+        ));
+    }
+
+    if segment.get_fold(req.follower_id).is_none() {
+        return Err(JournalServerError::NotSegmentReplica(
+            segment_iden.name(),
+            req.follower_id,
+        ));
+    }
+
+    isr_manager.record_fetch(&segment_iden, req.follower_id, req.fetch_offset);
+
+    let (segment_file, _) = open_segment_write(cache_manager, &segment_iden).await?;
+    let segment_file_lock = cache_manager.get_segment_file_lock(&segment_iden);
+    let _guard = segment_file_lock.read().await;
+    let offset_index = OffsetIndexManager::new(rocksdb_engine_handler.clone());
+    let start_position = offset_index
+        .get_last_nearest_position_by_offset(&segment_iden, req.fetch_offset)
+        .await?;
+
+    let batches = segment_file
+        .read_batches(start_position, req.fetch_offset, req.max_size)
+        .await?;
+
+    Ok(FetchSegmentDataReply {
+        leader_epoch: segment.leader_epoch,
+        high_watermark: isr_manager.get_high_watermark(&segment_iden),
+        batches: batches.iter().map(|batch| batch.encode()).collect(),
+    })
+}
+
+/// Starts a fetch thread for every segment this node follows, and stops them when the
+/// segment is deleted or this node becomes its leader.
+pub async fn start_replica_fetch_thread(
+    cache_manager: Arc<CacheManager>,
+    segment_file_manager: Arc<SegmentFileManager>,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    client_pool: Arc<ClientPool>,
+    isr_manager: Arc<IsrManager>,
+    stop_send: broadcast::Sender<bool>,
+) {
+    info!("Replica fetch thread started successfully");
+    let mut stop_recv = stop_send.subscribe();
+    loop {
+        select! {
+            val = stop_recv.recv() =>{
+                if let Ok(flag) = val {
+                    if flag {
+                        debug!("{}","Replica fetch thread exited successfully");
+                        break;
+                    }
+                }
+            }
+            _ = sleep(Duration::from_secs(1)) => {
+                for segment in cache_manager.get_follower_segments() {
+                    let segment_iden = SegmentIdentity::from_journal_segment(&segment);
+                    if cache_manager.contain_fetch_thread(&segment_iden)
+                        || !is_need_fetch(&cache_manager, &segment_file_manager, &segment)
+                    {
+                        continue;
+                    }
+
+                    let (fetch_stop_send, fetch_stop_recv) = broadcast::channel::<bool>(1);
+                    cache_manager.add_fetch_thread(&segment_iden, fetch_stop_send);
+                    start_segment_fetch_thread(
+                        cache_manager.clone(),
+                        segment_file_manager.clone(),
+                        rocksdb_engine_handler.clone(),
+                        client_pool.clone(),
+                        isr_manager.clone(),
+                        segment_iden,
+                        fetch_stop_recv,
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn start_segment_fetch_thread(
+    cache_manager: Arc<CacheManager>,
+    segment_file_manager: Arc<SegmentFileManager>,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    client_pool: Arc<ClientPool>,
+    isr_manager: Arc<IsrManager>,
+    segment_iden: SegmentIdentity,
+    mut stop_recv: broadcast::Receiver<bool>,
+) {
+    tokio::spawn(async move {
+        let conf = journal_server_conf();
+        let backoff = Duration::from_millis(conf.replication.replica_fetch_backoff_ms);
+        info!(
+            "Segment {} starts fetching data from its leader",
+            segment_iden.name()
+        );
+        loop {
+            select! {
+                val = stop_recv.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            break;
+                        }
+                    }
+                },
+                val = fetch_and_append(
+                    &cache_manager,
+                    &segment_file_manager,
+                    &rocksdb_engine_handler,
+                    &client_pool,
+                    &isr_manager,
+                    &segment_iden,
+                ) => {
+                    match val {
+                        Ok(Some(_)) => {},
+                        Ok(None) => {
+                            let segment = cache_manager.get_segment(&segment_iden);
+                            if segment.is_none()
+                                || !is_need_fetch(&cache_manager, &segment_file_manager, &segment.unwrap())
+                            {
+                                break;
+                            }
+                            sleep(backoff).await;
+                        }
+                        Err(e) => {
+                            error!(
+                                "Segment {} failed to fetch data from the leader, error message: {}",
+                                segment_iden.name(),
+                                e
+                            );
+                            sleep(backoff).await;
+                        }
+                    }
+                }
+            }
+        }
+        cache_manager.remove_fetch_thread(&segment_iden);
+        info!(
+            "Segment {} stops fetching data from its leader",
+            segment_iden.name()
+        );
+    });
+}
+
+/// Fetches the next record batches from the segment leader and appends them to the local
+/// segment file. Returns the new local end offset, or None when nothing was fetched.
+async fn fetch_and_append(
+    cache_manager: &Arc<CacheManager>,
+    segment_file_manager: &Arc<SegmentFileManager>,
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    client_pool: &Arc<ClientPool>,
+    isr_manager: &Arc<IsrManager>,
+    segment_iden: &SegmentIdentity,
+) -> Result<Option<u64>, JournalServerError> {
+    let conf = journal_server_conf();
+    let segment = if let Some(segment) = cache_manager.get_segment(segment_iden) {
+        segment
+    } else {
+        return Err(JournalServerError::SegmentNotExist(segment_iden.name()));
+    };
+
+    // this node has been elected as the leader of the segment
+    if segment.leader == conf.node_id {
+        return Ok(None);
+    }
+
+    truncate_to_high_watermark(
+        cache_manager,
+        segment_file_manager,
+        rocksdb_engine_handler,
+        isr_manager,
+        &segment,
+        segment_iden,
+    )
+    .await?;
+
+    let leader = if let Some(node) = cache_manager.get_node(segment.leader) {
+        node
+    } else {
+        return Err(JournalServerError::NodeNotExist(segment.leader));
+    };
+
+    let segment_file_meta =
+        if let Some(segment_file) = segment_file_manager.get_segment_file(segment_iden) {
+            segment_file
+        } else {
+            return Err(JournalServerError::SegmentFileMetaNotExists(
+                segment_iden.name(),
+            ));
+        };
+
+    let fetch_offset = (segment_file_meta.end_offset + 1).max(0) as u64;
+    let request = FetchSegmentDataRequest {
+        cluster_name: conf.cluster_name.clone(),
+        namespace: segment_iden.namespace.clone(),
+        shard_name: segment_iden.shard_name.clone(),
+        segment: segment_iden.segment_seq,
+        follower_id: conf.node_id,
+        leader_epoch: segment.leader_epoch,
+        fetch_offset,
+        max_size: conf.replication.replica_fetch_max_bytes,
+    };
+    let reply =
+        journal_inner_fetch_segment_data(client_pool.clone(), &[leader.node_inner_addr], request)
+            .await?;
+
+    // the leader changed while the request was in flight
+    if reply.leader_epoch != segment.leader_epoch {
+        return Err(JournalServerError::LeaderEpochNotMatch(
+            segment_iden.name(),
+            segment.leader_epoch,
+            reply.leader_epoch,
+        ));
+    }
+
+    isr_manager.update_high_watermark(segment_iden, reply.leader_epoch, reply.high_watermark);
+
+    // batches are appended as a whole, so the first one must start right at the local end
+    let mut batches = Vec::new();
+    for raw in reply.batches {
+        let batch = RecordBatch::decode(raw.as_ref(), &segment_iden.name(), 0)?;
+        if batch.header.base_offset >= fetch_offset {
+            batches.push(batch);
+        }
+    }
+
+    if batches.is_empty() {
+        return Ok(None);
+    }
+
+    let (segment_file, _) = open_segment_write(cache_manager, segment_iden).await?;
+    append_batches(segment_file_manager, &segment_file, segment_iden, &batches).await?;
+
+    // keep the producer states in step with the leader, this node may become the next one
+    let producer_state_manager =
+        ProducerStateManager::new(cache_manager.clone(), rocksdb_engine_handler.clone());
+    for batch in batches.iter() {
+        let records = decode_batch_records(&batch.header, &batch.payload)?;
+        producer_state_manager.update(
+            &segment_iden.namespace,
+            &segment_iden.shard_name,
+            &records,
+        )?;
+    }
+
+    try_trigger_build_index(
+        cache_manager,
+        segment_file_manager,
+        rocksdb_engine_handler,
+        segment_iden,
+    )
+    .await;
+
+    Ok(batches.last().map(|batch| batch.header.last_offset()))
+}
+
+/// The records above the high watermark were never committed and the new leader of the segment
+/// may not have them, so a follower drops them before it fetches in a new leader epoch.
+async fn truncate_to_high_watermark(
+    cache_manager: &Arc<CacheManager>,
+    segment_file_manager: &Arc<SegmentFileManager>,
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    isr_manager: &Arc<IsrManager>,
+    segment: &JournalSegment,
+    segment_iden: &SegmentIdentity,
+) -> Result<(), JournalServerError> {
+    let checkpoint = isr_manager.get_high_watermark_checkpoint(segment_iden)?;
+    if checkpoint.leader_epoch == segment.leader_epoch {
+        return Ok(());
+    }
+
+    let local_end_offset = segment_file_manager
+        .get_end_offset(segment_iden)
+        .unwrap_or(-1);
+    if local_end_offset > checkpoint.high_watermark {
+        let (segment_file, _) = open_segment_write(cache_manager, segment_iden).await?;
+        let file_path = data_file_segment(&segment_file.data_fold, segment_file.segment_no);
+        let recover_data =
+            truncate_segment_file(&segment_iden.name(), &file_path, checkpoint.high_watermark)?;
+        warn!(
+            "Segment {} truncated its data from offset {} to the high watermark {} in leader epoch {}",
+            segment_iden.name(),
+            local_end_offset,
+            recover_data.end_offset,
+            segment.leader_epoch
+        );
+
+        rebuild_segment_index(
+            rocksdb_engine_handler,
+            &OffsetIndexManager::new(rocksdb_engine_handler.clone()),
+            &TimestampIndexManager::new(rocksdb_engine_handler.clone()),
+            segment_iden,
+            &recover_data,
+        )?;
+        segment_file_manager.add_segment_file(SegmentFileMetadata {
+            namespace: segment_iden.namespace.clone(),
+            shard_name: segment_iden.shard_name.clone(),
+            segment_no: segment_iden.segment_seq,
+            start_offset: recover_data.start_offset,
+            end_offset: recover_data.end_offset,
+            start_timestamp: recover_data.start_timestamp,
+            end_timestamp: recover_data.end_timestamp,
+        });
+    }
+
+    isr_manager.reset_high_watermark(
+        segment_iden,
+        HighWatermarkCheckpoint {
+            leader_epoch: segment.leader_epoch,
+            high_watermark: checkpoint.high_watermark,
+        },
+    )
+}
+
+async fn append_batches(
+    segment_file_manager: &Arc<SegmentFileManager>,
+    segment_file: &SegmentFile,
+    segment_iden: &SegmentIdentity,
+    batches: &[RecordBatch],
+) -> Result<(), JournalServerError> {
+    let first_batch = &batches.first().unwrap().header;
+    let last_batch = &batches.last().unwrap().header;
+    let is_first_write = segment_file_manager
+        .get_segment_file(segment_iden)
+        .map(|meta| meta.start_offset < 0)
+        .unwrap_or(true);
+
+    segment_file.write(batches).await?;
+
+    if is_first_write {
+        segment_file_manager.update_start_offset(segment_iden, first_batch.base_offset as i64)?;
+        segment_file_manager.update_start_timestamp(segment_iden, first_batch.timestamp)?;
+    }
+    segment_file_manager.update_end_offset(segment_iden, last_batch.last_offset() as i64)?;
+    segment_file_manager.update_end_timestamp(segment_iden, last_batch.timestamp)?;
+    Ok(())
+}
+
+/// A follower keeps fetching until the segment is sealed and all of its data has been replicated.
+fn is_need_fetch(
+    cache_manager: &Arc<CacheManager>,
+    segment_file_manager: &Arc<SegmentFileManager>,
+    segment: &JournalSegment,
+) -> bool {
+    let conf = journal_server_conf();
+    if segment.leader == conf.node_id || segment.get_fold(conf.node_id).is_none() {
+        return false;
+    }
+
+    match segment.status {
+        SegmentStatus::Idle
+        | SegmentStatus::PreWrite
+        | SegmentStatus::PreDelete
+        | SegmentStatus::Deleting => false,
+        SegmentStatus::Write | SegmentStatus::PreSealUp => true,
+        SegmentStatus::SealUp => {
+            let segment_iden = SegmentIdentity::from_journal_segment(segment);
+            let end_offset = if let Some(meta) = cache_manager.get_segment_meta(&segment_iden) {
+                meta.end_offset
+            } else {
+                return false;
+            };
+            let local_end_offset = segment_file_manager
+                .get_end_offset(&segment_iden)
+                .unwrap_or(-1);
+            local_end_offset < end_offset
+        }
+    }
+}
@@ -12,4 +12,566 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashMap;
+use std::future::pending;
+use std::sync::Arc;
+use std::time::Duration;
+
+use checkpoint::{
+    get_high_watermark_checkpoint, save_high_watermark_checkpoint, HighWatermarkCheckpoint,
+};
+use common_base::config::journal_server::journal_server_conf;
+use common_base::tools::now_mills;
+use dashmap::DashMap;
+use futures::future::select_all;
+use grpc_clients::pool::ClientPool;
+use log::{debug, error, info};
+use metadata_struct::journal::segment::JournalSegment;
+use rocksdb_engine::RocksDBEngine;
+use tokio::select;
+use tokio::sync::{broadcast, watch};
+use tokio::time::{sleep, timeout};
+
+use crate::core::cache::CacheManager;
+use crate::core::error::JournalServerError;
+use crate::core::segment_meta::update_meta_isr;
+use crate::segment::manager::SegmentFileManager;
+use crate::segment::SegmentIdentity;
+
+pub mod checkpoint;
 pub mod fetch;
+
+/// Replication progress of a follower, as tracked by the segment leader.
+#[derive(Clone, Debug, Default)]
+pub struct ReplicaState {
+    // The offset of the next record the follower asked for
+    pub log_end_offset: u64,
+    pub last_fetch_time: u128,
+    pub last_caught_up_time: u128,
+}
+
+pub struct IsrManager {
+    cache_manager: Arc<CacheManager>,
+    client_pool: Arc<ClientPool>,
+    segment_file_manager: Arc<SegmentFileManager>,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    // segment name -> follower node id -> replica state, only maintained on the leader
+    replica_states: DashMap<String, DashMap<u64, ReplicaState>>,
+    // segment name -> high watermark, the last offset replicated to all in-sync replicas.
+    // The leader calculates it, followers learn it from the fetch response. Every change is
+    // checkpointed together with the leader epoch, see `HighWatermarkCheckpoint`.
+    high_watermarks: DashMap<String, watch::Sender<i64>>,
+}
+
+impl IsrManager {
+    pub fn new(
+        cache_manager: Arc<CacheManager>,
+        client_pool: Arc<ClientPool>,
+        segment_file_manager: Arc<SegmentFileManager>,
+        rocksdb_engine_handler: Arc<RocksDBEngine>,
+    ) -> Self {
+        IsrManager {
+            cache_manager,
+            client_pool,
+            segment_file_manager,
+            rocksdb_engine_handler,
+            replica_states: DashMap::with_capacity(8),
+            high_watermarks: DashMap::with_capacity(8),
+        }
+    }
+
+    /// Records the progress reported by a follower fetch and tries to advance the high watermark.
+    pub fn record_fetch(
+        &self,
+        segment_iden: &SegmentIdentity,
+        follower_id: u64,
+        fetch_offset: u64,
+    ) {
+        let now = now_mills();
+        let leader_leo = self.local_log_end_offset(segment_iden);
+        let states = self
+            .replica_states
+            .entry(segment_iden.name())
+            .or_insert_with(|| DashMap::with_capacity(2));
+        let mut state = states.entry(follower_id).or_insert_with(|| ReplicaState {
+            last_caught_up_time: now,
+            ..Default::default()
+        });
+        state.log_end_offset = fetch_offset;
+        state.last_fetch_time = now;
+        if fetch_offset >= leader_leo {
+            state.last_caught_up_time = now;
+        }
+        drop(state);
+        drop(states);
+
+        self.advance_high_watermark(segment_iden);
+    }
+
+    /// Only the leader calculates the high watermark, followers learn it from the fetch response.
+    pub fn advance_high_watermark(&self, segment_iden: &SegmentIdentity) {
+        let segment = if let Some(segment) = self.cache_manager.get_segment(segment_iden) {
+            segment
+        } else {
+            return;
+        };
+
+        if segment.leader != journal_server_conf().node_id {
+            return;
+        }
+
+        let states = self.replica_state_snapshot(segment_iden);
+        let high_watermark = calc_high_watermark(
+            &segment.isr,
+            segment.leader,
+            self.local_log_end_offset(segment_iden),
+            &states,
+        );
+        self.update_high_watermark(segment_iden, segment.leader_epoch, high_watermark);
+    }
+
+    pub fn update_high_watermark(
+        &self,
+        segment_iden: &SegmentIdentity,
+        leader_epoch: u32,
+        high_watermark: i64,
+    ) {
+        let modified = self
+            .high_watermark_sender(segment_iden)
+            .send_if_modified(|current| {
+                if high_watermark > *current {
+                    *current = high_watermark;
+                    return true;
+                }
+                false
+            });
+        if !modified {
+            return;
+        }
+
+        let checkpoint = HighWatermarkCheckpoint {
+            leader_epoch,
+            high_watermark,
+        };
+        if let Err(e) =
+            save_high_watermark_checkpoint(&self.rocksdb_engine_handler, segment_iden, checkpoint)
+        {
+            error!(
+                "Segment {} failed to checkpoint the high watermark {}, error message: {}",
+                segment_iden.name(),
+                high_watermark,
+                e
+            );
+        }
+    }
+
+    pub fn get_high_watermark(&self, segment_iden: &SegmentIdentity) -> i64 {
+        *self.high_watermark_sender(segment_iden).borrow()
+    }
+
+    /// Resets the high watermark after the local data was truncated to it in a new leader epoch.
+    pub fn reset_high_watermark(
+        &self,
+        segment_iden: &SegmentIdentity,
+        checkpoint: HighWatermarkCheckpoint,
+    ) -> Result<(), JournalServerError> {
+        save_high_watermark_checkpoint(
+            &self.rocksdb_engine_handler,
+            segment_iden,
+            checkpoint.clone(),
+        )?;
+        self.high_watermark_sender(segment_iden)
+            .send_replace(checkpoint.high_watermark);
+        Ok(())
+    }
+
+    pub fn get_high_watermark_checkpoint(
+        &self,
+        segment_iden: &SegmentIdentity,
+    ) -> Result<HighWatermarkCheckpoint, JournalServerError> {
+        get_high_watermark_checkpoint(&self.rocksdb_engine_handler, segment_iden)
+    }
+
+    // The high watermark starts from the checkpoint, so a restarted node does not hide
+    // records that were already committed.
+    fn high_watermark_sender(
+        &self,
+        segment_iden: &SegmentIdentity,
+    ) -> dashmap::mapref::one::RefMut<'_, String, watch::Sender<i64>> {
+        self.high_watermarks
+            .entry(segment_iden.name())
+            .or_insert_with(|| {
+                let high_watermark =
+                    match get_high_watermark_checkpoint(&self.rocksdb_engine_handler, segment_iden)
+                    {
+                        Ok(checkpoint) => checkpoint.high_watermark,
+                        Err(e) => {
+                            error!(
+                                "Segment {} failed to load the high watermark checkpoint, error message: {}",
+                                segment_iden.name(),
+                                e
+                            );
+                            -1
+                        }
+                    };
+                watch::channel(high_watermark).0
+            })
+    }
+
+    /// Blocks until `offset` has been replicated to every in-sync replica of the segment.
+    /// The write fails when this node stopped leading the segment in `leader_epoch` meanwhile,
+    /// a new leader may have truncated the records.
+    pub async fn wait_high_watermark(
+        &self,
+        segment_iden: &SegmentIdentity,
+        leader_epoch: u32,
+        offset: u64,
+        wait_timeout: Duration,
+    ) -> Result<(), JournalServerError> {
+        self.advance_high_watermark(segment_iden);
+
+        let mut receiver = self.high_watermark_sender(segment_iden).subscribe();
+
+        match timeout(
+            wait_timeout,
+            receiver.wait_for(|high_watermark| *high_watermark >= offset as i64),
+        )
+        .await
+        {
+            Ok(res) => {
+                res?;
+            }
+            Err(_) => {
+                return Err(JournalServerError::WriteAckTimeout(
+                    segment_iden.name(),
+                    offset,
+                    wait_timeout.as_millis(),
+                ));
+            }
+        }
+
+        self.check_leader_epoch(segment_iden, leader_epoch)?;
+
+        // The ISR may have shrunk while waiting, in which case fewer copies than required exist.
+        self.check_min_insync_replicas(segment_iden)
+    }
+
+    pub fn check_leader_epoch(
+        &self,
+        segment_iden: &SegmentIdentity,
+        leader_epoch: u32,
+    ) -> Result<(), JournalServerError> {
+        let segment = if let Some(segment) = self.cache_manager.get_segment(segment_iden) {
+            segment
+        } else {
+            return Err(JournalServerError::SegmentNotExist(segment_iden.name()));
+        };
+
+        if segment.leader != journal_server_conf().node_id {
+            return Err(JournalServerError::NotLeader(segment_iden.name()));
+        }
+
+        if segment.leader_epoch != leader_epoch {
+            return Err(JournalServerError::LeaderEpochNotMatch(
+                segment_iden.name(),
+                segment.leader_epoch,
+                leader_epoch,
+            ));
+        }
+        Ok(())
+    }
+
+    /// Waits until the high watermark of one of the segments rises above its threshold, returns
+    /// false when that does not happen within `wait_timeout`.
+    pub async fn wait_high_watermark_above(
+        &self,
+        thresholds: &[(SegmentIdentity, i64)],
+        wait_timeout: Duration,
+    ) -> bool {
+        let mut waits = Vec::new();
+        for (segment_iden, threshold) in thresholds.iter() {
+            let mut receiver = self.high_watermark_sender(segment_iden).subscribe();
+            let threshold = *threshold;
+            waits.push(Box::pin(async move {
+                if receiver
+                    .wait_for(|high_watermark| *high_watermark > threshold)
+                    .await
+                    .is_err()
+                {
+                    // the segment is gone, leave it to the timeout
+                    pending::<()>().await;
+                }
+            }));
+        }
+
+        if waits.is_empty() {
+            return false;
+        }
+        timeout(wait_timeout, select_all(waits)).await.is_ok()
+    }
+
+    pub fn check_min_insync_replicas(
+        &self,
+        segment_iden: &SegmentIdentity,
+    ) -> Result<(), JournalServerError> {
+        let segment = if let Some(segment) = self.cache_manager.get_segment(segment_iden) {
+            segment
+        } else {
+            return Err(JournalServerError::SegmentNotExist(segment_iden.name()));
+        };
+
+        let required = min_insync_replicas(segment.replicas.len());
+        if segment.isr.len() < required {
+            return Err(JournalServerError::NotEnoughInSyncReplicas(
+                segment_iden.name(),
+                segment.isr.len(),
+                required,
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn start_isr_check_thread(&self, stop_send: broadcast::Sender<bool>) {
+        info!("ISR check thread started successfully");
+        let mut stop_recv = stop_send.subscribe();
+        loop {
+            select! {
+                val = stop_recv.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            debug!("{}","ISR check thread exited successfully");
+                            break;
+                        }
+                    }
+                }
+                _ = sleep(Duration::from_secs(1)) => {
+                    self.check_isr().await;
+                }
+            }
+        }
+    }
+
+    async fn check_isr(&self) {
+        let conf = journal_server_conf();
+        let leader_segments = self.cache_manager.get_leader_segment();
+
+        // Clear the replica state of segments this node no longer leads
+        self.replica_states.retain(|name, _| {
+            leader_segments
+                .iter()
+                .any(|segment_iden| segment_iden.name() == *name)
+        });
+
+        let now = now_mills();
+        for segment_iden in leader_segments {
+            let mut segment = if let Some(segment) = self.cache_manager.get_segment(&segment_iden) {
+                segment
+            } else {
+                continue;
+            };
+
+            if segment.leader != conf.node_id || segment.replicas.len() <= 1 {
+                continue;
+            }
+
+            // Followers that have never fetched get a full lag window before leaving the ISR
+            let states = self
+                .replica_states
+                .entry(segment_iden.name())
+                .or_insert_with(|| DashMap::with_capacity(2));
+            for rep in segment.replicas.iter() {
+                if rep.node_id != segment.leader {
+                    states.entry(rep.node_id).or_insert_with(|| ReplicaState {
+                        last_caught_up_time: now,
+                        ..Default::default()
+                    });
+                }
+            }
+            drop(states);
+
+            let isr = calc_isr(
+                &segment,
+                &self.replica_state_snapshot(&segment_iden),
+                now,
+                conf.replication.replica_lag_time_max_ms as u128,
+            );
+            if isr == segment.isr {
+                continue;
+            }
+
+            info!(
+                "ISR of segment {} changes from {:?} to {:?}",
+                segment_iden.name(),
+                segment.isr,
+                isr
+            );
+
+            if let Err(e) = update_meta_isr(
+                self.client_pool.clone(),
+                &segment_iden,
+                segment.leader_epoch,
+                isr.clone(),
+            )
+            .await
+            {
+                error!(
+                    "Segment {} failed to report the ISR change to the placement center, error message: {}",
+                    segment_iden.name(),
+                    e
+                );
+                continue;
+            }
+
+            segment.isr = isr;
+            self.cache_manager.set_segment(segment);
+            self.advance_high_watermark(&segment_iden);
+        }
+    }
+
+    /// The offset of the next record that will be written to the local segment file.
+    fn local_log_end_offset(&self, segment_iden: &SegmentIdentity) -> u64 {
+        if let Some(end_offset) = self.segment_file_manager.get_end_offset(segment_iden) {
+            return (end_offset + 1).max(0) as u64;
+        }
+        0
+    }
+
+    fn replica_state_snapshot(&self, segment_iden: &SegmentIdentity) -> HashMap<u64, ReplicaState> {
+        let mut results = HashMap::new();
+        if let Some(states) = self.replica_states.get(&segment_iden.name()) {
+            for raw in states.iter() {
+                results.insert(*raw.key(), raw.value().clone());
+            }
+        }
+        results
+    }
+}
+
+/// The number of in-sync replicas required to acknowledge a write, a majority of the replicas.
+pub fn min_insync_replicas(replica_num: usize) -> usize {
+    replica_num / 2 + 1
+}
+
+/// The high watermark is the last offset that every ISR member has replicated, -1 if none.
+pub fn calc_high_watermark(
+    isr: &[u64],
+    leader: u64,
+    leader_leo: u64,
+    states: &HashMap<u64, ReplicaState>,
+) -> i64 {
+    let mut min_leo = leader_leo;
+    for node_id in isr {
+        if *node_id == leader {
+            continue;
+        }
+        let leo = if let Some(state) = states.get(node_id) {
+            state.log_end_offset
+        } else {
+            0
+        };
+        min_leo = min_leo.min(leo);
+    }
+    min_leo as i64 - 1
+}
+
+/// Followers that caught up with the leader within `lag_time_max` stay in (or join) the ISR,
+/// the others are removed. The leader always stays in the ISR.
+pub fn calc_isr(
+    segment: &JournalSegment,
+    states: &HashMap<u64, ReplicaState>,
+    now: u128,
+    lag_time_max: u128,
+) -> Vec<u64> {
+    let mut isr = Vec::new();
+    for rep in segment.replicas.iter() {
+        if rep.node_id == segment.leader {
+            isr.push(rep.node_id);
+            continue;
+        }
+
+        if let Some(state) = states.get(&rep.node_id) {
+            if now.saturating_sub(state.last_caught_up_time) <= lag_time_max {
+                isr.push(rep.node_id);
+            }
+        }
+    }
+    isr
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use metadata_struct::journal::segment::{JournalSegment, Replica};
+
+    use super::{calc_high_watermark, calc_isr, min_insync_replicas, ReplicaState};
+
+    fn build_segment() -> JournalSegment {
+        let replicas = (1..=3)
+            .map(|node_id| Replica {
+                replica_seq: node_id - 1,
+                node_id,
+                fold: "/tmp/jl".to_string(),
+            })
+            .collect();
+        JournalSegment {
+            namespace: "n1".to_string(),
+            shard_name: "s1".to_string(),
+            replicas,
+            leader: 1,
+            isr: vec![1, 2, 3],
+            ..Default::default()
+        }
+    }
+
+    fn replica_state(log_end_offset: u64, last_caught_up_time: u128) -> ReplicaState {
+        ReplicaState {
+            log_end_offset,
+            last_fetch_time: last_caught_up_time,
+            last_caught_up_time,
+        }
+    }
+
+    #[test]
+    fn calc_isr_test() {
+        let segment = build_segment();
+        let mut states = HashMap::new();
+        states.insert(2, replica_state(100, 10000));
+        states.insert(3, replica_state(50, 10000));
+        assert_eq!(calc_isr(&segment, &states, 15000, 10000), vec![1, 2, 3]);
+
+        // node 3 has not caught up for longer than the lag window
+        states.insert(2, replica_state(200, 25000));
+        assert_eq!(calc_isr(&segment, &states, 25000, 10000), vec![1, 2]);
+
+        // node 3 catches up again and rejoins
+        states.insert(3, replica_state(200, 26000));
+        assert_eq!(calc_isr(&segment, &states, 26000, 10000), vec![1, 2, 3]);
+
+        // followers without any state are not in sync
+        assert_eq!(calc_isr(&segment, &HashMap::new(), 26000, 10000), vec![1]);
+    }
+
+    #[test]
+    fn calc_high_watermark_test() {
+        let mut states = HashMap::new();
+        states.insert(2, replica_state(80, 0));
+        states.insert(3, replica_state(50, 0));
+
+        assert_eq!(calc_high_watermark(&[1, 2, 3], 1, 100, &states), 49);
+        assert_eq!(calc_high_watermark(&[1, 2], 1, 100, &states), 79);
+        assert_eq!(calc_high_watermark(&[1], 1, 100, &states), 99);
+        assert_eq!(calc_high_watermark(&[1], 1, 0, &states), -1);
+
+        // an ISR member that has never fetched holds the high watermark back
+        assert_eq!(calc_high_watermark(&[1, 4], 1, 100, &states), -1);
+    }
+
+    #[test]
+    fn min_insync_replicas_test() {
+        assert_eq!(min_insync_replicas(1), 1);
+        assert_eq!(min_insync_replicas(2), 2);
+        assert_eq!(min_insync_replicas(3), 2);
+        assert_eq!(min_insync_replicas(5), 3);
+    }
+}
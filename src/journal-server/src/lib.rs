@@ -24,10 +24,14 @@ use std::time::Duration;
 use common_base::config::journal_server::{journal_server_conf, JournalServerConfig};
 use common_base::metrics::register_prometheus_export;
 use common_base::runtime::create_runtime;
+use group::GroupCoordinator;
 use grpc_clients::pool::ClientPool;
 use index::engine::{column_family_list, storage_data_fold};
+use isr::fetch::start_replica_fetch_thread;
+use isr::IsrManager;
 use log::{error, info};
 use rocksdb_engine::RocksDBEngine;
+use segment::compaction::SegmentCompactionManager;
 use segment::manager::{
     load_local_segment_cache, metadata_and_local_segment_diff_check, SegmentFileManager,
 };
@@ -41,6 +45,7 @@ use tokio::sync::broadcast;
 use tokio::time::sleep;
 
 mod core;
+mod group;
 mod handler;
 mod index;
 mod isr;
@@ -58,6 +63,8 @@ pub struct JournalServer {
     offset_manager: Arc<OffsetManager>,
     segment_file_manager: Arc<SegmentFileManager>,
     rocksdb_engine_handler: Arc<RocksDBEngine>,
+    isr_manager: Arc<IsrManager>,
+    group_coordinator: Arc<GroupCoordinator>,
 }
 
 impl JournalServer {
@@ -85,6 +92,13 @@ impl JournalServer {
             cache_manager.clone(),
             segment_file_manager.clone(),
         ));
+        let isr_manager = Arc::new(IsrManager::new(
+            cache_manager.clone(),
+            client_pool.clone(),
+            segment_file_manager.clone(),
+            rocksdb_engine_handler.clone(),
+        ));
+        let group_coordinator = Arc::new(GroupCoordinator::new(cache_manager.clone()));
 
         JournalServer {
             config,
@@ -97,6 +111,8 @@ impl JournalServer {
             offset_manager,
             segment_file_manager,
             rocksdb_engine_handler,
+            isr_manager,
+            group_coordinator,
         }
     }
 
@@ -121,6 +137,7 @@ impl JournalServer {
             self.cache_manager.clone(),
             self.segment_file_manager.clone(),
             self.rocksdb_engine_handler.clone(),
+            self.isr_manager.clone(),
         );
         self.server_runtime.spawn(async move {
             match server.start().await {
@@ -140,6 +157,8 @@ impl JournalServer {
         let offset_manager = self.offset_manager.clone();
         let segment_file_manager = self.segment_file_manager.clone();
         let rocksdb_engine_handler = self.rocksdb_engine_handler.clone();
+        let isr_manager = self.isr_manager.clone();
+        let group_coordinator = self.group_coordinator.clone();
         self.server_runtime.spawn(async {
             start_tcp_server(
                 client_pool,
@@ -148,6 +167,8 @@ impl JournalServer {
                 offset_manager,
                 segment_file_manager,
                 rocksdb_engine_handler,
+                isr_manager,
+                group_coordinator,
                 stop_sx,
             )
             .await;
@@ -177,6 +198,46 @@ impl JournalServer {
         self.daemon_runtime.spawn(async move {
             segment_scroll.trigger_segment_scroll().await;
         });
+
+        let isr_manager = self.isr_manager.clone();
+        let stop_sx = self.stop_send.clone();
+        self.daemon_runtime.spawn(async move {
+            isr_manager.start_isr_check_thread(stop_sx).await;
+        });
+
+        let group_coordinator = self.group_coordinator.clone();
+        let stop_sx = self.stop_send.clone();
+        self.daemon_runtime.spawn(async move {
+            group_coordinator.start_group_check_thread(stop_sx).await;
+        });
+
+        let compaction_manager = SegmentCompactionManager::new(
+            self.cache_manager.clone(),
+            self.segment_file_manager.clone(),
+            self.rocksdb_engine_handler.clone(),
+        );
+        let stop_sx = self.stop_send.clone();
+        self.daemon_runtime.spawn(async move {
+            compaction_manager.start_compaction_thread(stop_sx).await;
+        });
+
+        let cache_manager = self.cache_manager.clone();
+        let segment_file_manager = self.segment_file_manager.clone();
+        let rocksdb_engine_handler = self.rocksdb_engine_handler.clone();
+        let client_pool = self.client_pool.clone();
+        let isr_manager = self.isr_manager.clone();
+        let stop_sx = self.stop_send.clone();
+        self.daemon_runtime.spawn(async move {
+            start_replica_fetch_thread(
+                cache_manager,
+                segment_file_manager,
+                rocksdb_engine_handler,
+                client_pool,
+                isr_manager,
+                stop_sx,
+            )
+            .await;
+        });
     }
 
     fn waiting_stop(&self) {
@@ -206,6 +267,7 @@ impl JournalServer {
                 let path = Path::new(&path);
                 match load_local_segment_cache(
                     path,
+                    &self.cache_manager,
                     &self.rocksdb_engine_handler,
                     &self.segment_file_manager,
                     &self.config.storage.data_path,
@@ -0,0 +1,326 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use prost::Message;
+use protocol::journal_server::compression::{compress, decompress};
+use protocol::journal_server::journal_engine::CompressionType;
+use protocol::journal_server::journal_record::{JournalRecord, JournalRecordBatch};
+
+use crate::core::error::JournalServerError;
+
+pub const BATCH_MAGIC: u8 = 0x4A;
+
+pub const BATCH_VERSION: u8 = 2;
+
+pub const BATCH_HEADER_LEN: u64 = 31;
+
+/// Upper bound of the payload of a single batch. A length field above it can only come
+/// from a damaged header, so readers stop before allocating a buffer for it.
+pub const BATCH_MAX_LEN: u64 = 16 * 1024 * 1024;
+
+/// Upper bound of a batch payload once decompressed, producer batches that restore to
+/// more are rejected.
+pub const BATCH_MAX_DECOMPRESSED_LEN: u64 = 64 * 1024 * 1024;
+
+/// Header written in front of every record batch in a segment file:
+/// `magic:u8 | version:u8 | compression:u8 | base_offset:u64 | record_count:u32 |
+/// timestamp:u64 | len:u32 | crc32c:u32`.
+/// The CRC covers every header field after the version and the compressed payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatchHeader {
+    pub compression: CompressionType,
+    pub base_offset: u64,
+    pub record_count: u32,
+    pub timestamp: u64,
+    pub len: u32,
+    pub crc: u32,
+}
+
+impl BatchHeader {
+    pub fn last_offset(&self) -> u64 {
+        self.base_offset + self.record_count.max(1) as u64 - 1
+    }
+
+    pub fn encode(&self) -> [u8; BATCH_HEADER_LEN as usize] {
+        let mut buf = [0u8; BATCH_HEADER_LEN as usize];
+        buf[0] = BATCH_MAGIC;
+        buf[1] = BATCH_VERSION;
+        buf[2] = self.compression as u8;
+        buf[3..11].copy_from_slice(&self.base_offset.to_be_bytes());
+        buf[11..15].copy_from_slice(&self.record_count.to_be_bytes());
+        buf[15..23].copy_from_slice(&self.timestamp.to_be_bytes());
+        buf[23..27].copy_from_slice(&self.len.to_be_bytes());
+        buf[27..31].copy_from_slice(&self.crc.to_be_bytes());
+        buf
+    }
+
+    pub fn decode(
+        buf: &[u8; BATCH_HEADER_LEN as usize],
+        segment_name: &str,
+        position: u64,
+    ) -> Result<Self, JournalServerError> {
+        let compression = CompressionType::try_from(buf[2] as i32);
+        if buf[0] != BATCH_MAGIC || buf[1] != BATCH_VERSION || compression.is_err() {
+            return Err(JournalServerError::SegmentRecordInvalidHeader(
+                segment_name.to_string(),
+                position,
+                buf[0],
+                buf[1],
+            ));
+        }
+        let len = u32::from_be_bytes(buf[23..27].try_into().unwrap());
+        if len as u64 > BATCH_MAX_LEN {
+            return Err(JournalServerError::SegmentRecordTooLarge(
+                segment_name.to_string(),
+                position,
+                len as u64,
+                BATCH_MAX_LEN,
+            ));
+        }
+        Ok(BatchHeader {
+            compression: compression.unwrap(),
+            base_offset: u64::from_be_bytes(buf[3..11].try_into().unwrap()),
+            record_count: u32::from_be_bytes(buf[11..15].try_into().unwrap()),
+            timestamp: u64::from_be_bytes(buf[15..23].try_into().unwrap()),
+            len,
+            crc: u32::from_be_bytes(buf[27..31].try_into().unwrap()),
+        })
+    }
+
+    pub fn verify(
+        &self,
+        payload: &[u8],
+        segment_name: &str,
+        position: u64,
+    ) -> Result<(), JournalServerError> {
+        let crc = batch_crc(&self.encode(), payload);
+        if crc != self.crc {
+            return Err(JournalServerError::SegmentRecordCrcMismatch(
+                segment_name.to_string(),
+                position,
+                self.crc,
+                crc,
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn batch_crc(header: &[u8; BATCH_HEADER_LEN as usize], payload: &[u8]) -> u32 {
+    let crc = crc32c::crc32c(&header[2..27]);
+    crc32c::crc32c_append(crc, payload)
+}
+
+/// A batch of records framed as one unit. The payload is a compressed
+/// `JournalRecordBatch` and is kept compressed from the producer to the segment file.
+/// Offsets are assigned by the leader and live in the header only. The header timestamp
+/// is the latest create time of the records in the batch.
+#[derive(Debug, Clone)]
+pub struct RecordBatch {
+    pub header: BatchHeader,
+    pub payload: Vec<u8>,
+}
+
+impl RecordBatch {
+    pub fn new(
+        compression: CompressionType,
+        base_offset: u64,
+        record_count: u32,
+        timestamp: u64,
+        payload: Vec<u8>,
+    ) -> Self {
+        let mut header = BatchHeader {
+            compression,
+            base_offset,
+            record_count,
+            timestamp,
+            len: payload.len() as u32,
+            crc: 0,
+        };
+        header.crc = batch_crc(&header.encode(), &payload);
+        RecordBatch { header, payload }
+    }
+
+    pub fn build(
+        compression: CompressionType,
+        base_offset: u64,
+        timestamp: u64,
+        records: Vec<JournalRecord>,
+    ) -> Result<Self, JournalServerError> {
+        let record_count = records.len() as u32;
+        let data = JournalRecordBatch { records }.encode_to_vec();
+        let payload = compress(compression, &data)?;
+        Ok(RecordBatch::new(
+            compression,
+            base_offset,
+            record_count,
+            timestamp,
+            payload,
+        ))
+    }
+
+    /// Parses a batch in its segment file layout, as shipped to followers.
+    pub fn decode(
+        data: &[u8],
+        segment_name: &str,
+        position: u64,
+    ) -> Result<Self, JournalServerError> {
+        let header_len = BATCH_HEADER_LEN as usize;
+        let header_buf: &[u8; BATCH_HEADER_LEN as usize] = data
+            .get(0..header_len)
+            .and_then(|buf| buf.try_into().ok())
+            .ok_or_else(|| {
+                JournalServerError::SegmentRecordInvalidHeader(
+                    segment_name.to_string(),
+                    position,
+                    0,
+                    0,
+                )
+            })?;
+        let header = BatchHeader::decode(header_buf, segment_name, position)?;
+        let payload = data[header_len..].to_vec();
+        if payload.len() != header.len as usize {
+            return Err(JournalServerError::SegmentRecordInvalidHeader(
+                segment_name.to_string(),
+                position,
+                header_buf[0],
+                header_buf[1],
+            ));
+        }
+        header.verify(&payload, segment_name, position)?;
+        Ok(RecordBatch { header, payload })
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(BATCH_HEADER_LEN as usize + self.payload.len());
+        buf.extend_from_slice(&self.header.encode());
+        buf.extend_from_slice(&self.payload);
+        buf
+    }
+}
+
+/// Decompresses a batch payload and restores the offset of every record from the batch
+/// header. Records keep the create time they were written with, a record without one
+/// takes the timestamp of the batch.
+pub fn decode_batch_records(
+    header: &BatchHeader,
+    payload: &[u8],
+) -> Result<Vec<JournalRecord>, JournalServerError> {
+    let data = decompress(
+        header.compression,
+        payload,
+        BATCH_MAX_DECOMPRESSED_LEN as usize,
+    )?;
+    let batch = JournalRecordBatch::decode(data.as_ref())?;
+    Ok(batch
+        .records
+        .into_iter()
+        .enumerate()
+        .map(|(i, mut record)| {
+            record.offset = header.base_offset + i as u64;
+            if record.create_time == 0 {
+                record.create_time = header.timestamp;
+            }
+            record
+        })
+        .collect())
+}
+
+/// Decodes a batch sent by a producer, returning its records so the leader can check them
+/// and assign offsets without recompressing the payload.
+pub fn decode_producer_batch(
+    compression: CompressionType,
+    payload: &[u8],
+) -> Result<Vec<JournalRecord>, JournalServerError> {
+    let data = decompress(compression, payload, BATCH_MAX_DECOMPRESSED_LEN as usize)?;
+    Ok(JournalRecordBatch::decode(data.as_ref())?.records)
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use protocol::journal_server::compression::compress;
+    use protocol::journal_server::journal_engine::CompressionType;
+    use protocol::journal_server::journal_record::{JournalRecord, JournalRecordBatch};
+
+    use super::{
+        decode_batch_records, decode_producer_batch, RecordBatch, BATCH_MAX_DECOMPRESSED_LEN,
+    };
+    use crate::core::error::JournalServerError;
+
+    #[test]
+    fn record_batch_codec_test() {
+        let records: Vec<JournalRecord> = (0..10)
+            .map(|i| JournalRecord {
+                pkid: i,
+                key: format!("k{}", i),
+                content: format!(r#"{{"device":"d{}","temperature":21.5}}"#, i).into_bytes(),
+                ..Default::default()
+            })
+            .collect();
+
+        let batch = RecordBatch::build(CompressionType::Zstd, 100, 1000, records).unwrap();
+        let data = batch.encode();
+
+        let batch = RecordBatch::decode(&data, "s1", 0).unwrap();
+        assert_eq!(batch.header.base_offset, 100);
+        assert_eq!(batch.header.record_count, 10);
+        assert_eq!(batch.header.last_offset(), 109);
+
+        let records = decode_batch_records(&batch.header, &batch.payload).unwrap();
+        assert_eq!(records.len(), 10);
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(record.offset, 100 + i as u64);
+            assert_eq!(record.create_time, 1000);
+            assert_eq!(record.key, format!("k{}", i));
+        }
+
+        // records stamped by the producer keep their create time
+        let records = vec![
+            JournalRecord {
+                create_time: 998,
+                ..Default::default()
+            },
+            JournalRecord {
+                create_time: 999,
+                ..Default::default()
+            },
+        ];
+        let stamped = RecordBatch::build(CompressionType::Lz4, 0, 999, records).unwrap();
+        let records = decode_batch_records(&stamped.header, &stamped.payload).unwrap();
+        assert_eq!(records[0].create_time, 998);
+        assert_eq!(records[1].create_time, 999);
+
+        let mut data = data;
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        assert!(matches!(
+            RecordBatch::decode(&data, "s1", 0),
+            Err(JournalServerError::SegmentRecordCrcMismatch(_, _, _, _))
+        ));
+    }
+
+    #[test]
+    fn producer_batch_decompress_limit_test() {
+        let record = JournalRecord {
+            content: vec![0u8; BATCH_MAX_DECOMPRESSED_LEN as usize],
+            ..Default::default()
+        };
+        let data = JournalRecordBatch {
+            records: vec![record],
+        }
+        .encode_to_vec();
+        let payload = compress(CompressionType::Zstd, &data).unwrap();
+        assert!(decode_producer_batch(CompressionType::Zstd, &payload).is_err());
+    }
+}
@@ -0,0 +1,321 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common_base::config::journal_server::journal_server_conf;
+use common_base::tools::now_second;
+use log::{debug, error, info};
+use metadata_struct::journal::segment::{JournalSegment, SegmentStatus};
+use metadata_struct::journal::shard::{CleanupPolicy, JournalShard};
+use protocol::journal_server::journal_record::JournalRecord;
+use rocksdb_engine::RocksDBEngine;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+
+use super::file::{SegmentFile, SegmentRecoverData};
+use super::manager::{rebuild_segment_index, SegmentFileManager};
+use super::SegmentIdentity;
+use crate::core::cache::CacheManager;
+use crate::core::consts::SEGMENT_COMPACTION_INTERVAL_SEC;
+use crate::core::error::JournalServerError;
+use crate::index::build::try_trigger_build_index;
+use crate::index::offset::OffsetIndexManager;
+use crate::index::time::TimestampIndexManager;
+
+/// Compacts the sealed segments of shards whose cleanup policy is `Compact`. Every replica
+/// compacts its local copy of a segment independently; the latest record of each key is
+/// determined from the segments of the shard that are stored on this node.
+pub struct SegmentCompactionManager {
+    cache_manager: Arc<CacheManager>,
+    segment_file_manager: Arc<SegmentFileManager>,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+}
+
+impl SegmentCompactionManager {
+    pub fn new(
+        cache_manager: Arc<CacheManager>,
+        segment_file_manager: Arc<SegmentFileManager>,
+        rocksdb_engine_handler: Arc<RocksDBEngine>,
+    ) -> Self {
+        SegmentCompactionManager {
+            cache_manager,
+            segment_file_manager,
+            rocksdb_engine_handler,
+        }
+    }
+
+    pub async fn start_compaction_thread(&self, stop_send: broadcast::Sender<bool>) {
+        info!("Segment compaction thread started successfully");
+        let mut stop_recv = stop_send.subscribe();
+        loop {
+            select! {
+                val = stop_recv.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            debug!("{}","Segment compaction thread exited successfully");
+                            break;
+                        }
+                    }
+                }
+                _ = sleep(Duration::from_secs(SEGMENT_COMPACTION_INTERVAL_SEC)) => {
+                    for shard in self.cache_manager.get_shards() {
+                        if shard.config.cleanup_policy != CleanupPolicy::Compact {
+                            continue;
+                        }
+                        if let Err(e) = self.compact_shard(&shard).await {
+                            error!(
+                                "Shard {} compaction failed with error message :{}",
+                                shard.name(),
+                                e
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn compact_shard(&self, shard: &JournalShard) -> Result<(), JournalServerError> {
+        let conf = journal_server_conf();
+        let mut segments: Vec<(JournalSegment, SegmentFile)> = Vec::new();
+        for segment in self
+            .cache_manager
+            .get_segments_list_by_shard(&shard.namespace, &shard.shard_name)
+        {
+            if segment.segment_seq < shard.start_segment_seq {
+                continue;
+            }
+            if let Some(fold) = segment.get_fold(conf.node_id) {
+                let segment_file = SegmentFile::new(
+                    segment.namespace.clone(),
+                    segment.shard_name.clone(),
+                    segment.segment_seq,
+                    fold,
+                );
+                if segment_file.exists() {
+                    segments.push((segment, segment_file));
+                }
+            }
+        }
+        segments.sort_by_key(|(segment, _)| segment.segment_seq);
+
+        // the active segment takes part in finding the latest record of every key,
+        // but only sealed segments are rewritten
+        let mut latest_offsets = HashMap::new();
+        for (_, segment_file) in segments.iter() {
+            scan_segment_records(segment_file, |record| {
+                if !record.key.is_empty() {
+                    latest_offsets.insert(record.key.clone(), record.offset);
+                }
+            })
+            .await?;
+        }
+
+        let now = now_second();
+        let tombstone_retention_sec = shard.config.tombstone_retention_sec();
+        for (segment, segment_file) in segments.iter() {
+            if segment.status != SegmentStatus::SealUp {
+                continue;
+            }
+
+            let segment_iden = SegmentIdentity::from_journal_segment(segment);
+            if self.cache_manager.contain_build_index_thread(&segment_iden) {
+                continue;
+            }
+
+            let mut removable = 0;
+            scan_segment_records(segment_file, |record| {
+                if !compaction_retain(record, &latest_offsets, now, tombstone_retention_sec) {
+                    removable += 1;
+                }
+            })
+            .await?;
+            if removable == 0 {
+                continue;
+            }
+
+            self.compact_segment(
+                &segment_iden,
+                segment_file,
+                &latest_offsets,
+                now,
+                tombstone_retention_sec,
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn compact_segment(
+        &self,
+        segment_iden: &SegmentIdentity,
+        segment_file: &SegmentFile,
+        latest_offsets: &HashMap<String, u64>,
+        now: u64,
+        tombstone_retention_sec: u64,
+    ) -> Result<(), JournalServerError> {
+        let segment_meta =
+            if let Some(meta) = self.segment_file_manager.get_segment_file(segment_iden) {
+                meta
+            } else {
+                return Err(JournalServerError::SegmentMetaNotExists(
+                    segment_iden.name(),
+                ));
+            };
+
+        let removed = segment_file
+            .compact(|record| {
+                compaction_retain(record, latest_offsets, now, tombstone_retention_sec)
+            })
+            .await?;
+        if removed == 0 {
+            return Ok(());
+        }
+
+        // Positions in the index point into the old file. The offset range of the segment
+        // does not change, so it is kept and the rest of the index is built again. Reads
+        // wait until the file and the index match again.
+        let segment_file_lock = self.cache_manager.get_segment_file_lock(segment_iden);
+        let guard = segment_file_lock.write().await;
+        segment_file.replace_with_compact().await?;
+        let recover_data = SegmentRecoverData {
+            valid_len: segment_file.size().await?,
+            truncated_len: 0,
+            start_offset: segment_meta.start_offset,
+            end_offset: segment_meta.end_offset,
+            start_timestamp: segment_meta.start_timestamp,
+            end_timestamp: segment_meta.end_timestamp,
+        };
+        rebuild_segment_index(
+            &self.rocksdb_engine_handler,
+            &OffsetIndexManager::new(self.rocksdb_engine_handler.clone()),
+            &TimestampIndexManager::new(self.rocksdb_engine_handler.clone()),
+            segment_iden,
+            &recover_data,
+        )?;
+        drop(guard);
+
+        try_trigger_build_index(
+            &self.cache_manager,
+            &self.segment_file_manager,
+            &self.rocksdb_engine_handler,
+            segment_iden,
+        )
+        .await;
+
+        info!(
+            "Segment {} compacted, {} records were removed",
+            segment_iden.name(),
+            removed
+        );
+        Ok(())
+    }
+}
+
+async fn scan_segment_records<F>(
+    segment_file: &SegmentFile,
+    mut f: F,
+) -> Result<(), JournalServerError>
+where
+    F: FnMut(&JournalRecord),
+{
+    let page_size = 10 * 1024 * 1024;
+    let mut start_position = 0;
+    let mut start_offset = 0;
+    loop {
+        let data = segment_file
+            .read_by_offset(start_position, start_offset, page_size)
+            .await?;
+        let last = if let Some(last) = data.last() {
+            last.clone()
+        } else {
+            break;
+        };
+        for read_data in data.iter() {
+            f(&read_data.record);
+        }
+        start_position = last.position;
+        start_offset = last.record.offset + 1;
+    }
+    Ok(())
+}
+
+/// Whether a record survives compaction: records without a key are always kept, otherwise
+/// only the latest record of its key is kept, unless it is a tombstone that is older than
+/// the tombstone retention.
+pub fn compaction_retain(
+    record: &JournalRecord,
+    latest_offsets: &HashMap<String, u64>,
+    now: u64,
+    tombstone_retention_sec: u64,
+) -> bool {
+    if record.key.is_empty() {
+        return true;
+    }
+
+    if let Some(offset) = latest_offsets.get(&record.key) {
+        if *offset != record.offset {
+            return false;
+        }
+    }
+
+    if record.content.is_empty() {
+        return now.saturating_sub(record.create_time) <= tombstone_retention_sec;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use protocol::journal_server::journal_record::JournalRecord;
+
+    use super::compaction_retain;
+
+    fn build_record(key: &str, offset: u64, content: &str, create_time: u64) -> JournalRecord {
+        JournalRecord {
+            key: key.to_string(),
+            offset,
+            content: content.as_bytes().to_vec(),
+            create_time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn compaction_retain_test() {
+        let mut latest_offsets = HashMap::new();
+        latest_offsets.insert("device-1".to_string(), 5);
+        latest_offsets.insert("device-2".to_string(), 8);
+
+        // only the latest version of a key survives
+        let old = build_record("device-1", 1, "{\"temp\":20}", 1000);
+        let latest = build_record("device-1", 5, "{\"temp\":21}", 1000);
+        assert!(!compaction_retain(&old, &latest_offsets, 2000, 100));
+        assert!(compaction_retain(&latest, &latest_offsets, 2000, 100));
+
+        // records without a key are never compacted
+        let no_key = build_record("", 2, "data", 1000);
+        assert!(compaction_retain(&no_key, &latest_offsets, 2000, 100));
+
+        // a tombstone is kept during the grace period and then removed
+        let tombstone = build_record("device-2", 8, "", 1000);
+        assert!(compaction_retain(&tombstone, &latest_offsets, 1050, 100));
+        assert!(!compaction_retain(&tombstone, &latest_offsets, 1101, 100));
+    }
+}
@@ -12,20 +12,26 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs::remove_dir_all;
-use std::io::ErrorKind;
+use std::io::{ErrorKind, Read, Write};
 use std::path::Path;
 use std::sync::Arc;
 
-use bytes::BytesMut;
 use common_base::config::journal_server::journal_server_conf;
 use common_base::tools::{file_exists, try_create_fold};
+use log::warn;
+use metadata_struct::journal::segment::segment_name;
 use prost::Message;
+use protocol::journal_server::journal_engine::CompressionType;
 use protocol::journal_server::journal_record::JournalRecord;
 use tokio::fs::{self, File, OpenOptions};
-use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
 
+use super::batch::{
+    decode_batch_records, BatchHeader, RecordBatch, BATCH_HEADER_LEN, BATCH_MAGIC, BATCH_MAX_LEN,
+    BATCH_VERSION,
+};
 use super::SegmentIdentity;
 use crate::core::cache::CacheManager;
 use crate::core::error::JournalServerError;
@@ -104,30 +110,44 @@ impl SegmentFile {
         Ok(remove_dir_all(segment_file)?)
     }
 
-    pub async fn write(
-        &self,
-        records: &[JournalRecord],
-    ) -> Result<HashMap<u64, u64>, JournalServerError> {
+    pub async fn write(&self, batches: &[RecordBatch]) -> Result<Vec<u64>, JournalServerError> {
         let segment_file = data_file_segment(&self.data_fold, self.segment_no);
         let file = OpenOptions::new().append(true).open(segment_file).await?;
         let mut writer = tokio::io::BufWriter::new(file);
+        let end_position = writer.seek(std::io::SeekFrom::End(0)).await?;
 
-        let mut results = HashMap::new();
-        for record in records {
-            let position = writer.stream_position().await?;
-
-            let data = JournalRecord::encode_to_vec(record);
-            writer.write_u64(record.offset).await?;
-            writer.write_u32(data.len() as u32).await?;
-            writer.write_all(data.as_ref()).await?;
+        // readers refuse larger batches, they must never reach the file
+        if let Some(batch) = batches
+            .iter()
+            .find(|batch| batch.payload.len() as u64 > BATCH_MAX_LEN)
+        {
+            return Err(JournalServerError::SegmentRecordTooLarge(
+                self.name(),
+                end_position,
+                batch.payload.len() as u64,
+                BATCH_MAX_LEN,
+            ));
+        }
 
-            results.insert(record.pkid, position);
+        let mut results = Vec::new();
+        for batch in batches {
+            let position = writer.stream_position().await?;
+            writer.write_all(&batch.header.encode()).await?;
+            writer.write_all(batch.payload.as_ref()).await?;
+            results.push(position);
         }
 
         writer.flush().await?;
         Ok(results)
     }
 
+    pub async fn sync(&self) -> Result<(), JournalServerError> {
+        let segment_file = data_file_segment(&self.data_fold, self.segment_no);
+        let file = OpenOptions::new().append(true).open(segment_file).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
     pub async fn size(&self) -> Result<u64, JournalServerError> {
         let segment_file = data_file_segment(&self.data_fold, self.segment_no);
         let metadata = fs::metadata(segment_file).await?;
@@ -142,10 +162,11 @@ impl SegmentFile {
     ) -> Result<Vec<ReadData>, JournalServerError> {
         let segment_file = data_file_segment(&self.data_fold, self.segment_no);
         let file = File::open(segment_file).await?;
+        let file_len = file.metadata().await?.len();
         let mut reader = tokio::io::BufReader::new(file);
 
         reader
-            .seek(std::io::SeekFrom::Current(start_position as i64))
+            .seek(std::io::SeekFrom::Start(start_position))
             .await?;
 
         let mut results = Vec::new();
@@ -155,34 +176,34 @@ impl SegmentFile {
                 break;
             }
 
-            // read offset
             let position = reader.stream_position().await?;
-
-            let record_offset = match reader.read_u64().await {
-                Ok(offset) => offset,
-                Err(e) => {
-                    if e.kind() == ErrorKind::UnexpectedEof {
-                        break;
-                    }
-                    return Err(e.into());
-                }
+            let header = match self.read_header(&mut reader, position).await? {
+                Some(header) => header,
+                None => break,
             };
 
-            // read len
-            let len = reader.read_u32().await?;
-
-            if record_offset < start_offset {
-                reader.seek(std::io::SeekFrom::Current(len as i64)).await?;
+            if header.last_offset() < start_offset {
+                reader
+                    .seek(std::io::SeekFrom::Current(header.len as i64))
+                    .await?;
                 continue;
             }
 
-            // read body
-            let mut buf = BytesMut::with_capacity(len as usize);
-            reader.read_buf(&mut buf).await?;
+            let payload = match self
+                .read_payload(&mut reader, position, &header, file_len)
+                .await?
+            {
+                Some(payload) => payload,
+                None => break,
+            };
 
-            already_size += buf.len() as u64;
-            let record = JournalRecord::decode(buf)?;
-            results.push(ReadData { position, record });
+            for record in self.decode_records(&header, &payload)? {
+                if record.offset < start_offset {
+                    continue;
+                }
+                results.push(ReadData { position, record });
+            }
+            already_size += header.len as u64;
         }
 
         Ok(results)
@@ -196,10 +217,11 @@ impl SegmentFile {
     ) -> Result<Vec<ReadData>, JournalServerError> {
         let segment_file = data_file_segment(&self.data_fold, self.segment_no);
         let file = File::open(segment_file).await?;
+        let file_len = file.metadata().await?.len();
         let mut reader = tokio::io::BufReader::new(file);
 
         reader
-            .seek(std::io::SeekFrom::Current(start_position as i64))
+            .seek(std::io::SeekFrom::Start(start_position))
             .await?;
 
         let mut results = Vec::new();
@@ -209,43 +231,40 @@ impl SegmentFile {
                 break;
             }
 
-            // read offset
             let position = reader.stream_position().await?;
-
-            let _ = match reader.read_u64().await {
-                Ok(offset) => offset,
-                Err(e) => {
-                    if e.kind() == ErrorKind::UnexpectedEof {
-                        break;
-                    }
-                    return Err(e.into());
-                }
+            let header = match self.read_header(&mut reader, position).await? {
+                Some(header) => header,
+                None => break,
             };
 
-            // read len
-            let len = reader.read_u32().await?;
-
-            // read body
-            let mut buf = BytesMut::with_capacity(len as usize);
-            reader.read_buf(&mut buf).await?;
-
-            let buf_len = buf.len();
-            let record = JournalRecord::decode(buf)?;
-
-            if record.create_time < timestamp {
+            // the header holds the latest create time of the batch
+            if header.timestamp < timestamp {
                 reader
-                    .seek(std::io::SeekFrom::Current(buf_len as i64))
+                    .seek(std::io::SeekFrom::Current(header.len as i64))
                     .await?;
                 continue;
             }
 
-            already_size += buf_len as u64;
-            results.push(ReadData { position, record });
+            let payload = match self
+                .read_payload(&mut reader, position, &header, file_len)
+                .await?
+            {
+                Some(payload) => payload,
+                None => break,
+            };
+
+            for record in self.decode_records(&header, &payload)? {
+                if record.create_time >= timestamp {
+                    results.push(ReadData { position, record });
+                }
+            }
+            already_size += header.len as u64;
         }
 
         Ok(results)
     }
 
+    /// Reads the batches at the given positions and returns all of their records.
     pub async fn read_by_positions(
         &self,
         positions: Vec<u64>,
@@ -253,43 +272,469 @@ impl SegmentFile {
     ) -> Result<Vec<ReadData>, JournalServerError> {
         let segment_file = data_file_segment(&self.data_fold, self.segment_no);
         let file = File::open(segment_file).await?;
+        let file_len = file.metadata().await?.len();
         let mut reader = tokio::io::BufReader::new(file);
 
         let mut results = Vec::new();
         let mut already_size = 0;
+        let mut read_positions = HashSet::new();
 
         for position in positions {
             if already_size > size {
                 break;
             }
 
-            reader
-                .seek(std::io::SeekFrom::Current(position as i64))
-                .await?;
+            // several records of the same batch point at the same position
+            if !read_positions.insert(position) {
+                continue;
+            }
+
+            reader.seek(std::io::SeekFrom::Start(position)).await?;
+
+            let header = match self.read_header(&mut reader, position).await? {
+                Some(header) => header,
+                None => break,
+            };
+
+            let payload = match self
+                .read_payload(&mut reader, position, &header, file_len)
+                .await?
+            {
+                Some(payload) => payload,
+                None => break,
+            };
+
+            for record in self.decode_records(&header, &payload)? {
+                results.push(ReadData { position, record });
+            }
+            already_size += header.len as u64;
+        }
+
+        Ok(results)
+    }
 
-            // read len
-            let len = reader.read_u32().await?;
+    /// Reads whole batches without decompressing them, starting with the batch that
+    /// contains `start_offset`. Used to ship data to followers as it is stored.
+    pub async fn read_batches(
+        &self,
+        start_position: u64,
+        start_offset: u64,
+        size: u64,
+    ) -> Result<Vec<RecordBatch>, JournalServerError> {
+        let segment_file = data_file_segment(&self.data_fold, self.segment_no);
+        let file = File::open(segment_file).await?;
+        let file_len = file.metadata().await?.len();
+        let mut reader = tokio::io::BufReader::new(file);
 
-            // read body
-            let mut buf = BytesMut::with_capacity(len as usize);
-            reader.read_buf(&mut buf).await?;
+        reader
+            .seek(std::io::SeekFrom::Start(start_position))
+            .await?;
 
-            let buf_len = buf.len();
-            let record = JournalRecord::decode(buf)?;
+        let mut results = Vec::new();
+        let mut already_size = 0;
+        loop {
+            if already_size > size {
+                break;
+            }
+
+            let position = reader.stream_position().await?;
+            let header = match self.read_header(&mut reader, position).await? {
+                Some(header) => header,
+                None => break,
+            };
+
+            if header.last_offset() < start_offset {
+                reader
+                    .seek(std::io::SeekFrom::Current(header.len as i64))
+                    .await?;
+                continue;
+            }
+
+            let payload = match self
+                .read_payload(&mut reader, position, &header, file_len)
+                .await?
+            {
+                Some(payload) => payload,
+                None => break,
+            };
 
-            already_size += buf_len as u64;
-            results.push(ReadData { position, record });
+            already_size += header.len as u64;
+            results.push(RecordBatch { header, payload });
         }
 
         Ok(results)
     }
 
+    /// Rewrites the segment file keeping only the records accepted by `retain`. Records keep
+    /// their offsets: a batch is copied as it is when all of its records are kept, otherwise
+    /// every run of consecutive kept records becomes a new batch with the same compression
+    /// and timestamp. The new file is left next to the segment file until
+    /// `replace_with_compact` swaps it in. Returns the number of removed records, nothing
+    /// is left behind when it is 0.
+    pub async fn compact<F>(&self, mut retain: F) -> Result<u64, JournalServerError>
+    where
+        F: FnMut(&JournalRecord) -> bool,
+    {
+        let segment_file = data_file_segment(&self.data_fold, self.segment_no);
+        let compact_file = data_file_segment_compact(&self.data_fold, self.segment_no);
+        let file = File::create(&compact_file).await?;
+        let mut writer = tokio::io::BufWriter::new(file);
+
+        let page_size = 10 * 1024 * 1024;
+        let mut position = 0;
+        let mut removed = 0;
+        loop {
+            let batches = self.read_batches(position, 0, page_size).await?;
+            if batches.is_empty() {
+                break;
+            }
+
+            for batch in batches {
+                position += BATCH_HEADER_LEN + batch.header.len as u64;
+
+                let records = self.decode_records(&batch.header, &batch.payload)?;
+                let total = records.len();
+                let kept: Vec<JournalRecord> = records.into_iter().filter(|r| retain(r)).collect();
+                if kept.len() == total {
+                    writer.write_all(&batch.encode()).await?;
+                    continue;
+                }
+                removed += (total - kept.len()) as u64;
+
+                for run in split_offset_runs(kept) {
+                    let base_offset = run[0].offset;
+                    let new_batch = RecordBatch::build(
+                        batch.header.compression,
+                        base_offset,
+                        batch.header.timestamp,
+                        run,
+                    )?;
+                    writer.write_all(&new_batch.encode()).await?;
+                }
+            }
+        }
+
+        writer.flush().await?;
+        if removed == 0 {
+            fs::remove_file(&compact_file).await?;
+            return Ok(0);
+        }
+
+        writer.get_ref().sync_all().await?;
+        Ok(removed)
+    }
+
+    /// Replaces the segment file with the output of `compact` atomically.
+    pub async fn replace_with_compact(&self) -> Result<(), JournalServerError> {
+        let segment_file = data_file_segment(&self.data_fold, self.segment_no);
+        let compact_file = data_file_segment_compact(&self.data_fold, self.segment_no);
+        fs::rename(&compact_file, &segment_file).await?;
+        Ok(())
+    }
+
+    // Returns None when the file ends before a complete header, which is either the end
+    // of the data or a batch that is still being written.
+    async fn read_header(
+        &self,
+        reader: &mut BufReader<File>,
+        position: u64,
+    ) -> Result<Option<BatchHeader>, JournalServerError> {
+        let mut buf = [0u8; BATCH_HEADER_LEN as usize];
+        if let Err(e) = reader.read_exact(&mut buf).await {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                return Ok(None);
+            }
+            return Err(e.into());
+        }
+        Ok(Some(BatchHeader::decode(&buf, &self.name(), position)?))
+    }
+
+    // Returns None when the batch runs past the end of the file, it is still being written.
+    async fn read_payload(
+        &self,
+        reader: &mut BufReader<File>,
+        position: u64,
+        header: &BatchHeader,
+        file_len: u64,
+    ) -> Result<Option<Vec<u8>>, JournalServerError> {
+        if position + BATCH_HEADER_LEN + header.len as u64 > file_len {
+            return Ok(None);
+        }
+
+        let mut buf = vec![0u8; header.len as usize];
+        if let Err(e) = reader.read_exact(&mut buf).await {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                return Ok(None);
+            }
+            return Err(e.into());
+        }
+        header.verify(&buf, &self.name(), position)?;
+        Ok(Some(buf))
+    }
+
+    fn decode_records(
+        &self,
+        header: &BatchHeader,
+        payload: &[u8],
+    ) -> Result<Vec<JournalRecord>, JournalServerError> {
+        let mut records = decode_batch_records(header, payload)?;
+        for record in records.iter_mut() {
+            record.namespace = self.namespace.clone();
+            record.shard_name = self.shard_name.clone();
+            record.segment = self.segment_no;
+        }
+        Ok(records)
+    }
+
+    fn name(&self) -> String {
+        segment_name(&self.namespace, &self.shard_name, self.segment_no)
+    }
+
     pub fn exists(&self) -> bool {
         let segment_file = data_file_segment(&self.data_fold, self.segment_no);
         Path::new(&segment_file).exists()
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentRecoverData {
+    pub valid_len: u64,
+    pub truncated_len: u64,
+    pub start_offset: i64,
+    pub end_offset: i64,
+    pub start_timestamp: i64,
+    pub end_timestamp: i64,
+}
+
+/// Scans a segment file from the beginning and truncates it after the last batch
+/// whose header and CRC are intact. A crash in the middle of a write leaves a partial
+/// batch at the tail; everything from the first damaged batch on is dropped, because
+/// the length fields after it can no longer be trusted.
+pub fn recover_segment_file(
+    segment_name: &str,
+    file_path: &str,
+) -> Result<SegmentRecoverData, JournalServerError> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(file_path)?;
+    let result = scan_segment_file(segment_name, &file, None)?;
+    truncate_file(&file, &result)?;
+    Ok(result)
+}
+
+/// Truncates a segment file after the last batch that ends at or below `high_watermark`.
+/// A follower does this when the segment gets a new leader, since the records above its
+/// high watermark may not exist on the new leader.
+pub fn truncate_segment_file(
+    segment_name: &str,
+    file_path: &str,
+    high_watermark: i64,
+) -> Result<SegmentRecoverData, JournalServerError> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(file_path)?;
+    let result = scan_segment_file(segment_name, &file, Some(high_watermark))?;
+    truncate_file(&file, &result)?;
+    Ok(result)
+}
+
+/// Scans a segment file like `recover_segment_file` without changing it, `truncated_len`
+/// is the number of damaged bytes at the end of the file.
+pub fn verify_segment_file(
+    segment_name: &str,
+    file_path: &str,
+) -> Result<SegmentRecoverData, JournalServerError> {
+    let file = std::fs::File::open(file_path)?;
+    scan_segment_file(segment_name, &file, None)
+}
+
+fn scan_segment_file(
+    segment_name: &str,
+    file: &std::fs::File,
+    max_end_offset: Option<i64>,
+) -> Result<SegmentRecoverData, JournalServerError> {
+    let file_len = file.metadata()?.len();
+    let mut reader = std::io::BufReader::new(file);
+
+    let mut result = SegmentRecoverData {
+        valid_len: 0,
+        truncated_len: 0,
+        start_offset: -1,
+        end_offset: -1,
+        start_timestamp: -1,
+        end_timestamp: -1,
+    };
+
+    loop {
+        let position = result.valid_len;
+        let mut buf = [0u8; BATCH_HEADER_LEN as usize];
+        if let Err(e) = reader.read_exact(&mut buf) {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                break;
+            }
+            return Err(e.into());
+        }
+
+        let header = match BatchHeader::decode(&buf, segment_name, position) {
+            Ok(header) => header,
+            Err(e) => {
+                warn!("{}", e);
+                break;
+            }
+        };
+
+        // a batch that runs past the end of the file is a torn write
+        let end_position = position + BATCH_HEADER_LEN + header.len as u64;
+        if end_position > file_len {
+            break;
+        }
+
+        let mut payload = vec![0u8; header.len as usize];
+        reader.read_exact(&mut payload)?;
+
+        if let Err(e) = header.verify(&payload, segment_name, position) {
+            warn!("{}", e);
+            break;
+        }
+
+        if let Some(max_end_offset) = max_end_offset {
+            if header.last_offset() as i64 > max_end_offset {
+                break;
+            }
+        }
+
+        if result.start_offset < 0 {
+            result.start_offset = header.base_offset as i64;
+            result.start_timestamp = header.timestamp as i64;
+        }
+        result.end_offset = header.last_offset() as i64;
+        result.end_timestamp = header.timestamp as i64;
+        result.valid_len = end_position;
+    }
+
+    result.truncated_len = file_len - result.valid_len;
+    Ok(result)
+}
+
+fn truncate_file(
+    file: &std::fs::File,
+    recover_data: &SegmentRecoverData,
+) -> Result<(), JournalServerError> {
+    if recover_data.truncated_len > 0 {
+        file.set_len(recover_data.valid_len)?;
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+/// The on-disk format of a segment file. Files written before record batches were
+/// introduced hold `offset:u64 | len:u32 | JournalRecord` entries without a header.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SegmentFileFormat {
+    Batch,
+    Legacy,
+}
+
+/// Detects the format from the first entry of the file. An empty file, or one whose first
+/// entry is neither a batch nor a legacy record, counts as a batch file and is left to the
+/// recovery. Batch files of another version are refused rather than truncated.
+pub fn segment_file_format(file_path: &str) -> Result<SegmentFileFormat, JournalServerError> {
+    let file = std::fs::File::open(file_path)?;
+    let file_len = file.metadata()?.len();
+    let mut reader = std::io::BufReader::new(file);
+
+    let mut buf = [0u8; LEGACY_RECORD_HEADER_LEN as usize];
+    if let Err(e) = reader.read_exact(&mut buf) {
+        if e.kind() == ErrorKind::UnexpectedEof {
+            return Ok(SegmentFileFormat::Batch);
+        }
+        return Err(e.into());
+    }
+
+    if buf[0] == BATCH_MAGIC {
+        if buf[1] != BATCH_VERSION {
+            return Err(JournalServerError::SegmentFileFormatNotSupported(
+                file_path.to_string(),
+                buf[1],
+            ));
+        }
+        return Ok(SegmentFileFormat::Batch);
+    }
+
+    let offset = u64::from_be_bytes(buf[0..8].try_into().unwrap());
+    let len = u32::from_be_bytes(buf[8..12].try_into().unwrap()) as u64;
+    if LEGACY_RECORD_HEADER_LEN + len > file_len || len > BATCH_MAX_LEN {
+        return Ok(SegmentFileFormat::Batch);
+    }
+
+    let mut data = vec![0u8; len as usize];
+    reader.read_exact(&mut data)?;
+    match JournalRecord::decode(data.as_ref()) {
+        Ok(record) if record.offset == offset => Ok(SegmentFileFormat::Legacy),
+        _ => Ok(SegmentFileFormat::Batch),
+    }
+}
+
+/// Rewrites a legacy segment file into batches, one per record so every record keeps its
+/// offset and create time. Like a compaction, the new file replaces the old one only once
+/// it is complete. A torn record at the end of the legacy file is dropped. Returns the
+/// number of converted records.
+pub fn convert_legacy_segment_file(
+    segment_name: &str,
+    file_path: &str,
+) -> Result<u64, JournalServerError> {
+    let file = std::fs::File::open(file_path)?;
+    let file_len = file.metadata()?.len();
+    let mut reader = std::io::BufReader::new(file);
+
+    let convert_file = format!("{}{}", file_path, SEGMENT_COMPACT_FILE_SUFFIX);
+    let mut writer = std::io::BufWriter::new(std::fs::File::create(&convert_file)?);
+
+    let mut position = 0;
+    let mut converted = 0;
+    loop {
+        let mut buf = [0u8; LEGACY_RECORD_HEADER_LEN as usize];
+        if let Err(e) = reader.read_exact(&mut buf) {
+            if e.kind() == ErrorKind::UnexpectedEof {
+                break;
+            }
+            return Err(e.into());
+        }
+
+        let len = u32::from_be_bytes(buf[8..12].try_into().unwrap()) as u64;
+        if position + LEGACY_RECORD_HEADER_LEN + len > file_len || len > BATCH_MAX_LEN {
+            warn!(
+                "Segment {} drops the damaged legacy record at position {} during conversion",
+                segment_name, position
+            );
+            break;
+        }
+
+        let mut data = vec![0u8; len as usize];
+        reader.read_exact(&mut data)?;
+        let record = JournalRecord::decode(data.as_ref())?;
+        let batch = RecordBatch::build(
+            CompressionType::None,
+            record.offset,
+            record.create_time,
+            vec![record],
+        )?;
+        writer.write_all(&batch.encode())?;
+
+        position += LEGACY_RECORD_HEADER_LEN + len;
+        converted += 1;
+    }
+
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    std::fs::rename(&convert_file, file_path)?;
+    Ok(converted)
+}
+
+const LEGACY_RECORD_HEADER_LEN: u64 = 12;
+
 pub fn data_fold_shard(namespace: &str, shard_name: &str, data_fold: &str) -> String {
     let file_name = format!("{}/{}", namespace, shard_name);
     format!("{}/{}", data_fold, file_name)
@@ -299,12 +744,80 @@ pub fn data_file_segment(data_fold: &str, segment_no: u32) -> String {
     format!("{}/{}.msg", data_fold, segment_no)
 }
 
+pub fn data_file_segment_compact(data_fold: &str, segment_no: u32) -> String {
+    format!(
+        "{}/{}.msg{}",
+        data_fold, segment_no, SEGMENT_COMPACT_FILE_SUFFIX
+    )
+}
+
+pub const SEGMENT_COMPACT_FILE_SUFFIX: &str = ".compact";
+
+pub const SEGMENT_DAMAGED_FILE_SUFFIX: &str = ".damaged";
+
+// Splits records sorted by offset into runs of consecutive offsets, since the records of
+// a batch are addressed by the base offset of the batch.
+fn split_offset_runs(records: Vec<JournalRecord>) -> Vec<Vec<JournalRecord>> {
+    let mut results: Vec<Vec<JournalRecord>> = Vec::new();
+    for record in records {
+        if let Some(run) = results.last_mut() {
+            if run.last().unwrap().offset + 1 == record.offset {
+                run.push(record);
+                continue;
+            }
+        }
+        results.push(vec![record]);
+    }
+    results
+}
+
 #[cfg(test)]
 mod tests {
     use common_base::tools::{now_second, unique_id};
+    use prost::Message;
+    use protocol::journal_server::journal_engine::CompressionType;
     use protocol::journal_server::journal_record::JournalRecord;
 
-    use super::SegmentFile;
+    use super::{
+        convert_legacy_segment_file, data_file_segment, recover_segment_file, segment_file_format,
+        truncate_segment_file, verify_segment_file, SegmentFile, SegmentFileFormat,
+    };
+    use crate::core::error::JournalServerError;
+    use crate::segment::batch::{RecordBatch, BATCH_HEADER_LEN, BATCH_MAX_LEN};
+
+    fn build_batch(
+        compression: CompressionType,
+        base_offset: u64,
+        record_num: u64,
+        timestamp: u64,
+    ) -> RecordBatch {
+        let records: Vec<JournalRecord> = (0..record_num)
+            .map(|i| JournalRecord {
+                content: format!("data1#-{}", base_offset + i).as_bytes().to_vec(),
+                key: format!("k{}", base_offset + i),
+                pkid: base_offset + i,
+                ..Default::default()
+            })
+            .collect();
+        RecordBatch::build(compression, base_offset, timestamp, records).unwrap()
+    }
+
+    async fn build_segment(batch_num: u64) -> SegmentFile {
+        let segment = SegmentFile::new(
+            unique_id(),
+            "s1".to_string(),
+            10,
+            "/tmp/jl/tests".to_string(),
+        );
+        segment.try_create().await.unwrap();
+
+        let batches: Vec<RecordBatch> = (0..batch_num)
+            .map(|i| build_batch(CompressionType::None, i, 1, 1000 + i))
+            .collect();
+        segment.write(&batches).await.unwrap();
+        segment
+    }
+
     #[tokio::test]
     async fn segment_create() {
         let data_fold = "/tmp/jl/tests";
@@ -339,29 +852,318 @@ mod tests {
 
         segment.try_create().await.unwrap();
         for i in 0..10 {
-            let value = format!("data1#-{}", i);
-            let record = JournalRecord {
-                content: value.as_bytes().to_vec(),
-                create_time: now_second(),
-                key: format!("k{}", i),
-                namespace: "n1".to_string(),
-                shard_name: "s1".to_string(),
-                offset: 1000 + i,
-                segment: 1,
-                tags: vec![],
-                ..Default::default()
-            };
-            match segment.write(&[record.clone()]).await {
+            let batch = build_batch(CompressionType::None, 1000 + i, 1, now_second());
+            match segment.write(&[batch]).await {
                 Ok(_) => {}
                 Err(e) => {
                     panic!("{:?}", e);
                 }
             }
         }
+        segment.sync().await.unwrap();
 
         let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        assert_eq!(res.len(), 10);
         for raw in res {
             println!("{:?}", raw);
         }
     }
+
+    #[tokio::test]
+    async fn segment_compressed_batch_rw_test() {
+        let segment = SegmentFile::new(
+            unique_id(),
+            "s1".to_string(),
+            10,
+            "/tmp/jl/tests".to_string(),
+        );
+        segment.try_create().await.unwrap();
+
+        let batches = vec![
+            build_batch(CompressionType::Lz4, 0, 10, 1000),
+            build_batch(CompressionType::Zstd, 10, 10, 1001),
+            build_batch(CompressionType::Snappy, 20, 10, 1002),
+        ];
+        let positions = segment.write(&batches).await.unwrap();
+        assert_eq!(positions.len(), 3);
+        assert_eq!(positions[0], 0);
+
+        // every record of a batch points at the position of the batch
+        let res = segment.read_by_offset(0, 15, 20000).await.unwrap();
+        assert_eq!(res.len(), 15);
+        assert_eq!(res.first().unwrap().record.offset, 15);
+        assert_eq!(res.first().unwrap().position, positions[1]);
+        assert_eq!(res.first().unwrap().record.key, "k15");
+        assert_eq!(res.first().unwrap().record.create_time, 1001);
+        assert_eq!(res.last().unwrap().record.offset, 29);
+
+        let res = segment
+            .read_by_positions(vec![positions[2], positions[2]], 20000)
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 10);
+        assert_eq!(res.first().unwrap().record.offset, 20);
+
+        let res = segment.read_by_timestamp(0, 1001, 20000).await.unwrap();
+        assert_eq!(res.len(), 20);
+
+        let res = segment.read_batches(0, 12, 20000).await.unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].header.base_offset, 10);
+        assert_eq!(res[0].payload, batches[1].payload);
+    }
+
+    #[tokio::test]
+    async fn segment_recover_torn_tail_test() {
+        let segment = build_segment(5).await;
+        let file_path = data_file_segment(&segment.data_fold, segment.segment_no);
+        let full_len = segment.size().await.unwrap();
+
+        // simulate a crash that only wrote part of the last batch
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&file_path)
+            .unwrap();
+        file.set_len(full_len - 3).unwrap();
+
+        let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        assert_eq!(res.len(), 4);
+
+        let recover_data = recover_segment_file("s1", &file_path).unwrap();
+        assert_eq!(recover_data.start_offset, 0);
+        assert_eq!(recover_data.end_offset, 3);
+        assert_eq!(recover_data.start_timestamp, 1000);
+        assert_eq!(recover_data.end_timestamp, 1003);
+        assert_eq!(
+            recover_data.valid_len + recover_data.truncated_len,
+            full_len - 3
+        );
+        assert_eq!(segment.size().await.unwrap(), recover_data.valid_len);
+
+        // new batches are appended right after the surviving data
+        let batch = build_batch(CompressionType::None, 4, 1, 1004);
+        let positions = segment.write(&[batch]).await.unwrap();
+        assert_eq!(positions[0], recover_data.valid_len);
+        let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        assert_eq!(res.len(), 5);
+        assert_eq!(res.last().unwrap().record.offset, 4);
+    }
+
+    #[tokio::test]
+    async fn segment_recover_oversized_len_test() {
+        let segment = build_segment(3).await;
+        let file_path = data_file_segment(&segment.data_fold, segment.segment_no);
+
+        // a damaged len field in the header of the third batch
+        let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        let position = res.get(2).unwrap().position as usize;
+        let mut data = std::fs::read(&file_path).unwrap();
+        data[position + 23..position + 27].copy_from_slice(&u32::MAX.to_be_bytes());
+        std::fs::write(&file_path, data).unwrap();
+
+        match segment.read_by_offset(0, 0, 20000).await {
+            Err(JournalServerError::SegmentRecordTooLarge(_, pos, len, max)) => {
+                assert_eq!(pos, position as u64);
+                assert_eq!(len, u32::MAX as u64);
+                assert_eq!(max, BATCH_MAX_LEN);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let recover_data = recover_segment_file("s1", &file_path).unwrap();
+        assert_eq!(recover_data.end_offset, 1);
+        assert_eq!(recover_data.valid_len, position as u64);
+        assert_eq!(segment.size().await.unwrap(), position as u64);
+    }
+
+    #[tokio::test]
+    async fn segment_verify_keeps_file_test() {
+        let segment = build_segment(3).await;
+        let file_path = data_file_segment(&segment.data_fold, segment.segment_no);
+        let full_len = segment.size().await.unwrap();
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .open(&file_path)
+            .unwrap();
+        file.set_len(full_len - 3).unwrap();
+
+        let verify_data = verify_segment_file("s1", &file_path).unwrap();
+        assert_eq!(verify_data.end_offset, 1);
+        assert!(verify_data.truncated_len > 0);
+        assert_eq!(segment.size().await.unwrap(), full_len - 3);
+    }
+
+    #[tokio::test]
+    async fn segment_file_version_not_supported_test() {
+        let segment = build_segment(1).await;
+        let file_path = data_file_segment(&segment.data_fold, segment.segment_no);
+        assert_eq!(
+            segment_file_format(&file_path).unwrap(),
+            SegmentFileFormat::Batch
+        );
+
+        let mut data = std::fs::read(&file_path).unwrap();
+        data[1] = 9;
+        std::fs::write(&file_path, data).unwrap();
+
+        match segment_file_format(&file_path) {
+            Err(JournalServerError::SegmentFileFormatNotSupported(_, version)) => {
+                assert_eq!(version, 9);
+            }
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn segment_legacy_file_convert_test() {
+        let segment = SegmentFile::new(
+            unique_id(),
+            "s1".to_string(),
+            10,
+            "/tmp/jl/tests".to_string(),
+        );
+        segment.try_create().await.unwrap();
+        let file_path = data_file_segment(&segment.data_fold, segment.segment_no);
+
+        // offset:u64 | len:u32 | JournalRecord, with a torn record at the end
+        let mut data = Vec::new();
+        for i in 0..3 {
+            let record = JournalRecord {
+                content: format!("data1#-{}", i).as_bytes().to_vec(),
+                key: format!("k{}", i),
+                offset: i,
+                create_time: 1000 + i,
+                ..Default::default()
+            };
+            let buf = record.encode_to_vec();
+            data.extend_from_slice(&i.to_be_bytes());
+            data.extend_from_slice(&(buf.len() as u32).to_be_bytes());
+            data.extend_from_slice(&buf);
+        }
+        data.truncate(data.len() - 2);
+        std::fs::write(&file_path, data).unwrap();
+
+        assert_eq!(
+            segment_file_format(&file_path).unwrap(),
+            SegmentFileFormat::Legacy
+        );
+        assert_eq!(convert_legacy_segment_file("s1", &file_path).unwrap(), 2);
+        assert_eq!(
+            segment_file_format(&file_path).unwrap(),
+            SegmentFileFormat::Batch
+        );
+
+        let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[1].record.offset, 1);
+        assert_eq!(res[1].record.key, "k1");
+        assert_eq!(res[1].record.create_time, 1001);
+    }
+
+    #[tokio::test]
+    async fn segment_truncate_to_high_watermark_test() {
+        let segment = build_segment(5).await;
+        let file_path = data_file_segment(&segment.data_fold, segment.segment_no);
+
+        let recover_data = truncate_segment_file("s1", &file_path, 2).unwrap();
+        assert_eq!(recover_data.start_offset, 0);
+        assert_eq!(recover_data.end_offset, 2);
+        assert_eq!(recover_data.end_timestamp, 1002);
+        assert_eq!(segment.size().await.unwrap(), recover_data.valid_len);
+
+        let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        assert_eq!(res.len(), 3);
+        assert_eq!(res.last().unwrap().record.offset, 2);
+
+        // nothing was committed yet, all data goes
+        let recover_data = truncate_segment_file("s1", &file_path, -1).unwrap();
+        assert_eq!(recover_data.end_offset, -1);
+        assert_eq!(segment.size().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn segment_record_crc_mismatch_test() {
+        let segment = build_segment(3).await;
+        let file_path = data_file_segment(&segment.data_fold, segment.segment_no);
+
+        // flip a byte in the payload of the second batch
+        let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        let position = res.get(1).unwrap().position;
+        let mut data = std::fs::read(&file_path).unwrap();
+        let index = (position + BATCH_HEADER_LEN) as usize;
+        data[index] ^= 0xFF;
+        std::fs::write(&file_path, data).unwrap();
+
+        match segment.read_by_offset(0, 0, 20000).await {
+            Err(JournalServerError::SegmentRecordCrcMismatch(_, pos, _, _)) => {
+                assert_eq!(pos, position);
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let recover_data = recover_segment_file("s1", &file_path).unwrap();
+        assert_eq!(recover_data.end_offset, 0);
+        assert_eq!(recover_data.valid_len, position);
+
+        let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        assert_eq!(res.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn segment_compact_test() {
+        let segment = SegmentFile::new(
+            unique_id(),
+            "s1".to_string(),
+            10,
+            "/tmp/jl/tests".to_string(),
+        );
+        segment.try_create().await.unwrap();
+
+        let batches = vec![
+            build_batch(CompressionType::Zstd, 0, 10, 1000),
+            build_batch(CompressionType::None, 10, 10, 1001),
+        ];
+        segment.write(&batches).await.unwrap();
+
+        // nothing to remove leaves the file untouched
+        let size = segment.size().await.unwrap();
+        assert_eq!(segment.compact(|_| true).await.unwrap(), 0);
+        assert_eq!(segment.size().await.unwrap(), size);
+
+        // drop the odd offsets of the first batch and offsets 15..17 of the second one
+        let removed = segment
+            .compact(|record| {
+                if record.offset < 10 {
+                    record.offset % 2 == 0
+                } else {
+                    !(15..=17).contains(&record.offset)
+                }
+            })
+            .await
+            .unwrap();
+        assert_eq!(removed, 8);
+
+        // the segment file is only replaced once the compacted file is swapped in
+        assert_eq!(segment.size().await.unwrap(), size);
+        segment.replace_with_compact().await.unwrap();
+
+        let res = segment.read_by_offset(0, 0, 20000).await.unwrap();
+        let offsets: Vec<u64> = res.iter().map(|raw| raw.record.offset).collect();
+        assert_eq!(offsets, vec![0, 2, 4, 6, 8, 10, 11, 12, 13, 14, 18, 19]);
+        for raw in res.iter() {
+            assert_eq!(raw.record.key, format!("k{}", raw.record.offset));
+            let timestamp = if raw.record.offset < 10 { 1000 } else { 1001 };
+            assert_eq!(raw.record.create_time, timestamp);
+        }
+
+        // reading from an offset that was compacted away starts at the next record
+        let res = segment.read_by_offset(0, 15, 20000).await.unwrap();
+        assert_eq!(res.first().unwrap().record.offset, 18);
+
+        let file_path = data_file_segment(&segment.data_fold, segment.segment_no);
+        let recover_data = recover_segment_file("s1", &file_path).unwrap();
+        assert_eq!(recover_data.truncated_len, 0);
+        assert_eq!(recover_data.end_offset, 19);
+    }
 }
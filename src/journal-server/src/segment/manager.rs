@@ -18,16 +18,23 @@ use std::sync::Arc;
 
 use common_base::config::journal_server::journal_server_conf;
 use dashmap::DashMap;
-use log::error;
+use log::{error, warn};
 use metadata_struct::journal::segment::{segment_name, JournalSegment};
 use rocksdb_engine::RocksDBEngine;
 
-use super::file::SegmentFile;
+use super::file::{
+    convert_legacy_segment_file, recover_segment_file, segment_file_format, verify_segment_file,
+    SegmentFile, SegmentFileFormat, SegmentRecoverData, SEGMENT_COMPACT_FILE_SUFFIX,
+    SEGMENT_DAMAGED_FILE_SUFFIX,
+};
 use super::SegmentIdentity;
+use crate::core::cache::CacheManager;
 use crate::core::error::JournalServerError;
+use crate::index::build::delete_segment_index;
 use crate::index::engine::storage_data_fold;
 use crate::index::offset::OffsetIndexManager;
 use crate::index::time::TimestampIndexManager;
+use crate::isr::checkpoint::{get_high_watermark_checkpoint, save_high_watermark_checkpoint};
 
 #[derive(Clone)]
 pub struct SegmentFileMetadata {
@@ -135,6 +142,7 @@ impl SegmentFileManager {
 
 pub fn load_local_segment_cache(
     dir: &Path,
+    cache_manager: &Arc<CacheManager>,
     rocksdb_engine_handler: &Arc<RocksDBEngine>,
     segment_file_manager: &Arc<SegmentFileManager>,
     local_data_folds: &Vec<String>,
@@ -154,6 +162,7 @@ pub fn load_local_segment_cache(
         if path.is_dir() {
             load_local_segment_cache(
                 &path,
+                cache_manager,
                 rocksdb_engine_handler,
                 segment_file_manager,
                 local_data_folds,
@@ -179,6 +188,20 @@ pub fn load_local_segment_cache(
             let shard_name = tmp_dir_slice.get(1).unwrap();
 
             let file_path = path.display().to_string();
+
+            // A compaction that was interrupted leaves its output behind, the segment file
+            // itself is only replaced once the output is complete.
+            if file_path.ends_with(SEGMENT_COMPACT_FILE_SUFFIX) {
+                warn!("Remove the unfinished compaction file {}", file_path);
+                fs::remove_file(&path)?;
+                continue;
+            }
+
+            // A damaged sealed segment is left aside for an operator to inspect.
+            if file_path.ends_with(SEGMENT_DAMAGED_FILE_SUFFIX) {
+                continue;
+            }
+
             let segment_file = file_path.split("/").last().unwrap();
             let segment = segment_file.replace(".msg", "");
             let segment_no = segment.parse::<u32>()?;
@@ -189,19 +212,77 @@ pub fn load_local_segment_cache(
                 segment_seq: segment_no,
             };
 
-            let start_offset = offset_manager.get_start_offset(&segment_iden)?;
+            // Files written before record batches existed are converted, their index
+            // positions no longer match
+            let is_converted = segment_file_format(&file_path)? == SegmentFileFormat::Legacy;
+            if is_converted {
+                let converted = convert_legacy_segment_file(&segment_iden.name(), &file_path)?;
+                warn!(
+                    "Segment {} converted {} records from the legacy file format",
+                    segment_iden.name(),
+                    converted
+                );
+            }
+
+            // Only the active segment can end in a torn write. A sealed segment was complete
+            // when it was sealed, so damage in it is corruption of committed records: the file
+            // is moved aside instead of being truncated.
+            let is_seal_up = cache_manager
+                .get_segment(&segment_iden)
+                .is_some_and(|segment| segment.is_seal_up());
+            let recover_data = if is_seal_up {
+                let recover_data = verify_segment_file(&segment_iden.name(), &file_path)?;
+                if recover_data.truncated_len > 0 {
+                    let damaged_file = format!("{}{}", file_path, SEGMENT_DAMAGED_FILE_SUFFIX);
+                    fs::rename(&path, &damaged_file)?;
+                    error!(
+                        "{}",
+                        JournalServerError::SegmentFileDamaged(
+                            file_path,
+                            recover_data.valid_len,
+                            damaged_file
+                        )
+                    );
+                    continue;
+                }
+                recover_data
+            } else {
+                // Drop any torn or corrupted tail left behind by a crash, then rebuild the
+                // local offsets and timestamps from the records that survived.
+                let recover_data = recover_segment_file(&segment_iden.name(), &file_path)?;
+                if recover_data.truncated_len > 0 {
+                    warn!(
+                        "Segment {} truncated {} bytes of damaged data at position {} during recovery",
+                        segment_iden.name(),
+                        recover_data.truncated_len,
+                        recover_data.valid_len
+                    );
+                }
+                recover_data
+            };
+
             let end_offset = offset_manager.get_end_offset(&segment_iden)?;
-            let start_timestamp = timestamp_manager.get_start_timestamp(&segment_iden)?;
-            let end_timestamp = timestamp_manager.get_end_timestamp(&segment_iden)?;
+            if is_converted
+                || recover_data.truncated_len > 0
+                || (recover_data.end_offset >= 0 && recover_data.end_offset != end_offset as i64)
+            {
+                rebuild_segment_index(
+                    rocksdb_engine_handler,
+                    &offset_manager,
+                    &timestamp_manager,
+                    &segment_iden,
+                    &recover_data,
+                )?;
+            }
 
             let metadata = SegmentFileMetadata {
                 namespace: namespace.to_string(),
                 shard_name: shard_name.to_string(),
                 segment_no,
-                start_offset: start_offset as i64,
-                end_offset: end_offset as i64,
-                start_timestamp: start_timestamp as i64,
-                end_timestamp: end_timestamp as i64,
+                start_offset: recover_data.start_offset,
+                end_offset: recover_data.end_offset,
+                start_timestamp: recover_data.start_timestamp,
+                end_timestamp: recover_data.end_timestamp,
             };
 
             segment_file_manager.add_segment_file(metadata);
@@ -210,6 +291,31 @@ pub fn load_local_segment_cache(
     Ok(())
 }
 
+// Index entries may point at records that no longer exist, so the whole index of the
+// segment is dropped and the build thread rebuilds it from the surviving data. The high
+// watermark checkpoint lives next to the index and is kept.
+pub(crate) fn rebuild_segment_index(
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    offset_manager: &OffsetIndexManager,
+    timestamp_manager: &TimestampIndexManager,
+    segment_iden: &SegmentIdentity,
+    recover_data: &SegmentRecoverData,
+) -> Result<(), JournalServerError> {
+    let mut checkpoint = get_high_watermark_checkpoint(rocksdb_engine_handler, segment_iden)?;
+    delete_segment_index(rocksdb_engine_handler, segment_iden)?;
+    checkpoint.high_watermark = checkpoint.high_watermark.min(recover_data.end_offset);
+    save_high_watermark_checkpoint(rocksdb_engine_handler, segment_iden, checkpoint)?;
+    if recover_data.end_offset < 0 {
+        return Ok(());
+    }
+
+    offset_manager.save_start_offset(segment_iden, recover_data.start_offset as u64)?;
+    offset_manager.save_end_offset(segment_iden, recover_data.end_offset as u64)?;
+    timestamp_manager.save_start_timestamp(segment_iden, recover_data.start_timestamp as u64)?;
+    timestamp_manager.save_end_timestamp(segment_iden, recover_data.end_timestamp as u64)?;
+    Ok(())
+}
+
 pub fn metadata_and_local_segment_diff_check() {
     //todo
 }
@@ -273,6 +379,7 @@ mod tests {
     use rocksdb_engine::RocksDBEngine;
 
     use super::{load_local_segment_cache, SegmentFileManager};
+    use crate::core::cache::CacheManager;
     use crate::index::engine::{column_family_list, storage_data_fold};
 
     #[tokio::test]
@@ -286,11 +393,13 @@ mod tests {
         ));
         let segment_file_manager =
             Arc::new(SegmentFileManager::new(rocksdb_engine_handler.clone()));
+        let cache_manager = Arc::new(CacheManager::new());
 
         for path in data_fold.clone() {
             let path = Path::new(&path);
             load_local_segment_cache(
                 path,
+                &cache_manager,
                 &rocksdb_engine_handler,
                 &segment_file_manager,
                 &data_fold,
@@ -14,8 +14,11 @@
 
 use metadata_struct::journal::segment::{segment_name, JournalSegment};
 
+pub mod batch;
+pub mod compaction;
 pub mod file;
 pub mod manager;
+pub mod producer;
 pub mod read;
 pub mod scroll;
 pub mod write;
@@ -0,0 +1,432 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+
+use common_base::config::journal_server::journal_server_conf;
+use grpc_clients::journal::inner::call::journal_inner_fetch_producer_state;
+use grpc_clients::pool::ClientPool;
+use log::info;
+use metadata_struct::journal::shard::shard_name_iden;
+use protocol::journal_server::journal_inner::FetchProducerStateRequest;
+use protocol::journal_server::journal_record::JournalRecord;
+use rocksdb_engine::engine::{rocksdb_engine_get, rocksdb_engine_prefix_map, rocksdb_engine_save};
+use rocksdb_engine::RocksDBEngine;
+use serde::{Deserialize, Serialize};
+
+use crate::core::cache::CacheManager;
+use crate::core::consts::{DB_COLUMN_FAMILY_INDEX, PRODUCER_STATE_WINDOW};
+use crate::core::error::JournalServerError;
+use crate::segment::SegmentIdentity;
+
+/// The sequence numbers a producer has written to a shard. The `pkid` of a record is its
+/// sequence number, the producer numbers the records of every shard one by one.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProducerState {
+    pub last_sequence: u64,
+    // sequence and offset of the latest records, answers the retry of a record already written
+    pub recent: VecDeque<(u64, u64)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SequenceCheck {
+    New,
+    // the offset the record was written at
+    Duplicate(u64),
+    // a gap or a record sent out of order, none of them can be written
+    OutOfOrder,
+}
+
+impl ProducerState {
+    pub fn check(&self, sequence: u64) -> SequenceCheck {
+        if sequence == self.last_sequence + 1 {
+            return SequenceCheck::New;
+        }
+        if let Some((_, offset)) = self.recent.iter().find(|(seq, _)| *seq == sequence) {
+            return SequenceCheck::Duplicate(*offset);
+        }
+        SequenceCheck::OutOfOrder
+    }
+
+    pub fn record(&mut self, sequence: u64, offset: u64) {
+        self.last_sequence = self.last_sequence.max(sequence);
+        self.recent.push_back((sequence, offset));
+        while self.recent.len() > PRODUCER_STATE_WINDOW {
+            self.recent.pop_front();
+        }
+    }
+}
+
+/// Tracks the producer states of the shards this node writes or replicates. The states are
+/// kept in RocksDB next to the indexes, so a restarted node or a follower that becomes the
+/// leader still recognizes the retries of records written before.
+pub struct ProducerStateManager {
+    cache_manager: Arc<CacheManager>,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+}
+
+impl ProducerStateManager {
+    pub fn new(
+        cache_manager: Arc<CacheManager>,
+        rocksdb_engine_handler: Arc<RocksDBEngine>,
+    ) -> Self {
+        ProducerStateManager {
+            cache_manager,
+            rocksdb_engine_handler,
+        }
+    }
+
+    /// Splits the records of a batch into the ones to write and the retries of records
+    /// already written, returned by pkid with the offset they were written at. The batch is
+    /// rejected when a sequence number does not follow the previous one of its producer.
+    pub fn filter_duplicates(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        records: &[JournalRecord],
+    ) -> Result<(Vec<JournalRecord>, HashMap<u64, u64>), JournalServerError> {
+        let mut states: HashMap<String, Option<ProducerState>> = HashMap::new();
+        let mut new_records = Vec::new();
+        let mut duplicates = HashMap::new();
+        for record in records.iter() {
+            if record.producer_id.is_empty() {
+                new_records.push(record.clone());
+                continue;
+            }
+
+            if !states.contains_key(&record.producer_id) {
+                let state = self.get_state(namespace, shard_name, &record.producer_id)?;
+                states.insert(record.producer_id.clone(), state);
+            }
+
+            // the first record of a producer starts its sequence
+            let state = states.get_mut(&record.producer_id).unwrap();
+            let check = if let Some(state) = state {
+                state.check(record.pkid)
+            } else {
+                SequenceCheck::New
+            };
+
+            match check {
+                SequenceCheck::New => {
+                    // the next record of the batch follows this one
+                    let state = state.get_or_insert_with(ProducerState::default);
+                    state.last_sequence = record.pkid;
+                    new_records.push(record.clone());
+                }
+                SequenceCheck::Duplicate(offset) => {
+                    duplicates.insert(record.pkid, offset);
+                }
+                SequenceCheck::OutOfOrder => {
+                    let last_sequence = state.as_ref().map(|state| state.last_sequence);
+                    return Err(JournalServerError::OutOfOrderProducerSequence(
+                        shard_name_iden(namespace, shard_name),
+                        record.producer_id.clone(),
+                        last_sequence.unwrap_or_default() + 1,
+                        record.pkid,
+                    ));
+                }
+            }
+        }
+        Ok((new_records, duplicates))
+    }
+
+    /// Records the sequence numbers of written records, their offsets must be set.
+    pub fn update(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        records: &[JournalRecord],
+    ) -> Result<(), JournalServerError> {
+        let mut states: HashMap<String, ProducerState> = HashMap::new();
+        for record in records.iter() {
+            if record.producer_id.is_empty() {
+                continue;
+            }
+
+            if !states.contains_key(&record.producer_id) {
+                let state = self
+                    .get_state(namespace, shard_name, &record.producer_id)?
+                    .unwrap_or_default();
+                states.insert(record.producer_id.clone(), state);
+            }
+
+            if let Some(state) = states.get_mut(&record.producer_id) {
+                state.record(record.pkid, record.offset);
+            }
+        }
+
+        for (producer_id, state) in states {
+            rocksdb_engine_save(
+                self.rocksdb_engine_handler.clone(),
+                DB_COLUMN_FAMILY_INDEX,
+                producer_state_key(namespace, shard_name, &producer_id),
+                state.clone(),
+            )?;
+            self.cache_manager
+                .set_producer_state(namespace, shard_name, &producer_id, state);
+        }
+        Ok(())
+    }
+
+    /// The producer states of a shard kept on this node, by producer id.
+    pub fn list_states(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+    ) -> Result<HashMap<String, ProducerState>, JournalServerError> {
+        let prefix = producer_state_prefix(namespace, shard_name);
+        let mut results = HashMap::new();
+        for raw in rocksdb_engine_prefix_map(
+            self.rocksdb_engine_handler.clone(),
+            DB_COLUMN_FAMILY_INDEX,
+            prefix.clone(),
+        )?
+        .iter()
+        {
+            if let Some(producer_id) = raw.key().strip_prefix(&prefix) {
+                let state = serde_json::from_slice::<ProducerState>(&raw.value().data)?;
+                results.insert(producer_id.to_string(), state);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Takes over producer states written on other nodes, a state only replaces the local
+    /// one of the same producer when it has seen later sequence numbers.
+    pub fn merge_states(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        states: HashMap<String, ProducerState>,
+    ) -> Result<(), JournalServerError> {
+        for (producer_id, state) in states {
+            if let Some(local) = self.get_state(namespace, shard_name, &producer_id)? {
+                if local.last_sequence >= state.last_sequence {
+                    continue;
+                }
+            }
+            rocksdb_engine_save(
+                self.rocksdb_engine_handler.clone(),
+                DB_COLUMN_FAMILY_INDEX,
+                producer_state_key(namespace, shard_name, &producer_id),
+                state.clone(),
+            )?;
+            self.cache_manager
+                .set_producer_state(namespace, shard_name, &producer_id, state);
+        }
+        Ok(())
+    }
+
+    /// The replicas of a new segment may not hold any segment of the shard before it, so the
+    /// producer states are taken over from the previous segment before the first write. Without
+    /// them the retry of a record written just before the segment rolled would be written again.
+    pub async fn load_previous_segment_states(
+        &self,
+        client_pool: &Arc<ClientPool>,
+        segment_iden: &SegmentIdentity,
+    ) -> Result<(), JournalServerError> {
+        if segment_iden.segment_seq == 0 {
+            return Ok(());
+        }
+        let previous_iden = SegmentIdentity::new(
+            &segment_iden.namespace,
+            &segment_iden.shard_name,
+            segment_iden.segment_seq - 1,
+        );
+        let previous = if let Some(segment) = self.cache_manager.get_segment(&previous_iden) {
+            segment
+        } else {
+            // the previous segment has been deleted, its producer states went with it
+            return Ok(());
+        };
+
+        let conf = journal_server_conf();
+        if previous.leader == conf.node_id {
+            return Ok(());
+        }
+
+        // the leader of the previous segment first, any of its replicas has the states as well
+        let mut node_ids = vec![previous.leader];
+        node_ids.extend(
+            previous
+                .replicas
+                .iter()
+                .map(|replica| replica.node_id)
+                .filter(|node_id| *node_id != previous.leader && *node_id != conf.node_id),
+        );
+        let addrs: Vec<String> = node_ids
+            .iter()
+            .filter_map(|node_id| self.cache_manager.get_node(*node_id))
+            .map(|node| node.node_inner_addr)
+            .collect();
+        if addrs.is_empty() {
+            return Err(JournalServerError::NodeNotExist(previous.leader));
+        }
+
+        let request = FetchProducerStateRequest {
+            cluster_name: conf.cluster_name.clone(),
+            namespace: segment_iden.namespace.clone(),
+            shard_name: segment_iden.shard_name.clone(),
+        };
+        let reply =
+            journal_inner_fetch_producer_state(client_pool.clone(), &addrs, request).await?;
+        let states = serde_json::from_slice::<HashMap<String, ProducerState>>(&reply.states)?;
+        info!(
+            "Segment {} took over {} producer states from segment {}",
+            segment_iden.name(),
+            states.len(),
+            previous_iden.name()
+        );
+        self.merge_states(&segment_iden.namespace, &segment_iden.shard_name, states)
+    }
+
+    fn get_state(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        producer_id: &str,
+    ) -> Result<Option<ProducerState>, JournalServerError> {
+        if let Some(state) =
+            self.cache_manager
+                .get_producer_state(namespace, shard_name, producer_id)
+        {
+            return Ok(Some(state));
+        }
+
+        if let Some(res) = rocksdb_engine_get(
+            self.rocksdb_engine_handler.clone(),
+            DB_COLUMN_FAMILY_INDEX,
+            producer_state_key(namespace, shard_name, producer_id),
+        )? {
+            let state = serde_json::from_slice::<ProducerState>(&res.data)?;
+            self.cache_manager.set_producer_state(
+                namespace,
+                shard_name,
+                producer_id,
+                state.clone(),
+            );
+            return Ok(Some(state));
+        }
+        Ok(None)
+    }
+}
+
+fn producer_state_prefix(namespace: &str, shard_name: &str) -> String {
+    format!("/producer/{}/{}/", namespace, shard_name)
+}
+
+fn producer_state_key(namespace: &str, shard_name: &str, producer_id: &str) -> String {
+    format!(
+        "{}{}",
+        producer_state_prefix(namespace, shard_name),
+        producer_id
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use common_base::tools::unique_id;
+    use protocol::journal_server::journal_record::JournalRecord;
+    use rocksdb_engine::RocksDBEngine;
+
+    use super::{ProducerState, ProducerStateManager, SequenceCheck};
+    use crate::core::cache::CacheManager;
+    use crate::core::consts::PRODUCER_STATE_WINDOW;
+    use crate::index::engine::{column_family_list, storage_data_fold};
+
+    fn build_manager() -> ProducerStateManager {
+        let data_fold = vec![format!("/tmp/tests/{}", unique_id())];
+        let rocksdb_engine_handler = Arc::new(RocksDBEngine::new(
+            &storage_data_fold(&data_fold),
+            10000,
+            column_family_list(),
+        ));
+        ProducerStateManager::new(Arc::new(CacheManager::new()), rocksdb_engine_handler)
+    }
+
+    fn build_record(producer_id: &str, pkid: u64, offset: u64) -> JournalRecord {
+        JournalRecord {
+            producer_id: producer_id.to_string(),
+            pkid,
+            offset,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn producer_state_check_test() {
+        let mut state = ProducerState::default();
+        for sequence in 1..=3 {
+            state.record(sequence, 100 + sequence);
+        }
+        assert_eq!(state.last_sequence, 3);
+        assert_eq!(state.check(4), SequenceCheck::New);
+        assert_eq!(state.check(2), SequenceCheck::Duplicate(102));
+
+        // a gap means that a record before it was not written
+        assert_eq!(state.check(5), SequenceCheck::OutOfOrder);
+
+        for sequence in 4..(4 + PRODUCER_STATE_WINDOW as u64) {
+            state.record(sequence, 100 + sequence);
+        }
+        assert_eq!(state.recent.len(), PRODUCER_STATE_WINDOW);
+        // older than the window, it cannot be told from a record that was never written
+        assert_eq!(state.check(1), SequenceCheck::OutOfOrder);
+        assert_eq!(state.check(4), SequenceCheck::Duplicate(104));
+    }
+
+    #[test]
+    fn producer_state_handover_test() {
+        // the leader of the previous segment wrote p1 up to 3 and p2 up to 1
+        let previous = build_manager();
+        let records = vec![
+            build_record("p1", 1, 0),
+            build_record("p1", 2, 1),
+            build_record("p1", 3, 2),
+            build_record("p2", 1, 3),
+        ];
+        previous.update("n1", "s1", &records).unwrap();
+
+        // the leader of the new segment already saw a later record of p2
+        let next = build_manager();
+        next.update("n1", "s1", &[build_record("p2", 2, 4)])
+            .unwrap();
+        next.merge_states("n1", "s1", previous.list_states("n1", "s1").unwrap())
+            .unwrap();
+
+        // the retry of a record written before the roll is answered with its offset
+        let (new_records, duplicates) = next
+            .filter_duplicates("n1", "s1", &[build_record("p1", 3, 0)])
+            .unwrap();
+        assert!(new_records.is_empty());
+        assert_eq!(duplicates.get(&3), Some(&2));
+
+        // a gap after the roll is still rejected
+        assert!(next
+            .filter_duplicates("n1", "s1", &[build_record("p1", 5, 0)])
+            .is_err());
+        let (new_records, _) = next
+            .filter_duplicates("n1", "s1", &[build_record("p1", 4, 0)])
+            .unwrap();
+        assert_eq!(new_records.len(), 1);
+
+        // the later local state of p2 is kept
+        let states = next.list_states("n1", "s1").unwrap();
+        assert_eq!(states.get("p2").unwrap().last_sequence, 2);
+    }
+}
@@ -12,6 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::HashSet;
 use std::sync::Arc;
 
 use protocol::journal_server::journal_engine::{
@@ -26,6 +27,7 @@ use crate::core::error::JournalServerError;
 use crate::index::offset::OffsetIndexManager;
 use crate::index::tag::TagIndexManager;
 use crate::index::time::TimestampIndexManager;
+use crate::index::IndexData;
 
 pub async fn read_data_req(
     cache_manager: &Arc<CacheManager>,
@@ -85,9 +87,13 @@ pub async fn read_data_req(
             ReadReqOptions {
                 max_size: 1024 * 1024,
                 max_record: 100,
+                ..Default::default()
             }
         };
 
+        // positions from the index must be read from the file they were built for
+        let segment_file_lock = cache_manager.get_segment_file_lock(&segment_iden);
+        let _guard = segment_file_lock.read().await;
         let read_data_list = match raw.ready_type() {
             ReadType::Offset => {
                 read_by_offset(
@@ -142,6 +148,7 @@ pub async fn read_data_req(
                 key: record.key,
                 value: record.content,
                 tags: record.tags,
+                timestamp: record.create_time,
             });
         }
         shard_message.messages = record_message;
@@ -208,10 +215,7 @@ async fn read_by_key(
         )
         .await?;
 
-    let positions = index_data_list.iter().map(|raw| raw.position).collect();
-    segment_file
-        .read_by_positions(positions, read_options.max_size)
-        .await
+    read_by_index(segment_file, index_data_list, read_options).await
 }
 
 async fn read_by_tag(
@@ -231,10 +235,25 @@ async fn read_by_tag(
         )
         .await?;
 
+    read_by_index(segment_file, index_data_list, read_options).await
+}
+
+// The index points at the batch holding each record, so only the indexed records are
+// kept from the batches that are read.
+async fn read_by_index(
+    segment_file: &SegmentFile,
+    index_data_list: Vec<IndexData>,
+    read_options: &ReadReqOptions,
+) -> Result<Vec<ReadData>, JournalServerError> {
+    let offsets: HashSet<u64> = index_data_list.iter().map(|raw| raw.offset).collect();
     let positions = index_data_list.iter().map(|raw| raw.position).collect();
-    segment_file
+    let res = segment_file
         .read_by_positions(positions, read_options.max_size)
-        .await
+        .await?;
+    Ok(res
+        .into_iter()
+        .filter(|read_data| offsets.contains(&read_data.record.offset))
+        .collect())
 }
 
 #[cfg(test)]
@@ -17,13 +17,17 @@ use std::sync::Arc;
 use std::time::Duration;
 
 use common_base::tools::now_second;
+use futures::future::join_all;
 use grpc_clients::pool::ClientPool;
-use log::error;
+use log::{error, warn};
 use metadata_struct::journal::segment::SegmentStatus;
+use prost::Message;
+use protocol::journal_server::compression::compress;
 use protocol::journal_server::journal_engine::{
-    WriteReqBody, WriteRespMessage, WriteRespMessageStatus,
+    AckMode, CompressionType, JournalEngineError, WriteReqBody, WriteReqSegmentMessages,
+    WriteRespMessage, WriteRespMessageStatus,
 };
-use protocol::journal_server::journal_record::JournalRecord;
+use protocol::journal_server::journal_record::{JournalRecord, JournalRecordBatch};
 use rocksdb_engine::RocksDBEngine;
 use tokio::select;
 use tokio::sync::mpsc::{self, Receiver, Sender};
@@ -31,12 +35,21 @@ use tokio::sync::{broadcast, oneshot};
 use tokio::time::timeout;
 
 use crate::core::cache::CacheManager;
-use crate::core::error::JournalServerError;
-use crate::core::segment_meta::{update_meta_end_timestamp, update_meta_start_timestamp};
+use crate::core::consts::DEFAULT_WRITE_ACK_TIMEOUT_MS;
+use crate::core::error::{get_journal_server_code, JournalServerError};
+use crate::core::segment::segment_validator;
+use crate::core::segment_meta::{
+    update_meta_end_timestamp, update_meta_size, update_meta_start_timestamp,
+};
 use crate::core::segment_status::sealup_segment;
 use crate::index::build::try_trigger_build_index;
-use crate::segment::file::{open_segment_write, SegmentFile};
+use crate::isr::IsrManager;
+use crate::segment::batch::{decode_producer_batch, RecordBatch};
+use crate::segment::file::{
+    data_file_segment, open_segment_write, truncate_segment_file, SegmentFile,
+};
 use crate::segment::manager::SegmentFileManager;
+use crate::segment::producer::ProducerStateManager;
 use crate::segment::SegmentIdentity;
 
 #[derive(Clone)]
@@ -47,7 +60,12 @@ pub struct SegmentWrite {
 }
 
 pub struct SegmentWriteData {
+    // decoded records of the batch, used to assign offsets and answer the producer
     data: Vec<JournalRecord>,
+    // the batch payload as it is stored in the segment file
+    compression: CompressionType,
+    payload: Vec<u8>,
+    sync: bool,
     resp_sx: oneshot::Sender<SegmentWriteResp>,
 }
 
@@ -63,84 +81,175 @@ pub async fn write_data_req(
     rocksdb_engine_handler: &Arc<RocksDBEngine>,
     segment_file_manager: &Arc<SegmentFileManager>,
     client_pool: &Arc<ClientPool>,
+    isr_manager: &Arc<IsrManager>,
     req_body: &WriteReqBody,
-) -> Result<Vec<WriteRespMessage>, JournalServerError> {
-    let mut results = Vec::new();
-    for shard_data in req_body.data.clone() {
+) -> Vec<WriteRespMessage> {
+    let ack_timeout = if req_body.timeout_ms > 0 {
+        Duration::from_millis(req_body.timeout_ms)
+    } else {
+        Duration::from_millis(DEFAULT_WRITE_ACK_TIMEOUT_MS)
+    };
+
+    // The shards are written concurrently and each gets its own result, so a shard that
+    // waits for its replicas or fails neither holds back nor fails the others
+    let writes = req_body.data.iter().map(|shard_data| async move {
         let mut resp_message = WriteRespMessage {
             namespace: shard_data.namespace.clone(),
             shard_name: shard_data.shard_name.clone(),
             segment: shard_data.segment,
             ..Default::default()
         };
+        match write_shard_data(
+            cache_manager,
+            rocksdb_engine_handler,
+            segment_file_manager,
+            client_pool,
+            isr_manager,
+            shard_data,
+            ack_timeout,
+        )
+        .await
+        {
+            Ok(messages) => {
+                resp_message.messages = messages;
+            }
+            Err(e) => {
+                resp_message.error = Some(JournalEngineError {
+                    code: get_journal_server_code(&e),
+                    error: e.to_string(),
+                });
+            }
+        }
+        resp_message
+    });
+    join_all(writes).await
+}
+
+async fn write_shard_data(
+    cache_manager: &Arc<CacheManager>,
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    segment_file_manager: &Arc<SegmentFileManager>,
+    client_pool: &Arc<ClientPool>,
+    isr_manager: &Arc<IsrManager>,
+    shard_data: &WriteReqSegmentMessages,
+    ack_timeout: Duration,
+) -> Result<Vec<WriteRespMessageStatus>, JournalServerError> {
+    let segment_iden = SegmentIdentity::new(
+        &shard_data.namespace,
+        &shard_data.shard_name,
+        shard_data.segment,
+    );
+    segment_validator(cache_manager, &segment_iden)?;
+
+    // The leader epoch the records are written in, acknowledging them after the
+    // leadership moved on would confirm records the new leader may have truncated
+    let leader_epoch = if let Some(segment) = cache_manager.get_segment(&segment_iden) {
+        segment.leader_epoch
+    } else {
+        return Err(JournalServerError::SegmentNotExist(segment_iden.name()));
+    };
 
-        let segment_iden = SegmentIdentity::new(
-            &shard_data.namespace,
-            &shard_data.shard_name,
-            shard_data.segment,
-        );
+    let ack_mode = shard_data.ack_mode();
+    if ack_mode == AckMode::All {
+        isr_manager.check_min_insync_replicas(&segment_iden)?;
+    }
 
-        let mut data_list = Vec::new();
+    // Records arrive either as a producer batch that is stored without recompressing
+    // it, or as single messages that the leader frames into a batch itself
+    let compression = shard_data.compression();
+    let (mut data_list, payload) = if !shard_data.batch.is_empty() {
+        let records = decode_producer_batch(compression, &shard_data.batch)?;
+        (records, shard_data.batch.clone())
+    } else {
+        let mut records = Vec::new();
         for message in shard_data.messages.iter() {
             // todo data validator
-            let record = JournalRecord {
+            records.push(JournalRecord {
                 content: message.value.clone(),
-                create_time: now_second(),
                 key: message.key.clone(),
-                namespace: shard_data.namespace.clone(),
-                shard_name: shard_data.shard_name.clone(),
-                segment: shard_data.segment,
                 tags: message.tags.clone(),
                 pkid: message.pkid,
+                producer_id: shard_data.producer_id.clone(),
                 ..Default::default()
-            };
-            data_list.push(record);
+            });
+        }
+        let data = JournalRecordBatch {
+            records: records.clone(),
         }
+        .encode_to_vec();
+        (records, compress(compression, &data)?)
+    };
 
-        let resp = write(
-            cache_manager,
-            rocksdb_engine_handler,
-            segment_file_manager,
-            client_pool,
-            &segment_iden,
-            data_list.clone(),
-        )
-        .await?;
+    // records stamped by the producer keep their own create time
+    let create_time = now_second();
+    for record in data_list.iter_mut() {
+        if record.create_time == 0 {
+            record.create_time = create_time;
+        }
+        record.namespace = shard_data.namespace.clone();
+        record.shard_name = shard_data.shard_name.clone();
+        record.segment = shard_data.segment;
+    }
+
+    let resp = write(
+        cache_manager,
+        rocksdb_engine_handler,
+        segment_file_manager,
+        client_pool,
+        &segment_iden,
+        data_list.clone(),
+        compression,
+        payload,
+        ack_mode != AckMode::None,
+    )
+    .await?;
 
-        if let Some(e) = resp.error {
-            return Err(e);
+    if let Some(e) = resp.error {
+        return Err(e);
+    }
+
+    // Wakes the long-poll reads of the segment. With replicas the high watermark
+    // only moves once the followers have fetched the records.
+    isr_manager.advance_high_watermark(&segment_iden);
+
+    // if position = 0, update start/timestamp
+    for (_, position) in resp.positions.iter() {
+        if *position == 0 {
+            let first_record = data_list.first().unwrap();
+            segment_position0_ac(
+                segment_file_manager,
+                client_pool,
+                &segment_iden,
+                *position as i64,
+                first_record.create_time,
+            )
+            .await?;
         }
+    }
 
-        // if position = 0, update start/timestamp
-        for (_, position) in resp.positions.iter() {
-            if *position == 0 {
-                let first_record = data_list.first().unwrap();
-                segment_position0_ac(
-                    segment_file_manager,
-                    client_pool,
-                    &segment_iden,
-                    *position as i64,
-                    first_record.create_time,
-                )
+    // With AckMode::All the response stays in the high watermark purgatory
+    // until the records have been replicated to all in-sync replicas
+    if ack_mode == AckMode::All {
+        if let Some(last_offset) = resp.offsets.values().max() {
+            isr_manager
+                .wait_high_watermark(&segment_iden, leader_epoch, *last_offset, ack_timeout)
                 .await?;
-            }
         }
+    }
 
-        let mut resp_message_status = Vec::new();
-        for (pkid, offset) in resp.offsets {
-            let status = WriteRespMessageStatus {
-                pkid,
-                offset,
-                ..Default::default()
-            };
-            resp_message_status.push(status);
-        }
-        resp_message.messages = resp_message_status;
-        results.push(resp_message);
+    let mut resp_message_status = Vec::new();
+    for (pkid, offset) in resp.offsets {
+        let status = WriteRespMessageStatus {
+            pkid,
+            offset,
+            ..Default::default()
+        };
+        resp_message_status.push(status);
     }
-    Ok(results)
+    Ok(resp_message_status)
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn write(
     cache_manager: &Arc<CacheManager>,
     rocksdb_engine_handler: &Arc<RocksDBEngine>,
@@ -148,6 +257,9 @@ async fn write(
     client_pool: &Arc<ClientPool>,
     segment_iden: &SegmentIdentity,
     data_list: Vec<JournalRecord>,
+    compression: CompressionType,
+    payload: Vec<u8>,
+    sync: bool,
 ) -> Result<SegmentWriteResp, JournalServerError> {
     let write = get_write(
         cache_manager,
@@ -161,6 +273,9 @@ async fn write(
     let (sx, rx) = oneshot::channel::<SegmentWriteResp>();
     let data = SegmentWriteData {
         data: data_list,
+        compression,
+        payload,
+        sync,
         resp_sx: sx,
     };
     write.data_sender.send(data).await?;
@@ -226,6 +341,11 @@ async fn start_segment_sync_write_thread(
 
     let mut local_segment_end_offset = segment_file_meta.end_offset;
     let (segment_write, max_file_size) = open_segment_write(&cache_manager, &segment_iden).await?;
+    let producer_state_manager =
+        ProducerStateManager::new(cache_manager.clone(), rocksdb_engine_handler.clone());
+    producer_state_manager
+        .load_previous_segment_states(&client_pool, &segment_iden)
+        .await?;
 
     tokio::spawn(async move {
         loop {
@@ -262,6 +382,7 @@ async fn start_segment_sync_write_thread(
                                         &packet,
                                         &segment_write,
                                         &segment_file_manager,
+                                        &producer_state_manager,
                                         &client_pool,
                                         local_segment_end_offset as u64).await
                                     {
@@ -304,6 +425,7 @@ async fn batch_write_segment(
     packet: &SegmentWriteData,
     segment_write: &SegmentFile,
     segment_file_manager: &Arc<SegmentFileManager>,
+    producer_state_manager: &ProducerStateManager,
     client_pool: &Arc<ClientPool>,
     mut local_segment_end_offset: u64,
 ) -> Result<(SegmentWriteResp, Option<u64>), JournalServerError> {
@@ -315,31 +437,94 @@ async fn batch_write_segment(
 
     let mut resp = SegmentWriteResp::default();
 
-    // build write data
+    // retries of records that were already written are answered with their first offset
+    let (mut records, duplicates) = producer_state_manager.filter_duplicates(
+        &segment_iden.namespace,
+        &segment_iden.shard_name,
+        &packet.data,
+    )?;
+    for (pkid, offset) in duplicates.iter() {
+        resp.offsets.insert(*pkid, *offset);
+    }
+    if records.is_empty() {
+        return Ok((resp, None));
+    }
+
+    // assign offsets, the whole batch is stored as one unit starting at the next offset
+    let previous_end_offset = local_segment_end_offset;
+    let base_offset = local_segment_end_offset + 1;
     let mut last_offset = None;
     let mut offsets = HashMap::new();
-    let mut records = Vec::new();
-    for mut record in packet.data.clone() {
-        record.offset = local_segment_end_offset + 1;
-        local_segment_end_offset = record.offset;
-
-        offsets.insert(record.pkid, record.offset);
-        last_offset = Some(record.offset);
+    for record in records.iter_mut() {
+        let offset = local_segment_end_offset + 1;
+        local_segment_end_offset = offset;
 
-        records.push(record);
+        record.offset = offset;
+        offsets.insert(record.pkid, offset);
+        last_offset = Some(offset);
     }
 
-    // batch write data
-    match segment_write.write(&records).await {
-        Ok(positions) => {
-            resp.offsets = offsets.clone();
-            resp.positions = positions;
+    // the producer payload is kept unless some of its records have to be left out
+    let batch_timestamp = records.iter().map(|r| r.create_time).max().unwrap();
+    let batch = if duplicates.is_empty() {
+        RecordBatch::new(
+            packet.compression,
+            base_offset,
+            records.len() as u32,
+            batch_timestamp,
+            packet.payload.clone(),
+        )
+    } else {
+        RecordBatch::build(
+            packet.compression,
+            base_offset,
+            batch_timestamp,
+            records.clone(),
+        )?
+    };
+
+    // write data, and fsync before acknowledging unless the client does not wait for the result
+    let mut write_result = segment_write.write(&[batch]).await;
+    if write_result.is_ok() && packet.sync {
+        if let Err(e) = segment_write.sync().await {
+            write_result = Err(e);
         }
+    }
+
+    let positions = match write_result {
+        Ok(positions) => positions,
         Err(e) => {
+            // the batch is not acknowledged, so whatever part of it reached the file is
+            // cut off again, the next batch then starts right after the last stored one
+            let file_path = data_file_segment(&segment_write.data_fold, segment_write.segment_no);
+            let recover_data = truncate_segment_file(
+                &segment_iden.name(),
+                &file_path,
+                previous_end_offset as i64,
+            )?;
+            warn!(
+                "Segment {} failed to write offsets {} to {}, truncated it back to offset {}, error message: {}",
+                segment_iden.name(),
+                base_offset,
+                local_segment_end_offset,
+                recover_data.end_offset,
+                e
+            );
             resp.error = Some(e);
+            return Ok((resp, None));
         }
+    };
+
+    resp.offsets.extend(offsets);
+    if let Some(position) = positions.first() {
+        resp.positions = records
+            .iter()
+            .map(|record| (record.pkid, *position))
+            .collect();
     }
 
+    producer_state_manager.update(&segment_iden.namespace, &segment_iden.shard_name, &records)?;
+
     // update local segment file end offset/Timestamp
     if let Some(end_offset) = last_offset {
         let last_recrd = records.last().unwrap();
@@ -434,6 +619,8 @@ async fn is_sealup_segment(
         && (local_segment_end_offset + packet_len) > segment_meta.end_offset as u64)
         || file_size >= max_file_size
     {
+        // the final size of the segment is what retention by bytes is based on
+        update_meta_size(client_pool.clone(), &segment_iden, file_size).await?;
         sealup_segment(cache_manager, client_pool, &segment_iden).await?;
         return Ok(true);
     }
@@ -18,8 +18,10 @@ use common_base::config::journal_server::journal_server_conf;
 use protocol::journal_server::journal_inner::journal_server_inner_service_server::JournalServerInnerService;
 use protocol::journal_server::journal_inner::{
     DeleteSegmentFileReply, DeleteSegmentFileRequest, DeleteShardFileReply, DeleteShardFileRequest,
-    GetSegmentDeleteStatusReply, GetSegmentDeleteStatusRequest, GetShardDeleteStatusReply,
-    GetShardDeleteStatusRequest, UpdateJournalCacheReply, UpdateJournalCacheRequest,
+    FetchProducerStateReply, FetchProducerStateRequest, FetchSegmentDataReply,
+    FetchSegmentDataRequest, GetSegmentDeleteStatusReply, GetSegmentDeleteStatusRequest,
+    GetShardDeleteStatusReply, GetShardDeleteStatusRequest, UpdateJournalCacheReply,
+    UpdateJournalCacheRequest,
 };
 use rocksdb_engine::RocksDBEngine;
 use tonic::{Request, Response, Status};
@@ -28,12 +30,16 @@ use crate::core::cache::CacheManager;
 use crate::core::notification::parse_notification;
 use crate::core::segment::{delete_local_segment, segment_already_delete};
 use crate::core::shard::{delete_local_shard, shard_is_delete};
+use crate::isr::fetch::fetch_segment_data_req;
+use crate::isr::IsrManager;
 use crate::segment::manager::SegmentFileManager;
+use crate::segment::producer::ProducerStateManager;
 
 pub struct GrpcJournalServerInnerService {
     cache_manager: Arc<CacheManager>,
     segment_file_manager: Arc<SegmentFileManager>,
     rocksdb_engine_handler: Arc<RocksDBEngine>,
+    isr_manager: Arc<IsrManager>,
 }
 
 impl GrpcJournalServerInnerService {
@@ -41,11 +47,13 @@ impl GrpcJournalServerInnerService {
         cache_manager: Arc<CacheManager>,
         segment_file_manager: Arc<SegmentFileManager>,
         rocksdb_engine_handler: Arc<RocksDBEngine>,
+        isr_manager: Arc<IsrManager>,
     ) -> Self {
         GrpcJournalServerInnerService {
             cache_manager,
             segment_file_manager,
             rocksdb_engine_handler,
+            isr_manager,
         }
     }
 }
@@ -157,4 +165,57 @@ impl JournalServerInnerService for GrpcJournalServerInnerService {
             }
         }
     }
+
+    async fn fetch_segment_data(
+        &self,
+        request: Request<FetchSegmentDataRequest>,
+    ) -> Result<Response<FetchSegmentDataReply>, Status> {
+        let req = request.into_inner();
+        let conf = journal_server_conf();
+        if req.cluster_name != conf.cluster_name {
+            return Ok(Response::new(FetchSegmentDataReply::default()));
+        }
+
+        match fetch_segment_data_req(
+            &self.cache_manager,
+            &self.rocksdb_engine_handler,
+            &self.isr_manager,
+            &req,
+        )
+        .await
+        {
+            Ok(reply) => {
+                return Ok(Response::new(reply));
+            }
+            Err(e) => {
+                return Err(Status::cancelled(e.to_string()));
+            }
+        }
+    }
+
+    async fn fetch_producer_state(
+        &self,
+        request: Request<FetchProducerStateRequest>,
+    ) -> Result<Response<FetchProducerStateReply>, Status> {
+        let req = request.into_inner();
+        let conf = journal_server_conf();
+        if req.cluster_name != conf.cluster_name {
+            return Ok(Response::new(FetchProducerStateReply::default()));
+        }
+
+        let producer_state_manager = ProducerStateManager::new(
+            self.cache_manager.clone(),
+            self.rocksdb_engine_handler.clone(),
+        );
+        let states = match producer_state_manager.list_states(&req.namespace, &req.shard_name) {
+            Ok(states) => states,
+            Err(e) => {
+                return Err(Status::cancelled(e.to_string()));
+            }
+        };
+        match serde_json::to_vec(&states) {
+            Ok(states) => Ok(Response::new(FetchProducerStateReply { states })),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
 }
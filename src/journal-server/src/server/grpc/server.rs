@@ -23,6 +23,7 @@ use rocksdb_engine::RocksDBEngine;
 use tonic::transport::Server;
 
 use crate::core::cache::CacheManager;
+use crate::isr::IsrManager;
 use crate::segment::manager::SegmentFileManager;
 use crate::server::grpc::admin::GrpcJournalServerAdminService;
 use crate::server::grpc::inner::GrpcJournalServerInnerService;
@@ -33,6 +34,7 @@ pub struct GrpcServer {
     cache_manager: Arc<CacheManager>,
     segment_file_manager: Arc<SegmentFileManager>,
     rocksdb_engine_handler: Arc<RocksDBEngine>,
+    isr_manager: Arc<IsrManager>,
 }
 
 impl GrpcServer {
@@ -42,6 +44,7 @@ impl GrpcServer {
         cache_manager: Arc<CacheManager>,
         segment_file_manager: Arc<SegmentFileManager>,
         rocksdb_engine_handler: Arc<RocksDBEngine>,
+        isr_manager: Arc<IsrManager>,
     ) -> Self {
         Self {
             port,
@@ -49,6 +52,7 @@ impl GrpcServer {
             cache_manager,
             segment_file_manager,
             rocksdb_engine_handler,
+            isr_manager,
         }
     }
     pub async fn start(&self) -> Result<(), CommonError> {
@@ -62,6 +66,7 @@ impl GrpcServer {
             self.cache_manager.clone(),
             self.segment_file_manager.clone(),
             self.rocksdb_engine_handler.clone(),
+            self.isr_manager.clone(),
         );
 
         Server::builder()
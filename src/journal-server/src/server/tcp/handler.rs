@@ -15,7 +15,9 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
+use common_base::config::journal_server::journal_server_conf;
 use log::{debug, error, info};
+use protocol::journal_server::codec::JournalEnginePacket;
 use tokio::select;
 use tokio::sync::broadcast;
 use tokio::sync::mpsc::{self, Receiver, Sender};
@@ -128,24 +130,23 @@ fn handler_child_process(
                     },
                     val = child_process_rx.recv()=>{
                         if let Some(packet) = val{
-                            if let Some(connect) = raw_connect_manager.get_connect(packet.connection_id) {
-                                if let Some(resp) = raw_command
-                                    .apply(raw_connect_manager.clone(), connect, packet.addr, packet.packet)
-                                    .await
-                                {
-                                    let response_package = ResponsePackage::new(packet.connection_id, resp);
-                                    match raw_response_queue_sx.send(response_package).await {
-                                        Ok(_) => {}
-                                        Err(err) => error!(
-                                            "Failed to write data to the response queue, error message: {:?}",
-                                            err
-                                        ),
-                                    }
-                                } else {
-                                    info!("{}","No backpacking is required for this request");
-                                }
+                            // A long-poll read may wait for new records, it must not hold up
+                            // the other requests of this handler process
+                            if is_long_poll_read(&packet.packet) {
+                                tokio::spawn(process_request(
+                                    raw_connect_manager.clone(),
+                                    raw_command.clone(),
+                                    raw_response_queue_sx.clone(),
+                                    packet,
+                                ));
                             } else {
-                                error!("{}", JournalServerError::NotFoundConnectionInCache(packet.connection_id));
+                                process_request(
+                                    raw_connect_manager.clone(),
+                                    raw_command.clone(),
+                                    raw_response_queue_sx.clone(),
+                                    packet,
+                                )
+                                .await;
                             }
                         }
                     }
@@ -154,3 +155,55 @@ fn handler_child_process(
         });
     }
 }
+
+async fn process_request(
+    connection_manager: Arc<ConnectionManager>,
+    command: Command,
+    response_queue_sx: Sender<ResponsePackage>,
+    packet: RequestPackage,
+) {
+    if let Some(connect) = connection_manager.get_connect(packet.connection_id) {
+        if let Some(resp) = command
+            .apply(
+                connection_manager.clone(),
+                connect,
+                packet.addr,
+                packet.packet,
+            )
+            .await
+        {
+            let response_package = ResponsePackage::new(packet.connection_id, resp);
+            match response_queue_sx.send(response_package).await {
+                Ok(_) => {}
+                Err(err) => error!(
+                    "Failed to write data to the response queue, error message: {:?}",
+                    err
+                ),
+            }
+        } else {
+            info!("{}", "No backpacking is required for this request");
+        }
+    } else {
+        error!(
+            "{}",
+            JournalServerError::NotFoundConnectionInCache(packet.connection_id)
+        );
+    }
+}
+
+fn is_long_poll_read(packet: &JournalEnginePacket) -> bool {
+    // reads never wait when the server does not allow it
+    if journal_server_conf().network.max_read_wait_ms == 0 {
+        return false;
+    }
+    if let JournalEnginePacket::ReadReq(req) = packet {
+        if let Some(body) = &req.body {
+            return body.messages.iter().any(|raw| {
+                raw.options
+                    .as_ref()
+                    .is_some_and(|options| options.max_wait_ms > 0)
+            });
+        }
+    }
+    false
+}
@@ -23,7 +23,9 @@ use tokio::sync::{broadcast, mpsc};
 
 use crate::core::cache::CacheManager;
 use crate::core::offset::OffsetManager;
+use crate::group::GroupCoordinator;
 use crate::handler::command::Command;
+use crate::isr::IsrManager;
 use crate::segment::manager::SegmentFileManager;
 use crate::server::connection::NetworkConnectionType;
 use crate::server::connection_manager::ConnectionManager;
@@ -33,6 +35,7 @@ use crate::server::tcp::response::response_process;
 use crate::server::tcp::tcp_server::acceptor_process;
 use crate::server::tcp::tls_server::acceptor_tls_process;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn start_tcp_server(
     client_pool: Arc<ClientPool>,
     connection_manager: Arc<ConnectionManager>,
@@ -40,6 +43,8 @@ pub async fn start_tcp_server(
     offset_manager: Arc<OffsetManager>,
     segment_file_manager: Arc<SegmentFileManager>,
     rocksdb_engine_handler: Arc<RocksDBEngine>,
+    isr_manager: Arc<IsrManager>,
+    group_coordinator: Arc<GroupCoordinator>,
     stop_sx: broadcast::Sender<bool>,
 ) {
     let conf = journal_server_conf();
@@ -49,6 +54,8 @@ pub async fn start_tcp_server(
         offset_manager,
         segment_file_manager,
         rocksdb_engine_handler,
+        isr_manager,
+        group_coordinator,
     );
 
     let proc_config = ProcessorConfig {
@@ -12,3 +12,15 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use axum::async_trait;
+use serde_json::{Map, Value};
+
+use crate::handler::error::MqttBrokerError;
+
+// A destination outside the broker, such as Kafka or Redis, that the rule engine
+// hands rule results to. Sinks are registered by name on the rule engine.
+#[async_trait]
+pub trait BridgeSink: Send + Sync {
+    async fn send(&self, rule_name: &str, data: &Map<String, Value>)
+        -> Result<(), MqttBrokerError>;
+}
@@ -61,6 +61,12 @@ impl UpdateAclCache {
                 error!("{}", e);
             }
         };
+        match self.auth_driver.update_blacklist_cache().await {
+            Ok(_) => {}
+            Err(e) => {
+                error!("Updating blacklist info normal exception, {}", e);
+            }
+        };
         sleep(Duration::from_secs(5)).await;
     }
 }
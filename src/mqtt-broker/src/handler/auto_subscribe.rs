@@ -0,0 +1,177 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use log::warn;
+use metadata_struct::mqtt::cluster::MqttAutoSubscribeRule;
+use protocol::mqtt::common::{Filter, MqttProtocol, RetainForwardRule, Subscribe};
+
+use crate::subscribe::sub_common::sub_path_validator;
+
+pub const AUTO_SUBSCRIBE_CLIENT_ID_PLACEHOLDER: &str = "${clientid}";
+pub const AUTO_SUBSCRIBE_USERNAME_PLACEHOLDER: &str = "${username}";
+
+// None when a value put in for a placeholder would add topic levels or wildcards, a client
+// id of `+` must not subscribe the client to the topics of every other client
+pub fn render_auto_subscribe_topic(topic: &str, client_id: &str, username: &str) -> Option<String> {
+    let mut result = topic.to_owned();
+    for (placeholder, value) in [
+        (AUTO_SUBSCRIBE_CLIENT_ID_PLACEHOLDER, client_id),
+        (AUTO_SUBSCRIBE_USERNAME_PLACEHOLDER, username),
+    ] {
+        if !result.contains(placeholder) {
+            continue;
+        }
+        if value.contains(['+', '#', '/', '\0']) {
+            return None;
+        }
+        result = result.replace(placeholder, value);
+    }
+    Some(result)
+}
+
+// Build the SUBSCRIBE a client would have sent for the configured auto subscribe rules,
+// rules that do not render to a valid topic filter are skipped.
+pub fn build_auto_subscribe(
+    protocol: &MqttProtocol,
+    rules: &[MqttAutoSubscribeRule],
+    client_id: &str,
+    username: &str,
+) -> Option<Subscribe> {
+    let mut filters = Vec::new();
+    for rule in rules {
+        let path = if let Some(path) = render_auto_subscribe_topic(&rule.topic, client_id, username)
+        {
+            path
+        } else {
+            warn!(
+                "Auto subscribe topic {} cannot be rendered for client {}, skipped.",
+                rule.topic, client_id
+            );
+            continue;
+        };
+        if !sub_path_validator(path.clone()) {
+            warn!(
+                "Auto subscribe topic {} of client {} is not a valid topic filter, skipped.",
+                path, client_id
+            );
+            continue;
+        }
+
+        let filter = if *protocol == MqttProtocol::Mqtt5 {
+            Filter {
+                path,
+                qos: rule.qos,
+                nolocal: rule.no_local,
+                preserve_retain: rule.retain_as_published,
+                retain_forward_rule: rule.retained_handling.clone(),
+            }
+        } else {
+            Filter {
+                path,
+                qos: rule.qos,
+                nolocal: false,
+                preserve_retain: false,
+                retain_forward_rule: RetainForwardRule::OnEverySubscribe,
+            }
+        };
+        filters.push(filter);
+    }
+
+    if filters.is_empty() {
+        return None;
+    }
+
+    Some(Subscribe {
+        packet_identifier: 0,
+        filters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use metadata_struct::mqtt::cluster::MqttAutoSubscribeRule;
+    use protocol::mqtt::common::{MqttProtocol, QoS, RetainForwardRule};
+
+    use super::{build_auto_subscribe, render_auto_subscribe_topic};
+
+    #[test]
+    fn render_auto_subscribe_topic_test() {
+        assert_eq!(
+            render_auto_subscribe_topic("device/${clientid}/cmd", "c1", "u1"),
+            Some("device/c1/cmd".to_string())
+        );
+        assert_eq!(
+            render_auto_subscribe_topic("user/${username}/${clientid}/#", "c1", "u1"),
+            Some("user/u1/c1/#".to_string())
+        );
+        assert_eq!(
+            render_auto_subscribe_topic("broadcast/all", "+", "#"),
+            Some("broadcast/all".to_string())
+        );
+
+        for client_id in ["+", "#", "a/b", "a\0"] {
+            assert_eq!(
+                render_auto_subscribe_topic("device/${clientid}/cmd", client_id, "u1"),
+                None
+            );
+        }
+        assert_eq!(
+            render_auto_subscribe_topic("user/${username}/cmd", "c1", "u/+"),
+            None
+        );
+        // only the values that are put in are checked
+        assert_eq!(
+            render_auto_subscribe_topic("device/${clientid}/cmd", "c1", "u/+"),
+            Some("device/c1/cmd".to_string())
+        );
+    }
+
+    #[test]
+    fn build_auto_subscribe_test() {
+        let rules = vec![
+            MqttAutoSubscribeRule {
+                topic: "device/${clientid}/cmd".to_string(),
+                qos: QoS::AtLeastOnce,
+                no_local: true,
+                retain_as_published: true,
+                retained_handling: RetainForwardRule::Never,
+            },
+            MqttAutoSubscribeRule {
+                topic: "device/a+/invalid".to_string(),
+                qos: QoS::AtMostOnce,
+                ..Default::default()
+            },
+        ];
+
+        let subscribe = build_auto_subscribe(&MqttProtocol::Mqtt5, &rules, "c1", "u1").unwrap();
+        assert_eq!(subscribe.filters.len(), 1);
+        let filter = &subscribe.filters[0];
+        assert_eq!(filter.path, "device/c1/cmd");
+        assert_eq!(filter.qos, QoS::AtLeastOnce);
+        assert!(filter.nolocal);
+        assert!(filter.preserve_retain);
+        assert_eq!(filter.retain_forward_rule, RetainForwardRule::Never);
+
+        let subscribe = build_auto_subscribe(&MqttProtocol::Mqtt4, &rules, "c1", "u1").unwrap();
+        let filter = &subscribe.filters[0];
+        assert!(!filter.nolocal);
+        assert!(!filter.preserve_retain);
+        assert_eq!(
+            filter.retain_forward_rule,
+            RetainForwardRule::OnEverySubscribe
+        );
+
+        assert!(build_auto_subscribe(&MqttProtocol::Mqtt5, &[], "c1", "u1").is_none());
+    }
+}
@@ -12,8 +12,10 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::collections::HashSet;
-use std::sync::Arc;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, BinaryHeap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::Duration;
 
 use common_base::config::broker_mqtt::broker_mqtt_conf;
@@ -25,8 +27,10 @@ use metadata_struct::acl::mqtt_acl::MqttAcl;
 use metadata_struct::acl::mqtt_blacklist::MqttAclBlackList;
 use metadata_struct::mqtt::cluster::MqttClusterDynamicConfig;
 use metadata_struct::mqtt::connection::MQTTConnection;
+use metadata_struct::mqtt::psk::MqttPskIdentity;
 use metadata_struct::mqtt::session::MqttSession;
 use metadata_struct::mqtt::topic::MqttTopic;
+use metadata_struct::mqtt::topic_rewrite_rule::MqttTopicRewriteAction;
 use metadata_struct::mqtt::user::MqttUser;
 use protocol::broker_mqtt::broker_mqtt_inner::{
     MqttBrokerUpdateCacheActionType, MqttBrokerUpdateCacheResourceType, UpdateCacheRequest,
@@ -36,12 +40,19 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast::Sender;
 use tokio::time::sleep;
 
+use crate::handler::delay_publish::DelayPublishMessage;
+use crate::handler::flow_control::RateLimiter;
+use crate::handler::topic_rewrite::TopicRewriteRule;
+use crate::rule_engine::RuleEngine;
 use crate::security::acl::metadata::AclMetadata;
+use crate::security::login::password::PasswordHashOptions;
 use crate::security::AuthDriver;
 use crate::storage::cluster::ClusterStorage;
 use crate::storage::topic::TopicStorage;
 use crate::storage::user::UserStorage;
+use crate::subscribe::sub_common::sub_filter_path;
 use crate::subscribe::subscriber::SubscribeData;
+use crate::subscribe::topic_trie::TopicTrie;
 
 #[derive(Clone, Serialize, Deserialize)]
 pub enum MetadataCacheAction {
@@ -96,6 +107,9 @@ pub struct ClientPkidData {
     pub create_time: u64,
 }
 
+// (deliver_time, offset, id) of the delayed messages, the next one due on top
+type DelayPublishQueue = BinaryHeap<Reverse<(u64, u64, String)>>;
+
 #[derive(Clone)]
 pub struct CacheManager {
     pub client_pool: Arc<ClientPool>,
@@ -109,6 +123,9 @@ pub struct CacheManager {
     // (username, User)
     pub user_info: DashMap<String, MqttUser>,
 
+    // (identity, PskIdentity)
+    pub psk_info: DashMap<String, MqttPskIdentity>,
+
     // (client_id, Session)
     pub session_info: DashMap<String, MqttSession>,
 
@@ -130,6 +147,12 @@ pub struct CacheManager {
     // (topic_id, topic_name)
     pub topic_id_name: DashMap<String, String>,
 
+    // topic names by level, finds the topics a filter matches
+    pub topic_trie: Arc<RwLock<TopicTrie<String>>>,
+
+    // (client_id, path) by the filter of the path, finds the subscriptions a topic matches
+    pub subscribe_trie: Arc<RwLock<TopicTrie<(String, String)>>>,
+
     // (client_id, HeartbeatShard)
     pub heartbeat_data: DashMap<String, ConnectionLiveTime>,
 
@@ -141,6 +164,28 @@ pub struct CacheManager {
 
     // acl metadata
     pub acl_metadata: AclMetadata,
+
+    // flow control token buckets
+    pub rate_limiter: Arc<RateLimiter>,
+
+    // (id, DelayPublishMessage) delayed messages waiting to be delivered
+    pub delay_publish_messages: DashMap<String, DelayPublishMessage>,
+
+    // delayed messages by due time, entries of messages that are gone are dropped when
+    // they reach the top
+    delay_publish_queue: Arc<Mutex<DelayPublishQueue>>,
+
+    // offsets of the Add records of the delayed messages
+    delay_publish_offsets: Arc<Mutex<BTreeSet<u64>>>,
+
+    // the delayed publish log is replayed into the cache up to this offset
+    delay_publish_read_offset: Arc<AtomicU64>,
+
+    // (action_source_topic, TopicRewriteRule)
+    pub topic_rewrite_rules: DashMap<String, TopicRewriteRule>,
+
+    // rule engine rules and bridge sinks
+    pub rule_engine: Arc<RuleEngine>,
 }
 
 impl CacheManager {
@@ -150,9 +195,12 @@ impl CacheManager {
             cluster_name,
             cluster_info: DashMap::with_capacity(1),
             user_info: DashMap::with_capacity(8),
+            psk_info: DashMap::with_capacity(8),
             session_info: DashMap::with_capacity(8),
             topic_info: DashMap::with_capacity(8),
             topic_id_name: DashMap::with_capacity(8),
+            topic_trie: Arc::new(RwLock::new(TopicTrie::new())),
+            subscribe_trie: Arc::new(RwLock::new(TopicTrie::new())),
             connection_info: DashMap::with_capacity(8),
             subscribe_filter: DashMap::with_capacity(8),
             subscribe_is_new: DashMap::with_capacity(8),
@@ -161,6 +209,13 @@ impl CacheManager {
             qos_ack_packet: DashMap::with_capacity(8),
             client_pkid_data: DashMap::with_capacity(8),
             acl_metadata: AclMetadata::new(),
+            rate_limiter: Arc::new(RateLimiter::new()),
+            delay_publish_messages: DashMap::with_capacity(8),
+            delay_publish_queue: Arc::new(Mutex::new(BinaryHeap::new())),
+            delay_publish_offsets: Arc::new(Mutex::new(BTreeSet::new())),
+            delay_publish_read_offset: Arc::new(AtomicU64::new(0)),
+            topic_rewrite_rules: DashMap::with_capacity(8),
+            rule_engine: Arc::new(RuleEngine::new()),
         }
     }
 
@@ -174,6 +229,9 @@ impl CacheManager {
         for filter in subscribe.filters {
             let mut is_new = false;
             let path = filter.path.clone();
+            if let Ok(mut trie) = self.subscribe_trie.write() {
+                trie.insert(&sub_filter_path(&path), (client_id.clone(), path.clone()));
+            }
             if let Some(data) = self.subscribe_filter.get_mut(&client_id) {
                 data.insert(
                     path.clone(),
@@ -214,6 +272,28 @@ impl CacheManager {
                     sub_list.remove(path);
                 }
             }
+            if let Ok(mut trie) = self.subscribe_trie.write() {
+                trie.remove(
+                    &sub_filter_path(path),
+                    &(client_id.to_string(), path.clone()),
+                );
+            }
+        }
+    }
+
+    /// The names of the topics matched by a subscription path.
+    pub fn get_topic_names_by_filter(&self, sub_path: &str) -> Vec<String> {
+        match self.topic_trie.read() {
+            Ok(trie) => trie.match_filter(&sub_filter_path(sub_path)),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// The (client_id, path) of the subscriptions matching a topic.
+    pub fn get_subscribes_by_topic(&self, topic_name: &str) -> Vec<(String, String)> {
+        match self.subscribe_trie.read() {
+            Ok(trie) => trie.match_topic(topic_name),
+            Err(_) => Vec::new(),
         }
     }
 
@@ -277,6 +357,19 @@ impl CacheManager {
             .retain(|username, _| usernames.contains(username));
     }
 
+    pub fn add_psk(&self, psk: MqttPskIdentity) {
+        self.psk_info.insert(psk.identity.clone(), psk);
+    }
+
+    pub fn del_psk(&self, identity: String) {
+        self.psk_info.remove(&identity);
+    }
+
+    pub fn retain_psks(&self, identities: HashSet<String>) {
+        self.psk_info
+            .retain(|identity, _| identities.contains(identity));
+    }
+
     pub fn add_session(&self, client_id: String, session: MqttSession) {
         self.session_info.insert(client_id, session);
     }
@@ -292,6 +385,9 @@ impl CacheManager {
         let t = topic.clone();
         self.topic_info.insert(topic_name.to_owned(), t.clone());
         self.topic_id_name.insert(t.topic_id, topic_name.to_owned());
+        if let Ok(mut trie) = self.topic_trie.write() {
+            trie.insert(topic_name, topic_name.to_owned());
+        }
     }
 
     pub fn update_topic_retain_message(&self, topic_name: &str, retain_message: Option<Vec<u8>>) {
@@ -333,7 +429,13 @@ impl CacheManager {
 
     pub fn remove_session(&self, client_id: &str) {
         self.session_info.remove(client_id);
-        self.subscribe_filter.remove(client_id);
+        if let Some((_, sub_list)) = self.subscribe_filter.remove(client_id) {
+            if let Ok(mut trie) = self.subscribe_trie.write() {
+                for (path, _) in sub_list {
+                    trie.remove(&sub_filter_path(&path), &(client_id.to_string(), path));
+                }
+            }
+        }
         self.subscribe_is_new.remove(client_id);
         self.publish_pkid_info.remove(client_id);
         self.heartbeat_data.remove(client_id);
@@ -353,6 +455,8 @@ impl CacheManager {
 
     pub fn remove_connection(&self, connect_id: u64) {
         self.connection_info.remove(&connect_id);
+        self.acl_metadata.remove_connection_acl(connect_id);
+        self.rate_limiter.remove_connection(connect_id);
     }
 
     pub fn get_topic_alias(&self, connect_id: u64, topic_alias: u16) -> Option<String> {
@@ -516,9 +620,21 @@ impl CacheManager {
     pub async fn init_system_user(&self) {
         // init system user
         let conf = broker_mqtt_conf();
+        let password_hash = match PasswordHashOptions::from_conf(&conf.auth.password_hash) {
+            Ok(options) => options,
+            Err(e) => {
+                panic!("{}", e.to_string());
+            }
+        };
+        let password = match password_hash.hash(&conf.system.default_password).await {
+            Ok(password) => password,
+            Err(e) => {
+                panic!("{}", e.to_string());
+            }
+        };
         let system_user_info = MqttUser {
             username: conf.system.default_user.clone(),
-            password: conf.system.default_password.clone(),
+            password,
             is_superuser: true,
         };
         let user_storage = UserStorage::new(self.client_pool.clone());
@@ -607,6 +723,112 @@ impl CacheManager {
         None
     }
 
+    pub fn add_delay_publish_message(&self, message: DelayPublishMessage) {
+        self.requeue_delay_publish_message(&message);
+        self.delay_publish_offsets
+            .lock()
+            .unwrap()
+            .insert(message.offset);
+        self.delay_publish_messages
+            .insert(message.id.clone(), message);
+    }
+
+    pub fn remove_delay_publish_message(&self, id: &str) {
+        if let Some((_, message)) = self.delay_publish_messages.remove(id) {
+            self.delay_publish_offsets
+                .lock()
+                .unwrap()
+                .remove(&message.offset);
+        }
+    }
+
+    // Puts a message taken from the queue back, it is taken again in the next round
+    pub fn requeue_delay_publish_message(&self, message: &DelayPublishMessage) {
+        self.delay_publish_queue.lock().unwrap().push(Reverse((
+            message.deliver_time,
+            message.offset,
+            message.id.clone(),
+        )));
+    }
+
+    pub fn has_due_delay_publish_message(&self, now: u64) -> bool {
+        let mut queue = self.delay_publish_queue.lock().unwrap();
+        while let Some(Reverse((deliver_time, _, id))) = queue.peek() {
+            if self.delay_publish_messages.contains_key(id) {
+                return *deliver_time <= now;
+            }
+            queue.pop();
+        }
+        false
+    }
+
+    // Takes the messages that are due off the queue, in the order they are due
+    pub fn take_due_delay_publish_messages(&self, now: u64) -> Vec<DelayPublishMessage> {
+        let mut queue = self.delay_publish_queue.lock().unwrap();
+        let mut results = Vec::new();
+        while let Some(Reverse((deliver_time, _, _))) = queue.peek() {
+            if *deliver_time > now {
+                break;
+            }
+            let Reverse((_, _, id)) = queue.pop().unwrap();
+            if let Some(message) = self.get_delay_publish_message(&id) {
+                results.push(message);
+            }
+        }
+        results
+    }
+
+    // Offset of the oldest Add record whose message is still pending
+    pub fn get_delay_publish_min_offset(&self) -> Option<u64> {
+        self.delay_publish_offsets.lock().unwrap().first().copied()
+    }
+
+    pub fn get_delay_publish_read_offset(&self) -> u64 {
+        self.delay_publish_read_offset.load(Ordering::Relaxed)
+    }
+
+    pub fn set_delay_publish_read_offset(&self, offset: u64) {
+        self.delay_publish_read_offset
+            .store(offset, Ordering::Relaxed);
+    }
+
+    pub fn get_delay_publish_message(&self, id: &str) -> Option<DelayPublishMessage> {
+        if let Some(data) = self.delay_publish_messages.get(id) {
+            return Some(data.clone());
+        }
+        None
+    }
+
+    pub fn add_topic_rewrite_rule(&self, rule: TopicRewriteRule) {
+        let key = CacheManager::topic_rewrite_rule_key(rule.rule.action, &rule.rule.source_topic);
+        self.topic_rewrite_rules.insert(key, rule);
+    }
+
+    pub fn remove_topic_rewrite_rule(&self, action: MqttTopicRewriteAction, source_topic: &str) {
+        let key = CacheManager::topic_rewrite_rule_key(action, source_topic);
+        self.topic_rewrite_rules.remove(&key);
+    }
+
+    // Rules are tried in the order they were created
+    pub fn get_topic_rewrite_rules(&self) -> Vec<TopicRewriteRule> {
+        let mut rules: Vec<TopicRewriteRule> = self
+            .topic_rewrite_rules
+            .iter()
+            .map(|raw| raw.value().clone())
+            .collect();
+        rules.sort_by(|a, b| {
+            a.rule
+                .create_time
+                .cmp(&b.rule.create_time)
+                .then_with(|| a.rule.source_topic.cmp(&b.rule.source_topic))
+        });
+        rules
+    }
+
+    pub fn topic_rewrite_rule_key(action: MqttTopicRewriteAction, source_topic: &str) -> String {
+        format!("{}_{}", action, source_topic)
+    }
+
     fn key(&self, client_id: &str, pkid: u16) -> String {
         format!("{}_{}", client_id, pkid)
     }
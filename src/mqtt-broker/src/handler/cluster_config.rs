@@ -12,14 +12,24 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use common_base::error::common::CommonError;
-use metadata_struct::mqtt::cluster::MqttClusterDynamicConfig;
+use grpc_clients::pool::ClientPool;
+use log::{error, info};
+use metadata_struct::mqtt::cluster::{MqttAutoSubscribeRule, MqttClusterDynamicConfig};
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
 
 use crate::handler::cache::CacheManager;
 use crate::storage::cluster::ClusterStorage;
 
+const CLUSTER_CONFIG_UPDATE_ATTEMPTS: u32 = 5;
+
 impl CacheManager {
-    pub(super) fn set_cluster_info(&self, cluster: MqttClusterDynamicConfig) {
+    pub(crate) fn set_cluster_info(&self, cluster: MqttClusterDynamicConfig) {
         self.cluster_info.insert(self.cluster_name.clone(), cluster);
     }
 
@@ -37,4 +47,123 @@ impl CacheManager {
             .await?;
         Ok(())
     }
+
+    pub fn get_auto_subscribe_rules(&self) -> Vec<MqttAutoSubscribeRule> {
+        self.get_cluster_info().auto_subscribe.rules
+    }
+
+    // Add an auto subscribe rule, or replace the rule with the same topic
+    pub async fn set_auto_subscribe_rule(
+        &self,
+        rule: MqttAutoSubscribeRule,
+    ) -> Result<(), CommonError> {
+        self.update_cluster_info(|dynamic_config| {
+            let rules = &mut dynamic_config.auto_subscribe.rules;
+            if let Some(existing) = rules.iter_mut().find(|raw| raw.topic == rule.topic) {
+                *existing = rule.clone();
+            } else {
+                rules.push(rule.clone());
+            }
+        })
+        .await
+    }
+
+    pub async fn delete_auto_subscribe_rule(&self, topic: &str) -> Result<(), CommonError> {
+        self.update_cluster_info(|dynamic_config| {
+            dynamic_config
+                .auto_subscribe
+                .rules
+                .retain(|rule| rule.topic != topic);
+        })
+        .await
+    }
+
+    // Applies the change to the stored config with a compare-and-set, so the changes made
+    // through other brokers at the same time are kept. The local cache may be stale.
+    async fn update_cluster_info<F>(&self, update: F) -> Result<(), CommonError>
+    where
+        F: Fn(&mut MqttClusterDynamicConfig),
+    {
+        let cluster_storage = ClusterStorage::new(self.client_pool.clone());
+        for _ in 0..CLUSTER_CONFIG_UPDATE_ATTEMPTS {
+            let data = cluster_storage
+                .get_cluster_config_data(&self.cluster_name)
+                .await?;
+            let mut dynamic_config = if data.is_empty() {
+                self.get_cluster_info()
+            } else {
+                serde_json::from_slice::<MqttClusterDynamicConfig>(&data)?
+            };
+            update(&mut dynamic_config);
+
+            if cluster_storage
+                .compare_and_set_cluster_config(&self.cluster_name, data, dynamic_config.clone())
+                .await?
+            {
+                self.set_cluster_info(dynamic_config);
+                return Ok(());
+            }
+        }
+        Err(CommonError::CommonError(format!(
+            "Cluster config of {} kept changing, gave up after {} attempts",
+            self.cluster_name, CLUSTER_CONFIG_UPDATE_ATTEMPTS
+        )))
+    }
+}
+
+// Reload the cluster dynamic config from the placement center, so changes made
+// through another broker take effect here without a restart.
+pub struct UpdateClusterConfigCache {
+    stop_send: broadcast::Sender<bool>,
+    cache_manager: Arc<CacheManager>,
+    client_pool: Arc<ClientPool>,
+}
+
+impl UpdateClusterConfigCache {
+    pub fn new(
+        stop_send: broadcast::Sender<bool>,
+        cache_manager: Arc<CacheManager>,
+        client_pool: Arc<ClientPool>,
+    ) -> Self {
+        UpdateClusterConfigCache {
+            stop_send,
+            cache_manager,
+            client_pool,
+        }
+    }
+
+    pub async fn start_update(&self) {
+        loop {
+            let mut stop_rx = self.stop_send.subscribe();
+            select! {
+                val = stop_rx.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            info!("{}","Cluster config cache updating thread stopped successfully.");
+                            break;
+                        }
+                    }
+                }
+                _ = self.update_cluster_config_cache()=>{
+                }
+            }
+        }
+    }
+
+    async fn update_cluster_config_cache(&self) {
+        let cluster_storage = ClusterStorage::new(self.client_pool.clone());
+        match cluster_storage
+            .get_cluster_config(&self.cache_manager.cluster_name)
+            .await
+        {
+            Ok(Some(cluster)) => {
+                self.cache_manager.set_cluster_info(cluster);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!("Updating cluster config cache failed, error message: {}", e);
+            }
+        }
+        sleep(Duration::from_secs(5)).await;
+    }
 }
@@ -173,13 +173,6 @@ where
                 let ack_pkg = resp_pkg.unwrap();
                 if let MqttPacket::ConnAck(conn_ack, _) = ack_pkg.clone() {
                     if conn_ack.code == ConnectReturnCode::Success {
-                        let username = if let Some(user) = login {
-                            user.username
-                        } else {
-                            "".to_string()
-                        };
-                        self.metadata_cache
-                            .login_success(tcp_connection.connection_id, username);
                         info!("connect [{}] login success", tcp_connection.connection_id);
                     }
                 }
@@ -0,0 +1,612 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common_base::config::broker_mqtt::broker_mqtt_conf;
+use common_base::tools::{now_second, unique_id};
+use grpc_clients::pool::ClientPool;
+use log::{error, info, warn};
+use metadata_struct::adapter::record::Record;
+use metadata_struct::mqtt::message::MqttMessage;
+use protocol::mqtt::common::{Publish, PublishProperties};
+use serde::{Deserialize, Serialize};
+use storage_adapter::storage::{ShardConfig, StorageAdapter};
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+
+use super::error::MqttBrokerError;
+use super::message::build_message_expire;
+use super::retain::save_retain_mqtt_message;
+use crate::handler::cache::CacheManager;
+use crate::handler::topic::{topic_name_validator, try_init_topic};
+use crate::storage::message::{cluster_name, MessageStorage};
+use crate::subscribe::sub_common::get_share_sub_leader;
+
+pub const DELAY_PUBLISH_TOPIC_PREFIX: &str = "$delayed/";
+// Shard that keeps the add/cancel/deliver log of delayed messages
+pub const DELAY_PUBLISH_SHARD_NAME: &str = "$delayed-publish";
+// Consumer group whose committed offset marks where replay starts, its
+// share-sub leader is the broker that delivers due messages
+pub const DELAY_PUBLISH_GROUP_NAME: &str = "$delayed-publish";
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default)]
+pub struct DelayPublishMessage {
+    pub id: String,
+    pub client_id: String,
+    pub topic_name: String,
+    pub delay_secs: u64,
+    pub deliver_time: u64,
+    pub create_time: u64,
+    pub message: MqttMessage,
+    // offset of the Add record in the delayed publish shard
+    #[serde(skip)]
+    pub offset: u64,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum DelayPublishAction {
+    Add(Box<DelayPublishMessage>),
+    Cancel(String),
+    Deliver(String),
+}
+
+pub fn is_delay_topic(topic_name: &str) -> bool {
+    topic_name.starts_with(DELAY_PUBLISH_TOPIC_PREFIX)
+}
+
+// Split $delayed/{seconds}/{topic} into the delay and the topic to deliver to
+pub fn parse_delay_topic(topic_name: &str) -> Result<(u64, String), MqttBrokerError> {
+    let invalid = || MqttBrokerError::DelayPublishTopicInvalid(topic_name.to_owned());
+    let rest = if let Some(rest) = topic_name.strip_prefix(DELAY_PUBLISH_TOPIC_PREFIX) {
+        rest
+    } else {
+        return Err(invalid());
+    };
+
+    let (secs, target) = if let Some(parts) = rest.split_once('/') {
+        parts
+    } else {
+        return Err(invalid());
+    };
+
+    let delay_secs = secs.parse::<u64>().map_err(|_| invalid())?;
+    if target.is_empty() || target.contains(['+', '#']) || is_delay_topic(target) {
+        return Err(invalid());
+    }
+    topic_name_validator(target)?;
+    Ok((delay_secs, target.to_owned()))
+}
+
+pub async fn save_delay_publish_message<S>(
+    cache_manager: &Arc<CacheManager>,
+    message_storage_adapter: &Arc<S>,
+    client_id: &str,
+    delay_secs: u64,
+    topic_name: &str,
+    publish: &Publish,
+    publish_properties: &Option<PublishProperties>,
+) -> Result<String, MqttBrokerError>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    let cluster = cache_manager.get_cluster_info();
+    let max_delay_secs = cluster.delay_publish.max_delay_secs;
+    if max_delay_secs > 0 && delay_secs > max_delay_secs {
+        return Err(MqttBrokerError::DelayPublishIntervalTooLong(
+            delay_secs,
+            max_delay_secs,
+        ));
+    }
+
+    let max_delayed_messages = cluster.delay_publish.max_delayed_messages;
+    if max_delayed_messages > 0
+        && pending_delay_publish_count(cache_manager, message_storage_adapter).await?
+            >= max_delayed_messages
+    {
+        return Err(MqttBrokerError::DelayPublishLimitExceeded(
+            max_delayed_messages,
+        ));
+    }
+
+    // The message expiry interval starts counting when the message is delivered
+    let message_expire = build_message_expire(cache_manager, publish_properties) + delay_secs;
+    let mut message =
+        MqttMessage::build_message(client_id, publish, publish_properties, message_expire);
+    message.topic = topic_name.to_owned().into();
+
+    let now = now_second();
+    let delay_message = DelayPublishMessage {
+        id: unique_id(),
+        client_id: client_id.to_owned(),
+        topic_name: topic_name.to_owned(),
+        delay_secs,
+        deliver_time: now + delay_secs,
+        create_time: now,
+        message,
+        offset: 0,
+    };
+    let id = delay_message.id.clone();
+
+    append_delay_publish_action(
+        message_storage_adapter,
+        &id,
+        &DelayPublishAction::Add(Box::new(delay_message)),
+    )
+    .await?;
+    Ok(id)
+}
+
+pub async fn cancel_delay_publish_message<S>(
+    cache_manager: &Arc<CacheManager>,
+    message_storage_adapter: &Arc<S>,
+    id: &str,
+) -> Result<(), MqttBrokerError>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    if cache_manager.get_delay_publish_message(id).is_none() {
+        return Err(MqttBrokerError::DelayPublishMessageNotFound(id.to_owned()));
+    }
+
+    append_delay_publish_action(
+        message_storage_adapter,
+        id,
+        &DelayPublishAction::Cancel(id.to_owned()),
+    )
+    .await?;
+    cache_manager.remove_delay_publish_message(id);
+    Ok(())
+}
+
+// The pending messages of the whole cluster: the ones replayed into the cache, plus the
+// ones added or removed by the records the replay has not reached yet
+async fn pending_delay_publish_count<S>(
+    cache_manager: &Arc<CacheManager>,
+    message_storage_adapter: &Arc<S>,
+) -> Result<u64, MqttBrokerError>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    let message_storage = MessageStorage::new(message_storage_adapter.clone());
+    let mut offset = cache_manager.get_delay_publish_read_offset();
+    let mut count = cache_manager.delay_publish_messages.len() as u64;
+    let mut added = HashSet::new();
+    loop {
+        let records = message_storage
+            .read_topic_message(DELAY_PUBLISH_SHARD_NAME, offset, 100, 0)
+            .await?;
+        if records.is_empty() {
+            break;
+        }
+
+        for record in records {
+            offset = record.offset.unwrap_or(offset) + 1;
+            match serde_json::from_slice::<DelayPublishAction>(&record.data) {
+                Ok(DelayPublishAction::Add(message)) => {
+                    if cache_manager
+                        .get_delay_publish_message(&message.id)
+                        .is_none()
+                    {
+                        added.insert(message.id);
+                    }
+                }
+                Ok(DelayPublishAction::Cancel(id)) | Ok(DelayPublishAction::Deliver(id)) => {
+                    if !added.remove(&id) && cache_manager.get_delay_publish_message(&id).is_some()
+                    {
+                        count = count.saturating_sub(1);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    Ok(count + added.len() as u64)
+}
+
+pub fn list_delay_publish_messages(cache_manager: &Arc<CacheManager>) -> Vec<DelayPublishMessage> {
+    let mut results: Vec<DelayPublishMessage> = cache_manager
+        .delay_publish_messages
+        .iter()
+        .map(|raw| raw.value().clone())
+        .collect();
+    results.sort_by(|a, b| {
+        a.deliver_time
+            .cmp(&b.deliver_time)
+            .then(a.offset.cmp(&b.offset))
+    });
+    results
+}
+
+async fn append_delay_publish_action<S>(
+    message_storage_adapter: &Arc<S>,
+    id: &str,
+    action: &DelayPublishAction,
+) -> Result<(), MqttBrokerError>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    let mut record = Record::build_byte(serde_json::to_vec(action)?);
+    record.set_key(id.to_owned());
+
+    let message_storage = MessageStorage::new(message_storage_adapter.clone());
+    message_storage
+        .append_topic_message(DELAY_PUBLISH_SHARD_NAME, vec![record])
+        .await?;
+    Ok(())
+}
+
+fn apply_delay_publish_action(
+    cache_manager: &Arc<CacheManager>,
+    action: DelayPublishAction,
+    offset: u64,
+) {
+    match action {
+        DelayPublishAction::Add(mut message) => {
+            message.offset = offset;
+            cache_manager.add_delay_publish_message(*message);
+        }
+        DelayPublishAction::Cancel(id) | DelayPublishAction::Deliver(id) => {
+            cache_manager.remove_delay_publish_message(&id);
+        }
+    }
+}
+
+// Every broker replays the delayed publish log into its cache so that any of
+// them can list or cancel pending messages and take over delivery on failover.
+pub struct DelayPublishManager<S> {
+    cache_manager: Arc<CacheManager>,
+    message_storage_adapter: Arc<S>,
+    client_pool: Arc<ClientPool>,
+    stop_send: broadcast::Sender<bool>,
+    commit_offset: u64,
+}
+
+impl<S> DelayPublishManager<S>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    pub fn new(
+        cache_manager: Arc<CacheManager>,
+        message_storage_adapter: Arc<S>,
+        client_pool: Arc<ClientPool>,
+        stop_send: broadcast::Sender<bool>,
+    ) -> Self {
+        DelayPublishManager {
+            cache_manager,
+            message_storage_adapter,
+            client_pool,
+            stop_send,
+            commit_offset: 0,
+        }
+    }
+
+    pub async fn start(&mut self) {
+        if let Err(e) = self
+            .message_storage_adapter
+            .create_shard(
+                cluster_name(),
+                DELAY_PUBLISH_SHARD_NAME.to_owned(),
+                ShardConfig::default(),
+            )
+            .await
+        {
+            warn!(
+                "Creating the delayed publish shard failed, error message: {}",
+                e
+            );
+        }
+
+        let message_storage = MessageStorage::new(self.message_storage_adapter.clone());
+        match message_storage
+            .get_group_offset(DELAY_PUBLISH_GROUP_NAME)
+            .await
+        {
+            Ok(offset) => {
+                self.cache_manager.set_delay_publish_read_offset(offset);
+                self.commit_offset = offset;
+            }
+            Err(e) => {
+                error!(
+                    "Reading the delayed publish offset failed, error message: {}",
+                    e
+                );
+            }
+        }
+
+        loop {
+            let mut stop_rx = self.stop_send.subscribe();
+            select! {
+                val = stop_rx.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            info!("{}","Delayed publish thread stopped successfully.");
+                            break;
+                        }
+                    }
+                }
+                _ = self.delay_publish() => {}
+            }
+        }
+    }
+
+    async fn delay_publish(&mut self) {
+        if let Err(e) = self.sync_delay_messages().await {
+            error!(
+                "Reading the delayed publish log failed, error message: {}",
+                e
+            );
+            sleep(Duration::from_secs(1)).await;
+        }
+
+        if let Err(e) = self.deliver_due_messages().await {
+            error!("Delivering delayed messages failed, error message: {}", e);
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+
+    async fn sync_delay_messages(&mut self) -> Result<(), MqttBrokerError> {
+        let message_storage = MessageStorage::new(self.message_storage_adapter.clone());
+        let read_offset = self.cache_manager.get_delay_publish_read_offset();
+        let records = message_storage
+            .read_topic_message(DELAY_PUBLISH_SHARD_NAME, read_offset, 100, 1000)
+            .await?;
+
+        if records.is_empty() {
+            sleep(Duration::from_millis(100)).await;
+            return Ok(());
+        }
+
+        for record in records {
+            let offset = record
+                .offset
+                .unwrap_or(self.cache_manager.get_delay_publish_read_offset());
+            match serde_json::from_slice::<DelayPublishAction>(&record.data) {
+                Ok(action) => apply_delay_publish_action(&self.cache_manager, action, offset),
+                Err(e) => {
+                    error!(
+                        "Delayed publish record at offset {} could not be decoded, error message: {}",
+                        offset, e
+                    );
+                }
+            }
+            self.cache_manager.set_delay_publish_read_offset(offset + 1);
+        }
+        Ok(())
+    }
+
+    async fn deliver_due_messages(&mut self) -> Result<(), MqttBrokerError> {
+        let now = now_second();
+
+        // Replay restarts at the oldest message still pending
+        let commit_offset = self
+            .cache_manager
+            .get_delay_publish_min_offset()
+            .unwrap_or(self.cache_manager.get_delay_publish_read_offset());
+
+        if !self.cache_manager.has_due_delay_publish_message(now)
+            && commit_offset == self.commit_offset
+        {
+            return Ok(());
+        }
+
+        let conf = broker_mqtt_conf();
+        let reply = get_share_sub_leader(
+            self.client_pool.clone(),
+            DELAY_PUBLISH_GROUP_NAME.to_owned(),
+        )
+        .await?;
+        if reply.broker_id != conf.broker_id {
+            self.commit_offset = commit_offset;
+            return Ok(());
+        }
+
+        let mut due_messages = self
+            .cache_manager
+            .take_due_delay_publish_messages(now)
+            .into_iter();
+        while let Some(message) = due_messages.next() {
+            if let Err(e) = self.deliver_message(&message).await {
+                // the messages left are delivered in the next round
+                self.cache_manager.requeue_delay_publish_message(&message);
+                for message in due_messages {
+                    self.cache_manager.requeue_delay_publish_message(&message);
+                }
+                return Err(e);
+            }
+            self.cache_manager.remove_delay_publish_message(&message.id);
+        }
+
+        let message_storage = MessageStorage::new(self.message_storage_adapter.clone());
+        if commit_offset != self.commit_offset {
+            message_storage
+                .commit_group_offset(
+                    DELAY_PUBLISH_GROUP_NAME,
+                    DELAY_PUBLISH_SHARD_NAME,
+                    commit_offset,
+                )
+                .await?;
+            self.commit_offset = commit_offset;
+        }
+        Ok(())
+    }
+
+    async fn deliver_message(&self, message: &DelayPublishMessage) -> Result<(), MqttBrokerError> {
+        let topic = try_init_topic(
+            &message.topic_name,
+            &self.cache_manager,
+            &self.message_storage_adapter,
+            &self.client_pool,
+        )
+        .await?;
+
+        if message.message.retain {
+            save_retain_mqtt_message(
+                &self.cache_manager,
+                &self.client_pool,
+                message.topic_name.clone(),
+                &message.message,
+            )
+            .await?;
+        }
+
+        let message_storage = MessageStorage::new(self.message_storage_adapter.clone());
+        let record = Record::build_byte(serde_json::to_vec(&message.message)?);
+        message_storage
+            .append_topic_message(&topic.topic_id, vec![record])
+            .await?;
+
+        append_delay_publish_action(
+            &self.message_storage_adapter,
+            &message.id,
+            &DelayPublishAction::Deliver(message.id.clone()),
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use grpc_clients::pool::ClientPool;
+
+    use super::{
+        apply_delay_publish_action, is_delay_topic, list_delay_publish_messages, parse_delay_topic,
+        DelayPublishAction, DelayPublishMessage,
+    };
+    use crate::handler::cache::CacheManager;
+
+    #[test]
+    fn parse_delay_topic_test() {
+        assert!(is_delay_topic("$delayed/10/a/b"));
+        assert!(!is_delay_topic("a/$delayed/10/b"));
+
+        let (delay_secs, topic_name) = parse_delay_topic("$delayed/10/a/b").unwrap();
+        assert_eq!(delay_secs, 10);
+        assert_eq!(topic_name, "a/b");
+
+        assert!(parse_delay_topic("$delayed/10").is_err());
+        assert!(parse_delay_topic("$delayed/10/").is_err());
+        assert!(parse_delay_topic("$delayed/abc/a").is_err());
+        assert!(parse_delay_topic("$delayed/-1/a").is_err());
+        assert!(parse_delay_topic("$delayed/10/a/+").is_err());
+        assert!(parse_delay_topic("$delayed/10/$delayed/10/a").is_err());
+    }
+
+    #[test]
+    fn apply_delay_publish_action_test() {
+        let client_pool = Arc::new(ClientPool::new(1));
+        let cache_manager = Arc::new(CacheManager::new(client_pool, "test".to_string()));
+
+        for (offset, id) in ["m1", "m2", "m3"].iter().enumerate() {
+            let message = DelayPublishMessage {
+                id: id.to_string(),
+                topic_name: "a/b".to_string(),
+                deliver_time: 100 - offset as u64,
+                ..Default::default()
+            };
+            apply_delay_publish_action(
+                &cache_manager,
+                DelayPublishAction::Add(Box::new(message)),
+                offset as u64,
+            );
+        }
+
+        let ids: Vec<String> = list_delay_publish_messages(&cache_manager)
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(ids, vec!["m3", "m2", "m1"]);
+        assert_eq!(
+            cache_manager
+                .get_delay_publish_message("m2")
+                .unwrap()
+                .offset,
+            1
+        );
+
+        apply_delay_publish_action(
+            &cache_manager,
+            DelayPublishAction::Cancel("m2".to_string()),
+            3,
+        );
+        apply_delay_publish_action(
+            &cache_manager,
+            DelayPublishAction::Deliver("m3".to_string()),
+            4,
+        );
+        // Actions for messages that are already gone are ignored
+        apply_delay_publish_action(
+            &cache_manager,
+            DelayPublishAction::Cancel("m9".to_string()),
+            5,
+        );
+
+        let ids: Vec<String> = list_delay_publish_messages(&cache_manager)
+            .into_iter()
+            .map(|message| message.id)
+            .collect();
+        assert_eq!(ids, vec!["m1"]);
+    }
+
+    #[test]
+    fn delay_publish_queue_test() {
+        let client_pool = Arc::new(ClientPool::new(1));
+        let cache_manager = Arc::new(CacheManager::new(client_pool, "test".to_string()));
+
+        for (offset, (id, deliver_time)) in [("m1", 30), ("m2", 10), ("m3", 20)].iter().enumerate()
+        {
+            let message = DelayPublishMessage {
+                id: id.to_string(),
+                deliver_time: *deliver_time,
+                ..Default::default()
+            };
+            apply_delay_publish_action(
+                &cache_manager,
+                DelayPublishAction::Add(Box::new(message)),
+                offset as u64 + 5,
+            );
+        }
+        assert_eq!(cache_manager.get_delay_publish_min_offset(), Some(5));
+        assert!(!cache_manager.has_due_delay_publish_message(5));
+
+        // a cancelled message is left out when its turn comes
+        apply_delay_publish_action(
+            &cache_manager,
+            DelayPublishAction::Cancel("m2".to_string()),
+            8,
+        );
+        assert!(!cache_manager.has_due_delay_publish_message(15));
+
+        let due: Vec<DelayPublishMessage> = cache_manager.take_due_delay_publish_messages(30);
+        let ids: Vec<&str> = due.iter().map(|message| message.id.as_str()).collect();
+        assert_eq!(ids, vec!["m3", "m1"]);
+        assert!(cache_manager.take_due_delay_publish_messages(30).is_empty());
+
+        // a message that failed to be delivered is taken again
+        cache_manager.requeue_delay_publish_message(&due[0]);
+        assert!(cache_manager.has_due_delay_publish_message(30));
+
+        apply_delay_publish_action(
+            &cache_manager,
+            DelayPublishAction::Deliver("m1".to_string()),
+            9,
+        );
+        assert_eq!(cache_manager.get_delay_publish_min_offset(), Some(7));
+    }
+}
@@ -34,6 +34,42 @@ pub enum MqttBrokerError {
     #[error("{0}")]
     FromMysqlError(#[from] mysql::Error),
 
+    #[error("{0}")]
+    FromRedisError(#[from] redis::RedisError),
+
+    #[error("{0}")]
+    FromBcryptError(#[from] bcrypt::BcryptError),
+
+    #[error("{0}")]
+    FromPasswordHashError(#[from] argon2::password_hash::Error),
+
+    #[error("{0}")]
+    FromJoinError(#[from] tokio::task::JoinError),
+
+    #[error("{0}")]
+    FromJwtError(#[from] jsonwebtoken::errors::Error),
+
+    #[error("{0}")]
+    FromReqwestError(#[from] reqwest::Error),
+
+    #[error("{0}")]
+    FromRustlsError(#[from] tokio_rustls::rustls::Error),
+
+    #[error("{0}")]
+    FromOpensslError(#[from] openssl::error::ErrorStack),
+
+    #[error("{0}")]
+    FromSslError(#[from] openssl::ssl::Error),
+
+    #[error("{0}")]
+    FromVerifierBuilderError(#[from] tokio_rustls::rustls::server::VerifierBuilderError),
+
+    #[error("{0}")]
+    FromQuicCipherSuiteError(#[from] quinn::crypto::rustls::NoInitialCipherSuite),
+
+    #[error("{0}")]
+    FromQuicConnectionError(#[from] quinn::ConnectionError),
+
     #[error("Topic alias is too long. alias is {0}")]
     TopicAliasTooLong(u16),
 
@@ -78,6 +114,9 @@ pub enum MqttBrokerError {
     #[error("Unavailable storage type")]
     UnavailableStorageType,
 
+    #[error("Invalid password hash algorithm: {0}")]
+    InvalidPasswordHashAlgorithm(String),
+
     #[error("{0}")]
     CommonError(String),
 
@@ -86,4 +125,40 @@ pub enum MqttBrokerError {
 
     #[error("invalid acl permission")]
     InvalidAclPermission,
+
+    #[error("Unsupported JWT algorithm {0}")]
+    UnsupportedJwtAlgorithm(String),
+
+    #[error("JWT claim [{0}] does not match the connection")]
+    JwtClaimMismatch(String),
+
+    #[error("Invalid peer certificate, {0}")]
+    InvalidPeerCertificate(String),
+
+    #[error("Invalid pre-shared key of length {0}, it must be a non-empty hex string")]
+    InvalidPskKey(usize),
+
+    #[error("Delayed publish topic [{0}] must be in the form $delayed/{{seconds}}/{{topic}}")]
+    DelayPublishTopicInvalid(String),
+
+    #[error("Delay interval {0}s exceeds the maximum allowed {1}s")]
+    DelayPublishIntervalTooLong(u64, u64),
+
+    #[error("The number of pending delayed messages has reached the limit {0}")]
+    DelayPublishLimitExceeded(u64),
+
+    #[error("Delayed message [{0}] does not exist")]
+    DelayPublishMessageNotFound(String),
+
+    #[error("Invalid rule SQL, {0}")]
+    RuleSqlInvalid(String),
+
+    #[error("Rule evaluation failed, {0}")]
+    RuleEvaluateFailed(String),
+
+    #[error("Invalid rule action, {0}")]
+    RuleActionInvalid(String),
+
+    #[error("Bridge sink [{0}] is not registered")]
+    BridgeSinkNotFound(String),
 }
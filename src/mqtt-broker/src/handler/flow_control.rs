@@ -12,16 +12,477 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use dashmap::mapref::one::RefMut;
+use dashmap::DashMap;
 use protocol::mqtt::common::{MqttProtocol, QoS};
 
+use super::cache::CacheManager;
+use crate::observability::metrics::flow_control::record_rate_limited_metrics;
+use crate::server::connection::NetworkConnectionType;
+
+// Once this many client IP buckets exist, buckets idle for longer than
+// CLIENT_IP_BUCKET_IDLE_TIME are dropped so the map cannot grow without bound.
+const CLIENT_IP_BUCKET_CLEAN_THRESHOLD: usize = 10000;
+const CLIENT_IP_BUCKET_IDLE_TIME: Duration = Duration::from_secs(60);
+
+const LIMIT_TYPE_BROKER_CONNECTION: &str = "broker_connection";
+const LIMIT_TYPE_LISTENER_CONNECTION: &str = "listener_connection";
+const LIMIT_TYPE_CLIENT_IP_CONNECTION: &str = "client_ip_connection";
+const LIMIT_TYPE_CLIENT_SUBSCRIBE: &str = "client_subscribe";
+const LIMIT_TYPE_CLIENT_PUBLISH: &str = "client_publish";
+const LIMIT_TYPE_CONNECTION_BYTES: &str = "connection_bytes";
+
 pub fn is_flow_control(protocol: &MqttProtocol, qos: QoS) -> bool {
     protocol.is_mqtt5() && (qos == QoS::AtLeastOnce || qos == QoS::ExactlyOnce)
 }
 
-pub fn is_connection_rate_exceeded() -> bool {
-    false
+// Checks the broker, listener and client IP connection rate limits for a newly accepted connection.
+// A connection only takes a token from each bucket when all of them have one left.
+pub fn is_connection_rate_exceeded(
+    cache_manager: &Arc<CacheManager>,
+    network_type: &NetworkConnectionType,
+    ip: &IpAddr,
+) -> bool {
+    let flow_control = cache_manager.get_cluster_info().flow_control;
+    match cache_manager.rate_limiter.try_acquire_connection(
+        network_type,
+        ip,
+        &ConnectionRateLimits {
+            broker: RateLimit::new(
+                flow_control.max_broker_connection_rate,
+                flow_control.max_broker_connection_burst,
+            ),
+            listener: RateLimit::new(
+                flow_control.max_listener_connection_rate,
+                flow_control.max_listener_connection_burst,
+            ),
+            client_ip: RateLimit::new(
+                flow_control.max_client_ip_connection_rate,
+                flow_control.max_client_ip_connection_burst,
+            ),
+        },
+    ) {
+        Ok(()) => false,
+        Err(limit_type) => {
+            record_rate_limited_metrics(limit_type);
+            true
+        }
+    }
 }
 
-pub fn is_subscribe_rate_exceeded() -> bool {
+pub fn is_subscribe_rate_exceeded(cache_manager: &Arc<CacheManager>, connect_id: u64) -> bool {
+    let flow_control = cache_manager.get_cluster_info().flow_control;
+    if !cache_manager.rate_limiter.try_acquire_subscribe(
+        connect_id,
+        RateLimit::new(
+            flow_control.max_client_subscribe_rate,
+            flow_control.max_client_subscribe_burst,
+        ),
+    ) {
+        record_rate_limited_metrics(LIMIT_TYPE_CLIENT_SUBSCRIBE);
+        return true;
+    }
     false
 }
+
+// Checks the per client publish rate and the per connection payload bytes rate.
+pub fn is_publish_rate_exceeded(
+    cache_manager: &Arc<CacheManager>,
+    connect_id: u64,
+    payload_len: usize,
+) -> bool {
+    let flow_control = cache_manager.get_cluster_info().flow_control;
+    match cache_manager.rate_limiter.try_acquire_publish(
+        connect_id,
+        payload_len as u64,
+        RateLimit::new(
+            flow_control.max_client_publish_rate,
+            flow_control.max_client_publish_burst,
+        ),
+        RateLimit::new(
+            flow_control.max_connection_bytes_rate,
+            flow_control.max_connection_bytes_burst,
+        ),
+    ) {
+        Ok(()) => false,
+        Err(limit_type) => {
+            record_rate_limited_metrics(limit_type);
+            true
+        }
+    }
+}
+
+// A per-second refill rate and the bucket capacity. A rate of 0 disables the limit,
+// a burst of 0 keeps one second worth of tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub rate: u64,
+    pub burst: u64,
+}
+
+impl RateLimit {
+    pub fn new(rate: u64, burst: u64) -> Self {
+        RateLimit { rate, burst }
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.rate == 0
+    }
+
+    pub fn capacity(&self) -> u64 {
+        if self.burst == 0 {
+            self.rate
+        } else {
+            self.burst
+        }
+    }
+}
+
+pub struct ConnectionRateLimits {
+    pub broker: RateLimit,
+    pub listener: RateLimit,
+    pub client_ip: RateLimit,
+}
+
+// A token bucket refilled continuously at the limit rate up to its capacity.
+// The limit is passed on every call so that dynamic config changes apply immediately.
+pub struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(limit: RateLimit) -> Self {
+        TokenBucket {
+            tokens: limit.capacity() as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, limit: RateLimit) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed * limit.rate as f64).min(limit.capacity() as f64);
+    }
+
+    // Refills the bucket and reports whether amount tokens are available, without taking them.
+    // An amount above the capacity passes once the bucket is full and leaves it in debt,
+    // otherwise it could never pass.
+    pub fn check(&mut self, limit: RateLimit, amount: u64) -> bool {
+        self.refill(limit);
+        self.tokens >= amount.min(limit.capacity()) as f64
+    }
+
+    pub fn consume(&mut self, amount: u64) {
+        self.tokens -= amount as f64;
+    }
+
+    pub fn try_acquire(&mut self, limit: RateLimit, amount: u64) -> bool {
+        if self.check(limit, amount) {
+            self.consume(amount);
+            return true;
+        }
+        false
+    }
+
+    pub fn idle_time(&self) -> Duration {
+        self.last_refill.elapsed()
+    }
+}
+
+// Token buckets for every flow control dimension. Unlimited dimensions keep no bucket.
+pub struct RateLimiter {
+    broker_connection: Mutex<Option<TokenBucket>>,
+    // (network_type, TokenBucket)
+    listener_connection: DashMap<String, TokenBucket>,
+    // (ip, TokenBucket)
+    client_ip_connection: DashMap<IpAddr, TokenBucket>,
+    // (connect_id, TokenBucket)
+    client_subscribe: DashMap<u64, TokenBucket>,
+    // (connect_id, TokenBucket)
+    client_publish: DashMap<u64, TokenBucket>,
+    // (connect_id, TokenBucket)
+    connection_bytes: DashMap<u64, TokenBucket>,
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        RateLimiter {
+            broker_connection: Mutex::new(None),
+            listener_connection: DashMap::with_capacity(4),
+            client_ip_connection: DashMap::with_capacity(8),
+            client_subscribe: DashMap::with_capacity(8),
+            client_publish: DashMap::with_capacity(8),
+            connection_bytes: DashMap::with_capacity(8),
+        }
+    }
+
+    // Returns the type of the first limit without a token left.
+    pub fn try_acquire_connection(
+        &self,
+        network_type: &NetworkConnectionType,
+        ip: &IpAddr,
+        limits: &ConnectionRateLimits,
+    ) -> Result<(), &'static str> {
+        if self.client_ip_connection.len() >= CLIENT_IP_BUCKET_CLEAN_THRESHOLD {
+            self.client_ip_connection
+                .retain(|_, bucket| bucket.idle_time() < CLIENT_IP_BUCKET_IDLE_TIME);
+        }
+
+        let mut client_ip = keyed_bucket(&self.client_ip_connection, *ip, limits.client_ip);
+        let mut listener = keyed_bucket(
+            &self.listener_connection,
+            network_type.to_string(),
+            limits.listener,
+        );
+        let mut broker_guard = self.broker_connection.lock().unwrap();
+        let mut broker = if limits.broker.is_unlimited() {
+            None
+        } else {
+            Some(broker_guard.get_or_insert_with(|| TokenBucket::new(limits.broker)))
+        };
+
+        if !check_bucket(client_ip.as_deref_mut(), limits.client_ip, 1) {
+            return Err(LIMIT_TYPE_CLIENT_IP_CONNECTION);
+        }
+        if !check_bucket(listener.as_deref_mut(), limits.listener, 1) {
+            return Err(LIMIT_TYPE_LISTENER_CONNECTION);
+        }
+        if !check_bucket(broker.as_deref_mut(), limits.broker, 1) {
+            return Err(LIMIT_TYPE_BROKER_CONNECTION);
+        }
+
+        for bucket in [client_ip.as_deref_mut(), listener.as_deref_mut(), broker]
+            .into_iter()
+            .flatten()
+        {
+            bucket.consume(1);
+        }
+        Ok(())
+    }
+
+    pub fn try_acquire_subscribe(&self, connect_id: u64, limit: RateLimit) -> bool {
+        match keyed_bucket(&self.client_subscribe, connect_id, limit) {
+            Some(mut bucket) => bucket.try_acquire(limit, 1),
+            None => true,
+        }
+    }
+
+    // Takes one publish token and payload_len bytes tokens, or nothing when either is short.
+    pub fn try_acquire_publish(
+        &self,
+        connect_id: u64,
+        payload_len: u64,
+        publish_limit: RateLimit,
+        bytes_limit: RateLimit,
+    ) -> Result<(), &'static str> {
+        let mut publish = keyed_bucket(&self.client_publish, connect_id, publish_limit);
+        let mut bytes = keyed_bucket(&self.connection_bytes, connect_id, bytes_limit);
+
+        if !check_bucket(publish.as_deref_mut(), publish_limit, 1) {
+            return Err(LIMIT_TYPE_CLIENT_PUBLISH);
+        }
+        if !check_bucket(bytes.as_deref_mut(), bytes_limit, payload_len) {
+            return Err(LIMIT_TYPE_CONNECTION_BYTES);
+        }
+
+        if let Some(bucket) = publish.as_deref_mut() {
+            bucket.consume(1);
+        }
+        if let Some(bucket) = bytes.as_deref_mut() {
+            bucket.consume(payload_len);
+        }
+        Ok(())
+    }
+
+    pub fn remove_connection(&self, connect_id: u64) {
+        self.client_subscribe.remove(&connect_id);
+        self.client_publish.remove(&connect_id);
+        self.connection_bytes.remove(&connect_id);
+    }
+}
+
+fn keyed_bucket<K>(
+    buckets: &DashMap<K, TokenBucket>,
+    key: K,
+    limit: RateLimit,
+) -> Option<RefMut<'_, K, TokenBucket>>
+where
+    K: std::hash::Hash + Eq,
+{
+    if limit.is_unlimited() {
+        return None;
+    }
+    Some(
+        buckets
+            .entry(key)
+            .or_insert_with(|| TokenBucket::new(limit)),
+    )
+}
+
+fn check_bucket(bucket: Option<&mut TokenBucket>, limit: RateLimit, amount: u64) -> bool {
+    match bucket {
+        Some(bucket) => bucket.check(limit, amount),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+    use std::sync::Arc;
+
+    use grpc_clients::pool::ClientPool;
+    use metadata_struct::mqtt::cluster::MqttClusterDynamicConfig;
+
+    use super::{
+        is_connection_rate_exceeded, is_publish_rate_exceeded, is_subscribe_rate_exceeded,
+        ConnectionRateLimits, RateLimit, RateLimiter, TokenBucket,
+    };
+    use crate::handler::cache::CacheManager;
+    use crate::server::connection::NetworkConnectionType;
+
+    #[tokio::test]
+    pub async fn token_bucket_test() {
+        let limit = RateLimit::new(3, 0);
+        let mut bucket = TokenBucket::new(limit);
+        assert!(bucket.try_acquire(limit, 1));
+        assert!(bucket.try_acquire(limit, 2));
+        assert!(!bucket.try_acquire(limit, 1));
+
+        tokio::time::sleep(std::time::Duration::from_millis(400)).await;
+        assert!(bucket.try_acquire(limit, 1));
+        assert!(!bucket.try_acquire(limit, 4));
+
+        // The burst caps the bucket independently of the refill rate
+        let limit = RateLimit::new(1000, 2);
+        let mut bucket = TokenBucket::new(limit);
+        assert!(bucket.try_acquire(limit, 2));
+        assert!(!bucket.try_acquire(limit, 1));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(bucket.try_acquire(limit, 2));
+    }
+
+    #[tokio::test]
+    pub async fn token_bucket_oversized_test() {
+        // A request larger than the burst passes on a full bucket and leaves it in debt
+        let limit = RateLimit::new(10, 4);
+        let mut bucket = TokenBucket::new(limit);
+        assert!(bucket.try_acquire(limit, 12));
+        assert!(!bucket.try_acquire(limit, 1));
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+        assert!(!bucket.try_acquire(limit, 1));
+        tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
+        assert!(bucket.try_acquire(limit, 12));
+
+        // A partly drained bucket still turns it away
+        let mut bucket = TokenBucket::new(limit);
+        assert!(bucket.try_acquire(limit, 1));
+        assert!(!bucket.try_acquire(limit, 12));
+    }
+
+    #[tokio::test]
+    pub async fn rate_limiter_test() {
+        let limiter = RateLimiter::new();
+        let unlimited = RateLimit::new(0, 0);
+        let one = RateLimit::new(1, 0);
+        let unlimited_connection = ConnectionRateLimits {
+            broker: unlimited,
+            listener: unlimited,
+            client_ip: unlimited,
+        };
+        let ip: IpAddr = "192.168.1.1".parse().unwrap();
+        for _ in 0..100 {
+            assert!(limiter
+                .try_acquire_connection(&NetworkConnectionType::Tcp, &ip, &unlimited_connection)
+                .is_ok());
+        }
+
+        assert!(limiter.try_acquire_publish(1, 0, one, unlimited).is_ok());
+        assert!(limiter.try_acquire_publish(1, 0, one, unlimited).is_err());
+        assert!(limiter.try_acquire_publish(2, 0, one, unlimited).is_ok());
+
+        limiter.remove_connection(1);
+        assert!(limiter.try_acquire_publish(1, 0, one, unlimited).is_ok());
+
+        let bytes = RateLimit::new(10, 0);
+        assert!(limiter.try_acquire_publish(3, 8, unlimited, bytes).is_ok());
+        assert!(limiter.try_acquire_publish(3, 8, unlimited, bytes).is_err());
+
+        // A publish rejected by the bytes limit does not spend a publish token
+        assert!(limiter.try_acquire_publish(3, 8, one, bytes).is_err());
+        assert!(limiter.try_acquire_publish(3, 0, one, bytes).is_ok());
+    }
+
+    #[tokio::test]
+    pub async fn connection_rate_limiter_test() {
+        let limiter = RateLimiter::new();
+        let limits = ConnectionRateLimits {
+            broker: RateLimit::new(0, 0),
+            listener: RateLimit::new(1, 0),
+            client_ip: RateLimit::new(1, 2),
+        };
+        let ip: IpAddr = "192.168.1.1".parse().unwrap();
+        let tcp = NetworkConnectionType::Tcp;
+        assert!(limiter.try_acquire_connection(&tcp, &ip, &limits).is_ok());
+        // Rejected by the tcp listener, the client ip keeps its second token
+        assert_eq!(
+            limiter.try_acquire_connection(&tcp, &ip, &limits),
+            Err("listener_connection")
+        );
+        assert!(limiter
+            .try_acquire_connection(&NetworkConnectionType::Tls, &ip, &limits)
+            .is_ok());
+        assert_eq!(
+            limiter.try_acquire_connection(&NetworkConnectionType::WebSockets, &ip, &limits),
+            Err("client_ip_connection")
+        );
+    }
+
+    #[tokio::test]
+    pub async fn flow_control_rate_exceeded_test() {
+        let client_pool = Arc::new(ClientPool::new(1));
+        let cache_manager = Arc::new(CacheManager::new(client_pool, "test".to_string()));
+        let mut cluster = MqttClusterDynamicConfig::new();
+        cluster.flow_control.max_client_ip_connection_rate = 1;
+        cluster.flow_control.max_listener_connection_rate = 2;
+        cluster.flow_control.max_client_subscribe_rate = 1;
+        cluster.flow_control.max_connection_bytes_rate = 16;
+        cache_manager.set_cluster_info(cluster);
+
+        let ip1: IpAddr = "192.168.1.1".parse().unwrap();
+        let ip2: IpAddr = "192.168.1.2".parse().unwrap();
+        let ip3: IpAddr = "192.168.1.3".parse().unwrap();
+        let ip4: IpAddr = "192.168.1.4".parse().unwrap();
+        let tcp = NetworkConnectionType::Tcp;
+        assert!(!is_connection_rate_exceeded(&cache_manager, &tcp, &ip1));
+        assert!(is_connection_rate_exceeded(&cache_manager, &tcp, &ip1));
+        assert!(!is_connection_rate_exceeded(&cache_manager, &tcp, &ip2));
+        assert!(is_connection_rate_exceeded(&cache_manager, &tcp, &ip3));
+        assert!(!is_connection_rate_exceeded(
+            &cache_manager,
+            &NetworkConnectionType::Tls,
+            &ip4
+        ));
+
+        assert!(!is_subscribe_rate_exceeded(&cache_manager, 1));
+        assert!(is_subscribe_rate_exceeded(&cache_manager, 1));
+
+        assert!(!is_publish_rate_exceeded(&cache_manager, 1, 10));
+        assert!(is_publish_rate_exceeded(&cache_manager, 1, 10));
+        cache_manager.remove_connection(1);
+        assert!(!is_subscribe_rate_exceeded(&cache_manager, 1));
+        assert!(!is_publish_rate_exceeded(&cache_manager, 1, 10));
+    }
+}
@@ -13,11 +13,13 @@
 // limitations under the License.
 
 pub mod acl;
+pub mod auto_subscribe;
 pub mod cache;
 pub mod cluster_config;
 pub mod command;
 pub mod connection;
 pub mod constant;
+pub mod delay_publish;
 pub mod error;
 pub mod flow_control;
 pub mod heartbreat;
@@ -30,5 +32,6 @@ pub mod response;
 pub mod retain;
 pub mod session;
 pub mod topic;
+pub mod topic_rewrite;
 pub mod user;
 pub mod validator;
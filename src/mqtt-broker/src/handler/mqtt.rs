@@ -15,9 +15,11 @@
 use std::net::SocketAddr;
 use std::sync::Arc;
 
+use common_base::config::broker_mqtt::broker_mqtt_conf;
 use common_base::tools::now_second;
 use grpc_clients::pool::ClientPool;
 use log::{error, warn};
+use metadata_struct::mqtt::connection::MQTTConnection;
 use metadata_struct::mqtt::message::MqttMessage;
 use protocol::mqtt::common::{
     Connect, ConnectProperties, ConnectReturnCode, Disconnect, DisconnectProperties,
@@ -29,7 +31,10 @@ use protocol::mqtt::common::{
 };
 use storage_adapter::storage::StorageAdapter;
 
+use super::auto_subscribe::build_auto_subscribe;
 use super::connection::disconnect_connection;
+use super::delay_publish::{is_delay_topic, parse_delay_topic, save_delay_publish_message};
+use super::error::MqttBrokerError;
 use super::flow_control::is_flow_control;
 use super::message::build_message_expire;
 use super::retain::try_send_retain_message;
@@ -51,6 +56,7 @@ use crate::handler::response::{
 use crate::handler::retain::save_retain_message;
 use crate::handler::session::{build_session, save_session};
 use crate::handler::topic::{get_topic_name, try_init_topic};
+use crate::handler::topic_rewrite::rewrite_sub_path;
 use crate::handler::validator::{
     connect_validator, publish_validator, subscribe_validator, un_subscribe_validator,
 };
@@ -58,6 +64,7 @@ use crate::observability::system_topic::event::{
     st_report_connected_event, st_report_disconnected_event, st_report_subscribed_event,
     st_report_unsubscribed_event,
 };
+use crate::rule_engine::apply_rules;
 use crate::security::AuthDriver;
 use crate::server::connection_manager::ConnectionManager;
 use crate::storage::message::MessageStorage;
@@ -115,6 +122,7 @@ where
 
         if let Some(res) = connect_validator(
             &self.protocol,
+            &self.cache_manager,
             &cluster,
             &connect,
             &connect_properties,
@@ -126,9 +134,49 @@ where
             return res;
         }
 
+        // A verified client certificate or PSK identity can stand in for the client_id and username
+        let network_connection = self.connection_manager.get_connect(connect_id);
+        let peer_identity = network_connection
+            .as_ref()
+            .and_then(|connection| connection.peer_identity.clone());
+        let psk_identity = network_connection
+            .as_ref()
+            .and_then(|connection| connection.psk_identity.clone());
+        let x509_conf = &broker_mqtt_conf().auth.x509;
+
+        let (client_id, new_client_id) = match peer_identity
+            .as_ref()
+            .and_then(|identity| identity.client_id(x509_conf))
+        {
+            Some(client_id) => (client_id, false),
+            None => get_client_id(&connect.client_id),
+        };
+
+        let login_username = match peer_identity
+            .as_ref()
+            .and_then(|identity| identity.username(x509_conf))
+        {
+            Some(username) => username,
+            None => match psk_identity {
+                Some(identity) => identity,
+                None => login
+                    .as_ref()
+                    .map(|info| info.username.clone())
+                    .unwrap_or_default(),
+            },
+        };
+
         match self
             .auth_driver
-            .check_login_auth(login, &connect_properties, &addr)
+            .check_login_auth(
+                connect_id,
+                &client_id,
+                &self.protocol,
+                login,
+                &network_connection,
+                &connect_properties,
+                &addr,
+            )
             .await
         {
             Ok(flag) => {
@@ -151,9 +199,7 @@ where
             }
         }
 
-        let (client_id, new_client_id) = get_client_id(&connect.client_id);
-
-        let connection = build_connection(
+        let mut connection = build_connection(
             connect_id,
             client_id.clone(),
             &cluster,
@@ -161,6 +207,7 @@ where
             &connect_properties,
             &addr,
         );
+        connection.login_success(login_username);
 
         let (session, new_session) = match build_session(
             connect_id,
@@ -248,6 +295,18 @@ where
         )
         .await;
 
+        // retained messages of the auto subscriptions must not reach the client before the CONNACK
+        if !self.cache_manager.get_auto_subscribe_rules().is_empty() {
+            let connack_written = self.connection_manager.wait_connack_written(connect_id);
+            let service = self.clone();
+            let connection = connection.clone();
+            tokio::spawn(async move {
+                if connack_written.await.is_ok() {
+                    service.auto_subscribe(connect_id, &connection).await;
+                }
+            });
+        }
+
         response_packet_mqtt_connect_success(
             &self.protocol,
             &cluster,
@@ -333,9 +392,41 @@ where
             }
         };
 
+        // PUBLISH to $delayed/{seconds}/{topic} is held back and delivered to {topic} later
+        let (delay_secs, target_topic_name) = if is_delay_topic(&topic_name) {
+            match parse_delay_topic(&topic_name) {
+                Ok((delay_secs, target)) => (Some(delay_secs), target),
+                Err(e) => {
+                    if is_flow_control(&self.protocol, publish.qos) {
+                        connection.recv_qos_message_decr();
+                    }
+
+                    if is_puback {
+                        return Some(response_packet_mqtt_puback_fail(
+                            &self.protocol,
+                            &connection,
+                            publish.pkid,
+                            PubAckReason::TopicNameInvalid,
+                            Some(e.to_string()),
+                        ));
+                    } else {
+                        return Some(response_packet_mqtt_pubrec_fail(
+                            &self.protocol,
+                            &connection,
+                            publish.pkid,
+                            PubRecReason::TopicNameInvalid,
+                            Some(e.to_string()),
+                        ));
+                    }
+                }
+            }
+        } else {
+            (None, topic_name.clone())
+        };
+
         if !self
             .auth_driver
-            .allow_publish(&connection, &topic_name, publish.retain, publish.qos)
+            .allow_publish(&connection, &target_topic_name, publish.retain, publish.qos)
             .await
         {
             if is_puback {
@@ -358,7 +449,7 @@ where
         }
 
         let topic = match try_init_topic(
-            &topic_name,
+            &target_topic_name,
             &self.cache_manager,
             &self.message_storage_adapter,
             &self.client_pool,
@@ -393,6 +484,20 @@ where
 
         let client_id = connection.client_id.clone();
 
+        if let Some(delay_secs) = delay_secs {
+            return self
+                .delay_publish(
+                    connect_id,
+                    &connection,
+                    &topic_name,
+                    &target_topic_name,
+                    delay_secs,
+                    publish,
+                    publish_properties,
+                )
+                .await;
+        }
+
         // Persisting retain message data
         match save_retain_message(
             &self.cache_manager,
@@ -471,6 +576,9 @@ where
         } else {
             "-1".to_string()
         };
+
+        apply_rules(&self.cache_manager, &connection, &topic_name, &publish).await;
+
         let user_properties: Vec<(String, String)> = vec![("offset".to_string(), offset)];
 
         self.cache_manager
@@ -545,6 +653,113 @@ where
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
+    async fn delay_publish(
+        &self,
+        connect_id: u64,
+        connection: &MQTTConnection,
+        topic_name: &str,
+        target_topic_name: &str,
+        delay_secs: u64,
+        publish: Publish,
+        publish_properties: Option<PublishProperties>,
+    ) -> Option<MqttPacket> {
+        let is_puback = publish.qos != QoS::ExactlyOnce;
+        let id = match save_delay_publish_message(
+            &self.cache_manager,
+            &self.message_storage_adapter,
+            &connection.client_id,
+            delay_secs,
+            target_topic_name,
+            &publish,
+            &publish_properties,
+        )
+        .await
+        {
+            Ok(id) => id,
+            Err(e) => {
+                if is_flow_control(&self.protocol, publish.qos) {
+                    connection.recv_qos_message_decr();
+                }
+
+                let reason = if matches!(e, MqttBrokerError::DelayPublishLimitExceeded(_)) {
+                    (PubAckReason::QuotaExceeded, PubRecReason::QuotaExceeded)
+                } else {
+                    (
+                        PubAckReason::UnspecifiedError,
+                        PubRecReason::UnspecifiedError,
+                    )
+                };
+                if is_puback {
+                    return Some(response_packet_mqtt_puback_fail(
+                        &self.protocol,
+                        connection,
+                        publish.pkid,
+                        reason.0,
+                        Some(e.to_string()),
+                    ));
+                } else {
+                    return Some(response_packet_mqtt_pubrec_fail(
+                        &self.protocol,
+                        connection,
+                        publish.pkid,
+                        reason.1,
+                        Some(e.to_string()),
+                    ));
+                }
+            }
+        };
+        let user_properties: Vec<(String, String)> = vec![("delay_id".to_string(), id)];
+
+        self.cache_manager
+            .add_topic_alias(connect_id, topic_name, &publish_properties);
+
+        match publish.qos {
+            QoS::AtMostOnce => None,
+            QoS::AtLeastOnce => {
+                if is_flow_control(&self.protocol, publish.qos) {
+                    connection.recv_qos_message_decr();
+                }
+
+                Some(response_packet_mqtt_puback_success(
+                    &self.protocol,
+                    PubAckReason::Success,
+                    publish.pkid,
+                    user_properties,
+                ))
+            }
+            QoS::ExactlyOnce => {
+                if let Err(e) = pkid_save(
+                    &self.cache_manager,
+                    &self.client_pool,
+                    &connection.client_id,
+                    publish.pkid,
+                )
+                .await
+                {
+                    if is_flow_control(&self.protocol, publish.qos) {
+                        connection.recv_qos_message_decr();
+                    }
+
+                    return Some(response_packet_mqtt_pubrec_fail(
+                        &self.protocol,
+                        connection,
+                        publish.pkid,
+                        PubRecReason::UnspecifiedError,
+                        Some(e.to_string()),
+                    ));
+                }
+
+                Some(response_packet_mqtt_pubrec_success(
+                    &self.protocol,
+                    PubRecReason::Success,
+                    publish.pkid,
+                    user_properties,
+                ))
+            }
+        }
+    }
+
     pub async fn publish_ack(
         &self,
         connect_id: u64,
@@ -706,7 +921,7 @@ where
     pub async fn subscribe(
         &self,
         connect_id: u64,
-        subscribe: Subscribe,
+        mut subscribe: Subscribe,
         subscribe_properties: Option<SubscribeProperties>,
     ) -> MqttPacket {
         let connection = if let Some(se) = self.cache_manager.connection_info.get(&connect_id) {
@@ -720,6 +935,15 @@ where
 
         let client_id = connection.client_id.clone();
 
+        for filter in subscribe.filters.iter_mut() {
+            filter.path = rewrite_sub_path(
+                &self.cache_manager,
+                &filter.path,
+                &client_id,
+                &connection.login_user,
+            );
+        }
+
         if let Some(packet) = subscribe_validator(
             &self.protocol,
             &self.cache_manager,
@@ -825,6 +1049,95 @@ where
         response_packet_mqtt_suback(&self.protocol, &connection, pkid, return_codes, None)
     }
 
+    // Subscribe a newly connected client to the cluster's auto subscribe rules, going through
+    // the same topic rewrite, ACL check and subscription registration as an explicit SUBSCRIBE.
+    async fn auto_subscribe(&self, connect_id: u64, connection: &MQTTConnection) {
+        let rules = self.cache_manager.get_auto_subscribe_rules();
+        let subscribe = if let Some(subscribe) = build_auto_subscribe(
+            &self.protocol,
+            &rules,
+            &connection.client_id,
+            &connection.login_user,
+        ) {
+            subscribe
+        } else {
+            return;
+        };
+
+        let mut filters = Vec::new();
+        for mut filter in subscribe.filters {
+            filter.path = rewrite_sub_path(
+                &self.cache_manager,
+                &filter.path,
+                &connection.client_id,
+                &connection.login_user,
+            );
+            let filter_subscribe = Subscribe {
+                packet_identifier: subscribe.packet_identifier,
+                filters: vec![filter.clone()],
+            };
+            if !self
+                .auth_driver
+                .allow_subscribe(connection, &filter_subscribe)
+                .await
+            {
+                warn!(
+                    "Client {} is not authorized to auto subscribe to {}, skipped.",
+                    connection.client_id, filter.path
+                );
+                continue;
+            }
+            filters.push(filter);
+        }
+
+        if filters.is_empty() {
+            return;
+        }
+
+        let subscribe = Subscribe {
+            packet_identifier: subscribe.packet_identifier,
+            filters,
+        };
+
+        self.cache_manager.add_client_subscribe(
+            connection.client_id.clone(),
+            self.protocol.clone(),
+            subscribe.clone(),
+            None,
+        );
+
+        self.subscribe_manager
+            .add_subscribe(
+                connection.client_id.clone(),
+                self.protocol.clone(),
+                subscribe.clone(),
+                None,
+            )
+            .await;
+
+        st_report_subscribed_event(
+            &self.message_storage_adapter,
+            &self.cache_manager,
+            &self.client_pool,
+            connection,
+            connect_id,
+            &self.connection_manager,
+            &subscribe,
+        )
+        .await;
+
+        try_send_retain_message(
+            self.protocol.clone(),
+            connection.client_id.clone(),
+            subscribe,
+            None,
+            self.client_pool.clone(),
+            self.cache_manager.clone(),
+            self.connection_manager.clone(),
+        )
+        .await;
+    }
+
     pub async fn ping(&self, connect_id: u64, _: PingReq) -> MqttPacket {
         let connection = if let Some(se) = self.cache_manager.connection_info.get(&connect_id) {
             se.clone()
@@ -848,7 +1161,7 @@ where
     pub async fn un_subscribe(
         &self,
         connect_id: u64,
-        un_subscribe: Unsubscribe,
+        mut un_subscribe: Unsubscribe,
         _: Option<UnsubscribeProperties>,
     ) -> MqttPacket {
         let connection = if let Some(se) = self.cache_manager.connection_info.get(&connect_id) {
@@ -860,6 +1173,16 @@ where
             );
         };
 
+        // Unsubscribe from the same filter the SUBSCRIBE was rewritten to
+        for path in un_subscribe.filters.iter_mut() {
+            *path = rewrite_sub_path(
+                &self.cache_manager,
+                path,
+                &connection.client_id,
+                &connection.login_user,
+            );
+        }
+
         if let Some(packet) = un_subscribe_validator(
             &connection.client_id,
             &self.cache_manager,
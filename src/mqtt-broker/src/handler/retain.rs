@@ -53,23 +53,34 @@ pub async fn save_retain_message(
         return Ok(());
     }
 
+    let message_expire = build_message_expire(cache_manager, publish_properties);
+    let retain_message =
+        MqttMessage::build_message(client_id, publish, publish_properties, message_expire);
+    save_retain_mqtt_message(cache_manager, client_pool, topic_name, &retain_message).await
+}
+
+// Stores a message that is published with the retain flag, an empty payload clears the
+// retained message of the topic
+pub async fn save_retain_mqtt_message(
+    cache_manager: &Arc<CacheManager>,
+    client_pool: &Arc<ClientPool>,
+    topic_name: String,
+    message: &MqttMessage,
+) -> Result<(), MqttBrokerError> {
     let topic_storage = TopicStorage::new(client_pool.clone());
 
-    if publish.payload.is_empty() {
+    if message.payload.is_empty() {
         topic_storage
             .delete_retain_message(topic_name.clone())
             .await?;
         cache_manager.update_topic_retain_message(&topic_name, Some(Vec::new()));
     } else {
-        record_retain_recv_metrics(publish.qos);
-        let message_expire = build_message_expire(cache_manager, publish_properties);
-        let retain_message =
-            MqttMessage::build_message(client_id, publish, publish_properties, message_expire);
+        record_retain_recv_metrics(message.qos);
         topic_storage
-            .set_retain_message(topic_name.clone(), &retain_message, message_expire)
+            .set_retain_message(topic_name.clone(), message, message.expiry_interval)
             .await?;
 
-        cache_manager.update_topic_retain_message(&topic_name, Some(retain_message.encode()));
+        cache_manager.update_topic_retain_message(&topic_name, Some(message.encode()));
     }
 
     Ok(())
@@ -19,12 +19,14 @@ use common_base::config::broker_mqtt::broker_mqtt_conf;
 use common_base::tools::unique_id;
 use grpc_clients::pool::ClientPool;
 use metadata_struct::mqtt::topic::MqttTopic;
+use metadata_struct::mqtt::topic_rewrite_rule::MqttTopicRewriteAction;
 use protocol::mqtt::common::{Publish, PublishProperties};
 use regex::Regex;
 use storage_adapter::storage::{ShardConfig, StorageAdapter};
 
 use super::error::MqttBrokerError;
 use crate::handler::cache::CacheManager;
+use crate::handler::topic_rewrite::rewrite_topic_name;
 use crate::storage::message::cluster_name;
 use crate::storage::topic::TopicStorage;
 
@@ -100,6 +102,15 @@ pub fn get_topic_name(
         } else {
             return Err(MqttBrokerError::TopicNameInvalid());
         }
+    } else if let Some(connection) = metadata_cache.connection_info.get(&connect_id) {
+        // A topic found through an alias was already rewritten when the alias was set
+        rewrite_topic_name(
+            metadata_cache,
+            MqttTopicRewriteAction::Publish,
+            &topic,
+            &connection.client_id,
+            &connection.login_user,
+        )
     } else {
         topic
     };
@@ -0,0 +1,417 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+
+use grpc_clients::pool::ClientPool;
+use log::{error, info};
+use metadata_struct::mqtt::topic_rewrite_rule::{MqttTopicRewriteAction, MqttTopicRewriteRule};
+use regex::Regex;
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+
+use crate::handler::cache::CacheManager;
+use crate::storage::topic_rewrite_rule::TopicRewriteRuleStorage;
+use crate::subscribe::sub_common::sub_path_validator;
+use crate::subscribe::topic_trie::topic_filter_match;
+
+const SHARE_SUB_REWRITE_PREFIX: &str = "$share/";
+
+// A rule as it is kept in the cache, its regex is compiled once when it is loaded
+#[derive(Clone)]
+pub struct TopicRewriteRule {
+    pub rule: MqttTopicRewriteRule,
+    pub regex: Option<Regex>,
+}
+
+impl TopicRewriteRule {
+    pub fn new(rule: MqttTopicRewriteRule) -> Result<Self, regex::Error> {
+        let regex = if rule.regex.is_empty() {
+            None
+        } else {
+            Some(Regex::new(&rule.regex)?)
+        };
+        Ok(TopicRewriteRule { rule, regex })
+    }
+}
+
+// Rewrite the topic of a PUBLISH or the filter of a SUBSCRIBE/UNSUBSCRIBE with
+// the first rule whose source filter (and regex, when set) matches it.
+pub fn rewrite_topic_name(
+    cache_manager: &Arc<CacheManager>,
+    action: MqttTopicRewriteAction,
+    topic_name: &str,
+    client_id: &str,
+    username: &str,
+) -> String {
+    if cache_manager.topic_rewrite_rules.is_empty() {
+        return topic_name.to_owned();
+    }
+
+    let rules = cache_manager.get_topic_rewrite_rules();
+    apply_topic_rewrite_rules(&rules, action, topic_name, client_id, username)
+        .unwrap_or_else(|| topic_name.to_owned())
+}
+
+// Shared subscriptions keep their $share/{group}/ prefix, only the filter is rewritten
+pub fn rewrite_sub_path(
+    cache_manager: &Arc<CacheManager>,
+    sub_path: &str,
+    client_id: &str,
+    username: &str,
+) -> String {
+    if let Some(rest) = sub_path.strip_prefix(SHARE_SUB_REWRITE_PREFIX) {
+        if let Some((group_name, filter)) = rest.split_once('/') {
+            let filter = rewrite_topic_name(
+                cache_manager,
+                MqttTopicRewriteAction::Subscribe,
+                filter,
+                client_id,
+                username,
+            );
+            return format!("{}{}/{}", SHARE_SUB_REWRITE_PREFIX, group_name, filter);
+        }
+    }
+    rewrite_topic_name(
+        cache_manager,
+        MqttTopicRewriteAction::Subscribe,
+        sub_path,
+        client_id,
+        username,
+    )
+}
+
+pub fn apply_topic_rewrite_rules(
+    rules: &[TopicRewriteRule],
+    action: MqttTopicRewriteAction,
+    topic_name: &str,
+    client_id: &str,
+    username: &str,
+) -> Option<String> {
+    for TopicRewriteRule { rule, regex } in rules {
+        if !rule.action.contains(action) || !topic_filter_match(topic_name, &rule.source_topic) {
+            continue;
+        }
+
+        let captures = if let Some(regex) = regex {
+            if let Some(caps) = regex.captures(topic_name) {
+                caps.iter()
+                    .skip(1)
+                    .map(|cap| cap.map(|m| m.as_str().to_owned()).unwrap_or_default())
+                    .collect()
+            } else {
+                continue;
+            }
+        } else {
+            wildcard_captures(topic_name, &rule.source_topic)
+        };
+
+        let dest_topic = render_dest_topic(&rule.dest_topic, &captures, client_id, username);
+        // a rule must not turn a topic into a filter, or a filter into an invalid one
+        let valid = if action == MqttTopicRewriteAction::Publish {
+            !dest_topic.contains(['+', '#'])
+        } else {
+            sub_path_validator(dest_topic.clone())
+        };
+        if !valid {
+            error!(
+                "Topic rewrite rule {} rewrote {} to the invalid topic {}",
+                rule.source_topic, topic_name, dest_topic
+            );
+            continue;
+        }
+        return Some(dest_topic);
+    }
+    None
+}
+
+// Every + level of the filter captures one topic level, # captures the rest of the topic
+fn wildcard_captures(topic_name: &str, filter: &str) -> Vec<String> {
+    let topic_levels: Vec<&str> = topic_name.split('/').collect();
+    let mut captures = Vec::new();
+    for (i, level) in filter.split('/').enumerate() {
+        match level {
+            "+" => captures.push(topic_levels.get(i).unwrap_or(&"").to_string()),
+            "#" => {
+                captures.push(
+                    topic_levels
+                        .get(i..)
+                        .map(|levels| levels.join("/"))
+                        .unwrap_or_default(),
+                );
+                break;
+            }
+            _ => {}
+        }
+    }
+    captures
+}
+
+fn render_dest_topic(
+    dest_topic: &str,
+    captures: &[String],
+    client_id: &str,
+    username: &str,
+) -> String {
+    let mut result = String::with_capacity(dest_topic.len());
+    let mut rest = dest_topic;
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        if let Some(inner) = rest.strip_prefix('{') {
+            if let Some(end) = inner.find('}') {
+                let value = match &inner[..end] {
+                    "clientid" => Some(client_id),
+                    "username" => Some(username),
+                    _ => None,
+                };
+                if let Some(value) = value {
+                    result.push_str(value);
+                    rest = &inner[end + 1..];
+                    continue;
+                }
+            }
+            result.push('$');
+            continue;
+        }
+
+        let digits = rest.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            result.push('$');
+            continue;
+        }
+        if let Some(value) = rest[..digits]
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| index.checked_sub(1))
+            .and_then(|index| captures.get(index))
+        {
+            result.push_str(value);
+        }
+        rest = &rest[digits..];
+    }
+    result.push_str(rest);
+    result
+}
+
+pub struct UpdateTopicRewriteCache {
+    stop_send: broadcast::Sender<bool>,
+    cache_manager: Arc<CacheManager>,
+    client_pool: Arc<ClientPool>,
+}
+
+impl UpdateTopicRewriteCache {
+    pub fn new(
+        stop_send: broadcast::Sender<bool>,
+        cache_manager: Arc<CacheManager>,
+        client_pool: Arc<ClientPool>,
+    ) -> Self {
+        UpdateTopicRewriteCache {
+            stop_send,
+            cache_manager,
+            client_pool,
+        }
+    }
+
+    pub async fn start_update(&self) {
+        loop {
+            let mut stop_rx = self.stop_send.subscribe();
+            select! {
+                val = stop_rx.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            info!("{}","Topic rewrite rule cache updating thread stopped successfully.");
+                            break;
+                        }
+                    }
+                }
+                _ = self.update_topic_rewrite_cache()=>{
+                }
+            }
+        }
+    }
+
+    async fn update_topic_rewrite_cache(&self) {
+        let storage = TopicRewriteRuleStorage::new(self.client_pool.clone());
+        match storage.list_topic_rewrite_rule().await {
+            Ok(rules) => {
+                let mut keys = HashSet::new();
+                for rule in rules {
+                    let key = CacheManager::topic_rewrite_rule_key(rule.action, &rule.source_topic);
+                    // an unchanged rule keeps its compiled regex
+                    if let Some(cached) = self.cache_manager.topic_rewrite_rules.get(&key) {
+                        if cached.rule == rule {
+                            keys.insert(key);
+                            continue;
+                        }
+                    }
+                    match TopicRewriteRule::new(rule) {
+                        Ok(rule) => {
+                            keys.insert(key);
+                            self.cache_manager.add_topic_rewrite_rule(rule);
+                        }
+                        Err(e) => {
+                            error!(
+                                "Topic rewrite rule {} has an invalid regex, error message: {}",
+                                key, e
+                            );
+                        }
+                    }
+                }
+                self.cache_manager
+                    .topic_rewrite_rules
+                    .retain(|key, _| keys.contains(key));
+            }
+            Err(e) => {
+                error!(
+                    "Updating topic rewrite rule cache failed, error message: {}",
+                    e
+                );
+            }
+        }
+        sleep(Duration::from_secs(5)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metadata_struct::mqtt::topic_rewrite_rule::{MqttTopicRewriteAction, MqttTopicRewriteRule};
+
+    use super::{
+        apply_topic_rewrite_rules, render_dest_topic, wildcard_captures, TopicRewriteRule,
+    };
+
+    fn rule(
+        action: MqttTopicRewriteAction,
+        source_topic: &str,
+        dest_topic: &str,
+        regex: &str,
+    ) -> TopicRewriteRule {
+        TopicRewriteRule::new(MqttTopicRewriteRule {
+            action,
+            source_topic: source_topic.to_string(),
+            dest_topic: dest_topic.to_string(),
+            regex: regex.to_string(),
+            create_time: 0,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn render_dest_topic_test() {
+        let captures = vec!["a".to_string(), "b/c".to_string()];
+        assert_eq!(
+            render_dest_topic("z/$1/$2", &captures, "c1", "u1"),
+            "z/a/b/c"
+        );
+        assert_eq!(
+            render_dest_topic("z/${clientid}/${username}/$1", &captures, "c1", "u1"),
+            "z/c1/u1/a"
+        );
+        // Unknown captures render empty, unknown placeholders and lone $ are kept
+        assert_eq!(render_dest_topic("z/$3", &captures, "c1", "u1"), "z/");
+        assert_eq!(
+            render_dest_topic("$SYS/${other}/$", &captures, "c1", "u1"),
+            "$SYS/${other}/$"
+        );
+    }
+
+    #[test]
+    fn wildcard_captures_test() {
+        assert_eq!(wildcard_captures("x/y/a", "x/y/+"), vec!["a"]);
+        assert_eq!(wildcard_captures("x/1/y/2/3", "x/+/y/#"), vec!["1", "2/3"]);
+        assert_eq!(wildcard_captures("x", "x/#"), vec![""]);
+    }
+
+    #[test]
+    fn apply_topic_rewrite_rules_test() {
+        let rules = vec![
+            rule(MqttTopicRewriteAction::All, "x/y/+", "z/y/$1", ""),
+            rule(
+                MqttTopicRewriteAction::Publish,
+                "device/#",
+                "d/${clientid}/$1",
+                "^device/(.+)/up$",
+            ),
+            rule(MqttTopicRewriteAction::Subscribe, "device/#", "d/$1", ""),
+        ];
+
+        let publish = MqttTopicRewriteAction::Publish;
+        let subscribe = MqttTopicRewriteAction::Subscribe;
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, publish, "x/y/a", "c1", "u1"),
+            Some("z/y/a".to_string())
+        );
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, subscribe, "x/y/+", "c1", "u1"),
+            Some("z/y/+".to_string())
+        );
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, publish, "device/t1/up", "c1", "u1"),
+            Some("d/c1/t1".to_string())
+        );
+        // The regex of the publish rule does not match and the subscribe rule does not apply
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, publish, "device/t1/down", "c1", "u1"),
+            None
+        );
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, subscribe, "device/t1/#", "c1", "u1"),
+            Some("d/t1/#".to_string())
+        );
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, publish, "x/y/a/b", "c1", "u1"),
+            None
+        );
+    }
+
+    #[test]
+    fn apply_topic_rewrite_rules_invalid_topic_test() {
+        let rules = vec![
+            rule(MqttTopicRewriteAction::All, "x/#", "y/${clientid}/$1", ""),
+            rule(MqttTopicRewriteAction::Publish, "z/#", "#/$1", ""),
+        ];
+        assert!(TopicRewriteRule::new(MqttTopicRewriteRule {
+            action: MqttTopicRewriteAction::All,
+            source_topic: "x/#".to_string(),
+            dest_topic: "y".to_string(),
+            regex: "(".to_string(),
+            create_time: 0,
+        })
+        .is_err());
+
+        let publish = MqttTopicRewriteAction::Publish;
+        let subscribe = MqttTopicRewriteAction::Subscribe;
+        // a client id with a wildcard must not turn the topic into a filter
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, publish, "x/a", "c+", "u1"),
+            None
+        );
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, publish, "z/a", "c1", "u1"),
+            None
+        );
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, subscribe, "x/+", "c1", "u1"),
+            Some("y/c1/+".to_string())
+        );
+        assert_eq!(
+            apply_topic_rewrite_rules(&rules, subscribe, "x/a", "c#", "u1"),
+            None
+        );
+    }
+}
@@ -61,6 +61,12 @@ impl UpdateUserCache {
                 error!("{}", e);
             }
         };
+        match self.auth_driver.update_psk_cache().await {
+            Ok(_) => {}
+            Err(e) => {
+                error!("Updating psk identity cache failed, error message: {}", e);
+            }
+        };
         sleep(Duration::from_secs(5)).await;
     }
 }
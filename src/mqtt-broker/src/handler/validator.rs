@@ -27,12 +27,14 @@ use protocol::mqtt::common::{
     LastWillProperties, Login, MqttPacket, MqttProtocol, PubAckReason, PubRecReason, Publish,
     PublishProperties, QoS, Subscribe, SubscribeReasonCode, UnsubAckReason, Unsubscribe,
 };
+use tokio::io::AsyncWrite;
 use tokio_util::codec::FramedWrite;
 
 use super::cache::CacheManager;
 use super::error::MqttBrokerError;
 use super::flow_control::{
-    is_connection_rate_exceeded, is_flow_control, is_subscribe_rate_exceeded,
+    is_connection_rate_exceeded, is_flow_control, is_publish_rate_exceeded,
+    is_subscribe_rate_exceeded,
 };
 use super::pkid::pkid_exists;
 use super::response::{
@@ -43,129 +45,56 @@ use super::response::{
 use super::topic::topic_name_validator;
 use crate::security::authentication_acl;
 use crate::security::login::is_ip_blacklist;
+use crate::server::connection::NetworkConnectionType;
 use crate::server::connection_manager::ConnectionManager;
 use crate::subscribe::sub_common::sub_path_validator;
 
-pub async fn tcp_establish_connection_check(
-    addr: &SocketAddr,
-    connection_manager: &Arc<ConnectionManager>,
-    write_frame_stream: &mut FramedWrite<tokio::io::WriteHalf<tokio::net::TcpStream>, MqttCodec>,
-) -> bool {
-    if connection_manager.tcp_connect_num_check() {
-        let packet_wrapper = MqttPacketWrapper {
-            protocol_version: MqttProtocol::Mqtt5.into(),
-            packet: response_packet_mqtt_distinct_by_reason(
-                &MqttProtocol::Mqtt5,
-                Some(DisconnectReasonCode::QuotaExceeded),
-            ),
-        };
-        match write_frame_stream.send(packet_wrapper).await {
-            Ok(_) => {}
-            Err(e) => error!("{}", e),
-        }
-
-        match write_frame_stream.close().await {
-            Ok(_) => {
-                error!(
-                    "tcp connection failed to establish from IP: {}",
-                    addr.to_string()
-                );
-            }
-            Err(e) => error!("{}", e),
-        }
-        return false;
-    }
-
-    if is_connection_rate_exceeded() {
-        let packet_wrapper = MqttPacketWrapper {
-            protocol_version: MqttProtocol::Mqtt5.into(),
-            packet: response_packet_mqtt_distinct_by_reason(
-                &MqttProtocol::Mqtt5,
-                Some(DisconnectReasonCode::ConnectionRateExceeded),
-            ),
-        };
-        match write_frame_stream.send(packet_wrapper).await {
-            Ok(_) => {}
-            Err(e) => error!("{}", e),
-        }
-
-        match write_frame_stream.close().await {
-            Ok(_) => {
-                error!(
-                    "tcp connection failed to establish from IP: {}",
-                    addr.to_string()
-                );
-            }
-            Err(e) => error!("{}", e),
-        }
-        return false;
-    }
-    true
-}
-
-pub async fn tcp_tls_establish_connection_check(
+// Checks the connection number and connection rate limits for a newly accepted tcp, tls or quic
+// connection. A rejected connection is sent a DISCONNECT with the reason and closed.
+pub async fn establish_connection_check<T>(
     addr: &SocketAddr,
+    network_type: &NetworkConnectionType,
+    cache_manager: &Arc<CacheManager>,
     connection_manager: &Arc<ConnectionManager>,
-    write_frame_stream: &mut FramedWrite<
-        tokio::io::WriteHalf<tokio_rustls::server::TlsStream<tokio::net::TcpStream>>,
-        MqttCodec,
-    >,
-) -> bool {
-    if connection_manager.tcp_connect_num_check() {
-        let packet_wrapper = MqttPacketWrapper {
-            protocol_version: MqttProtocol::Mqtt5.into(),
-            packet: response_packet_mqtt_distinct_by_reason(
-                &MqttProtocol::Mqtt5,
-                Some(DisconnectReasonCode::QuotaExceeded),
-            ),
-        };
-        match write_frame_stream.send(packet_wrapper).await {
-            Ok(_) => {}
-            Err(e) => error!("{}", e),
-        }
+    write_frame_stream: &mut FramedWrite<T, MqttCodec>,
+) -> bool
+where
+    T: AsyncWrite + Unpin,
+{
+    let reason = if connection_manager.tcp_connect_num_check() {
+        DisconnectReasonCode::QuotaExceeded
+    } else if is_connection_rate_exceeded(cache_manager, network_type, &addr.ip()) {
+        DisconnectReasonCode::ConnectionRateExceeded
+    } else {
+        return true;
+    };
 
-        match write_frame_stream.close().await {
-            Ok(_) => {
-                error!(
-                    "tcp connection failed to establish from IP: {}",
-                    addr.to_string()
-                );
-            }
-            Err(e) => error!("{}", e),
-        }
-        return false;
+    let packet_wrapper = MqttPacketWrapper {
+        protocol_version: MqttProtocol::Mqtt5.into(),
+        packet: response_packet_mqtt_distinct_by_reason(&MqttProtocol::Mqtt5, Some(reason)),
+    };
+    match write_frame_stream.send(packet_wrapper).await {
+        Ok(_) => {}
+        Err(e) => error!("{}", e),
     }
 
-    if is_connection_rate_exceeded() {
-        let packet_wrapper = MqttPacketWrapper {
-            protocol_version: MqttProtocol::Mqtt5.into(),
-            packet: response_packet_mqtt_distinct_by_reason(
-                &MqttProtocol::Mqtt5,
-                Some(DisconnectReasonCode::ConnectionRateExceeded),
-            ),
-        };
-        match write_frame_stream.send(packet_wrapper).await {
-            Ok(_) => {}
-            Err(e) => error!("{}", e),
-        }
-
-        match write_frame_stream.close().await {
-            Ok(_) => {
-                error!(
-                    "tcp connection failed to establish from IP: {}",
-                    addr.to_string()
-                );
-            }
-            Err(e) => error!("{}", e),
+    match write_frame_stream.close().await {
+        Ok(_) => {
+            error!(
+                "{} connection failed to establish from IP: {}",
+                network_type,
+                addr.to_string()
+            );
         }
-        return false;
+        Err(e) => error!("{}", e),
     }
-    true
+    false
 }
 
 #[allow(clippy::too_many_arguments)]
 pub fn connect_validator(
     protocol: &MqttProtocol,
+    cache_manager: &Arc<CacheManager>,
     cluster: &MqttClusterDynamicConfig,
     connect: &Connect,
     connect_properties: &Option<ConnectProperties>,
@@ -183,7 +112,7 @@ pub fn connect_validator(
         ));
     }
 
-    if is_ip_blacklist(addr) {
+    if is_ip_blacklist(cache_manager, addr) {
         return Some(response_packet_mqtt_connect_fail(
             protocol,
             ConnectReturnCode::Banned,
@@ -393,8 +322,9 @@ pub async fn publish_validator(
         }
     }
 
-    if is_flow_control(protocol, publish.qos)
-        && connection.get_recv_qos_message() >= cluster.protocol.receive_max as isize
+    if (is_flow_control(protocol, publish.qos)
+        && connection.get_recv_qos_message() >= cluster.protocol.receive_max as isize)
+        || is_publish_rate_exceeded(cache_manager, connection.connect_id, publish.payload.len())
     {
         if is_puback {
             return Some(response_packet_mqtt_puback_fail(
@@ -445,7 +375,7 @@ pub async fn publish_validator(
 
 pub async fn subscribe_validator(
     protocol: &MqttProtocol,
-    _cache_manager: &Arc<CacheManager>,
+    cache_manager: &Arc<CacheManager>,
     _client_pool: &Arc<ClientPool>,
     connection: &MQTTConnection,
     subscribe: &Subscribe,
@@ -507,7 +437,7 @@ pub async fn subscribe_validator(
         ));
     }
 
-    if is_subscribe_rate_exceeded() {
+    if is_subscribe_rate_exceeded(cache_manager, connection.connect_id) {
         return Some(response_packet_mqtt_suback(
             protocol,
             connection,
@@ -22,22 +22,27 @@ use common_base::tools::now_second;
 use grpc_clients::pool::ClientPool;
 use handler::acl::UpdateAclCache;
 use handler::cache::CacheManager;
+use handler::cluster_config::UpdateClusterConfigCache;
+use handler::delay_publish::DelayPublishManager;
 use handler::heartbreat::{register_node, report_heartbeat};
 use handler::keep_alive::ClientKeepAlive;
+use handler::topic_rewrite::UpdateTopicRewriteCache;
 use handler::user::UpdateUserCache;
 use lazy_static::lazy_static;
 use log::{error, info};
 use observability::start_opservability;
+use rule_engine::{RuleTaskWorker, UpdateRuleCache};
 use security::AuthDriver;
 use server::connection_manager::ConnectionManager;
 use server::grpc::server::GrpcServer;
 use server::http::server::{start_http_server, HttpServerState};
+use server::quic::server::start_quic_server;
 use server::tcp::server::start_tcp_server;
 use server::websocket::server::{websocket_server, websockets_server, WebSocketServerState};
 use storage::cluster::ClusterStorage;
 use storage_adapter::memory::MemoryStorageAdapter;
+use storage_adapter::rocksdb::RocksDBStorageAdapter;
 // use storage_adapter::mysql::MySQLStorageAdapter;
-// use storage_adapter::rocksdb::RocksDBStorageAdapter;
 use storage_adapter::storage::StorageAdapter;
 use storage_adapter::StorageType;
 use subscribe::sub_exclusive::SubscribeExclusive;
@@ -53,8 +58,10 @@ lazy_static! {
     pub static ref BROKER_START_TIME: u64 = now_second();
 }
 
+mod bridge;
 pub mod handler;
 mod observability;
+mod rule_engine;
 pub mod security;
 mod server;
 pub mod storage;
@@ -86,19 +93,19 @@ pub fn start_mqtt_broker_server(stop_send: broadcast::Sender<bool>) {
         //         MqttBroker::new(client_pool, message_storage_adapter, metadata_cache);
         //     server.start(stop_send);
         // }
-        // StorageType::RocksDB => {
-        //     if conf.storage.rocksdb_data_path.is_empty() {
-        //         panic!("storaget type is [rocksdb],[storage.rocksdb_path] cannot be empty");
-        //     }
-        //     let message_storage_adapter = Arc::new(RocksDBStorageAdapter::new(
-        //         conf.storage.rocksdb_data_path.as_str(),
-        //         conf.storage.rocksdb_max_open_files.unwrap_or(10000),
-        //     ));
-        //     let server = MqttBroker::new(client_pool, message_storage_adapter, metadata_cache);
-        //     server.start(stop_send);
-        // }
+        StorageType::RocksDB => {
+            if conf.storage.rocksdb_data_path.is_empty() {
+                panic!("storaget type is [rocksdb],[storage.rocksdb_data_path] cannot be empty");
+            }
+            let message_storage_adapter = Arc::new(RocksDBStorageAdapter::new(
+                conf.storage.rocksdb_data_path.as_str(),
+                conf.storage.rocksdb_max_open_files.unwrap_or(10000),
+            ));
+            let server = MqttBroker::new(client_pool, message_storage_adapter, metadata_cache);
+            server.start(stop_send);
+        }
         _ => {
-            panic!("Message data storage type configuration error, optional :mysql, memory");
+            panic!("Message data storage type configuration error, optional :memory, rocksdb");
         }
     }
 }
@@ -151,6 +158,7 @@ where
         self.register_node();
         self.start_grpc_server();
         self.start_mqtt_server(stop_send.clone());
+        self.start_quic_server(stop_send.clone());
         self.start_http_server();
         self.start_websocket_server(stop_send.clone());
         self.start_keep_alive_thread(stop_send.clone());
@@ -159,6 +167,11 @@ where
         self.start_update_acl_cache_thread(stop_send.clone());
         self.start_push_server();
         self.start_system_topic_thread(stop_send.clone());
+        self.start_delay_publish_thread(stop_send.clone());
+        self.start_update_topic_rewrite_cache_thread(stop_send.clone());
+        self.start_update_cluster_config_cache_thread(stop_send.clone());
+        self.start_update_rule_cache_thread(stop_send.clone());
+        self.start_rule_task_worker_thread(stop_send.clone());
         self.awaiting_stop(stop_send);
     }
 
@@ -184,6 +197,28 @@ where
         });
     }
 
+    fn start_quic_server(&self, stop_send: broadcast::Sender<bool>) {
+        let cache = self.cache_manager.clone();
+        let message_storage_adapter = self.message_storage_adapter.clone();
+        let subscribe_manager = self.subscribe_manager.clone();
+        let client_pool = self.client_pool.clone();
+        let connection_manager = self.connection_manager.clone();
+        let auth_driver = self.auth_driver.clone();
+
+        self.runtime.spawn(async move {
+            start_quic_server(
+                subscribe_manager,
+                cache,
+                connection_manager,
+                message_storage_adapter,
+                client_pool,
+                stop_send,
+                auth_driver,
+            )
+            .await
+        });
+    }
+
     fn start_grpc_server(&self) {
         let conf = broker_mqtt_conf();
         let server = GrpcServer::new(
@@ -334,6 +369,67 @@ where
         });
     }
 
+    fn start_update_topic_rewrite_cache_thread(&self, stop_send: broadcast::Sender<bool>) {
+        let update_topic_rewrite_cache = UpdateTopicRewriteCache::new(
+            stop_send,
+            self.cache_manager.clone(),
+            self.client_pool.clone(),
+        );
+
+        self.runtime.spawn(async move {
+            update_topic_rewrite_cache.start_update().await;
+        });
+    }
+
+    fn start_update_cluster_config_cache_thread(&self, stop_send: broadcast::Sender<bool>) {
+        let update_cluster_config_cache = UpdateClusterConfigCache::new(
+            stop_send,
+            self.cache_manager.clone(),
+            self.client_pool.clone(),
+        );
+
+        self.runtime.spawn(async move {
+            update_cluster_config_cache.start_update().await;
+        });
+    }
+
+    fn start_update_rule_cache_thread(&self, stop_send: broadcast::Sender<bool>) {
+        let update_rule_cache = UpdateRuleCache::new(
+            stop_send,
+            self.cache_manager.clone(),
+            self.client_pool.clone(),
+        );
+
+        self.runtime.spawn(async move {
+            update_rule_cache.start_update().await;
+        });
+    }
+
+    fn start_rule_task_worker_thread(&self, stop_send: broadcast::Sender<bool>) {
+        let rule_task_worker = RuleTaskWorker::new(
+            self.cache_manager.clone(),
+            self.message_storage_adapter.clone(),
+            self.client_pool.clone(),
+            stop_send,
+        );
+
+        self.runtime.spawn(async move {
+            rule_task_worker.start().await;
+        });
+    }
+
+    fn start_delay_publish_thread(&self, stop_send: broadcast::Sender<bool>) {
+        let mut delay_publish = DelayPublishManager::new(
+            self.cache_manager.clone(),
+            self.message_storage_adapter.clone(),
+            self.client_pool.clone(),
+            stop_send,
+        );
+        self.runtime.spawn(async move {
+            delay_publish.start().await;
+        });
+    }
+
     pub fn awaiting_stop(&self, stop_send: broadcast::Sender<bool>) {
         self.runtime.spawn(async move {
             sleep(Duration::from_millis(5)).await;
@@ -0,0 +1,34 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use lazy_static::lazy_static;
+use prometheus::{register_int_gauge_vec, IntGaugeVec};
+
+use crate::handler::constant::METRICS_KEY_TYPE_NAME;
+
+lazy_static! {
+    // Number of connections, packets and bytes rejected by flow control rate limits
+    static ref FLOW_CONTROL_RATE_LIMITED: IntGaugeVec = register_int_gauge_vec!(
+        "flow_control_rate_limited",
+        "Number of requests rejected by flow control rate limits",
+        &[METRICS_KEY_TYPE_NAME]
+    )
+    .unwrap();
+}
+
+pub fn record_rate_limited_metrics(limit_type: &str) {
+    FLOW_CONTROL_RATE_LIMITED
+        .with_label_values(&[limit_type])
+        .inc();
+}
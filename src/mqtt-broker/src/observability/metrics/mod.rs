@@ -14,7 +14,9 @@
 
 pub mod auth;
 pub mod events;
+pub mod flow_control;
 pub mod packets;
 pub mod publish;
+pub mod rule_engine;
 pub mod server;
 pub mod session;
@@ -0,0 +1,50 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use lazy_static::lazy_static;
+use prometheus::{register_int_counter_vec, IntCounterVec};
+
+use crate::handler::constant::METRICS_KEY_RULE_NAME;
+
+lazy_static! {
+    // Number of messages that matched a rule
+    static ref RULE_ENGINE_RULE_HIT: IntCounterVec = register_int_counter_vec!(
+        "rule_engine_rule_hit",
+        "Number of messages that matched the FROM and WHERE clauses of a rule",
+        &[METRICS_KEY_RULE_NAME]
+    )
+    .unwrap();
+
+    // Number of messages a rule failed to evaluate or act on
+    static ref RULE_ENGINE_RULE_FAIL: IntCounterVec = register_int_counter_vec!(
+        "rule_engine_rule_fail",
+        "Number of messages a rule failed to evaluate or whose actions failed",
+        &[METRICS_KEY_RULE_NAME]
+    )
+    .unwrap();
+}
+
+pub fn record_rule_hit_metrics(rule_name: &str) {
+    RULE_ENGINE_RULE_HIT.with_label_values(&[rule_name]).inc();
+}
+
+pub fn record_rule_fail_metrics(rule_name: &str) {
+    RULE_ENGINE_RULE_FAIL.with_label_values(&[rule_name]).inc();
+}
+
+// Drop the series of a deleted rule so they do not linger in the exporter
+pub fn remove_rule_metrics(rule_name: &str) {
+    let _ = RULE_ENGINE_RULE_HIT.remove_label_values(&[rule_name]);
+    let _ = RULE_ENGINE_RULE_FAIL.remove_label_values(&[rule_name]);
+}
@@ -0,0 +1,219 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use grpc_clients::pool::ClientPool;
+use metadata_struct::adapter::record::Record;
+use metadata_struct::mqtt::message::MqttMessage;
+use metadata_struct::mqtt::rule::MqttRuleAction;
+use protocol::mqtt::common::{qos, Publish};
+use serde_json::{Map, Value};
+use storage_adapter::storage::StorageAdapter;
+
+use crate::handler::cache::CacheManager;
+use crate::handler::error::MqttBrokerError;
+use crate::handler::message::build_message_expire;
+use crate::handler::retain::save_retain_message;
+use crate::handler::topic::{topic_name_validator, try_init_topic};
+use crate::storage::message::{cluster_name, MessageStorage};
+
+// The message a rule fired on, used to fill placeholders of republish topics
+#[derive(Clone)]
+pub struct RuleSource {
+    pub client_id: String,
+    pub username: String,
+    pub topic_name: String,
+}
+
+// None when a value put in for a placeholder would add topic levels or wildcards, a client
+// id of `victim/cmd` must not republish into the topics of another client. The source topic
+// keeps its levels, it is the topic the client was allowed to publish to.
+pub fn render_republish_topic(topic: &str, source: &RuleSource) -> Option<String> {
+    let mut result = topic.to_owned();
+    for (placeholder, value, forbidden) in [
+        ("${clientid}", &source.client_id, &['+', '#', '/', '\0'][..]),
+        ("${username}", &source.username, &['+', '#', '/', '\0'][..]),
+        ("${topic}", &source.topic_name, &['+', '#', '\0'][..]),
+    ] {
+        if !result.contains(placeholder) {
+            continue;
+        }
+        if value.contains(forbidden) {
+            return None;
+        }
+        result = result.replace(placeholder, value);
+    }
+    // the topic validator lets wildcards through, a republish topic must not have any
+    if result.contains(['+', '#']) || topic_name_validator(&result).is_err() {
+        return None;
+    }
+    Some(result)
+}
+
+pub async fn execute_rule_action<S>(
+    cache_manager: &Arc<CacheManager>,
+    message_storage_adapter: &Arc<S>,
+    client_pool: &Arc<ClientPool>,
+    rule_name: &str,
+    action: &MqttRuleAction,
+    source: &RuleSource,
+    output: &Map<String, Value>,
+) -> Result<(), MqttBrokerError>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    match action {
+        MqttRuleAction::Republish {
+            topic,
+            qos: action_qos,
+            retain,
+        } => {
+            let action_qos = if let Some(action_qos) = qos(*action_qos) {
+                action_qos
+            } else {
+                return Err(MqttBrokerError::RuleActionInvalid(format!(
+                    "Republish action has an invalid QoS {}",
+                    action_qos
+                )));
+            };
+            let topic_name = if let Some(topic_name) = render_republish_topic(topic, source) {
+                topic_name
+            } else {
+                return Err(MqttBrokerError::TopicNameIncorrectlyFormatted(format!(
+                    "{} rendered for client {}",
+                    topic, source.client_id
+                )));
+            };
+            let publish = Publish {
+                dup: false,
+                qos: action_qos,
+                pkid: 0,
+                retain: *retain,
+                topic: Bytes::from(topic_name.clone()),
+                payload: Bytes::from(serde_json::to_vec(output)?),
+            };
+            republish(
+                cache_manager,
+                message_storage_adapter,
+                client_pool,
+                &topic_name,
+                &source.client_id,
+                &publish,
+            )
+            .await
+        }
+
+        MqttRuleAction::Storage { shard_name } => {
+            let record = Record::build_byte(serde_json::to_vec(output)?);
+            message_storage_adapter
+                .write(cluster_name(), shard_name.clone(), record)
+                .await?;
+            Ok(())
+        }
+
+        MqttRuleAction::Bridge { sink_name } => {
+            let sink = if let Some(sink) = cache_manager.rule_engine.get_bridge_sink(sink_name) {
+                sink
+            } else {
+                return Err(MqttBrokerError::BridgeSinkNotFound(sink_name.clone()));
+            };
+            sink.send(rule_name, output).await
+        }
+    }
+}
+
+// Republished messages are written straight to the topic, they do not pass
+// through the rule engine again so rules cannot trigger each other in a loop
+async fn republish<S>(
+    cache_manager: &Arc<CacheManager>,
+    message_storage_adapter: &Arc<S>,
+    client_pool: &Arc<ClientPool>,
+    topic_name: &str,
+    client_id: &str,
+    publish: &Publish,
+) -> Result<(), MqttBrokerError>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    let topic = try_init_topic(
+        topic_name,
+        cache_manager,
+        message_storage_adapter,
+        client_pool,
+    )
+    .await?;
+
+    save_retain_message(
+        cache_manager,
+        client_pool,
+        topic_name.to_string(),
+        client_id,
+        publish,
+        &None,
+    )
+    .await?;
+
+    let message_expire = build_message_expire(cache_manager, &None);
+    if let Some(record) = MqttMessage::build_record(client_id, publish, &None, message_expire) {
+        let message_storage = MessageStorage::new(message_storage_adapter.clone());
+        message_storage
+            .append_topic_message(&topic.topic_id, vec![record])
+            .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_republish_topic, RuleSource};
+
+    #[test]
+    fn render_republish_topic_test() {
+        let source = RuleSource {
+            client_id: "c1".to_string(),
+            username: "u1".to_string(),
+            topic_name: "sensors/1/data".to_string(),
+        };
+        assert_eq!(
+            render_republish_topic("alarm/${clientid}/${username}", &source),
+            Some("alarm/c1/u1".to_string())
+        );
+        assert_eq!(
+            render_republish_topic("copy/${topic}", &source),
+            Some("copy/sensors/1/data".to_string())
+        );
+
+        for client_id in ["+", "#", "victim/cmd", "a\0"] {
+            let source = RuleSource {
+                client_id: client_id.to_string(),
+                ..source.clone()
+            };
+            assert_eq!(render_republish_topic("alarm/${clientid}", &source), None);
+        }
+        let source = RuleSource {
+            username: "u/+".to_string(),
+            ..source
+        };
+        assert_eq!(render_republish_topic("alarm/${username}", &source), None);
+        // only the values that are put in are checked
+        assert_eq!(
+            render_republish_topic("alarm/${clientid}", &source),
+            Some("alarm/c1".to_string())
+        );
+        // the rendered topic must be a valid topic name
+        assert_eq!(render_republish_topic("alarm/#", &source), None);
+    }
+}
@@ -0,0 +1,554 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+
+use action::{execute_rule_action, RuleSource};
+use common_base::tools::now_mills;
+use dashmap::DashMap;
+use grpc_clients::pool::ClientPool;
+use log::{error, info, warn};
+use metadata_struct::mqtt::connection::MQTTConnection;
+use metadata_struct::mqtt::rule::{MqttRule, MqttRuleAction};
+use protocol::mqtt::common::{qos, Publish};
+use serde_json::{Map, Value};
+use sql::RuleSql;
+use storage_adapter::storage::StorageAdapter;
+use tokio::select;
+use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio::time::sleep;
+
+use crate::bridge::BridgeSink;
+use crate::handler::cache::CacheManager;
+use crate::handler::error::MqttBrokerError;
+use crate::observability::metrics::rule_engine::{
+    record_rule_fail_metrics, record_rule_hit_metrics, remove_rule_metrics,
+};
+use crate::storage::rule::RuleStorage;
+use crate::subscribe::topic_trie::TopicTrie;
+
+pub mod action;
+pub mod sql;
+
+// Published messages waiting for their rules to be evaluated, a full queue
+// holds up the publishers until the workers catch up
+const RULE_TASK_QUEUE_SIZE: usize = 1000;
+// Messages whose rules are evaluated at the same time
+const RULE_TASK_CONCURRENCY: usize = 64;
+
+// A rule together with its parsed SQL and the counters of this broker
+pub struct RuleRuntime {
+    pub rule: MqttRule,
+    pub sql: RuleSql,
+    // Messages that matched FROM and passed WHERE
+    pub hit: AtomicU64,
+    // Messages the rule failed to evaluate or whose actions failed
+    pub fail: AtomicU64,
+}
+
+impl RuleRuntime {
+    fn record_hit(&self) {
+        self.hit.fetch_add(1, Ordering::Relaxed);
+        record_rule_hit_metrics(&self.rule.rule_name);
+    }
+
+    fn record_fail(&self) {
+        self.fail.fetch_add(1, Ordering::Relaxed);
+        record_rule_fail_metrics(&self.rule.rule_name);
+    }
+}
+
+// The rules matching a published message, queued for the rule workers
+pub struct RuleTask {
+    rules: Vec<Arc<RuleRuntime>>,
+    columns: Map<String, Value>,
+    source: RuleSource,
+}
+
+pub struct RuleEngine {
+    // (rule_name, RuleRuntime)
+    rules: DashMap<String, Arc<RuleRuntime>>,
+    // FROM filter -> rule_name
+    from_trie: RwLock<TopicTrie<String>>,
+    // (sink_name, BridgeSink)
+    bridge_sinks: DashMap<String, Arc<dyn BridgeSink>>,
+    task_send: mpsc::Sender<RuleTask>,
+    // Taken by the rule workers when they start
+    task_recv: Mutex<Option<mpsc::Receiver<RuleTask>>>,
+}
+
+impl Default for RuleEngine {
+    fn default() -> Self {
+        RuleEngine::new()
+    }
+}
+
+impl RuleEngine {
+    pub fn new() -> Self {
+        let (task_send, task_recv) = mpsc::channel(RULE_TASK_QUEUE_SIZE);
+        RuleEngine {
+            rules: DashMap::new(),
+            from_trie: RwLock::new(TopicTrie::new()),
+            bridge_sinks: DashMap::new(),
+            task_send,
+            task_recv: Mutex::new(Some(task_recv)),
+        }
+    }
+
+    pub fn add_rule(&self, rule: MqttRule) -> Result<(), MqttBrokerError> {
+        // An unchanged rule keeps its counters
+        if let Some(runtime) = self.rules.get(&rule.rule_name) {
+            if runtime.rule == rule {
+                return Ok(());
+            }
+        }
+        let sql = RuleSql::parse(&rule.sql)?;
+        validate_rule_actions(&rule.actions)?;
+
+        let rule_name = rule.rule_name.clone();
+        let from = sql.from.clone();
+        let previous = self.rules.insert(
+            rule_name.clone(),
+            Arc::new(RuleRuntime {
+                rule,
+                sql,
+                hit: AtomicU64::new(0),
+                fail: AtomicU64::new(0),
+            }),
+        );
+
+        let mut from_trie = self.from_trie.write().unwrap();
+        if let Some(previous) = previous {
+            for filter in previous.sql.from.iter() {
+                from_trie.remove(filter, &rule_name);
+            }
+        }
+        for filter in from.iter() {
+            from_trie.insert(filter, rule_name.clone());
+        }
+        Ok(())
+    }
+
+    pub fn remove_rule(&self, rule_name: &str) {
+        if let Some((_, runtime)) = self.rules.remove(rule_name) {
+            let mut from_trie = self.from_trie.write().unwrap();
+            for filter in runtime.sql.from.iter() {
+                from_trie.remove(filter, &rule_name.to_string());
+            }
+        }
+        remove_rule_metrics(rule_name);
+    }
+
+    pub fn get_rules(&self) -> Vec<Arc<RuleRuntime>> {
+        let mut rules: Vec<Arc<RuleRuntime>> =
+            self.rules.iter().map(|raw| raw.value().clone()).collect();
+        rules.sort_by_key(|runtime| runtime.rule.create_time);
+        rules
+    }
+
+    // Enabled rules whose FROM clause matches the topic
+    pub fn match_rules(&self, topic_name: &str) -> Vec<Arc<RuleRuntime>> {
+        let rule_names = self.from_trie.read().unwrap().match_topic(topic_name);
+        let mut rules: Vec<Arc<RuleRuntime>> = rule_names
+            .iter()
+            .filter_map(|rule_name| self.rules.get(rule_name))
+            .filter(|raw| raw.rule.enable)
+            .map(|raw| raw.value().clone())
+            .collect();
+        rules.sort_by_key(|runtime| runtime.rule.create_time);
+        rules
+    }
+
+    pub fn register_bridge_sink(&self, sink_name: &str, sink: Arc<dyn BridgeSink>) {
+        self.bridge_sinks.insert(sink_name.to_string(), sink);
+    }
+
+    pub fn get_bridge_sink(&self, sink_name: &str) -> Option<Arc<dyn BridgeSink>> {
+        self.bridge_sinks
+            .get(sink_name)
+            .map(|sink| sink.value().clone())
+    }
+
+    fn take_task_recv(&self) -> Option<mpsc::Receiver<RuleTask>> {
+        self.task_recv.lock().unwrap().take()
+    }
+}
+
+// Actions are checked before a rule is accepted so they do not fail on every message
+pub fn validate_rule_actions(actions: &[MqttRuleAction]) -> Result<(), MqttBrokerError> {
+    for action in actions.iter() {
+        match action {
+            MqttRuleAction::Republish {
+                topic, qos: num, ..
+            } => {
+                if topic.is_empty() || qos(*num).is_none() {
+                    return Err(MqttBrokerError::RuleActionInvalid(format!(
+                        "Republish action needs a topic and a QoS of 0, 1 or 2, got {:?}",
+                        action
+                    )));
+                }
+            }
+            MqttRuleAction::Storage { shard_name } => {
+                if shard_name.is_empty() {
+                    return Err(MqttBrokerError::RuleActionInvalid(
+                        "Storage action needs a shard name".to_string(),
+                    ));
+                }
+            }
+            MqttRuleAction::Bridge { sink_name } => {
+                if sink_name.is_empty() {
+                    return Err(MqttBrokerError::RuleActionInvalid(
+                        "Bridge action needs a sink name".to_string(),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// The columns a rule can select from and filter on
+pub fn build_rule_columns(
+    client_id: &str,
+    username: &str,
+    topic_name: &str,
+    publish: &Publish,
+) -> Map<String, Value> {
+    // JSON payloads can be addressed field by field, anything else is kept as text
+    let payload = match serde_json::from_slice::<Value>(&publish.payload) {
+        Ok(value) => value,
+        Err(_) => Value::String(String::from_utf8_lossy(&publish.payload).to_string()),
+    };
+
+    let mut columns = Map::new();
+    columns.insert("clientid".to_string(), Value::from(client_id));
+    columns.insert("username".to_string(), Value::from(username));
+    columns.insert("topic".to_string(), Value::from(topic_name));
+    columns.insert("qos".to_string(), Value::from(publish.qos as u8));
+    columns.insert("retain".to_string(), Value::from(publish.retain));
+    columns.insert("payload".to_string(), payload);
+    columns.insert("timestamp".to_string(), Value::from(now_mills() as u64));
+    columns
+}
+
+// Queue the rules matching a published message for the rule workers,
+// waits for room in the queue when the workers fall behind
+pub async fn apply_rules(
+    cache_manager: &Arc<CacheManager>,
+    connection: &MQTTConnection,
+    topic_name: &str,
+    publish: &Publish,
+) {
+    let rules = cache_manager.rule_engine.match_rules(topic_name);
+    if rules.is_empty() {
+        return;
+    }
+
+    let task = RuleTask {
+        rules,
+        columns: build_rule_columns(
+            &connection.client_id,
+            &connection.login_user,
+            topic_name,
+            publish,
+        ),
+        source: RuleSource {
+            client_id: connection.client_id.clone(),
+            username: connection.login_user.clone(),
+            topic_name: topic_name.to_string(),
+        },
+    };
+    if let Err(e) = cache_manager.rule_engine.task_send.send(task).await {
+        error!(
+            "Queueing the rules of topic {} failed, error message: {}",
+            topic_name, e
+        );
+    }
+}
+
+pub struct RuleTaskWorker<S> {
+    cache_manager: Arc<CacheManager>,
+    message_storage_adapter: Arc<S>,
+    client_pool: Arc<ClientPool>,
+    stop_send: broadcast::Sender<bool>,
+}
+
+impl<S> RuleTaskWorker<S>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    pub fn new(
+        cache_manager: Arc<CacheManager>,
+        message_storage_adapter: Arc<S>,
+        client_pool: Arc<ClientPool>,
+        stop_send: broadcast::Sender<bool>,
+    ) -> Self {
+        RuleTaskWorker {
+            cache_manager,
+            message_storage_adapter,
+            client_pool,
+            stop_send,
+        }
+    }
+
+    pub async fn start(&self) {
+        let mut task_recv = if let Some(task_recv) = self.cache_manager.rule_engine.take_task_recv()
+        {
+            task_recv
+        } else {
+            error!("{}", "Rule task workers are already running.");
+            return;
+        };
+        let semaphore = Arc::new(Semaphore::new(RULE_TASK_CONCURRENCY));
+        let mut stop_rx = self.stop_send.subscribe();
+        loop {
+            select! {
+                val = stop_rx.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            info!("{}","Rule task worker thread stopped successfully.");
+                            break;
+                        }
+                    }
+                }
+                val = task_recv.recv() =>{
+                    let task = if let Some(task) = val {
+                        task
+                    } else {
+                        break;
+                    };
+                    let permit = match semaphore.clone().acquire_owned().await {
+                        Ok(permit) => permit,
+                        Err(_) => break,
+                    };
+                    let cache_manager = self.cache_manager.clone();
+                    let message_storage_adapter = self.message_storage_adapter.clone();
+                    let client_pool = self.client_pool.clone();
+                    tokio::spawn(async move {
+                        execute_rule_task(&cache_manager, &message_storage_adapter, &client_pool, task)
+                            .await;
+                        drop(permit);
+                    });
+                }
+            }
+        }
+    }
+}
+
+async fn execute_rule_task<S>(
+    cache_manager: &Arc<CacheManager>,
+    message_storage_adapter: &Arc<S>,
+    client_pool: &Arc<ClientPool>,
+    task: RuleTask,
+) where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    let RuleTask {
+        rules,
+        columns,
+        source,
+    } = task;
+    for runtime in rules {
+        let output = match runtime.sql.apply(&columns) {
+            Ok(Some(output)) => output,
+            Ok(None) => continue,
+            Err(e) => {
+                runtime.record_fail();
+                warn!(
+                    "Rule {} failed to evaluate the message of topic {}, error message: {}",
+                    runtime.rule.rule_name, source.topic_name, e
+                );
+                continue;
+            }
+        };
+        runtime.record_hit();
+
+        let mut failed = false;
+        for action in runtime.rule.actions.iter() {
+            if let Err(e) = execute_rule_action(
+                cache_manager,
+                message_storage_adapter,
+                client_pool,
+                &runtime.rule.rule_name,
+                action,
+                &source,
+                &output,
+            )
+            .await
+            {
+                failed = true;
+                warn!(
+                    "Rule {} failed to execute action {:?}, error message: {}",
+                    runtime.rule.rule_name, action, e
+                );
+            }
+        }
+        if failed {
+            runtime.record_fail();
+        }
+    }
+}
+
+pub struct UpdateRuleCache {
+    stop_send: broadcast::Sender<bool>,
+    cache_manager: Arc<CacheManager>,
+    client_pool: Arc<ClientPool>,
+}
+
+impl UpdateRuleCache {
+    pub fn new(
+        stop_send: broadcast::Sender<bool>,
+        cache_manager: Arc<CacheManager>,
+        client_pool: Arc<ClientPool>,
+    ) -> Self {
+        UpdateRuleCache {
+            stop_send,
+            cache_manager,
+            client_pool,
+        }
+    }
+
+    pub async fn start_update(&self) {
+        loop {
+            let mut stop_rx = self.stop_send.subscribe();
+            select! {
+                val = stop_rx.recv() =>{
+                    if let Ok(flag) = val {
+                        if flag {
+                            info!("{}","Rule cache updating thread stopped successfully.");
+                            break;
+                        }
+                    }
+                }
+                _ = self.update_rule_cache()=>{
+                }
+            }
+        }
+    }
+
+    async fn update_rule_cache(&self) {
+        let storage = RuleStorage::new(self.client_pool.clone());
+        match storage.list_rule().await {
+            Ok(rules) => {
+                let rule_engine = &self.cache_manager.rule_engine;
+                let mut names = HashSet::new();
+                for rule in rules {
+                    names.insert(rule.rule_name.clone());
+                    let rule_name = rule.rule_name.clone();
+                    if let Err(e) = rule_engine.add_rule(rule) {
+                        error!("Loading rule {} failed, error message: {}", rule_name, e);
+                    }
+                }
+                for runtime in rule_engine.get_rules() {
+                    if !names.contains(&runtime.rule.rule_name) {
+                        rule_engine.remove_rule(&runtime.rule.rule_name);
+                    }
+                }
+            }
+            Err(e) => {
+                error!("Updating rule cache failed, error message: {}", e);
+            }
+        }
+        sleep(Duration::from_secs(5)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use metadata_struct::mqtt::rule::{MqttRule, MqttRuleAction};
+    use protocol::mqtt::common::{Publish, QoS};
+    use serde_json::{json, Value};
+
+    use super::{build_rule_columns, RuleEngine};
+
+    fn rule(rule_name: &str, sql: &str, enable: bool) -> MqttRule {
+        MqttRule {
+            rule_name: rule_name.to_string(),
+            sql: sql.to_string(),
+            actions: Vec::new(),
+            enable,
+            description: "".to_string(),
+            create_time: 0,
+        }
+    }
+
+    #[test]
+    fn rule_engine_match_test() {
+        let engine = RuleEngine::new();
+        engine
+            .add_rule(rule("r1", "SELECT * FROM \"sensors/+/data\"", true))
+            .unwrap();
+        engine
+            .add_rule(rule("r2", "SELECT * FROM \"sensors/#\"", false))
+            .unwrap();
+        assert!(engine.add_rule(rule("r3", "SELECT *", true)).is_err());
+
+        let rules = engine.match_rules("sensors/1/data");
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].rule.rule_name, "r1");
+        assert!(engine.match_rules("other/1/data").is_empty());
+
+        // A changed rule is indexed by its new FROM filters only
+        engine
+            .add_rule(rule("r1", "SELECT * FROM \"other/+/data\"", true))
+            .unwrap();
+        assert!(engine.match_rules("sensors/1/data").is_empty());
+        assert_eq!(engine.match_rules("other/1/data").len(), 1);
+
+        engine.remove_rule("r1");
+        assert!(engine.match_rules("other/1/data").is_empty());
+        assert_eq!(engine.get_rules().len(), 1);
+    }
+
+    #[test]
+    fn rule_engine_invalid_action_test() {
+        let engine = RuleEngine::new();
+        let mut invalid = rule("r1", "SELECT * FROM \"sensors/#\"", true);
+        invalid.actions = vec![MqttRuleAction::Republish {
+            topic: "alarm".to_string(),
+            qos: 3,
+            retain: false,
+        }];
+        assert!(engine.add_rule(invalid).is_err());
+        assert!(engine.get_rules().is_empty());
+    }
+
+    #[test]
+    fn build_rule_columns_test() {
+        let publish = Publish {
+            dup: false,
+            qos: QoS::AtLeastOnce,
+            pkid: 1,
+            retain: false,
+            topic: Bytes::from("sensors/1/data"),
+            payload: Bytes::from(r#"{"temp": 81}"#),
+        };
+        let columns = build_rule_columns("c1", "u1", "sensors/1/data", &publish);
+        assert_eq!(columns["clientid"], json!("c1"));
+        assert_eq!(columns["username"], json!("u1"));
+        assert_eq!(columns["qos"], json!(1));
+        assert_eq!(columns["payload"], json!({"temp": 81}));
+
+        let publish = Publish {
+            payload: Bytes::from("plain text"),
+            ..publish
+        };
+        let columns = build_rule_columns("c1", "u1", "sensors/1/data", &publish);
+        assert_eq!(columns["payload"], Value::from("plain text"));
+    }
+}
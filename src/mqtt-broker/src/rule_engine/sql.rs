@@ -0,0 +1,750 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::cmp::Ordering;
+
+use serde_json::{Map, Number, Value};
+
+use crate::handler::error::MqttBrokerError;
+use crate::subscribe::topic_trie::topic_filter_match;
+
+const KEYWORDS: [&str; 9] = [
+    "SELECT", "FROM", "WHERE", "AS", "AND", "OR", "NOT", "TRUE", "FALSE",
+];
+
+// A parsed rule statement: SELECT <fields> FROM "<topic filter>", ... [WHERE <condition>]
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleSql {
+    pub fields: Vec<SelectField>,
+    pub from: Vec<String>,
+    pub condition: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectField {
+    // SELECT * keeps every column of the message
+    All,
+    Column { expr: Expr, alias: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    // A column such as clientid, or a path into a column such as payload.temp
+    Column(Vec<String>),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryOp {
+    Or,
+    And,
+    Eq,
+    NotEq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(Number),
+    // 'single quoted'
+    Str(String),
+    // "double quoted"
+    Quoted(String),
+    Symbol(&'static str),
+}
+
+impl RuleSql {
+    pub fn parse(sql: &str) -> Result<Self, MqttBrokerError> {
+        let tokens = tokenize(sql)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        parser.parse_statement()
+    }
+
+    pub fn match_topic(&self, topic_name: &str) -> bool {
+        self.from
+            .iter()
+            .any(|filter| topic_filter_match(topic_name, filter))
+    }
+
+    // Project the columns of a message through the SELECT fields,
+    // returns None when the WHERE condition filters the message out
+    pub fn apply(
+        &self,
+        columns: &Map<String, Value>,
+    ) -> Result<Option<Map<String, Value>>, MqttBrokerError> {
+        if let Some(condition) = &self.condition {
+            if !is_true(&condition.eval(columns)?) {
+                return Ok(None);
+            }
+        }
+
+        let mut output = Map::new();
+        for field in self.fields.iter() {
+            match field {
+                SelectField::All => {
+                    for (key, value) in columns {
+                        output.insert(key.clone(), value.clone());
+                    }
+                }
+                SelectField::Column { expr, alias } => {
+                    output.insert(alias.clone(), expr.eval(columns)?);
+                }
+            }
+        }
+        Ok(Some(output))
+    }
+}
+
+impl Expr {
+    pub fn eval(&self, columns: &Map<String, Value>) -> Result<Value, MqttBrokerError> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Column(path) => Ok(column_value(columns, path)),
+            Expr::Not(expr) => Ok(Value::Bool(!is_true(&expr.eval(columns)?))),
+            Expr::Neg(expr) => {
+                let value = expr.eval(columns)?;
+                arithmetic(BinaryOp::Sub, &Value::from(0), &value)
+            }
+            Expr::Binary(left, op, right) => {
+                let left = left.eval(columns)?;
+                match op {
+                    // Short circuit, the right side may refer to columns the message lacks
+                    BinaryOp::And => {
+                        if !is_true(&left) {
+                            return Ok(Value::Bool(false));
+                        }
+                        Ok(Value::Bool(is_true(&right.eval(columns)?)))
+                    }
+                    BinaryOp::Or => {
+                        if is_true(&left) {
+                            return Ok(Value::Bool(true));
+                        }
+                        Ok(Value::Bool(is_true(&right.eval(columns)?)))
+                    }
+                    BinaryOp::Eq => Ok(Value::Bool(is_equal(&left, &right.eval(columns)?))),
+                    BinaryOp::NotEq => Ok(Value::Bool(!is_equal(&left, &right.eval(columns)?))),
+                    BinaryOp::Gt | BinaryOp::Gte | BinaryOp::Lt | BinaryOp::Lte => {
+                        let ordering = compare(&left, &right.eval(columns)?);
+                        Ok(Value::Bool(match ordering {
+                            Some(ordering) => match op {
+                                BinaryOp::Gt => ordering == Ordering::Greater,
+                                BinaryOp::Gte => ordering != Ordering::Less,
+                                BinaryOp::Lt => ordering == Ordering::Less,
+                                _ => ordering != Ordering::Greater,
+                            },
+                            // Values of different types never satisfy an ordering
+                            None => false,
+                        }))
+                    }
+                    BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                        arithmetic(*op, &left, &right.eval(columns)?)
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn column_value(columns: &Map<String, Value>, path: &[String]) -> Value {
+    let mut value = if let Some(value) = columns.get(&path[0]) {
+        value
+    } else {
+        return Value::Null;
+    };
+    for key in path.iter().skip(1) {
+        let next = match value {
+            Value::Object(object) => object.get(key),
+            Value::Array(array) => key.parse::<usize>().ok().and_then(|i| array.get(i)),
+            _ => None,
+        };
+        value = if let Some(next) = next {
+            next
+        } else {
+            return Value::Null;
+        };
+    }
+    value.clone()
+}
+
+fn is_true(value: &Value) -> bool {
+    matches!(value, Value::Bool(true))
+}
+
+// Numbers and numeric strings compare as numbers, so payloads that carry
+// numbers as strings still work with WHERE payload.temp > 80
+fn as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(number) => number.as_f64(),
+        Value::String(text) => text.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+fn compare(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::String(left), Value::String(right)) => Some(left.cmp(right)),
+        (Value::Bool(left), Value::Bool(right)) => Some(left.cmp(right)),
+        (Value::Number(_), _) | (_, Value::Number(_)) => as_f64(left)?.partial_cmp(&as_f64(right)?),
+        _ => None,
+    }
+}
+
+fn is_equal(left: &Value, right: &Value) -> bool {
+    match compare(left, right) {
+        Some(ordering) => ordering == Ordering::Equal,
+        None => left == right,
+    }
+}
+
+fn arithmetic(op: BinaryOp, left: &Value, right: &Value) -> Result<Value, MqttBrokerError> {
+    // + joins strings when either side is not a number
+    if op == BinaryOp::Add
+        && (left.is_string() || right.is_string())
+        && (as_f64(left).is_none() || as_f64(right).is_none())
+    {
+        return Ok(Value::String(format!(
+            "{}{}",
+            value_to_string(left),
+            value_to_string(right)
+        )));
+    }
+
+    let (left_num, right_num) = match (as_f64(left), as_f64(right)) {
+        (Some(left), Some(right)) => (left, right),
+        _ => {
+            return Err(MqttBrokerError::RuleEvaluateFailed(format!(
+                "cannot apply {:?} to {} and {}",
+                op, left, right
+            )));
+        }
+    };
+
+    if let (Value::Number(left), Value::Number(right)) = (left, right) {
+        if let (Some(left), Some(right)) = (left.as_i64(), right.as_i64()) {
+            let result = match op {
+                BinaryOp::Add => left.checked_add(right),
+                BinaryOp::Sub => left.checked_sub(right),
+                BinaryOp::Mul => left.checked_mul(right),
+                _ => None,
+            };
+            if let Some(result) = result {
+                return Ok(Value::from(result));
+            }
+        }
+    }
+
+    let result = match op {
+        BinaryOp::Add => left_num + right_num,
+        BinaryOp::Sub => left_num - right_num,
+        BinaryOp::Mul => left_num * right_num,
+        _ => {
+            if right_num == 0.0 {
+                return Err(MqttBrokerError::RuleEvaluateFailed(
+                    "division by zero".to_string(),
+                ));
+            }
+            left_num / right_num
+        }
+    };
+    match Number::from_f64(result) {
+        Some(number) => Ok(Value::Number(number)),
+        None => Err(MqttBrokerError::RuleEvaluateFailed(format!(
+            "{:?} of {} and {} is not a finite number",
+            op, left, right
+        ))),
+    }
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Null => "".to_string(),
+        _ => value.to_string(),
+    }
+}
+
+fn sql_error(message: String) -> MqttBrokerError {
+    MqttBrokerError::RuleSqlInvalid(message)
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>, MqttBrokerError> {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = if let Ok(number) = text.parse::<i64>() {
+                Number::from(number)
+            } else if let Some(number) = text.parse::<f64>().ok().and_then(Number::from_f64) {
+                number
+            } else {
+                return Err(sql_error(format!("invalid number {}", text)));
+            };
+            tokens.push(Token::Number(number));
+            continue;
+        }
+
+        if c == '\'' || c == '"' {
+            let start = i + 1;
+            let end = if let Some(offset) = chars[start..].iter().position(|ch| *ch == c) {
+                start + offset
+            } else {
+                return Err(sql_error(format!("unterminated string at position {}", i)));
+            };
+            let text: String = chars[start..end].iter().collect();
+            tokens.push(if c == '\'' {
+                Token::Str(text)
+            } else {
+                Token::Quoted(text)
+            });
+            i = end + 1;
+            continue;
+        }
+
+        let next = chars.get(i + 1).copied();
+        let symbol = match (c, next) {
+            ('!', Some('=')) | ('<', Some('>')) => Some("!="),
+            ('>', Some('=')) => Some(">="),
+            ('<', Some('=')) => Some("<="),
+            _ => None,
+        };
+        if let Some(symbol) = symbol {
+            tokens.push(Token::Symbol(symbol));
+            i += 2;
+            continue;
+        }
+
+        let symbol = match c {
+            '*' => "*",
+            ',' => ",",
+            '(' => "(",
+            ')' => ")",
+            '=' => "=",
+            '>' => ">",
+            '<' => "<",
+            '+' => "+",
+            '-' => "-",
+            '/' => "/",
+            _ => {
+                return Err(sql_error(format!(
+                    "unexpected character '{}' at position {}",
+                    c, i
+                )));
+            }
+        };
+        tokens.push(Token::Symbol(symbol));
+        i += 1;
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case(keyword))
+    }
+
+    fn is_symbol(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(sym)) if *sym == symbol)
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), MqttBrokerError> {
+        if !self.is_keyword(keyword) {
+            return Err(sql_error(format!(
+                "expected {} but found {}",
+                keyword,
+                self.describe_next()
+            )));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn describe_next(&self) -> String {
+        match self.peek() {
+            Some(Token::Ident(ident)) => ident.clone(),
+            Some(Token::Number(number)) => number.to_string(),
+            Some(Token::Str(text)) => format!("'{}'", text),
+            Some(Token::Quoted(text)) => format!("\"{}\"", text),
+            Some(Token::Symbol(symbol)) => symbol.to_string(),
+            None => "end of statement".to_string(),
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<RuleSql, MqttBrokerError> {
+        self.expect_keyword("SELECT")?;
+        let mut fields = vec![self.parse_field()?];
+        while self.is_symbol(",") {
+            self.pos += 1;
+            fields.push(self.parse_field()?);
+        }
+
+        self.expect_keyword("FROM")?;
+        let mut from = vec![self.parse_topic_filter()?];
+        while self.is_symbol(",") {
+            self.pos += 1;
+            from.push(self.parse_topic_filter()?);
+        }
+
+        let condition = if self.is_keyword("WHERE") {
+            self.pos += 1;
+            Some(self.parse_or()?)
+        } else {
+            None
+        };
+
+        if self.peek().is_some() {
+            return Err(sql_error(format!(
+                "unexpected {} after the statement",
+                self.describe_next()
+            )));
+        }
+        Ok(RuleSql {
+            fields,
+            from,
+            condition,
+        })
+    }
+
+    fn parse_field(&mut self) -> Result<SelectField, MqttBrokerError> {
+        if self.is_symbol("*") {
+            self.pos += 1;
+            return Ok(SelectField::All);
+        }
+
+        let expr = self.parse_or()?;
+        let alias = if self.is_keyword("AS") {
+            self.pos += 1;
+            match self.next() {
+                Some(Token::Ident(ident)) if !is_keyword(&ident) => ident,
+                Some(Token::Quoted(ident)) => ident,
+                _ => return Err(sql_error("expected an alias after AS".to_string())),
+            }
+        } else if let Expr::Column(path) = &expr {
+            path.join(".")
+        } else {
+            return Err(sql_error(
+                "a selected expression that is not a column needs an alias".to_string(),
+            ));
+        };
+        Ok(SelectField::Column { expr, alias })
+    }
+
+    fn parse_topic_filter(&mut self) -> Result<String, MqttBrokerError> {
+        match self.next() {
+            Some(Token::Quoted(filter)) | Some(Token::Str(filter)) if !filter.is_empty() => {
+                Ok(filter)
+            }
+            _ => Err(sql_error(
+                "FROM expects a quoted topic filter such as \"sensors/+/data\"".to_string(),
+            )),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, MqttBrokerError> {
+        let mut expr = self.parse_and()?;
+        while self.is_keyword("OR") {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            expr = Expr::Binary(Box::new(expr), BinaryOp::Or, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, MqttBrokerError> {
+        let mut expr = self.parse_not()?;
+        while self.is_keyword("AND") {
+            self.pos += 1;
+            let right = self.parse_not()?;
+            expr = Expr::Binary(Box::new(expr), BinaryOp::And, Box::new(right));
+        }
+        Ok(expr)
+    }
+
+    fn parse_not(&mut self) -> Result<Expr, MqttBrokerError> {
+        if self.is_keyword("NOT") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, MqttBrokerError> {
+        let expr = self.parse_additive()?;
+        let op = match self.peek() {
+            Some(Token::Symbol("=")) => BinaryOp::Eq,
+            Some(Token::Symbol("!=")) => BinaryOp::NotEq,
+            Some(Token::Symbol(">")) => BinaryOp::Gt,
+            Some(Token::Symbol(">=")) => BinaryOp::Gte,
+            Some(Token::Symbol("<")) => BinaryOp::Lt,
+            Some(Token::Symbol("<=")) => BinaryOp::Lte,
+            _ => return Ok(expr),
+        };
+        self.pos += 1;
+        let right = self.parse_additive()?;
+        Ok(Expr::Binary(Box::new(expr), op, Box::new(right)))
+    }
+
+    fn parse_additive(&mut self) -> Result<Expr, MqttBrokerError> {
+        let mut expr = self.parse_multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("+")) => BinaryOp::Add,
+                Some(Token::Symbol("-")) => BinaryOp::Sub,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            let right = self.parse_multiplicative()?;
+            expr = Expr::Binary(Box::new(expr), op, Box::new(right));
+        }
+    }
+
+    fn parse_multiplicative(&mut self) -> Result<Expr, MqttBrokerError> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Symbol("*")) => BinaryOp::Mul,
+                Some(Token::Symbol("/")) => BinaryOp::Div,
+                _ => return Ok(expr),
+            };
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            expr = Expr::Binary(Box::new(expr), op, Box::new(right));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, MqttBrokerError> {
+        if self.is_symbol("-") {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, MqttBrokerError> {
+        let description = self.describe_next();
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Literal(Value::Number(number))),
+            Some(Token::Str(text)) | Some(Token::Quoted(text)) => {
+                Ok(Expr::Literal(Value::String(text)))
+            }
+            Some(Token::Symbol("(")) => {
+                let expr = self.parse_or()?;
+                if !self.is_symbol(")") {
+                    return Err(sql_error(format!(
+                        "expected ) but found {}",
+                        self.describe_next()
+                    )));
+                }
+                self.pos += 1;
+                Ok(expr)
+            }
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case("TRUE") => {
+                Ok(Expr::Literal(Value::Bool(true)))
+            }
+            Some(Token::Ident(ident)) if ident.eq_ignore_ascii_case("FALSE") => {
+                Ok(Expr::Literal(Value::Bool(false)))
+            }
+            Some(Token::Ident(ident)) if !is_keyword(&ident) => {
+                let path: Vec<String> = ident.split('.').map(|key| key.to_string()).collect();
+                if path.iter().any(|key| key.is_empty()) {
+                    return Err(sql_error(format!("invalid column {}", ident)));
+                }
+                Ok(Expr::Column(path))
+            }
+            _ => Err(sql_error(format!("unexpected {}", description))),
+        }
+    }
+}
+
+fn is_keyword(ident: &str) -> bool {
+    KEYWORDS
+        .iter()
+        .any(|keyword| ident.eq_ignore_ascii_case(keyword))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Map, Value};
+
+    use super::{BinaryOp, Expr, RuleSql, SelectField};
+
+    fn columns(value: Value) -> Map<String, Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn parse_rule_sql_test() {
+        let sql = RuleSql::parse(
+            "SELECT payload.temp as t, clientid FROM \"sensors/+/data\" WHERE payload.temp > 80",
+        )
+        .unwrap();
+        assert_eq!(
+            sql.fields,
+            vec![
+                SelectField::Column {
+                    expr: Expr::Column(vec!["payload".to_string(), "temp".to_string()]),
+                    alias: "t".to_string(),
+                },
+                SelectField::Column {
+                    expr: Expr::Column(vec!["clientid".to_string()]),
+                    alias: "clientid".to_string(),
+                },
+            ]
+        );
+        assert_eq!(sql.from, vec!["sensors/+/data".to_string()]);
+        assert_eq!(
+            sql.condition,
+            Some(Expr::Binary(
+                Box::new(Expr::Column(vec![
+                    "payload".to_string(),
+                    "temp".to_string()
+                ])),
+                BinaryOp::Gt,
+                Box::new(Expr::Literal(json!(80))),
+            ))
+        );
+
+        let sql = RuleSql::parse("select * from \"a/#\", \"b/+\"").unwrap();
+        assert_eq!(sql.fields, vec![SelectField::All]);
+        assert_eq!(sql.from.len(), 2);
+        assert!(sql.condition.is_none());
+        assert!(sql.match_topic("a/b/c"));
+        assert!(sql.match_topic("b/c"));
+        assert!(!sql.match_topic("c/d"));
+
+        assert!(RuleSql::parse("SELECT clientid").is_err());
+        assert!(RuleSql::parse("SELECT FROM \"a\"").is_err());
+        assert!(RuleSql::parse("SELECT clientid FROM a").is_err());
+        assert!(RuleSql::parse("SELECT 1 + 1 FROM \"a\"").is_err());
+        assert!(RuleSql::parse("SELECT clientid FROM \"a\" WHERE").is_err());
+        assert!(RuleSql::parse("SELECT clientid FROM \"a\" WHERE (qos = 1").is_err());
+        assert!(RuleSql::parse("SELECT clientid FROM \"a\" LIMIT 1").is_err());
+        assert!(RuleSql::parse("SELECT clientid FROM \"a\" WHERE topic = 'x").is_err());
+    }
+
+    #[test]
+    fn apply_rule_sql_test() {
+        let sql = RuleSql::parse(
+            "SELECT payload.temp as t, clientid FROM \"sensors/+/data\" WHERE payload.temp > 80",
+        )
+        .unwrap();
+        let output = sql
+            .apply(&columns(json!({
+                "clientid": "c1",
+                "topic": "sensors/1/data",
+                "payload": {"temp": 85.5}
+            })))
+            .unwrap()
+            .unwrap();
+        assert_eq!(Value::Object(output), json!({"t": 85.5, "clientid": "c1"}));
+
+        // filtered out by WHERE
+        let output = sql
+            .apply(&columns(json!({"clientid": "c1", "payload": {"temp": 20}})))
+            .unwrap();
+        assert!(output.is_none());
+
+        // a missing column never satisfies a comparison
+        let output = sql
+            .apply(&columns(json!({"clientid": "c1", "payload": "text"})))
+            .unwrap();
+        assert!(output.is_none());
+
+        // numeric strings compare as numbers
+        let output = sql
+            .apply(&columns(
+                json!({"clientid": "c1", "payload": {"temp": "90"}}),
+            ))
+            .unwrap();
+        assert!(output.is_some());
+    }
+
+    #[test]
+    fn eval_expression_test() {
+        let row = columns(json!({
+            "clientid": "c1",
+            "qos": 1,
+            "payload": {"a": 3, "b": 4, "list": [10, 20], "name": "x"}
+        }));
+        let eval = |sql: &str| {
+            let sql = RuleSql::parse(&format!("SELECT {} AS v FROM \"#\"", sql)).unwrap();
+            sql.apply(&row).unwrap().unwrap().remove("v").unwrap()
+        };
+
+        assert_eq!(eval("payload.a + payload.b * 2"), json!(11));
+        assert_eq!(eval("(payload.a + payload.b) * 2"), json!(14));
+        assert_eq!(eval("payload.b / 2"), json!(2.0));
+        assert_eq!(eval("-payload.a"), json!(-3));
+        assert_eq!(eval("payload.list.1"), json!(20));
+        assert_eq!(eval("payload.missing"), Value::Null);
+        assert_eq!(eval("'id-' + clientid"), json!("id-c1"));
+        assert_eq!(eval("qos = 1 AND payload.name = 'x'"), json!(true));
+        assert_eq!(eval("qos != 1 OR payload.name <> 'x'"), json!(false));
+        assert_eq!(eval("NOT qos >= 2"), json!(true));
+        assert_eq!(eval("payload.a <= 3 and true"), json!(true));
+
+        let sql = RuleSql::parse("SELECT payload.a / 0 AS v FROM \"#\"").unwrap();
+        assert!(sql.apply(&row).is_err());
+        let sql = RuleSql::parse("SELECT payload.a * payload.name AS v FROM \"#\"").unwrap();
+        assert!(sql.apply(&row).is_err());
+    }
+}
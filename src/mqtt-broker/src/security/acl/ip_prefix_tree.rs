@@ -0,0 +1,197 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+
+use ipnet::IpNet;
+
+const IPV4_BITS: u8 = 32;
+const IPV6_BITS: u8 = 128;
+
+#[derive(Default, Clone)]
+struct Node {
+    children: [Option<usize>; 2],
+    // (entry, end_time) of the blacklist entries ending at this node, an Ip entry
+    // and an IPCIDR entry of the same address share the node
+    entries: HashMap<String, u64>,
+}
+
+/// Binary prefix tree of the Ip and IPCIDR blacklist entries. A lookup walks at
+/// most 32 (IPv4) or 128 (IPv6) nodes no matter how many ranges are blacklisted.
+#[derive(Clone)]
+pub struct IpPrefixTree {
+    nodes: Vec<Node>,
+    ipv4_root: usize,
+    ipv6_root: usize,
+}
+
+impl Default for IpPrefixTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IpPrefixTree {
+    pub fn new() -> Self {
+        IpPrefixTree {
+            nodes: vec![Node::default(), Node::default()],
+            ipv4_root: 0,
+            ipv6_root: 1,
+        }
+    }
+
+    pub fn insert(&mut self, net: IpNet, entry: &str, end_time: u64) {
+        let (mut index, key) = self.root_and_key(&net.network());
+        for i in 0..net.prefix_len() {
+            let bit = key_bit(key, i);
+            index = match self.nodes[index].children[bit] {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(Node::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[index].children[bit] = Some(child);
+                    child
+                }
+            };
+        }
+        self.nodes[index]
+            .entries
+            .insert(entry.to_string(), end_time);
+    }
+
+    pub fn remove(&mut self, net: IpNet, entry: &str) {
+        let (mut index, key) = self.root_and_key(&net.network());
+        for i in 0..net.prefix_len() {
+            index = match self.nodes[index].children[key_bit(key, i)] {
+                Some(child) => child,
+                None => return,
+            };
+        }
+        self.nodes[index].entries.remove(entry);
+    }
+
+    /// Whether any range containing ip is blacklisted beyond now.
+    pub fn contains(&self, ip: &IpAddr, now: u64) -> bool {
+        let ip = canonical_ip(ip);
+        let (mut index, key) = self.root_and_key(&ip);
+        let bits = match ip {
+            IpAddr::V4(_) => IPV4_BITS,
+            IpAddr::V6(_) => IPV6_BITS,
+        };
+        for i in 0..=bits {
+            let node = &self.nodes[index];
+            if node.entries.values().any(|end_time| *end_time > now) {
+                return true;
+            }
+            if i == bits {
+                break;
+            }
+            index = match node.children[key_bit(key, i)] {
+                Some(child) => child,
+                None => return false,
+            };
+        }
+        false
+    }
+
+    // IPv4 keys are left aligned so both families share the same bit walk.
+    fn root_and_key(&self, ip: &IpAddr) -> (usize, u128) {
+        match ip {
+            IpAddr::V4(ip) => (self.ipv4_root, (u32::from(*ip) as u128) << 96),
+            IpAddr::V6(ip) => (self.ipv6_root, u128::from(*ip)),
+        }
+    }
+}
+
+fn key_bit(key: u128, index: u8) -> usize {
+    ((key >> (127 - index as u32)) & 1) as usize
+}
+
+// IPv4 clients of a dual stack listener show up as ::ffff:a.b.c.d
+fn canonical_ip(ip: &IpAddr) -> IpAddr {
+    if let IpAddr::V6(v6) = ip {
+        if let Some(v4) = v6.to_ipv4_mapped() {
+            return IpAddr::V4(v4);
+        }
+    }
+    *ip
+}
+
+/// Parses the resource name of an Ip (single address) or IPCIDR blacklist entry.
+pub fn parse_ip_net(resource_name: &str) -> Option<IpNet> {
+    if let Ok(net) = resource_name.parse::<IpNet>() {
+        return Some(net.trunc());
+    }
+    resource_name.parse::<IpAddr>().ok().map(IpNet::from)
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+
+    use super::{parse_ip_net, IpPrefixTree};
+
+    fn ip(data: &str) -> IpAddr {
+        data.parse().unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn ip_prefix_tree_test() {
+        let mut tree = IpPrefixTree::new();
+        assert!(!tree.contains(&ip("127.0.0.1"), 10));
+
+        tree.insert(parse_ip_net("192.168.1.0/24").unwrap(), "a", 100);
+        tree.insert(parse_ip_net("10.0.0.1").unwrap(), "b", 100);
+        tree.insert(parse_ip_net("2001:db8::/32").unwrap(), "c", 100);
+
+        assert!(tree.contains(&ip("192.168.1.1"), 10));
+        assert!(tree.contains(&ip("192.168.1.255"), 10));
+        assert!(!tree.contains(&ip("192.168.2.1"), 10));
+        assert!(tree.contains(&ip("10.0.0.1"), 10));
+        assert!(!tree.contains(&ip("10.0.0.2"), 10));
+        assert!(tree.contains(&ip("2001:db8::1"), 10));
+        assert!(!tree.contains(&ip("2001:db9::1"), 10));
+        assert!(tree.contains(&ip("::ffff:192.168.1.7"), 10));
+
+        // expired
+        assert!(!tree.contains(&ip("192.168.1.1"), 100));
+
+        tree.remove(parse_ip_net("192.168.1.0/24").unwrap(), "a");
+        assert!(!tree.contains(&ip("192.168.1.1"), 10));
+        assert!(tree.contains(&ip("10.0.0.1"), 10));
+
+        // entries ending at the same node are removed one by one
+        tree.insert(parse_ip_net("10.0.0.1/32").unwrap(), "d", 100);
+        tree.remove(parse_ip_net("10.0.0.1").unwrap(), "b");
+        assert!(tree.contains(&ip("10.0.0.1"), 10));
+        tree.remove(parse_ip_net("10.0.0.1/32").unwrap(), "d");
+        assert!(!tree.contains(&ip("10.0.0.1"), 10));
+
+        tree.insert(parse_ip_net("0.0.0.0/0").unwrap(), "e", 100);
+        assert!(tree.contains(&ip("8.8.8.8"), 10));
+        assert!(!tree.contains(&ip("::1"), 10));
+    }
+
+    #[tokio::test]
+    pub async fn parse_ip_net_test() {
+        assert_eq!(
+            parse_ip_net("192.168.1.7/24").unwrap().to_string(),
+            "192.168.1.0/24"
+        );
+        assert_eq!(parse_ip_net("10.0.0.1").unwrap().to_string(), "10.0.0.1/32");
+        assert_eq!(parse_ip_net("::1").unwrap().to_string(), "::1/128");
+        assert!(parse_ip_net("localhost").is_none());
+    }
+}
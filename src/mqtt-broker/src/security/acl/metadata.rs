@@ -12,10 +12,18 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+
+use common_base::tools::now_second;
 use dashmap::DashMap;
+use log::warn;
 use metadata_struct::acl::mqtt_acl::{MqttAcl, MqttAclResourceType};
 use metadata_struct::acl::mqtt_blacklist::{MqttAclBlackList, MqttAclBlackListType};
 
+use super::ip_prefix_tree::{parse_ip_net, IpPrefixTree};
+
 #[derive(Clone)]
 pub struct AclMetadata {
     // blacklist
@@ -25,10 +33,18 @@ pub struct AclMetadata {
     pub blacklist_user_match: DashMap<String, Vec<MqttAclBlackList>>,
     pub blacklist_client_id_match: DashMap<String, Vec<MqttAclBlackList>>,
     pub blacklist_ip_match: DashMap<String, Vec<MqttAclBlackList>>,
+    // Ip and IPCIDR entries, checked when a connection is accepted
+    pub blacklist_ip_tree: Arc<RwLock<IpPrefixTree>>,
 
     // acl
     pub acl_user: DashMap<String, Vec<MqttAcl>>,
     pub acl_client_id: DashMap<String, Vec<MqttAcl>>,
+
+    // (connect_id, acl carried by the login credential, e.g. jwt claims)
+    pub acl_connection: DashMap<u64, Vec<MqttAcl>>,
+
+    // (connect_id, the login credential was marked as super user by the auth backend)
+    pub super_user_connection: DashMap<u64, bool>,
 }
 
 impl Default for AclMetadata {
@@ -46,9 +62,12 @@ impl AclMetadata {
             blacklist_user_match: DashMap::with_capacity(2),
             blacklist_client_id_match: DashMap::with_capacity(2),
             blacklist_ip_match: DashMap::with_capacity(2),
+            blacklist_ip_tree: Arc::new(RwLock::new(IpPrefixTree::new())),
 
             acl_user: DashMap::with_capacity(2),
             acl_client_id: DashMap::with_capacity(2),
+            acl_connection: DashMap::with_capacity(2),
+            super_user_connection: DashMap::with_capacity(2),
         }
     }
 
@@ -84,6 +103,27 @@ impl AclMetadata {
         }
     }
 
+    pub fn add_connection_acl(&self, connect_id: u64, acl_list: Vec<MqttAcl>) {
+        if acl_list.is_empty() {
+            self.acl_connection.remove(&connect_id);
+            return;
+        }
+        self.acl_connection.insert(connect_id, acl_list);
+    }
+
+    pub fn remove_connection_acl(&self, connect_id: u64) {
+        self.acl_connection.remove(&connect_id);
+        self.super_user_connection.remove(&connect_id);
+    }
+
+    pub fn add_super_user_connection(&self, connect_id: u64) {
+        self.super_user_connection.insert(connect_id, true);
+    }
+
+    pub fn is_super_user_connection(&self, connect_id: u64) -> bool {
+        self.super_user_connection.contains_key(&connect_id)
+    }
+
     pub fn parse_mqtt_blacklist(&self, blacklist: MqttAclBlackList) {
         match blacklist.blacklist_type {
             MqttAclBlackListType::ClientId => {
@@ -95,6 +135,7 @@ impl AclMetadata {
                     .insert(blacklist.resource_name.clone(), blacklist);
             }
             MqttAclBlackListType::Ip => {
+                self.add_ip_tree(&blacklist);
                 self.blacklist_ip
                     .insert(blacklist.resource_name.clone(), blacklist);
             }
@@ -115,6 +156,7 @@ impl AclMetadata {
                 }
             }
             MqttAclBlackListType::IPCIDR => {
+                self.add_ip_tree(&blacklist);
                 let key = self.get_ip_cidr_key();
                 if let Some(mut data) = self.blacklist_ip_match.get_mut(&key) {
                     data.push(blacklist)
@@ -134,6 +176,7 @@ impl AclMetadata {
                 self.blacklist_user.remove(&blacklist.resource_name);
             }
             MqttAclBlackListType::Ip => {
+                self.remove_ip_tree(&blacklist);
                 self.blacklist_ip.remove(&blacklist.resource_name);
             }
             MqttAclBlackListType::ClientIdMatch => {
@@ -145,12 +188,129 @@ impl AclMetadata {
                 self.blacklist_user_match.remove(&key);
             }
             MqttAclBlackListType::IPCIDR => {
+                self.remove_ip_tree(&blacklist);
                 let key = self.get_ip_cidr_key();
                 self.blacklist_ip_match.remove(&key);
             }
         }
     }
 
+    // Replaces the cached blacklist with the full list read from the storage,
+    // the ip tree is rebuilt aside and swapped in so lookups never see it half filled.
+    pub fn reload_blacklist(&self, blacklist_list: Vec<MqttAclBlackList>) {
+        let mut ip_tree = IpPrefixTree::new();
+        let mut names: HashMap<String, HashSet<String>> = HashMap::new();
+        let mut match_list: HashMap<String, Vec<MqttAclBlackList>> = HashMap::new();
+
+        for blacklist in blacklist_list {
+            if matches!(
+                blacklist.blacklist_type,
+                MqttAclBlackListType::Ip | MqttAclBlackListType::IPCIDR
+            ) {
+                match parse_ip_net(&blacklist.resource_name) {
+                    Some(net) => {
+                        ip_tree.insert(net, &ip_tree_entry(&blacklist), blacklist.end_time)
+                    }
+                    None => warn!(
+                        "invalid ip blacklist resource name: {}",
+                        blacklist.resource_name
+                    ),
+                }
+            }
+            match blacklist.blacklist_type {
+                MqttAclBlackListType::ClientId
+                | MqttAclBlackListType::User
+                | MqttAclBlackListType::Ip => {
+                    names
+                        .entry(blacklist.blacklist_type.to_string())
+                        .or_default()
+                        .insert(blacklist.resource_name.clone());
+                    self.parse_mqtt_blacklist_exact(blacklist);
+                }
+                MqttAclBlackListType::ClientIdMatch
+                | MqttAclBlackListType::UserMatch
+                | MqttAclBlackListType::IPCIDR => {
+                    match_list
+                        .entry(blacklist.blacklist_type.to_string())
+                        .or_default()
+                        .push(blacklist);
+                }
+            }
+        }
+
+        if let Ok(mut tree) = self.blacklist_ip_tree.write() {
+            *tree = ip_tree;
+        }
+
+        for (blacklist_type, map) in [
+            (MqttAclBlackListType::ClientId, &self.blacklist_client_id),
+            (MqttAclBlackListType::User, &self.blacklist_user),
+            (MqttAclBlackListType::Ip, &self.blacklist_ip),
+        ] {
+            let keep = names
+                .remove(&blacklist_type.to_string())
+                .unwrap_or_default();
+            map.retain(|name, _| keep.contains(name));
+        }
+
+        for (blacklist_type, map) in [
+            (
+                MqttAclBlackListType::ClientIdMatch,
+                &self.blacklist_client_id_match,
+            ),
+            (MqttAclBlackListType::UserMatch, &self.blacklist_user_match),
+            (MqttAclBlackListType::IPCIDR, &self.blacklist_ip_match),
+        ] {
+            let key = blacklist_type.to_string();
+            match match_list.remove(&key) {
+                Some(data) => {
+                    map.insert(key, data);
+                }
+                None => {
+                    map.remove(&key);
+                }
+            }
+        }
+    }
+
+    pub fn is_ip_blacklist(&self, ip: &IpAddr) -> bool {
+        match self.blacklist_ip_tree.read() {
+            Ok(tree) => tree.contains(ip, now_second()),
+            Err(_) => false,
+        }
+    }
+
+    fn parse_mqtt_blacklist_exact(&self, blacklist: MqttAclBlackList) {
+        let map = match blacklist.blacklist_type {
+            MqttAclBlackListType::ClientId => &self.blacklist_client_id,
+            MqttAclBlackListType::User => &self.blacklist_user,
+            MqttAclBlackListType::Ip => &self.blacklist_ip,
+            _ => return,
+        };
+        map.insert(blacklist.resource_name.clone(), blacklist);
+    }
+
+    fn add_ip_tree(&self, blacklist: &MqttAclBlackList) {
+        let Some(net) = parse_ip_net(&blacklist.resource_name) else {
+            warn!(
+                "invalid ip blacklist resource name: {}",
+                blacklist.resource_name
+            );
+            return;
+        };
+        if let Ok(mut tree) = self.blacklist_ip_tree.write() {
+            tree.insert(net, &ip_tree_entry(blacklist), blacklist.end_time);
+        }
+    }
+
+    fn remove_ip_tree(&self, blacklist: &MqttAclBlackList) {
+        if let Some(net) = parse_ip_net(&blacklist.resource_name) {
+            if let Ok(mut tree) = self.blacklist_ip_tree.write() {
+                tree.remove(net, &ip_tree_entry(blacklist));
+            }
+        }
+    }
+
     pub fn get_blacklist_user_match(&self) -> Option<Vec<MqttAclBlackList>> {
         let key = self.get_user_match_key();
         if let Some(data) = self.blacklist_user_match.get(&key) {
@@ -188,6 +348,11 @@ impl AclMetadata {
     }
 }
 
+// Identifies a blacklist entry inside the ip tree
+fn ip_tree_entry(blacklist: &MqttAclBlackList) -> String {
+    format!("{}_{}", blacklist.blacklist_type, blacklist.resource_name)
+}
+
 #[cfg(test)]
 mod test {
     use common_base::tools::now_second;
@@ -363,4 +528,61 @@ mod test {
             2
         );
     }
+
+    #[tokio::test]
+    pub async fn reload_blacklist_test() {
+        let acl_metadata = AclMetadata::new();
+        let ip = "192.168.1.1".parse().unwrap();
+        let user_blacklist = MqttAclBlackList {
+            blacklist_type: MqttAclBlackListType::User,
+            resource_name: "test_user".to_string(),
+            end_time: now_second() + 100,
+            desc: "".to_string(),
+        };
+        let ip_cidr_blacklist = MqttAclBlackList {
+            blacklist_type: MqttAclBlackListType::IPCIDR,
+            resource_name: "192.168.1.0/24".to_string(),
+            end_time: now_second() + 100,
+            desc: "".to_string(),
+        };
+        acl_metadata.reload_blacklist(vec![user_blacklist.clone(), ip_cidr_blacklist]);
+        assert!(acl_metadata.blacklist_user.contains_key("test_user"));
+        assert_eq!(acl_metadata.get_blacklist_ip_match().unwrap().len(), 1);
+        assert!(acl_metadata.is_ip_blacklist(&ip));
+
+        // entries deleted from the storage are dropped from the cache
+        acl_metadata.reload_blacklist(vec![user_blacklist]);
+        assert!(acl_metadata.blacklist_user.contains_key("test_user"));
+        assert!(acl_metadata.get_blacklist_ip_match().is_none());
+        assert!(!acl_metadata.is_ip_blacklist(&ip));
+
+        acl_metadata.reload_blacklist(Vec::new());
+        assert!(acl_metadata.blacklist_user.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn remove_shared_ip_blacklist_test() {
+        let acl_metadata = AclMetadata::new();
+        let ip = "10.0.0.1".parse().unwrap();
+        let ip_blacklist = MqttAclBlackList {
+            blacklist_type: MqttAclBlackListType::Ip,
+            resource_name: "10.0.0.1".to_string(),
+            end_time: now_second() + 100,
+            desc: "".to_string(),
+        };
+        let ip_cidr_blacklist = MqttAclBlackList {
+            blacklist_type: MqttAclBlackListType::IPCIDR,
+            resource_name: "10.0.0.1/32".to_string(),
+            end_time: now_second() + 100,
+            desc: "".to_string(),
+        };
+        acl_metadata.parse_mqtt_blacklist(ip_blacklist.clone());
+        acl_metadata.parse_mqtt_blacklist(ip_cidr_blacklist.clone());
+
+        // both entries end at the same tree node
+        acl_metadata.remove_mqtt_blacklist(ip_blacklist);
+        assert!(acl_metadata.is_ip_blacklist(&ip));
+        acl_metadata.remove_mqtt_blacklist(ip_cidr_blacklist);
+        assert!(!acl_metadata.is_ip_blacklist(&ip));
+    }
 }
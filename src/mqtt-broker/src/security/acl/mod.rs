@@ -25,7 +25,9 @@ use regex::Regex;
 
 use crate::handler::cache::CacheManager;
 use crate::handler::constant::WILDCARD_RESOURCE;
+use crate::subscribe::sub_common::path_match;
 
+pub mod ip_prefix_tree;
 pub mod metadata;
 
 pub fn is_allow_acl(
@@ -37,7 +39,11 @@ pub fn is_allow_acl(
     _: QoS,
 ) -> bool {
     // check super user
-    if is_super_user(cache_mamanger, &connection.login_user) {
+    if is_super_user(cache_mamanger, &connection.login_user)
+        || cache_mamanger
+            .acl_metadata
+            .is_super_user_connection(connection.connect_id)
+    {
         return true;
     }
 
@@ -46,6 +52,14 @@ pub fn is_allow_acl(
         return false;
     }
 
+    // check the acl carried by the login credential, e.g. jwt claims.
+    // It can only narrow the permissions, the cluster acl below still applies.
+    if connection_acl_permission(cache_mamanger, connection, topic_name, &action)
+        == Some(MqttAclPermission::Deny)
+    {
+        return false;
+    }
+
     // chack acl
     if is_acl_deny(cache_mamanger, connection, topic_name, action) {
         return false;
@@ -53,12 +67,18 @@ pub fn is_allow_acl(
 
     // check retain acl
     if retain
-        && is_acl_deny(
+        && (connection_acl_permission(
             cache_mamanger,
             connection,
             topic_name,
-            MqttAclAction::Retain,
-        )
+            &MqttAclAction::Retain,
+        ) == Some(MqttAclPermission::Deny)
+            || is_acl_deny(
+                cache_mamanger,
+                connection,
+                topic_name,
+                MqttAclAction::Retain,
+            ))
     {
         return false;
     }
@@ -117,27 +137,13 @@ fn is_blacklist(cache_manager: &Arc<CacheManager>, connection: &MQTTConnection)
         }
     }
 
-    // check ip blacklist
-    if let Some(data) = cache_manager
-        .acl_metadata
-        .blacklist_ip
-        .get(&connection.source_ip_addr)
-    {
-        if data.end_time < now_second() {
+    // check ip and ip cidr blacklist
+    if let Ok(ip) = connection.source_ip_addr.parse::<IpAddr>() {
+        if cache_manager.acl_metadata.is_ip_blacklist(&ip) {
             return true;
         }
     }
 
-    if let Some(data) = cache_manager.acl_metadata.get_blacklist_ip_match() {
-        for raw in data {
-            if ip_match(&connection.source_ip_addr, &raw.resource_name)
-                && raw.end_time < now_second()
-            {
-                return true;
-            }
-        }
-    }
-
     false
 }
 
@@ -182,6 +188,33 @@ fn is_acl_deny(
     false
 }
 
+// The first rule that matches the topic and action decides the permission.
+fn connection_acl_permission(
+    cache_mamanger: &Arc<CacheManager>,
+    connection: &MQTTConnection,
+    topic_name: &str,
+    action: &MqttAclAction,
+) -> Option<MqttAclPermission> {
+    let acl_list = cache_mamanger
+        .acl_metadata
+        .acl_connection
+        .get(&connection.connect_id)?;
+
+    for raw in acl_list.iter() {
+        let action_match = raw.action == *action
+            || raw.action == MqttAclAction::All
+            || (raw.action == MqttAclAction::PubSub
+                && (*action == MqttAclAction::Publish || *action == MqttAclAction::Subscribe));
+
+        if action_match
+            && (topic_match(topic_name, &raw.topic) || path_match(topic_name, &raw.topic))
+        {
+            return Some(raw.permission.clone());
+        }
+    }
+    None
+}
+
 fn topic_match(topic_name: &str, match_topic_name: &str) -> bool {
     if match_topic_name == WILDCARD_RESOURCE {
         return true;
@@ -216,8 +249,12 @@ mod test {
     use metadata_struct::acl::mqtt_blacklist::{MqttAclBlackList, MqttAclBlackListType};
     use metadata_struct::mqtt::connection::{ConnectionConfig, MQTTConnection};
     use metadata_struct::mqtt::user::MqttUser;
+    use protocol::mqtt::common::QoS;
 
-    use super::{ip_match, is_acl_deny, is_blacklist, is_super_user, topic_match};
+    use super::{
+        connection_acl_permission, ip_match, is_acl_deny, is_allow_acl, is_blacklist,
+        is_super_user, topic_match,
+    };
     use crate::handler::cache::CacheManager;
     use crate::handler::constant::WILDCARD_RESOURCE;
 
@@ -642,6 +679,102 @@ mod test {
         ));
     }
 
+    #[tokio::test]
+    pub async fn check_connection_acl_test() {
+        let client_pool = Arc::new(ClientPool::new(1));
+        let cluster_name = "test".to_string();
+        let cache_manager = Arc::new(CacheManager::new(client_pool, cluster_name));
+        let config = ConnectionConfig {
+            connect_id: 1,
+            client_id: "client_id-1".to_string(),
+            receive_maximum: 3,
+            max_packet_size: 3,
+            topic_alias_max: 3,
+            request_problem_info: 1,
+            keep_alive: 2,
+            source_ip_addr: "127.0.0.1".to_string(),
+        };
+        let mut connection = MQTTConnection::new(config);
+        connection.login_success("loboxu".to_string());
+
+        assert!(connection_acl_permission(
+            &cache_manager,
+            &connection,
+            "device/client_id-1/up",
+            &MqttAclAction::Publish
+        )
+        .is_none());
+
+        let acl_list = vec![
+            MqttAcl {
+                resource_type: MqttAclResourceType::ClientId,
+                resource_name: connection.client_id.clone(),
+                topic: "device/client_id-1/+".to_string(),
+                ip: WILDCARD_RESOURCE.to_string(),
+                action: MqttAclAction::PubSub,
+                permission: MqttAclPermission::Allow,
+            },
+            MqttAcl {
+                resource_type: MqttAclResourceType::ClientId,
+                resource_name: connection.client_id.clone(),
+                topic: WILDCARD_RESOURCE.to_string(),
+                ip: WILDCARD_RESOURCE.to_string(),
+                action: MqttAclAction::All,
+                permission: MqttAclPermission::Deny,
+            },
+        ];
+        cache_manager
+            .acl_metadata
+            .add_connection_acl(connection.connect_id, acl_list);
+
+        assert!(is_allow_acl(
+            &cache_manager,
+            &connection,
+            "device/client_id-1/up",
+            MqttAclAction::Publish,
+            false,
+            QoS::AtLeastOnce
+        ));
+        assert!(!is_allow_acl(
+            &cache_manager,
+            &connection,
+            "device/client_id-2/up",
+            MqttAclAction::Publish,
+            false,
+            QoS::AtLeastOnce
+        ));
+
+        // the cluster acl still applies when the connection acl allows the topic
+        let acl = MqttAcl {
+            resource_type: MqttAclResourceType::ClientId,
+            resource_name: connection.client_id.clone(),
+            topic: "device/client_id-1/up".to_string(),
+            ip: WILDCARD_RESOURCE.to_string(),
+            action: MqttAclAction::Publish,
+            permission: MqttAclPermission::Deny,
+        };
+        cache_manager.add_acl(acl.clone());
+        assert!(!is_allow_acl(
+            &cache_manager,
+            &connection,
+            "device/client_id-1/up",
+            MqttAclAction::Publish,
+            false,
+            QoS::AtLeastOnce
+        ));
+        cache_manager.remove_acl(acl);
+
+        cache_manager.remove_connection(connection.connect_id);
+        assert!(is_allow_acl(
+            &cache_manager,
+            &connection,
+            "device/client_id-2/up",
+            MqttAclAction::Publish,
+            false,
+            QoS::AtLeastOnce
+        ));
+    }
+
     #[tokio::test]
     pub async fn topic_match_test() {
         let topic_name = "t1";
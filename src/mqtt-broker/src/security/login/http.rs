@@ -11,3 +11,438 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::async_trait;
+use common_base::config::common::AuthHttp;
+use common_base::tools::now_second;
+use log::warn;
+use lru::LruCache;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+
+use super::Authentication;
+use crate::handler::error::MqttBrokerError;
+
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+const DEFAULT_POOL_SIZE: usize = 8;
+const DEFAULT_CACHE_TTL_SEC: u64 = 30;
+const DEFAULT_CACHE_MAX_SIZE: usize = 10000;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HttpAuthResult {
+    Allow { is_superuser: bool },
+    Deny,
+    // The service has no opinion on the credential, the next authenticator decides.
+    Ignore,
+}
+
+#[derive(Clone, Debug)]
+pub struct HttpAuthRequest {
+    pub client_id: String,
+    pub username: String,
+    pub password: String,
+    pub peer_addr: SocketAddr,
+    pub proto_ver: u8,
+}
+
+#[derive(Debug, Deserialize)]
+struct HttpAuthResponse {
+    #[serde(default)]
+    result: Option<String>,
+    #[serde(default)]
+    is_superuser: bool,
+}
+
+pub struct HttpAuthClient {
+    config: AuthHttp,
+    client: Client,
+    // (credential hash, (result, expire time)), bounded so that random credentials cannot grow it
+    cache: Mutex<LruCache<u64, (HttpAuthResult, u64)>>,
+}
+
+impl HttpAuthClient {
+    pub fn new(config: AuthHttp) -> Result<Self, MqttBrokerError> {
+        let timeout_ms = if config.timeout_ms == 0 {
+            DEFAULT_TIMEOUT_MS
+        } else {
+            config.timeout_ms
+        };
+        let pool_size = if config.pool_size == 0 {
+            DEFAULT_POOL_SIZE
+        } else {
+            config.pool_size
+        };
+        let cache_max_size = if config.cache_max_size == 0 {
+            DEFAULT_CACHE_MAX_SIZE
+        } else {
+            config.cache_max_size
+        };
+        // reqwest is built without a bundled crypto provider so that rustls keeps
+        // a single process-wide provider for the broker's own TLS listeners.
+        let mut roots = RootCertStore::empty();
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+        let tls_config = ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        let client = Client::builder()
+            .use_preconfigured_tls(tls_config)
+            .timeout(Duration::from_millis(timeout_ms))
+            .pool_max_idle_per_host(pool_size)
+            .build()?;
+        Ok(HttpAuthClient {
+            config,
+            client,
+            cache: Mutex::new(LruCache::new(NonZeroUsize::new(cache_max_size).unwrap())),
+        })
+    }
+
+    pub async fn authenticate(&self, request: &HttpAuthRequest) -> HttpAuthResult {
+        let key = self.cache_key(request);
+        if let Some(result) = self.get_cache(key) {
+            return result;
+        }
+
+        let result = match self.call(request).await {
+            Ok(result) => result,
+            Err(e) => {
+                warn!(
+                    "client [{}] http authentication request failed, error message: {}",
+                    request.client_id, e
+                );
+                return HttpAuthResult::Ignore;
+            }
+        };
+
+        if result != HttpAuthResult::Ignore {
+            self.cache
+                .lock()
+                .unwrap()
+                .put(key, (result.clone(), now_second() + self.cache_ttl_sec()));
+        }
+        result
+    }
+
+    fn get_cache(&self, key: u64) -> Option<HttpAuthResult> {
+        let mut cache = self.cache.lock().unwrap();
+        let (result, expire_time) = cache.get(&key)?.clone();
+        if expire_time > now_second() {
+            return Some(result);
+        }
+        cache.pop(&key);
+        None
+    }
+
+    async fn call(&self, request: &HttpAuthRequest) -> Result<HttpAuthResult, MqttBrokerError> {
+        let mut builder = self
+            .client
+            .post(&self.config.url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(self.render_body(request));
+        for (name, value) in self.config.headers.iter() {
+            builder = builder.header(name, value);
+        }
+
+        let response = builder.send().await?;
+        let status = response.status();
+        let body = response.text().await?;
+        Ok(parse_response(status, &body))
+    }
+
+    fn render_body(&self, request: &HttpAuthRequest) -> String {
+        if self.config.body.is_empty() {
+            return json!({
+                "clientid": request.client_id,
+                "username": request.username,
+                "password": request.password,
+                "peerhost": request.peer_addr.ip().to_string(),
+                "proto_ver": request.proto_ver,
+            })
+            .to_string();
+        }
+
+        self.config
+            .body
+            .replace("${clientid}", &escape_json(&request.client_id))
+            .replace("${username}", &escape_json(&request.username))
+            .replace("${password}", &escape_json(&request.password))
+            .replace("${peerhost}", &request.peer_addr.ip().to_string())
+            .replace("${proto_ver}", &request.proto_ver.to_string())
+    }
+
+    fn cache_key(&self, request: &HttpAuthRequest) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        request.client_id.hash(&mut hasher);
+        request.username.hash(&mut hasher);
+        request.password.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn cache_ttl_sec(&self) -> u64 {
+        if self.config.cache_ttl_sec == 0 {
+            return DEFAULT_CACHE_TTL_SEC;
+        }
+        self.config.cache_ttl_sec
+    }
+}
+
+// 2xx responses with a JSON body decide by its "result" field, only an explicit
+// "allow" lets the client in. 2xx responses that cannot be parsed or carry no
+// result and 401/403 deny the client, 204 and everything else is ignored.
+fn parse_response(status: StatusCode, body: &str) -> HttpAuthResult {
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return HttpAuthResult::Deny;
+    }
+
+    if !status.is_success() || status == StatusCode::NO_CONTENT {
+        return HttpAuthResult::Ignore;
+    }
+
+    let response = match serde_json::from_str::<HttpAuthResponse>(body) {
+        Ok(response) => response,
+        Err(e) => {
+            warn!(
+                "http authentication response is not valid json, error message: {}",
+                e
+            );
+            return HttpAuthResult::Deny;
+        }
+    };
+
+    match response.result.as_deref() {
+        Some("allow") => HttpAuthResult::Allow {
+            is_superuser: response.is_superuser,
+        },
+        Some("deny") | None => HttpAuthResult::Deny,
+        _ => HttpAuthResult::Ignore,
+    }
+}
+
+fn escape_json(value: &str) -> String {
+    let data = Value::String(value.to_string()).to_string();
+    data[1..data.len() - 1].to_string()
+}
+
+pub struct Http {
+    request: HttpAuthRequest,
+    client: Arc<HttpAuthClient>,
+}
+
+impl Http {
+    pub fn new(request: HttpAuthRequest, client: Arc<HttpAuthClient>) -> Self {
+        Http { request, client }
+    }
+
+    pub async fn result(&self) -> HttpAuthResult {
+        self.client.authenticate(&self.request).await
+    }
+}
+
+#[async_trait]
+impl Authentication for Http {
+    async fn apply(&self) -> Result<bool, MqttBrokerError> {
+        Ok(matches!(self.result().await, HttpAuthResult::Allow { .. }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::response::{IntoResponse, Response};
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use common_base::config::common::AuthHttp;
+    use serde_json::{json, Value};
+    use tokio::net::TcpListener;
+
+    use super::{Http, HttpAuthClient, HttpAuthRequest, HttpAuthResult};
+    use crate::security::login::Authentication;
+
+    async fn auth_handler(
+        State(counter): State<Arc<AtomicUsize>>,
+        Json(body): Json<Value>,
+    ) -> Response {
+        counter.fetch_add(1, Ordering::SeqCst);
+        let username = body["username"].as_str().unwrap_or_default();
+        let password = body["password"].as_str().unwrap_or_default();
+        match (username, password) {
+            ("admin", "pwd123") => (
+                StatusCode::OK,
+                Json(json!({"result": "allow", "is_superuser": true})),
+            )
+                .into_response(),
+            ("lobo", "pwd123") => {
+                (StatusCode::OK, Json(json!({"result": "allow"}))).into_response()
+            }
+            ("lobo", _) => (StatusCode::OK, Json(json!({"result": "deny"}))).into_response(),
+            ("blocked", _) => (StatusCode::FORBIDDEN, Json(json!({}))).into_response(),
+            ("html", _) => (StatusCode::OK, "<html>ok</html>").into_response(),
+            ("empty", _) => (StatusCode::OK, Json(json!({}))).into_response(),
+            ("superuser", _) => {
+                (StatusCode::OK, Json(json!({"is_superuser": true}))).into_response()
+            }
+            ("no_content", _) => StatusCode::NO_CONTENT.into_response(),
+            _ => (StatusCode::OK, Json(json!({"result": "ignore"}))).into_response(),
+        }
+    }
+
+    async fn start_stub_server(counter: Arc<AtomicUsize>) -> String {
+        let app = Router::new()
+            .route("/auth", post(auth_handler))
+            .with_state(counter);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        format!("http://{}/auth", addr)
+    }
+
+    fn build_request(username: &str, password: &str) -> HttpAuthRequest {
+        HttpAuthRequest {
+            client_id: "client-1".to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            peer_addr: "127.0.0.1:1000".parse().unwrap(),
+            proto_ver: 5,
+        }
+    }
+
+    #[tokio::test]
+    pub async fn http_auth_result_test() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let url = start_stub_server(counter.clone()).await;
+        let client = Arc::new(
+            HttpAuthClient::new(AuthHttp {
+                enable: true,
+                url,
+                ..Default::default()
+            })
+            .unwrap(),
+        );
+
+        let result = client.authenticate(&build_request("admin", "pwd123")).await;
+        assert_eq!(result, HttpAuthResult::Allow { is_superuser: true });
+
+        let result = client.authenticate(&build_request("lobo", "pwd123")).await;
+        assert_eq!(
+            result,
+            HttpAuthResult::Allow {
+                is_superuser: false
+            }
+        );
+
+        let result = client.authenticate(&build_request("lobo", "pwd")).await;
+        assert_eq!(result, HttpAuthResult::Deny);
+
+        let result = client.authenticate(&build_request("blocked", "pwd")).await;
+        assert_eq!(result, HttpAuthResult::Deny);
+
+        let result = client.authenticate(&build_request("other", "pwd")).await;
+        assert_eq!(result, HttpAuthResult::Ignore);
+
+        // a 2xx response that is not json fails closed
+        let result = client.authenticate(&build_request("html", "pwd")).await;
+        assert_eq!(result, HttpAuthResult::Deny);
+
+        // a json body without an explicit result fails closed
+        let result = client.authenticate(&build_request("empty", "pwd")).await;
+        assert_eq!(result, HttpAuthResult::Deny);
+        let result = client
+            .authenticate(&build_request("superuser", "pwd"))
+            .await;
+        assert_eq!(result, HttpAuthResult::Deny);
+
+        let result = client
+            .authenticate(&build_request("no_content", "pwd"))
+            .await;
+        assert_eq!(result, HttpAuthResult::Ignore);
+
+        let http = Http::new(build_request("lobo", "pwd123"), client.clone());
+        assert!(http.apply().await.unwrap());
+        let http = Http::new(build_request("lobo", "pwd"), client.clone());
+        assert!(!http.apply().await.unwrap());
+    }
+
+    #[tokio::test]
+    pub async fn http_auth_cache_test() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let url = start_stub_server(counter.clone()).await;
+        let client = HttpAuthClient::new(AuthHttp {
+            enable: true,
+            url,
+            body: r#"{"username":"${username}","password":"${password}","proto":${proto_ver}}"#
+                .to_string(),
+            cache_ttl_sec: 60,
+            ..Default::default()
+        })
+        .unwrap();
+
+        for _ in 0..3 {
+            let result = client.authenticate(&build_request("lobo", "pwd123")).await;
+            assert_eq!(
+                result,
+                HttpAuthResult::Allow {
+                    is_superuser: false
+                }
+            );
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 1);
+
+        // ignore results are never cached
+        for _ in 0..2 {
+            let result = client.authenticate(&build_request("other", "pwd")).await;
+            assert_eq!(result, HttpAuthResult::Ignore);
+        }
+        assert_eq!(counter.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    pub async fn http_auth_cache_bounded_test() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let url = start_stub_server(counter.clone()).await;
+        let client = HttpAuthClient::new(AuthHttp {
+            enable: true,
+            url,
+            cache_ttl_sec: 60,
+            cache_max_size: 2,
+            ..Default::default()
+        })
+        .unwrap();
+
+        for password in ["p1", "p2", "p3"] {
+            client.authenticate(&build_request("lobo", password)).await;
+        }
+        assert_eq!(client.cache.lock().unwrap().len(), 2);
+
+        // the oldest credential was evicted and is requested again
+        client.authenticate(&build_request("lobo", "p1")).await;
+        assert_eq!(counter.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    pub async fn http_auth_unavailable_test() {
+        let client = HttpAuthClient::new(AuthHttp {
+            enable: true,
+            url: "http://127.0.0.1:1/auth".to_string(),
+            timeout_ms: 500,
+            ..Default::default()
+        })
+        .unwrap();
+        let result = client.authenticate(&build_request("lobo", "pwd123")).await;
+        assert_eq!(result, HttpAuthResult::Ignore);
+    }
+}
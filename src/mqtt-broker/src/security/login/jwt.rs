@@ -11,3 +11,387 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use axum::async_trait;
+use common_base::config::common::AuthJwt;
+use common_base::tools::read_file;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use log::warn;
+use metadata_struct::acl::mqtt_acl::{
+    MqttAcl, MqttAclAction, MqttAclPermission, MqttAclResourceType,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::Authentication;
+use crate::handler::constant::WILDCARD_RESOURCE;
+use crate::handler::error::MqttBrokerError;
+
+const DEFAULT_ACL_CLAIM_NAME: &str = "acl";
+const PLACEHOLDER_CLIENT_ID: &str = "${clientid}";
+const PLACEHOLDER_USERNAME: &str = "${username}";
+
+#[derive(Debug, Deserialize)]
+pub struct JwtClaims {
+    #[serde(default)]
+    pub sub: Option<String>,
+    #[serde(default)]
+    pub clientid: Option<String>,
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
+}
+
+// A single acl rule carried in the token, for example:
+// {"permission": "allow", "action": "publish", "topic": "device/${clientid}/#"}
+#[derive(Debug, Deserialize)]
+struct JwtAclRule {
+    permission: String,
+    action: String,
+    topic: String,
+}
+
+pub struct JwtVerifier {
+    config: AuthJwt,
+    key: DecodingKey,
+    validation: Validation,
+}
+
+impl JwtVerifier {
+    pub fn new(config: AuthJwt) -> Result<Self, MqttBrokerError> {
+        let algorithm = Algorithm::from_str(&config.algorithm)
+            .map_err(|_| MqttBrokerError::UnsupportedJwtAlgorithm(config.algorithm.clone()))?;
+
+        let key = match algorithm {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                if config.secret_base64_encoded {
+                    DecodingKey::from_base64_secret(&config.secret)?
+                } else {
+                    DecodingKey::from_secret(config.secret.as_bytes())
+                }
+            }
+            Algorithm::ES256 | Algorithm::ES384 => {
+                DecodingKey::from_ec_pem(read_file(&config.public_key)?.as_bytes())?
+            }
+            Algorithm::RS256
+            | Algorithm::RS384
+            | Algorithm::RS512
+            | Algorithm::PS256
+            | Algorithm::PS384
+            | Algorithm::PS512 => {
+                DecodingKey::from_rsa_pem(read_file(&config.public_key)?.as_bytes())?
+            }
+            Algorithm::EdDSA => {
+                return Err(MqttBrokerError::UnsupportedJwtAlgorithm(
+                    config.algorithm.clone(),
+                ))
+            }
+        };
+
+        let mut validation = Validation::new(algorithm);
+        // "exp" is required unless tokens without an expiry are explicitly allowed,
+        // "nbf" is optional but always checked when the token carries it.
+        if config.allow_no_expiry {
+            validation.required_spec_claims.clear();
+        }
+        validation.validate_nbf = true;
+
+        Ok(JwtVerifier {
+            config,
+            key,
+            validation,
+        })
+    }
+
+    pub fn verify(
+        &self,
+        username: &str,
+        client_id: &str,
+        token: &str,
+    ) -> Result<JwtClaims, MqttBrokerError> {
+        let claims = decode::<JwtClaims>(token, &self.key, &self.validation)?.claims;
+
+        if self.config.verify_username && claims.sub.as_deref() != Some(username) {
+            return Err(MqttBrokerError::JwtClaimMismatch("sub".to_string()));
+        }
+
+        if self.config.verify_client_id && claims.clientid.as_deref() != Some(client_id) {
+            return Err(MqttBrokerError::JwtClaimMismatch("clientid".to_string()));
+        }
+
+        Ok(claims)
+    }
+
+    fn acl_claim_name(&self) -> &str {
+        if self.config.acl_claim_name.is_empty() {
+            return DEFAULT_ACL_CLAIM_NAME;
+        }
+        &self.config.acl_claim_name
+    }
+}
+
+pub fn is_jwt_token(token: &str) -> bool {
+    decode_header(token).is_ok()
+}
+
+pub struct Jwt {
+    username: String,
+    client_id: String,
+    token: String,
+    verifier: Arc<JwtVerifier>,
+}
+
+impl Jwt {
+    pub fn new(
+        username: String,
+        client_id: String,
+        token: String,
+        verifier: Arc<JwtVerifier>,
+    ) -> Self {
+        Jwt {
+            username,
+            client_id,
+            token,
+            verifier,
+        }
+    }
+
+    pub fn claims(&self) -> Result<JwtClaims, MqttBrokerError> {
+        self.verifier
+            .verify(&self.username, &self.client_id, &self.token)
+    }
+
+    // Returns None when the token is rejected, errors are only returned for broker side failures.
+    pub fn verified_claims(&self) -> Result<Option<JwtClaims>, MqttBrokerError> {
+        match self.claims() {
+            Ok(claims) => Ok(Some(claims)),
+            Err(e @ MqttBrokerError::FromJwtError(_))
+            | Err(e @ MqttBrokerError::JwtClaimMismatch(_)) => {
+                warn!(
+                    "client [{}] jwt authentication failed, error message: {}",
+                    self.client_id, e
+                );
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Convert the acl rules in the claims into acl entries bound to the client id of the connection.
+    pub fn acl_list(&self, claims: &JwtClaims) -> Result<Vec<MqttAcl>, MqttBrokerError> {
+        let rules = match claims.extra.get(self.verifier.acl_claim_name()) {
+            Some(value) => serde_json::from_value::<Vec<JwtAclRule>>(value.clone())?,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut results = Vec::new();
+        for rule in rules {
+            let permission = match rule.permission.to_lowercase().as_str() {
+                "allow" => MqttAclPermission::Allow,
+                "deny" => MqttAclPermission::Deny,
+                _ => return Err(MqttBrokerError::InvalidAclPermission),
+            };
+            let action = match rule.action.to_lowercase().as_str() {
+                "all" => MqttAclAction::All,
+                "subscribe" => MqttAclAction::Subscribe,
+                "publish" => MqttAclAction::Publish,
+                "pubsub" => MqttAclAction::PubSub,
+                "retain" => MqttAclAction::Retain,
+                "qos" => MqttAclAction::Qos,
+                _ => return Err(MqttBrokerError::InvalidAclAction),
+            };
+            let topic = rule
+                .topic
+                .replace(PLACEHOLDER_CLIENT_ID, &self.client_id)
+                .replace(PLACEHOLDER_USERNAME, &self.username);
+
+            results.push(MqttAcl {
+                resource_type: MqttAclResourceType::ClientId,
+                resource_name: self.client_id.clone(),
+                topic,
+                ip: WILDCARD_RESOURCE.to_string(),
+                action,
+                permission,
+            });
+        }
+        Ok(results)
+    }
+}
+
+#[async_trait]
+impl Authentication for Jwt {
+    async fn apply(&self) -> Result<bool, MqttBrokerError> {
+        Ok(self.verified_claims()?.is_some())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use common_base::config::common::AuthJwt;
+    use common_base::tools::now_second;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use metadata_struct::acl::mqtt_acl::{MqttAclAction, MqttAclPermission};
+    use serde_json::{json, Value};
+
+    use super::{is_jwt_token, Jwt, JwtVerifier};
+    use crate::security::login::Authentication;
+
+    fn secret() -> String {
+        "robustmq-secret".to_string()
+    }
+
+    fn build_token(claims: Value) -> String {
+        encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(secret().as_bytes()),
+        )
+        .unwrap()
+    }
+
+    fn build_verifier(verify_username: bool, verify_client_id: bool) -> Arc<JwtVerifier> {
+        build_verifier_with(AuthJwt {
+            verify_username,
+            verify_client_id,
+            ..Default::default()
+        })
+    }
+
+    fn build_verifier_with(config: AuthJwt) -> Arc<JwtVerifier> {
+        let config = AuthJwt {
+            enable: true,
+            algorithm: "HS256".to_string(),
+            secret: secret(),
+            ..config
+        };
+        Arc::new(JwtVerifier::new(config).unwrap())
+    }
+
+    #[tokio::test]
+    pub async fn jwt_verify_test() {
+        let verifier = build_verifier(false, false);
+        let token = build_token(json!({"sub": "lobo", "exp": now_second() + 60}));
+        assert!(is_jwt_token(&token));
+        assert!(!is_jwt_token("pwd123"));
+
+        let jwt = Jwt::new(
+            "lobo".to_string(),
+            "client-1".to_string(),
+            token,
+            verifier.clone(),
+        );
+        assert!(jwt.apply().await.unwrap());
+
+        // expired
+        let token = build_token(json!({"sub": "lobo", "exp": now_second() - 3600}));
+        let jwt = Jwt::new(
+            "lobo".to_string(),
+            "client-1".to_string(),
+            token,
+            verifier.clone(),
+        );
+        assert!(!jwt.apply().await.unwrap());
+
+        // not yet valid
+        let token = build_token(json!({
+            "sub": "lobo",
+            "nbf": now_second() + 3600,
+            "exp": now_second() + 7200
+        }));
+        let jwt = Jwt::new(
+            "lobo".to_string(),
+            "client-1".to_string(),
+            token,
+            verifier.clone(),
+        );
+        assert!(!jwt.apply().await.unwrap());
+
+        // signed with another secret
+        let token = encode(
+            &Header::default(),
+            &json!({"sub": "lobo"}),
+            &EncodingKey::from_secret("other".as_bytes()),
+        )
+        .unwrap();
+        let jwt = Jwt::new("lobo".to_string(), "client-1".to_string(), token, verifier);
+        assert!(!jwt.apply().await.unwrap());
+    }
+
+    #[tokio::test]
+    pub async fn jwt_no_expiry_test() {
+        let token = build_token(json!({"sub": "lobo"}));
+
+        // "exp" is required by default
+        let jwt = Jwt::new(
+            "lobo".to_string(),
+            "client-1".to_string(),
+            token.clone(),
+            build_verifier(false, false),
+        );
+        assert!(!jwt.apply().await.unwrap());
+
+        let verifier = build_verifier_with(AuthJwt {
+            allow_no_expiry: true,
+            ..Default::default()
+        });
+        let jwt = Jwt::new("lobo".to_string(), "client-1".to_string(), token, verifier);
+        assert!(jwt.apply().await.unwrap());
+    }
+
+    #[tokio::test]
+    pub async fn jwt_bind_claims_test() {
+        let verifier = build_verifier(true, true);
+        let token = build_token(json!({
+            "sub": "lobo",
+            "clientid": "client-1",
+            "exp": now_second() + 60
+        }));
+
+        let jwt = Jwt::new(
+            "lobo".to_string(),
+            "client-1".to_string(),
+            token.clone(),
+            verifier.clone(),
+        );
+        assert!(jwt.apply().await.unwrap());
+
+        let jwt = Jwt::new(
+            "other".to_string(),
+            "client-1".to_string(),
+            token.clone(),
+            verifier.clone(),
+        );
+        assert!(!jwt.apply().await.unwrap());
+
+        let jwt = Jwt::new("lobo".to_string(), "client-2".to_string(), token, verifier);
+        assert!(!jwt.apply().await.unwrap());
+    }
+
+    #[tokio::test]
+    pub async fn jwt_acl_list_test() {
+        let verifier = build_verifier(false, false);
+        let token = build_token(json!({
+            "sub": "lobo",
+            "exp": now_second() + 60,
+            "acl": [
+                {"permission": "allow", "action": "publish", "topic": "device/${clientid}/up"},
+                {"permission": "deny", "action": "subscribe", "topic": "#"}
+            ]
+        }));
+        let jwt = Jwt::new("lobo".to_string(), "client-1".to_string(), token, verifier);
+        let claims = jwt.claims().unwrap();
+        let acl_list = jwt.acl_list(&claims).unwrap();
+        assert_eq!(acl_list.len(), 2);
+        assert_eq!(acl_list[0].topic, "device/client-1/up");
+        assert_eq!(acl_list[0].action, MqttAclAction::Publish);
+        assert_eq!(acl_list[0].permission, MqttAclPermission::Allow);
+        assert_eq!(acl_list[0].resource_name, "client-1");
+        assert_eq!(acl_list[1].action, MqttAclAction::Subscribe);
+        assert_eq!(acl_list[1].permission, MqttAclPermission::Deny);
+    }
+}
@@ -13,13 +13,16 @@
 // limitations under the License.
 
 use std::net::SocketAddr;
+use std::sync::Arc;
 
 use axum::async_trait;
 
+use crate::handler::cache::CacheManager;
 use crate::handler::error::MqttBrokerError;
 
 pub mod http;
 pub mod jwt;
+pub mod password;
 pub mod plaintext;
 pub mod psk;
 pub mod x509;
@@ -29,16 +32,62 @@ pub trait Authentication {
     async fn apply(&self) -> Result<bool, MqttBrokerError>;
 }
 
-pub fn is_ip_blacklist(_: &SocketAddr) -> bool {
-    false
+pub fn is_ip_blacklist(cache_manager: &Arc<CacheManager>, addr: &SocketAddr) -> bool {
+    cache_manager.acl_metadata.is_ip_blacklist(&addr.ip())
 }
 
 #[cfg(test)]
 mod test {
+    use std::sync::Arc;
+
+    use common_base::tools::now_second;
+    use grpc_clients::pool::ClientPool;
+    use metadata_struct::acl::mqtt_blacklist::{MqttAclBlackList, MqttAclBlackListType};
+
     use super::is_ip_blacklist;
+    use crate::handler::cache::CacheManager;
 
     #[tokio::test]
     pub async fn is_ip_blacklist_test() {
-        assert!(!is_ip_blacklist(&"127.0.0.1:1000".parse().unwrap()))
+        let client_pool = Arc::new(ClientPool::new(1));
+        let cache_manager = Arc::new(CacheManager::new(client_pool, "test".to_string()));
+        assert!(!is_ip_blacklist(
+            &cache_manager,
+            &"127.0.0.1:1000".parse().unwrap()
+        ));
+
+        let blacklist = MqttAclBlackList {
+            blacklist_type: MqttAclBlackListType::IPCIDR,
+            resource_name: "127.0.0.0/8".to_string(),
+            end_time: now_second() + 100,
+            desc: "".to_string(),
+        };
+        cache_manager.add_blacklist(blacklist.clone());
+        assert!(is_ip_blacklist(
+            &cache_manager,
+            &"127.0.0.1:1000".parse().unwrap()
+        ));
+        assert!(!is_ip_blacklist(
+            &cache_manager,
+            &"10.0.0.1:1000".parse().unwrap()
+        ));
+
+        cache_manager.remove_blacklist(blacklist);
+        assert!(!is_ip_blacklist(
+            &cache_manager,
+            &"127.0.0.1:1000".parse().unwrap()
+        ));
+
+        // expired entries are ignored
+        cache_manager.add_blacklist(MqttAclBlackList {
+            blacklist_type: MqttAclBlackListType::Ip,
+            resource_name: "127.0.0.1".to_string(),
+            end_time: now_second() - 1,
+            desc: "".to_string(),
+        });
+        assert!(!is_ip_blacklist(
+            &cache_manager,
+            &"127.0.0.1:1000".parse().unwrap()
+        ));
     }
 }
@@ -0,0 +1,337 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::str::FromStr;
+
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use common_base::config::common::AuthPasswordHash;
+use pbkdf2::Pbkdf2;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::handler::error::MqttBrokerError;
+
+const SHA256_PASSWORD_PREFIX: &str = "$sha256$";
+const PBKDF2_PASSWORD_PREFIX: &str = "$pbkdf2";
+const ARGON2_PASSWORD_PREFIX: &str = "$argon2";
+const BCRYPT_PASSWORD_PREFIXES: [&str; 4] = ["$2a$", "$2b$", "$2x$", "$2y$"];
+const BCRYPT_PASSWORD_LEN: usize = 60;
+const SALT_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordHashAlgorithm {
+    Plain,
+    Bcrypt,
+    Pbkdf2,
+    Argon2,
+    Sha256,
+}
+
+impl FromStr for PasswordHashAlgorithm {
+    type Err = MqttBrokerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" | "plain" => Ok(PasswordHashAlgorithm::Plain),
+            "bcrypt" => Ok(PasswordHashAlgorithm::Bcrypt),
+            "pbkdf2" => Ok(PasswordHashAlgorithm::Pbkdf2),
+            "argon2" => Ok(PasswordHashAlgorithm::Argon2),
+            "sha256" => Ok(PasswordHashAlgorithm::Sha256),
+            _ => Err(MqttBrokerError::InvalidPasswordHashAlgorithm(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaltPosition {
+    Prefix,
+    Suffix,
+}
+
+impl FromStr for SaltPosition {
+    type Err = MqttBrokerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "" | "prefix" => Ok(SaltPosition::Prefix),
+            "suffix" => Ok(SaltPosition::Suffix),
+            _ => Err(MqttBrokerError::InvalidPasswordHashAlgorithm(s.to_string())),
+        }
+    }
+}
+
+impl SaltPosition {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SaltPosition::Prefix => "prefix",
+            SaltPosition::Suffix => "suffix",
+        }
+    }
+}
+
+// Algorithm used to hash the password of new users and of users still stored in cleartext.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordHashOptions {
+    pub algorithm: PasswordHashAlgorithm,
+    pub salt_position: SaltPosition,
+}
+
+impl PasswordHashOptions {
+    pub fn from_conf(conf: &AuthPasswordHash) -> Result<Self, MqttBrokerError> {
+        let algorithm = if conf.algorithm.is_empty() {
+            PasswordHashAlgorithm::Argon2
+        } else {
+            PasswordHashAlgorithm::from_str(&conf.algorithm)?
+        };
+        Ok(PasswordHashOptions {
+            algorithm,
+            salt_position: SaltPosition::from_str(&conf.salt_position)?,
+        })
+    }
+
+    pub async fn hash(&self, password: &str) -> Result<String, MqttBrokerError> {
+        hash_password_blocking(self.algorithm, self.salt_position, password).await
+    }
+}
+
+pub fn generate_salt() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SALT_LEN)
+        .map(char::from)
+        .collect()
+}
+
+// Hex encoded sha256 of the salted password.
+pub fn sha256_digest(salt_position: SaltPosition, salt: &str, password: &str) -> String {
+    let mut hasher = Sha256::new();
+    match salt_position {
+        SaltPosition::Prefix => {
+            hasher.update(salt.as_bytes());
+            hasher.update(password.as_bytes());
+        }
+        SaltPosition::Suffix => {
+            hasher.update(password.as_bytes());
+            hasher.update(salt.as_bytes());
+        }
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// A sha256 password is kept in MqttUser.password as $sha256$<prefix|suffix>$<salt>$<hex digest>,
+// bcrypt hashes already carry their own cost and salt.
+pub fn encode_sha256_password(salt_position: SaltPosition, salt: &str, digest: &str) -> String {
+    format!(
+        "{}{}${}${}",
+        SHA256_PASSWORD_PREFIX,
+        salt_position.as_str(),
+        salt,
+        digest
+    )
+}
+
+pub fn hash_password(
+    algorithm: PasswordHashAlgorithm,
+    salt_position: SaltPosition,
+    password: &str,
+) -> Result<String, MqttBrokerError> {
+    match algorithm {
+        PasswordHashAlgorithm::Plain => Ok(password.to_string()),
+        PasswordHashAlgorithm::Bcrypt => Ok(bcrypt::hash(password, bcrypt::DEFAULT_COST)?),
+        PasswordHashAlgorithm::Pbkdf2 => {
+            let salt = SaltString::generate(&mut OsRng);
+            Ok(Pbkdf2
+                .hash_password(password.as_bytes(), &salt)?
+                .to_string())
+        }
+        PasswordHashAlgorithm::Argon2 => {
+            let salt = SaltString::generate(&mut OsRng);
+            Ok(Argon2::default()
+                .hash_password(password.as_bytes(), &salt)?
+                .to_string())
+        }
+        PasswordHashAlgorithm::Sha256 => {
+            let salt = generate_salt();
+            let digest = sha256_digest(salt_position, &salt, password);
+            Ok(encode_sha256_password(salt_position, &salt, &digest))
+        }
+    }
+}
+
+// bcrypt, pbkdf2 and argon2 take tens of milliseconds on purpose, keep them off the runtime workers.
+#[allow(clippy::result_large_err)]
+pub async fn hash_password_blocking(
+    algorithm: PasswordHashAlgorithm,
+    salt_position: SaltPosition,
+    password: &str,
+) -> Result<String, MqttBrokerError> {
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || hash_password(algorithm, salt_position, &password)).await?
+}
+
+pub fn is_bcrypt_password(stored: &str) -> bool {
+    stored.len() == BCRYPT_PASSWORD_LEN
+        && BCRYPT_PASSWORD_PREFIXES
+            .iter()
+            .any(|prefix| stored.starts_with(prefix))
+}
+
+// Users created before passwords were hashed still hold the cleartext password.
+pub fn is_hashed_password(stored: &str) -> bool {
+    is_bcrypt_password(stored)
+        || stored.starts_with(SHA256_PASSWORD_PREFIX)
+        || stored.starts_with(PBKDF2_PASSWORD_PREFIX)
+        || stored.starts_with(ARGON2_PASSWORD_PREFIX)
+}
+
+// Checks the password sent in CONNECT against the stored value, which is either
+// a bcrypt hash, a PHC string (pbkdf2, argon2), an encoded sha256 password or
+// the password itself.
+pub fn verify_password(stored: &str, password: &str) -> Result<bool, MqttBrokerError> {
+    if is_bcrypt_password(stored) {
+        return Ok(bcrypt::verify(password, stored)?);
+    }
+
+    if stored.starts_with(PBKDF2_PASSWORD_PREFIX) || stored.starts_with(ARGON2_PASSWORD_PREFIX) {
+        let hash = PasswordHash::new(stored)?;
+        let res = if stored.starts_with(PBKDF2_PASSWORD_PREFIX) {
+            Pbkdf2.verify_password(password.as_bytes(), &hash)
+        } else {
+            Argon2::default().verify_password(password.as_bytes(), &hash)
+        };
+        return match res {
+            Ok(()) => Ok(true),
+            Err(argon2::password_hash::Error::Password) => Ok(false),
+            Err(e) => Err(e.into()),
+        };
+    }
+
+    if let Some(encoded) = stored.strip_prefix(SHA256_PASSWORD_PREFIX) {
+        let parts: Vec<&str> = encoded.splitn(3, '$').collect();
+        if parts.len() != 3 {
+            return Err(MqttBrokerError::InvalidPasswordHashAlgorithm(
+                SHA256_PASSWORD_PREFIX.to_string(),
+            ));
+        }
+        let salt_position = SaltPosition::from_str(parts[0])?;
+        let digest = sha256_digest(salt_position, parts[1], password);
+        return Ok(digest.as_bytes().ct_eq(parts[2].as_bytes()).into());
+    }
+
+    Ok(stored.as_bytes().ct_eq(password.as_bytes()).into())
+}
+
+#[allow(clippy::result_large_err)]
+pub async fn verify_password_blocking(
+    stored: &str,
+    password: &str,
+) -> Result<bool, MqttBrokerError> {
+    let stored = stored.to_string();
+    let password = password.to_string();
+    tokio::task::spawn_blocking(move || verify_password(&stored, &password)).await?
+}
+
+#[cfg(test)]
+mod test {
+    use common_base::config::common::AuthPasswordHash;
+
+    use super::{
+        encode_sha256_password, hash_password, hash_password_blocking, is_hashed_password,
+        sha256_digest, verify_password, verify_password_blocking, PasswordHashAlgorithm,
+        PasswordHashOptions, SaltPosition,
+    };
+
+    #[tokio::test]
+    pub async fn hash_password_test() {
+        for algorithm in [
+            PasswordHashAlgorithm::Plain,
+            PasswordHashAlgorithm::Bcrypt,
+            PasswordHashAlgorithm::Pbkdf2,
+            PasswordHashAlgorithm::Argon2,
+            PasswordHashAlgorithm::Sha256,
+        ] {
+            let stored = hash_password_blocking(algorithm, SaltPosition::Prefix, "pwd123")
+                .await
+                .unwrap();
+            assert_eq!(
+                is_hashed_password(&stored),
+                algorithm != PasswordHashAlgorithm::Plain
+            );
+            assert!(verify_password_blocking(&stored, "pwd123").await.unwrap());
+            assert!(!verify_password_blocking(&stored, "pwd1234").await.unwrap());
+        }
+
+        let stored = hash_password(
+            PasswordHashAlgorithm::Sha256,
+            SaltPosition::Suffix,
+            "pwd123",
+        )
+        .unwrap();
+        assert!(stored.starts_with("$sha256$suffix$"));
+        assert!(verify_password(&stored, "pwd123").unwrap());
+    }
+
+    #[tokio::test]
+    pub async fn sha256_digest_test() {
+        // echo -n "saltpwd123" | sha256sum
+        let digest = sha256_digest(SaltPosition::Prefix, "salt", "pwd123");
+        assert_eq!(
+            digest,
+            "1c76576362c925b9522842884f30dcb824ea61ce9be4bd104331352911d5c4a2"
+        );
+        let stored = encode_sha256_password(SaltPosition::Prefix, "salt", &digest);
+        assert_eq!(stored, format!("$sha256$prefix$salt${}", digest));
+        assert!(verify_password(&stored, "pwd123").unwrap());
+
+        let stored = encode_sha256_password(SaltPosition::Suffix, "salt", &digest);
+        assert!(!verify_password(&stored, "pwd123").unwrap());
+    }
+
+    #[tokio::test]
+    pub async fn password_hash_options_test() {
+        let options = PasswordHashOptions::from_conf(&AuthPasswordHash::default()).unwrap();
+        assert_eq!(options.algorithm, PasswordHashAlgorithm::Argon2);
+        assert_eq!(options.salt_position, SaltPosition::Prefix);
+        assert!(options
+            .hash("pwd123")
+            .await
+            .unwrap()
+            .starts_with("$argon2id$"));
+
+        let conf = AuthPasswordHash {
+            algorithm: "sha256".to_string(),
+            ..Default::default()
+        };
+        let options = PasswordHashOptions::from_conf(&conf).unwrap();
+        assert!(options
+            .hash("pwd123")
+            .await
+            .unwrap()
+            .starts_with("$sha256$prefix$"));
+
+        let conf = AuthPasswordHash {
+            algorithm: "md5".to_string(),
+            ..Default::default()
+        };
+        assert!(PasswordHashOptions::from_conf(&conf).is_err());
+    }
+}
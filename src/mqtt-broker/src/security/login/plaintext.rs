@@ -16,6 +16,7 @@ use std::sync::Arc;
 
 use axum::async_trait;
 
+use super::password::verify_password_blocking;
 use super::Authentication;
 use crate::handler::cache::CacheManager;
 use crate::handler::error::MqttBrokerError;
@@ -39,10 +40,11 @@ impl Plaintext {
 #[async_trait]
 impl Authentication for Plaintext {
     async fn apply(&self) -> Result<bool, MqttBrokerError> {
-        if let Some(user) = self.cache_manager.user_info.get(&self.username) {
-            return Ok(user.password == self.password);
-        }
-        return Err(MqttBrokerError::UserDoesNotExist);
+        let stored = match self.cache_manager.user_info.get(&self.username) {
+            Some(user) => user.password.clone(),
+            None => return Err(MqttBrokerError::UserDoesNotExist),
+        };
+        verify_password_blocking(&stored, &self.password).await
     }
 }
 
@@ -57,6 +59,7 @@ mod test {
 
     use super::Plaintext;
     use crate::handler::cache::CacheManager;
+    use crate::security::login::password::{hash_password, PasswordHashAlgorithm, SaltPosition};
     use crate::security::login::Authentication;
 
     #[tokio::test]
@@ -95,4 +98,36 @@ mod test {
         let res = pt.apply().await.unwrap();
         assert!(!res);
     }
+
+    #[tokio::test]
+    pub async fn plaintext_hashed_password_test() {
+        let client_pool: Arc<ClientPool> = Arc::new(ClientPool::new(100));
+        let cache_manager: Arc<CacheManager> =
+            Arc::new(CacheManager::new(client_pool.clone(), "test".to_string()));
+        let password = hash_password(
+            PasswordHashAlgorithm::Sha256,
+            SaltPosition::Prefix,
+            "pwd123",
+        )
+        .unwrap();
+        cache_manager.add_user(MqttUser {
+            username: "lobo".to_string(),
+            password,
+            is_superuser: false,
+        });
+
+        let pt = Plaintext::new(
+            "lobo".to_string(),
+            "pwd123".to_string(),
+            cache_manager.clone(),
+        );
+        assert!(pt.apply().await.unwrap());
+
+        let pt = Plaintext::new(
+            "lobo".to_string(),
+            "pwd1111".to_string(),
+            cache_manager.clone(),
+        );
+        assert!(!pt.apply().await.unwrap());
+    }
 }
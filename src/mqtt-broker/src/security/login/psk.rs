@@ -11,3 +11,193 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use std::sync::Arc;
+
+use axum::async_trait;
+use common_base::config::common::AuthPsk;
+use openssl::ssl::{SslAcceptor, SslMethod, SslVersion};
+
+use super::Authentication;
+use crate::handler::cache::CacheManager;
+use crate::handler::error::MqttBrokerError;
+
+const DEFAULT_PSK_CIPHERS: &str = "PSK";
+
+// Pre-shared keys are stored hex encoded, e.g. "1a2b3c4d".
+pub fn decode_psk(psk: &str) -> Result<Vec<u8>, MqttBrokerError> {
+    if psk.is_empty() {
+        return Err(MqttBrokerError::InvalidPskKey(psk.len()));
+    }
+    let mut key = Vec::with_capacity(psk.len() / 2);
+    for pair in psk.as_bytes().chunks(2) {
+        let byte = std::str::from_utf8(pair)
+            .ok()
+            .filter(|_| pair.len() == 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(byte) => key.push(byte),
+            None => return Err(MqttBrokerError::InvalidPskKey(psk.len())),
+        }
+    }
+    Ok(key)
+}
+
+// PSK cipher suites are negotiated in TLS 1.2, which is what constrained
+// devices without certificate support implement. The key of an identity is
+// looked up in the psk cache during the handshake.
+pub fn build_psk_acceptor(
+    cache_manager: Arc<CacheManager>,
+    config: &AuthPsk,
+) -> Result<SslAcceptor, MqttBrokerError> {
+    let ciphers = if config.ciphers.is_empty() {
+        DEFAULT_PSK_CIPHERS
+    } else {
+        config.ciphers.as_str()
+    };
+
+    let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls_server())?;
+    builder.set_max_proto_version(Some(SslVersion::TLS1_2))?;
+    builder.set_cipher_list(ciphers)?;
+    builder.set_psk_server_callback(move |_, identity, psk_buf| {
+        let identity = match identity.and_then(|raw| std::str::from_utf8(raw).ok()) {
+            Some(identity) => identity,
+            None => return Ok(0),
+        };
+        if let Some(psk) = cache_manager.psk_info.get(identity) {
+            if let Ok(key) = decode_psk(&psk.psk) {
+                if key.len() <= psk_buf.len() {
+                    psk_buf[..key.len()].copy_from_slice(&key);
+                    return Ok(key.len());
+                }
+            }
+        }
+        // A zero length key makes the handshake fail with unknown_psk_identity
+        Ok(0)
+    });
+    Ok(builder.build())
+}
+
+pub struct Psk {
+    identity: Option<String>,
+    config: AuthPsk,
+}
+
+impl Psk {
+    pub fn new(identity: Option<String>, config: AuthPsk) -> Self {
+        Psk { identity, config }
+    }
+}
+
+#[async_trait]
+impl Authentication for Psk {
+    // The handshake only completes when the client holds the key of its identity.
+    async fn apply(&self) -> Result<bool, MqttBrokerError> {
+        if !self.config.enable {
+            return Ok(false);
+        }
+        Ok(self.identity.is_some())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::pin::Pin;
+    use std::sync::Arc;
+
+    use common_base::config::common::AuthPsk;
+    use grpc_clients::pool::ClientPool;
+    use metadata_struct::mqtt::psk::MqttPskIdentity;
+    use openssl::ssl::{Ssl, SslConnector, SslMethod, SslVerifyMode, SslVersion};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_openssl::SslStream;
+
+    use super::{build_psk_acceptor, decode_psk, Psk};
+    use crate::handler::cache::CacheManager;
+    use crate::security::login::Authentication;
+
+    async fn handshake(identity: &str, key: &str) -> Option<String> {
+        let client_pool = Arc::new(ClientPool::new(1));
+        let cache_manager = Arc::new(CacheManager::new(client_pool, "test".to_string()));
+        cache_manager.add_psk(MqttPskIdentity {
+            identity: "sensor-1".to_string(),
+            psk: "0102030405060708".to_string(),
+        });
+        let acceptor = build_psk_acceptor(cache_manager, &AuthPsk::default()).unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let ssl = Ssl::new(acceptor.context()).unwrap();
+            let mut stream = SslStream::new(ssl, stream).unwrap();
+            match Pin::new(&mut stream).accept().await {
+                Ok(()) => stream
+                    .ssl()
+                    .psk_identity()
+                    .map(|raw| String::from_utf8_lossy(raw).to_string()),
+                Err(_) => None,
+            }
+        });
+
+        let identity = identity.to_string();
+        let key = decode_psk(key).unwrap();
+        let mut connector = SslConnector::builder(SslMethod::tls_client()).unwrap();
+        connector.set_verify(SslVerifyMode::NONE);
+        connector
+            .set_max_proto_version(Some(SslVersion::TLS1_2))
+            .unwrap();
+        connector.set_cipher_list("PSK").unwrap();
+        connector.set_psk_client_callback(move |_, _, identity_buf, psk_buf| {
+            identity_buf[..identity.len()].copy_from_slice(identity.as_bytes());
+            identity_buf[identity.len()] = 0;
+            psk_buf[..key.len()].copy_from_slice(&key);
+            Ok(key.len())
+        });
+        let ssl = connector
+            .build()
+            .configure()
+            .unwrap()
+            .into_ssl("localhost")
+            .unwrap();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut stream = SslStream::new(ssl, stream).unwrap();
+        let _ = Pin::new(&mut stream).connect().await;
+
+        server.await.unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn decode_psk_test() {
+        assert_eq!(decode_psk("0aFF10").unwrap(), vec![0x0a, 0xff, 0x10]);
+        assert!(decode_psk("").is_err());
+        assert!(decode_psk("abc").is_err());
+        assert!(decode_psk("zz").is_err());
+    }
+
+    #[tokio::test]
+    pub async fn psk_handshake_test() {
+        assert_eq!(
+            handshake("sensor-1", "0102030405060708").await,
+            Some("sensor-1".to_string())
+        );
+        assert_eq!(handshake("sensor-1", "0807060504030201").await, None);
+        assert_eq!(handshake("sensor-2", "0102030405060708").await, None);
+    }
+
+    #[tokio::test]
+    pub async fn psk_apply_test() {
+        let config = AuthPsk {
+            enable: true,
+            ..Default::default()
+        };
+        let psk = Psk::new(Some("sensor-1".to_string()), config.clone());
+        assert!(psk.apply().await.unwrap());
+
+        let psk = Psk::new(None, config);
+        assert!(!psk.apply().await.unwrap());
+
+        let psk = Psk::new(Some("sensor-1".to_string()), AuthPsk::default());
+        assert!(!psk.apply().await.unwrap());
+    }
+}
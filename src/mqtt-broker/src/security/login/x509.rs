@@ -11,3 +11,208 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use std::net::IpAddr;
+
+use axum::async_trait;
+use common_base::config::common::AuthX509;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use x509_parser::extensions::GeneralName;
+use x509_parser::parse_x509_certificate;
+
+use super::Authentication;
+use crate::handler::error::MqttBrokerError;
+
+const PEER_CERT_FIELD_CN: &str = "cn";
+const PEER_CERT_FIELD_SAN: &str = "san";
+
+// The identity carried by a client certificate that has already been verified
+// against the CA bundle during the TLS handshake.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct X509Identity {
+    pub common_name: Option<String>,
+    pub subject_alt_names: Vec<String>,
+}
+
+impl X509Identity {
+    pub fn from_der(der: &[u8]) -> Result<Self, MqttBrokerError> {
+        let (_, cert) = parse_x509_certificate(der)
+            .map_err(|e| MqttBrokerError::InvalidPeerCertificate(e.to_string()))?;
+
+        let common_name = cert
+            .subject()
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .map(|cn| cn.to_string());
+
+        let mut subject_alt_names = Vec::new();
+        if let Ok(Some(san)) = cert.subject_alternative_name() {
+            for name in san.value.general_names.iter() {
+                match name {
+                    GeneralName::DNSName(value)
+                    | GeneralName::RFC822Name(value)
+                    | GeneralName::URI(value) => subject_alt_names.push(value.to_string()),
+                    GeneralName::IPAddress(octets) => {
+                        if let Some(ip) = ip_from_octets(octets) {
+                            subject_alt_names.push(ip.to_string());
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(X509Identity {
+            common_name,
+            subject_alt_names,
+        })
+    }
+
+    // The first certificate is the client's own, the rest is its chain.
+    pub fn from_peer_certificates(certs: Option<&[CertificateDer<'_>]>) -> Option<Self> {
+        let cert = certs?.first()?;
+        match X509Identity::from_der(cert.as_ref()) {
+            Ok(identity) => Some(identity),
+            Err(e) => {
+                warn!("Failed to read the identity of the client certificate, error message: {e}");
+                None
+            }
+        }
+    }
+
+    pub fn field(&self, field: &str) -> Option<String> {
+        match field.to_lowercase().as_str() {
+            PEER_CERT_FIELD_CN => self.common_name.clone(),
+            PEER_CERT_FIELD_SAN => self.subject_alt_names.first().cloned(),
+            _ => None,
+        }
+    }
+
+    pub fn username(&self, config: &AuthX509) -> Option<String> {
+        self.field(&config.peer_cert_as_username)
+    }
+
+    pub fn client_id(&self, config: &AuthX509) -> Option<String> {
+        self.field(&config.peer_cert_as_clientid)
+    }
+}
+
+fn ip_from_octets(octets: &[u8]) -> Option<IpAddr> {
+    if let Ok(v4) = <[u8; 4]>::try_from(octets) {
+        return Some(IpAddr::from(v4));
+    }
+    if let Ok(v6) = <[u8; 16]>::try_from(octets) {
+        return Some(IpAddr::from(v6));
+    }
+    None
+}
+
+pub struct X509 {
+    identity: X509Identity,
+    config: AuthX509,
+}
+
+impl X509 {
+    pub fn new(identity: X509Identity, config: AuthX509) -> Self {
+        X509 { identity, config }
+    }
+}
+
+#[async_trait]
+impl Authentication for X509 {
+    // The certificate chain was checked by the TLS layer, so the login succeeds
+    // as long as the certificate carries the field used as the username.
+    async fn apply(&self) -> Result<bool, MqttBrokerError> {
+        if !self.config.enable {
+            return Ok(false);
+        }
+        Ok(self.identity.username(&self.config).is_some())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use common_base::config::common::AuthX509;
+    use tokio_rustls::rustls::pki_types::CertificateDer;
+
+    use super::{X509Identity, X509};
+    use crate::security::login::Authentication;
+
+    fn read_cert(name: &str) -> Vec<CertificateDer<'static>> {
+        let path = format!(
+            "{}/../../config/example/certs/{}",
+            env!("CARGO_MANIFEST_DIR"),
+            name
+        );
+        let data = std::fs::read(path).unwrap();
+        rustls_pemfile::certs(&mut data.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn x509_identity_test() {
+        let certs = read_cert("cert.pem");
+        let identity = X509Identity::from_peer_certificates(Some(&certs)).unwrap();
+        assert_eq!(identity.common_name, None);
+        assert_eq!(identity.subject_alt_names, vec!["localhost".to_string()]);
+
+        let certs = read_cert("ca.pem");
+        let identity = X509Identity::from_peer_certificates(Some(&certs)).unwrap();
+        assert_eq!(
+            identity.common_name,
+            Some("mkcert root@OpenEuler-22.03-SP1".to_string())
+        );
+        assert!(identity.subject_alt_names.is_empty());
+
+        assert!(X509Identity::from_peer_certificates(None).is_none());
+        assert!(X509Identity::from_der(b"not a certificate").is_err());
+    }
+
+    #[tokio::test]
+    pub async fn x509_identity_field_test() {
+        let identity = X509Identity {
+            common_name: Some("device-1".to_string()),
+            subject_alt_names: vec!["device-1.iot.local".to_string()],
+        };
+        let config = AuthX509 {
+            enable: true,
+            peer_cert_as_username: "cn".to_string(),
+            peer_cert_as_clientid: "SAN".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(identity.username(&config), Some("device-1".to_string()));
+        assert_eq!(
+            identity.client_id(&config),
+            Some("device-1.iot.local".to_string())
+        );
+        assert_eq!(identity.field(""), None);
+    }
+
+    #[tokio::test]
+    pub async fn x509_apply_test() {
+        let identity = X509Identity {
+            common_name: None,
+            subject_alt_names: vec!["localhost".to_string()],
+        };
+        let mut config = AuthX509 {
+            enable: true,
+            peer_cert_as_username: "san".to_string(),
+            ..Default::default()
+        };
+        let x509 = X509::new(identity.clone(), config.clone());
+        assert!(x509.apply().await.unwrap());
+
+        config.peer_cert_as_username = "cn".to_string();
+        let x509 = X509::new(identity.clone(), config.clone());
+        assert!(!x509.apply().await.unwrap());
+
+        config.enable = false;
+        config.peer_cert_as_username = "san".to_string();
+        let x509 = X509::new(identity, config);
+        assert!(!x509.apply().await.unwrap());
+    }
+}
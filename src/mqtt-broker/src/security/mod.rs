@@ -23,19 +23,28 @@ use common_base::config::broker_mqtt::broker_mqtt_conf;
 use common_base::config::common::Auth;
 use dashmap::DashMap;
 use grpc_clients::pool::ClientPool;
+use log::warn;
+use login::http::{Http, HttpAuthClient, HttpAuthRequest, HttpAuthResult};
+use login::jwt::{is_jwt_token, Jwt, JwtVerifier};
+use login::password::{is_hashed_password, PasswordHashAlgorithm, PasswordHashOptions};
 use login::plaintext::Plaintext;
+use login::psk::{decode_psk, Psk};
+use login::x509::X509;
 use login::Authentication;
 use metadata_struct::acl::mqtt_acl::{MqttAcl, MqttAclAction, MqttAclResourceType};
 use metadata_struct::acl::mqtt_blacklist::MqttAclBlackList;
 use metadata_struct::mqtt::connection::MQTTConnection;
+use metadata_struct::mqtt::psk::MqttPskIdentity;
 use metadata_struct::mqtt::user::MqttUser;
 use mysql::MySQLAuthStorageAdapter;
 use placement::PlacementAuthStorageAdapter;
-use protocol::mqtt::common::{ConnectProperties, Login, QoS, Subscribe};
+use protocol::mqtt::common::{ConnectProperties, Login, MqttProtocol, QoS, Subscribe};
+use redis::RedisAuthStorageAdapter;
 use storage_adapter::StorageType;
 
 use crate::handler::cache::CacheManager;
 use crate::handler::error::MqttBrokerError;
+use crate::server::connection::NetworkConnection;
 use crate::subscribe::sub_common::get_sub_topic_id_list;
 
 pub mod acl;
@@ -65,12 +74,21 @@ pub trait AuthStorageAdapter {
     async fn save_blacklist(&self, blacklist: MqttAclBlackList) -> Result<(), MqttBrokerError>;
 
     async fn delete_blacklist(&self, blacklist: MqttAclBlackList) -> Result<(), MqttBrokerError>;
+
+    async fn read_all_psk(&self) -> Result<Vec<MqttPskIdentity>, MqttBrokerError>;
+
+    async fn save_psk(&self, psk: MqttPskIdentity) -> Result<(), MqttBrokerError>;
+
+    async fn delete_psk(&self, identity: String) -> Result<(), MqttBrokerError>;
 }
 
 pub struct AuthDriver {
     cache_manager: Arc<CacheManager>,
     client_pool: Arc<ClientPool>,
     driver: Arc<dyn AuthStorageAdapter + Send + 'static + Sync>,
+    jwt_verifier: Option<Arc<JwtVerifier>>,
+    http_client: Option<Arc<HttpAuthClient>>,
+    password_hash: PasswordHashOptions,
 }
 
 impl AuthDriver {
@@ -82,16 +100,43 @@ impl AuthDriver {
                 panic!("{}", e.to_string());
             }
         };
+        let jwt_verifier = match build_jwt_verifier(&conf.auth) {
+            Ok(verifier) => verifier,
+            Err(e) => {
+                panic!("{}", e.to_string());
+            }
+        };
+        let http_client = match build_http_client(&conf.auth) {
+            Ok(client) => client,
+            Err(e) => {
+                panic!("{}", e.to_string());
+            }
+        };
+        let password_hash = match PasswordHashOptions::from_conf(&conf.auth.password_hash) {
+            Ok(options) => options,
+            Err(e) => {
+                panic!("{}", e.to_string());
+            }
+        };
         AuthDriver {
             cache_manager,
             driver,
             client_pool,
+            jwt_verifier,
+            http_client,
+            password_hash,
         }
     }
 
     pub fn update_driver(&mut self, auth: Auth) -> Result<(), MqttBrokerError> {
+        let jwt_verifier = build_jwt_verifier(&auth)?;
+        let http_client = build_http_client(&auth)?;
+        let password_hash = PasswordHashOptions::from_conf(&auth.password_hash)?;
         let driver = build_driver(self.client_pool.clone(), auth)?;
         self.driver = driver;
+        self.jwt_verifier = jwt_verifier;
+        self.http_client = http_client;
+        self.password_hash = password_hash;
         Ok(())
     }
 
@@ -107,11 +152,12 @@ impl AuthDriver {
         self.driver.read_all_blacklist().await
     }
 
-    pub async fn save_user(&self, user_info: MqttUser) -> Result<(), MqttBrokerError> {
+    pub async fn save_user(&self, mut user_info: MqttUser) -> Result<(), MqttBrokerError> {
         let username = user_info.username.clone();
         if let Some(_user) = self.cache_manager.user_info.get(&username) {
             return Err(MqttBrokerError::UserAlreadyExist);
         }
+        user_info.password = self.password_hash.hash(&user_info.password).await?;
         self.cache_manager.add_user(user_info.clone());
         self.driver.save_user(user_info).await
     }
@@ -140,11 +186,45 @@ impl AuthDriver {
         Ok(())
     }
 
+    pub async fn read_all_psk(&self) -> Result<Vec<MqttPskIdentity>, MqttBrokerError> {
+        self.driver.read_all_psk().await
+    }
+
+    pub async fn save_psk(&self, psk: MqttPskIdentity) -> Result<(), MqttBrokerError> {
+        decode_psk(&psk.psk)?;
+        self.cache_manager.add_psk(psk.clone());
+        self.driver.save_psk(psk).await
+    }
+
+    pub async fn delete_psk(&self, identity: String) -> Result<(), MqttBrokerError> {
+        self.driver.delete_psk(identity.clone()).await?;
+        self.cache_manager.del_psk(identity);
+        Ok(())
+    }
+
+    pub async fn update_psk_cache(&self) -> Result<(), MqttBrokerError> {
+        let all_psks = self.driver.read_all_psk().await?;
+
+        let mut identities = HashSet::new();
+        for psk in all_psks {
+            identities.insert(psk.identity.clone());
+            self.cache_manager.add_psk(psk);
+        }
+        self.cache_manager.retain_psks(identities);
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub async fn check_login_auth(
         &self,
+        connect_id: u64,
+        client_id: &str,
+        protocol: &MqttProtocol,
         login: &Option<Login>,
+        network_connection: &Option<NetworkConnection>,
         _: &Option<ConnectProperties>,
-        _: &SocketAddr,
+        addr: &SocketAddr,
     ) -> Result<bool, MqttBrokerError> {
         let cluster = self.cache_manager.get_cluster_info();
 
@@ -152,10 +232,70 @@ impl AuthDriver {
             return Ok(true);
         }
 
+        if let Some(connection) = network_connection {
+            if let Some(identity) = &connection.peer_identity {
+                let x509 = X509::new(identity.clone(), broker_mqtt_conf().auth.x509.clone());
+                if x509.apply().await? {
+                    return Ok(true);
+                }
+            }
+
+            let psk = Psk::new(
+                connection.psk_identity.clone(),
+                broker_mqtt_conf().auth.psk.clone(),
+            );
+            if psk.apply().await? {
+                return Ok(true);
+            }
+        }
+
         if let Some(info) = login {
-            return self
+            if let Some(verifier) = &self.jwt_verifier {
+                if is_jwt_token(&info.password) {
+                    return self
+                        .jwt_check_login(connect_id, client_id, info, verifier.clone())
+                        .await;
+                }
+            }
+
+            if let Some(http_client) = &self.http_client {
+                let request = HttpAuthRequest {
+                    client_id: client_id.to_owned(),
+                    username: info.username.clone(),
+                    password: info.password.clone(),
+                    peer_addr: *addr,
+                    proto_ver: protocol.clone().into(),
+                };
+                match Http::new(request, http_client.clone()).result().await {
+                    HttpAuthResult::Allow { is_superuser } => {
+                        if is_superuser {
+                            self.cache_manager
+                                .acl_metadata
+                                .add_super_user_connection(connect_id);
+                        }
+                        return Ok(true);
+                    }
+                    HttpAuthResult::Deny => return Ok(false),
+                    HttpAuthResult::Ignore => {}
+                }
+            }
+
+            if self
                 .plaintext_check_login(&info.username, &info.password)
-                .await;
+                .await?
+            {
+                if let Err(e) = self
+                    .rehash_plaintext_user(&info.username, &info.password)
+                    .await
+                {
+                    warn!(
+                        "failed to rehash the password of user [{}], error message: {}",
+                        info.username, e
+                    );
+                }
+                return Ok(true);
+            }
+            return Ok(false);
         }
 
         Ok(false)
@@ -193,6 +333,14 @@ impl AuthDriver {
         Ok(())
     }
 
+    pub async fn update_blacklist_cache(&self) -> Result<(), MqttBrokerError> {
+        let all_blacklists = self.driver.read_all_blacklist().await?;
+        self.cache_manager
+            .acl_metadata
+            .reload_blacklist(all_blacklists);
+        Ok(())
+    }
+
     pub async fn save_blacklist(&self, blacklist: MqttAclBlackList) -> Result<(), MqttBrokerError> {
         self.cache_manager.add_blacklist(blacklist.clone());
         self.driver.save_blacklist(blacklist).await
@@ -266,7 +414,7 @@ impl AuthDriver {
             Err(e) => {
                 // If the user does not exist, try to get the user information from the storage layer
                 if e.to_string() == MqttBrokerError::UserDoesNotExist.to_string() {
-                    return self.try_get_check_user_by_driver(username).await;
+                    return self.try_get_check_user_by_driver(username, password).await;
                 }
                 return Err(e);
             }
@@ -274,13 +422,64 @@ impl AuthDriver {
         Ok(false)
     }
 
-    async fn try_get_check_user_by_driver(&self, username: &str) -> Result<bool, MqttBrokerError> {
+    // Users stored before passwords were hashed are migrated the first time they log in successfully.
+    async fn rehash_plaintext_user(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<(), MqttBrokerError> {
+        if self.password_hash.algorithm == PasswordHashAlgorithm::Plain {
+            return Ok(());
+        }
+        let mut user = match self.cache_manager.user_info.get(username) {
+            Some(user) => user.clone(),
+            None => return Ok(()),
+        };
+        if is_hashed_password(&user.password) {
+            return Ok(());
+        }
+        user.password = self.password_hash.hash(password).await?;
+        self.driver.save_user(user.clone()).await?;
+        self.cache_manager.add_user(user);
+        Ok(())
+    }
+
+    async fn jwt_check_login(
+        &self,
+        connect_id: u64,
+        client_id: &str,
+        login: &Login,
+        verifier: Arc<JwtVerifier>,
+    ) -> Result<bool, MqttBrokerError> {
+        let jwt = Jwt::new(
+            login.username.clone(),
+            client_id.to_owned(),
+            login.password.clone(),
+            verifier,
+        );
+        let claims = match jwt.verified_claims()? {
+            Some(claims) => claims,
+            None => return Ok(false),
+        };
+
+        let acl_list = jwt.acl_list(&claims)?;
+        self.cache_manager
+            .acl_metadata
+            .add_connection_acl(connect_id, acl_list);
+        Ok(true)
+    }
+
+    async fn try_get_check_user_by_driver(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<bool, MqttBrokerError> {
         if let Some(user) = self.driver.get_user(username.to_owned()).await? {
             self.cache_manager.add_user(user.clone());
 
             let plaintext = Plaintext::new(
                 user.username.clone(),
-                user.password.clone(),
+                password.to_owned(),
                 self.cache_manager.clone(),
             );
 
@@ -309,9 +508,28 @@ pub fn build_driver(
         return Ok(Arc::new(driver));
     }
 
+    if matches!(storage_type, StorageType::Redis) {
+        let driver = RedisAuthStorageAdapter::new(auth.redis_addr.clone(), auth.redis.clone())?;
+        return Ok(Arc::new(driver));
+    }
+
     Err(MqttBrokerError::UnavailableStorageType)
 }
 
+pub fn build_jwt_verifier(auth: &Auth) -> Result<Option<Arc<JwtVerifier>>, MqttBrokerError> {
+    if !auth.jwt.enable {
+        return Ok(None);
+    }
+    Ok(Some(Arc::new(JwtVerifier::new(auth.jwt.clone())?)))
+}
+
+pub fn build_http_client(auth: &Auth) -> Result<Option<Arc<HttpAuthClient>>, MqttBrokerError> {
+    if !auth.http.enable {
+        return Ok(None);
+    }
+    Ok(Some(Arc::new(HttpAuthClient::new(auth.http.clone())?)))
+}
+
 pub fn authentication_acl() -> bool {
     false
 }
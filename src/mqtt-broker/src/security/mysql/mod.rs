@@ -18,6 +18,7 @@ use metadata_struct::acl::mqtt_acl::{
     MqttAcl, MqttAclAction, MqttAclPermission, MqttAclResourceType,
 };
 use metadata_struct::acl::mqtt_blacklist::MqttAclBlackList;
+use metadata_struct::mqtt::psk::MqttPskIdentity;
 use metadata_struct::mqtt::user::MqttUser;
 use mysql::prelude::Queryable;
 use mysql::Pool;
@@ -49,6 +50,10 @@ impl MySQLAuthStorageAdapter {
     fn table_acl(&self) -> String {
         "mqtt_acl".to_string()
     }
+
+    fn table_psk(&self) -> String {
+        "mqtt_psk".to_string()
+    }
 }
 
 #[async_trait]
@@ -93,7 +98,7 @@ impl AuthStorageAdapter for MySQLAuthStorageAdapter {
     async fn save_user(&self, user_info: MqttUser) -> Result<(), MqttBrokerError> {
         let mut conn = self.pool.get_conn()?;
         let sql = format!(
-            "insert into {} ( `username`, `password`, `is_superuser`, `salt`) values ('{}', '{}', '{}', null);",
+            "replace into {} ( `username`, `password`, `is_superuser`, `salt`) values ('{}', '{}', '{}', null);",
             self.table_user(),
             user_info.username,
             user_info.password,
@@ -230,6 +235,43 @@ impl AuthStorageAdapter for MySQLAuthStorageAdapter {
     async fn delete_blacklist(&self, _blacklist: MqttAclBlackList) -> Result<(), MqttBrokerError> {
         return Ok(());
     }
+
+    async fn read_all_psk(&self) -> Result<Vec<MqttPskIdentity>, MqttBrokerError> {
+        let mut conn = self.pool.get_conn()?;
+        let sql = format!("select identity, psk from {}", self.table_psk());
+        let data: Vec<(String, String)> = conn.query(sql)?;
+        let mut results = Vec::new();
+        for raw in data {
+            results.push(MqttPskIdentity {
+                identity: raw.0,
+                psk: raw.1,
+            });
+        }
+        return Ok(results);
+    }
+
+    async fn save_psk(&self, psk: MqttPskIdentity) -> Result<(), MqttBrokerError> {
+        let mut conn = self.pool.get_conn()?;
+        let sql = format!(
+            "replace into {} (`identity`, `psk`) values ('{}', '{}');",
+            self.table_psk(),
+            psk.identity,
+            psk.psk,
+        );
+        let _data: Vec<(String, String)> = conn.query(sql)?;
+        return Ok(());
+    }
+
+    async fn delete_psk(&self, identity: String) -> Result<(), MqttBrokerError> {
+        let mut conn = self.pool.get_conn()?;
+        let sql = format!(
+            "delete from {} where identity = '{}';",
+            self.table_psk(),
+            identity
+        );
+        let _data: Vec<(String, String)> = conn.query(sql)?;
+        return Ok(());
+    }
 }
 
 #[cfg(test)]
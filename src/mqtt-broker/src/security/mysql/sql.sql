@@ -1,7 +1,7 @@
 CREATE TABLE `mqtt_user` (
 `id` int(11) unsigned NOT NULL AUTO_INCREMENT,
 `username` varchar(100) DEFAULT NULL,
-`password` varchar(100) DEFAULT NULL,
+`password` varchar(255) DEFAULT NULL,
 `salt` varchar(35) DEFAULT NULL,
 `is_superuser` tinyint(1) DEFAULT 0,
 `created` datetime DEFAULT NULL,
@@ -20,5 +20,13 @@ CREATE TABLE `mqtt_acl` (
 PRIMARY KEY (`id`)
 ) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
 
+CREATE TABLE `mqtt_psk` (
+`id` int(11) unsigned NOT NULL AUTO_INCREMENT,
+`identity` varchar(100) NOT NULL COMMENT 'PSK identity',
+`psk` varchar(256) NOT NULL COMMENT 'Hex encoded pre-shared key',
+PRIMARY KEY (`id`),
+UNIQUE KEY `mqtt_psk_identity` (`identity`)
+) ENGINE=InnoDB DEFAULT CHARSET=utf8mb4;
+
 INSERT INTO `mqtt_user` ( `username`, `password`, `salt`) VALUES
 ('robustmq', 'robustmq@2024', NULL);
\ No newline at end of file
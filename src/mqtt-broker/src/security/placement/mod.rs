@@ -19,12 +19,14 @@ use dashmap::DashMap;
 use grpc_clients::pool::ClientPool;
 use metadata_struct::acl::mqtt_acl::MqttAcl;
 use metadata_struct::acl::mqtt_blacklist::MqttAclBlackList;
+use metadata_struct::mqtt::psk::MqttPskIdentity;
 use metadata_struct::mqtt::user::MqttUser;
 
 use super::AuthStorageAdapter;
 use crate::handler::error::MqttBrokerError;
 use crate::storage::acl::AclStorage;
 use crate::storage::blacklist::BlackListStorage;
+use crate::storage::psk::PskStorage;
 use crate::storage::user::UserStorage;
 
 pub struct PlacementAuthStorageAdapter {
@@ -88,4 +90,19 @@ impl AuthStorageAdapter for PlacementAuthStorageAdapter {
         let blacklist_storage = BlackListStorage::new(self.client_pool.clone());
         return blacklist_storage.delete_blacklist(blacklist).await;
     }
+
+    async fn read_all_psk(&self) -> Result<Vec<MqttPskIdentity>, MqttBrokerError> {
+        let psk_storage = PskStorage::new(self.client_pool.clone());
+        return psk_storage.list_psk().await;
+    }
+
+    async fn save_psk(&self, psk: MqttPskIdentity) -> Result<(), MqttBrokerError> {
+        let psk_storage = PskStorage::new(self.client_pool.clone());
+        return psk_storage.save_psk(psk).await;
+    }
+
+    async fn delete_psk(&self, identity: String) -> Result<(), MqttBrokerError> {
+        let psk_storage = PskStorage::new(self.client_pool.clone());
+        return psk_storage.delete_psk(identity).await;
+    }
 }
@@ -11,3 +11,472 @@
 // WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
 // See the License for the specific language governing permissions and
 // limitations under the License.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use axum::async_trait;
+use common_base::config::common::AuthRedis;
+use dashmap::DashMap;
+use metadata_struct::acl::mqtt_acl::{MqttAcl, MqttAclResourceType};
+use metadata_struct::acl::mqtt_blacklist::MqttAclBlackList;
+use metadata_struct::mqtt::psk::MqttPskIdentity;
+use metadata_struct::mqtt::user::MqttUser;
+use redis::aio::ConnectionLike;
+use redis::{AsyncCommands, AsyncIter};
+use third_driver::redis::{build_redis_conn, RedisConnection};
+
+use super::login::password::{
+    encode_sha256_password, generate_salt, hash_password_blocking, is_hashed_password,
+    sha256_digest, PasswordHashAlgorithm, SaltPosition,
+};
+use super::AuthStorageAdapter;
+use crate::handler::error::MqttBrokerError;
+
+const DEFAULT_USER_KEY_PREFIX: &str = "mqtt_user:";
+const DEFAULT_ACL_KEY_PREFIX: &str = "mqtt_acl:";
+const DEFAULT_BLACKLIST_KEY: &str = "mqtt_blacklist";
+const DEFAULT_PSK_KEY: &str = "mqtt_psk";
+
+const FIELD_PASSWORD_HASH: &str = "password_hash";
+const FIELD_SALT: &str = "salt";
+const FIELD_IS_SUPERUSER: &str = "is_superuser";
+
+pub struct RedisAuthStorageAdapter<C = RedisConnection> {
+    conn: C,
+    conf: AuthRedis,
+    password_hash_algorithm: PasswordHashAlgorithm,
+    salt_position: SaltPosition,
+}
+
+impl RedisAuthStorageAdapter<RedisConnection> {
+    pub fn new(addr: String, conf: AuthRedis) -> Result<Self, MqttBrokerError> {
+        let conn = build_redis_conn(&addr)?;
+        RedisAuthStorageAdapter::with_connection(conn, conf)
+    }
+}
+
+impl<C> RedisAuthStorageAdapter<C>
+where
+    C: ConnectionLike + Clone + Send + Sync,
+{
+    pub fn with_connection(conn: C, conf: AuthRedis) -> Result<Self, MqttBrokerError> {
+        let password_hash_algorithm =
+            PasswordHashAlgorithm::from_str(&conf.password_hash_algorithm)?;
+        let salt_position = SaltPosition::from_str(&conf.salt_position)?;
+        Ok(RedisAuthStorageAdapter {
+            conn,
+            conf,
+            password_hash_algorithm,
+            salt_position,
+        })
+    }
+
+    // Commands are multiplexed over a shared connection, every caller works on its own handle.
+    fn conn(&self) -> C {
+        self.conn.clone()
+    }
+
+    fn key_user(&self, username: &str) -> String {
+        format!("{}{}", self.user_key_prefix(), username)
+    }
+
+    fn user_key_prefix(&self) -> &str {
+        or_default(&self.conf.user_key_prefix, DEFAULT_USER_KEY_PREFIX)
+    }
+
+    fn key_acl(&self, resource_type: &MqttAclResourceType, resource_name: &str) -> String {
+        format!(
+            "{}{}:{}",
+            self.acl_key_prefix(),
+            resource_type.to_string().to_lowercase(),
+            resource_name
+        )
+    }
+
+    fn acl_key_prefix(&self) -> &str {
+        or_default(&self.conf.acl_key_prefix, DEFAULT_ACL_KEY_PREFIX)
+    }
+
+    fn key_blacklist(&self) -> &str {
+        or_default(&self.conf.blacklist_key, DEFAULT_BLACKLIST_KEY)
+    }
+
+    fn key_psk(&self) -> &str {
+        or_default(&self.conf.psk_key, DEFAULT_PSK_KEY)
+    }
+
+    fn field_blacklist(&self, blacklist: &MqttAclBlackList) -> String {
+        format!("{}:{}", blacklist.blacklist_type, blacklist.resource_name)
+    }
+
+    async fn scan_keys(&self, conn: &mut C, prefix: &str) -> Result<Vec<String>, MqttBrokerError> {
+        let mut iter: AsyncIter<String> = conn.scan_match(format!("{}*", prefix)).await?;
+        let mut keys = Vec::new();
+        while let Some(key) = iter.next_item().await {
+            keys.push(key);
+        }
+        Ok(keys)
+    }
+
+    // Turns the fields of a user hash into a MqttUser whose password can be checked by verify_password.
+    fn decode_user(
+        &self,
+        username: String,
+        fields: HashMap<String, String>,
+    ) -> Result<Option<MqttUser>, MqttBrokerError> {
+        let password_hash = match fields.get(FIELD_PASSWORD_HASH) {
+            Some(data) => data.clone(),
+            None => return Ok(None),
+        };
+        let password = match self.password_hash_algorithm {
+            // Passwords hashed by the broker carry their own algorithm
+            _ if is_hashed_password(&password_hash) => password_hash,
+            PasswordHashAlgorithm::Sha256 => {
+                let salt = fields.get(FIELD_SALT).cloned().unwrap_or_default();
+                encode_sha256_password(self.salt_position, &salt, &password_hash)
+            }
+            PasswordHashAlgorithm::Plain => password_hash,
+            _ => {
+                return Err(MqttBrokerError::InvalidPasswordHashAlgorithm(format!(
+                    "user {} does not have a {:?} password hash",
+                    username, self.password_hash_algorithm
+                )));
+            }
+        };
+        let is_superuser = fields
+            .get(FIELD_IS_SUPERUSER)
+            .map(|flag| flag == "1" || flag == "true")
+            .unwrap_or(false);
+        Ok(Some(MqttUser {
+            username,
+            password,
+            is_superuser,
+        }))
+    }
+
+    // Returns the password_hash and salt fields stored for a password.
+    async fn encode_password(&self, password: &str) -> Result<(String, String), MqttBrokerError> {
+        if is_hashed_password(password) {
+            return Ok((password.to_string(), String::new()));
+        }
+        match self.password_hash_algorithm {
+            PasswordHashAlgorithm::Sha256 => {
+                let salt = generate_salt();
+                let digest = sha256_digest(self.salt_position, &salt, password);
+                Ok((digest, salt))
+            }
+            algorithm => Ok((
+                hash_password_blocking(algorithm, self.salt_position, password).await?,
+                String::new(),
+            )),
+        }
+    }
+}
+
+fn or_default<'a>(value: &'a str, default: &'a str) -> &'a str {
+    if value.is_empty() {
+        default
+    } else {
+        value
+    }
+}
+
+#[async_trait]
+impl<C> AuthStorageAdapter for RedisAuthStorageAdapter<C>
+where
+    C: ConnectionLike + Clone + Send + Sync,
+{
+    async fn read_all_user(&self) -> Result<DashMap<String, MqttUser>, MqttBrokerError> {
+        let mut conn = self.conn();
+        let prefix = self.user_key_prefix().to_string();
+        let results = DashMap::with_capacity(2);
+        for key in self.scan_keys(&mut conn, &prefix).await? {
+            let fields: HashMap<String, String> = conn.hgetall(&key).await?;
+            let username = key[prefix.len()..].to_string();
+            if let Some(user) = self.decode_user(username.clone(), fields)? {
+                results.insert(username, user);
+            }
+        }
+        Ok(results)
+    }
+
+    async fn get_user(&self, username: String) -> Result<Option<MqttUser>, MqttBrokerError> {
+        let mut conn = self.conn();
+        let fields: HashMap<String, String> = conn.hgetall(self.key_user(&username)).await?;
+        self.decode_user(username, fields)
+    }
+
+    async fn save_user(&self, user_info: MqttUser) -> Result<(), MqttBrokerError> {
+        let (password_hash, salt) = self.encode_password(&user_info.password).await?;
+        let mut conn = self.conn();
+        let _: () = conn
+            .hset_multiple(
+                self.key_user(&user_info.username),
+                &[
+                    (FIELD_PASSWORD_HASH, password_hash),
+                    (FIELD_SALT, salt),
+                    (
+                        FIELD_IS_SUPERUSER,
+                        (user_info.is_superuser as u8).to_string(),
+                    ),
+                ],
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_user(&self, username: String) -> Result<(), MqttBrokerError> {
+        let mut conn = self.conn();
+        let _: () = conn.del(self.key_user(&username)).await?;
+        Ok(())
+    }
+
+    async fn read_all_acl(&self) -> Result<Vec<MqttAcl>, MqttBrokerError> {
+        let mut conn = self.conn();
+        let prefix = self.acl_key_prefix().to_string();
+        let mut results = Vec::new();
+        for key in self.scan_keys(&mut conn, &prefix).await? {
+            let members: Vec<String> = conn.smembers(&key).await?;
+            for member in members {
+                results.push(MqttAcl::decode(member.as_bytes())?);
+            }
+        }
+        Ok(results)
+    }
+
+    async fn save_acl(&self, acl: MqttAcl) -> Result<(), MqttBrokerError> {
+        let key = self.key_acl(&acl.resource_type, &acl.resource_name);
+        let member = String::from_utf8(acl.encode()?)?;
+        let mut conn = self.conn();
+        let _: () = conn.sadd(key, member).await?;
+        Ok(())
+    }
+
+    async fn delete_acl(&self, acl: MqttAcl) -> Result<(), MqttBrokerError> {
+        let key = self.key_acl(&acl.resource_type, &acl.resource_name);
+        let member = String::from_utf8(acl.encode()?)?;
+        let mut conn = self.conn();
+        let _: () = conn.srem(key, member).await?;
+        Ok(())
+    }
+
+    async fn read_all_blacklist(&self) -> Result<Vec<MqttAclBlackList>, MqttBrokerError> {
+        let mut conn = self.conn();
+        let values: HashMap<String, String> = conn.hgetall(self.key_blacklist()).await?;
+        let mut results = Vec::new();
+        for value in values.values() {
+            results.push(MqttAclBlackList::decode(value.as_bytes())?);
+        }
+        Ok(results)
+    }
+
+    async fn save_blacklist(&self, blacklist: MqttAclBlackList) -> Result<(), MqttBrokerError> {
+        let field = self.field_blacklist(&blacklist);
+        let value = String::from_utf8(blacklist.encode()?)?;
+        let mut conn = self.conn();
+        let _: () = conn.hset(self.key_blacklist(), field, value).await?;
+        Ok(())
+    }
+
+    async fn delete_blacklist(&self, blacklist: MqttAclBlackList) -> Result<(), MqttBrokerError> {
+        let field = self.field_blacklist(&blacklist);
+        let mut conn = self.conn();
+        let _: () = conn.hdel(self.key_blacklist(), field).await?;
+        Ok(())
+    }
+
+    async fn read_all_psk(&self) -> Result<Vec<MqttPskIdentity>, MqttBrokerError> {
+        let mut conn = self.conn();
+        let values: HashMap<String, String> = conn.hgetall(self.key_psk()).await?;
+        Ok(values
+            .into_iter()
+            .map(|(identity, psk)| MqttPskIdentity { identity, psk })
+            .collect())
+    }
+
+    async fn save_psk(&self, psk: MqttPskIdentity) -> Result<(), MqttBrokerError> {
+        let mut conn = self.conn();
+        let _: () = conn.hset(self.key_psk(), psk.identity, psk.psk).await?;
+        Ok(())
+    }
+
+    async fn delete_psk(&self, identity: String) -> Result<(), MqttBrokerError> {
+        let mut conn = self.conn();
+        let _: () = conn.hdel(self.key_psk(), identity).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use common_base::config::common::AuthRedis;
+    use metadata_struct::acl::mqtt_acl::{
+        MqttAcl, MqttAclAction, MqttAclPermission, MqttAclResourceType,
+    };
+    use metadata_struct::acl::mqtt_blacklist::{MqttAclBlackList, MqttAclBlackListType};
+    use metadata_struct::mqtt::user::MqttUser;
+    use redis::{cmd, Value};
+    use redis_test::{MockCmd, MockRedisConnection};
+
+    use super::RedisAuthStorageAdapter;
+    use crate::security::login::password::{sha256_digest, verify_password, SaltPosition};
+    use crate::security::AuthStorageAdapter;
+
+    fn bulk(data: &str) -> Value {
+        Value::BulkString(data.as_bytes().to_vec())
+    }
+
+    #[tokio::test]
+    async fn read_all_user_test() {
+        let digest = sha256_digest(SaltPosition::Suffix, "s1", "robustmq@2024");
+        let conn = MockRedisConnection::new(vec![
+            MockCmd::new(
+                cmd("SCAN").arg(0).arg("MATCH").arg("users:*"),
+                Ok(Value::Array(vec![
+                    bulk("0"),
+                    Value::Array(vec![bulk("users:robustmq")]),
+                ])),
+            ),
+            MockCmd::new(
+                cmd("HGETALL").arg("users:robustmq"),
+                Ok(Value::Array(vec![
+                    bulk("password_hash"),
+                    bulk(&digest),
+                    bulk("salt"),
+                    bulk("s1"),
+                    bulk("is_superuser"),
+                    bulk("1"),
+                ])),
+            ),
+        ]);
+        let conf = AuthRedis {
+            user_key_prefix: "users:".to_string(),
+            password_hash_algorithm: "sha256".to_string(),
+            salt_position: "suffix".to_string(),
+            ..Default::default()
+        };
+        let adapter = RedisAuthStorageAdapter::with_connection(conn, conf).unwrap();
+        let users = adapter.read_all_user().await.unwrap();
+        let user = users.get("robustmq").unwrap();
+        assert!(user.is_superuser);
+        assert!(verify_password(&user.password, "robustmq@2024").unwrap());
+        assert!(!verify_password(&user.password, "robustmq").unwrap());
+    }
+
+    #[tokio::test]
+    async fn user_test() {
+        let conn = MockRedisConnection::new(vec![
+            MockCmd::new(
+                cmd("HMSET")
+                    .arg("mqtt_user:robustmq")
+                    .arg("password_hash")
+                    .arg("robustmq@2024")
+                    .arg("salt")
+                    .arg("")
+                    .arg("is_superuser")
+                    .arg("0"),
+                Ok(Value::Okay),
+            ),
+            MockCmd::new(
+                cmd("HGETALL").arg("mqtt_user:robustmq"),
+                Ok(Value::Array(vec![
+                    bulk("password_hash"),
+                    bulk("robustmq@2024"),
+                    bulk("is_superuser"),
+                    bulk("0"),
+                ])),
+            ),
+            MockCmd::new(cmd("DEL").arg("mqtt_user:robustmq"), Ok(Value::Int(1))),
+            MockCmd::new(
+                cmd("HGETALL").arg("mqtt_user:robustmq"),
+                Ok(Value::Array(vec![])),
+            ),
+        ]);
+        let adapter = RedisAuthStorageAdapter::with_connection(conn, AuthRedis::default()).unwrap();
+        let user = MqttUser {
+            username: "robustmq".to_string(),
+            password: "robustmq@2024".to_string(),
+            is_superuser: false,
+        };
+        adapter.save_user(user.clone()).await.unwrap();
+        let res = adapter.get_user("robustmq".to_string()).await.unwrap();
+        assert_eq!(res, Some(user));
+        adapter.delete_user("robustmq".to_string()).await.unwrap();
+        let res = adapter.get_user("robustmq".to_string()).await.unwrap();
+        assert!(res.is_none());
+    }
+
+    #[tokio::test]
+    async fn acl_test() {
+        let acl = MqttAcl {
+            resource_type: MqttAclResourceType::User,
+            resource_name: "robustmq".to_string(),
+            topic: "tp-1".to_string(),
+            ip: "*".to_string(),
+            action: MqttAclAction::Publish,
+            permission: MqttAclPermission::Deny,
+        };
+        let member = String::from_utf8(acl.encode().unwrap()).unwrap();
+        let conn = MockRedisConnection::new(vec![
+            MockCmd::new(
+                cmd("SADD").arg("mqtt_acl:user:robustmq").arg(&member),
+                Ok(Value::Int(1)),
+            ),
+            MockCmd::new(
+                cmd("SCAN").arg(0).arg("MATCH").arg("mqtt_acl:*"),
+                Ok(Value::Array(vec![
+                    bulk("0"),
+                    Value::Array(vec![bulk("mqtt_acl:user:robustmq")]),
+                ])),
+            ),
+            MockCmd::new(
+                cmd("SMEMBERS").arg("mqtt_acl:user:robustmq"),
+                Ok(Value::Array(vec![bulk(&member)])),
+            ),
+            MockCmd::new(
+                cmd("SREM").arg("mqtt_acl:user:robustmq").arg(&member),
+                Ok(Value::Int(1)),
+            ),
+        ]);
+        let adapter = RedisAuthStorageAdapter::with_connection(conn, AuthRedis::default()).unwrap();
+        adapter.save_acl(acl.clone()).await.unwrap();
+        assert_eq!(adapter.read_all_acl().await.unwrap(), vec![acl.clone()]);
+        adapter.delete_acl(acl).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn blacklist_test() {
+        let blacklist = MqttAclBlackList {
+            blacklist_type: MqttAclBlackListType::ClientId,
+            resource_name: "client-1".to_string(),
+            end_time: 1000,
+            desc: "".to_string(),
+        };
+        let value = String::from_utf8(blacklist.encode().unwrap()).unwrap();
+        let conn = MockRedisConnection::new(vec![
+            MockCmd::new(
+                cmd("HSET")
+                    .arg("mqtt_blacklist")
+                    .arg("ClientId:client-1")
+                    .arg(&value),
+                Ok(Value::Int(1)),
+            ),
+            MockCmd::new(
+                cmd("HGETALL").arg("mqtt_blacklist"),
+                Ok(Value::Array(vec![bulk("ClientId:client-1"), bulk(&value)])),
+            ),
+            MockCmd::new(
+                cmd("HDEL").arg("mqtt_blacklist").arg("ClientId:client-1"),
+                Ok(Value::Int(1)),
+            ),
+        ]);
+        let adapter = RedisAuthStorageAdapter::with_connection(conn, AuthRedis::default()).unwrap();
+        adapter.save_blacklist(blacklist.clone()).await.unwrap();
+        assert_eq!(
+            adapter.read_all_blacklist().await.unwrap(),
+            vec![blacklist.clone()]
+        );
+        adapter.delete_blacklist(blacklist).await.unwrap();
+    }
+}
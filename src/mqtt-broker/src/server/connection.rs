@@ -20,6 +20,9 @@ use log::error;
 use protocol::mqtt::common::MqttProtocol;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
+
+use crate::security::login::x509::X509Identity;
+
 static CONNECTION_ID_BUILD: AtomicU64 = AtomicU64::new(1);
 
 #[derive(Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
@@ -28,6 +31,7 @@ pub enum NetworkConnectionType {
     Tls,
     WebSocket,
     WebSockets,
+    Quic,
 }
 
 impl fmt::Display for NetworkConnectionType {
@@ -40,6 +44,7 @@ impl fmt::Display for NetworkConnectionType {
                 NetworkConnectionType::Tls => "tls",
                 NetworkConnectionType::WebSocket => "websocket",
                 NetworkConnectionType::WebSockets => "websockets",
+                NetworkConnectionType::Quic => "quic",
             }
         )
     }
@@ -51,6 +56,8 @@ pub struct NetworkConnection {
     pub connection_id: u64,
     pub protocol: Option<MqttProtocol>,
     pub addr: SocketAddr,
+    pub peer_identity: Option<X509Identity>,
+    pub psk_identity: Option<String>,
     #[serde(skip_serializing, skip_deserializing)]
     pub connection_stop_sx: Option<mpsc::Sender<bool>>,
 }
@@ -67,6 +74,8 @@ impl NetworkConnection {
             connection_id,
             protocol: None,
             addr,
+            peer_identity: None,
+            psk_identity: None,
             connection_stop_sx,
         }
     }
@@ -79,6 +88,14 @@ impl NetworkConnection {
         self.protocol = Some(protocol);
     }
 
+    pub fn set_peer_identity(&mut self, identity: Option<X509Identity>) {
+        self.peer_identity = identity;
+    }
+
+    pub fn set_psk_identity(&mut self, identity: Option<String>) {
+        self.psk_identity = identity;
+    }
+
     pub fn is_mqtt3(&self) -> bool {
         if let Some(protocol) = self.protocol.clone() {
             return protocol == MqttProtocol::Mqtt3;
@@ -103,6 +120,7 @@ impl NetworkConnection {
     pub fn is_tcp(&self) -> bool {
         self.connection_type == NetworkConnectionType::Tcp
             || self.connection_type == NetworkConnectionType::Tls
+            || self.connection_type == NetworkConnectionType::Quic
     }
 
     pub async fn stop_connection(&self) {
@@ -21,11 +21,14 @@ use futures::stream::SplitSink;
 use futures::SinkExt;
 use log::{error, info};
 use protocol::mqtt::codec::{MqttCodec, MqttPacketWrapper};
-use protocol::mqtt::common::MqttProtocol;
+use protocol::mqtt::common::{MqttPacket, MqttProtocol};
+use quinn::SendStream;
+use tokio::sync::oneshot;
 use tokio::time::sleep;
 use tokio_util::codec::FramedWrite;
 
 use super::connection::{NetworkConnection, NetworkConnectionType};
+use super::tcp::tls_server::TlsIo;
 use crate::handler::cache::CacheManager;
 use crate::handler::error::MqttBrokerError;
 use crate::observability::metrics::packets::record_sent_metrics;
@@ -34,14 +37,11 @@ pub struct ConnectionManager {
     connections: DashMap<u64, NetworkConnection>,
     tcp_write_list:
         DashMap<u64, FramedWrite<tokio::io::WriteHalf<tokio::net::TcpStream>, MqttCodec>>,
-    tcp_tls_write_list: DashMap<
-        u64,
-        FramedWrite<
-            tokio::io::WriteHalf<tokio_rustls::server::TlsStream<tokio::net::TcpStream>>,
-            MqttCodec,
-        >,
-    >,
+    tcp_tls_write_list: DashMap<u64, FramedWrite<tokio::io::WriteHalf<Box<dyn TlsIo>>, MqttCodec>>,
     websocket_write_list: DashMap<u64, SplitSink<WebSocket, Message>>,
+    quic_write_list: DashMap<u64, FramedWrite<SendStream, MqttCodec>>,
+    // notified once the CONNACK of the connection has been written
+    connack_waiters: DashMap<u64, oneshot::Sender<()>>,
     cache_manager: Arc<CacheManager>,
 }
 
@@ -51,12 +51,15 @@ impl ConnectionManager {
         let tcp_write_list = DashMap::with_capacity(64);
         let tcp_tls_write_list = DashMap::with_capacity(64);
         let websocket_write_list = DashMap::with_capacity(64);
+        let quic_write_list = DashMap::with_capacity(64);
         ConnectionManager {
             connections,
             tcp_write_list,
             tcp_tls_write_list,
             cache_manager,
             websocket_write_list,
+            quic_write_list,
+            connack_waiters: DashMap::with_capacity(64),
         }
     }
 
@@ -80,10 +83,7 @@ impl ConnectionManager {
     pub fn add_tcp_tls_write(
         &self,
         connection_id: u64,
-        write: FramedWrite<
-            tokio::io::WriteHalf<tokio_rustls::server::TlsStream<tokio::net::TcpStream>>,
-            MqttCodec,
-        >,
+        write: FramedWrite<tokio::io::WriteHalf<Box<dyn TlsIo>>, MqttCodec>,
     ) {
         self.tcp_tls_write_list.insert(connection_id, write);
     }
@@ -92,6 +92,10 @@ impl ConnectionManager {
         self.websocket_write_list.insert(connection_id, write);
     }
 
+    pub fn add_quic_write(&self, connection_id: u64, write: FramedWrite<SendStream, MqttCodec>) {
+        self.quic_write_list.insert(connection_id, write);
+    }
+
     pub async fn close_all_connect(&self) {
         for (connect_id, _) in self.connections.clone() {
             self.close_connect(connect_id).await;
@@ -102,6 +106,7 @@ impl ConnectionManager {
         if let Some((_, connection)) = self.connections.remove(&connection_id) {
             connection.stop_connection().await;
         }
+        self.connack_waiters.remove(&connection_id);
 
         if let Some((id, mut stream)) = self.tcp_write_list.remove(&connection_id) {
             match stream.close().await {
@@ -138,6 +143,18 @@ impl ConnectionManager {
                 Err(e) => error!("{}", e),
             }
         }
+
+        if let Some((id, mut stream)) = self.quic_write_list.remove(&connection_id) {
+            match stream.close().await {
+                Ok(_) => {
+                    info!(
+                        "server closes the quic connection actively, connection id [{}]",
+                        id
+                    );
+                }
+                Err(e) => error!("{}", e),
+            }
+        }
     }
 
     pub async fn write_websocket_frame(
@@ -161,6 +178,7 @@ impl ConnectionManager {
                                 };
 
                             record_sent_metrics(&packet_wrapper, network_type);
+                            self.notify_packet_written(connection_id, &packet_wrapper.packet);
                             break;
                         }
                         Err(e) => {
@@ -211,6 +229,9 @@ impl ConnectionManager {
             if connection.connection_type == NetworkConnectionType::Tls {
                 return self.write_tcp_tls_frame(connection_id, resp).await;
             }
+            if connection.connection_type == NetworkConnectionType::Quic {
+                return self.write_quic_frame(connection_id, resp).await;
+            }
         }
 
         let mut times = 0;
@@ -229,6 +250,7 @@ impl ConnectionManager {
                                 };
 
                             record_sent_metrics(&resp, network_type);
+                            self.notify_packet_written(connection_id, &resp.packet);
                             break;
                         }
                         Err(e) => {
@@ -290,6 +312,7 @@ impl ConnectionManager {
                                 };
 
                             record_sent_metrics(&resp, network_type);
+                            self.notify_packet_written(connection_id, &resp.packet);
                             break;
                         }
                         Err(e) => {
@@ -331,6 +354,78 @@ impl ConnectionManager {
         Ok(())
     }
 
+    async fn write_quic_frame(
+        &self,
+        connection_id: u64,
+        resp: MqttPacketWrapper,
+    ) -> Result<(), MqttBrokerError> {
+        let mut times = 0;
+        let cluster = self.cache_manager.get_cluster_info();
+        loop {
+            match self.quic_write_list.try_get_mut(&connection_id) {
+                dashmap::try_result::TryResult::Present(mut da) => {
+                    match da.send(resp.clone()).await {
+                        Ok(_) => {
+                            record_sent_metrics(&resp, NetworkConnectionType::Quic.to_string());
+                            self.notify_packet_written(connection_id, &resp.packet);
+                            break;
+                        }
+                        Err(e) => {
+                            if times > cluster.network.response_max_try_mut_times {
+                                return Err(MqttBrokerError::CommonError(format!(
+                                    "Failed to write data to the mqtt quic client, error message: {e:?}"
+                                )));
+                            }
+                        }
+                    }
+                }
+                dashmap::try_result::TryResult::Absent => {
+                    if times > cluster.network.response_max_try_mut_times {
+                        return Err(MqttBrokerError::CommonError(
+                            format!(
+                                "[write_frame]Connection management could not obtain an available quic connection. Connection ID: {},len:{}",
+                                connection_id,
+                                self.quic_write_list.len()
+                            )
+                        ));
+                    }
+                }
+                dashmap::try_result::TryResult::Locked => {
+                    if times > cluster.network.response_max_try_mut_times {
+                        return Err(MqttBrokerError::CommonError(
+                            format!(
+                                "[write_frame]Connection management failed to get quic connection variable reference, connection ID: {}",connection_id
+                            )
+                        ));
+                    }
+                }
+            }
+            times += 1;
+            sleep(Duration::from_millis(
+                cluster.network.response_try_mut_sleep_time_ms,
+            ))
+            .await
+        }
+        Ok(())
+    }
+
+    /// Resolves once the CONNACK of the connection has been written, packets that are sent
+    /// to the client on their own must not go out before it. The sender is dropped when the
+    /// connection closes first.
+    pub fn wait_connack_written(&self, connection_id: u64) -> oneshot::Receiver<()> {
+        let (sx, rx) = oneshot::channel();
+        self.connack_waiters.insert(connection_id, sx);
+        rx
+    }
+
+    fn notify_packet_written(&self, connection_id: u64, packet: &MqttPacket) {
+        if let MqttPacket::ConnAck(_, _) = packet {
+            if let Some((_, sx)) = self.connack_waiters.remove(&connection_id) {
+                let _ = sx.send(());
+            }
+        }
+    }
+
     pub fn tcp_connect_num_check(&self) -> bool {
         let cluster = self.cache_manager.get_cluster_info();
         if self.connections.len() >= cluster.network.tcp_max_connection_num as usize {
@@ -12,54 +12,85 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use common_base::config::broker_mqtt::broker_mqtt_conf;
-use common_base::tools::serialize_value;
+use common_base::tools::{now_second, serialize_value};
 use grpc_clients::pool::ClientPool;
+use log::warn;
 use metadata_struct::acl::mqtt_acl::MqttAcl;
 use metadata_struct::acl::mqtt_blacklist::{MqttAclBlackList, MqttAclBlackListType};
+use metadata_struct::mqtt::cluster::MqttAutoSubscribeRule;
+use metadata_struct::mqtt::psk::MqttPskIdentity;
+use metadata_struct::mqtt::rule::{MqttRule, MqttRuleAction};
+use metadata_struct::mqtt::topic_rewrite_rule::{MqttTopicRewriteAction, MqttTopicRewriteRule};
 use metadata_struct::mqtt::user::MqttUser;
 use protocol::broker_mqtt::broker_mqtt_admin::mqtt_broker_admin_service_server::MqttBrokerAdminService;
 use protocol::broker_mqtt::broker_mqtt_admin::{
-    ClusterStatusReply, ClusterStatusRequest, CreateAclReply, CreateAclRequest,
-    CreateBlacklistReply, CreateBlacklistRequest, CreateUserReply, CreateUserRequest,
-    DeleteAclReply, DeleteAclRequest, DeleteBlacklistReply, DeleteBlacklistRequest,
-    DeleteUserReply, DeleteUserRequest, EnableSlowSubScribeReply, EnableSlowSubscribeRequest,
-    ListAclReply, ListAclRequest, ListBlacklistReply, ListBlacklistRequest, ListConnectionRaw,
-    ListConnectionReply, ListConnectionRequest, ListSlowSubScribeRaw, ListSlowSubscribeReply,
-    ListSlowSubscribeRequest, ListTopicReply, ListTopicRequest, ListUserReply, ListUserRequest,
-    MqttTopic,
+    AutoSubscribeRuleRaw, CancelDelayPublishReply, CancelDelayPublishRequest, ClusterStatusReply,
+    ClusterStatusRequest, CreateAclReply, CreateAclRequest, CreateBlacklistReply,
+    CreateBlacklistRequest, CreatePskReply, CreatePskRequest, CreateRuleReply, CreateRuleRequest,
+    CreateTopicRewriteRuleReply, CreateTopicRewriteRuleRequest, CreateUserReply, CreateUserRequest,
+    DelayPublishMessageRaw, DeleteAclReply, DeleteAclRequest, DeleteAutoSubscribeRuleReply,
+    DeleteAutoSubscribeRuleRequest, DeleteBlacklistReply, DeleteBlacklistRequest, DeletePskReply,
+    DeletePskRequest, DeleteRuleReply, DeleteRuleRequest, DeleteTopicRewriteRuleReply,
+    DeleteTopicRewriteRuleRequest, DeleteUserReply, DeleteUserRequest, EnableSlowSubScribeReply,
+    EnableSlowSubscribeRequest, ListAclReply, ListAclRequest, ListAutoSubscribeRuleReply,
+    ListAutoSubscribeRuleRequest, ListBlacklistReply, ListBlacklistRequest, ListConnectionRaw,
+    ListConnectionReply, ListConnectionRequest, ListDelayPublishReply, ListDelayPublishRequest,
+    ListPskReply, ListPskRequest, ListRuleReply, ListRuleRequest, ListSlowSubScribeRaw,
+    ListSlowSubscribeReply, ListSlowSubscribeRequest, ListTopicReply, ListTopicRequest,
+    ListTopicRewriteRuleReply, ListTopicRewriteRuleRequest, ListUserReply, ListUserRequest,
+    MqttTopic, RuleRaw, SetAutoSubscribeRuleReply, SetAutoSubscribeRuleRequest,
+    TopicRewriteRuleRaw,
 };
+use protocol::mqtt::common::{qos, RetainForwardRule};
+use storage_adapter::storage::{ShardConfig, StorageAdapter};
 use tonic::{Request, Response, Status};
 
 use crate::handler::cache::CacheManager;
+use crate::handler::delay_publish::{cancel_delay_publish_message, list_delay_publish_messages};
+use crate::handler::error::MqttBrokerError;
+use crate::handler::topic_rewrite::TopicRewriteRule;
+use crate::rule_engine::sql::RuleSql;
+use crate::rule_engine::validate_rule_actions;
 use crate::security::AuthDriver;
 use crate::server::connection_manager::ConnectionManager;
 use crate::storage::cluster::ClusterStorage;
+use crate::storage::message::cluster_name;
+use crate::storage::rule::RuleStorage;
+use crate::storage::topic_rewrite_rule::TopicRewriteRuleStorage;
+use crate::subscribe::sub_common::sub_path_validator;
 
-pub struct GrpcAdminServices {
+pub struct GrpcAdminServices<S> {
     client_pool: Arc<ClientPool>,
     cache_manager: Arc<CacheManager>,
     connection_manager: Arc<ConnectionManager>,
+    message_storage_adapter: Arc<S>,
 }
 
-impl GrpcAdminServices {
+impl<S> GrpcAdminServices<S> {
     pub fn new(
         client_pool: Arc<ClientPool>,
         cache_manager: Arc<CacheManager>,
         connection_manager: Arc<ConnectionManager>,
+        message_storage_adapter: Arc<S>,
     ) -> Self {
         GrpcAdminServices {
             client_pool,
             cache_manager,
             connection_manager,
+            message_storage_adapter,
         }
     }
 }
 
 #[tonic::async_trait]
-impl MqttBrokerAdminService for GrpcAdminServices {
+impl<S> MqttBrokerAdminService for GrpcAdminServices<S>
+where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
     // --- cluster ---
     async fn cluster_status(
         &self,
@@ -270,6 +301,54 @@ impl MqttBrokerAdminService for GrpcAdminServices {
         }
     }
 
+    async fn mqtt_broker_list_psk(
+        &self,
+        _: Request<ListPskRequest>,
+    ) -> Result<Response<ListPskReply>, Status> {
+        let auth_driver = AuthDriver::new(self.cache_manager.clone(), self.client_pool.clone());
+        match auth_driver.read_all_psk().await {
+            Ok(data) => {
+                let identities = data.into_iter().map(|psk| psk.identity).collect();
+                Ok(Response::new(ListPskReply { identities }))
+            }
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn mqtt_broker_create_psk(
+        &self,
+        request: Request<CreatePskRequest>,
+    ) -> Result<Response<CreatePskReply>, Status> {
+        let req = request.into_inner();
+        if req.identity.is_empty() {
+            return Err(Status::invalid_argument(
+                "Psk identity cannot be empty".to_string(),
+            ));
+        }
+        let psk = MqttPskIdentity {
+            identity: req.identity,
+            psk: req.psk,
+        };
+
+        let auth_driver = AuthDriver::new(self.cache_manager.clone(), self.client_pool.clone());
+        match auth_driver.save_psk(psk).await {
+            Ok(_) => Ok(Response::new(CreatePskReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn mqtt_broker_delete_psk(
+        &self,
+        request: Request<DeletePskRequest>,
+    ) -> Result<Response<DeletePskReply>, Status> {
+        let req = request.into_inner();
+        let auth_driver = AuthDriver::new(self.cache_manager.clone(), self.client_pool.clone());
+        match auth_driver.delete_psk(req.identity).await {
+            Ok(_) => Ok(Response::new(DeletePskReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
     // --- connection ---
     async fn mqtt_broker_list_connection(
         &self,
@@ -369,4 +448,324 @@ impl MqttBrokerAdminService for GrpcAdminServices {
 
         return Ok(Response::new(reply));
     }
+
+    // --- delayed publish ---
+    async fn mqtt_broker_list_delay_publish(
+        &self,
+        _: Request<ListDelayPublishRequest>,
+    ) -> Result<Response<ListDelayPublishReply>, Status> {
+        let messages = list_delay_publish_messages(&self.cache_manager)
+            .into_iter()
+            .map(|message| DelayPublishMessageRaw {
+                id: message.id,
+                client_id: message.client_id,
+                topic_name: message.topic_name,
+                delay_secs: message.delay_secs,
+                deliver_time: message.deliver_time,
+                create_time: message.create_time,
+            })
+            .collect();
+        Ok(Response::new(ListDelayPublishReply { messages }))
+    }
+
+    async fn mqtt_broker_cancel_delay_publish(
+        &self,
+        request: Request<CancelDelayPublishRequest>,
+    ) -> Result<Response<CancelDelayPublishReply>, Status> {
+        let req = request.into_inner();
+        match cancel_delay_publish_message(
+            &self.cache_manager,
+            &self.message_storage_adapter,
+            &req.id,
+        )
+        .await
+        {
+            Ok(_) => Ok(Response::new(CancelDelayPublishReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    // --- topic rewrite ---
+    async fn mqtt_broker_list_topic_rewrite_rule(
+        &self,
+        _: Request<ListTopicRewriteRuleRequest>,
+    ) -> Result<Response<ListTopicRewriteRuleReply>, Status> {
+        let rules = self
+            .cache_manager
+            .get_topic_rewrite_rules()
+            .into_iter()
+            .map(|TopicRewriteRule { rule, .. }| TopicRewriteRuleRaw {
+                action: rule.action.to_string(),
+                source_topic: rule.source_topic,
+                dest_topic: rule.dest_topic,
+                regex: rule.regex,
+                create_time: rule.create_time,
+            })
+            .collect();
+        Ok(Response::new(ListTopicRewriteRuleReply { rules }))
+    }
+
+    async fn mqtt_broker_create_topic_rewrite_rule(
+        &self,
+        request: Request<CreateTopicRewriteRuleRequest>,
+    ) -> Result<Response<CreateTopicRewriteRuleReply>, Status> {
+        let req = request.into_inner();
+        let action = match MqttTopicRewriteAction::parse(&req.action) {
+            Ok(action) => action,
+            Err(e) => return Err(Status::invalid_argument(e.to_string())),
+        };
+        if !sub_path_validator(req.source_topic.clone()) {
+            return Err(Status::invalid_argument(format!(
+                "Source topic {} is not a valid topic filter",
+                req.source_topic
+            )));
+        }
+        if req.dest_topic.is_empty() {
+            return Err(Status::invalid_argument(
+                "Destination topic cannot be empty".to_string(),
+            ));
+        }
+        let rule = match TopicRewriteRule::new(MqttTopicRewriteRule {
+            action,
+            source_topic: req.source_topic,
+            dest_topic: req.dest_topic,
+            regex: req.regex,
+            create_time: now_second(),
+        }) {
+            Ok(rule) => rule,
+            Err(e) => return Err(Status::invalid_argument(e.to_string())),
+        };
+
+        let storage = TopicRewriteRuleStorage::new(self.client_pool.clone());
+        match storage.save_topic_rewrite_rule(rule.rule.clone()).await {
+            Ok(_) => {
+                self.cache_manager.add_topic_rewrite_rule(rule);
+                Ok(Response::new(CreateTopicRewriteRuleReply::default()))
+            }
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn mqtt_broker_delete_topic_rewrite_rule(
+        &self,
+        request: Request<DeleteTopicRewriteRuleRequest>,
+    ) -> Result<Response<DeleteTopicRewriteRuleReply>, Status> {
+        let req = request.into_inner();
+        let action = match MqttTopicRewriteAction::parse(&req.action) {
+            Ok(action) => action,
+            Err(e) => return Err(Status::invalid_argument(e.to_string())),
+        };
+
+        let storage = TopicRewriteRuleStorage::new(self.client_pool.clone());
+        match storage
+            .delete_topic_rewrite_rule(action.to_string(), req.source_topic.clone())
+            .await
+        {
+            Ok(_) => {
+                self.cache_manager
+                    .remove_topic_rewrite_rule(action, &req.source_topic);
+                Ok(Response::new(DeleteTopicRewriteRuleReply::default()))
+            }
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn mqtt_broker_list_auto_subscribe_rule(
+        &self,
+        _: Request<ListAutoSubscribeRuleRequest>,
+    ) -> Result<Response<ListAutoSubscribeRuleReply>, Status> {
+        let rules = self
+            .cache_manager
+            .get_auto_subscribe_rules()
+            .into_iter()
+            .map(|rule| AutoSubscribeRuleRaw {
+                topic: rule.topic,
+                qos: rule.qos as u32,
+                no_local: rule.no_local,
+                retain_as_published: rule.retain_as_published,
+                retained_handling: u8::from(rule.retained_handling) as u32,
+            })
+            .collect();
+        Ok(Response::new(ListAutoSubscribeRuleReply { rules }))
+    }
+
+    async fn mqtt_broker_set_auto_subscribe_rule(
+        &self,
+        request: Request<SetAutoSubscribeRuleRequest>,
+    ) -> Result<Response<SetAutoSubscribeRuleReply>, Status> {
+        let req = request.into_inner();
+        if req.topic.is_empty() {
+            return Err(Status::invalid_argument(
+                "Auto subscribe topic cannot be empty".to_string(),
+            ));
+        }
+        let qos = if let Some(qos) = u8::try_from(req.qos).ok().and_then(qos) {
+            qos
+        } else {
+            return Err(Status::invalid_argument(format!(
+                "QoS {} is not valid, it must be 0, 1 or 2",
+                req.qos
+            )));
+        };
+        let retained_handling = match req.retained_handling {
+            0 => RetainForwardRule::OnEverySubscribe,
+            1 => RetainForwardRule::OnNewSubscribe,
+            2 => RetainForwardRule::Never,
+            _ => {
+                return Err(Status::invalid_argument(format!(
+                    "Retained handling {} is not valid, it must be 0, 1 or 2",
+                    req.retained_handling
+                )));
+            }
+        };
+
+        let rule = MqttAutoSubscribeRule {
+            topic: req.topic,
+            qos,
+            no_local: req.no_local,
+            retain_as_published: req.retain_as_published,
+            retained_handling,
+        };
+        match self.cache_manager.set_auto_subscribe_rule(rule).await {
+            Ok(_) => Ok(Response::new(SetAutoSubscribeRuleReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn mqtt_broker_delete_auto_subscribe_rule(
+        &self,
+        request: Request<DeleteAutoSubscribeRuleRequest>,
+    ) -> Result<Response<DeleteAutoSubscribeRuleReply>, Status> {
+        let req = request.into_inner();
+        match self
+            .cache_manager
+            .delete_auto_subscribe_rule(&req.topic)
+            .await
+        {
+            Ok(_) => Ok(Response::new(DeleteAutoSubscribeRuleReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn mqtt_broker_list_rule(
+        &self,
+        _: Request<ListRuleRequest>,
+    ) -> Result<Response<ListRuleReply>, Status> {
+        let mut rules = Vec::new();
+        for runtime in self.cache_manager.rule_engine.get_rules() {
+            let actions = match serde_json::to_string(&runtime.rule.actions) {
+                Ok(actions) => actions,
+                Err(e) => return Err(Status::cancelled(e.to_string())),
+            };
+            rules.push(RuleRaw {
+                rule_name: runtime.rule.rule_name.clone(),
+                sql: runtime.rule.sql.clone(),
+                actions,
+                enable: runtime.rule.enable,
+                description: runtime.rule.description.clone(),
+                create_time: runtime.rule.create_time,
+                hit: runtime.hit.load(Ordering::Relaxed),
+                fail: runtime.fail.load(Ordering::Relaxed),
+            });
+        }
+        Ok(Response::new(ListRuleReply { rules }))
+    }
+
+    async fn mqtt_broker_create_rule(
+        &self,
+        request: Request<CreateRuleRequest>,
+    ) -> Result<Response<CreateRuleReply>, Status> {
+        let req = request.into_inner();
+        if req.rule_name.is_empty() {
+            return Err(Status::invalid_argument(
+                "Rule name cannot be empty".to_string(),
+            ));
+        }
+        let sql = match RuleSql::parse(&req.sql) {
+            Ok(sql) => sql,
+            Err(e) => return Err(Status::invalid_argument(e.to_string())),
+        };
+        for filter in sql.from.iter() {
+            if !sub_path_validator(filter.clone()) {
+                return Err(Status::invalid_argument(format!(
+                    "FROM topic {} is not a valid topic filter",
+                    filter
+                )));
+            }
+        }
+        let actions = match MqttRuleAction::decode_list(&req.actions) {
+            Ok(actions) => actions,
+            Err(e) => return Err(Status::invalid_argument(e.to_string())),
+        };
+        if actions.is_empty() {
+            return Err(Status::invalid_argument(
+                "A rule needs at least one action".to_string(),
+            ));
+        }
+        if let Err(e) = validate_rule_actions(&actions) {
+            return Err(Status::invalid_argument(e.to_string()));
+        }
+        for action in actions.iter() {
+            match action {
+                MqttRuleAction::Storage { shard_name } => {
+                    if let Err(e) = self
+                        .message_storage_adapter
+                        .create_shard(cluster_name(), shard_name.clone(), ShardConfig::default())
+                        .await
+                    {
+                        warn!(
+                            "Creating the shard {} of rule {} failed, error message: {}",
+                            shard_name, req.rule_name, e
+                        );
+                    }
+                }
+                MqttRuleAction::Bridge { sink_name } => {
+                    // Without a registered sink every message of the rule would fail
+                    if self
+                        .cache_manager
+                        .rule_engine
+                        .get_bridge_sink(sink_name)
+                        .is_none()
+                    {
+                        return Err(Status::invalid_argument(
+                            MqttBrokerError::BridgeSinkNotFound(sink_name.clone()).to_string(),
+                        ));
+                    }
+                }
+                MqttRuleAction::Republish { .. } => {}
+            }
+        }
+
+        let rule = MqttRule {
+            rule_name: req.rule_name,
+            sql: req.sql,
+            actions,
+            enable: req.enable,
+            description: req.description,
+            create_time: now_second(),
+        };
+        let storage = RuleStorage::new(self.client_pool.clone());
+        match storage.save_rule(rule.clone()).await {
+            Ok(_) => match self.cache_manager.rule_engine.add_rule(rule) {
+                Ok(_) => Ok(Response::new(CreateRuleReply::default())),
+                Err(e) => Err(Status::cancelled(e.to_string())),
+            },
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn mqtt_broker_delete_rule(
+        &self,
+        request: Request<DeleteRuleRequest>,
+    ) -> Result<Response<DeleteRuleReply>, Status> {
+        let req = request.into_inner();
+        let storage = RuleStorage::new(self.client_pool.clone());
+        match storage.delete_rule(req.rule_name.clone()).await {
+            Ok(_) => {
+                self.cache_manager.rule_engine.remove_rule(&req.rule_name);
+                Ok(Response::new(DeleteRuleReply::default()))
+            }
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
 }
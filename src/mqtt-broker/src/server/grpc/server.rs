@@ -71,6 +71,7 @@ where
             self.client_pool.clone(),
             self.metadata_cache.clone(),
             self.connection_manager.clone(),
+            self.message_storage_adapter.clone(),
         );
         Server::builder()
             .add_service(MqttBrokerInnerServiceServer::new(inner_handler))
@@ -0,0 +1,398 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use futures_util::StreamExt;
+use log::{debug, error, info};
+use protocol::mqtt::codec::MqttCodec;
+use quinn::{Connection, Endpoint, Incoming, RecvStream};
+use tokio::select;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio_rustls::rustls::pki_types::CertificateDer;
+use tokio_util::codec::{FramedRead, FramedWrite};
+
+use crate::handler::cache::CacheManager;
+use crate::handler::error::MqttBrokerError;
+use crate::handler::validator::establish_connection_check;
+use crate::observability::metrics::packets::{
+    record_received_error_metrics, record_received_metrics,
+};
+use crate::observability::slow::request::try_record_total_request_ms;
+use crate::security::login::is_ip_blacklist;
+use crate::security::login::x509::X509Identity;
+use crate::server::connection::{NetworkConnection, NetworkConnectionType};
+use crate::server::connection_manager::ConnectionManager;
+use crate::server::packet::RequestPackage;
+
+/// The `acceptor_quic_process` function accepts incoming QUIC connections on the endpoint.
+/// Each MQTT session is carried by the first bidirectional stream opened by the client,
+/// the handshake and the stream setup run in their own task so that a slow client does not
+/// hold up the acceptor.
+///
+/// # Parameters
+/// - `accept_thread_num`: The number of threads to spawn for accepting connections.
+/// - `endpoint`: The quinn `Endpoint` bound to the quic port.
+/// - `stop_sx`: A `broadcast::Sender` used to send stop signals to all acceptor threads.
+/// - `connection_manager`: An `Arc`-wrapped `ConnectionManager` instance for managing all network connections.
+/// - `request_queue_sx`: A `Sender` for sending `RequestPackage` instances to a processing queue.
+/// - `cache_manager`: An `Arc`-wrapped `CacheManager` for managing cache operations.
+///
+pub(crate) async fn acceptor_quic_process(
+    accept_thread_num: usize,
+    endpoint: Endpoint,
+    stop_sx: broadcast::Sender<bool>,
+    connection_manager: Arc<ConnectionManager>,
+    request_queue_sx: Sender<RequestPackage>,
+    cache_manager: Arc<CacheManager>,
+) {
+    for index in 1..=accept_thread_num {
+        let endpoint = endpoint.clone();
+        let connection_manager = connection_manager.clone();
+        let mut stop_rx = stop_sx.subscribe();
+        let raw_request_queue_sx = request_queue_sx.clone();
+        let cache_manager = cache_manager.clone();
+        tokio::spawn(async move {
+            debug!("QUIC Server acceptor thread {} start successfully.", index);
+            loop {
+                select! {
+                    val = stop_rx.recv() =>{
+                        if let Ok(flag) = val {
+                            if flag {
+                                endpoint.close(0u32.into(), b"server stopped");
+                                debug!("QUIC Server acceptor thread {} stopped successfully.",index);
+                                break;
+                            }
+                        }
+                    }
+                    val = endpoint.accept()=>{
+                        let Some(incoming) = val else {
+                            debug!("QUIC Server endpoint is closed, acceptor thread {} exits.",index);
+                            break;
+                        };
+
+                        let addr = incoming.remote_address();
+                        info!("accept quic connection:{:?}",addr);
+                        if is_ip_blacklist(&cache_manager, &addr) {
+                            info!("quic connection from blacklisted ip {} is rejected", addr);
+                            incoming.refuse();
+                            continue;
+                        }
+
+                        let connection_manager = connection_manager.clone();
+                        let request_queue_sx = raw_request_queue_sx.clone();
+                        let cache_manager = cache_manager.clone();
+                        tokio::spawn(async move {
+                            if let Err(e) = establish_quic_connection(incoming, connection_manager, request_queue_sx, cache_manager).await {
+                                error!("QUIC accept failed to create connection with error message :{:?}",e);
+                            }
+                        });
+                    }
+                };
+            }
+        });
+    }
+}
+
+async fn establish_quic_connection(
+    incoming: Incoming,
+    connection_manager: Arc<ConnectionManager>,
+    request_queue_sx: Sender<RequestPackage>,
+    cache_manager: Arc<CacheManager>,
+) -> Result<(), MqttBrokerError> {
+    let addr = incoming.remote_address();
+    let quic_connection = incoming.await?;
+    let peer_identity = quic_connection
+        .peer_identity()
+        .and_then(|identity| identity.downcast::<Vec<CertificateDer<'static>>>().ok())
+        .and_then(|certs| X509Identity::from_peer_certificates(Some(&certs)));
+
+    let (send_stream, recv_stream) = quic_connection.accept_bi().await?;
+    let codec = MqttCodec::new(None);
+    let read_frame_stream = FramedRead::new(recv_stream, codec.clone());
+    let mut write_frame_stream = FramedWrite::new(send_stream, codec.clone());
+
+    if !establish_connection_check(
+        &addr,
+        &NetworkConnectionType::Quic,
+        &cache_manager,
+        &connection_manager,
+        &mut write_frame_stream,
+    )
+    .await
+    {
+        // Dropping the connection right away would discard the DISCONNECT before the
+        // client has received it
+        let _ = write_frame_stream.get_ref().stopped().await;
+        quic_connection.close(0u32.into(), b"connection rejected");
+        return Ok(());
+    }
+
+    let (connection_stop_sx, connection_stop_rx) = mpsc::channel::<bool>(1);
+    let mut connection =
+        NetworkConnection::new(NetworkConnectionType::Quic, addr, Some(connection_stop_sx));
+    connection.set_peer_identity(peer_identity);
+    connection_manager.add_connection(connection.clone());
+    connection_manager.add_quic_write(connection.connection_id, write_frame_stream);
+
+    read_quic_frame_process(
+        read_frame_stream,
+        quic_connection,
+        connection,
+        request_queue_sx,
+        connection_stop_rx,
+        cache_manager,
+    );
+    Ok(())
+}
+
+// The quinn connection is owned by the read task, it is closed once the broker stops the
+// MQTT connection or the client finishes the stream. The remote address may change over the lifetime of the connection
+// when the client migrates between networks.
+fn read_quic_frame_process(
+    mut read_frame_stream: FramedRead<RecvStream, MqttCodec>,
+    quic_connection: Connection,
+    connection: NetworkConnection,
+    request_queue_sx: Sender<RequestPackage>,
+    mut connection_stop_rx: Receiver<bool>,
+    cache_manager: Arc<CacheManager>,
+) {
+    let network_type = NetworkConnectionType::Quic;
+    tokio::spawn(async move {
+        loop {
+            select! {
+                val = connection_stop_rx.recv() =>{
+                    if let Some(flag) = val{
+                        if flag {
+                            quic_connection.close(0u32.into(), b"connection closed");
+                            debug!("QUIC connection 【{}】 acceptor thread stopped successfully.",connection.connection_id);
+                            break;
+                        }
+                    }
+                }
+                val = read_frame_stream.next()=>{
+                    if let Some(pkg) = val {
+                        match pkg {
+                            Ok(pack) => {
+                                record_received_metrics(&connection, &pack, &network_type);
+
+                                debug!("revc quic packet:{:?}", pack);
+                                let package =
+                                    RequestPackage::new(connection.connection_id, quic_connection.remote_address(), pack);
+
+                                match request_queue_sx.send(package.clone()).await {
+                                    Ok(_) => {
+                                        try_record_total_request_ms(cache_manager.clone(),package.clone());
+                                    }
+                                    Err(err) => error!("Failed to write data to the request queue, error message: {:?}",err),
+                                }
+                            }
+                            Err(e) => {
+                                record_received_error_metrics(network_type.clone());
+                                debug!("QUIC connection parsing packet format error message :{:?}",e)
+                            }
+                        }
+                    }else {
+                        quic_connection.close(0u32.into(), b"stream finished");
+                        debug!("QUIC connection 【{}】 stream is finished by the client.",connection.connection_id);
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::SocketAddr;
+    use std::path::Path;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use grpc_clients::pool::ClientPool;
+    use metadata_struct::mqtt::cluster::MqttClusterDynamicConfig;
+    use protocol::mqtt::common::MqttPacket;
+    use quinn::crypto::rustls::QuicClientConfig;
+    use quinn::{ClientConfig, Connection, ConnectionError, Endpoint};
+    use tokio::sync::{broadcast, mpsc};
+    use tokio::time::timeout;
+    use tokio_rustls::rustls::client::danger::{
+        HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+    };
+    use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+    use tokio_rustls::rustls::{DigitallySignedStruct, SignatureScheme};
+
+    use super::acceptor_quic_process;
+    use crate::handler::cache::CacheManager;
+    use crate::server::connection_manager::ConnectionManager;
+    use crate::server::packet::RequestPackage;
+    use crate::server::quic::server::{quic_server_config, QUIC_ALPN_MQTT};
+    use crate::server::tcp::tls_server::{load_certs, load_key};
+
+    // MQTT 3.1.1 CONNECT with client id "c1"
+    const CONNECT_PACKET: [u8; 16] = [
+        0x10, 0x0E, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, 0x02, 0x00, 0x3C, 0x00, 0x02, b'c',
+        b'1',
+    ];
+
+    // The example certificate is only used to set up the QUIC handshake, it is not verified.
+    #[derive(Debug)]
+    struct SkipServerVerification;
+
+    impl ServerCertVerifier for SkipServerVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &CertificateDer<'_>,
+            _intermediates: &[CertificateDer<'_>],
+            _server_name: &ServerName<'_>,
+            _ocsp_response: &[u8],
+            _now: UnixTime,
+        ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+
+        fn verify_tls12_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn verify_tls13_signature(
+            &self,
+            _message: &[u8],
+            _cert: &CertificateDer<'_>,
+            _dss: &DigitallySignedStruct,
+        ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+            Ok(HandshakeSignatureValid::assertion())
+        }
+
+        fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+            vec![
+                SignatureScheme::RSA_PSS_SHA256,
+                SignatureScheme::RSA_PSS_SHA384,
+                SignatureScheme::RSA_PSS_SHA512,
+                SignatureScheme::RSA_PKCS1_SHA256,
+                SignatureScheme::ECDSA_NISTP256_SHA256,
+                SignatureScheme::ECDSA_NISTP384_SHA384,
+                SignatureScheme::ED25519,
+            ]
+        }
+    }
+
+    fn start_server(
+        cluster: MqttClusterDynamicConfig,
+    ) -> (
+        SocketAddr,
+        mpsc::Receiver<RequestPackage>,
+        broadcast::Sender<bool>,
+    ) {
+        let certs_dir = format!("{}/../../config/example/certs", env!("CARGO_MANIFEST_DIR"));
+        let tls_config = tokio_rustls::rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(
+                load_certs(Path::new(&format!("{}/cert.pem", certs_dir))).unwrap(),
+                load_key(Path::new(&format!("{}/key.pem", certs_dir))).unwrap(),
+            )
+            .unwrap();
+        let endpoint = Endpoint::server(
+            quic_server_config(tls_config).unwrap(),
+            "127.0.0.1:0".parse().unwrap(),
+        )
+        .unwrap();
+        let addr = endpoint.local_addr().unwrap();
+
+        let client_pool = Arc::new(ClientPool::new(1));
+        let cache_manager = Arc::new(CacheManager::new(client_pool, "test".to_string()));
+        cache_manager.set_cluster_info(cluster);
+        let connection_manager = Arc::new(ConnectionManager::new(cache_manager.clone()));
+        let (request_queue_sx, request_queue_rx) = mpsc::channel::<RequestPackage>(10);
+        let (stop_sx, _) = broadcast::channel(1);
+        tokio::spawn(acceptor_quic_process(
+            1,
+            endpoint,
+            stop_sx.clone(),
+            connection_manager,
+            request_queue_sx,
+            cache_manager,
+        ));
+        (addr, request_queue_rx, stop_sx)
+    }
+
+    async fn connect(addr: SocketAddr) -> Connection {
+        let mut tls_config = tokio_rustls::rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(SkipServerVerification))
+            .with_no_client_auth();
+        tls_config.alpn_protocols = vec![QUIC_ALPN_MQTT.to_vec()];
+        let mut endpoint = Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+        endpoint.set_default_client_config(ClientConfig::new(Arc::new(
+            QuicClientConfig::try_from(tls_config).unwrap(),
+        )));
+        endpoint.connect(addr, "localhost").unwrap().await.unwrap()
+    }
+
+    #[tokio::test]
+    pub async fn quic_read_packet_test() {
+        let (addr, mut request_queue_rx, stop_sx) = start_server(MqttClusterDynamicConfig::new());
+        let connection = connect(addr).await;
+        let (mut send, _recv) = connection.open_bi().await.unwrap();
+        send.write_all(&CONNECT_PACKET).await.unwrap();
+
+        let package = timeout(Duration::from_secs(5), request_queue_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(matches!(package.packet, MqttPacket::Connect(..)));
+
+        // Finishing the stream closes the whole connection instead of leaving it idle
+        send.finish().unwrap();
+        let err = timeout(Duration::from_secs(5), connection.closed())
+            .await
+            .unwrap();
+        assert!(matches!(err, ConnectionError::ApplicationClosed(_)));
+        stop_sx.send(true).unwrap();
+    }
+
+    #[tokio::test]
+    pub async fn quic_connection_rate_exceeded_test() {
+        let mut cluster = MqttClusterDynamicConfig::new();
+        cluster.flow_control.max_broker_connection_rate = 1;
+        let (addr, mut request_queue_rx, stop_sx) = start_server(cluster);
+
+        let first = connect(addr).await;
+        let (mut send, _recv) = first.open_bi().await.unwrap();
+        send.write_all(&CONNECT_PACKET).await.unwrap();
+        timeout(Duration::from_secs(5), request_queue_rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+
+        // The second connection is sent a DISCONNECT before any packet is read
+        let second = connect(addr).await;
+        let (mut send, mut recv) = second.open_bi().await.unwrap();
+        send.write_all(&CONNECT_PACKET).await.unwrap();
+        let data = timeout(Duration::from_secs(5), recv.read_to_end(1024))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(data.first(), Some(&0xE0));
+        assert!(request_queue_rx.try_recv().is_err());
+        stop_sx.send(true).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use common_base::config::broker_mqtt::broker_mqtt_conf;
+use grpc_clients::pool::ClientPool;
+use log::info;
+use quinn::crypto::rustls::QuicServerConfig;
+use quinn::{Endpoint, ServerConfig};
+use storage_adapter::storage::StorageAdapter;
+use tokio::sync::{broadcast, mpsc};
+
+use super::quic_server::acceptor_quic_process;
+use crate::handler::cache::CacheManager;
+use crate::handler::command::Command;
+use crate::handler::error::MqttBrokerError;
+use crate::security::AuthDriver;
+use crate::server::connection_manager::ConnectionManager;
+use crate::server::packet::{RequestPackage, ResponsePackage};
+use crate::server::tcp::handler::handler_process;
+use crate::server::tcp::response::response_process;
+use crate::server::tcp::tls_server::build_tls_server_config;
+use crate::subscribe::subscribe_manager::SubscribeManager;
+
+// ALPN protocol id negotiated by MQTT over QUIC clients
+pub const QUIC_ALPN_MQTT: &[u8] = b"mqtt";
+
+pub async fn start_quic_server<S>(
+    sucscribe_manager: Arc<SubscribeManager>,
+    cache_manager: Arc<CacheManager>,
+    connection_manager: Arc<ConnectionManager>,
+    message_storage_adapter: Arc<S>,
+    client_pool: Arc<ClientPool>,
+    stop_sx: broadcast::Sender<bool>,
+    auth_driver: Arc<AuthDriver>,
+) where
+    S: StorageAdapter + Sync + Send + 'static + Clone,
+{
+    let conf = broker_mqtt_conf();
+    let command = Command::new(
+        cache_manager.clone(),
+        message_storage_adapter.clone(),
+        sucscribe_manager.clone(),
+        client_pool.clone(),
+        connection_manager.clone(),
+        auth_driver.clone(),
+    );
+
+    let server_config = match build_quic_server_config() {
+        Ok(data) => data,
+        Err(e) => {
+            panic!("{}", e.to_string());
+        }
+    };
+
+    let addr: SocketAddr = match format!("0.0.0.0:{}", conf.network.quic_port).parse() {
+        Ok(data) => data,
+        Err(e) => {
+            panic!("{}", e.to_string());
+        }
+    };
+
+    let endpoint = match Endpoint::server(server_config, addr) {
+        Ok(data) => data,
+        Err(e) => {
+            panic!("{}", e.to_string());
+        }
+    };
+
+    let (request_queue_sx, request_queue_rx) = mpsc::channel::<RequestPackage>(1000);
+    let (response_queue_sx, response_queue_rx) = mpsc::channel::<ResponsePackage>(1000);
+
+    acceptor_quic_process(
+        conf.tcp_thread.accept_thread_num,
+        endpoint,
+        stop_sx.clone(),
+        connection_manager.clone(),
+        request_queue_sx,
+        cache_manager.clone(),
+    )
+    .await;
+
+    handler_process(
+        conf.tcp_thread.handler_thread_num,
+        request_queue_rx,
+        connection_manager.clone(),
+        response_queue_sx,
+        stop_sx.clone(),
+        command,
+    )
+    .await;
+
+    response_process(
+        conf.tcp_thread.response_thread_num,
+        connection_manager,
+        cache_manager,
+        sucscribe_manager,
+        response_queue_rx,
+        client_pool,
+        stop_sx,
+    )
+    .await;
+
+    info!(
+        "MQTT QUIC Server started successfully, listening port: {}",
+        conf.network.quic_port
+    );
+}
+
+// Uses the same certificate, key and client certificate verification as the tcps listener.
+// Connection migration is left enabled, so clients keep their session when their address changes.
+pub(crate) fn build_quic_server_config() -> Result<ServerConfig, MqttBrokerError> {
+    quic_server_config(build_tls_server_config()?)
+}
+
+pub(crate) fn quic_server_config(
+    mut tls_config: tokio_rustls::rustls::ServerConfig,
+) -> Result<ServerConfig, MqttBrokerError> {
+    tls_config.alpn_protocols = vec![QUIC_ALPN_MQTT.to_vec()];
+    let quic_config = QuicServerConfig::try_from(tls_config)?;
+    Ok(ServerConfig::with_crypto(Arc::new(quic_config)))
+}
@@ -20,9 +20,11 @@ use log::info;
 use storage_adapter::storage::StorageAdapter;
 use tokio::net::TcpListener;
 use tokio::sync::{broadcast, mpsc};
+use tokio_rustls::TlsAcceptor;
 
 use crate::handler::cache::CacheManager;
 use crate::handler::command::Command;
+use crate::security::login::psk::build_psk_acceptor;
 use crate::security::AuthDriver;
 use crate::server::connection::NetworkConnectionType;
 use crate::server::connection_manager::ConnectionManager;
@@ -30,7 +32,9 @@ use crate::server::packet::{RequestPackage, ResponsePackage};
 use crate::server::tcp::handler::handler_process;
 use crate::server::tcp::response::response_process;
 use crate::server::tcp::tcp_server::acceptor_process;
-use crate::server::tcp::tls_server::acceptor_tls_process;
+use crate::server::tcp::tls_server::{
+    acceptor_tls_process, build_tls_server_config, TlsServerAcceptor,
+};
 use crate::subscribe::subscribe_manager::SubscribeManager;
 
 pub async fn start_tcp_server<S>(
@@ -71,16 +75,51 @@ pub async fn start_tcp_server<S>(
     );
     server.start(conf.network.tcp_port).await;
 
+    let tls_config = match build_tls_server_config() {
+        Ok(data) => data,
+        Err(e) => {
+            panic!("{}", e.to_string());
+        }
+    };
     let mut server = TcpServer::<S>::new(
-        command,
+        command.clone(),
         proc_config,
         stop_sx.clone(),
-        connection_manager,
+        connection_manager.clone(),
         sucscribe_manager.clone(),
-        cache_manager,
-        client_pool,
+        cache_manager.clone(),
+        client_pool.clone(),
     );
-    server.start_tls(conf.network.tcps_port).await;
+    server
+        .start_tls(
+            conf.network.tcps_port,
+            TlsServerAcceptor::Rustls(TlsAcceptor::from(Arc::new(tls_config))),
+        )
+        .await;
+
+    if conf.auth.psk.enable {
+        let psk_acceptor = match build_psk_acceptor(cache_manager.clone(), &conf.auth.psk) {
+            Ok(data) => data,
+            Err(e) => {
+                panic!("{}", e.to_string());
+            }
+        };
+        let mut server = TcpServer::<S>::new(
+            command,
+            proc_config,
+            stop_sx.clone(),
+            connection_manager,
+            sucscribe_manager.clone(),
+            cache_manager,
+            client_pool,
+        );
+        server
+            .start_tls(
+                conf.network.tcps_psk_port,
+                TlsServerAcceptor::Psk(Arc::new(psk_acceptor)),
+            )
+            .await;
+    }
 }
 
 // U: codec: encoder + decoder
@@ -180,7 +219,7 @@ where
         info!("MQTT TCP Server started successfully, listening port: {port}");
     }
 
-    pub async fn start_tls(&mut self, port: u32) {
+    pub async fn start_tls(&mut self, port: u32, tls_acceptor: TlsServerAcceptor) {
         let listener = match TcpListener::bind(format!("0.0.0.0:{}", port)).await {
             Ok(tl) => tl,
             Err(e) => {
@@ -199,6 +238,8 @@ where
             self.network_connection_type.clone(),
             self.connection_manager.clone(),
             request_queue_sx,
+            tls_acceptor,
+            self.cache_manager.clone(),
         )
         .await;
 
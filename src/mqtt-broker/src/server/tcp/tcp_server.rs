@@ -26,11 +26,12 @@ use tokio::{io, select};
 use tokio_util::codec::{FramedRead, FramedWrite};
 
 use crate::handler::cache::CacheManager;
-use crate::handler::validator::tcp_establish_connection_check;
+use crate::handler::validator::establish_connection_check;
 use crate::observability::metrics::packets::{
     record_received_error_metrics, record_received_metrics,
 };
 use crate::observability::slow::request::try_record_total_request_ms;
+use crate::security::login::is_ip_blacklist;
 use crate::server::connection::{NetworkConnection, NetworkConnectionType};
 use crate::server::connection_manager::ConnectionManager;
 use crate::server::packet::RequestPackage;
@@ -80,13 +81,17 @@ pub(crate) async fn acceptor_process(
                         match val{
                             Ok((stream, addr)) => {
                                 info!("accept tcp connection:{:?}",addr);
+                                if is_ip_blacklist(&cache_manager, &addr) {
+                                    info!("tcp connection from blacklisted ip {} is rejected", addr);
+                                    continue;
+                                }
 
                                 let (r_stream, w_stream) = io::split(stream);
                                 let codec = MqttCodec::new(None);
                                 let read_frame_stream = FramedRead::new(r_stream, codec.clone());
                                 let mut  write_frame_stream = FramedWrite::new(w_stream, codec.clone());
 
-                                if !tcp_establish_connection_check(&addr,&connection_manager,&mut write_frame_stream).await{
+                                if !establish_connection_check(&addr,&NetworkConnectionType::Tcp,&cache_manager,&connection_manager,&mut write_frame_stream).await{
                                     continue;
                                 }
 
@@ -15,28 +15,39 @@
 use std::fs::File;
 use std::io::{self, BufReader, ErrorKind};
 use std::path::Path;
+use std::pin::Pin;
 use std::sync::Arc;
 use std::time::Duration;
 
 use common_base::config::broker_mqtt::broker_mqtt_conf;
+use common_base::config::common::AuthX509;
 use futures_util::StreamExt;
 use log::{debug, error, info};
+use openssl::ssl::{Ssl, SslAcceptor};
 use protocol::mqtt::codec::MqttCodec;
 use rustls_pemfile::{certs, private_key};
-use tokio::net::TcpListener;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::select;
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::sync::{broadcast, mpsc};
 use tokio::time::sleep;
+use tokio_openssl::SslStream;
 use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
-use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::rustls::server::danger::ClientCertVerifier;
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
 use tokio_rustls::TlsAcceptor;
 use tokio_util::codec::{FramedRead, FramedWrite};
 
-use crate::handler::validator::tcp_tls_establish_connection_check;
+use crate::handler::cache::CacheManager;
+use crate::handler::error::MqttBrokerError;
+use crate::handler::validator::establish_connection_check;
 use crate::observability::metrics::packets::{
     record_received_error_metrics, record_received_metrics,
 };
+use crate::security::login::is_ip_blacklist;
+use crate::security::login::x509::X509Identity;
 use crate::server::connection::{NetworkConnection, NetworkConnectionType};
 use crate::server::connection_manager::ConnectionManager;
 use crate::server::packet::RequestPackage;
@@ -54,6 +65,85 @@ pub(crate) fn load_key(path: &Path) -> io::Result<PrivateKeyDer<'static>> {
         ))
 }
 
+// Both the rustls and the openssl (TLS-PSK) streams are handled as a TlsIo once
+// the handshake is done.
+pub trait TlsIo: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+impl<T> TlsIo for T where T: AsyncRead + AsyncWrite + Send + Sync + Unpin {}
+
+#[derive(Clone)]
+pub(crate) enum TlsServerAcceptor {
+    Rustls(TlsAcceptor),
+    Psk(Arc<SslAcceptor>),
+}
+
+impl TlsServerAcceptor {
+    // Returns the stream with the client certificate identity or the PSK
+    // identity proven during the handshake.
+    async fn accept(
+        &self,
+        stream: TcpStream,
+    ) -> Result<(Box<dyn TlsIo>, Option<X509Identity>, Option<String>), MqttBrokerError> {
+        match self {
+            TlsServerAcceptor::Rustls(acceptor) => {
+                let stream = acceptor.accept(stream).await?;
+                let peer_identity =
+                    X509Identity::from_peer_certificates(stream.get_ref().1.peer_certificates());
+                Ok((Box::new(stream), peer_identity, None))
+            }
+            TlsServerAcceptor::Psk(acceptor) => {
+                let ssl = Ssl::new(acceptor.context())?;
+                let mut stream = SslStream::new(ssl, stream)?;
+                Pin::new(&mut stream).accept().await?;
+                let psk_identity = stream
+                    .ssl()
+                    .psk_identity()
+                    .map(|raw| String::from_utf8_lossy(raw).to_string());
+                Ok((Box::new(stream), None, psk_identity))
+            }
+        }
+    }
+}
+
+// Shared by the tcps and wss listeners. When x509 auth is enabled the client
+// certificate is verified against the configured CA bundle.
+pub(crate) fn build_tls_server_config() -> Result<ServerConfig, MqttBrokerError> {
+    let conf = broker_mqtt_conf();
+    let certs = load_certs(Path::new(&conf.network.tls_cert))?;
+    let key = load_key(Path::new(&conf.network.tls_key))?;
+
+    let builder = ServerConfig::builder();
+    let config = match build_client_cert_verifier(&conf.auth.x509)? {
+        Some(verifier) => builder
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)?,
+        None => builder.with_no_client_auth().with_single_cert(certs, key)?,
+    };
+    Ok(config)
+}
+
+pub(crate) fn build_client_cert_verifier(
+    x509: &AuthX509,
+) -> Result<Option<Arc<dyn ClientCertVerifier>>, MqttBrokerError> {
+    if !x509.enable {
+        return Ok(None);
+    }
+
+    let mut roots = RootCertStore::empty();
+    for cert in load_certs(Path::new(&x509.ca_cert))? {
+        roots.add(cert)?;
+    }
+
+    let builder = WebPkiClientVerifier::builder(Arc::new(roots));
+    let verifier = if x509.fail_if_no_peer_cert {
+        builder.build()?
+    } else {
+        builder.allow_unauthenticated().build()?
+    };
+    Ok(Some(verifier))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub(crate) async fn acceptor_tls_process(
     accept_thread_num: usize,
     listener_arc: Arc<TcpListener>,
@@ -61,36 +151,12 @@ pub(crate) async fn acceptor_tls_process(
     network_connection_type: NetworkConnectionType,
     connection_manager: Arc<ConnectionManager>,
     request_queue_sx: Sender<RequestPackage>,
+    tls_acceptor: TlsServerAcceptor,
+    cache_manager: Arc<CacheManager>,
 ) {
-    let conf = broker_mqtt_conf();
-
-    let certs = match load_certs(Path::new(&conf.network.tls_cert)) {
-        Ok(data) => data,
-        Err(e) => {
-            panic!("{}", e.to_string());
-        }
-    };
-
-    let key = match load_key(Path::new(&conf.network.tls_key)) {
-        Ok(data) => data,
-        Err(e) => {
-            panic!("{}", e.to_string());
-        }
-    };
-
-    let config = match ServerConfig::builder()
-        .with_no_client_auth()
-        .with_single_cert(certs, key)
-    {
-        Ok(data) => data,
-        Err(e) => {
-            panic!("{}", e.to_string());
-        }
-    };
-    let tls_acceptor = TlsAcceptor::from(Arc::new(config));
-
     for index in 1..=accept_thread_num {
         let listener = listener_arc.clone();
+        let cache_manager = cache_manager.clone();
         let connection_manager = connection_manager.clone();
         let mut stop_rx = stop_sx.subscribe();
         let raw_request_queue_sx = request_queue_sx.clone();
@@ -112,7 +178,11 @@ pub(crate) async fn acceptor_tls_process(
                         match val{
                             Ok((stream, addr)) => {
                                 info!("accept tcp tls connection:{:?}",addr);
-                                let stream = match raw_tls_acceptor.accept(stream).await{
+                                if is_ip_blacklist(&cache_manager, &addr) {
+                                    info!("tcp tls connection from blacklisted ip {} is rejected", addr);
+                                    continue;
+                                }
+                                let (stream, peer_identity, psk_identity) = match raw_tls_acceptor.accept(stream).await{
                                     Ok(da) => da,
                                     Err(e) => {
                                         error!("Tls Accepter failed to read Stream with error message :{e:?}");
@@ -124,16 +194,18 @@ pub(crate) async fn acceptor_tls_process(
                                 let read_frame_stream = FramedRead::new(r_stream, codec.clone());
                                 let mut  write_frame_stream = FramedWrite::new(w_stream, codec.clone());
 
-                                if !tcp_tls_establish_connection_check(&addr,&connection_manager,&mut write_frame_stream).await{
+                                if !establish_connection_check(&addr,&NetworkConnectionType::Tls,&cache_manager,&connection_manager,&mut write_frame_stream).await{
                                     continue;
                                 }
 
                                 let (connection_stop_sx, connection_stop_rx) = mpsc::channel::<bool>(1);
-                                let connection = NetworkConnection::new(
+                                let mut connection = NetworkConnection::new(
                                     crate::server::connection::NetworkConnectionType::Tls,
                                     addr,
                                     Some(connection_stop_sx.clone())
                                 );
+                                connection.set_peer_identity(peer_identity);
+                                connection.set_psk_identity(psk_identity);
                                 connection_manager.add_connection(connection.clone());
                                 connection_manager.add_tcp_tls_write(connection.connection_id, write_frame_stream);
 
@@ -151,10 +223,7 @@ pub(crate) async fn acceptor_tls_process(
 }
 
 pub(crate) fn read_tls_frame_process(
-    mut read_frame_stream: FramedRead<
-        tokio::io::ReadHalf<tokio_rustls::server::TlsStream<tokio::net::TcpStream>>,
-        MqttCodec,
-    >,
+    mut read_frame_stream: FramedRead<tokio::io::ReadHalf<Box<dyn TlsIo>>, MqttCodec>,
     connection: NetworkConnection,
     request_queue_sx: Sender<RequestPackage>,
     mut connection_stop_rx: Receiver<bool>,
@@ -12,34 +12,45 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+use std::io;
 use std::net::SocketAddr;
-use std::path::PathBuf;
 use std::sync::Arc;
 
 use axum::extract::ws::{Message, WebSocket};
 use axum::extract::{ConnectInfo, State, WebSocketUpgrade};
-use axum::response::Response;
+use axum::http::StatusCode;
+use axum::middleware::AddExtension;
+use axum::response::{IntoResponse, Response};
 use axum::routing::get;
-use axum::Router;
+use axum::{Extension, Router};
 use axum_extra::headers::UserAgent;
 use axum_extra::TypedHeader;
-use axum_server::tls_rustls::RustlsConfig;
+use axum_server::accept::Accept;
+use axum_server::tls_rustls::{RustlsAcceptor, RustlsConfig};
 use bytes::{BufMut, BytesMut};
 use common_base::config::broker_mqtt::broker_mqtt_conf;
+use futures_util::future::BoxFuture;
 use futures_util::stream::StreamExt;
 use grpc_clients::pool::ClientPool;
 use log::{debug, error, info};
 use protocol::mqtt::codec::{MqttCodec, MqttPacketWrapper};
 use protocol::mqtt::common::{MqttPacket, MqttProtocol};
 use storage_adapter::storage::StorageAdapter;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::select;
 use tokio::sync::broadcast::{self};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
 
 use crate::handler::cache::CacheManager;
 use crate::handler::command::Command;
+use crate::handler::flow_control::is_connection_rate_exceeded;
+use crate::security::login::is_ip_blacklist;
+use crate::security::login::x509::X509Identity;
 use crate::security::AuthDriver;
-use crate::server::connection::NetworkConnection;
+use crate::server::connection::{NetworkConnection, NetworkConnectionType};
 use crate::server::connection_manager::ConnectionManager;
+use crate::server::tcp::tls_server::build_tls_server_config;
 use crate::subscribe::subscribe_manager::SubscribeManager;
 
 pub const ROUTE_ROOT: &str = "/mqtt";
@@ -112,23 +123,20 @@ where
         .unwrap();
     let app = routes_v1(state);
 
-    let tls_config = match RustlsConfig::from_pem_file(
-        PathBuf::from(config.network.tls_cert.clone()),
-        PathBuf::from(config.network.tls_key.clone()),
-    )
-    .await
-    {
-        Ok(cf) => cf,
+    let tls_config = match build_tls_server_config() {
+        Ok(cf) => RustlsConfig::from_config(Arc::new(cf)),
         Err(e) => {
             panic!("{}", e.to_string());
         }
     };
+    let acceptor = PeerCertAcceptor::new(RustlsAcceptor::new(tls_config));
 
     info!(
         "Broker WebSocket TLS Server start success. port:{}",
         config.network.websockets_port
     );
-    match axum_server::bind_rustls(ip, tls_config)
+    match axum_server::bind(ip)
+        .acceptor(acceptor)
         .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
     {
@@ -137,6 +145,43 @@ where
     }
 }
 
+// The client certificate verified in the TLS handshake, handed to ws_handler
+// as a request extension.
+#[derive(Clone)]
+pub struct PeerCertificate(pub Option<X509Identity>);
+
+#[derive(Clone)]
+pub struct PeerCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl PeerCertAcceptor {
+    pub fn new(inner: RustlsAcceptor) -> Self {
+        PeerCertAcceptor { inner }
+    }
+}
+
+impl<I, S> Accept<I, S> for PeerCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, PeerCertificate>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            let peer_identity =
+                X509Identity::from_peer_certificates(stream.get_ref().1.peer_certificates());
+            let service = Extension(PeerCertificate(peer_identity)).layer(service);
+            Ok((stream, service))
+        })
+    }
+}
+
 fn routes_v1<S>(state: WebSocketServerState<S>) -> Router
 where
     S: StorageAdapter + Sync + Send + 'static + Clone,
@@ -152,6 +197,7 @@ async fn ws_handler<S>(
     State(state): State<WebSocketServerState<S>>,
     user_agent: Option<TypedHeader<UserAgent>>,
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    peer_cert: Option<Extension<PeerCertificate>>,
 ) -> Response
 where
     S: StorageAdapter + Sync + Send + 'static + Clone,
@@ -162,6 +208,27 @@ where
         String::from("Unknown Source")
     };
     info!("`{user_agent}` at {addr} connected.");
+    // Rejected before the upgrade, no MQTT bytes are read from a blacklisted ip
+    if is_ip_blacklist(&state.cache_manager, &addr) {
+        info!(
+            "websocket connection from blacklisted ip {} is rejected",
+            addr
+        );
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    // Only the websockets listener installs the peer certificate extension
+    let network_type = if peer_cert.is_some() {
+        NetworkConnectionType::WebSockets
+    } else {
+        NetworkConnectionType::WebSocket
+    };
+    if is_connection_rate_exceeded(&state.cache_manager, &network_type, &addr.ip()) {
+        info!(
+            "websocket connection from ip {} exceeds the connection rate",
+            addr
+        );
+        return StatusCode::TOO_MANY_REQUESTS.into_response();
+    }
     let command = Command::new(
         state.cache_manager.clone(),
         state.message_storage_adapter.clone(),
@@ -170,12 +237,14 @@ where
         state.connection_manager.clone(),
         state.auth_driver.clone(),
     );
+    let peer_identity = peer_cert.and_then(|Extension(PeerCertificate(identity))| identity);
     let codec = MqttCodec::new(None);
     ws.protocols(["mqtt", "mqttv3.1"])
         .on_upgrade(move |socket| {
             handle_socket(
                 socket,
                 addr,
+                peer_identity,
                 command,
                 codec,
                 state.connection_manager.clone(),
@@ -187,6 +256,7 @@ where
 async fn handle_socket<S>(
     socket: WebSocket,
     addr: SocketAddr,
+    peer_identity: Option<X509Identity>,
     mut command: Command<S>,
     mut codec: MqttCodec,
     connection_manager: Arc<ConnectionManager>,
@@ -195,11 +265,8 @@ async fn handle_socket<S>(
     S: StorageAdapter + Sync + Send + 'static + Clone,
 {
     let (sender, mut receiver) = socket.split();
-    let mut tcp_connection = NetworkConnection::new(
-        crate::server::connection::NetworkConnectionType::WebSocket,
-        addr,
-        None,
-    );
+    let mut tcp_connection = NetworkConnection::new(NetworkConnectionType::WebSocket, addr, None);
+    tcp_connection.set_peer_identity(peer_identity);
 
     connection_manager.add_websocket_write(tcp_connection.connection_id, sender);
     connection_manager.add_connection(tcp_connection.clone());
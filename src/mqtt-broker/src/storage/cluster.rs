@@ -135,6 +135,7 @@ impl ClusterStorage {
             cluster_name: cluster_name.to_string(),
             resources,
             config: cluster.encode(),
+            ..Default::default()
         };
 
         set_resource_config(self.client_pool.clone(), &config.placement_center, request).await?;
@@ -142,6 +143,30 @@ impl ClusterStorage {
         Ok(())
     }
 
+    // Writes the cluster config only if the stored one is still `expected`, as returned by
+    // `get_cluster_config_data`. Returns false when another write came first.
+    pub async fn compare_and_set_cluster_config(
+        &self,
+        cluster_name: &str,
+        expected: Vec<u8>,
+        cluster: MqttClusterDynamicConfig,
+    ) -> Result<bool, CommonError> {
+        let config = broker_mqtt_conf();
+        let resources = self.cluster_config_resources(cluster_name.to_string());
+        let request = SetResourceConfigRequest {
+            cluster_name: cluster_name.to_string(),
+            resources,
+            config: cluster.encode(),
+            compare_and_set: true,
+            expected_config: expected,
+        };
+
+        let reply =
+            set_resource_config(self.client_pool.clone(), &config.placement_center, request)
+                .await?;
+        Ok(!reply.conflict)
+    }
+
     pub async fn delete_cluster_config(&self, cluster_name: &str) -> Result<(), CommonError> {
         let config = broker_mqtt_conf();
         let resources = self.cluster_config_resources(cluster_name.to_string());
@@ -158,6 +183,22 @@ impl ClusterStorage {
         &self,
         cluster_name: &str,
     ) -> Result<Option<MqttClusterDynamicConfig>, CommonError> {
+        let data = self.get_cluster_config_data(cluster_name).await?;
+        if data.is_empty() {
+            Ok(None)
+        } else {
+            match serde_json::from_slice::<MqttClusterDynamicConfig>(&data) {
+                Ok(data) => Ok(Some(data)),
+                Err(e) => Err(CommonError::CommonError(e.to_string())),
+            }
+        }
+    }
+
+    // The cluster config as it is stored, empty when there is none
+    pub async fn get_cluster_config_data(
+        &self,
+        cluster_name: &str,
+    ) -> Result<Vec<u8>, CommonError> {
         let config = broker_mqtt_conf();
         let resources = self.cluster_config_resources(cluster_name.to_string());
         let request = GetResourceConfigRequest {
@@ -165,20 +206,10 @@ impl ClusterStorage {
             resources,
         };
 
-        match get_resource_config(self.client_pool.clone(), &config.placement_center, request).await
-        {
-            Ok(data) => {
-                if data.config.is_empty() {
-                    Ok(None)
-                } else {
-                    match serde_json::from_slice::<MqttClusterDynamicConfig>(&data.config) {
-                        Ok(data) => Ok(Some(data)),
-                        Err(e) => Err(CommonError::CommonError(e.to_string())),
-                    }
-                }
-            }
-            Err(e) => Err(e),
-        }
+        let reply =
+            get_resource_config(self.client_pool.clone(), &config.placement_center, request)
+                .await?;
+        Ok(reply.config)
     }
 
     fn cluster_config_resources(&self, cluster_name: String) -> Vec<String> {
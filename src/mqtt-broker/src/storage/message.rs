@@ -56,11 +56,13 @@ where
         topic_id: &str,
         offset: u64,
         record_num: u64,
+        max_wait_ms: u64,
     ) -> Result<Vec<Record>, CommonError> {
         let shard_name = topic_id;
         let namespace = cluster_name();
         let mut read_config = ReadConfig::new();
         read_config.max_record_num = record_num;
+        read_config.max_wait_ms = max_wait_ms;
 
         self.storage_adapter
             .read_by_offset(namespace, shard_name.to_owned(), offset, read_config)
@@ -16,6 +16,9 @@ pub mod acl;
 pub mod blacklist;
 pub mod cluster;
 pub mod message;
+pub mod psk;
+pub mod rule;
 pub mod session;
 pub mod topic;
+pub mod topic_rewrite_rule;
 pub mod user;
@@ -0,0 +1,68 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use common_base::config::broker_mqtt::broker_mqtt_conf;
+use grpc_clients::placement::mqtt::call::{create_psk, delete_psk, list_psk};
+use grpc_clients::pool::ClientPool;
+use metadata_struct::mqtt::psk::MqttPskIdentity;
+use protocol::placement_center::placement_center_mqtt::{
+    CreatePskRequest, DeletePskRequest, ListPskRequest,
+};
+
+use crate::handler::error::MqttBrokerError;
+
+pub struct PskStorage {
+    client_pool: Arc<ClientPool>,
+}
+
+impl PskStorage {
+    pub fn new(client_pool: Arc<ClientPool>) -> Self {
+        PskStorage { client_pool }
+    }
+
+    pub async fn list_psk(&self) -> Result<Vec<MqttPskIdentity>, MqttBrokerError> {
+        let config = broker_mqtt_conf();
+        let request = ListPskRequest {
+            cluster_name: config.cluster_name.clone(),
+        };
+        let reply = list_psk(self.client_pool.clone(), &config.placement_center, request).await?;
+        let mut list = Vec::new();
+        for raw in reply.psks {
+            list.push(MqttPskIdentity::decode(raw.as_slice())?);
+        }
+        Ok(list)
+    }
+
+    pub async fn save_psk(&self, psk: MqttPskIdentity) -> Result<(), MqttBrokerError> {
+        let config = broker_mqtt_conf();
+        let request = CreatePskRequest {
+            cluster_name: config.cluster_name.clone(),
+            psk: psk.encode(),
+        };
+        create_psk(self.client_pool.clone(), &config.placement_center, request).await?;
+        Ok(())
+    }
+
+    pub async fn delete_psk(&self, identity: String) -> Result<(), MqttBrokerError> {
+        let config = broker_mqtt_conf();
+        let request = DeletePskRequest {
+            cluster_name: config.cluster_name.clone(),
+            identity,
+        };
+        delete_psk(self.client_pool.clone(), &config.placement_center, request).await?;
+        Ok(())
+    }
+}
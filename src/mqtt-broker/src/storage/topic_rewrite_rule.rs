@@ -0,0 +1,83 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::sync::Arc;
+
+use common_base::config::broker_mqtt::broker_mqtt_conf;
+use grpc_clients::placement::mqtt::call::{
+    create_topic_rewrite_rule, delete_topic_rewrite_rule, list_topic_rewrite_rule,
+};
+use grpc_clients::pool::ClientPool;
+use metadata_struct::mqtt::topic_rewrite_rule::MqttTopicRewriteRule;
+use protocol::placement_center::placement_center_mqtt::{
+    CreateTopicRewriteRuleRequest, DeleteTopicRewriteRuleRequest, ListTopicRewriteRuleRequest,
+};
+
+use crate::handler::error::MqttBrokerError;
+
+pub struct TopicRewriteRuleStorage {
+    client_pool: Arc<ClientPool>,
+}
+
+impl TopicRewriteRuleStorage {
+    pub fn new(client_pool: Arc<ClientPool>) -> Self {
+        TopicRewriteRuleStorage { client_pool }
+    }
+
+    pub async fn list_topic_rewrite_rule(
+        &self,
+    ) -> Result<Vec<MqttTopicRewriteRule>, MqttBrokerError> {
+        let config = broker_mqtt_conf();
+        let request = ListTopicRewriteRuleRequest {
+            cluster_name: config.cluster_name.clone(),
+        };
+        let reply =
+            list_topic_rewrite_rule(self.client_pool.clone(), &config.placement_center, request)
+                .await?;
+        let mut list = Vec::new();
+        for raw in reply.rules {
+            list.push(MqttTopicRewriteRule::decode(raw.as_slice())?);
+        }
+        Ok(list)
+    }
+
+    pub async fn save_topic_rewrite_rule(
+        &self,
+        rule: MqttTopicRewriteRule,
+    ) -> Result<(), MqttBrokerError> {
+        let config = broker_mqtt_conf();
+        let request = CreateTopicRewriteRuleRequest {
+            cluster_name: config.cluster_name.clone(),
+            rule: rule.encode()?,
+        };
+        create_topic_rewrite_rule(self.client_pool.clone(), &config.placement_center, request)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn delete_topic_rewrite_rule(
+        &self,
+        action: String,
+        source_topic: String,
+    ) -> Result<(), MqttBrokerError> {
+        let config = broker_mqtt_conf();
+        let request = DeleteTopicRewriteRuleRequest {
+            cluster_name: config.cluster_name.clone(),
+            action,
+            source_topic,
+        };
+        delete_topic_rewrite_rule(self.client_pool.clone(), &config.placement_center, request)
+            .await?;
+        Ok(())
+    }
+}
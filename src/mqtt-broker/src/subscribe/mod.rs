@@ -21,6 +21,7 @@ pub mod sub_share_follower;
 pub mod sub_share_leader;
 pub mod subscribe_manager;
 pub mod subscriber;
+pub mod topic_trie;
 
 #[derive(Clone, Default, Debug)]
 pub(crate) struct SubPublishParam {
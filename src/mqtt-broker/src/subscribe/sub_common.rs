@@ -33,6 +33,7 @@ use tokio::select;
 use tokio::sync::broadcast::{self, Sender};
 use tokio::time::{sleep, timeout};
 
+use super::topic_trie::topic_filter_match;
 use super::SubPublishParam;
 use crate::handler::cache::{CacheManager, QosAckPackageData};
 use crate::handler::error::MqttBrokerError;
@@ -43,6 +44,9 @@ use crate::storage::message::MessageStorage;
 
 const SHARE_SUB_PREFIX: &str = "$share";
 
+// How long a push thread waits in the storage adapter for new messages of an idle topic
+pub const PUSH_READ_MAX_WAIT_MS: u64 = 1000;
+
 pub fn path_contain_sub(_: &str) -> bool {
     true
 }
@@ -66,35 +70,18 @@ pub fn sub_path_validator(sub_path: String) -> bool {
     true
 }
 
-pub fn path_regex_match(topic_name: String, sub_path: String) -> bool {
-    let path = if is_share_sub(sub_path.clone()) {
-        let (_, group_path) = decode_share_info(sub_path);
+pub fn path_match(topic_name: &str, sub_path: &str) -> bool {
+    topic_filter_match(topic_name, &sub_filter_path(sub_path))
+}
+
+/// The topic filter of a subscription path, without the $share/{group} prefix.
+pub fn sub_filter_path(sub_path: &str) -> String {
+    if is_share_sub(sub_path.to_string()) {
+        let (_, group_path) = decode_share_info(sub_path.to_string());
         group_path
     } else {
-        sub_path
-    };
-
-    // Path perfect matching
-    if topic_name == path {
-        return true;
-    }
-
-    if path.contains("+") {
-        let sub_regex = path.replace("+", "[^+*/]+");
-        let re = Regex::new(&sub_regex.to_string()).unwrap();
-        return re.is_match(&topic_name);
-    }
-
-    if path.contains("#") {
-        if path.split("/").last().unwrap() != "#" {
-            return false;
-        }
-        let sub_regex = path.replace("#", "[^+#]+");
-        let re = Regex::new(&sub_regex.to_string()).unwrap();
-        return re.is_match(&topic_name);
+        sub_path.to_string()
     }
-
-    false
 }
 
 pub fn min_qos(qos: QoS, sub_qos: QoS) -> QoS {
@@ -109,9 +96,9 @@ pub async fn get_sub_topic_id_list(
     sub_path: &str,
 ) -> Vec<String> {
     let mut result = Vec::new();
-    for (topic_id, topic_name) in metadata_cache.topic_id_name.clone() {
-        if path_regex_match(topic_name.clone(), sub_path.to_string()) {
-            result.push(topic_id);
+    for topic_name in metadata_cache.get_topic_names_by_filter(sub_path) {
+        if let Some(topic) = metadata_cache.get_topic_by_name(&topic_name) {
+            result.push(topic.topic_id);
         }
     }
     result
@@ -431,7 +418,7 @@ mod tests {
 
     use crate::handler::cache::CacheManager;
     use crate::subscribe::sub_common::{
-        decode_share_info, get_sub_topic_id_list, is_share_sub, min_qos, path_regex_match,
+        decode_share_info, get_sub_topic_id_list, is_share_sub, min_qos, path_match,
         sub_path_validator,
     };
 
@@ -479,58 +466,58 @@ mod tests {
         assert_eq!(topic_name, "/finance/#".to_string());
     }
     #[test]
-    fn path_regex_match_test() {
+    fn path_match_test() {
         let topic_name = "/loboxu/test".to_string();
         let sub_regex = "/loboxu/#".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
 
         let topic_name = "/topic/test".to_string();
         let sub_regex = "/topic/test".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/1/temperature".to_string();
         let sub_regex = r"/sensor/+/temperature".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/1/2/temperature3".to_string();
         let sub_regex = r"/sensor/+/temperature".to_string();
-        assert!(!path_regex_match(topic_name, sub_regex));
+        assert!(!path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/temperature3".to_string();
         let sub_regex = r"/sensor/+/temperature".to_string();
-        assert!(!path_regex_match(topic_name, sub_regex));
+        assert!(!path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/temperature3".to_string();
         let sub_regex = r"/sensor/+".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/temperature3/tmpq".to_string();
         let sub_regex = r"/sensor/#".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
 
         let topic_name = "/topic/test".to_string();
         let sub_regex = "$share/groupname/topic/test".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/1/temperature".to_string();
         let sub_regex = r"$share/groupname/sensor/+/temperature".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/1/2/temperature3".to_string();
         let sub_regex = r"$share/groupname/sensor/+/temperature".to_string();
-        assert!(!path_regex_match(topic_name, sub_regex));
+        assert!(!path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/temperature3".to_string();
         let sub_regex = r"$share/groupname/sensor/+/temperature".to_string();
-        assert!(!path_regex_match(topic_name, sub_regex));
+        assert!(!path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/temperature3".to_string();
         let sub_regex = r"$share/groupname/sensor/+".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
 
         let topic_name = r"/sensor/temperature3/tmpq".to_string();
         let sub_regex = r"$share/groupname/sensor/#".to_string();
-        assert!(path_regex_match(topic_name, sub_regex));
+        assert!(path_match(&topic_name, &sub_regex));
     }
 
     #[test]
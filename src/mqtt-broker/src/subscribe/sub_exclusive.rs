@@ -28,7 +28,7 @@ use tokio::time::sleep;
 
 use super::sub_common::{
     loop_commit_offset, min_qos, publish_message_qos0, publish_message_to_client,
-    qos2_send_publish, qos2_send_pubrel, wait_packet_ack,
+    qos2_send_publish, qos2_send_pubrel, wait_packet_ack, PUSH_READ_MAX_WAIT_MS,
 };
 use super::subscribe_manager::SubscribeManager;
 use super::subscriber::Subscriber;
@@ -163,10 +163,9 @@ where
                             ) => {
                                 match val{
                                     Ok(offset_op) => {
+                                        // the read already waited for new messages when there were none
                                         if let Some(off) = offset_op{
                                             offset = off + 1;
-                                        }else{
-                                            sleep(Duration::from_millis(100)).await;
                                         }
                                     }
                                     Err(e) => {
@@ -206,7 +205,12 @@ where
     let client_id = subscriber.client_id.clone();
 
     let results = message_storage
-        .read_topic_message(&subscriber.topic_id, offset, record_num)
+        .read_topic_message(
+            &subscriber.topic_id,
+            offset,
+            record_num,
+            PUSH_READ_MAX_WAIT_MS,
+        )
         .await?;
 
     if results.is_empty() {
@@ -27,7 +27,7 @@ use tokio::time::sleep;
 
 use super::sub_common::{
     loop_commit_offset, min_qos, publish_message_qos0, publish_message_to_client,
-    qos2_send_publish, qos2_send_pubrel, wait_packet_ack,
+    qos2_send_publish, qos2_send_pubrel, wait_packet_ack, PUSH_READ_MAX_WAIT_MS,
 };
 use super::subscribe_manager::{ShareLeaderSubscribeData, SubscribeManager};
 use crate::handler::cache::{CacheManager, QosAckPackageData, QosAckPackageType, QosAckPacketInfo};
@@ -247,7 +247,12 @@ where
     let record_num = calc_record_num(sub_list.len());
 
     let results = message_storage
-        .read_topic_message(&sub_data.topic_id, offset, record_num as u64)
+        .read_topic_message(
+            &sub_data.topic_id,
+            offset,
+            record_num as u64,
+            PUSH_READ_MAX_WAIT_MS,
+        )
         .await?;
 
     if results.is_empty() {
@@ -24,7 +24,7 @@ use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast::Sender;
 use tokio::time::sleep;
 
-use super::sub_common::{decode_share_info, get_share_sub_leader, is_share_sub, path_regex_match};
+use super::sub_common::{decode_share_info, get_share_sub_leader, is_share_sub, path_match};
 use crate::handler::cache::CacheManager;
 use crate::subscribe::subscriber::Subscriber;
 
@@ -101,23 +101,31 @@ impl SubscribeManager {
 
     pub async fn parse_subscribe_by_new_topic(&self) {
         for (topic_name, topic) in self.metadata_cache.topic_info.clone() {
-            for (client_id, sub_list) in self.metadata_cache.subscribe_filter.clone() {
-                for (_, data) in sub_list {
-                    let subscribe = Subscribe {
-                        packet_identifier: 0,
-                        filters: vec![data.filter],
+            for (client_id, path) in self.metadata_cache.get_subscribes_by_topic(&topic_name) {
+                let data =
+                    if let Some(sub_list) = self.metadata_cache.subscribe_filter.get(&client_id) {
+                        if let Some(data) = sub_list.get(&path) {
+                            data.clone()
+                        } else {
+                            continue;
+                        }
+                    } else {
+                        continue;
                     };
-                    let subscribe_properties = data.subscribe_properties;
-                    self.parse_subscribe(
-                        topic_name.clone(),
-                        topic.topic_id.clone(),
-                        client_id.clone(),
-                        data.protocol.clone(),
-                        subscribe,
-                        subscribe_properties,
-                    )
-                    .await;
-                }
+
+                let subscribe = Subscribe {
+                    packet_identifier: 0,
+                    filters: vec![data.filter],
+                };
+                self.parse_subscribe(
+                    topic_name.clone(),
+                    topic.topic_id.clone(),
+                    client_id,
+                    data.protocol,
+                    subscribe,
+                    data.subscribe_properties,
+                )
+                .await;
             }
         }
     }
@@ -129,16 +137,28 @@ impl SubscribeManager {
         subscribe: Subscribe,
         subscribe_properties: Option<SubscribeProperties>,
     ) {
-        for (topic_name, topic) in self.metadata_cache.topic_info.clone() {
-            self.parse_subscribe(
-                topic_name,
-                topic.topic_id,
-                client_id.clone(),
-                protocol.clone(),
-                subscribe.clone(),
-                subscribe_properties.clone(),
-            )
-            .await;
+        for filter in subscribe.filters.iter() {
+            let filter_subscribe = Subscribe {
+                packet_identifier: subscribe.packet_identifier,
+                filters: vec![filter.clone()],
+            };
+            for topic_name in self.metadata_cache.get_topic_names_by_filter(&filter.path) {
+                let topic = if let Some(topic) = self.metadata_cache.get_topic_by_name(&topic_name)
+                {
+                    topic
+                } else {
+                    continue;
+                };
+                self.parse_subscribe(
+                    topic_name,
+                    topic.topic_id,
+                    client_id.clone(),
+                    protocol.clone(),
+                    filter_subscribe.clone(),
+                    subscribe_properties.clone(),
+                )
+                .await;
+            }
         }
     }
 
@@ -166,61 +186,55 @@ impl SubscribeManager {
     }
 
     pub fn remove_subscribe(&self, client_id: &str, filter_path: &[String]) {
-        for (topic_name, _) in self.metadata_cache.topic_info.clone() {
-            for path in filter_path {
-                if !path_regex_match(topic_name.clone(), path.clone()) {
-                    continue;
-                }
-
-                if is_share_sub(path.clone()) {
-                    let (group_name, sub_name) = decode_share_info(path.clone());
-                    // share leader
-                    for (key, data) in self.share_leader_subscribe.clone() {
-                        let mut flag = false;
-                        for (sub_key, share_sub) in data.sub_list {
-                            if share_sub.client_id == *client_id
-                                && (share_sub.group_name.is_some()
-                                    && share_sub.group_name.unwrap() == group_name)
-                                && share_sub.sub_path == sub_name
-                            {
-                                let mut_data = self.share_leader_subscribe.get_mut(&key).unwrap();
-                                mut_data.sub_list.remove(&sub_key);
-                                flag = true;
-                            }
+        for path in filter_path {
+            if is_share_sub(path.clone()) {
+                let (group_name, sub_name) = decode_share_info(path.clone());
+                // share leader
+                for (key, data) in self.share_leader_subscribe.clone() {
+                    let mut flag = false;
+                    for (sub_key, share_sub) in data.sub_list {
+                        if share_sub.client_id == *client_id
+                            && (share_sub.group_name.is_some()
+                                && share_sub.group_name.unwrap() == group_name)
+                            && share_sub.sub_path == sub_name
+                        {
+                            let mut_data = self.share_leader_subscribe.get_mut(&key).unwrap();
+                            mut_data.sub_list.remove(&sub_key);
+                            flag = true;
                         }
+                    }
 
-                        if flag {
-                            if let Some(sx) = self.share_leader_push_thread.get(&key) {
-                                match sx.send(true) {
-                                    Ok(_) => {}
-                                    Err(e) => error!("{}", e),
-                                }
+                    if flag {
+                        if let Some(sx) = self.share_leader_push_thread.get(&key) {
+                            match sx.send(true) {
+                                Ok(_) => {}
+                                Err(e) => error!("{}", e),
                             }
                         }
                     }
+                }
 
-                    // share follower
-                    for (key, data) in self.share_follower_subscribe.clone() {
-                        if data.client_id == *client_id && data.filter.path == *path {
-                            self.share_follower_subscribe.remove(&key);
-                            if let Some(sx) = self.share_follower_resub_thread.get(&key) {
-                                match sx.send(true) {
-                                    Ok(_) => {}
-                                    Err(e) => error!("{}", e),
-                                }
+                // share follower
+                for (key, data) in self.share_follower_subscribe.clone() {
+                    if data.client_id == *client_id && data.filter.path == *path {
+                        self.share_follower_subscribe.remove(&key);
+                        if let Some(sx) = self.share_follower_resub_thread.get(&key) {
+                            match sx.send(true) {
+                                Ok(_) => {}
+                                Err(e) => error!("{}", e),
                             }
                         }
                     }
-                } else {
-                    for (key, subscriber) in self.exclusive_subscribe.clone() {
-                        if subscriber.client_id == *client_id && subscriber.sub_path == *path {
-                            if let Some(sx) = self.exclusive_push_thread.get(&key) {
-                                match sx.send(true) {
-                                    Ok(_) => {}
-                                    Err(e) => error!("{}", e),
-                                }
-                                self.exclusive_subscribe.remove(&key);
+                }
+            } else {
+                for (key, subscriber) in self.exclusive_subscribe.clone() {
+                    if subscriber.client_id == *client_id && subscriber.sub_path == *path {
+                        if let Some(sx) = self.exclusive_push_thread.get(&key) {
+                            match sx.send(true) {
+                                Ok(_) => {}
+                                Err(e) => error!("{}", e),
                             }
+                            self.exclusive_subscribe.remove(&key);
                         }
                     }
                 }
@@ -248,7 +262,7 @@ impl SubscribeManager {
                 let conf = broker_mqtt_conf();
                 let (group_name, sub_name) = decode_share_info(filter.path.clone());
 
-                if path_regex_match(topic_name.clone(), sub_name.clone()) {
+                if path_match(&topic_name, &sub_name) {
                     match get_share_sub_leader(self.client_pool.clone(), group_name.clone()).await {
                         Ok(reply) => {
                             if reply.broker_id == conf.broker_id {
@@ -394,7 +408,7 @@ impl SubscribeManager {
         sub_identifier: Option<usize>,
         filter: Filter,
     ) {
-        if path_regex_match(topic_name.clone(), filter.path.clone()) {
+        if path_match(&topic_name, &filter.path) {
             let key = self.exclusive_key(&client_id, &filter.path, &topic_id);
             let sub = Subscriber {
                 protocol: protocol.clone(),
@@ -0,0 +1,330 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+const SINGLE_LEVEL_WILDCARD: &str = "+";
+const MULTI_LEVEL_WILDCARD: &str = "#";
+const SYS_TOPIC_PREFIX: &str = "$";
+
+#[derive(Clone)]
+struct TrieNode<V> {
+    children: HashMap<String, TrieNode<V>>,
+    values: HashSet<V>,
+}
+
+impl<V> Default for TrieNode<V> {
+    fn default() -> Self {
+        TrieNode {
+            children: HashMap::new(),
+            values: HashSet::new(),
+        }
+    }
+}
+
+impl<V: Clone + Eq + Hash> TrieNode<V> {
+    // Returns whether the node is empty afterwards, so the caller can drop it.
+    fn remove(&mut self, levels: &[&str], value: &V) -> bool {
+        if let Some((level, rest)) = levels.split_first() {
+            if let Some(child) = self.children.get_mut(*level) {
+                if child.remove(rest, value) {
+                    self.children.remove(*level);
+                }
+            }
+        } else {
+            self.values.remove(value);
+        }
+        self.values.is_empty() && self.children.is_empty()
+    }
+
+    fn collect_filters(&self, levels: &[&str], index: usize, results: &mut HashSet<V>) {
+        // wildcards at the first level do not match topics starting with $
+        let wildcard = index > 0 || !levels[0].starts_with(SYS_TOPIC_PREFIX);
+        if wildcard {
+            // # also matches the parent level, a/# matches a
+            if let Some(child) = self.children.get(MULTI_LEVEL_WILDCARD) {
+                results.extend(child.values.iter().cloned());
+            }
+        }
+
+        if index == levels.len() {
+            results.extend(self.values.iter().cloned());
+            return;
+        }
+
+        if let Some(child) = self.children.get(levels[index]) {
+            child.collect_filters(levels, index + 1, results);
+        }
+        if wildcard {
+            if let Some(child) = self.children.get(SINGLE_LEVEL_WILDCARD) {
+                child.collect_filters(levels, index + 1, results);
+            }
+        }
+    }
+
+    fn collect_topics(&self, levels: &[&str], index: usize, results: &mut HashSet<V>) {
+        if index == levels.len() {
+            results.extend(self.values.iter().cloned());
+            return;
+        }
+
+        match levels[index] {
+            MULTI_LEVEL_WILDCARD => {
+                results.extend(self.values.iter().cloned());
+                for (level, child) in self.children.iter() {
+                    if index == 0 && level.starts_with(SYS_TOPIC_PREFIX) {
+                        continue;
+                    }
+                    child.collect_all(results);
+                }
+            }
+            SINGLE_LEVEL_WILDCARD => {
+                for (level, child) in self.children.iter() {
+                    if index == 0 && level.starts_with(SYS_TOPIC_PREFIX) {
+                        continue;
+                    }
+                    child.collect_topics(levels, index + 1, results);
+                }
+            }
+            level => {
+                if let Some(child) = self.children.get(level) {
+                    child.collect_topics(levels, index + 1, results);
+                }
+            }
+        }
+    }
+
+    fn collect_all(&self, results: &mut HashSet<V>) {
+        results.extend(self.values.iter().cloned());
+        for child in self.children.values() {
+            child.collect_all(results);
+        }
+    }
+}
+
+/// Index of topic names or topic filters by level. A lookup only walks the levels of the
+/// topic or filter it is given, instead of comparing it with every entry.
+///
+/// The same structure serves both directions: `match_topic` treats the stored paths as
+/// filters and returns the ones matching a topic name, `match_filter` treats them as topic
+/// names and returns the ones a filter matches.
+#[derive(Clone)]
+pub struct TopicTrie<V> {
+    root: TrieNode<V>,
+}
+
+impl<V: Clone + Eq + Hash> Default for TopicTrie<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Clone + Eq + Hash> TopicTrie<V> {
+    pub fn new() -> Self {
+        TopicTrie {
+            root: TrieNode::default(),
+        }
+    }
+
+    pub fn insert(&mut self, path: &str, value: V) {
+        let mut node = &mut self.root;
+        for level in path.split('/') {
+            node = node.children.entry(level.to_string()).or_default();
+        }
+        node.values.insert(value);
+    }
+
+    pub fn remove(&mut self, path: &str, value: &V) {
+        let levels: Vec<&str> = path.split('/').collect();
+        self.root.remove(&levels, value);
+    }
+
+    /// The values of the stored filters that match topic_name.
+    pub fn match_topic(&self, topic_name: &str) -> Vec<V> {
+        let levels: Vec<&str> = topic_name.split('/').collect();
+        let mut results = HashSet::new();
+        self.root.collect_filters(&levels, 0, &mut results);
+        results.into_iter().collect()
+    }
+
+    /// The values of the stored topic names that filter matches.
+    pub fn match_filter(&self, filter: &str) -> Vec<V> {
+        let levels: Vec<&str> = filter.split('/').collect();
+        let mut results = HashSet::new();
+        self.root.collect_topics(&levels, 0, &mut results);
+        results.into_iter().collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root.values.is_empty() && self.root.children.is_empty()
+    }
+}
+
+/// Whether topic_name matches filter, following the matching rules of the MQTT
+/// specification (4.7): `+` matches exactly one level, `#` matches the parent level and any
+/// number of child levels, and a wildcard first level does not match a topic starting with `$`.
+pub fn topic_filter_match(topic_name: &str, filter: &str) -> bool {
+    let topic_levels: Vec<&str> = topic_name.split('/').collect();
+    let filter_levels: Vec<&str> = filter.split('/').collect();
+
+    if topic_name.starts_with(SYS_TOPIC_PREFIX)
+        && (filter_levels[0] == SINGLE_LEVEL_WILDCARD || filter_levels[0] == MULTI_LEVEL_WILDCARD)
+    {
+        return false;
+    }
+
+    for (i, filter_level) in filter_levels.iter().enumerate() {
+        if *filter_level == MULTI_LEVEL_WILDCARD {
+            return i == filter_levels.len() - 1;
+        }
+        let topic_level = if let Some(level) = topic_levels.get(i) {
+            level
+        } else {
+            return false;
+        };
+        if *filter_level != SINGLE_LEVEL_WILDCARD && filter_level != topic_level {
+            return false;
+        }
+    }
+    topic_levels.len() == filter_levels.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+
+    use super::{topic_filter_match, TopicTrie};
+
+    #[test]
+    fn topic_filter_match_test() {
+        assert!(topic_filter_match("a/b", "a/b"));
+        assert!(topic_filter_match("a/b", "a/+"));
+        assert!(topic_filter_match("a/", "a/+"));
+        assert!(!topic_filter_match("a/b/c", "a/+"));
+        assert!(!topic_filter_match("a", "a/+"));
+        assert!(topic_filter_match("a", "a/#"));
+        assert!(topic_filter_match("a/b/c", "a/#"));
+        assert!(!topic_filter_match("ab", "a/#"));
+        assert!(topic_filter_match("a/b/c", "#"));
+        assert!(topic_filter_match("/a", "+/a"));
+        assert!(!topic_filter_match("x/a/b", "a/+"));
+
+        assert!(!topic_filter_match("$SYS/broker", "#"));
+        assert!(!topic_filter_match("$SYS/broker", "+/broker"));
+        assert!(topic_filter_match("$SYS/broker", "$SYS/#"));
+        assert!(topic_filter_match("$SYS/broker", "$SYS/+"));
+    }
+
+    #[test]
+    fn topic_trie_test() {
+        let mut filters = TopicTrie::new();
+        filters.insert("a/+", "c1".to_string());
+        filters.insert("a/#", "c2".to_string());
+        filters.insert("#", "c3".to_string());
+        filters.insert("$SYS/#", "c4".to_string());
+
+        let mut result = filters.match_topic("a");
+        result.sort();
+        assert_eq!(result, vec!["c2".to_string(), "c3".to_string()]);
+        assert_eq!(filters.match_topic("$SYS/broker"), vec!["c4".to_string()]);
+
+        filters.remove("a/#", &"c2".to_string());
+        assert_eq!(filters.match_topic("a"), vec!["c3".to_string()]);
+        for (filter, value) in [("a/+", "c1"), ("#", "c3"), ("$SYS/#", "c4")] {
+            filters.remove(filter, &value.to_string());
+        }
+        assert!(filters.is_empty());
+
+        let mut topics = TopicTrie::new();
+        for topic_name in ["a", "a/b", "a/b/c", "$SYS/broker", "/a"] {
+            topics.insert(topic_name, topic_name.to_string());
+        }
+        let mut result = topics.match_filter("a/#");
+        result.sort();
+        assert_eq!(result, vec!["a", "a/b", "a/b/c"]);
+        assert_eq!(topics.match_filter("+/+/c"), vec!["a/b/c"]);
+        assert_eq!(topics.match_filter("+/a"), vec!["/a"]);
+        assert_eq!(topics.match_filter("+/broker"), Vec::<String>::new());
+    }
+
+    fn random_path(rng: &mut StdRng, levels: &[&str]) -> String {
+        let len = rng.gen_range(1..=4);
+        let mut path: Vec<&str> = (0..len).map(|_| *levels.choose(rng).unwrap()).collect();
+        // a filter only has # as its last level
+        if let Some(i) = path.iter().position(|level| *level == "#") {
+            path.truncate(i + 1);
+        }
+        path.join("/")
+    }
+
+    #[test]
+    fn topic_trie_matches_reference_test() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let topic_levels = ["a", "b", "", "$SYS"];
+        let filter_levels = ["a", "b", "", "$SYS", "+", "#"];
+
+        for _ in 0..50 {
+            let topic_list: HashSet<String> = (0..30)
+                .map(|_| random_path(&mut rng, &topic_levels))
+                .collect();
+            let filter_list: HashSet<String> = (0..30)
+                .map(|_| random_path(&mut rng, &filter_levels))
+                .collect();
+
+            let mut topics = TopicTrie::new();
+            for topic_name in topic_list.iter() {
+                topics.insert(topic_name, topic_name.clone());
+            }
+            let mut filters = TopicTrie::new();
+            for filter in filter_list.iter() {
+                filters.insert(filter, filter.clone());
+            }
+
+            for filter in filter_list.iter() {
+                let mut expect: Vec<String> = topic_list
+                    .iter()
+                    .filter(|topic_name| topic_filter_match(topic_name, filter))
+                    .cloned()
+                    .collect();
+                let mut result = topics.match_filter(filter);
+                expect.sort();
+                result.sort();
+                assert_eq!(result, expect, "filter {}", filter);
+            }
+
+            for topic_name in topic_list.iter() {
+                let mut expect: Vec<String> = filter_list
+                    .iter()
+                    .filter(|filter| topic_filter_match(topic_name, filter))
+                    .cloned()
+                    .collect();
+                let mut result = filters.match_topic(topic_name);
+                expect.sort();
+                result.sort();
+                assert_eq!(result, expect, "topic {}", topic_name);
+            }
+
+            // removing every entry leaves nothing behind
+            for filter in filter_list.iter() {
+                filters.remove(filter, filter);
+            }
+            assert!(filters.is_empty());
+        }
+    }
+}
@@ -81,6 +81,14 @@ pub enum PlacementCenterError {
     #[error("segment {0} state cache error, server current state {1}, passed state {2}")]
     SegmentStateError(String, String, String),
 
+    #[error("ISR {1} of Segment {0} is invalid, it must contain the leader and only replicas of the segment")]
+    SegmentIsrError(String, String),
+
+    #[error(
+        "Leader epoch of Segment {0} is {1}, the ISR change from leader epoch {2} is rejected"
+    )]
+    SegmentLeaderEpochNotMatch(String, u32, u32),
+
     #[error("Segment {0} state is {1} and no deletion is allowed")]
     NoAllowDeleteSegment(String, String),
 
@@ -57,6 +57,14 @@ impl JournalCacheManager {
         Some(res.clone())
     }
 
+    pub fn get_shard_list(&self) -> Vec<JournalShard> {
+        let mut results = Vec::new();
+        for raw in self.shard_list.iter() {
+            results.push(raw.value().clone());
+        }
+        results
+    }
+
     pub fn set_shard(&self, shard: &JournalShard) {
         self.shard_list.insert(
             self.shard_key(&shard.cluster_name, &shard.namespace, &shard.shard_name),
@@ -253,7 +253,7 @@ pub async fn gc_segment_thread(
         }
 
         // update info
-        if !flag {
+        if flag {
             // delete segment
             if let Err(e) = sync_delete_segment_info(&raft_machine_apply, &segment).await {
                 error!(
@@ -280,20 +280,22 @@ pub async fn gc_segment_thread(
                 };
             }
 
-            // update start segment by shard
-            if let Err(e) = update_start_segment_by_shard(
-                &raft_machine_apply,
-                &engine_cache,
-                &mut shard,
-                segment.segment_seq,
-            )
-            .await
-            {
-                error!(
-                    "Updating the Shard {} start segment information failed with error message {}",
-                    shard.name(),
-                    e
-                );
+            // update start segment by shard, the start segment only moves forward
+            if shard.start_segment_seq <= segment.segment_seq {
+                if let Err(e) = update_start_segment_by_shard(
+                    &raft_machine_apply,
+                    &engine_cache,
+                    &mut shard,
+                    segment.segment_seq + 1,
+                )
+                .await
+                {
+                    error!(
+                        "Updating the Shard {} start segment information failed with error message {}",
+                        shard.name(),
+                        e
+                    );
+                }
             }
 
             engine_cache.remove_wait_delete_segment(&segment);
@@ -0,0 +1,181 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use grpc_clients::pool::ClientPool;
+use log::{error, info, warn};
+use metadata_struct::journal::segment::{JournalSegment, SegmentStatus};
+
+use super::call_node::{update_cache_by_set_segment, JournalInnerCallManager};
+use crate::core::cache::PlacementCacheManager;
+use crate::journal::cache::JournalCacheManager;
+use crate::journal::services::segmet::sync_save_segment_info;
+use crate::route::apply::RaftMachineApply;
+
+/// Moves the leadership of segments whose leader node is gone to another in-sync replica.
+/// Nodes are removed from the cluster cache when their heartbeat times out.
+pub async fn segment_leader_election_thread(
+    raft_machine_apply: Arc<RaftMachineApply>,
+    engine_cache: Arc<JournalCacheManager>,
+    cluster_cache: Arc<PlacementCacheManager>,
+    call_manager: Arc<JournalInnerCallManager>,
+    client_pool: Arc<ClientPool>,
+) {
+    for shard in engine_cache.get_shard_list() {
+        for segment in engine_cache.get_segment_list_by_shard(
+            &shard.cluster_name,
+            &shard.namespace,
+            &shard.shard_name,
+        ) {
+            let is_alive = |node_id: u64| {
+                cluster_cache
+                    .get_broker_node(&segment.cluster_name, node_id)
+                    .is_some()
+            };
+            if is_alive(segment.leader)
+                || segment.status == SegmentStatus::PreDelete
+                || segment.status == SegmentStatus::Deleting
+            {
+                continue;
+            }
+
+            let new_segment = if let Some(new_segment) = elect_segment_leader(&segment, is_alive) {
+                new_segment
+            } else {
+                warn!(
+                    "Leader {} of Segment {} is offline and none of its in-sync replicas {:?} is available",
+                    segment.leader,
+                    segment.name(),
+                    segment.isr
+                );
+                continue;
+            };
+
+            if let Err(e) = sync_save_segment_info(&raft_machine_apply, &new_segment).await {
+                error!(
+                    "Failed to save the new leader of Segment {} with error message: {}",
+                    segment.name(),
+                    e
+                );
+                continue;
+            }
+
+            info!(
+                "Leader of Segment {} changes from {} to {} in leader epoch {}",
+                segment.name(),
+                segment.leader,
+                new_segment.leader,
+                new_segment.leader_epoch
+            );
+
+            if let Err(e) = update_cache_by_set_segment(
+                &segment.cluster_name,
+                &call_manager,
+                &client_pool,
+                new_segment,
+            )
+            .await
+            {
+                error!(
+                    "Failed to notify the journal nodes of the new leader of Segment {} with error message: {}",
+                    segment.name(),
+                    e
+                );
+            }
+        }
+    }
+}
+
+/// Elects the first replica that is still in sync and alive as the new leader of the segment
+/// and starts a new leader epoch. Only ISR members have every committed record, so None is
+/// returned when none of them is available.
+pub fn elect_segment_leader<F>(segment: &JournalSegment, is_alive: F) -> Option<JournalSegment>
+where
+    F: Fn(u64) -> bool,
+{
+    if segment.status == SegmentStatus::PreDelete || segment.status == SegmentStatus::Deleting {
+        return None;
+    }
+
+    let isr: Vec<u64> = segment
+        .isr
+        .iter()
+        .filter(|node_id| **node_id != segment.leader && is_alive(**node_id))
+        .copied()
+        .collect();
+
+    let leader = segment
+        .replicas
+        .iter()
+        .map(|rep| rep.node_id)
+        .find(|node_id| isr.contains(node_id))?;
+
+    let mut new_segment = segment.clone();
+    new_segment.leader = leader;
+    new_segment.leader_epoch += 1;
+    new_segment.isr = isr;
+    Some(new_segment)
+}
+
+#[cfg(test)]
+mod tests {
+    use metadata_struct::journal::segment::{JournalSegment, Replica, SegmentStatus};
+
+    use super::elect_segment_leader;
+
+    fn build_segment() -> JournalSegment {
+        let replicas = (1..=3)
+            .map(|node_id| Replica {
+                replica_seq: node_id - 1,
+                node_id,
+                fold: "/tmp/jl".to_string(),
+            })
+            .collect();
+        JournalSegment {
+            namespace: "n1".to_string(),
+            shard_name: "s1".to_string(),
+            replicas,
+            leader: 1,
+            leader_epoch: 2,
+            isr: vec![1, 2, 3],
+            status: SegmentStatus::Write,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn elect_segment_leader_test() {
+        let segment = build_segment();
+        let new_segment = elect_segment_leader(&segment, |node_id| node_id != 1).unwrap();
+        assert_eq!(new_segment.leader, 2);
+        assert_eq!(new_segment.leader_epoch, 3);
+        assert_eq!(new_segment.isr, vec![2, 3]);
+
+        // replicas that are out of sync or offline are never elected
+        let mut segment = build_segment();
+        segment.isr = vec![1, 3];
+        let new_segment = elect_segment_leader(&segment, |node_id| node_id != 1).unwrap();
+        assert_eq!(new_segment.leader, 3);
+        assert_eq!(new_segment.isr, vec![3]);
+
+        let new_segment = elect_segment_leader(&segment, |node_id| node_id == 2);
+        assert!(new_segment.is_none());
+
+        // segments that are being deleted keep their leader
+        let mut segment = build_segment();
+        segment.status = SegmentStatus::PreDelete;
+        assert!(elect_segment_leader(&segment, |node_id| node_id != 1).is_none());
+    }
+}
@@ -15,10 +15,14 @@
 use std::sync::Arc;
 use std::time::Duration;
 
+use call_node::JournalInnerCallManager;
 use gc::{gc_segment_thread, gc_shard_thread};
 use grpc_clients::pool::ClientPool;
+use leader_election::segment_leader_election_thread;
 use log::info;
 use preferred_election::PreferredElection;
+use retention::retention_segment_thread;
+use rocksdb_engine::RocksDBEngine;
 use tokio::time::sleep;
 
 use super::cache::JournalCacheManager;
@@ -27,13 +31,17 @@ use crate::route::apply::RaftMachineApply;
 
 pub mod call_node;
 pub mod gc;
+pub mod leader_election;
 pub mod preferred_election;
+pub mod retention;
 
 pub struct StorageEngineController {
     raft_machine_apply: Arc<RaftMachineApply>,
     engine_cache: Arc<JournalCacheManager>,
     cluster_cache: Arc<PlacementCacheManager>,
     client_pool: Arc<ClientPool>,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    call_manager: Arc<JournalInnerCallManager>,
 }
 
 impl StorageEngineController {
@@ -42,18 +50,24 @@ impl StorageEngineController {
         engine_cache: Arc<JournalCacheManager>,
         cluster_cache: Arc<PlacementCacheManager>,
         client_pool: Arc<ClientPool>,
+        rocksdb_engine_handler: Arc<RocksDBEngine>,
+        call_manager: Arc<JournalInnerCallManager>,
     ) -> Self {
         StorageEngineController {
             raft_machine_apply,
             engine_cache,
             cluster_cache,
             client_pool,
+            rocksdb_engine_handler,
+            call_manager,
         }
     }
 
     pub async fn start(&self) {
         self.delete_shard_gc_thread();
         self.delete_segment_gc_thread();
+        self.retention_segment_thread();
+        self.segment_leader_election_thread();
         self.preferred_replica_election();
         info!("Storage Engine Controller started successfully");
     }
@@ -96,6 +110,48 @@ impl StorageEngineController {
         });
     }
 
+    pub fn retention_segment_thread(&self) {
+        let raft_machine_apply = self.raft_machine_apply.clone();
+        let engine_cache = self.engine_cache.clone();
+        let rocksdb_engine_handler = self.rocksdb_engine_handler.clone();
+        let call_manager = self.call_manager.clone();
+        let client_pool = self.client_pool.clone();
+        tokio::spawn(async move {
+            loop {
+                retention_segment_thread(
+                    raft_machine_apply.clone(),
+                    engine_cache.clone(),
+                    rocksdb_engine_handler.clone(),
+                    call_manager.clone(),
+                    client_pool.clone(),
+                )
+                .await;
+                sleep(Duration::from_secs(5)).await;
+            }
+        });
+    }
+
+    pub fn segment_leader_election_thread(&self) {
+        let raft_machine_apply = self.raft_machine_apply.clone();
+        let engine_cache = self.engine_cache.clone();
+        let cluster_cache = self.cluster_cache.clone();
+        let call_manager = self.call_manager.clone();
+        let client_pool = self.client_pool.clone();
+        tokio::spawn(async move {
+            loop {
+                segment_leader_election_thread(
+                    raft_machine_apply.clone(),
+                    engine_cache.clone(),
+                    cluster_cache.clone(),
+                    call_manager.clone(),
+                    client_pool.clone(),
+                )
+                .await;
+                sleep(Duration::from_secs(1)).await;
+            }
+        });
+    }
+
     pub fn preferred_replica_election(&self) {
         let election = PreferredElection::new();
         tokio::spawn(async move {
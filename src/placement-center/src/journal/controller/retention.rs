@@ -0,0 +1,295 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::sync::Arc;
+
+use common_base::tools::now_second;
+use grpc_clients::pool::ClientPool;
+use log::{error, info};
+use metadata_struct::journal::namespace::namespace_retention_resource_key;
+use metadata_struct::journal::segment::SegmentStatus;
+use metadata_struct::journal::segment_meta::JournalSegmentMetadata;
+use metadata_struct::journal::shard::{JournalShard, JournalShardStatus, RetentionPolicy};
+use rocksdb_engine::RocksDBEngine;
+
+use super::call_node::{
+    update_cache_by_set_segment, update_cache_by_set_shard, JournalInnerCallManager,
+};
+use crate::core::error::PlacementCenterError;
+use crate::journal::cache::JournalCacheManager;
+use crate::journal::services::segmet::update_segment_status;
+use crate::journal::services::shard::update_start_segment_by_shard;
+use crate::route::apply::RaftMachineApply;
+use crate::storage::placement::config::ResourceConfigStorage;
+
+pub async fn retention_segment_thread(
+    raft_machine_apply: Arc<RaftMachineApply>,
+    engine_cache: Arc<JournalCacheManager>,
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+    call_manager: Arc<JournalInnerCallManager>,
+    client_pool: Arc<ClientPool>,
+) {
+    for shard in engine_cache.get_shard_list() {
+        if shard.status != JournalShardStatus::Run {
+            continue;
+        }
+
+        if let Err(e) = retention_shard(
+            &raft_machine_apply,
+            &engine_cache,
+            &rocksdb_engine_handler,
+            &call_manager,
+            &client_pool,
+            shard.clone(),
+        )
+        .await
+        {
+            error!(
+                "Failed to apply the retention policy of Shard {} with error message: {}",
+                shard.name(),
+                e
+            );
+        }
+    }
+}
+
+async fn retention_shard(
+    raft_machine_apply: &Arc<RaftMachineApply>,
+    engine_cache: &Arc<JournalCacheManager>,
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    call_manager: &Arc<JournalInnerCallManager>,
+    client_pool: &Arc<ClientPool>,
+    mut shard: JournalShard,
+) -> Result<(), PlacementCenterError> {
+    let policy = if let Some(policy) = shard_retention_policy(rocksdb_engine_handler, &shard)? {
+        policy
+    } else {
+        return Ok(());
+    };
+
+    if policy.is_unlimited() {
+        return Ok(());
+    }
+
+    let mut segments = engine_cache.get_segment_list_by_shard(
+        &shard.cluster_name,
+        &shard.namespace,
+        &shard.shard_name,
+    );
+    segments.retain(|segment| segment.segment_seq >= shard.start_segment_seq);
+    segments.sort_by_key(|segment| segment.segment_seq);
+
+    let live_segment_num = segments
+        .iter()
+        .filter(|segment| {
+            segment.status != SegmentStatus::PreDelete && segment.status != SegmentStatus::Deleting
+        })
+        .count() as u32;
+
+    // only the contiguous sealed segments at the head of the shard can be deleted,
+    // otherwise the earliest offset of the shard would have holes. Segments already
+    // marked by an earlier round that did not finish are skipped.
+    let mut sealed_metas = Vec::new();
+    let mut last_deleting = None;
+    for segment in segments.iter() {
+        if segment.status == SegmentStatus::PreDelete || segment.status == SegmentStatus::Deleting {
+            if sealed_metas.is_empty() {
+                last_deleting = Some(segment.segment_seq);
+            }
+            continue;
+        }
+        if segment.status != SegmentStatus::SealUp {
+            break;
+        }
+        if let Some(meta) = engine_cache.get_segment_meta(
+            &segment.cluster_name,
+            &segment.namespace,
+            &segment.shard_name,
+            segment.segment_seq,
+        ) {
+            sealed_metas.push(meta);
+        } else {
+            break;
+        }
+    }
+
+    let expired = expired_segments(&policy, now_second(), &sealed_metas, live_segment_num);
+    let last_expired = if let Some(seq) = expired.last().copied().max(last_deleting) {
+        seq
+    } else {
+        return Ok(());
+    };
+
+    for segment_seq in expired.iter() {
+        let mut segment = if let Some(segment) = engine_cache.get_segment(
+            &shard.cluster_name,
+            &shard.namespace,
+            &shard.shard_name,
+            *segment_seq,
+        ) {
+            segment
+        } else {
+            continue;
+        };
+
+        update_segment_status(
+            engine_cache,
+            raft_machine_apply,
+            &segment,
+            SegmentStatus::PreDelete,
+        )
+        .await?;
+
+        segment.status = SegmentStatus::PreDelete;
+        engine_cache.add_wait_delete_segment(&segment);
+
+        update_cache_by_set_segment(
+            &segment.cluster_name,
+            call_manager,
+            client_pool,
+            segment.clone(),
+        )
+        .await?;
+
+        info!(
+            "Segment {} is out of the retention policy of Shard {}, it will be deleted",
+            segment.name(),
+            shard.name()
+        );
+    }
+
+    // The start segment moves last. If marking stops halfway, the remaining segments
+    // stay above the start segment and the next round picks them up again.
+    update_start_segment_by_shard(
+        raft_machine_apply,
+        engine_cache,
+        &mut shard,
+        last_expired + 1,
+    )
+    .await?;
+    update_cache_by_set_shard(
+        &shard.cluster_name,
+        call_manager,
+        client_pool,
+        shard.clone(),
+    )
+    .await?;
+    Ok(())
+}
+
+fn shard_retention_policy(
+    rocksdb_engine_handler: &Arc<RocksDBEngine>,
+    shard: &JournalShard,
+) -> Result<Option<RetentionPolicy>, PlacementCenterError> {
+    if let Some(policy) = shard.config.retention.clone() {
+        return Ok(Some(policy));
+    }
+
+    let storage = ResourceConfigStorage::new(rocksdb_engine_handler.clone());
+    if let Some(data) = storage.get(
+        shard.cluster_name.clone(),
+        namespace_retention_resource_key(&shard.namespace),
+    )? {
+        return Ok(Some(serde_json::from_slice::<RetentionPolicy>(&data)?));
+    }
+    Ok(None)
+}
+
+/// Returns the seq of the segments outside of the policy. `segments` are the sealed
+/// segments at the head of the shard sorted from oldest to newest, and `live_segment_num`
+/// is the number of segments of the shard that are not being deleted.
+pub fn expired_segments(
+    policy: &RetentionPolicy,
+    now_sec: u64,
+    segments: &[JournalSegmentMetadata],
+    live_segment_num: u32,
+) -> Vec<u32> {
+    let mut total_bytes: u64 = segments.iter().map(|meta| meta.size.max(0) as u64).sum();
+    let mut live_segment_num = live_segment_num;
+    let mut results = Vec::new();
+
+    for meta in segments {
+        let expired_by_age = policy.max_age_sec > 0
+            && meta.end_timestamp > 0
+            && now_sec.saturating_sub(meta.end_timestamp as u64) > policy.max_age_sec;
+        let expired_by_bytes = policy.max_bytes > 0 && total_bytes > policy.max_bytes;
+        let expired_by_num =
+            policy.max_segment_num > 0 && live_segment_num > policy.max_segment_num;
+
+        if !expired_by_age && !expired_by_bytes && !expired_by_num {
+            break;
+        }
+
+        results.push(meta.segment_seq);
+        total_bytes = total_bytes.saturating_sub(meta.size.max(0) as u64);
+        live_segment_num = live_segment_num.saturating_sub(1);
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use metadata_struct::journal::segment_meta::JournalSegmentMetadata;
+    use metadata_struct::journal::shard::RetentionPolicy;
+
+    use super::expired_segments;
+
+    fn build_metas(num: u32, size: i64, end_timestamp: i64) -> Vec<JournalSegmentMetadata> {
+        (0..num)
+            .map(|i| JournalSegmentMetadata {
+                segment_seq: i,
+                size,
+                end_timestamp: end_timestamp + i as i64,
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn expired_segments_by_age_test() {
+        let policy = RetentionPolicy {
+            max_age_sec: 100,
+            ..Default::default()
+        };
+        let metas = build_metas(5, 10, 1000);
+        assert_eq!(expired_segments(&policy, 1102, &metas, 6), vec![0, 1]);
+        assert!(expired_segments(&policy, 1000, &metas, 6).is_empty());
+    }
+
+    #[test]
+    fn expired_segments_by_bytes_test() {
+        let policy = RetentionPolicy {
+            max_bytes: 25,
+            ..Default::default()
+        };
+        let metas = build_metas(5, 10, 1000);
+        assert_eq!(expired_segments(&policy, 1000, &metas, 6), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn expired_segments_by_num_test() {
+        let policy = RetentionPolicy {
+            max_segment_num: 4,
+            ..Default::default()
+        };
+        let metas = build_metas(5, 10, 1000);
+        assert_eq!(expired_segments(&policy, 1000, &metas, 6), vec![0, 1]);
+
+        // the active segment is never deleted even if the limit is smaller
+        let policy = RetentionPolicy {
+            max_segment_num: 1,
+            ..Default::default()
+        };
+        assert_eq!(expired_segments(&policy, 1000, &metas, 6).len(), 5);
+    }
+}
@@ -95,6 +95,7 @@ pub async fn create_segment_by_req(
             end_offset: -1,
             start_timestamp: -1,
             end_timestamp: -1,
+            size: 0,
         };
         sync_save_segment_metadata_info(raft_machine_apply, &metadata).await?;
 
@@ -261,21 +262,32 @@ pub async fn update_segment_meta_req(
         ));
     }
 
-    if engine_cache
-        .get_segment(
-            &req.cluster_name,
-            &req.namespace,
-            &req.shard_name,
-            req.segment_no,
-        )
-        .is_none()
-    {
+    let segment = if let Some(segment) = engine_cache.get_segment(
+        &req.cluster_name,
+        &req.namespace,
+        &req.shard_name,
+        req.segment_no,
+    ) {
+        segment
+    } else {
         return Err(PlacementCenterError::SegmentDoesNotExist(format!(
             "{}_{}",
             req.shard_name, req.segment_no
         )));
     };
 
+    if !req.isr.is_empty() {
+        update_segment_isr(
+            raft_machine_apply,
+            call_manager,
+            client_pool,
+            segment,
+            req.leader_epoch,
+            req.isr.clone(),
+        )
+        .await?;
+    }
+
     let mut segment_meta = if let Some(meta) = engine_cache.get_segment_meta(
         &req.cluster_name,
         &req.namespace,
@@ -294,6 +306,10 @@ pub async fn update_segment_meta_req(
         segment_meta.start_offset = req.start_offset;
     }
 
+    if req.size > 0 {
+        segment_meta.size = req.size;
+    }
+
     if req.end_offset > 0 {
         segment_meta.end_offset = req.end_offset;
     }
@@ -314,6 +330,51 @@ pub async fn update_segment_meta_req(
     Ok(())
 }
 
+async fn update_segment_isr(
+    raft_machine_apply: &Arc<RaftMachineApply>,
+    call_manager: &Arc<JournalInnerCallManager>,
+    client_pool: &Arc<ClientPool>,
+    mut segment: JournalSegment,
+    leader_epoch: u32,
+    isr: Vec<u64>,
+) -> Result<(), PlacementCenterError> {
+    // A leader that has been replaced must not change the ISR anymore
+    if segment.leader_epoch != leader_epoch {
+        return Err(PlacementCenterError::SegmentLeaderEpochNotMatch(
+            segment.name(),
+            segment.leader_epoch,
+            leader_epoch,
+        ));
+    }
+
+    // The leader always stays in the ISR, and only replicas of the segment can join it.
+    if !isr.contains(&segment.leader)
+        || isr
+            .iter()
+            .any(|node_id| !segment.replicas.iter().any(|rep| rep.node_id == *node_id))
+    {
+        return Err(PlacementCenterError::SegmentIsrError(
+            segment.name(),
+            format!("{:?}", isr),
+        ));
+    }
+
+    if segment.isr == isr {
+        return Ok(());
+    }
+
+    segment.isr = isr;
+    sync_save_segment_info(raft_machine_apply, &segment).await?;
+    update_cache_by_set_segment(
+        &segment.cluster_name,
+        call_manager,
+        client_pool,
+        segment.clone(),
+    )
+    .await?;
+    Ok(())
+}
+
 pub async fn build_segment(
     shard_info: &JournalShard,
     engine_cache: &Arc<JournalCacheManager>,
@@ -18,7 +18,7 @@ use common_base::tools::{now_mills, unique_id};
 use grpc_clients::pool::ClientPool;
 use metadata_struct::journal::segment::SegmentStatus;
 use metadata_struct::journal::segment_meta::JournalSegmentMetadata;
-use metadata_struct::journal::shard::{JournalShard, JournalShardStatus};
+use metadata_struct::journal::shard::{JournalShard, JournalShardConfig, JournalShardStatus};
 use protocol::placement_center::placement_center_journal::{
     CreateShardReply, CreateShardRequest, DeleteShardReply, DeleteShardRequest,
 };
@@ -56,6 +56,12 @@ pub async fn create_shard_by_req(
     {
         shard
     } else {
+        let config = if req.shard_config.is_empty() {
+            JournalShardConfig::default()
+        } else {
+            serde_json::from_slice::<JournalShardConfig>(&req.shard_config)?
+        };
+
         let shard = JournalShard {
             shard_uid: unique_id(),
             cluster_name: req.cluster_name.clone(),
@@ -67,6 +73,7 @@ pub async fn create_shard_by_req(
             last_segment_seq: 0,
             status: JournalShardStatus::Run,
             create_time: now_mills(),
+            config,
         };
 
         sync_save_shard_info(raft_machine_apply, &shard).await?;
@@ -95,6 +102,7 @@ pub async fn create_shard_by_req(
             end_offset: -1,
             start_timestamp: 0,
             end_timestamp: -1,
+            size: 0,
         };
 
         sync_save_segment_metadata_info(raft_machine_apply, &metadata).await?;
@@ -228,6 +228,8 @@ impl PlacementCenter {
             self.engine_cache.clone(),
             self.cluster_cache.clone(),
             self.client_pool.clone(),
+            self.rocksdb_engine_handler.clone(),
+            self.call_manager.clone(),
         );
         tokio::spawn(async move {
             journal_controller.start().await;
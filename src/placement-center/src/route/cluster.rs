@@ -85,11 +85,24 @@ impl DataRouteCluster {
         Ok(())
     }
 
-    pub fn set_resource_config(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
+    // Returns the stored config when it is not the one a compare-and-set expects, the
+    // state machine applies the writes one by one so the check and the write are atomic
+    pub fn set_resource_config(
+        &self,
+        value: Vec<u8>,
+    ) -> Result<Option<Vec<u8>>, PlacementCenterError> {
         let req = SetResourceConfigRequest::decode(value.as_ref())?;
         let config_storage = ResourceConfigStorage::new(self.rocksdb_engine_handler.clone());
+        if req.compare_and_set {
+            let current = config_storage
+                .get(req.cluster_name.clone(), req.resources.clone())?
+                .unwrap_or_default();
+            if current != req.expected_config {
+                return Ok(Some(current));
+            }
+        }
         config_storage.save(req.cluster_name, req.resources, req.config)?;
-        Ok(())
+        Ok(None)
     }
 
     pub fn delete_resource_config(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
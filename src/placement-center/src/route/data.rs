@@ -74,4 +74,10 @@ pub enum StorageDataType {
     MqttDeleteAcl,
     MqttSetBlacklist,
     MqttDeleteBlacklist,
+    MqttSetTopicRewriteRule,
+    MqttDeleteTopicRewriteRule,
+    MqttSetRule,
+    MqttDeleteRule,
+    MqttSetPsk,
+    MqttDeletePsk,
 }
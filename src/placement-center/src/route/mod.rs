@@ -96,8 +96,7 @@ impl DataRoute {
             StorageDataType::ClusterDeleteCluster => Ok(None),
 
             StorageDataType::ClusterSetResourceConfig => {
-                self.route_cluster.set_resource_config(storage_data.value)?;
-                Ok(None)
+                self.route_cluster.set_resource_config(storage_data.value)
             }
             StorageDataType::ClusterDeleteResourceConfig => {
                 self.route_cluster
@@ -169,6 +168,32 @@ impl DataRoute {
                 self.route_cluster.delete_blacklist(storage_data.value)?;
                 Ok(None)
             }
+            StorageDataType::MqttSetTopicRewriteRule => {
+                self.route_mqtt
+                    .create_topic_rewrite_rule(storage_data.value)?;
+                Ok(None)
+            }
+            StorageDataType::MqttDeleteTopicRewriteRule => {
+                self.route_mqtt
+                    .delete_topic_rewrite_rule(storage_data.value)?;
+                Ok(None)
+            }
+            StorageDataType::MqttSetRule => {
+                self.route_mqtt.create_rule(storage_data.value)?;
+                Ok(None)
+            }
+            StorageDataType::MqttDeleteRule => {
+                self.route_mqtt.delete_rule(storage_data.value)?;
+                Ok(None)
+            }
+            StorageDataType::MqttSetPsk => {
+                self.route_mqtt.create_psk(storage_data.value)?;
+                Ok(None)
+            }
+            StorageDataType::MqttDeletePsk => {
+                self.route_mqtt.delete_psk(storage_data.value)?;
+                Ok(None)
+            }
             StorageDataType::MqttSetUser => {
                 self.route_mqtt.create_user(storage_data.value)?;
                 Ok(None)
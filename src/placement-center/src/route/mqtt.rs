@@ -14,18 +14,26 @@
 
 use std::sync::Arc;
 
+use metadata_struct::mqtt::psk::MqttPskIdentity;
+use metadata_struct::mqtt::rule::MqttRule;
 use metadata_struct::mqtt::session::MqttSession;
 use metadata_struct::mqtt::topic::MqttTopic;
+use metadata_struct::mqtt::topic_rewrite_rule::MqttTopicRewriteRule;
 use prost::Message as _;
 use protocol::placement_center::placement_center_mqtt::{
-    CreateSessionRequest, CreateUserRequest, DeleteSessionRequest, DeleteTopicRequest,
-    DeleteUserRequest, SaveLastWillMessageRequest, UpdateSessionRequest,
+    CreatePskRequest, CreateRuleRequest, CreateSessionRequest, CreateTopicRewriteRuleRequest,
+    CreateUserRequest, DeletePskRequest, DeleteRuleRequest, DeleteSessionRequest,
+    DeleteTopicRequest, DeleteTopicRewriteRuleRequest, DeleteUserRequest,
+    SaveLastWillMessageRequest, UpdateSessionRequest,
 };
 
 use crate::core::error::PlacementCenterError;
 use crate::storage::mqtt::lastwill::MqttLastWillStorage;
+use crate::storage::mqtt::psk::MqttPskStorage;
+use crate::storage::mqtt::rule::MqttRuleStorage;
 use crate::storage::mqtt::session::MqttSessionStorage;
 use crate::storage::mqtt::topic::MqttTopicStorage;
+use crate::storage::mqtt::topic_rewrite_rule::MqttTopicRewriteRuleStorage;
 use crate::storage::mqtt::user::MqttUserStorage;
 use crate::storage::rocksdb::RocksDBEngine;
 
@@ -69,6 +77,51 @@ impl DataRouteMqtt {
         Ok(())
     }
 
+    pub fn create_topic_rewrite_rule(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
+        let req = CreateTopicRewriteRuleRequest::decode(value.as_ref())?;
+        let storage = MqttTopicRewriteRuleStorage::new(self.rocksdb_engine_handler.clone());
+        let rule = serde_json::from_slice::<MqttTopicRewriteRule>(&req.rule)?;
+        storage.save(&req.cluster_name, rule)?;
+        Ok(())
+    }
+
+    pub fn delete_topic_rewrite_rule(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
+        let req = DeleteTopicRewriteRuleRequest::decode(value.as_ref())?;
+        let storage = MqttTopicRewriteRuleStorage::new(self.rocksdb_engine_handler.clone());
+        storage.delete(&req.cluster_name, &req.action, &req.source_topic)?;
+        Ok(())
+    }
+
+    pub fn create_rule(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
+        let req = CreateRuleRequest::decode(value.as_ref())?;
+        let storage = MqttRuleStorage::new(self.rocksdb_engine_handler.clone());
+        let rule = serde_json::from_slice::<MqttRule>(&req.rule)?;
+        storage.save(&req.cluster_name, rule)?;
+        Ok(())
+    }
+
+    pub fn delete_rule(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
+        let req = DeleteRuleRequest::decode(value.as_ref())?;
+        let storage = MqttRuleStorage::new(self.rocksdb_engine_handler.clone());
+        storage.delete(&req.cluster_name, &req.rule_name)?;
+        Ok(())
+    }
+
+    pub fn create_psk(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
+        let req = CreatePskRequest::decode(value.as_ref())?;
+        let storage = MqttPskStorage::new(self.rocksdb_engine_handler.clone());
+        let psk = serde_json::from_slice::<MqttPskIdentity>(&req.psk)?;
+        storage.save(&req.cluster_name, psk)?;
+        Ok(())
+    }
+
+    pub fn delete_psk(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
+        let req = DeletePskRequest::decode(value.as_ref())?;
+        let storage = MqttPskStorage::new(self.rocksdb_engine_handler.clone());
+        storage.delete(&req.cluster_name, &req.identity)?;
+        Ok(())
+    }
+
     pub fn save_last_will_message(&self, value: Vec<u8>) -> Result<(), PlacementCenterError> {
         let req = SaveLastWillMessageRequest::decode(value.as_ref())?;
         let storage = MqttLastWillStorage::new(self.rocksdb_engine_handler.clone());
@@ -193,7 +193,11 @@ impl PlacementCenterService for GrpcPlacementService {
         );
 
         match self.raft_machine_apply.client_write(data).await {
-            Ok(_) => return Ok(Response::new(SetResourceConfigReply::default())),
+            Ok(resp) => {
+                // the state machine answers with the stored config when the compare failed
+                let conflict = resp.is_some_and(|resp| resp.data.value.is_some());
+                return Ok(Response::new(SetResourceConfigReply { conflict }));
+            }
             Err(e) => {
                 return Err(Status::cancelled(e.to_string()));
             }
@@ -17,16 +17,20 @@ use std::sync::Arc;
 use prost::Message;
 use protocol::placement_center::placement_center_mqtt::mqtt_service_server::MqttService;
 use protocol::placement_center::placement_center_mqtt::{
-    CreateAclReply, CreateAclRequest, CreateBlacklistReply, CreateBlacklistRequest,
-    CreateSessionReply, CreateSessionRequest, CreateTopicReply, CreateTopicRequest,
-    CreateUserReply, CreateUserRequest, DeleteAclReply, DeleteAclRequest, DeleteBlacklistReply,
-    DeleteBlacklistRequest, DeleteSessionReply, DeleteSessionRequest, DeleteTopicReply,
-    DeleteTopicRequest, DeleteUserReply, DeleteUserRequest, GetShareSubLeaderReply,
+    CreateAclReply, CreateAclRequest, CreateBlacklistReply, CreateBlacklistRequest, CreatePskReply,
+    CreatePskRequest, CreateRuleReply, CreateRuleRequest, CreateSessionReply, CreateSessionRequest,
+    CreateTopicReply, CreateTopicRequest, CreateTopicRewriteRuleReply,
+    CreateTopicRewriteRuleRequest, CreateUserReply, CreateUserRequest, DeleteAclReply,
+    DeleteAclRequest, DeleteBlacklistReply, DeleteBlacklistRequest, DeletePskReply,
+    DeletePskRequest, DeleteRuleReply, DeleteRuleRequest, DeleteSessionReply, DeleteSessionRequest,
+    DeleteTopicReply, DeleteTopicRequest, DeleteTopicRewriteRuleReply,
+    DeleteTopicRewriteRuleRequest, DeleteUserReply, DeleteUserRequest, GetShareSubLeaderReply,
     GetShareSubLeaderRequest, ListAclReply, ListAclRequest, ListBlacklistReply,
-    ListBlacklistRequest, ListSessionReply, ListSessionRequest, ListTopicReply, ListTopicRequest,
-    ListUserReply, ListUserRequest, SaveLastWillMessageReply, SaveLastWillMessageRequest,
-    SetTopicRetainMessageReply, SetTopicRetainMessageRequest, UpdateSessionReply,
-    UpdateSessionRequest,
+    ListBlacklistRequest, ListPskReply, ListPskRequest, ListRuleReply, ListRuleRequest,
+    ListSessionReply, ListSessionRequest, ListTopicReply, ListTopicRequest,
+    ListTopicRewriteRuleReply, ListTopicRewriteRuleRequest, ListUserReply, ListUserRequest,
+    SaveLastWillMessageReply, SaveLastWillMessageRequest, SetTopicRetainMessageReply,
+    SetTopicRetainMessageRequest, UpdateSessionReply, UpdateSessionRequest,
 };
 use tonic::{Request, Response, Status};
 
@@ -37,8 +41,11 @@ use crate::route::apply::RaftMachineApply;
 use crate::route::data::{StorageData, StorageDataType};
 use crate::storage::mqtt::acl::AclStorage;
 use crate::storage::mqtt::blacklist::MqttBlackListStorage;
+use crate::storage::mqtt::psk::MqttPskStorage;
+use crate::storage::mqtt::rule::MqttRuleStorage;
 use crate::storage::mqtt::session::MqttSessionStorage;
 use crate::storage::mqtt::topic::MqttTopicStorage;
+use crate::storage::mqtt::topic_rewrite_rule::MqttTopicRewriteRuleStorage;
 use crate::storage::mqtt::user::MqttUserStorage;
 use crate::storage::rocksdb::RocksDBEngine;
 
@@ -502,4 +509,165 @@ impl MqttService for GrpcMqttService {
             }
         }
     }
+
+    async fn list_topic_rewrite_rule(
+        &self,
+        request: Request<ListTopicRewriteRuleRequest>,
+    ) -> Result<Response<ListTopicRewriteRuleReply>, Status> {
+        let req = request.into_inner();
+        let storage = MqttTopicRewriteRuleStorage::new(self.rocksdb_engine_handler.clone());
+        match storage.list(&req.cluster_name) {
+            Ok(list) => {
+                let mut rules = Vec::new();
+                for rule in list {
+                    match rule.encode() {
+                        Ok(data) => {
+                            rules.push(data);
+                        }
+                        Err(e) => {
+                            return Err(Status::cancelled(e.to_string()));
+                        }
+                    }
+                }
+                Ok(Response::new(ListTopicRewriteRuleReply { rules }))
+            }
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+
+    async fn create_topic_rewrite_rule(
+        &self,
+        request: Request<CreateTopicRewriteRuleRequest>,
+    ) -> Result<Response<CreateTopicRewriteRuleReply>, Status> {
+        let req = request.into_inner();
+        let data = StorageData::new(
+            StorageDataType::MqttSetTopicRewriteRule,
+            CreateTopicRewriteRuleRequest::encode_to_vec(&req),
+        );
+
+        match self.raft_machine_apply.client_write(data).await {
+            Ok(_) => Ok(Response::new(CreateTopicRewriteRuleReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn delete_topic_rewrite_rule(
+        &self,
+        request: Request<DeleteTopicRewriteRuleRequest>,
+    ) -> Result<Response<DeleteTopicRewriteRuleReply>, Status> {
+        let req = request.into_inner();
+        let data = StorageData::new(
+            StorageDataType::MqttDeleteTopicRewriteRule,
+            DeleteTopicRewriteRuleRequest::encode_to_vec(&req),
+        );
+
+        match self.raft_machine_apply.client_write(data).await {
+            Ok(_) => Ok(Response::new(DeleteTopicRewriteRuleReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn list_rule(
+        &self,
+        request: Request<ListRuleRequest>,
+    ) -> Result<Response<ListRuleReply>, Status> {
+        let req = request.into_inner();
+        let storage = MqttRuleStorage::new(self.rocksdb_engine_handler.clone());
+        match storage.list(&req.cluster_name) {
+            Ok(list) => {
+                let mut rules = Vec::new();
+                for rule in list {
+                    match rule.encode() {
+                        Ok(data) => {
+                            rules.push(data);
+                        }
+                        Err(e) => {
+                            return Err(Status::cancelled(e.to_string()));
+                        }
+                    }
+                }
+                Ok(Response::new(ListRuleReply { rules }))
+            }
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+
+    async fn create_rule(
+        &self,
+        request: Request<CreateRuleRequest>,
+    ) -> Result<Response<CreateRuleReply>, Status> {
+        let req = request.into_inner();
+        let data = StorageData::new(
+            StorageDataType::MqttSetRule,
+            CreateRuleRequest::encode_to_vec(&req),
+        );
+
+        match self.raft_machine_apply.client_write(data).await {
+            Ok(_) => Ok(Response::new(CreateRuleReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn delete_rule(
+        &self,
+        request: Request<DeleteRuleRequest>,
+    ) -> Result<Response<DeleteRuleReply>, Status> {
+        let req = request.into_inner();
+        let data = StorageData::new(
+            StorageDataType::MqttDeleteRule,
+            DeleteRuleRequest::encode_to_vec(&req),
+        );
+
+        match self.raft_machine_apply.client_write(data).await {
+            Ok(_) => Ok(Response::new(DeleteRuleReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn list_psk(
+        &self,
+        request: Request<ListPskRequest>,
+    ) -> Result<Response<ListPskReply>, Status> {
+        let req = request.into_inner();
+        let storage = MqttPskStorage::new(self.rocksdb_engine_handler.clone());
+        match storage.list(&req.cluster_name) {
+            Ok(list) => {
+                let psks = list.iter().map(|psk| psk.encode()).collect();
+                Ok(Response::new(ListPskReply { psks }))
+            }
+            Err(e) => Err(Status::internal(e.to_string())),
+        }
+    }
+
+    async fn create_psk(
+        &self,
+        request: Request<CreatePskRequest>,
+    ) -> Result<Response<CreatePskReply>, Status> {
+        let req = request.into_inner();
+        let data = StorageData::new(
+            StorageDataType::MqttSetPsk,
+            CreatePskRequest::encode_to_vec(&req),
+        );
+
+        match self.raft_machine_apply.client_write(data).await {
+            Ok(_) => Ok(Response::new(CreatePskReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
+
+    async fn delete_psk(
+        &self,
+        request: Request<DeletePskRequest>,
+    ) -> Result<Response<DeletePskReply>, Status> {
+        let req = request.into_inner();
+        let data = StorageData::new(
+            StorageDataType::MqttDeletePsk,
+            DeletePskRequest::encode_to_vec(&req),
+        );
+
+        match self.raft_machine_apply.client_write(data).await {
+            Ok(_) => Ok(Response::new(DeletePskReply::default())),
+            Err(e) => Err(Status::cancelled(e.to_string())),
+        }
+    }
 }
@@ -207,3 +207,34 @@ pub fn storage_key_mqtt_blacklist(
 pub fn storage_key_mqtt_blacklist_prefix(cluster_name: &str) -> String {
     format!("/mqtt/blacklist/{}/", cluster_name)
 }
+
+pub fn storage_key_mqtt_topic_rewrite_rule(
+    cluster_name: &str,
+    action: &str,
+    source_topic: &str,
+) -> String {
+    format!(
+        "/mqtt/topic_rewrite_rule/{}/{}/{}",
+        cluster_name, action, source_topic
+    )
+}
+
+pub fn storage_key_mqtt_topic_rewrite_rule_prefix(cluster_name: &str) -> String {
+    format!("/mqtt/topic_rewrite_rule/{}/", cluster_name)
+}
+
+pub fn storage_key_mqtt_rule(cluster_name: &str, rule_name: &str) -> String {
+    format!("/mqtt/rule/{}/{}", cluster_name, rule_name)
+}
+
+pub fn storage_key_mqtt_rule_prefix(cluster_name: &str) -> String {
+    format!("/mqtt/rule/{}/", cluster_name)
+}
+
+pub fn storage_key_mqtt_psk(cluster_name: &str, identity: &str) -> String {
+    format!("/mqtt/psk/{}/{}", cluster_name, identity)
+}
+
+pub fn storage_key_mqtt_psk_prefix(cluster_name: &str) -> String {
+    format!("/mqtt/psk/{}/", cluster_name)
+}
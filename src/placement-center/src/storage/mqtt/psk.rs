@@ -0,0 +1,103 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use std::sync::Arc;
+
+use common_base::error::common::CommonError;
+use metadata_struct::mqtt::psk::MqttPskIdentity;
+
+use crate::storage::engine::{
+    engine_delete_by_cluster, engine_prefix_list_by_cluster, engine_save_by_cluster,
+};
+use crate::storage::keys::{storage_key_mqtt_psk, storage_key_mqtt_psk_prefix};
+use crate::storage::rocksdb::RocksDBEngine;
+
+pub struct MqttPskStorage {
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+}
+
+impl MqttPskStorage {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> Self {
+        MqttPskStorage {
+            rocksdb_engine_handler,
+        }
+    }
+
+    pub fn save(&self, cluster_name: &str, psk: MqttPskIdentity) -> Result<(), CommonError> {
+        let key = storage_key_mqtt_psk(cluster_name, &psk.identity);
+        engine_save_by_cluster(self.rocksdb_engine_handler.clone(), key, psk)
+    }
+
+    pub fn list(&self, cluster_name: &str) -> Result<Vec<MqttPskIdentity>, CommonError> {
+        let prefix_key = storage_key_mqtt_psk_prefix(cluster_name);
+        let data = engine_prefix_list_by_cluster(self.rocksdb_engine_handler.clone(), prefix_key)?;
+        let mut results = Vec::new();
+        for raw in data {
+            results.push(serde_json::from_slice::<MqttPskIdentity>(&raw.data)?);
+        }
+        Ok(results)
+    }
+
+    pub fn delete(&self, cluster_name: &str, identity: &str) -> Result<(), CommonError> {
+        let key = storage_key_mqtt_psk(cluster_name, identity);
+        engine_delete_by_cluster(self.rocksdb_engine_handler.clone(), key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::remove_dir_all;
+    use std::sync::Arc;
+
+    use common_base::config::placement_center::placement_center_test_conf;
+    use metadata_struct::mqtt::psk::MqttPskIdentity;
+
+    use crate::storage::mqtt::psk::MqttPskStorage;
+    use crate::storage::rocksdb::{column_family_list, RocksDBEngine};
+
+    #[tokio::test]
+    async fn psk_storage_test() {
+        let config = placement_center_test_conf();
+
+        let rs = Arc::new(RocksDBEngine::new(
+            config.rocksdb.data_path.as_str(),
+            config.rocksdb.max_open_files.unwrap(),
+            column_family_list(),
+        ));
+        let storage = MqttPskStorage::new(rs);
+        let cluster_name = "test_cluster".to_string();
+        let psk1 = MqttPskIdentity {
+            identity: "device-1".to_string(),
+            psk: "0102".to_string(),
+        };
+        let psk2 = MqttPskIdentity {
+            identity: "device-2".to_string(),
+            psk: "0304".to_string(),
+        };
+        storage.save(&cluster_name, psk1.clone()).unwrap();
+        storage.save(&cluster_name, psk2).unwrap();
+        assert_eq!(storage.list(&cluster_name).unwrap().len(), 2);
+
+        // identities are stored under their own keys, saving one does not touch the others
+        let psk1 = MqttPskIdentity {
+            identity: "device-1".to_string(),
+            psk: "0506".to_string(),
+        };
+        storage.save(&cluster_name, psk1.clone()).unwrap();
+        storage.delete(&cluster_name, "device-2").unwrap();
+        assert_eq!(storage.list(&cluster_name).unwrap(), vec![psk1]);
+
+        remove_dir_all(config.rocksdb.data_path).unwrap();
+    }
+}
@@ -0,0 +1,124 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::sync::Arc;
+
+use common_base::error::common::CommonError;
+use metadata_struct::mqtt::topic_rewrite_rule::MqttTopicRewriteRule;
+
+use crate::storage::engine::{
+    engine_delete_by_cluster, engine_prefix_list_by_cluster, engine_save_by_cluster,
+};
+use crate::storage::keys::{
+    storage_key_mqtt_topic_rewrite_rule, storage_key_mqtt_topic_rewrite_rule_prefix,
+};
+use crate::storage::rocksdb::RocksDBEngine;
+
+pub struct MqttTopicRewriteRuleStorage {
+    rocksdb_engine_handler: Arc<RocksDBEngine>,
+}
+
+impl MqttTopicRewriteRuleStorage {
+    pub fn new(rocksdb_engine_handler: Arc<RocksDBEngine>) -> Self {
+        MqttTopicRewriteRuleStorage {
+            rocksdb_engine_handler,
+        }
+    }
+
+    pub fn save(&self, cluster_name: &str, rule: MqttTopicRewriteRule) -> Result<(), CommonError> {
+        let key = storage_key_mqtt_topic_rewrite_rule(
+            cluster_name,
+            &rule.action.to_string(),
+            &rule.source_topic,
+        );
+        engine_save_by_cluster(self.rocksdb_engine_handler.clone(), key, rule)
+    }
+
+    pub fn list(&self, cluster_name: &str) -> Result<Vec<MqttTopicRewriteRule>, CommonError> {
+        let prefix_key = storage_key_mqtt_topic_rewrite_rule_prefix(cluster_name);
+        let data = engine_prefix_list_by_cluster(self.rocksdb_engine_handler.clone(), prefix_key)?;
+        let mut results = Vec::new();
+        for raw in data {
+            results.push(serde_json::from_slice::<MqttTopicRewriteRule>(&raw.data)?);
+        }
+        Ok(results)
+    }
+
+    pub fn delete(
+        &self,
+        cluster_name: &str,
+        action: &str,
+        source_topic: &str,
+    ) -> Result<(), CommonError> {
+        let key = storage_key_mqtt_topic_rewrite_rule(cluster_name, action, source_topic);
+        engine_delete_by_cluster(self.rocksdb_engine_handler.clone(), key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::remove_dir_all;
+    use std::sync::Arc;
+
+    use common_base::config::placement_center::placement_center_test_conf;
+    use metadata_struct::mqtt::topic_rewrite_rule::{MqttTopicRewriteAction, MqttTopicRewriteRule};
+
+    use crate::storage::mqtt::topic_rewrite_rule::MqttTopicRewriteRuleStorage;
+    use crate::storage::rocksdb::{column_family_list, RocksDBEngine};
+
+    #[tokio::test]
+    async fn topic_rewrite_rule_storage_test() {
+        let config = placement_center_test_conf();
+
+        let rs = Arc::new(RocksDBEngine::new(
+            config.rocksdb.data_path.as_str(),
+            config.rocksdb.max_open_files.unwrap(),
+            column_family_list(),
+        ));
+        let storage = MqttTopicRewriteRuleStorage::new(rs);
+        let cluster_name = "test_cluster".to_string();
+        let rule = MqttTopicRewriteRule {
+            action: MqttTopicRewriteAction::All,
+            source_topic: "x/y/+".to_string(),
+            dest_topic: "z/y/$1".to_string(),
+            regex: "".to_string(),
+            create_time: 1,
+        };
+        storage.save(&cluster_name, rule.clone()).unwrap();
+
+        let rule2 = MqttTopicRewriteRule {
+            action: MqttTopicRewriteAction::Publish,
+            source_topic: "x/y/+".to_string(),
+            dest_topic: "z/p/$1".to_string(),
+            regex: "".to_string(),
+            create_time: 2,
+        };
+        storage.save(&cluster_name, rule2).unwrap();
+
+        let res = storage.list(&cluster_name).unwrap();
+        assert_eq!(res.len(), 2);
+
+        storage
+            .delete(
+                &cluster_name,
+                &MqttTopicRewriteAction::Publish.to_string(),
+                "x/y/+",
+            )
+            .unwrap();
+
+        let res = storage.list(&cluster_name).unwrap();
+        assert_eq!(res, vec![rule]);
+
+        remove_dir_all(config.rocksdb.data_path).unwrap();
+    }
+}
@@ -19,13 +19,15 @@ use prost::Message as _;
 use tokio_util::codec;
 
 use super::journal_engine::{
-    ApiKey, CreateShardReq, CreateShardReqBody, CreateShardResp, CreateShardRespBody,
-    DeleteShardReq, DeleteShardReqBody, DeleteShardResp, DeleteShardRespBody, FetchOffsetReq,
-    FetchOffsetReqBody, FetchOffsetResp, FetchOffsetRespBody, GetClusterMetadataReq,
-    GetClusterMetadataResp, GetClusterMetadataRespBody, GetShardMetadataReq,
-    GetShardMetadataReqBody, GetShardMetadataResp, GetShardMetadataRespBody, ReadReq, ReadReqBody,
-    ReadResp, ReadRespBody, ReqHeader, RespHeader, WriteReq, WriteReqBody, WriteResp,
-    WriteRespBody,
+    ApiKey, CommitOffsetReq, CommitOffsetReqBody, CommitOffsetResp, CommitOffsetRespBody,
+    CreateShardReq, CreateShardReqBody, CreateShardResp, CreateShardRespBody, DeleteShardReq,
+    DeleteShardReqBody, DeleteShardResp, DeleteShardRespBody, FetchOffsetReq, FetchOffsetReqBody,
+    FetchOffsetResp, FetchOffsetRespBody, GetClusterMetadataReq, GetClusterMetadataResp,
+    GetClusterMetadataRespBody, GetShardMetadataReq, GetShardMetadataReqBody, GetShardMetadataResp,
+    GetShardMetadataRespBody, HeartbeatReq, HeartbeatReqBody, HeartbeatResp, HeartbeatRespBody,
+    JoinGroupReq, JoinGroupReqBody, JoinGroupResp, JoinGroupRespBody, LeaveGroupReq,
+    LeaveGroupReqBody, LeaveGroupResp, LeaveGroupRespBody, ReadReq, ReadReqBody, ReadResp,
+    ReadRespBody, ReqHeader, RespHeader, WriteReq, WriteReqBody, WriteResp, WriteRespBody,
 };
 use super::Error;
 
@@ -54,6 +56,18 @@ pub enum JournalEnginePacket {
     FetchOffsetReq(FetchOffsetReq),
     FetchOffsetResp(FetchOffsetResp),
 
+    // CommitOffset
+    CommitOffsetReq(CommitOffsetReq),
+    CommitOffsetResp(CommitOffsetResp),
+
+    // Consumer Group
+    JoinGroupReq(JoinGroupReq),
+    JoinGroupResp(JoinGroupResp),
+    HeartbeatReq(HeartbeatReq),
+    HeartbeatResp(HeartbeatResp),
+    LeaveGroupReq(LeaveGroupReq),
+    LeaveGroupResp(LeaveGroupResp),
+
     // CreateShard
     CreateShardReq(CreateShardReq),
     CreateShardResp(CreateShardResp),
@@ -76,6 +90,14 @@ impl fmt::Display for JournalEnginePacket {
             JournalEnginePacket::GetShardMetadataResp(_) => write!(f, "GetShardMetadataResp"),
             JournalEnginePacket::FetchOffsetReq(_) => write!(f, "FetchOffsetReq"),
             JournalEnginePacket::FetchOffsetResp(_) => write!(f, "FetchOffsetResp"),
+            JournalEnginePacket::CommitOffsetReq(_) => write!(f, "CommitOffsetReq"),
+            JournalEnginePacket::CommitOffsetResp(_) => write!(f, "CommitOffsetResp"),
+            JournalEnginePacket::JoinGroupReq(_) => write!(f, "JoinGroupReq"),
+            JournalEnginePacket::JoinGroupResp(_) => write!(f, "JoinGroupResp"),
+            JournalEnginePacket::HeartbeatReq(_) => write!(f, "HeartbeatReq"),
+            JournalEnginePacket::HeartbeatResp(_) => write!(f, "HeartbeatResp"),
+            JournalEnginePacket::LeaveGroupReq(_) => write!(f, "LeaveGroupReq"),
+            JournalEnginePacket::LeaveGroupResp(_) => write!(f, "LeaveGroupResp"),
             JournalEnginePacket::CreateShardReq(_) => write!(f, "CreateShardReq"),
             JournalEnginePacket::CreateShardResp(_) => write!(f, "CreateShardResp"),
             JournalEnginePacket::DeleteShardReq(_) => write!(f, "DeleteShardReq"),
@@ -184,6 +206,66 @@ impl codec::Encoder<JournalEnginePacket> for JournalServerCodec {
                 body_byte = FetchOffsetRespBody::encode_to_vec(&body);
             }
 
+            // CommitOffset
+            JournalEnginePacket::CommitOffsetReq(data) => {
+                let header = data.header.unwrap();
+                let body = data.body.unwrap();
+                header_byte = ReqHeader::encode_to_vec(&header);
+                body_byte = CommitOffsetReqBody::encode_to_vec(&body);
+                req_type = 1;
+            }
+            JournalEnginePacket::CommitOffsetResp(data) => {
+                let header = data.header.unwrap();
+                let body = data.body.unwrap();
+                header_byte = RespHeader::encode_to_vec(&header);
+                body_byte = CommitOffsetRespBody::encode_to_vec(&body);
+            }
+
+            // JoinGroup
+            JournalEnginePacket::JoinGroupReq(data) => {
+                let header = data.header.unwrap();
+                let body = data.body.unwrap();
+                header_byte = ReqHeader::encode_to_vec(&header);
+                body_byte = JoinGroupReqBody::encode_to_vec(&body);
+                req_type = 1;
+            }
+            JournalEnginePacket::JoinGroupResp(data) => {
+                let header = data.header.unwrap();
+                let body = data.body.unwrap();
+                header_byte = RespHeader::encode_to_vec(&header);
+                body_byte = JoinGroupRespBody::encode_to_vec(&body);
+            }
+
+            // Heartbeat
+            JournalEnginePacket::HeartbeatReq(data) => {
+                let header = data.header.unwrap();
+                let body = data.body.unwrap();
+                header_byte = ReqHeader::encode_to_vec(&header);
+                body_byte = HeartbeatReqBody::encode_to_vec(&body);
+                req_type = 1;
+            }
+            JournalEnginePacket::HeartbeatResp(data) => {
+                let header = data.header.unwrap();
+                let body = data.body.unwrap();
+                header_byte = RespHeader::encode_to_vec(&header);
+                body_byte = HeartbeatRespBody::encode_to_vec(&body);
+            }
+
+            // LeaveGroup
+            JournalEnginePacket::LeaveGroupReq(data) => {
+                let header = data.header.unwrap();
+                let body = data.body.unwrap();
+                header_byte = ReqHeader::encode_to_vec(&header);
+                body_byte = LeaveGroupReqBody::encode_to_vec(&body);
+                req_type = 1;
+            }
+            JournalEnginePacket::LeaveGroupResp(data) => {
+                let header = data.header.unwrap();
+                let body = data.body.unwrap();
+                header_byte = RespHeader::encode_to_vec(&header);
+                body_byte = LeaveGroupRespBody::encode_to_vec(&body);
+            }
+
             // CreateShard
             JournalEnginePacket::CreateShardReq(data) => {
                 let header = data.header.unwrap();
@@ -335,6 +417,14 @@ impl codec::Decoder for JournalServerCodec {
 
                         ApiKey::FetchOffset => fetch_offset_req(body_bytes, header),
 
+                        ApiKey::CommitOffset => commit_offset_req(body_bytes, header),
+
+                        ApiKey::JoinGroup => join_group_req(body_bytes, header),
+
+                        ApiKey::Heartbeat => heartbeat_req(body_bytes, header),
+
+                        ApiKey::LeaveGroup => leave_group_req(body_bytes, header),
+
                         ApiKey::CreateShard => create_shard_req(body_bytes, header),
 
                         ApiKey::DeleteShard => delete_shard_req(body_bytes, header),
@@ -357,6 +447,14 @@ impl codec::Decoder for JournalServerCodec {
 
                     ApiKey::FetchOffset => fetch_offset_resp(body_bytes, header),
 
+                    ApiKey::CommitOffset => commit_offset_resp(body_bytes, header),
+
+                    ApiKey::JoinGroup => join_group_resp(body_bytes, header),
+
+                    ApiKey::Heartbeat => heartbeat_resp(body_bytes, header),
+
+                    ApiKey::LeaveGroup => leave_group_resp(body_bytes, header),
+
                     ApiKey::CreateShard => create_shard_resp(body_bytes, header),
 
                     ApiKey::DeleteShard => delete_shard_resp(body_bytes, header),
@@ -624,6 +722,158 @@ fn fetch_offset_resp(
     }
 }
 
+fn commit_offset_req(
+    body_bytes: BytesMut,
+    header: ReqHeader,
+) -> Result<Option<JournalEnginePacket>, Error> {
+    match CommitOffsetReqBody::decode(body_bytes.as_ref()) {
+        Ok(body) => {
+            let item = JournalEnginePacket::CommitOffsetReq(CommitOffsetReq {
+                header: Some(header),
+                body: Some(body),
+            });
+            Ok(Some(item))
+        }
+        Err(e) => Err(Error::DecodeBodyError(
+            "commit_offset_req".to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
+fn commit_offset_resp(
+    body_bytes: BytesMut,
+    header: RespHeader,
+) -> Result<Option<JournalEnginePacket>, Error> {
+    match CommitOffsetRespBody::decode(body_bytes.as_ref()) {
+        Ok(body) => {
+            let item = JournalEnginePacket::CommitOffsetResp(CommitOffsetResp {
+                header: Some(header),
+                body: Some(body),
+            });
+            Ok(Some(item))
+        }
+        Err(e) => Err(Error::DecodeBodyError(
+            "commit_offset_resp".to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
+fn join_group_req(
+    body_bytes: BytesMut,
+    header: ReqHeader,
+) -> Result<Option<JournalEnginePacket>, Error> {
+    match JoinGroupReqBody::decode(body_bytes.as_ref()) {
+        Ok(body) => {
+            let item = JournalEnginePacket::JoinGroupReq(JoinGroupReq {
+                header: Some(header),
+                body: Some(body),
+            });
+            Ok(Some(item))
+        }
+        Err(e) => Err(Error::DecodeBodyError(
+            "join_group_req".to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
+fn join_group_resp(
+    body_bytes: BytesMut,
+    header: RespHeader,
+) -> Result<Option<JournalEnginePacket>, Error> {
+    match JoinGroupRespBody::decode(body_bytes.as_ref()) {
+        Ok(body) => {
+            let item = JournalEnginePacket::JoinGroupResp(JoinGroupResp {
+                header: Some(header),
+                body: Some(body),
+            });
+            Ok(Some(item))
+        }
+        Err(e) => Err(Error::DecodeBodyError(
+            "join_group_resp".to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
+fn heartbeat_req(
+    body_bytes: BytesMut,
+    header: ReqHeader,
+) -> Result<Option<JournalEnginePacket>, Error> {
+    match HeartbeatReqBody::decode(body_bytes.as_ref()) {
+        Ok(body) => {
+            let item = JournalEnginePacket::HeartbeatReq(HeartbeatReq {
+                header: Some(header),
+                body: Some(body),
+            });
+            Ok(Some(item))
+        }
+        Err(e) => Err(Error::DecodeBodyError(
+            "heartbeat_req".to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
+fn heartbeat_resp(
+    body_bytes: BytesMut,
+    header: RespHeader,
+) -> Result<Option<JournalEnginePacket>, Error> {
+    match HeartbeatRespBody::decode(body_bytes.as_ref()) {
+        Ok(body) => {
+            let item = JournalEnginePacket::HeartbeatResp(HeartbeatResp {
+                header: Some(header),
+                body: Some(body),
+            });
+            Ok(Some(item))
+        }
+        Err(e) => Err(Error::DecodeBodyError(
+            "heartbeat_resp".to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
+fn leave_group_req(
+    body_bytes: BytesMut,
+    header: ReqHeader,
+) -> Result<Option<JournalEnginePacket>, Error> {
+    match LeaveGroupReqBody::decode(body_bytes.as_ref()) {
+        Ok(body) => {
+            let item = JournalEnginePacket::LeaveGroupReq(LeaveGroupReq {
+                header: Some(header),
+                body: Some(body),
+            });
+            Ok(Some(item))
+        }
+        Err(e) => Err(Error::DecodeBodyError(
+            "leave_group_req".to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
+fn leave_group_resp(
+    body_bytes: BytesMut,
+    header: RespHeader,
+) -> Result<Option<JournalEnginePacket>, Error> {
+    match LeaveGroupRespBody::decode(body_bytes.as_ref()) {
+        Ok(body) => {
+            let item = JournalEnginePacket::LeaveGroupResp(LeaveGroupResp {
+                header: Some(header),
+                body: Some(body),
+            });
+            Ok(Some(item))
+        }
+        Err(e) => Err(Error::DecodeBodyError(
+            "leave_group_resp".to_string(),
+            e.to_string(),
+        )),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
@@ -0,0 +1,120 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::io::{Error, ErrorKind, Read};
+
+use super::journal_engine::CompressionType;
+
+/// Compresses a record batch payload with the given codec.
+pub fn compress(compression: CompressionType, data: &[u8]) -> Result<Vec<u8>, Error> {
+    match compression {
+        CompressionType::None => Ok(data.to_vec()),
+        CompressionType::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+        CompressionType::Zstd => zstd::encode_all(data, zstd::DEFAULT_COMPRESSION_LEVEL),
+        CompressionType::Snappy => Ok(snap::raw::Encoder::new().compress_vec(data)?),
+    }
+}
+
+/// Restores a record batch payload compressed by [`compress`]. The payload may come straight
+/// from a producer, so a payload that would restore to more than `max_len` bytes is
+/// rejected before its buffer is allocated.
+pub fn decompress(
+    compression: CompressionType,
+    data: &[u8],
+    max_len: usize,
+) -> Result<Vec<u8>, Error> {
+    match compression {
+        CompressionType::None => {
+            check_decompressed_len(data.len(), max_len)?;
+            Ok(data.to_vec())
+        }
+        CompressionType::Lz4 => {
+            // the size is prepended as a little endian u32
+            let size = data
+                .get(0..4)
+                .map(|buf| u32::from_le_bytes(buf.try_into().unwrap()) as usize)
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "lz4 payload without size"))?;
+            check_decompressed_len(size, max_len)?;
+            lz4_flex::decompress_size_prepended(data)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+        }
+        CompressionType::Zstd => {
+            let mut buf = Vec::new();
+            zstd::stream::read::Decoder::new(data)?
+                .take(max_len as u64 + 1)
+                .read_to_end(&mut buf)?;
+            check_decompressed_len(buf.len(), max_len)?;
+            Ok(buf)
+        }
+        CompressionType::Snappy => {
+            check_decompressed_len(snap::raw::decompress_len(data)?, max_len)?;
+            Ok(snap::raw::Decoder::new().decompress_vec(data)?)
+        }
+    }
+}
+
+fn check_decompressed_len(len: usize, max_len: usize) -> Result<(), Error> {
+    if len > max_len {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "decompressed size {} exceeds the limit of {} bytes",
+                len, max_len
+            ),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress, decompress};
+    use crate::journal_server::journal_engine::CompressionType;
+
+    #[test]
+    fn compress_roundtrip_test() {
+        let data = r#"{"device":"d1","temperature":21.5,"humidity":40}"#.repeat(100);
+        for compression in [
+            CompressionType::None,
+            CompressionType::Lz4,
+            CompressionType::Zstd,
+            CompressionType::Snappy,
+        ] {
+            let compressed = compress(compression, data.as_bytes()).unwrap();
+            if compression != CompressionType::None {
+                assert!(compressed.len() < data.len());
+            }
+            let raw = decompress(compression, &compressed, data.len()).unwrap();
+            assert_eq!(raw, data.as_bytes());
+        }
+    }
+
+    #[test]
+    fn decompress_limit_test() {
+        // a small payload that restores to 10 MB
+        let data = vec![0u8; 10 * 1024 * 1024];
+        for compression in [
+            CompressionType::None,
+            CompressionType::Lz4,
+            CompressionType::Zstd,
+            CompressionType::Snappy,
+        ] {
+            let compressed = compress(compression, &data).unwrap();
+            assert!(decompress(compression, &compressed, 1024 * 1024).is_err());
+            assert_eq!(
+                decompress(compression, &compressed, data.len()).unwrap().len(),
+                data.len()
+            );
+        }
+    }
+}
@@ -34,6 +34,7 @@ pub mod journal_record {
 }
 
 pub mod codec;
+pub mod compression;
 
 /// Error during serialization and deserialization
 #[derive(Debug, thiserror::Error)]
@@ -94,7 +94,8 @@ impl StorageAdapter for JournalStorageAdapter {
                 if let Some(err) = resp.error {
                     return Err(CommonError::CommonError(err));
                 }
-                return Ok(resp.offset);
+                // the adapter writes with the default AckMode::All, which returns the offset
+                return Ok(resp.offset.unwrap_or_default());
             }
             Err(e) => {
                 return Err(CommonError::CommonError(e.to_string()));
@@ -16,8 +16,9 @@ use std::str::FromStr;
 
 pub mod journal;
 pub mod memory;
+pub mod notify;
 // pub mod mysql;
-// pub mod rocksdb;
+pub mod rocksdb;
 pub mod storage;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -27,6 +28,7 @@ pub enum StorageType {
     Mysql,
     Placement,
     RocksDB,
+    Redis,
 }
 
 impl FromStr for StorageType {
@@ -39,6 +41,7 @@ impl FromStr for StorageType {
             "mysql" => Ok(StorageType::Mysql),
             "placement" => Ok(StorageType::Placement),
             "rocksdb" => Ok(StorageType::RocksDB),
+            "redis" => Ok(StorageType::Redis),
             _ => Err(()),
         }
     }
@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use axum::async_trait;
 use common_base::error::common::CommonError;
@@ -20,6 +21,7 @@ use dashmap::DashMap;
 use metadata_struct::adapter::read_config::ReadConfig;
 use metadata_struct::adapter::record::Record;
 
+use crate::notify::ShardNotifier;
 use crate::storage::{ShardConfig, ShardOffset, StorageAdapter};
 
 #[derive(Clone)]
@@ -27,6 +29,7 @@ pub struct MemoryStorageAdapter {
     pub shard_data: DashMap<String, Vec<Record>>,
     //group, (namespace_shard_name,offset)
     pub group_data: DashMap<String, DashMap<String, u64>>,
+    notifier: Arc<ShardNotifier>,
 }
 
 impl Default for MemoryStorageAdapter {
@@ -40,12 +43,36 @@ impl MemoryStorageAdapter {
         MemoryStorageAdapter {
             shard_data: DashMap::with_capacity(256),
             group_data: DashMap::with_capacity(256),
+            notifier: Arc::new(ShardNotifier::new()),
         }
     }
 
     pub fn shard_key(&self, namespace: &str, shard_name: &str) -> String {
         format!("{}_{}", namespace, shard_name)
     }
+
+    fn read_shard_by_offset(
+        &self,
+        shard_key: &str,
+        offset: u64,
+        read_config: &ReadConfig,
+    ) -> Vec<Record> {
+        let mut result = Vec::new();
+        if let Some(data_list) = self.shard_data.get(shard_key) {
+            if data_list.len() < offset as usize {
+                return result;
+            }
+
+            for i in offset..(offset + read_config.max_record_num) {
+                if let Some(value) = data_list.get(i as usize) {
+                    result.push(value.clone());
+                } else {
+                    break;
+                }
+            }
+        }
+        result
+    }
 }
 
 impl MemoryStorageAdapter {}
@@ -64,8 +91,9 @@ impl StorageAdapter for MemoryStorageAdapter {
     }
 
     async fn delete_shard(&self, namespace: String, shard_name: String) -> Result<(), CommonError> {
-        self.shard_data
-            .remove(&self.shard_key(&namespace, &shard_name));
+        let shard_key = self.shard_key(&namespace, &shard_name);
+        self.shard_data.remove(&shard_key);
+        self.notifier.remove(&shard_key);
         return Ok(());
     }
 
@@ -94,9 +122,10 @@ impl StorageAdapter for MemoryStorageAdapter {
                 msg.offset = Some(offset as u64);
                 data_list.push(msg);
             }
-            self.shard_data.insert(shard_key, data_list);
+            self.shard_data.insert(shard_key.clone(), data_list);
         }
 
+        self.notifier.notify(&shard_key);
         return Ok(offset_res);
     }
 
@@ -117,13 +146,15 @@ impl StorageAdapter for MemoryStorageAdapter {
             start_offset
         } else {
             data.offset = Some(0);
-            self.shard_data.insert(shard_key, vec![data]);
+            self.shard_data.insert(shard_key.clone(), vec![data]);
             0
         };
 
+        self.notifier.notify(&shard_key);
         return Ok(offset as u64);
     }
 
+    #[allow(clippy::result_large_err)]
     async fn read_by_offset(
         &self,
         namespace: String,
@@ -132,24 +163,11 @@ impl StorageAdapter for MemoryStorageAdapter {
         read_config: ReadConfig,
     ) -> Result<Vec<Record>, CommonError> {
         let shard_key = self.shard_key(&namespace, &shard_name);
-
-        if let Some(data_list) = self.shard_data.get(&shard_key) {
-            if data_list.len() < offset as usize {
-                return Ok(Vec::new());
-            }
-
-            let mut result = Vec::new();
-            for i in offset..(offset + read_config.max_record_num) {
-                if let Some(value) = data_list.get(i as usize) {
-                    result.push(value.clone());
-                } else {
-                    break;
-                }
-            }
-            return Ok(result);
-        }
-
-        Ok(Vec::new())
+        self.notifier
+            .read_or_wait(&shard_key, read_config.max_wait_ms, || {
+                Ok(self.read_shard_by_offset(&shard_key, offset, &read_config))
+            })
+            .await
     }
 
     async fn read_by_tag(
@@ -229,10 +247,13 @@ impl StorageAdapter for MemoryStorageAdapter {
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
 
     use common_base::tools::unique_id;
     use metadata_struct::adapter::read_config::ReadConfig;
     use metadata_struct::adapter::record::Record;
+    use tokio::time::sleep;
 
     use super::MemoryStorageAdapter;
     use crate::storage::StorageAdapter;
@@ -394,4 +415,50 @@ mod tests {
             .await
             .unwrap();
     }
+    #[tokio::test]
+    async fn read_wait_for_write() {
+        let storage_adapter = Arc::new(MemoryStorageAdapter::new());
+        let namespace = unique_id();
+        let shard_name = "test-wait".to_string();
+        let mut read_config = ReadConfig::new();
+        read_config.max_wait_ms = 100;
+
+        // nothing is written, the read returns empty after max_wait_ms
+        let start = Instant::now();
+        let res = storage_adapter
+            .read_by_offset(
+                namespace.clone(),
+                shard_name.clone(),
+                0,
+                read_config.clone(),
+            )
+            .await
+            .unwrap();
+        assert!(res.is_empty());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+
+        // a write wakes the waiting read
+        let writer = storage_adapter.clone();
+        let (raw_namespace, raw_shard_name) = (namespace.clone(), shard_name.clone());
+        tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+            writer
+                .write(
+                    raw_namespace,
+                    raw_shard_name,
+                    Record::build_str("test1".to_string()),
+                )
+                .await
+                .unwrap();
+        });
+
+        read_config.max_wait_ms = 10000;
+        let start = Instant::now();
+        let res = storage_adapter
+            .read_by_offset(namespace, shard_name, 0, read_config)
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
 }
@@ -0,0 +1,116 @@
+// Copyright 2023 RobustMQ Team
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+use std::sync::Arc;
+use std::time::Duration;
+
+use common_base::error::common::CommonError;
+use dashmap::DashMap;
+use metadata_struct::adapter::record::Record;
+use tokio::sync::Notify;
+use tokio::time::{timeout_at, Instant};
+
+// Longest time a read waits for new records, longer waits asked by the caller are cut to it
+pub const MAX_READ_WAIT_MS: u64 = 30000;
+
+/// Lets reads wait for new records of a shard, for the adapters whose writes go through
+/// this process. Writes to the shard wake every waiting read.
+#[derive(Default)]
+pub struct ShardNotifier {
+    // namespace_shard_name, notify
+    shards: DashMap<String, Arc<Notify>>,
+}
+
+impl ShardNotifier {
+    pub fn new() -> Self {
+        ShardNotifier {
+            shards: DashMap::with_capacity(256),
+        }
+    }
+
+    pub fn notify(&self, shard_key: &str) {
+        if let Some(notify) = self.shards.get(shard_key) {
+            notify.notify_waiters();
+        }
+    }
+
+    pub fn remove(&self, shard_key: &str) {
+        if let Some((_, notify)) = self.shards.remove(shard_key) {
+            notify.notify_waiters();
+        }
+    }
+
+    /// Runs `read` and, while it returns no records, runs it again after every write to the
+    /// shard until `max_wait_ms`, at most [`MAX_READ_WAIT_MS`], has passed.
+    pub async fn read_or_wait<F>(
+        &self,
+        shard_key: &str,
+        max_wait_ms: u64,
+        mut read: F,
+    ) -> Result<Vec<Record>, CommonError>
+    where
+        F: FnMut() -> Result<Vec<Record>, CommonError>,
+    {
+        let max_wait_ms = max_wait_ms.min(MAX_READ_WAIT_MS);
+        if max_wait_ms == 0 {
+            return read();
+        }
+
+        let deadline = match Instant::now().checked_add(Duration::from_millis(max_wait_ms)) {
+            Some(deadline) => deadline,
+            None => return read(),
+        };
+        loop {
+            let notify = self
+                .shards
+                .entry(shard_key.to_string())
+                .or_insert_with(|| Arc::new(Notify::new()))
+                .clone();
+
+            // Register before reading, so a write between the read and the wait is not missed
+            let notified = notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let records = read()?;
+            if !records.is_empty() {
+                return Ok(records);
+            }
+
+            if timeout_at(deadline, notified).await.is_err() {
+                return Ok(records);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use metadata_struct::adapter::record::Record;
+
+    use super::ShardNotifier;
+
+    #[tokio::test]
+    #[allow(clippy::result_large_err)]
+    async fn read_or_wait_max_wait_test() {
+        let notifier = ShardNotifier::new();
+        // a wait far beyond what an Instant can hold is cut to the server maximum
+        let records = notifier
+            .read_or_wait("shard", u64::MAX, || {
+                Ok(vec![Record::build_str("test".to_string())])
+            })
+            .await
+            .unwrap();
+        assert_eq!(records.len(), 1);
+    }
+}
@@ -12,391 +12,678 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-// use std::fmt::Display;
-// use std::sync::Arc;
-
-// use axum::async_trait;
-// use common_base::error::common::CommonError;
-// use metadata_struct::adapter::record::Record;
-// use rocksdb_engine::RocksDBEngine;
-
-// use crate::storage::{ShardConfig, StorageAdapter};
-
-// const DB_COLUMN_FAMILY_KV: &str = "kv";
-// const DB_COLUMN_FAMILY_RECORD: &str = "record";
-
-// fn column_family_list() -> Vec<String> {
-//     vec![
-//         DB_COLUMN_FAMILY_KV.to_string(),
-//         DB_COLUMN_FAMILY_RECORD.to_string(),
-//     ]
-// }
-
-// #[derive(Clone)]
-// pub struct RocksDBStorageAdapter {
-//     pub db: Arc<RocksDBEngine>,
-// }
-
-// impl RocksDBStorageAdapter {
-//     pub fn new(db_path: &str, max_open_files: i32) -> Self {
-//         RocksDBStorageAdapter {
-//             db: Arc::new(RocksDBEngine::new(
-//                 db_path,
-//                 max_open_files,
-//                 column_family_list(),
-//             )),
-//         }
-//     }
-
-//     #[inline(always)]
-//     pub fn record_key<S1: Display>(&self, namespace: S1, shard_name: S1, index: u128) -> String {
-//         format!("{}_{}_record_{}", namespace, shard_name, index)
-//     }
-
-//     #[inline(always)]
-//     pub fn offset_shard_key<S1: Display>(&self, namespace: S1, shard_name: S1) -> String {
-//         format!("{}_{}_{}", namespace, shard_name, "shard_offset")
-//     }
-
-//     #[inline(always)]
-//     pub fn offset_key<S1: Display, S2: Display>(
-//         &self,
-//         namespace: S1,
-//         shard_name: S1,
-//         group_id: S2,
-//     ) -> String {
-//         format!("{}_{}_{}", namespace, shard_name, group_id)
-//     }
-
-//     pub fn get_offset<S1: Display, S2: Display>(
-//         &self,
-//         namespace: S1,
-//         shard_name: S1,
-//         group_id: S2,
-//     ) -> Option<u128> {
-//         let key = self.offset_key(namespace, shard_name, group_id);
-//         self.db
-//             .cf_handle(DB_COLUMN_FAMILY_KV)
-//             .and_then(|cf| self.db.read(cf, &key).ok()?)
-//     }
-// }
-
-// impl RocksDBStorageAdapter {}
-
-// #[async_trait]
-// impl StorageAdapter for RocksDBStorageAdapter {
-//     async fn create_shard(
-//         &self,
-//         namespace: String,
-//         shard_name: String,
-//         _: ShardConfig,
-//     ) -> Result<(), CommonError> {
-//         let cf = self.db.cf_handle(DB_COLUMN_FAMILY_RECORD).unwrap();
-//         let key = self.offset_shard_key(namespace, shard_name);
-//         self.db.write(cf, key.as_str(), &0_u128)
-//     }
-
-//     async fn delete_shard(&self, namespace: String, shard_name: String) -> Result<(), CommonError> {
-//         let cf = self.db.cf_handle(DB_COLUMN_FAMILY_RECORD).unwrap();
-//         let key = self.offset_shard_key(namespace, shard_name);
-//         self.db.delete_prefix(cf, &key)
-//     }
-
-//     async fn write(
-//         &self,
-//         namespace: String,
-//         shard_name: String,
-//         message: Vec<Record>,
-//     ) -> Result<Vec<usize>, CommonError> {
-//         let cf = self.db.cf_handle(DB_COLUMN_FAMILY_RECORD).unwrap();
-//         let key_shard_offset = self.offset_shard_key(namespace.clone(), shard_name.clone());
-//         let offset = self
-//             .db
-//             .read::<u128>(cf, key_shard_offset.as_str())?
-//             .unwrap_or(0);
-
-//         let mut start_offset = offset;
-
-//         let mut offset_res = Vec::new();
-//         for mut msg in message {
-//             offset_res.push(start_offset as usize);
-//             msg.offset = start_offset;
-
-//             let record_key = self.record_key(namespace.clone(), shard_name.clone(), start_offset);
-//             self.db.write(cf, &record_key, &msg)?;
-//             start_offset += 1;
-//         }
-
-//         self.db
-//             .write(cf, key_shard_offset.as_str(), &start_offset)?;
-
-//         Ok(offset_res)
-//     }
-
-//     async fn stream_read(
-//         &self,
-//         namespace: String,
-//         shard_name: String,
-//         group_id: String,
-//         record_num: Option<u128>,
-//         _: Option<usize>,
-//     ) -> Result<Option<Vec<Record>>, CommonError> {
-//         let cf = self.db.cf_handle(DB_COLUMN_FAMILY_RECORD).unwrap();
-//         let group_offset_key = self.offset_key(namespace.clone(), shard_name.clone(), group_id);
-//         let offset = self
-//             .db
-//             .read::<u128>(cf, group_offset_key.as_str())?
-//             .unwrap_or(0);
-
-//         let num = record_num.unwrap_or(10);
-
-//         let mut cur_offset = 0;
-//         let mut result = Vec::new();
-//         for i in offset..(offset + num) {
-//             let record_key = self.record_key(namespace.clone(), shard_name.clone(), i);
-//             let value = self.db.read::<Record>(cf, &record_key)?;
-
-//             if let Some(value) = value {
-//                 result.push(value.clone());
-//                 cur_offset += 1;
-//             } else {
-//                 break;
-//             }
-//         }
-
-//         if cur_offset > 0 {
-//             self.db
-//                 .write(cf, group_offset_key.as_str(), &(offset + cur_offset))?;
-//         }
-//         Ok(Some(result))
-//     }
-
-//     async fn stream_commit_offset(
-//         &self,
-//         namespace: String,
-//         shard_name: String,
-//         group_id: String,
-//         offset: u128,
-//     ) -> Result<bool, CommonError> {
-//         let cf = self.db.cf_handle(DB_COLUMN_FAMILY_RECORD).unwrap();
-//         let key = self.offset_key(namespace, group_id, shard_name);
-//         self.db.write(cf, key.as_str(), &offset)?;
-//         Ok(true)
-//     }
-
-//     async fn stream_read_by_offset(
-//         &self,
-//         namespace: String,
-//         shard_name: String,
-//         offset: usize,
-//     ) -> Result<Option<Record>, CommonError> {
-//         let cf = self.db.cf_handle(DB_COLUMN_FAMILY_RECORD).unwrap();
-//         self.db.read::<Record>(
-//             cf,
-//             self.record_key(namespace, shard_name, offset as u128)
-//                 .as_str(),
-//         )
-//     }
-
-//     async fn stream_read_by_timestamp(
-//         &self,
-//         _: String,
-//         _: String,
-//         _: u128,
-//         _: u128,
-//         _: Option<usize>,
-//         _: Option<usize>,
-//     ) -> Result<Option<Vec<Record>>, CommonError> {
-//         Ok(None)
-//     }
-
-//     async fn stream_read_by_key(
-//         &self,
-//         _: String,
-//         _: String,
-//         _: String,
-//     ) -> Result<Option<Record>, CommonError> {
-//         Ok(None)
-//     }
-//     async fn close(&self) -> Result<(), CommonError> {
-//         Ok(())
-//     }
-// }
-
-// #[cfg(test)]
-// mod tests {
-//     use common_base::tools::unique_id;
-//     use metadata_struct::adapter::record::Record;
-
-//     use super::RocksDBStorageAdapter;
-//     use crate::storage::StorageAdapter;
-//     #[tokio::test]
-//     async fn stream_read_write() {
-//         let db_path = format!("/tmp/robustmq_{}", unique_id());
-
-//         let storage_adapter = RocksDBStorageAdapter::new(db_path.as_str(), 100);
-//         let namespace = unique_id();
-//         let shard_name = "test-11".to_string();
-//         let ms1 = "test1".to_string();
-//         let ms2 = "test2".to_string();
-//         let data = vec![
-//             Record::build_b(ms1.clone().as_bytes().to_vec()),
-//             Record::build_b(ms2.clone().as_bytes().to_vec()),
-//         ];
-
-//         let result = storage_adapter
-//             .stream_write(namespace.clone(), shard_name.clone(), data)
-//             .await
-//             .unwrap();
-
-//         assert_eq!(result.first().unwrap().clone(), 0);
-//         assert_eq!(result.get(1).unwrap().clone(), 1);
-//         assert_eq!(
-//             storage_adapter
-//                 .stream_read(
-//                     namespace.clone(),
-//                     shard_name.clone(),
-//                     "test_".to_string(),
-//                     Some(10),
-//                     None
-//                 )
-//                 .await
-//                 .unwrap()
-//                 .unwrap()
-//                 .len(),
-//             2
-//         );
-
-//         let ms3 = "test3".to_string();
-//         let ms4 = "test4".to_string();
-//         let data = vec![
-//             Record::build_b(ms3.clone().as_bytes().to_vec()),
-//             Record::build_b(ms4.clone().as_bytes().to_vec()),
-//         ];
-
-//         let result = storage_adapter
-//             .stream_write(namespace.clone(), shard_name.clone(), data)
-//             .await
-//             .unwrap();
-//         let result_read = storage_adapter
-//             .stream_read(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 "test_".to_string(),
-//                 Some(10),
-//                 None,
-//             )
-//             .await
-//             .unwrap();
-//         println!("{:?}", result_read);
-
-//         assert_eq!(result.first().unwrap().clone(), 2);
-//         assert_eq!(result.get(1).unwrap().clone(), 3);
-//         assert_eq!(result_read.unwrap().len(), 2);
-
-//         let group_id = "test_group_id".to_string();
-//         let record_num = Some(1);
-//         let record_size = None;
-//         let res = storage_adapter
-//             .stream_read(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 group_id.clone(),
-//                 record_num,
-//                 record_size,
-//             )
-//             .await
-//             .unwrap()
-//             .unwrap();
-//         assert_eq!(
-//             String::from_utf8(res.first().unwrap().clone().data).unwrap(),
-//             ms1
-//         );
-//         storage_adapter
-//             .stream_commit_offset(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 group_id.clone(),
-//                 res.first().unwrap().clone().offset,
-//             )
-//             .await
-//             .unwrap();
-
-//         let res = storage_adapter
-//             .stream_read(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 group_id.clone(),
-//                 record_num,
-//                 record_size,
-//             )
-//             .await
-//             .unwrap()
-//             .unwrap();
-//         assert_eq!(
-//             String::from_utf8(res.first().unwrap().clone().data).unwrap(),
-//             ms2
-//         );
-//         storage_adapter
-//             .stream_commit_offset(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 group_id.clone(),
-//                 res.first().unwrap().clone().offset,
-//             )
-//             .await
-//             .unwrap();
-
-//         let res = storage_adapter
-//             .stream_read(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 group_id.clone(),
-//                 record_num,
-//                 record_size,
-//             )
-//             .await
-//             .unwrap()
-//             .unwrap();
-//         assert_eq!(
-//             String::from_utf8(res.first().unwrap().clone().data).unwrap(),
-//             ms3
-//         );
-//         storage_adapter
-//             .stream_commit_offset(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 group_id.clone(),
-//                 res.first().unwrap().clone().offset,
-//             )
-//             .await
-//             .unwrap();
-
-//         let res = storage_adapter
-//             .stream_read(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 group_id.clone(),
-//                 record_num,
-//                 record_size,
-//             )
-//             .await
-//             .unwrap()
-//             .unwrap();
-//         assert_eq!(
-//             String::from_utf8(res.first().unwrap().clone().data).unwrap(),
-//             ms4
-//         );
-//         storage_adapter
-//             .stream_commit_offset(
-//                 namespace.clone(),
-//                 shard_name.clone(),
-//                 group_id.clone(),
-//                 res.first().unwrap().clone().offset,
-//             )
-//             .await
-//             .unwrap();
-
-//         let _ = std::fs::remove_dir_all(&db_path);
-//     }
-// }
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use axum::async_trait;
+use common_base::error::common::CommonError;
+use dashmap::DashMap;
+use metadata_struct::adapter::read_config::ReadConfig;
+use metadata_struct::adapter::record::Record;
+use rocksdb::{ColumnFamily, WriteBatch};
+use rocksdb_engine::RocksDBEngine;
+use serde::{Deserialize, Serialize};
+
+use crate::notify::ShardNotifier;
+use crate::storage::{ShardConfig, ShardOffset, StorageAdapter};
+
+const DB_COLUMN_FAMILY_KV: &str = "kv";
+const DB_COLUMN_FAMILY_RECORD: &str = "record";
+const DB_COLUMN_FAMILY_INDEX: &str = "index";
+
+fn column_family_list() -> Vec<String> {
+    vec![
+        DB_COLUMN_FAMILY_KV.to_string(),
+        DB_COLUMN_FAMILY_RECORD.to_string(),
+        DB_COLUMN_FAMILY_INDEX.to_string(),
+    ]
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct GroupOffset {
+    namespace: String,
+    shard_name: String,
+    offset: u64,
+}
+
+// Single node persistent storage adapter.
+// Records are stored under zero-padded offsets so that iteration order is offset order,
+// key, tag and timestamp lookups go through the secondary indexes in the index column family.
+#[derive(Clone)]
+pub struct RocksDBStorageAdapter {
+    pub db: Arc<RocksDBEngine>,
+    // (shard_key, next offset), the entry lock serializes writes to a shard
+    shard_next_offset: Arc<DashMap<String, u64>>,
+    notifier: Arc<ShardNotifier>,
+}
+
+impl RocksDBStorageAdapter {
+    pub fn new(db_path: &str, max_open_files: i32) -> Self {
+        RocksDBStorageAdapter {
+            db: Arc::new(RocksDBEngine::new(
+                db_path,
+                max_open_files,
+                column_family_list(),
+            )),
+            shard_next_offset: Arc::new(DashMap::with_capacity(256)),
+            notifier: Arc::new(ShardNotifier::new()),
+        }
+    }
+
+    // The namespace is length prefixed so that no two (namespace, shard_name) pairs share a key
+    pub fn shard_key(&self, namespace: &str, shard_name: &str) -> String {
+        format!("{}:{}_{}", namespace.len(), namespace, shard_name)
+    }
+
+    #[inline(always)]
+    pub fn shard_offset_key(&self, namespace: &str, shard_name: &str) -> String {
+        format!("/offset/{}/{}", namespace, shard_name)
+    }
+
+    #[inline(always)]
+    pub fn record_prefix(&self, namespace: &str, shard_name: &str) -> String {
+        format!("/record/{}/{}/", namespace, shard_name)
+    }
+
+    #[inline(always)]
+    pub fn record_key(&self, namespace: &str, shard_name: &str, offset: u64) -> String {
+        format!(
+            "{}{:020}",
+            self.record_prefix(namespace, shard_name),
+            offset
+        )
+    }
+
+    #[inline(always)]
+    pub fn key_index_prefix(&self, namespace: &str, shard_name: &str, key: &str) -> String {
+        format!("/key/{}/{}/{}/", namespace, shard_name, key)
+    }
+
+    #[inline(always)]
+    pub fn tag_index_prefix(&self, namespace: &str, shard_name: &str, tag: &str) -> String {
+        format!("/tag/{}/{}/{}/", namespace, shard_name, tag)
+    }
+
+    #[inline(always)]
+    pub fn timestamp_index_prefix(&self, namespace: &str, shard_name: &str) -> String {
+        format!("/timestamp/{}/{}/", namespace, shard_name)
+    }
+
+    #[inline(always)]
+    pub fn group_offset_prefix(&self, group_name: &str) -> String {
+        format!("/group/{}/", group_name)
+    }
+
+    #[inline(always)]
+    pub fn group_offset_key(&self, group_name: &str, namespace: &str, shard_name: &str) -> String {
+        format!(
+            "{}{}/{}",
+            self.group_offset_prefix(group_name),
+            namespace,
+            shard_name
+        )
+    }
+
+    fn cf(&self, name: &str) -> Result<&ColumnFamily, CommonError> {
+        self.db.cf_handle(name).ok_or_else(|| {
+            CommonError::CommonError(format!("Column family {} does not exist", name))
+        })
+    }
+
+    fn read_shard_next_offset(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+    ) -> Result<u64, CommonError> {
+        let cf = self.cf(DB_COLUMN_FAMILY_KV)?;
+        Ok(self
+            .db
+            .read::<u64>(cf, &self.shard_offset_key(namespace, shard_name))?
+            .unwrap_or(0))
+    }
+
+    // Writes the records with consecutive offsets starting at the shard's next offset.
+    // The records, their indexes and the next offset go into a single WriteBatch,
+    // so a crash never leaves a partially written batch behind.
+    fn append_records(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        records: Vec<Record>,
+    ) -> Result<Vec<u64>, CommonError> {
+        let shard_key = self.shard_key(namespace, shard_name);
+        let next_offset = match self.shard_next_offset.get(&shard_key) {
+            Some(offset) => *offset,
+            None => self.read_shard_next_offset(namespace, shard_name)?,
+        };
+        let mut entry = self
+            .shard_next_offset
+            .entry(shard_key)
+            .or_insert(next_offset);
+
+        let record_cf = self.cf(DB_COLUMN_FAMILY_RECORD)?;
+        let index_cf = self.cf(DB_COLUMN_FAMILY_INDEX)?;
+        let mut batch = WriteBatch::default();
+        let mut offset = *entry;
+        let mut offset_res = Vec::with_capacity(records.len());
+        for mut record in records {
+            record.offset = Some(offset);
+            batch.put_cf(
+                record_cf,
+                self.record_key(namespace, shard_name, offset),
+                serde_json::to_vec(&record)?,
+            );
+
+            let offset_value = serde_json::to_vec(&offset)?;
+            if !record.key.is_empty() {
+                let index_key = format!(
+                    "{}{:020}",
+                    self.key_index_prefix(namespace, shard_name, &record.key),
+                    offset
+                );
+                batch.put_cf(index_cf, index_key, &offset_value);
+            }
+
+            for tag in record.tags.iter() {
+                let index_key = format!(
+                    "{}{:020}",
+                    self.tag_index_prefix(namespace, shard_name, tag),
+                    offset
+                );
+                batch.put_cf(index_cf, index_key, &offset_value);
+            }
+
+            let index_key = format!(
+                "{}{:020}/{:020}",
+                self.timestamp_index_prefix(namespace, shard_name),
+                record.timestamp,
+                offset
+            );
+            batch.put_cf(index_cf, index_key, &offset_value);
+
+            offset_res.push(offset);
+            offset += 1;
+        }
+
+        let kv_cf = self.cf(DB_COLUMN_FAMILY_KV)?;
+        batch.put_cf(
+            kv_cf,
+            self.shard_offset_key(namespace, shard_name),
+            serde_json::to_vec(&offset)?,
+        );
+        self.db.db.write(batch)?;
+        *entry = offset;
+        self.notifier.notify(entry.key());
+        Ok(offset_res)
+    }
+
+    fn read_records(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        offset: u64,
+        read_config: &ReadConfig,
+    ) -> Result<Vec<Record>, CommonError> {
+        let cf = self.cf(DB_COLUMN_FAMILY_RECORD)?;
+        let prefix = self.record_prefix(namespace, shard_name);
+        let mut iter = self.db.db.raw_iterator_cf(cf);
+        iter.seek(self.record_key(namespace, shard_name, offset));
+
+        let mut result = Vec::new();
+        let mut size = 0;
+        while iter.valid() && (result.len() as u64) < read_config.max_record_num {
+            let (Some(key), Some(value)) = (iter.key(), iter.value()) else {
+                break;
+            };
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+
+            let record = serde_json::from_slice::<Record>(value)?;
+            size += record.data.len() as u64;
+            if !result.is_empty() && size > read_config.max_size {
+                break;
+            }
+            result.push(record);
+            iter.next();
+        }
+        Ok(result)
+    }
+
+    fn read_record(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        offset: u64,
+    ) -> Result<Option<Record>, CommonError> {
+        let cf = self.cf(DB_COLUMN_FAMILY_RECORD)?;
+        self.db
+            .read::<Record>(cf, &self.record_key(namespace, shard_name, offset))
+    }
+
+    // Loads the records referenced by an index prefix, starting at start_key.
+    // `filter` drops index entries that no longer match the record they point to.
+    fn read_by_index<F>(
+        &self,
+        namespace: &str,
+        shard_name: &str,
+        prefix: &str,
+        start_key: &str,
+        read_config: &ReadConfig,
+        filter: F,
+    ) -> Result<Vec<Record>, CommonError>
+    where
+        F: Fn(&Record) -> bool,
+    {
+        let index_cf = self.cf(DB_COLUMN_FAMILY_INDEX)?;
+        let mut iter = self.db.db.raw_iterator_cf(index_cf);
+        iter.seek(start_key);
+
+        let mut result = Vec::new();
+        let mut size = 0;
+        while iter.valid() && (result.len() as u64) < read_config.max_record_num {
+            let (Some(key), Some(value)) = (iter.key(), iter.value()) else {
+                break;
+            };
+            if !key.starts_with(prefix.as_bytes()) {
+                break;
+            }
+
+            let offset = serde_json::from_slice::<u64>(value)?;
+            if let Some(record) = self.read_record(namespace, shard_name, offset)? {
+                if filter(&record) {
+                    size += record.data.len() as u64;
+                    if !result.is_empty() && size > read_config.max_size {
+                        break;
+                    }
+                    result.push(record);
+                }
+            }
+            iter.next();
+        }
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl StorageAdapter for RocksDBStorageAdapter {
+    async fn create_shard(
+        &self,
+        namespace: String,
+        shard_name: String,
+        _: ShardConfig,
+    ) -> Result<(), CommonError> {
+        let cf = self.cf(DB_COLUMN_FAMILY_KV)?;
+        let key = self.shard_offset_key(&namespace, &shard_name);
+        if self.db.read::<u64>(cf, &key)?.is_none() {
+            self.db.write(cf, &key, &0_u64)?;
+        }
+        Ok(())
+    }
+
+    async fn delete_shard(&self, namespace: String, shard_name: String) -> Result<(), CommonError> {
+        let shard_key = self.shard_key(&namespace, &shard_name);
+        let entry = self.shard_next_offset.entry(shard_key.clone()).or_insert(0);
+
+        let record_cf = self.cf(DB_COLUMN_FAMILY_RECORD)?;
+        self.db
+            .delete_prefix(record_cf, &self.record_prefix(&namespace, &shard_name))?;
+
+        let index_cf = self.cf(DB_COLUMN_FAMILY_INDEX)?;
+        for prefix in [
+            format!("/key/{}/{}/", namespace, shard_name),
+            format!("/tag/{}/{}/", namespace, shard_name),
+            self.timestamp_index_prefix(&namespace, &shard_name),
+        ] {
+            self.db.delete_prefix(index_cf, &prefix)?;
+        }
+
+        let kv_cf = self.cf(DB_COLUMN_FAMILY_KV)?;
+        self.db
+            .delete(kv_cf, &self.shard_offset_key(&namespace, &shard_name))?;
+        drop(entry);
+        self.shard_next_offset.remove(&shard_key);
+        self.notifier.remove(&shard_key);
+        Ok(())
+    }
+
+    async fn write(
+        &self,
+        namespace: String,
+        shard_name: String,
+        data: Record,
+    ) -> Result<u64, CommonError> {
+        let offsets = self.append_records(&namespace, &shard_name, vec![data])?;
+        Ok(offsets[0])
+    }
+
+    async fn batch_write(
+        &self,
+        namespace: String,
+        shard_name: String,
+        data: Vec<Record>,
+    ) -> Result<Vec<u64>, CommonError> {
+        self.append_records(&namespace, &shard_name, data)
+    }
+
+    #[allow(clippy::result_large_err)]
+    async fn read_by_offset(
+        &self,
+        namespace: String,
+        shard_name: String,
+        offset: u64,
+        read_config: ReadConfig,
+    ) -> Result<Vec<Record>, CommonError> {
+        let shard_key = self.shard_key(&namespace, &shard_name);
+        self.notifier
+            .read_or_wait(&shard_key, read_config.max_wait_ms, || {
+                self.read_records(&namespace, &shard_name, offset, &read_config)
+            })
+            .await
+    }
+
+    #[allow(clippy::result_large_err)]
+    async fn read_by_tag(
+        &self,
+        namespace: String,
+        shard_name: String,
+        offset: u64,
+        tag: String,
+        read_config: ReadConfig,
+    ) -> Result<Vec<Record>, CommonError> {
+        let prefix = self.tag_index_prefix(&namespace, &shard_name, &tag);
+        let start_key = format!("{}{:020}", prefix, offset);
+        let shard_key = self.shard_key(&namespace, &shard_name);
+        self.notifier
+            .read_or_wait(&shard_key, read_config.max_wait_ms, || {
+                self.read_by_index(
+                    &namespace,
+                    &shard_name,
+                    &prefix,
+                    &start_key,
+                    &read_config,
+                    |record| record.tags.contains(&tag),
+                )
+            })
+            .await
+    }
+
+    async fn read_by_key(
+        &self,
+        namespace: String,
+        shard_name: String,
+        key: String,
+        read_config: ReadConfig,
+    ) -> Result<Vec<Record>, CommonError> {
+        let prefix = self.key_index_prefix(&namespace, &shard_name, &key);
+        self.read_by_index(
+            &namespace,
+            &shard_name,
+            &prefix,
+            &prefix,
+            &read_config,
+            |record| record.key == key,
+        )
+    }
+
+    async fn get_offset_by_timestamp(
+        &self,
+        namespace: String,
+        shard_name: String,
+        timestamp: u64,
+    ) -> Result<Option<ShardOffset>, CommonError> {
+        let index_cf = self.cf(DB_COLUMN_FAMILY_INDEX)?;
+        let prefix = self.timestamp_index_prefix(&namespace, &shard_name);
+        let mut iter = self.db.db.raw_iterator_cf(index_cf);
+        iter.seek(format!("{}{:020}", prefix, timestamp));
+
+        if !iter.valid() {
+            return Ok(None);
+        }
+        if let (Some(key), Some(value)) = (iter.key(), iter.value()) {
+            if key.starts_with(prefix.as_bytes()) {
+                let offset = serde_json::from_slice::<u64>(value)?;
+                return Ok(Some(ShardOffset {
+                    namespace,
+                    shard_name,
+                    offset,
+                    ..Default::default()
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    async fn get_offset_by_group(
+        &self,
+        group_name: String,
+    ) -> Result<Vec<ShardOffset>, CommonError> {
+        let cf = self.cf(DB_COLUMN_FAMILY_KV)?;
+        let mut results = Vec::new();
+        for (_, value) in self
+            .db
+            .read_prefix(cf, &self.group_offset_prefix(&group_name))?
+        {
+            let data = serde_json::from_slice::<GroupOffset>(&value)?;
+            results.push(ShardOffset {
+                namespace: data.namespace,
+                shard_name: data.shard_name,
+                offset: data.offset,
+                ..Default::default()
+            });
+        }
+        Ok(results)
+    }
+
+    async fn commit_offset(
+        &self,
+        group_name: String,
+        namespace: String,
+        offset: HashMap<String, u64>,
+    ) -> Result<(), CommonError> {
+        let cf = self.cf(DB_COLUMN_FAMILY_KV)?;
+        for (shard_name, offset) in offset {
+            let key = self.group_offset_key(&group_name, &namespace, &shard_name);
+            let data = GroupOffset {
+                namespace: namespace.clone(),
+                shard_name,
+                offset,
+            };
+            self.db.write(cf, &key, &data)?;
+        }
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<(), CommonError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use common_base::tools::unique_id;
+    use metadata_struct::adapter::read_config::ReadConfig;
+    use metadata_struct::adapter::record::Record;
+
+    use super::RocksDBStorageAdapter;
+    use crate::storage::{ShardConfig, StorageAdapter};
+
+    #[tokio::test]
+    async fn stream_read_write() {
+        let db_path = format!("/tmp/robustmq_{}", unique_id());
+        let storage_adapter = RocksDBStorageAdapter::new(db_path.as_str(), 100);
+        let namespace = unique_id();
+        let shard_name = "test-11".to_string();
+        storage_adapter
+            .create_shard(
+                namespace.clone(),
+                shard_name.clone(),
+                ShardConfig::default(),
+            )
+            .await
+            .unwrap();
+
+        let mut ms1 = Record::build_str("test1".to_string());
+        ms1.set_key("k1".to_string());
+        ms1.set_tags(vec!["t1".to_string()]);
+        ms1.timestamp = 1000;
+        let mut ms2 = Record::build_str("test2".to_string());
+        ms2.set_key("k2".to_string());
+        ms2.set_tags(vec!["t1".to_string(), "t2".to_string()]);
+        ms2.timestamp = 2000;
+        let result = storage_adapter
+            .batch_write(namespace.clone(), shard_name.clone(), vec![ms1, ms2])
+            .await
+            .unwrap();
+        assert_eq!(result, vec![0, 1]);
+
+        let mut ms3 = Record::build_str("test3".to_string());
+        ms3.set_key("k1".to_string());
+        ms3.timestamp = 3000;
+        let offset = storage_adapter
+            .write(namespace.clone(), shard_name.clone(), ms3)
+            .await
+            .unwrap();
+        assert_eq!(offset, 2);
+
+        let mut read_config = ReadConfig::new();
+        let res = storage_adapter
+            .read_by_offset(
+                namespace.clone(),
+                shard_name.clone(),
+                1,
+                read_config.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 2);
+        assert_eq!(res[0].offset, Some(1));
+        assert_eq!(String::from_utf8(res[1].data.clone()).unwrap(), "test3");
+
+        read_config.max_record_num = 1;
+        let res = storage_adapter
+            .read_by_offset(
+                namespace.clone(),
+                shard_name.clone(),
+                0,
+                read_config.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+
+        let read_config = ReadConfig::new();
+        let res = storage_adapter
+            .read_by_key(
+                namespace.clone(),
+                shard_name.clone(),
+                "k1".to_string(),
+                read_config.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            res.iter().map(|r| r.offset.unwrap()).collect::<Vec<u64>>(),
+            vec![0, 2]
+        );
+
+        let res = storage_adapter
+            .read_by_tag(
+                namespace.clone(),
+                shard_name.clone(),
+                1,
+                "t1".to_string(),
+                read_config.clone(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].offset, Some(1));
+
+        let res = storage_adapter
+            .get_offset_by_timestamp(namespace.clone(), shard_name.clone(), 1500)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(res.offset, 1);
+        let res = storage_adapter
+            .get_offset_by_timestamp(namespace.clone(), shard_name.clone(), 5000)
+            .await
+            .unwrap();
+        assert!(res.is_none());
+
+        let group_id = "test_group_id".to_string();
+        let mut offset_data = HashMap::new();
+        offset_data.insert(shard_name.clone(), 1);
+        storage_adapter
+            .commit_offset(group_id.clone(), namespace.clone(), offset_data)
+            .await
+            .unwrap();
+        let offsets = storage_adapter
+            .get_offset_by_group(group_id.clone())
+            .await
+            .unwrap();
+        assert_eq!(offsets.len(), 1);
+        assert_eq!(offsets[0].namespace, namespace);
+        assert_eq!(offsets[0].shard_name, shard_name);
+        assert_eq!(offsets[0].offset, 1);
+
+        storage_adapter
+            .delete_shard(namespace.clone(), shard_name.clone())
+            .await
+            .unwrap();
+        let res = storage_adapter
+            .read_by_offset(
+                namespace.clone(),
+                shard_name.clone(),
+                0,
+                read_config.clone(),
+            )
+            .await
+            .unwrap();
+        assert!(res.is_empty());
+        let offset = storage_adapter
+            .write(
+                namespace.clone(),
+                shard_name.clone(),
+                Record::build_str("test4".to_string()),
+            )
+            .await
+            .unwrap();
+        assert_eq!(offset, 0);
+
+        let _ = std::fs::remove_dir_all(&db_path);
+    }
+
+    #[tokio::test]
+    async fn shard_key_collision_test() {
+        let db_path = format!("/tmp/robustmq_{}", unique_id());
+        let storage_adapter = RocksDBStorageAdapter::new(db_path.as_str(), 100);
+        assert_ne!(
+            storage_adapter.shard_key("a_b", "c"),
+            storage_adapter.shard_key("a", "b_c")
+        );
+
+        for (namespace, shard_name) in [("a_b", "c"), ("a", "b_c")] {
+            storage_adapter
+                .create_shard(
+                    namespace.to_string(),
+                    shard_name.to_string(),
+                    ShardConfig::default(),
+                )
+                .await
+                .unwrap();
+            let offset = storage_adapter
+                .write(
+                    namespace.to_string(),
+                    shard_name.to_string(),
+                    Record::build_str("test".to_string()),
+                )
+                .await
+                .unwrap();
+            assert_eq!(offset, 0);
+        }
+    }
+}
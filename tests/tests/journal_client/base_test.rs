@@ -18,8 +18,8 @@ mod tests {
     use futures::{SinkExt, StreamExt};
     use protocol::journal_server::codec::{JournalEnginePacket, JournalServerCodec};
     use protocol::journal_server::journal_engine::{
-        ApiKey, ApiVersion, CreateShardReq, CreateShardReqBody, DeleteShardReq, DeleteShardReqBody,
-        GetClusterMetadataReq, GetShardMetadataReq, GetShardMetadataReqBody,
+        AckMode, ApiKey, ApiVersion, CreateShardReq, CreateShardReqBody, DeleteShardReq,
+        DeleteShardReqBody, GetClusterMetadataReq, GetShardMetadataReq, GetShardMetadataReqBody,
         GetShardMetadataReqShard, ReadReq, ReadReqBody, ReadReqFilter, ReadReqMessage, ReadType,
         ReqHeader, WriteReq, WriteReqBody, WriteReqMessages, WriteReqSegmentMessages,
     };
@@ -64,6 +64,7 @@ mod tests {
                 namespace: "b1".to_string(),
                 shard_name: "s1".to_string(),
                 replica_num: 1,
+                ..Default::default()
             }),
         });
 
@@ -146,7 +147,10 @@ mod tests {
                         value: serde_json::to_vec(&now_second().to_string()).unwrap(),
                         tags: vec!["t1".to_string()],
                     }],
+                    ack_mode: AckMode::All.into(),
+                    ..Default::default()
                 }],
+                timeout_ms: 0,
             }),
         });
 
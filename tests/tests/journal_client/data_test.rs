@@ -22,7 +22,7 @@ mod tests {
     use journal_client::tool::resp_header_error;
     use protocol::journal_server::codec::{JournalEnginePacket, JournalServerCodec};
     use protocol::journal_server::journal_engine::{
-        ApiKey, ApiVersion, CreateShardReq, CreateShardReqBody, GetClusterMetadataReq,
+        AckMode, ApiKey, ApiVersion, CreateShardReq, CreateShardReqBody, GetClusterMetadataReq,
         GetShardMetadataReq, GetShardMetadataReqBody, GetShardMetadataReqShard, ReadReq,
         ReadReqBody, ReadReqFilter, ReadReqMessage, ReadType, ReqHeader, WriteReq, WriteReqBody,
         WriteReqMessages, WriteReqSegmentMessages,
@@ -80,6 +80,7 @@ mod tests {
                 namespace: namespace.clone(),
                 shard_name: shard_name.clone(),
                 replica_num,
+                ..Default::default()
             }),
         });
 
@@ -160,7 +161,10 @@ mod tests {
                             value: value.clone(),
                             tags: tags.clone(),
                         }],
+                        ack_mode: AckMode::All.into(),
+                        ..Default::default()
                     }],
+                    timeout_ms: 0,
                 }),
             });
 
@@ -222,4 +226,91 @@ mod tests {
             panic!();
         }
     }
+
+    #[tokio::test]
+    async fn ack_mode_write_test() {
+        let server_addr = "127.0.0.1:3110";
+        let namespace = unique_id();
+        let shard_name = "s1".to_string();
+
+        let socket = TcpStream::connect(server_addr).await.unwrap();
+        let mut stream = Framed::new(socket, JournalServerCodec::new());
+
+        let req_packet = JournalEnginePacket::CreateShardReq(CreateShardReq {
+            header: Some(ReqHeader {
+                api_key: ApiKey::CreateShard.into(),
+                api_version: ApiVersion::V0.into(),
+            }),
+            body: Some(CreateShardReqBody {
+                namespace: namespace.clone(),
+                shard_name: shard_name.clone(),
+                replica_num: 1,
+                ..Default::default()
+            }),
+        });
+        let _ = stream.send(req_packet.clone()).await;
+        if let Some(Ok(JournalEnginePacket::CreateShardResp(data))) = stream.next().await {
+            assert!(resp_header_error(&data.header, req_packet).is_ok());
+        } else {
+            panic!();
+        }
+        sleep(Duration::from_secs(3)).await;
+
+        // one shard for every ack mode, and one that does not exist
+        let build_shard =
+            |shard_name: &str, pkid: u64, ack_mode: AckMode| WriteReqSegmentMessages {
+                namespace: namespace.clone(),
+                shard_name: shard_name.to_string(),
+                segment: 0,
+                messages: vec![WriteReqMessages {
+                    pkid,
+                    key: "k1".to_string(),
+                    value: serde_json::to_vec("dsfaerwqrsf").unwrap(),
+                    tags: vec!["t1".to_string()],
+                }],
+                ack_mode: ack_mode.into(),
+                ..Default::default()
+            };
+        let req_packet = JournalEnginePacket::WriteReq(WriteReq {
+            header: Some(ReqHeader {
+                api_key: ApiKey::Write.into(),
+                api_version: ApiVersion::V0.into(),
+            }),
+            body: Some(WriteReqBody {
+                data: vec![
+                    build_shard(&shard_name, 1, AckMode::All),
+                    build_shard(&shard_name, 2, AckMode::Leader),
+                    build_shard(&shard_name, 3, AckMode::None),
+                    build_shard("not-exist", 4, AckMode::All),
+                ],
+                timeout_ms: 0,
+            }),
+        });
+        let _ = stream.send(req_packet.clone()).await;
+
+        if let Some(Ok(JournalEnginePacket::WriteResp(data))) = stream.next().await {
+            assert!(resp_header_error(&data.header, req_packet).is_ok());
+            let status = data.body.unwrap().status;
+            assert_eq!(status.len(), 4);
+
+            // the shards are written concurrently, each record gets one of the first offsets
+            let mut offsets = Vec::new();
+            for shard_status in status.iter().take(3) {
+                assert!(shard_status.error.is_none());
+                let msg = shard_status.messages.first().unwrap();
+                assert!(msg.error.is_none());
+                offsets.push(msg.offset);
+            }
+            offsets.sort();
+            assert_eq!(offsets, vec![0, 1, 2]);
+
+            // the failed shard does not fail the others
+            let failed = status.get(3).unwrap();
+            assert_eq!(failed.shard_name, "not-exist");
+            assert!(failed.error.is_some());
+            assert!(failed.messages.is_empty());
+        } else {
+            panic!();
+        }
+    }
 }
@@ -298,6 +298,7 @@ mod tests {
             namespace: namespace(),
             shard_name: shard_name(),
             replica: shard_replica(),
+            ..Default::default()
         };
         match client.create_shard(tonic::Request::new(request)).await {
             Ok(_) => {}